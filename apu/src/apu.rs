@@ -1,10 +1,20 @@
-use crate::{cpu::Spc700, memory::Memory, timers::Timers};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use crate::{cpu::Spc700, dsp, memory::Memory, timers::Timers};
+use common::save_state::{StateError, StateReader, StateWriter, Stateful, Tag};
 
 // The SPC700 CPU runs at 1.024 MHz.
 // The DSP produces one output sample every 32 CPU cycles (32 kHz).
 // We count CPU cycles and only tick the DSP when this threshold is reached.
 const DSP_CYCLES_PER_SAMPLE: u32 = 32;
 
+/// SPC700 clock rate in Hz.
+pub const CLOCK_HZ: u64 = 1_024_000;
+
+/// Master clock rate of an NTSC console in Hz, the default for
+/// [`Apu::set_master_clock_hz`].
+pub const NTSC_MASTER_CLOCK_HZ: u64 = 21_477_272;
+
 pub struct Apu {
     pub cpu:    Spc700,
     pub memory: Memory,
@@ -16,6 +26,22 @@ pub struct Apu {
     /// Counts CPU cycles since the last DSP tick.
     /// Resets to 0 every DSP_CYCLES_PER_SAMPLE cycles.
     dsp_cycles: u32,
+
+    /// Stereo samples output by the DSP ticks and not taken yet.
+    samples: VecDeque<(i16, i16)>,
+
+    /// Host-side output volume, 8.8 fixed point (see [`Self::set_volume`]).
+    volume: u16,
+
+    /// Host-side mute (see [`Self::set_muted`]).
+    muted: bool,
+
+    /// Rate of the console master clock, see [`Self::set_master_clock_hz`].
+    master_clock_hz: u64,
+
+    /// Master cycles elapsed on the console side, which the APU runs up
+    /// to lazily (see [`Self::catch_up`]).
+    master_cycles: u64,
 }
 
 impl Apu {
@@ -26,6 +52,11 @@ impl Apu {
             timers:     Timers::new(),
             cycles:     0,
             dsp_cycles: 0,
+            samples:    VecDeque::new(),
+            volume:     dsp::UNITY_GAIN,
+            muted:      false,
+            master_clock_hz: NTSC_MASTER_CLOCK_HZ,
+            master_cycles:   0,
         };
 
         // Load the reset vector and initialise SP so the CPU starts correctly.
@@ -34,44 +65,202 @@ impl Apu {
         apu
     }
 
+    /// Create an APU whose DSP noise generator starts from `seed`.
+    ///
+    /// All other APU state is already deterministic, so two APUs built
+    /// with the same seed and fed the same program render identical audio
+    /// on every run and platform.
+    pub fn with_seed(seed: u16) -> Self {
+        let mut apu = Self::new();
+        apu.memory.dsp.seed_noise(seed);
+        apu
+    }
+
     /// Step the APU forward by `cycles` CPU cycles.
     ///
     /// Each call ticks:
-    ///   - The SPC700 CPU  (every cycle)
+    ///   - The SPC700 CPU  (every cycle, see [`Spc700::cycle`])
     ///   - The timers      (every cycle)
     ///   - The DSP         (once every 32 cycles → 32 kHz)
     ///
+    /// The 32-cycle divider carries over between calls, so `cycles` can be any
+    /// slice of time (e.g. the SPC700 cycles of a master clock period): the DSP
+    /// output stays at exactly one sample per 32 CPU cycles. Its samples are
+    /// queued for [`Self::take_samples`] or the render functions.
+    ///
     /// All DSP access goes through `self.memory.dsp`;
     pub fn step(&mut self, cycles: u32) {
         for _ in 0..cycles {
-            self.cpu.step(&mut self.memory);
+            self.cpu.cycle(&mut self.memory);
             self.timers.step(&mut self.memory);
 
             self.dsp_cycles += 1;
             if self.dsp_cycles >= DSP_CYCLES_PER_SAMPLE {
                 self.dsp_cycles = 0;
                 self.memory.dsp.step(&self.memory.ram);
+                self.memory.dsp.step_echo(&mut self.memory.ram);
+                let mixed = self.memory.dsp.render_output();
+                let sample = self.output(mixed);
+                self.samples.push_back(sample);
             }
 
             self.cycles += 1;
         }
     }
 
+    /// Set the rate of the console master clock, which
+    /// [`Self::advance_master_cycles`] are converted from.
+    pub fn set_master_clock_hz(&mut self, hz: u64) {
+        self.master_clock_hz = hz;
+    }
+
+    /// Let `cycles` master cycles elapse on the console side.
+    ///
+    /// The APU doesn't run yet: it catches up with the console in one
+    /// batch when the main CPU accesses the ports ([`Self::read_port`],
+    /// [`Self::write_port`]) or on [`Self::catch_up`], and otherwise runs
+    /// when its audio is rendered. This is cheap enough to be called on
+    /// every master cycle.
+    pub fn advance_master_cycles(&mut self, cycles: u64) {
+        self.master_cycles += cycles;
+    }
+
+    /// SPC700 cycles the APU has to have run to be in sync with the console.
+    fn target_cycles(&self) -> u64 {
+        (self.master_cycles as u128 * CLOCK_HZ as u128 / self.master_clock_hz as u128) as u64
+    }
+
+    /// Run the APU up to the console time.
+    ///
+    /// The APU may already be ahead when [`Self::render_audio`] stepped it
+    /// for missing samples: it then waits for the console to catch up
+    /// instead, so both clocks never drift apart.
+    pub fn catch_up(&mut self) {
+        let behind = self.target_cycles().saturating_sub(self.cycles);
+        self.step(u32::try_from(behind).unwrap_or(u32::MAX));
+    }
+
+    /// Main CPU read of port `port` (0–3, $2140–$2143).
+    ///
+    /// The APU catches up first, so the value is the one the SPC700 wrote
+    /// by the time of the access.
+    pub fn read_port(&mut self, port: usize) -> u8 {
+        self.catch_up();
+        self.memory.cpu_port_read(port)
+    }
+
+    /// Main CPU write of `value` to port `port` (0–3, $2140–$2143), after
+    /// catching up so that the SPC700 sees it at the time of the access.
+    pub fn write_port(&mut self, port: usize, value: u8) {
+        self.catch_up();
+        self.memory.cpu_port_write(port, value);
+    }
+
+    /// Scale the audio output by `volume`: 0.0 is silent, 1.0 leaves it
+    /// unchanged, up to [`dsp::MAX_GAIN`].
+    ///
+    /// Applied after the DSP mix, for frontend volume sliders: the emulated
+    /// registers (and what the game reads back) are unaffected. Per-voice
+    /// trims are [`dsp::Dsp::set_voice_gain`].
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = dsp::gain_to_fixed(volume);
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume as f32 / dsp::UNITY_GAIN as f32
+    }
+
+    /// Silence the audio output; the APU keeps running.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Apply the host output controls to a mixed DSP sample.
+    fn output(&self, (left, right): (i16, i16)) -> (i16, i16) {
+        if self.muted {
+            return (0, 0);
+        }
+        let scale = |sample: i16| {
+            let scaled = (sample as i32 * self.volume as i32) >> 8;
+            scaled.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+        };
+        (scale(left), scale(right))
+    }
+
+    /// Take the samples output by the DSP since the last call, oldest first.
+    ///
+    /// Callers driving the APU with [`Self::step`] should take them regularly:
+    /// they are queued until then.
+    pub fn take_samples(&mut self) -> Vec<(i16, i16)> {
+        self.samples.drain(..).collect()
+    }
+
+    /// The oldest sample not taken yet, stepping the APU up to the next DSP
+    /// tick if there is none.
+    fn next_sample(&mut self) -> (i16, i16) {
+        if self.samples.is_empty() {
+            self.step(DSP_CYCLES_PER_SAMPLE - self.dsp_cycles);
+        }
+        self.samples.pop_front().expect("a DSP tick outputs a sample")
+    }
+
     /// Generate `num_samples` stereo output samples.
     ///
-    /// Steps the APU internally for each sample so that CPU, timers, and DSP
-    /// all advance in lock-step.  Returns a `Vec` of `(left, right)` pairs.
+    /// Samples queued by earlier [`Self::step`] calls come first, then the APU
+    /// is stepped for each missing sample so that CPU, timers, and DSP all
+    /// advance in lock-step.  Returns a `Vec` of `(left, right)` pairs.
     pub fn render_audio(&mut self, num_samples: usize) -> Vec<(i16, i16)> {
-        let mut buff = Vec::with_capacity(num_samples);
+        (0..num_samples).map(|_| self.next_sample()).collect()
+    }
 
-        for _ in 0..num_samples {
-            // Advance the full APU by one DSP period (32 CPU cycles = 1 sample).
-            self.step(DSP_CYCLES_PER_SAMPLE);
+    /// Render stereo frames into `buffer` as interleaved `L, R` samples.
+    ///
+    /// Fills `buffer.len() / 2` frames (a trailing odd sample is left
+    /// untouched) and returns the number of frames rendered. Unlike
+    /// [`Self::render_audio`] this does not allocate, which makes it
+    /// convenient for comparing exact output in regression tests.
+    pub fn render_frames_to_buffer(&mut self, buffer: &mut [i16]) -> usize {
+        let frames = buffer.len() / 2;
 
-            // Collect the stereo output from the DSP as an explicit (L, R) pair.
-            buff.push(self.memory.dsp.render_audio_single());
+        for frame in buffer.chunks_exact_mut(2) {
+            let (left, right) = self.next_sample();
+            frame[0] = left;
+            frame[1] = right;
         }
 
-        buff
+        frames
+    }
+}
+
+/// SPC700, memory, DSP and clocks. The samples not taken yet are dropped on load, and the
+/// host output controls (volume, mute, master clock rate) are settings of the emulator and
+/// are not saved.
+impl Stateful for Apu {
+    const TAG: Tag = *b"APU ";
+    const VERSION: u16 = 1;
+
+    fn save(&self, writer: &mut StateWriter) {
+        self.cpu.save_state(writer);
+        self.memory.save_state(writer);
+        writer.u64(self.cycles);
+        writer.u32(self.dsp_cycles);
+        writer.u64(self.master_cycles);
+    }
+
+    fn load(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.cpu.load_state(reader)?;
+        self.memory.load_state(reader)?;
+        self.cycles = reader.u64()?;
+        self.dsp_cycles = reader.u32()?;
+        self.master_cycles = reader.u64()?;
+        if self.dsp_cycles >= DSP_CYCLES_PER_SAMPLE {
+            return Err(reader.corrupt());
+        }
+        self.samples.clear();
+        Ok(())
     }
 }
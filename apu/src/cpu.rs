@@ -1,4 +1,5 @@
 use crate::memory::Memory;
+use common::save_state::{StateError, StateReader, StateWriter};
 
 #[derive(Default)]
 pub struct Registers {
@@ -20,9 +21,269 @@ pub const FLAG_P: u8 = 0x20; // Direct Page
 pub const FLAG_V: u8 = 0x40; // Overflow
 pub const FLAG_N: u8 = 0x80; // Negative
 
+/// What the SPC700 did during a cycle, see [`Spc700::cycle`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleResult {
+    /// Read the byte at the address
+    Read(u16),
+    /// Wrote a byte at the address
+    Write(u16),
+    /// No memory access
+    Internal,
+}
+
+/// Operand of an instruction: the bytes fetched after the opcode, and whether
+/// the instruction then reads or writes memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Implied,
+    /// One operand byte, the value itself
+    Immediate,
+    /// One operand byte, an offset in the direct page
+    Direct,
+    /// Two operand bytes, a 16-bit address
+    Absolute,
+    /// No operand byte, the direct page offset is X: `(X)`
+    IndirectX,
+    /// One operand byte, a signed offset added to PC when the branch is taken
+    Relative,
+    /// Two operand bytes, a value then a direct page offset: `d, #imm`
+    DirectImmediate,
+    /// One operand byte, a direct page pointer to which Y is added: `[d]+Y`
+    DirectIndirectY,
+    /// Two operand bytes, a 16-bit address to which X is added, pointing to
+    /// the new PC: `[!a+X]`
+    AbsoluteIndirectX,
+}
+
+impl Mode {
+    /// Operand bytes fetched after the opcode
+    const fn operand_bytes(self) -> u8 {
+        match self {
+            Mode::Implied | Mode::IndirectX => 0,
+            Mode::Immediate | Mode::Direct | Mode::Relative | Mode::DirectIndirectY => 1,
+            Mode::Absolute | Mode::DirectImmediate | Mode::AbsoluteIndirectX => 2,
+        }
+    }
+
+    /// Whether the operand is in memory, read or written on the last cycle
+    const fn accesses_memory(self) -> bool {
+        !matches!(self, Mode::Implied | Mode::Immediate | Mode::Relative)
+    }
+}
+
+/// Operation of an instruction, applied once its operand is available
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Nop,
+    MovAX,
+    MovAY,
+    MovXA,
+    MovYA,
+    MovSX,
+    MovXS,
+    IncA,
+    IncX,
+    IncY,
+    DecA,
+    DecX,
+    DecY,
+    LdA,
+    LdX,
+    LdY,
+    StA,
+    StX,
+    StY,
+    /// Store of the immediate operand, `MOV d, #imm`
+    StImm,
+    /// 16-bit load and store of YA
+    LdYA,
+    StYA,
+    /// Read-modify-write of a memory operand
+    Inc,
+    Dec,
+    Adc,
+    Sbc,
+    Cmp,
+    CmpX,
+    CmpY,
+    /// Compare of a memory operand with the immediate one, `CMP d, #imm`
+    CmpImm,
+    And,
+    Ora,
+    Eor,
+    Bra,
+    Bpl,
+    Bmi,
+    Bvc,
+    Bvs,
+    Bcc,
+    Bcs,
+    Bne,
+    Beq,
+    Jmp,
+}
+
+impl Op {
+    /// Whether the operation also accesses memory on the cycle before the last one:
+    /// read-modify-writes and 16-bit operands
+    const fn accesses_twice(self) -> bool {
+        matches!(self, Op::Inc | Op::Dec | Op::LdYA | Op::StYA | Op::Jmp)
+    }
+}
+
+/// A decoded opcode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Instr {
+    op: Op,
+    mode: Mode,
+    /// Total cycles, opcode fetch included
+    cycles: u8,
+}
+
+/// Declares the implemented opcodes as `opcode => Op, Mode, cycles;` and
+/// generates [`Spc700::decode`] from them.
+///
+/// The cycle layout follows from the mode and the cycle count: the opcode
+/// fetch, the operand bytes, internal cycles, and for memory operands the data
+/// access on the last cycle. A cycle count too small for the mode fails to
+/// compile.
+macro_rules! opcodes {
+    ($($opcode:literal => $op:ident, $mode:ident, $cycles:literal;)*) => {
+        $(const _: () = assert!(
+            $cycles >= 2 && $cycles > Mode::$mode.operand_bytes() + Mode::$mode.accesses_memory() as u8,
+            concat!("too few cycles for opcode ", stringify!($opcode)),
+        );)*
+
+        impl Spc700 {
+            fn decode(opcode: u8) -> Option<Instr> {
+                match opcode {
+                    $($opcode => Some(Instr { op: Op::$op, mode: Mode::$mode, cycles: $cycles }),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+    0x00 => Nop,   Implied,   2; // NOP
+
+    // Register moves
+    0x7D => MovAX, Implied,   2; // MOV A, X
+    0xDD => MovAY, Implied,   2; // MOV A, Y
+    0x5D => MovXA, Implied,   2; // MOV X, A
+    0xFD => MovYA, Implied,   2; // MOV Y, A
+    0xBD => MovSX, Implied,   2; // MOV SP, X
+    0x9D => MovXS, Implied,   2; // MOV X, SP
+
+    // Register increments and decrements
+    0xBC => IncA,  Implied,   2; // INC A
+    0x3D => IncX,  Implied,   2; // INC X
+    0xFC => IncY,  Implied,   2; // INC Y
+    0x9C => DecA,  Implied,   2; // DEC A
+    0x1D => DecX,  Implied,   2; // DEC X
+    0xDC => DecY,  Implied,   2; // DEC Y
+
+    // Immediate loads
+    0xE8 => LdA,   Immediate, 2; // LDA #imm
+    0xCD => LdX,   Immediate, 2; // LDX #imm
+    0x8D => LdY,   Immediate, 2; // LDY #imm
+
+    // Absolute loads
+    0xE5 => LdA,   Absolute,  4; // MOV A, !a
+    0xE9 => LdX,   Absolute,  4; // MOV X, !a
+    0xEC => LdY,   Absolute,  4; // MOV Y, !a
+
+    // Direct Page loads
+    0xE4 => LdA,   Direct,    3; // MOV A, d
+    0xF8 => LdX,   Direct,    3; // MOV X, d
+    0xEB => LdY,   Direct,    3; // MOV Y, d
+
+    // Indirect loads
+    0xE6 => LdA,   IndirectX, 3; // MOV A, (X)
+
+    // Stores
+    0xC4 => StA,   Direct,    3; // MOV d, A
+    0xC5 => StA,   Absolute,  4; // MOV !a, A
+    0xC9 => StX,   Absolute,  4; // MOV !a, X
+    0xCC => StY,   Absolute,  4; // MOV !a, Y
+    0xD8 => StX,   Direct,    4; // MOV d, X
+    0xCB => StY,   Direct,    4; // MOV d, Y
+    0xC6 => StA,   IndirectX, 4; // MOV (X), A
+    0xD7 => StA,   DirectIndirectY, 7; // MOV [d]+Y, A
+    0x8F => StImm, DirectImmediate, 5; // MOV d, #imm
+
+    // 16-bit moves
+    0xBA => LdYA,  Direct,    5; // MOVW YA, d
+    0xDA => StYA,  Direct,    5; // MOVW d, YA
+
+    // Memory increments and decrements
+    0xAB => Inc,   Direct,    4; // INC d
+    0x8B => Dec,   Direct,    4; // DEC d
+
+    // Arithmetic & logic
+    0x88 => Adc,   Immediate, 2; // ADC #imm
+    0xA8 => Sbc,   Immediate, 2; // SBC #imm
+    0x68 => Cmp,   Immediate, 2; // CMP #imm
+    0xC8 => CmpX,  Immediate, 2; // CMP X, #imm
+    0xAD => CmpY,  Immediate, 2; // CMP Y, #imm
+    0x3E => CmpX,  Direct,    3; // CMP X, d
+    0x7E => CmpY,  Direct,    3; // CMP Y, d
+    0x78 => CmpImm, DirectImmediate, 5; // CMP d, #imm
+    0x28 => And,   Immediate, 2; // AND #imm
+    0x08 => Ora,   Immediate, 2; // ORA #imm
+    0x48 => Eor,   Immediate, 2; // EOR #imm
+
+    // Branches: 2 cycles when not taken
+    0x2F => Bra,   Relative,  4; // BRA r
+    0x10 => Bpl,   Relative,  4; // BPL r
+    0x30 => Bmi,   Relative,  4; // BMI r
+    0x50 => Bvc,   Relative,  4; // BVC r
+    0x70 => Bvs,   Relative,  4; // BVS r
+    0x90 => Bcc,   Relative,  4; // BCC r
+    0xB0 => Bcs,   Relative,  4; // BCS r
+    0xD0 => Bne,   Relative,  4; // BNE r
+    0xF0 => Beq,   Relative,  4; // BEQ r
+
+    // Jumps
+    0x1F => Jmp,   AbsoluteIndirectX, 6; // JMP [!a+X]
+}
+
+/// Instruction in progress, decoded on its opcode fetch and resumed by each
+/// [`Spc700::cycle`]
+#[derive(Debug, Clone, Copy)]
+struct InstrState {
+    opcode: u8,
+    instr: Instr,
+    /// Cycles of the instruction done so far, opcode fetch included
+    cycle: u8,
+    /// Address of the memory operand, built over the operand cycles, or the
+    /// offset of a branch
+    addr: u16,
+    /// Immediate operand of `d, #imm` instructions, or the first byte read by
+    /// the instructions accessing memory twice
+    data: u8,
+}
+
+/// Opcode the SPC700 doesn't implement yet, which halts it (see [`Spc700::halted`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnhandledOpcode {
+    pub opcode: u8,
+    /// Address of the opcode, where PC stays
+    pub addr: u16,
+}
+
 pub struct Spc700 {
     pub regs: Registers,
     pub cycles: u32,
+    /// None between two instructions
+    instr: Option<InstrState>,
+    /// Set when the CPU reached an unimplemented opcode; it only runs internal
+    /// cycles until the next reset
+    halt: Option<UnhandledOpcode>,
+    /// Opcode the CPU halted on, until taken by [`Self::take_unhandled_opcode`]
+    unhandled_opcode: Option<UnhandledOpcode>,
 }
 
 impl Spc700 {
@@ -30,6 +291,9 @@ impl Spc700 {
         Self {
             regs: Registers::default(),
             cycles: 0,
+            instr: None,
+            halt: None,
+            unhandled_opcode: None,
         }
     }
 
@@ -37,54 +301,165 @@ impl Spc700 {
         self.regs.pc = mem.read16(0xFFFE); // Reset vector
         self.regs.sp = 0xFF;
         self.regs.psw = 0;
+        self.instr = None;
+        self.halt = None;
+    }
+
+    /// The opcode the CPU halted on, `None` while it executes instructions
+    pub fn halted(&self) -> Option<UnhandledOpcode> {
+        self.halt
+    }
+
+    /// Returns the opcode the CPU halted on since the previous call, for the host to
+    /// report it once.
+    pub fn take_unhandled_opcode(&mut self) -> Option<UnhandledOpcode> {
+        self.unhandled_opcode.take()
     }
 
+    /// Whether the CPU is between two instructions (the next cycle fetches an opcode)
+    pub fn at_instruction_boundary(&self) -> bool {
+        self.instr.is_none()
+    }
+
+    /// Run the current instruction to its end, or a whole instruction when called
+    /// between two instructions.
     pub fn step(&mut self, mem: &mut Memory) {
-        let opcode = mem.read8_mut(self.regs.pc);
-        self.regs.pc = self.regs.pc.wrapping_add(1);
+        self.cycle(mem);
+        while !self.at_instruction_boundary() {
+            self.cycle(mem);
+        }
+    }
 
-        match opcode {
-            0x00 => self.inst_nop(), // NOP
-        
-            // Register moves
-            0x7D => self.inst_mov_a_x(), // MOV A, X
-            0xDD => self.inst_mov_a_y(), // MOV A, Y
-            0x5D => self.inst_mov_x_a(), // MOV X, A
-            0xFD => self.inst_mov_y_a(), // MOV Y, A
-        
-            // Immediate loads
-            0xE8 => self.inst_lda_imm(mem), // LDA #imm
-            0xCD => self.inst_ldx_imm(mem), // LDX #imm
-            0x8D => self.inst_ldy_imm(mem), // LDY #imm
-        
-            // Absolute loads
-            0xE5 => self.inst_lda_abs(mem), // MOV A, !a
-            0xE9 => self.inst_ldx_abs(mem), // MOV X, !a
-            0xEC => self.inst_ldy_abs(mem), // MOV Y, !a
-        
-            // Direct Page loads
-            0xE4 => self.inst_lda_dp(mem), // MOV A, d
-            0xF8 => self.inst_ldx_dp(mem), // MOV X, d
-            0xEB => self.inst_ldy_dp(mem), // MOV Y, d
-        
-            // Stores
-            0xC4 => self.inst_sta_dp(mem),  // MOV d, A
-            0xC5 => self.inst_sta_abs(mem), // MOV !a, A
-            0xC9 => self.inst_stx_abs(mem), // MOV !a, X
-            0xCC => self.inst_sty_abs(mem), // MOV !a, Y
-        
-            // Arithmetic & logic
-            0x88 => self.inst_adc_imm(mem), // ADC #imm
-            0xA8 => self.inst_sbc_imm(mem), // SBC #imm
-            0x68 => self.inst_cmp_imm(mem), // CMP #imm
-            0x28 => self.inst_and_imm(mem), // AND #imm
-            0x08 => self.inst_ora_imm(mem), // ORA #imm
-            0x48 => self.inst_eor_imm(mem), // EOR #imm
-        
-            // Catch-all
-            _ => unimplemented!("Opcode {:02X} not yet implemented", opcode),
+    /// Run a single CPU cycle, resuming the instruction in progress, and
+    /// return the memory access it made.
+    ///
+    /// Like the main CPU, an instruction spans several calls, so the caller can
+    /// interleave the SPC700 with the other chips at cycle granularity: a value
+    /// written by the SNES CPU to a port is seen by the very next read.
+    pub fn cycle(&mut self, mem: &mut Memory) -> CycleResult {
+        self.cycles += 1;
+        if self.halt.is_some() {
+            return CycleResult::Internal;
+        }
+
+        let Some(mut state) = self.instr else {
+            let pc = self.regs.pc;
+            let opcode = self.read_immediate(mem);
+            let Some(instr) = Self::decode(opcode) else {
+                // Stay on the opcode, as the main CPU does when it traps
+                self.regs.pc = pc;
+                let unhandled = UnhandledOpcode { opcode, addr: pc };
+                self.halt = Some(unhandled);
+                self.unhandled_opcode = Some(unhandled);
+                return CycleResult::Read(pc);
+            };
+            let addr = match instr.mode {
+                Mode::IndirectX => self.dp_base() | self.regs.x as u16,
+                _ => 0,
+            };
+            self.instr = Some(InstrState { opcode, instr, cycle: 1, addr, data: 0 });
+            return CycleResult::Read(pc);
+        };
+
+        let Instr { op, mode, cycles } = state.instr;
+        let mut last = state.cycle + 1 == cycles;
+        let pc = self.regs.pc;
+        let result = if state.cycle <= mode.operand_bytes() {
+            let byte = self.read_immediate(mem);
+            match (mode, state.cycle) {
+                (Mode::Immediate, _) => self.execute(op, byte),
+                (Mode::Direct | Mode::DirectIndirectY, _) => state.addr = self.dp_base() | byte as u16,
+                (Mode::DirectImmediate, 1) => state.data = byte,
+                (Mode::DirectImmediate, _) => state.addr = self.dp_base() | byte as u16,
+                (Mode::Relative, _) => {
+                    state.addr = byte as i8 as u16;
+                    // A branch not taken ends after its offset
+                    last = !self.branch_taken(op);
+                }
+                (Mode::Absolute | Mode::AbsoluteIndirectX, 1) => state.addr = byte as u16,
+                (Mode::AbsoluteIndirectX, _) => {
+                    state.addr = (state.addr | (byte as u16) << 8).wrapping_add(self.regs.x as u16);
+                }
+                _ => state.addr |= (byte as u16) << 8,
+            }
+            CycleResult::Read(pc)
+        } else if mode == Mode::DirectIndirectY && state.cycle <= 3 {
+            // The two bytes of the pointer, then Y is added to it
+            let pointer = if state.cycle == 2 { state.addr } else { Self::next_in_page(state.addr) };
+            let byte = mem.read8_mut(pointer);
+            if state.cycle == 2 {
+                state.data = byte;
+            } else {
+                state.addr = u16::from_le_bytes([state.data, byte]).wrapping_add(self.regs.y as u16);
+            }
+            CycleResult::Read(pointer)
+        } else if state.cycle + 2 == cycles && op.accesses_twice() {
+            self.first_access(op, &mut state, mem)
+        } else if last && mode.accesses_memory() {
+            self.access(op, &state, mem)
+        } else {
+            match mode {
+                Mode::Implied if last => self.execute(op, 0),
+                Mode::Relative if last => self.regs.pc = self.regs.pc.wrapping_add(state.addr),
+                _ => {}
+            }
+            CycleResult::Internal
+        };
+
+        state.cycle += 1;
+        self.instr = (!last).then_some(state);
+        result
+    }
+
+    /// Writes the registers and the instruction in progress to a save state.
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        let Registers { a, x, y, sp, pc, psw } = self.regs;
+        for value in [a, x, y, sp] {
+            writer.u8(value);
+        }
+        writer.u16(pc);
+        writer.u8(psw);
+        writer.u32(self.cycles);
+        writer.bool(self.halt.is_some());
+        if let Some(halt) = self.halt {
+            writer.u8(halt.opcode);
+        }
+        writer.bool(self.instr.is_some());
+        if let Some(state) = self.instr {
+            writer.u8(state.opcode);
+            writer.u8(state.cycle);
+            writer.u16(state.addr);
+            writer.u8(state.data);
+        }
+    }
+
+    /// Restores the CPU written by [`Self::save_state`], resuming the instruction it was in.
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        let regs = &mut self.regs;
+        for value in [&mut regs.a, &mut regs.x, &mut regs.y, &mut regs.sp] {
+            *value = reader.u8()?;
+        }
+        regs.pc = reader.u16()?;
+        regs.psw = reader.u8()?;
+        self.cycles = reader.u32()?;
+        self.halt = None;
+        if reader.bool()? {
+            let opcode = reader.u8()?;
+            self.halt = Some(UnhandledOpcode { opcode, addr: regs.pc });
         }
-    }        
+        self.instr = None;
+        if reader.bool()? {
+            let opcode = reader.u8()?;
+            let cycle = reader.u8()?;
+            let addr = reader.u16()?;
+            let data = reader.u8()?;
+            let instr = Self::decode(opcode)
+                .filter(|instr| (1..instr.cycles).contains(&cycle))
+                .ok_or_else(|| reader.corrupt())?;
+            self.instr = Some(InstrState { opcode, instr, cycle, addr, data });
+        }
+        Ok(())
+    }
 
     // Flag helpers
     pub fn set_flag(&mut self, mask: u8, value: bool) {
@@ -97,7 +472,7 @@ impl Spc700 {
 
     pub fn get_flag(&self, mask: u8) -> bool {
         (self.regs.psw & mask) != 0
-    }      
+    }
 
     fn set_zn_flags(&mut self, value: u8) {
         self.set_flag(FLAG_Z, value == 0);
@@ -112,6 +487,28 @@ impl Spc700 {
         }
     }
 
+    /// Address of the second byte of a 16-bit operand at `addr`, which wraps
+    /// within the page like the direct page does
+    fn next_in_page(addr: u16) -> u16 {
+        (addr & 0xFF00) | (addr.wrapping_add(1) & 0x00FF)
+    }
+
+    /// Whether the condition of the branch `op` is met
+    fn branch_taken(&self, op: Op) -> bool {
+        match op {
+            Op::Bra => true,
+            Op::Bpl => !self.get_flag(FLAG_N),
+            Op::Bmi => self.get_flag(FLAG_N),
+            Op::Bvc => !self.get_flag(FLAG_V),
+            Op::Bvs => self.get_flag(FLAG_V),
+            Op::Bcc => !self.get_flag(FLAG_C),
+            Op::Bcs => self.get_flag(FLAG_C),
+            Op::Bne => !self.get_flag(FLAG_Z),
+            Op::Beq => self.get_flag(FLAG_Z),
+            _ => unreachable!("{op:?} is not a branch"),
+        }
+    }
+
     /// Read the next byte from memory at PC and advance PC by 1.
     ///
     /// Uses `read8_mut` so that reads of `$FD–$FF` (timer counters)
@@ -122,143 +519,154 @@ impl Spc700 {
         value
     }
 
-    /// Read a 16-bit little-endian immediate (two bytes) and advance PC by 2.
-    fn read_immediate16(&mut self, mem: &mut Memory) -> u16 {
-        let lo = self.read_immediate(mem) as u16;
-        let hi = self.read_immediate(mem) as u16;
-        lo | (hi << 8)
-    }
-
-    // Implemented instructions
-    fn inst_mov_a_x(&mut self) {
-        self.regs.a = self.regs.x;
-        self.cycles += 2;
-    }
-    fn inst_mov_a_y(&mut self) {
-        self.regs.a = self.regs.y;
-        self.cycles += 2;
-    }
-    fn inst_mov_x_a(&mut self) {
-        self.regs.x = self.regs.a;
-        self.cycles += 2;
-    }
-    fn inst_mov_y_a(&mut self) {
-        self.regs.y = self.regs.a;
-        self.cycles += 2;
-    }
-    fn inst_nop(&mut self) {
-        self.cycles += 2;
-    }
-
-    pub fn inst_lda_imm(&mut self, mem: &mut Memory) {
-        self.regs.a = self.read_immediate(mem);
-        self.set_zn_flags(self.regs.a);
-        self.cycles += 2;
-    }
-
-    pub fn inst_ldx_imm(&mut self, mem: &mut Memory) {
-        self.regs.x = self.read_immediate(mem);
-        self.set_zn_flags(self.regs.x);
-        self.cycles += 2;
-    }
-
-    pub fn inst_ldy_imm(&mut self, mem: &mut Memory) {
-        self.regs.y = self.read_immediate(mem);
-        self.set_zn_flags(self.regs.y);
-        self.cycles += 2;
-    }
-
-    pub fn inst_sta_abs(&mut self, mem: &mut Memory) {
-        let addr = self.read_immediate16(mem);
-        mem.write8(addr, self.regs.a);
-        self.cycles += 4;
-    }
-
-    pub fn inst_stx_abs(&mut self, mem: &mut Memory) {
-        let addr = self.read_immediate16(mem);
-        mem.write8(addr, self.regs.x);
-        self.cycles += 4;
+    /// The data cycle of a memory operand: stores write their register (the high
+    /// byte for 16-bit ones) or the immediate operand, read-modify-writes write
+    /// the new value, everything else reads the operand and executes.
+    fn access(&mut self, op: Op, state: &InstrState, mem: &mut Memory) -> CycleResult {
+        let addr = match op {
+            Op::LdYA | Op::StYA => Self::next_in_page(state.addr),
+            Op::Jmp => state.addr.wrapping_add(1),
+            _ => state.addr,
+        };
+        let stored = match op {
+            Op::StA => self.regs.a,
+            Op::StX => self.regs.x,
+            Op::StY | Op::StYA => self.regs.y,
+            Op::StImm => state.data,
+            Op::Inc | Op::Dec => {
+                let value = if op == Op::Inc {
+                    state.data.wrapping_add(1)
+                } else {
+                    state.data.wrapping_sub(1)
+                };
+                self.set_zn_flags(value);
+                value
+            }
+            _ => {
+                let value = mem.read8_mut(addr);
+                match op {
+                    Op::LdYA => {
+                        self.regs.a = state.data;
+                        self.regs.y = value;
+                        self.set_flag(FLAG_Z, state.data == 0 && value == 0);
+                        self.set_flag(FLAG_N, value & 0x80 != 0);
+                    }
+                    Op::Jmp => self.regs.pc = u16::from_le_bytes([state.data, value]),
+                    Op::CmpImm => self.cmp(value, state.data),
+                    _ => self.execute(op, value),
+                }
+                return CycleResult::Read(addr);
+            }
+        };
+        mem.write8(addr, stored);
+        CycleResult::Write(addr)
     }
 
-    pub fn inst_sty_abs(&mut self, mem: &mut Memory) {
-        let addr = self.read_immediate16(mem);
-        mem.write8(addr, self.regs.y);
-        self.cycles += 4;
-    }
-
-    pub fn inst_lda_abs(&mut self, mem: &mut Memory) {
-        let addr = self.read_immediate16(mem);
-        self.regs.a = mem.read8_mut(addr);
-        self.set_zn_flags(self.regs.a);
-        self.cycles += 4;
-    }
-
-    pub fn inst_ldx_abs(&mut self, mem: &mut Memory) {
-        let addr = self.read_immediate16(mem);
-        self.regs.x = mem.read8_mut(addr);
-        self.set_zn_flags(self.regs.x);
-        self.cycles += 4;
-    }
-
-    pub fn inst_ldy_abs(&mut self, mem: &mut Memory) {
-        let addr = self.read_immediate16(mem);
-        self.regs.y = mem.read8_mut(addr);
-        self.set_zn_flags(self.regs.y);
-        self.cycles += 4;
-    }    
-
-    // Load from direct page
-    pub fn inst_lda_dp(&mut self, mem: &mut Memory) {
-        let offset = self.read_immediate(mem) as u16;
-        let addr = self.dp_base() | offset;
-        self.regs.a = mem.read8_mut(addr);
-        self.set_zn_flags(self.regs.a);
-        self.cycles += 3;
-    }
-
-    pub fn inst_ldx_dp(&mut self, mem: &mut Memory) {
-        let offset = self.read_immediate(mem) as u16;
-        let addr = self.dp_base() | offset;
-        self.regs.x = mem.read8_mut(addr);
-        self.set_zn_flags(self.regs.x);
-        self.cycles += 3;
+    /// The cycle before the data cycle of the instructions accessing memory
+    /// twice: the low byte of 16-bit operands, or the read of a read-modify-write
+    fn first_access(&mut self, op: Op, state: &mut InstrState, mem: &mut Memory) -> CycleResult {
+        if op == Op::StYA {
+            mem.write8(state.addr, self.regs.a);
+            CycleResult::Write(state.addr)
+        } else {
+            state.data = mem.read8_mut(state.addr);
+            CycleResult::Read(state.addr)
+        }
     }
 
-    pub fn inst_ldy_dp(&mut self, mem: &mut Memory) {
-        let offset = self.read_immediate(mem) as u16;
-        let addr = self.dp_base() | offset;
-        self.regs.y = mem.read8_mut(addr);
-        self.set_zn_flags(self.regs.y);
-        self.cycles += 3;
-    }
-    
-    pub fn inst_sta_dp(&mut self, mem: &mut Memory) {
-        let offset = self.read_immediate(mem) as u16;
-        let addr = self.dp_base() | offset;
-        mem.write8(addr, self.regs.a);
-    
-        self.cycles += 3;
-    }
-    
-    pub fn inst_stx_dp(&mut self, mem: &mut Memory) {
-        let offset = self.read_immediate(mem) as u16;
-        let addr = self.dp_base() | offset;
-        mem.write8(addr, self.regs.x);
-    
-        self.cycles += 3;
+    /// Apply `op` to its operand `value` (unused by implied instructions)
+    fn execute(&mut self, op: Op, value: u8) {
+        match op {
+            Op::Nop => {}
+
+            Op::MovAX => {
+                self.regs.a = self.regs.x;
+                self.set_zn_flags(self.regs.a);
+            }
+            Op::MovAY => {
+                self.regs.a = self.regs.y;
+                self.set_zn_flags(self.regs.a);
+            }
+            Op::MovXA => {
+                self.regs.x = self.regs.a;
+                self.set_zn_flags(self.regs.x);
+            }
+            Op::MovYA => {
+                self.regs.y = self.regs.a;
+                self.set_zn_flags(self.regs.y);
+            }
+            Op::MovSX => self.regs.sp = self.regs.x,
+            Op::MovXS => {
+                self.regs.x = self.regs.sp;
+                self.set_zn_flags(self.regs.x);
+            }
+
+            Op::IncA => {
+                self.regs.a = self.regs.a.wrapping_add(1);
+                self.set_zn_flags(self.regs.a);
+            }
+            Op::IncX => {
+                self.regs.x = self.regs.x.wrapping_add(1);
+                self.set_zn_flags(self.regs.x);
+            }
+            Op::IncY => {
+                self.regs.y = self.regs.y.wrapping_add(1);
+                self.set_zn_flags(self.regs.y);
+            }
+            Op::DecA => {
+                self.regs.a = self.regs.a.wrapping_sub(1);
+                self.set_zn_flags(self.regs.a);
+            }
+            Op::DecX => {
+                self.regs.x = self.regs.x.wrapping_sub(1);
+                self.set_zn_flags(self.regs.x);
+            }
+            Op::DecY => {
+                self.regs.y = self.regs.y.wrapping_sub(1);
+                self.set_zn_flags(self.regs.y);
+            }
+
+            Op::LdA => {
+                self.regs.a = value;
+                self.set_zn_flags(value);
+            }
+            Op::LdX => {
+                self.regs.x = value;
+                self.set_zn_flags(value);
+            }
+            Op::LdY => {
+                self.regs.y = value;
+                self.set_zn_flags(value);
+            }
+
+            Op::Adc => self.adc(value),
+            Op::Sbc => self.sbc(value),
+            Op::Cmp => self.cmp(self.regs.a, value),
+            Op::CmpX => self.cmp(self.regs.x, value),
+            Op::CmpY => self.cmp(self.regs.y, value),
+
+            // Bitwise AND/OR/XOR with accumulator
+            Op::And => {
+                self.regs.a &= value;
+                self.set_zn_flags(self.regs.a);
+            }
+            Op::Ora => {
+                self.regs.a |= value;
+                self.set_zn_flags(self.regs.a);
+            }
+            Op::Eor => {
+                self.regs.a ^= value;
+                self.set_zn_flags(self.regs.a);
+            }
+
+            Op::StA | Op::StX | Op::StY | Op::StImm | Op::LdYA | Op::StYA | Op::Inc | Op::Dec
+            | Op::CmpImm | Op::Jmp => unreachable!("{op:?} is done by Spc700::access"),
+            Op::Bra | Op::Bpl | Op::Bmi | Op::Bvc | Op::Bvs | Op::Bcc | Op::Bcs | Op::Bne
+            | Op::Beq => unreachable!("branches are done by Spc700::cycle"),
+        }
     }
-    
-    pub fn inst_sty_dp(&mut self, mem: &mut Memory) {
-        let offset = self.read_immediate(mem) as u16;
-        let addr = self.dp_base() | offset;
-        mem.write8(addr, self.regs.y);
-    
-        self.cycles += 3;
-    }    
-
-    pub fn inst_adc_imm(&mut self, mem: &mut Memory) {
-        let value = self.read_immediate(mem);
 
+    fn adc(&mut self, value: u8) {
         let carry_in = if self.get_flag(FLAG_C) { 1 } else { 0 };
         let result = self.regs.a as u16 + value as u16 + carry_in as u16;
 
@@ -274,24 +682,17 @@ impl Spc700 {
         );
 
         self.regs.a = result_u8;
-        self.cycles += 2;
     }
 
-    /// Compare memory with accumulator (sets flags only)
-    pub fn inst_cmp_imm(&mut self, mem: &mut Memory) {
-        let value = self.read_immediate(mem);
-
-        let result = self.regs.a.wrapping_sub(value);
+    /// Compare a register with a value (sets flags only)
+    fn cmp(&mut self, register: u8, value: u8) {
+        let result = register.wrapping_sub(value);
 
-        self.set_flag(FLAG_C, self.regs.a >= value);
+        self.set_flag(FLAG_C, register >= value);
         self.set_zn_flags(result);
-
-        self.cycles += 2;
     }
 
-    pub fn inst_sbc_imm(&mut self, mem: &mut Memory) {
-        let value = self.read_immediate(mem);
-
+    fn sbc(&mut self, value: u8) {
         let carry_in = if self.get_flag(FLAG_C) { 0 } else { 1 }; // SPC700 uses inverted carry
         let result = self.regs.a as i16 - value as i16 - carry_in as i16;
 
@@ -304,27 +705,6 @@ impl Spc700 {
         );
 
         self.regs.a = result_u8;
-        self.cycles += 2;
-    }
-
-    /// Bitwise AND with accumulator
-    pub fn inst_and_imm(&mut self, mem: &mut Memory) {
-        self.regs.a &= self.read_immediate(mem);
-        self.set_zn_flags(self.regs.a);
-        self.cycles += 2;
-    }
-
-    /// Bitwise OR with accumulator
-    pub fn inst_ora_imm(&mut self, mem: &mut Memory) {
-        self.regs.a |= self.read_immediate(mem);
-        self.set_zn_flags(self.regs.a);
-        self.cycles += 2;
-    }
-
-    /// Bitwise XOR with accumulator
-    pub fn inst_eor_imm(&mut self, mem: &mut Memory) {
-        self.regs.a ^= self.read_immediate(mem);
-        self.set_zn_flags(self.regs.a);
-        self.cycles += 2;
     }
 }
+
@@ -0,0 +1,58 @@
+use common::save_state::{StateError, StateReader, StateWriter};
+
+/// How the DSP mix is turned into output samples, see [`super::Dsp::set_output_mode`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// The mix as computed, clamped to 16 bits once at the end.
+    #[default]
+    Raw,
+
+    /// Like the console: the mix saturates to 16 bits after each voice
+    /// and after the master volume, then the output stage halves it and
+    /// removes its DC offset with a high-pass filter.
+    Hardware,
+}
+
+/// Fraction bits of the high-pass filter state.
+const FRACTION_BITS: u32 = 8;
+
+/// First-order high-pass filter of the analog output stage.
+///
+/// `y[n] = x[n] - x[n-1] + (1 - 1/256) * y[n-1]`, a pole at about 20 Hz
+/// at 32 kHz: it only removes DC offsets and rumble, like the coupling
+/// capacitors of the console.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HighPass {
+    /// Last input sample.
+    input: i32,
+
+    /// Last output, with [`FRACTION_BITS`] fraction bits so that the
+    /// decay doesn't get stuck on truncation.
+    output: i32,
+}
+
+impl HighPass {
+    /// Filter one sample.
+    pub fn filter(&mut self, sample: i16) -> i16 {
+        let delta = (sample as i32 - self.input) << FRACTION_BITS;
+        self.output = delta + self.output - (self.output >> 8);
+        self.input  = sample as i32;
+        clamp16(self.output >> FRACTION_BITS)
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.u32(self.input as u32);
+        writer.u32(self.output as u32);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.input = reader.u32()? as i32;
+        self.output = reader.u32()? as i32;
+        Ok(())
+    }
+}
+
+/// Saturate to the 16-bit range, like the DSP adders.
+pub(super) fn clamp16(value: i32) -> i16 {
+    value.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
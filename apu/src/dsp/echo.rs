@@ -0,0 +1,137 @@
+use crate::memory::RawARAM;
+use super::dac::clamp16;
+use common::save_state::{StateError, StateReader, StateWriter};
+
+/// $0D EFB — echo feedback.
+const EFB: usize = 0x0D;
+/// $2C/$3C EVOLL/EVOLR — echo output volume, left and right.
+const EVOL: [usize; 2] = [0x2C, 0x3C];
+/// $6C FLG — bit 5 (ECEN) set disables the echo buffer writes.
+const FLG: usize = 0x6C;
+/// $6D ESA — echo buffer page in APU RAM.
+const ESA: usize = 0x6D;
+/// $7D EDL — echo delay, bits 3-0.
+const EDL: usize = 0x7D;
+
+/// FLG bit 5: echo buffer writes disabled.
+pub const FLG_ECEN: u8 = 0x20;
+
+/// Bytes of echo buffer per EDL step (16 ms of stereo samples).
+const BYTES_PER_DELAY: u16 = 0x800;
+
+/// The echo unit: a ring buffer of stereo samples in APU RAM, read
+/// back through an 8-tap FIR filter.
+///
+/// The buffer lives at ESA * 0x100 in APU RAM and is written on every
+/// sample while FLG bit 5 (ECEN) is clear, even when no voice is sent to
+/// the echo (EON): games must keep their code and data out of it, and
+/// some rely on it not overwriting anything before they set FLG.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Echo {
+    /// Byte offset of the current sample pair in the buffer.
+    offset: u16,
+
+    /// Buffer length in bytes, latched from EDL when the offset wraps
+    /// to 0, so a new delay only applies from the next pass.
+    length: u16,
+
+    /// The last 8 samples read from the buffer, left and right,
+    /// `history_pos` being the newest.
+    history: [[i16; 2]; 8],
+    history_pos: usize,
+
+    /// Echo output of the last sample, scaled by EVOL.
+    output: [i16; 2],
+}
+
+impl Echo {
+    /// Echo output of the last sample, scaled by EVOL, added to the mix.
+    pub fn output(&self) -> (i16, i16) {
+        (self.output[0], self.output[1])
+    }
+
+    /// Byte offset of the next sample pair in the buffer.
+    pub fn offset(&self) -> u16 {
+        self.offset
+    }
+
+    /// Advance the echo by one sample: read the oldest sample pair of the
+    /// buffer, filter it, then write `input` (the EON voices) plus the
+    /// filtered feedback in its place.
+    ///
+    /// Addresses wrap around the 64 KB of APU RAM.
+    pub fn step(&mut self, registers: &[u8; 128], ram: &mut RawARAM, input: [i32; 2]) {
+        let addr = (registers[ESA] as u16 * 0x100).wrapping_add(self.offset);
+
+        self.history_pos = (self.history_pos + 1) % 8;
+        for ch in 0..2 {
+            let lo = addr.wrapping_add(ch as u16 * 2);
+            let hi = lo.wrapping_add(1);
+            let sample = i16::from_le_bytes([ram[lo as usize], ram[hi as usize]]);
+            // Samples are stored with 15 bits of precision
+            self.history[self.history_pos][ch] = sample >> 1;
+        }
+
+        let write = registers[FLG] & FLG_ECEN == 0;
+        for ch in 0..2 {
+            let fir = self.fir(registers, ch);
+            let evol = registers[EVOL[ch]] as i8 as i32;
+            self.output[ch] = clamp16((fir * evol) >> 7);
+
+            if write {
+                let efb = registers[EFB] as i8 as i32;
+                let sample = clamp16(input[ch] + ((fir * efb) >> 7)) & !1;
+                let [lo, hi] = sample.to_le_bytes();
+                let lo_addr = addr.wrapping_add(ch as u16 * 2);
+                ram[lo_addr as usize] = lo;
+                ram[lo_addr.wrapping_add(1) as usize] = hi;
+            }
+        }
+
+        if self.offset == 0 {
+            self.length = (registers[EDL] & 0x0F) as u16 * BYTES_PER_DELAY;
+        }
+        self.offset += 4;
+        if self.offset >= self.length {
+            self.offset = 0;
+        }
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.u16(self.offset);
+        writer.u16(self.length);
+        for sample in self.history.iter().chain([&self.output]).flatten() {
+            writer.u16(*sample as u16);
+        }
+        writer.u8(self.history_pos as u8);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.offset = reader.u16()?;
+        self.length = reader.u16()?;
+        for sample in self.history.iter_mut().chain([&mut self.output]).flatten() {
+            *sample = reader.u16()? as i16;
+        }
+        self.history_pos = reader.u8()? as usize;
+        if self.history_pos >= self.history.len() {
+            return Err(reader.corrupt());
+        }
+        Ok(())
+    }
+
+    /// 8-tap FIR over the history of channel `ch`, C0 applying to the
+    /// oldest sample. Like the hardware, the first 7 taps wrap at 16 bits
+    /// and only the last one saturates.
+    fn fir(&self, registers: &[u8; 128], ch: usize) -> i32 {
+        let mut sum: i32 = 0;
+        for tap in 0..8 {
+            let sample = self.history[(self.history_pos + 1 + tap) % 8][ch] as i32;
+            let coef = registers[(tap << 4) | 0x0F] as i8 as i32;
+            sum += (sample * coef) >> 6;
+            if tap == 6 {
+                sum = sum as i16 as i32;
+            }
+        }
+        (clamp16(sum) & !1) as i32
+    }
+}
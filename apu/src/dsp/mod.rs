@@ -1,16 +1,26 @@
 mod adsr;
 mod brr;
+mod dac;
+mod echo;
+mod noise;
+mod view;
 mod voice;
 
 // Re-export everything tests and external code need
 pub use adsr::{Adsr, EnvelopePhase};
 use adsr::ENVELOPE_RATE_TABLE;
 pub use brr::{Brr, decode_brr_nibble, decode_brr_block};
-pub use voice::Voice;
+pub use dac::{HighPass, OutputMode};
+pub use echo::{Echo, FLG_ECEN};
+pub use noise::{Noise, NOISE_DEFAULT_SEED};
+pub use view::{DspView, EchoView, VoiceView, DSP_SAMPLE_RATE};
+pub use voice::{Voice, VoiceInfo};
 
+use common::save_state::{StateError, StateReader, StateWriter};
 use common::u16_split::U16Split;
 
 use crate::memory::RawARAM;
+use dac::clamp16;
 
 /// The SNES DSP: 8 voices, ADSR envelopes, BRR decoding, stereo mix.
 pub struct Dsp {
@@ -32,20 +42,101 @@ pub struct Dsp {
 
     /// $1C MVOLR — master right volume, signed (-128..+127).
     master_vol_right: i8,
+
+    /// $3D NON — noise enable, one bit per voice (bit 0 = voice 0).
+    noise_enable: u8,
+
+    /// $6C FLG bits 4-0 — noise clock rate index (0–31).
+    noise_rate: u8,
+
+    /// Noise generator shared by all NON-enabled voices.
+    pub noise: Noise,
+
+    /// KON bits written and not polled yet. A write replaces the pending
+    /// bits, so a second KON write before the poll drops the first one.
+    pending_kon: u8,
+
+    /// Toggled every tick: KON and KOFF are only polled every other sample.
+    every_other_sample: bool,
+
+    /// Echo unit, stepped by [`Self::step_echo`].
+    pub echo: Echo,
+
+    /// Debug mute mask, one bit per voice. Muted voices keep running but
+    /// are left out of the mix.
+    muted: u8,
+
+    /// Debug solo mask. When non-zero, only soloed voices are mixed.
+    soloed: u8,
+
+    /// Host-side gain trim per voice, 8.8 fixed point (0x100 = unchanged).
+    voice_gain: [u16; 8],
+
+    /// How the mix is turned into output samples.
+    output_mode: OutputMode,
+
+    /// Output stage high-pass filters, left and right, used in
+    /// [`OutputMode::Hardware`].
+    high_pass: [HighPass; 2],
+}
+
+/// Ticks a voice stays silent after being keyed on, before its envelope
+/// and pitch start.
+pub const KON_DELAY: u8 = 5;
+
+/// Gain of 1.0 in the 8.8 fixed point used by the output controls.
+pub const UNITY_GAIN: u16 = 0x100;
+
+/// Largest gain accepted by the output controls (×4).
+pub const MAX_GAIN: f32 = 4.0;
+
+/// Convert a gain factor to 8.8 fixed point, clamped to `0.0..=MAX_GAIN`.
+pub fn gain_to_fixed(gain: f32) -> u16 {
+    (gain.clamp(0.0, MAX_GAIN) * UNITY_GAIN as f32 + 0.5) as u16
 }
 
 impl Dsp {
     pub fn new() -> Self {
+        let mut registers = [0u8; 128];
+        // FLG powers up with soft reset, mute and echo writes disabled
+        registers[0x6C] = 0xE0;
+
         Self {
-            registers: [0u8; 128],
+            registers,
             voices: [Voice::default(); 8],
             dir_base: 0,
             // Hardware resets master volume to 0; game code sets it during boot.
             master_vol_left:  0,
             master_vol_right: 0,
+            noise_enable: 0,
+            noise_rate:   0,
+            noise: Noise::default(),
+            pending_kon:        0,
+            every_other_sample: false,
+            echo:  Echo::default(),
+            muted:  0,
+            soloed: 0,
+            voice_gain: [UNITY_GAIN; 8],
+            output_mode: OutputMode::Raw,
+            high_pass:   [HighPass::default(); 2],
         }
     }
 
+    /// Create a DSP whose noise generator starts from `seed`.
+    pub fn with_noise_seed(seed: u16) -> Self {
+        let mut dsp = Self::new();
+        dsp.seed_noise(seed);
+        dsp
+    }
+
+    /// Restart the noise generator from `seed`.
+    ///
+    /// The noise LFSR is the only stateful source of pseudo-random output
+    /// in the DSP; seeding it makes rendered audio reproducible.
+    pub fn seed_noise(&mut self, seed: u16) {
+        self.noise = Noise::new(seed);
+    }
+
     /// Read a DSP register by its 7-bit index.
     ///
     /// DSP register map (7-bit index `0x00–0x7F`):
@@ -125,24 +216,12 @@ impl Dsp {
 
             // ---- Global registers ----
             _ => match idx {
-                // $4C: KON — key on, one bit per voice (bit 0 = voice 0)
-                0x4C => {
-                    for v in 0..8usize {
-                        if value & (1 << v) != 0 {
-                            self.key_on_voice(v);
-                        }
-                    }
-                }
-
-                // $5C: KOFF — key off, enter release phase
-                0x5C => {
-                    for v in 0..8usize {
-                        if value & (1 << v) != 0 {
-                            self.voices[v].key_on = false;
-                            self.voices[v].adsr.envelope_phase = EnvelopePhase::Release;
-                        }
-                    }
-                }
+                // $4C: KON — key on, one bit per voice (bit 0 = voice 0),
+                // latched until the next poll in step()
+                0x4C => self.pending_kon = value,
+
+                // $5C: KOFF — key off, read from the register file by the
+                // poll in step() for as long as the bits stay set
 
                 // $0C: MVOLL — master left  volume (signed)
                 0x0C => self.master_vol_left  = value as i8,
@@ -153,21 +232,53 @@ impl Dsp {
                 // $5D: DIR — sample directory base page
                 0x5D => self.dir_base = value,
 
-                // All other registers (echo, FIR, noise, etc.) not yet implemented
+                // $3D: NON — voices that play noise instead of BRR samples
+                0x3D => self.noise_enable = value,
+
+                // $6C: FLG — the noise rate (bits 4-0) and the echo write
+                // disable (bit 5, read by the echo unit) are modelled;
+                // reset and mute bits are stored but ignored.
+                0x6C => self.noise_rate = value & 0x1F,
+
+                // Echo registers (EVOL, EFB, EON, ESA, EDL, FIR) are read
+                // from the register file by the echo unit.
                 _ => {}
             }
         }
     }
 
+    /// Poll KON and KOFF, done every other sample by the hardware.
+    ///
+    /// KOFF is a level: its voices are released on every poll until the
+    /// bits are cleared, including voices keyed on meanwhile. KON is an
+    /// edge: the pending bits are cleared once polled, and win over KOFF
+    /// within the same poll.
+    fn poll_key_on_off(&mut self) {
+        let kon  = core::mem::take(&mut self.pending_kon);
+        let koff = self.registers[0x5C];
+        for v in 0..8usize {
+            if koff & (1 << v) != 0 {
+                self.voices[v].key_on = false;
+                self.voices[v].adsr.envelope_phase = EnvelopePhase::Release;
+            }
+            if kon & (1 << v) != 0 {
+                self.key_on_voice(v);
+            }
+        }
+    }
+
     /// Handle key-on for voice `v`.
     ///
-    /// Marks the voice active and resets all playback state.
+    /// Marks the voice active and resets all playback state, restarting
+    /// the sample of a voice already playing. The voice then stays silent
+    /// for [`KON_DELAY`] ticks before its envelope and pitch start.
     /// The actual BRR start/loop addresses are read from the DIR table
-    /// on the first call to `step()` after key-on, when we have access
+    /// on the first call to `step()` after the delay, when we have access
     /// to APU RAM.
     fn key_on_voice(&mut self, v: usize) {
         let voice = &mut self.voices[v];
-        voice.key_on = true;
+        voice.key_on    = true;
+        voice.kon_delay = KON_DELAY;
 
         // Compute the directory entry address for this source number.
         // Each DIR entry is 4 bytes: [start_lo, start_hi, loop_lo, loop_hi].
@@ -207,38 +318,213 @@ impl Dsp {
     /// Takes `&RawARAM` rather than `&Memory` so the caller can pass
     /// `&memory.ram` without conflicting with the `&mut memory.dsp` borrow.
     pub fn step(&mut self, ram: &RawARAM) {
+        // KON and KOFF are polled every other sample, the first tick
+        // after power-on included
+        self.every_other_sample = !self.every_other_sample;
+        if self.every_other_sample {
+            self.poll_key_on_off();
+        }
+
         // Split borrows so we can pass &mut voice and &mut self.registers
         // into Voice::step() simultaneously — the borrow checker allows
         // borrowing separate struct fields at the same time.
+        self.noise.step(self.noise_rate);
+        let noise_sample = self.noise.output();
+        let noise_enable = self.noise_enable;
+
         let (voices, registers) = (&mut self.voices, &mut self.registers);
 
         for (i, voice) in voices.iter_mut().enumerate() {
-            voice.step(i, ram, registers);
+            let noise = (noise_enable & (1 << i) != 0).then_some(noise_sample);
+            voice.step(i, ram, registers, noise);
+        }
+    }
+
+    /// Advance the echo unit by one sample, after [`Self::step`].
+    ///
+    /// Reads the echo buffer in APU RAM and, unless FLG bit 5 (ECEN) is
+    /// set, writes the voices enabled in EON back into it: the buffer
+    /// overwrites whatever the program keeps there. Its output is added
+    /// to the next mixes.
+    ///
+    /// The echo input is the game-visible voice output: the host mute,
+    /// solo and gain controls leave it and the APU RAM unchanged.
+    pub fn step_echo(&mut self, ram: &mut RawARAM) {
+        let eon = self.registers[0x4D];
+        let mut input = [0i32; 2];
+        for (v, voice) in self.voices.iter().enumerate() {
+            if voice.adsr.envelope_phase == EnvelopePhase::Off || eon & (1 << v) == 0 {
+                continue;
+            }
+            let output = voice.output() as i32;
+            input[0] = clamp16(input[0] + ((output * voice.left_vol  as i32) >> 7)) as i32;
+            input[1] = clamp16(input[1] + ((output * voice.right_vol as i32) >> 7)) as i32;
+        }
+        self.echo.step(&self.registers, ram, input);
+    }
+
+    // ============================================================
+    // Save states
+    // ============================================================
+
+    /// Writes the registers, voices, noise, echo and output filters to a save state. The
+    /// debugging and host output controls (mute, solo, gain, output mode) are settings of
+    /// the emulator and are not saved.
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.bytes(&self.registers);
+        for voice in &self.voices {
+            voice.save_state(writer);
         }
+        writer.u8(self.dir_base);
+        writer.u8(self.master_vol_left as u8);
+        writer.u8(self.master_vol_right as u8);
+        writer.u8(self.noise_enable);
+        writer.u8(self.noise_rate);
+        self.noise.save_state(writer);
+        writer.u8(self.pending_kon);
+        writer.bool(self.every_other_sample);
+        self.echo.save_state(writer);
+        for filter in &self.high_pass {
+            filter.save_state(writer);
+        }
+    }
+
+    /// Restores the DSP written by [`Self::save_state`].
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.registers = reader.array()?;
+        for voice in &mut self.voices {
+            voice.load_state(reader)?;
+        }
+        self.dir_base = reader.u8()?;
+        self.master_vol_left = reader.u8()? as i8;
+        self.master_vol_right = reader.u8()? as i8;
+        self.noise_enable = reader.u8()?;
+        self.noise_rate = reader.u8()?;
+        self.noise.load_state(reader)?;
+        self.pending_kon = reader.u8()?;
+        self.every_other_sample = reader.bool()?;
+        self.echo.load_state(reader)?;
+        for filter in &mut self.high_pass {
+            filter.load_state(reader)?;
+        }
+        Ok(())
+    }
+
+    // ============================================================
+    // Debugging: mute/solo and channel inspection
+    // ============================================================
+
+    /// Mute or unmute voice `v` (0–7) in the mix.
+    ///
+    /// Debug-only: the voice keeps playing, so ENVX/OUTX/ENDX and the
+    /// game-visible state are unaffected.
+    pub fn set_voice_muted(&mut self, v: usize, muted: bool) {
+        set_bit(&mut self.muted, v, muted);
+    }
+
+    pub fn is_voice_muted(&self, v: usize) -> bool {
+        self.muted & (1 << v) != 0
+    }
+
+    /// Add or remove voice `v` (0–7) from the solo set.
+    /// While any voice is soloed, only soloed voices are mixed.
+    pub fn set_voice_solo(&mut self, v: usize, solo: bool) {
+        set_bit(&mut self.soloed, v, solo);
+    }
+
+    pub fn is_voice_soloed(&self, v: usize) -> bool {
+        self.soloed & (1 << v) != 0
+    }
+
+    /// Unmute and unsolo every voice.
+    pub fn clear_mute_solo(&mut self) {
+        self.muted  = 0;
+        self.soloed = 0;
+    }
+
+    /// Whether voice `v` contributes to the mix after mute/solo.
+    pub fn is_voice_audible(&self, v: usize) -> bool {
+        let soloed = self.soloed == 0 || self.is_voice_soloed(v);
+        soloed && !self.is_voice_muted(v)
+    }
+
+    /// Scale voice `v` (0–7) by `gain` in the mix: 0.0 is silent, 1.0
+    /// leaves it unchanged, up to [`MAX_GAIN`].
+    ///
+    /// Like muting, this is a host-side control for frontends (per-channel
+    /// sliders): the emulated registers and voice state are unaffected.
+    pub fn set_voice_gain(&mut self, v: usize, gain: f32) {
+        self.voice_gain[v] = gain_to_fixed(gain);
+    }
+
+    pub fn voice_gain(&self, v: usize) -> f32 {
+        self.voice_gain[v] as f32 / UNITY_GAIN as f32
+    }
+
+    /// Select between the raw mix and the output of the console, see
+    /// [`OutputMode`]. The output filters restart from silence.
+    pub fn set_output_mode(&mut self, mode: OutputMode) {
+        self.output_mode = mode;
+        self.high_pass   = [HighPass::default(); 2];
+    }
+
+    pub fn output_mode(&self) -> OutputMode {
+        self.output_mode
+    }
+
+    /// Snapshot of voice `v` (0–7) for a channel viewer.
+    pub fn voice_info(&self, v: usize) -> VoiceInfo {
+        let voice = &self.voices[v];
+        VoiceInfo {
+            envelope_phase: voice.adsr.envelope_phase,
+            envelope_level: voice.adsr.envelope_level,
+            pitch:          voice.pitch,
+            srcn:           voice.srcn,
+            key_on:         voice.key_on,
+            noise:          self.noise_enable & (1 << v) != 0,
+            output:         voice.output(),
+            muted:          self.is_voice_muted(v),
+            soloed:         self.is_voice_soloed(v),
+            audible:        self.is_voice_audible(v),
+        }
+    }
+
+    /// Snapshots of all 8 voices, indexed by voice number.
+    pub fn voice_infos(&self) -> [VoiceInfo; 8] {
+        core::array::from_fn(|v| self.voice_info(v))
     }
 
     /// Mix all active voices into one stereo output sample pair.
     ///
     /// Uses integer arithmetic throughout to match hardware behaviour.
     /// Volumes are signed i8; samples and envelope are 16-bit.
-    /// The accumulator is i32 to prevent overflow during summation.
+    /// The accumulator is i32 to prevent overflow during summation; in
+    /// [`OutputMode::Hardware`] it saturates to 16 bits after each voice
+    /// instead, like the DSP adders.
+    ///
+    /// This is the mix only, without the output stage of
+    /// [`Self::render_output`].
     pub fn render_audio_single(&self) -> (i16, i16) {
+        let saturate = self.output_mode == OutputMode::Hardware;
         let mut left:  i32 = 0;
         let mut right: i32 = 0;
 
-        for voice in self.voices.iter() {
-            if voice.adsr.envelope_phase == EnvelopePhase::Off {
+        for (v, voice) in self.voices.iter().enumerate() {
+            if voice.adsr.envelope_phase == EnvelopePhase::Off || !self.is_voice_audible(v) {
                 continue;
             }
 
-            // Scale sample by 11-bit envelope (0–0x7FF) → back to ~16-bit range
-            let env    = voice.adsr.envelope_level as i32; // 0–0x7FF
-            let sample = voice.current_sample as i32;      // -32768..+32767
-            let scaled = (sample * env) >> 11;             // ~16-bit result
+            // Sample scaled by the 11-bit envelope (0–0x7FF), ~16-bit result,
+            // then by the host gain trim (exact at unity gain)
+            let scaled = (voice.output() as i32 * self.voice_gain[v] as i32) >> 8;
 
             // Apply signed per-voice volumes (i8, -128..+127), shift by 7
             left  += (scaled * voice.left_vol  as i32) >> 7;
             right += (scaled * voice.right_vol as i32) >> 7;
+            if saturate {
+                left  = clamp16(left)  as i32;
+                right = clamp16(right) as i32;
+            }
         }
 
         // Apply master volume ($0C/$1C) as a final output stage scaler.
@@ -247,10 +533,40 @@ impl Dsp {
         // already-summed mix past i16 range again.
         left  = (left  * self.master_vol_left  as i32) >> 7;
         right = (right * self.master_vol_right as i32) >> 7;
+        if saturate {
+            left  = clamp16(left)  as i32;
+            right = clamp16(right) as i32;
+        }
+
+        // Echo output, already scaled by EVOL ($2C/$3C)
+        let (echo_left, echo_right) = self.echo.output();
+        left  += echo_left  as i32;
+        right += echo_right as i32;
+
+        (clamp16(left), clamp16(right))
+    }
+
+    /// Mix all active voices and pass the result through the output
+    /// stage: unchanged in [`OutputMode::Raw`], halved and high-pass
+    /// filtered in [`OutputMode::Hardware`].
+    ///
+    /// Call once per DSP tick, the filters keep their state between calls.
+    pub fn render_output(&mut self) -> (i16, i16) {
+        let (left, right) = self.render_audio_single();
+        match self.output_mode {
+            OutputMode::Raw      => (left, right),
+            OutputMode::Hardware => (
+                self.high_pass[0].filter(left  >> 1),
+                self.high_pass[1].filter(right >> 1),
+            ),
+        }
+    }
+}
 
-        (
-            left .clamp(i16::MIN as i32, i16::MAX as i32) as i16,
-            right.clamp(i16::MIN as i32, i16::MAX as i32) as i16,
-        )
+fn set_bit(mask: &mut u8, bit: usize, set: bool) {
+    if set {
+        *mask |= 1 << bit;
+    } else {
+        *mask &= !(1 << bit);
     }
 }
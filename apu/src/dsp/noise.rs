@@ -0,0 +1,82 @@
+use super::adsr::ENVELOPE_RATE_TABLE;
+use common::save_state::{StateError, StateReader, StateWriter};
+
+/// LFSR state the DSP powers up with.
+pub const NOISE_DEFAULT_SEED: u16 = 0x4000;
+
+/// The DSP noise generator.
+///
+/// A single 15-bit LFSR shared by every voice whose NON ($3D) bit is set.
+/// It is clocked at the rate selected by FLG ($6C) bits 4-0, using the
+/// same period table as the ADSR envelope.
+///
+/// The generator is fully deterministic: two generators seeded with the
+/// same value produce the same sequence on every platform.
+#[derive(Debug, Clone, Copy)]
+pub struct Noise {
+    /// 15-bit shift register (bit 14 = newest bit).
+    lfsr: u16,
+
+    /// Ticks since the last LFSR shift.
+    tick_counter: u16,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self::new(NOISE_DEFAULT_SEED)
+    }
+}
+
+impl Noise {
+    /// Create a generator starting from `seed`.
+    ///
+    /// Only the low 15 bits are used. An all-zero register would never
+    /// change, so a zero seed falls back to [`NOISE_DEFAULT_SEED`].
+    pub fn new(seed: u16) -> Self {
+        let lfsr = match seed & 0x7FFF {
+            0 => NOISE_DEFAULT_SEED,
+            s => s,
+        };
+        Self { lfsr, tick_counter: 0 }
+    }
+
+    /// Current LFSR state.
+    pub fn lfsr(&self) -> u16 {
+        self.lfsr
+    }
+
+    /// Advance one DSP tick at FLG noise rate `rate` (0–31).
+    /// Rate 0 never clocks the LFSR; rate 31 clocks it every tick.
+    pub fn step(&mut self, rate: u8) {
+        let period = ENVELOPE_RATE_TABLE[(rate & 0x1F) as usize];
+        if period == 0 {
+            return;
+        }
+
+        self.tick_counter += 1;
+        if self.tick_counter < period {
+            return;
+        }
+        self.tick_counter = 0;
+
+        // New bit 14 = bit 0 XOR bit 1.
+        let feedback = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+        self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+    }
+
+    /// Signed 16-bit noise sample: the LFSR shifted left by one.
+    pub fn output(&self) -> i16 {
+        (self.lfsr << 1) as i16
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.u16(self.lfsr);
+        writer.u16(self.tick_counter);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.lfsr = reader.u16()?;
+        self.tick_counter = reader.u16()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use super::adsr::EnvelopePhase;
+use super::Dsp;
+
+/// Native DSP output rate, reached by a voice at pitch 0x1000.
+pub const DSP_SAMPLE_RATE: u32 = 32_000;
+
+/// Decoded snapshot of the DSP registers and voice state, for a debug UI.
+///
+/// Every field is a plain value, so a frontend can keep one view and refresh
+/// it each frame with [`DspView::update`] without allocating.
+/// Produced by [`Dsp::view`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DspView {
+    /// Raw register file ($00–$7F), as read through $F3.
+    pub registers: [u8; 128],
+
+    /// The 8 voices, indexed by voice number.
+    pub voices: [VoiceView; 8],
+
+    /// $0C MVOLL — master left volume, signed.
+    pub master_vol_left: i8,
+
+    /// $1C MVOLR — master right volume, signed.
+    pub master_vol_right: i8,
+
+    /// $5D DIR — address of the sample directory in APU RAM.
+    pub dir_addr: u16,
+
+    /// $6C FLG bit 7 — soft reset: every voice is keyed off and muted.
+    pub soft_reset: bool,
+
+    /// $6C FLG bit 6 — output muted.
+    pub mute: bool,
+
+    /// $6C FLG bits 4-0 — noise clock rate index (0–31).
+    pub noise_rate: u8,
+
+    /// Echo unit configuration.
+    pub echo: EchoView,
+}
+
+/// Decoded registers and playback state of one voice.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VoiceView {
+    /// $x0 VOL(L) — left volume, signed.
+    pub left_vol: i8,
+
+    /// $x1 VOL(R) — right volume, signed.
+    pub right_vol: i8,
+
+    /// $x2/$x3 PITCH — 14-bit pitch (0x1000 = 32 kHz).
+    pub pitch: u16,
+
+    /// $x4 SRCN — sample source number.
+    pub srcn: u8,
+
+    /// $x5 ADSR1 bit 7 — envelope from ADSR (true) or GAIN (false).
+    pub adsr_mode: bool,
+
+    /// $x5 ADSR1 bits 3-0 — attack rate index (0–15).
+    pub attack_rate: u8,
+
+    /// $x5 ADSR1 bits 6-4 — decay rate index (0–7).
+    pub decay_rate: u8,
+
+    /// $x6 ADSR2 bits 7-5 — sustain level (0–7).
+    pub sustain_level: u8,
+
+    /// $x6 ADSR2 bits 4-0 — sustain rate index (0–31).
+    pub sustain_rate: u8,
+
+    /// $x7 GAIN — raw value, used when `adsr_mode` is false.
+    pub gain: u8,
+
+    /// $x8 ENVX — 7-bit envelope level.
+    pub envx: u8,
+
+    /// $x9 OUTX — signed top byte of the voice output.
+    pub outx: i8,
+
+    /// Current ADSR phase.
+    pub envelope_phase: EnvelopePhase,
+
+    /// 11-bit envelope level (0–0x7FF).
+    pub envelope_level: u16,
+
+    /// Whether the voice is keyed on.
+    pub key_on: bool,
+
+    /// $7C ENDX bit — the voice reached the end block of its sample.
+    pub ended: bool,
+
+    /// $2D PMON bit — pitch modulated by the previous voice's output.
+    pub pitch_mod: bool,
+
+    /// $3D NON bit — plays noise instead of its sample.
+    pub noise: bool,
+
+    /// $4D EON bit — sent to the echo unit.
+    pub echo: bool,
+}
+
+/// Decoded echo registers.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EchoView {
+    /// $2C EVOLL — echo left volume, signed.
+    pub left_vol: i8,
+
+    /// $3C EVOLR — echo right volume, signed.
+    pub right_vol: i8,
+
+    /// $0D EFB — echo feedback, signed.
+    pub feedback: i8,
+
+    /// $6D ESA — address of the echo buffer in APU RAM.
+    pub start_addr: u16,
+
+    /// $7D EDL bits 3-0 — echo delay, in 16 ms steps (0–15).
+    pub delay: u8,
+
+    /// $6C FLG bit 5 clear — the echo unit writes its buffer to APU RAM.
+    pub write_enabled: bool,
+
+    /// $xF C0–C7 — FIR filter coefficients, signed.
+    pub fir: [i8; 8],
+}
+
+impl VoiceView {
+    /// Playback rate of the sample in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.pitch as u32 * DSP_SAMPLE_RATE / 0x1000
+    }
+}
+
+impl EchoView {
+    /// Size of the echo buffer in bytes: 2 KiB per delay step, 4 bytes at delay 0.
+    pub fn buffer_len(&self) -> usize {
+        if self.delay == 0 {
+            4
+        } else {
+            self.delay as usize * 0x800
+        }
+    }
+}
+
+impl DspView {
+    /// Refresh the view from `dsp` in place.
+    pub fn update(&mut self, dsp: &Dsp) {
+        let regs = &dsp.registers;
+        self.registers = *regs;
+
+        let bit = |reg: usize, v: usize| regs[reg] & (1 << v) != 0;
+        for (v, view) in self.voices.iter_mut().enumerate() {
+            let base  = v << 4;
+            let voice = &dsp.voices[v];
+            *view = VoiceView {
+                left_vol:       regs[base] as i8,
+                right_vol:      regs[base | 0x1] as i8,
+                pitch:          u16::from_le_bytes([regs[base | 0x2], regs[base | 0x3]]) & 0x3FFF,
+                srcn:           regs[base | 0x4],
+                adsr_mode:      regs[base | 0x5] & 0x80 != 0,
+                attack_rate:    regs[base | 0x5] & 0x0F,
+                decay_rate:     (regs[base | 0x5] >> 4) & 0x07,
+                sustain_level:  regs[base | 0x6] >> 5,
+                sustain_rate:   regs[base | 0x6] & 0x1F,
+                gain:           regs[base | 0x7],
+                envx:           regs[base | 0x8],
+                outx:           regs[base | 0x9] as i8,
+                envelope_phase: voice.adsr.envelope_phase,
+                envelope_level: voice.adsr.envelope_level,
+                key_on:         voice.key_on,
+                ended:          bit(0x7C, v),
+                pitch_mod:      bit(0x2D, v),
+                noise:          bit(0x3D, v),
+                echo:           bit(0x4D, v),
+            };
+        }
+
+        let flg = regs[0x6C];
+        self.master_vol_left  = regs[0x0C] as i8;
+        self.master_vol_right = regs[0x1C] as i8;
+        self.dir_addr         = regs[0x5D] as u16 * 0x100;
+        self.soft_reset       = flg & 0x80 != 0;
+        self.mute             = flg & 0x40 != 0;
+        self.noise_rate       = flg & 0x1F;
+        self.echo = EchoView {
+            left_vol:      regs[0x2C] as i8,
+            right_vol:     regs[0x3C] as i8,
+            feedback:      regs[0x0D] as i8,
+            start_addr:    regs[0x6D] as u16 * 0x100,
+            delay:         regs[0x7D] & 0x0F,
+            write_enabled: flg & 0x20 == 0,
+            fir:           core::array::from_fn(|i| regs[(i << 4) | 0xF] as i8),
+        };
+    }
+}
+
+impl Default for DspView {
+    fn default() -> Self {
+        Self {
+            registers:        [0; 128],
+            voices:           [VoiceView::default(); 8],
+            master_vol_left:  0,
+            master_vol_right: 0,
+            dir_addr:         0,
+            soft_reset:       false,
+            mute:             false,
+            noise_rate:       0,
+            echo:             EchoView::default(),
+        }
+    }
+}
+
+impl Dsp {
+    /// Decoded snapshot of the registers and voices, for a debug UI.
+    /// To refresh a view every frame, see [`DspView::update`].
+    pub fn view(&self) -> DspView {
+        let mut view = DspView::default();
+        view.update(self);
+        view
+    }
+}
@@ -2,6 +2,7 @@ use crate::memory::RawARAM;
 
 use super::adsr::{Adsr, EnvelopePhase};
 use super::brr::{Brr, decode_brr_block, ram_read8};
+use common::save_state::{StateError, StateReader, StateWriter};
 
 /// One voice (channel) of the SNES APU DSP.
 #[derive(Debug, Clone, Copy, Default)]
@@ -22,6 +23,10 @@ pub struct Voice {
     /// Whether this voice is currently keyed on (actively playing).
     pub key_on: bool,
 
+    /// Ticks left before a voice just keyed on starts: its envelope is
+    /// held at 0 and its pitch counter stopped meanwhile.
+    pub kon_delay: u8,
+
     /// 16-bit pitch counter used to pace sample consumption.
     /// Every 0x1000 units = 1 BRR sample consumed.
     pub pitch_counter: u16,
@@ -36,6 +41,41 @@ pub struct Voice {
     pub brr: Brr,
 }
 
+/// Read-only snapshot of one voice, for debugger channel viewers.
+/// Produced by [`super::Dsp::voice_info`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoiceInfo {
+    /// Current ADSR phase.
+    pub envelope_phase: EnvelopePhase,
+
+    /// 11-bit envelope level (0–0x7FF).
+    pub envelope_level: u16,
+
+    /// 14-bit pitch value.
+    pub pitch: u16,
+
+    /// Sample source number.
+    pub srcn: u8,
+
+    /// Whether the voice is keyed on.
+    pub key_on: bool,
+
+    /// Whether the voice plays noise (NON bit set) instead of BRR data.
+    pub noise: bool,
+
+    /// Post-envelope output level, before per-voice volume.
+    pub output: i16,
+
+    /// Debug mute flag.
+    pub muted: bool,
+
+    /// Debug solo flag.
+    pub soloed: bool,
+
+    /// Whether the voice reaches the mix after mute/solo.
+    pub audible: bool,
+}
+
 impl Voice {
     /// Advance this voice by one DSP tick.
     ///
@@ -43,7 +83,48 @@ impl Voice {
     /// offsets and the ENDX bitmask.
     /// `registers` is the DSP register file; ENVX, OUTX, and ENDX are
     /// written here so the CPU can read them back via `$F3`.
-    pub fn step(&mut self, i: usize, ram: &RawARAM, registers: &mut [u8; 128]) {
+    /// `noise` is the shared noise sample when this voice's NON bit is set;
+    /// it replaces the BRR output, while BRR decoding keeps running so
+    /// end/loop flags and ENDX keep their timing.
+    pub fn step(&mut self, i: usize, ram: &RawARAM, registers: &mut [u8; 128], noise: Option<i16>) {
+        self.advance(i, ram, registers);
+
+        if let Some(sample) = noise
+            && self.adsr.envelope_phase != EnvelopePhase::Off
+        {
+            self.current_sample = sample;
+        }
+
+        // 5. Update read-only ENVX ($X8) and OUTX ($X9) registers.
+        // Done every tick, so released and silent voices are reflected too.
+        registers[(i << 4) | 0x8] = self.envx();
+        registers[(i << 4) | 0x9] = self.outx();
+    }
+
+    /// ENVX value: envelope_level >> 4 (11-bit → 7-bit)
+    pub fn envx(&self) -> u8 {
+        (self.adsr.envelope_level >> 4) as u8
+    }
+
+    /// OUTX value: signed top byte of the post-envelope output
+    pub fn outx(&self) -> u8 {
+        (self.output() >> 8) as u8
+    }
+
+    /// Current sample scaled by the 11-bit envelope (0–0x7FF),
+    /// before the per-voice volumes are applied.
+    pub fn output(&self) -> i16 {
+        ((self.current_sample as i32 * self.adsr.envelope_level as i32) >> 11) as i16
+    }
+
+    /// Envelope, BRR decoding and pitch steps of [`Self::step`].
+    fn advance(&mut self, i: usize, ram: &RawARAM, registers: &mut [u8; 128]) {
+        // 0. Key-on delay: silent, neither envelope nor pitch run
+        if self.kon_delay > 0 {
+            self.kon_delay -= 1;
+            return;
+        }
+
         // 1. Envelope update
         if self.adsr.envelope_phase != EnvelopePhase::Off {
             self.adsr.update_envelope();
@@ -97,11 +178,6 @@ impl Voice {
             }
         }
 
-        // 5. Update read-only ENVX ($X8) and OUTX ($X9) registers.
-        //   ENVX = envelope_level >> 4  (11-bit → 7-bit)
-        //   OUTX = current_sample  >> 8 (signed top byte)
-        registers[(i << 4) | 0x8] = (self.adsr.envelope_level >> 4) as u8;
-        registers[(i << 4) | 0x9] = (self.current_sample >> 8) as u8;
     }
 
     /// Decode the next 9-byte BRR block and advance the BRR address.
@@ -137,4 +213,95 @@ impl Voice {
             self.brr.addr = self.brr.addr.wrapping_add(9);
         }
     }
+
+    /// Writes the voice, its envelope and its BRR decoder to a save state.
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.u8(self.left_vol as u8);
+        writer.u8(self.right_vol as u8);
+        writer.u16(self.pitch);
+        writer.u8(self.srcn);
+        writer.bool(self.key_on);
+        writer.u8(self.kon_delay);
+        writer.u16(self.pitch_counter);
+        writer.u16(self.current_sample as u16);
+
+        let adsr = &self.adsr;
+        writer.bool(adsr.adsr_mode);
+        for value in [
+            adsr.attack_rate,
+            adsr.decay_rate,
+            adsr.sustain_level,
+            adsr.sustain_rate,
+        ] {
+            writer.u8(value);
+        }
+        writer.u16(adsr.envelope_level);
+        writer.u8(match adsr.envelope_phase {
+            EnvelopePhase::Attack => 0,
+            EnvelopePhase::Decay => 1,
+            EnvelopePhase::Sustain => 2,
+            EnvelopePhase::Release => 3,
+            EnvelopePhase::Off => 4,
+        });
+        writer.u16(adsr.tick_counter);
+
+        let brr = &self.brr;
+        writer.u16(brr.addr);
+        writer.u8(brr.nibble_idx);
+        writer.u16(brr.prev1 as u16);
+        writer.u16(brr.prev2 as u16);
+        writer.u16(brr.loop_addr);
+        for sample in brr.sample_buffer {
+            writer.u16(sample as u16);
+        }
+        writer.u8(brr.buffer_fill);
+    }
+
+    /// Restores the voice written by [`Self::save_state`].
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.left_vol = reader.u8()? as i8;
+        self.right_vol = reader.u8()? as i8;
+        self.pitch = reader.u16()?;
+        self.srcn = reader.u8()?;
+        self.key_on = reader.bool()?;
+        self.kon_delay = reader.u8()?;
+        self.pitch_counter = reader.u16()?;
+        self.current_sample = reader.u16()? as i16;
+
+        let adsr = &mut self.adsr;
+        adsr.adsr_mode = reader.bool()?;
+        for value in [
+            &mut adsr.attack_rate,
+            &mut adsr.decay_rate,
+            &mut adsr.sustain_level,
+            &mut adsr.sustain_rate,
+        ] {
+            *value = reader.u8()?;
+        }
+        adsr.envelope_level = reader.u16()?;
+        adsr.envelope_phase = match reader.u8()? {
+            0 => EnvelopePhase::Attack,
+            1 => EnvelopePhase::Decay,
+            2 => EnvelopePhase::Sustain,
+            3 => EnvelopePhase::Release,
+            4 => EnvelopePhase::Off,
+            _ => return Err(reader.corrupt()),
+        };
+        adsr.tick_counter = reader.u16()?;
+
+        let brr = &mut self.brr;
+        brr.addr = reader.u16()?;
+        brr.nibble_idx = reader.u8()?;
+        brr.prev1 = reader.u16()? as i16;
+        brr.prev2 = reader.u16()? as i16;
+        brr.loop_addr = reader.u16()?;
+        for sample in &mut brr.sample_buffer {
+            *sample = reader.u16()? as i16;
+        }
+        brr.buffer_fill = reader.u8()?;
+        if brr.nibble_idx > 16 || brr.buffer_fill > 16 {
+            return Err(reader.corrupt());
+        }
+        Ok(())
+    }
 }
@@ -1,8 +1,18 @@
+//! The audio processing unit: SPC700 CPU, S-DSP and their shared memory. The crate is
+//! `no_std` (with `alloc`) when the default `std` feature is disabled, which only leaves out
+//! WAV file writing.
+
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+extern crate alloc;
+
 pub mod cpu;
 pub mod dsp;
 pub mod memory;
 pub mod timers;
 pub mod apu;
+#[cfg(feature = "std")]
+pub mod wav;
 
 pub use apu::Apu;
 pub use cpu::Spc700;
@@ -4,37 +4,34 @@
 ///
 ///   Test 1 — BRR encoder helper + single-voice sine wave
 ///     Verifies that BRR encoding/decoding round-trips correctly.
-///     Writes "test1_sine.raw".
+///     Writes "test1_sine.wav".
 ///
 ///   Test 2 — All 8 voices simultaneously (different pitches)
 ///     Puts a simple tone on each voice at a different pitch value,
 ///     confirming the mixer sums all 8 channels.
-///     Writes "test2_8voices.raw".
+///     Writes "test2_8voices.wav".
 ///
 ///   Test 3 — ADSR phase progression
 ///     One voice with a clearly audible attack → decay → sustain → release
-///     shape. Prints envelope level milestones to stdout.
-///     Writes "test3_adsr.raw".
+///     shape. Logs envelope level milestones.
+///     Writes "test3_adsr.wav".
 ///
 ///   Test 4 — BRR loop flag
 ///     Encodes a short one-block sample with the loop flag set,
 ///     verifies it keeps playing rather than going silent.
-///     Writes "test4_loop.raw".
+///     Writes "test4_loop.wav".
 ///
 ///   Test 5 — Stereo pan
 ///     Two voices: one panned hard left, one hard right.
-///     Writes "test5_stereo.raw".
+///     Writes "test5_stereo.wav".
 ///
-/// All output files are raw signed 16-bit little-endian PCM at 32 000 Hz mono
-/// (tests 1–4) or stereo interleaved (test 5).
-/// Play back with e.g.:
-///   ffplay -f s16le -ar 32000 -ac 1 test1_sine.raw
-///   ffplay -f s16le -ar 32000 -ac 2 test5_stereo.raw
+/// All output files are 16-bit PCM WAV at 32 000 Hz, mono (tests 1–4)
+/// or stereo (test 5), playable in any audio player.
 
 use apu::dsp::{Dsp, EnvelopePhase};
 use apu::Memory;
-use std::fs::File;
-use std::io::Write;
+use apu::wav::{write_wav, write_wav_mono};
+use tracing::{info, info_span, warn};
 
 // ============================================================
 // BRR BLOCK BUILDER
@@ -117,27 +114,30 @@ fn write_dir_entry(mem: &mut Memory, dir_page: u8, srcn: u8, start: u16, loop_ad
 
 // ============================================================
 // DSP CONFIGURATION HELPERS
-// These write directly to DSP registers via the Memory bus,
-// exactly as the SPC700 CPU would via the $F2/$F3 port pair.
-//
-// DSP register addresses (as seen on the memory bus after the
-// Memory layer strips the 0xF200 base):
-//   Voice N register R  →  bus address 0xF200 + (N << 4) + R
-//   Global register G   →  bus address 0xF200 + G
+// These write DSP registers through the Memory bus, exactly as
+// the SPC700 CPU does via the $F2/$F3 port pair:
+//   Voice N register R  →  DSP register (N << 4) + R
+//   Global register G   →  DSP register G
 // ============================================================
 
-const DSP_BASE: u16 = 0xF200;
+const DSPADDR: u16 = 0x00F2;
+const DSPDATA: u16 = 0x00F3;
+
+/// Write a DSP register by its index (0x00–0x7F) through $F2/$F3.
+fn dsp_write(mem: &mut Memory, reg: u8, val: u8) {
+    mem.write8(DSPADDR, reg);
+    mem.write8(DSPDATA, val);
+}
 
 /// Write a per-voice DSP register.
 /// `voice` = 0–7, `reg` = 0x0–0xF (offset within the voice's 16-byte block).
 fn dsp_voice_write(mem: &mut Memory, voice: u8, reg: u8, val: u8) {
-    let addr = DSP_BASE + ((voice as u16) << 4) + reg as u16;
-    mem.write8(addr, val);
+    dsp_write(mem, (voice << 4) + reg, val);
 }
 
 /// Write a global DSP register by its index (0x00–0x7F).
 fn dsp_global_write(mem: &mut Memory, reg: u8, val: u8) {
-    mem.write8(DSP_BASE + reg as u16, val);
+    dsp_write(mem, reg, val);
 }
 
 /// Configure ADSR for a voice in one call.
@@ -208,7 +208,8 @@ fn write_square_brr_loop(mem: &mut Memory, start_addr: u16) {
 // ============================================================
 
 fn test1_sine() {
-    println!("\n=== Test 1: Single voice sine wave (no loop) ===");
+    let _span = info_span!("test1_sine").entered();
+    info!("Single voice sine wave (no loop)");
 
     let mut mem = Memory::new();
 
@@ -261,13 +262,13 @@ fn test1_sine() {
         if !env_phase_logged
             && mem.dsp.voices[0].adsr.envelope_phase == EnvelopePhase::Off
         {
-            println!("  Voice went silent at sample {i}");
+            info!(sample = i, "Voice went silent");
             env_phase_logged = true;
         }
     }
 
-    save_mono("test1_sine.raw", &out);
-    println!("  Written test1_sine.raw ({} samples, 32 kHz mono s16le)", out.len());
+    save_mono("test1_sine.wav", &out);
+    info!(samples = out.len(), "Written test1_sine.wav (32 kHz mono)");
 }
 
 // ============================================================
@@ -275,7 +276,8 @@ fn test1_sine() {
 // ============================================================
 
 fn test2_8voices() {
-    println!("\n=== Test 2: All 8 voices, different pitches ===");
+    let _span = info_span!("test2_8voices").entered();
+    info!("All 8 voices, different pitches");
 
     let mut mem = Memory::new();
 
@@ -330,10 +332,10 @@ fn test2_8voices() {
 
     // Quick sanity: at least some non-zero output expected
     let non_zero = out.iter().filter(|&&s| s != 0).count();
-    println!("  Non-zero samples: {non_zero}/{}", out.len());
+    info!(non_zero, samples = out.len(), "Non-zero samples");
 
-    save_mono("test2_8voices.raw", &out);
-    println!("  Written test2_8voices.raw");
+    save_mono("test2_8voices.wav", &out);
+    info!("Written test2_8voices.wav");
 }
 
 // ============================================================
@@ -341,7 +343,8 @@ fn test2_8voices() {
 // ============================================================
 
 fn test3_adsr() {
-    println!("\n=== Test 3: ADSR envelope shape ===");
+    let _span = info_span!("test3_adsr").entered();
+    info!("ADSR envelope shape");
 
     let mut mem = Memory::new();
 
@@ -379,7 +382,7 @@ fn test3_adsr() {
         // Key-off at 1 second
         if i == release_start {
             key_off(&mut mem, 0x01);
-            println!("  Key-off triggered at sample {i}");
+            info!(sample = i, "Key-off triggered");
         }
 
         mem.dsp.step(&mem.ram);
@@ -397,13 +400,13 @@ fn test3_adsr() {
                 EnvelopePhase::Off     => "Off",
             };
             let level = mem.dsp.voices[0].adsr.envelope_level;
-            println!("  → {name} at sample {i} (envelope = {level:#05X})");
+            info!(sample = i, envelope = format_args!("{level:#05X}"), "{name}");
             last_phase = cur_phase;
         }
     }
 
-    save_mono("test3_adsr.raw", &out);
-    println!("  Written test3_adsr.raw");
+    save_mono("test3_adsr.wav", &out);
+    info!("Written test3_adsr.wav");
 }
 
 // ============================================================
@@ -411,7 +414,8 @@ fn test3_adsr() {
 // ============================================================
 
 fn test4_loop() {
-    println!("\n=== Test 4: BRR loop flag ===");
+    let _span = info_span!("test4_loop").entered();
+    info!("BRR loop flag");
 
     let mut mem = Memory::new();
 
@@ -473,21 +477,21 @@ fn test4_loop() {
         out.push(l);
 
         if mem.dsp.voices[0].adsr.envelope_phase == EnvelopePhase::Off {
-            println!("  ✗ Voice unexpectedly went silent at sample {i}");
+            warn!(sample = i, "Voice unexpectedly went silent");
             break;
         }
     }
 
     let went_silent = mem.dsp.voices[0].adsr.envelope_phase == EnvelopePhase::Off;
     if !went_silent {
-        println!("  ✓ Voice still active after {} samples — loop is working", num_samples);
+        info!(samples = num_samples, "Voice still active, loop is working");
     }
 
     let non_zero = out.iter().filter(|&&s| s != 0).count();
-    println!("  Non-zero samples: {non_zero}/{}", out.len());
+    info!(non_zero, samples = out.len(), "Non-zero samples");
 
-    save_mono("test4_loop.raw", &out);
-    println!("  Written test4_loop.raw");
+    save_mono("test4_loop.wav", &out);
+    info!("Written test4_loop.wav");
 }
 
 // ============================================================
@@ -495,7 +499,8 @@ fn test4_loop() {
 // ============================================================
 
 fn test5_stereo() {
-    println!("\n=== Test 5: Stereo pan (voice 0 = left, voice 1 = right) ===");
+    let _span = info_span!("test5_stereo").entered();
+    info!("Stereo pan (voice 0 = left, voice 1 = right)");
 
     let mut mem = Memory::new();
 
@@ -545,15 +550,14 @@ fn test5_stereo() {
     // Sanity: left channel should have signal, right should be near zero and vice versa
     let left_energy:  i64 = left_out .iter().map(|&s| s as i64 * s as i64).sum();
     let right_energy: i64 = right_out.iter().map(|&s| s as i64 * s as i64).sum();
-    println!("  Left  channel energy: {left_energy}");
-    println!("  Right channel energy: {right_energy}");
+    info!(left_energy, right_energy, "Channel energy");
 
     if left_energy > 0 && right_energy > 0 {
-        println!("  ✓ Both channels carry signal");
+        info!("Both channels carry signal");
     }
 
-    save_stereo_interleaved("test5_stereo.raw", &left_out, &right_out);
-    println!("  Written test5_stereo.raw (32 kHz stereo s16le)");
+    save_stereo_interleaved("test5_stereo.wav", &left_out, &right_out);
+    info!("Written test5_stereo.wav (32 kHz stereo)");
 }
 
 // ============================================================
@@ -561,18 +565,12 @@ fn test5_stereo() {
 // ============================================================
 
 fn save_mono(path: &str, samples: &[i16]) {
-    let mut f = File::create(path).expect("could not create file");
-    for &s in samples {
-        f.write_all(&s.to_le_bytes()).unwrap();
-    }
+    write_wav_mono(path, samples, SAMPLE_RATE).expect("could not write WAV file");
 }
 
 fn save_stereo_interleaved(path: &str, left: &[i16], right: &[i16]) {
-    let mut f = File::create(path).expect("could not create file");
-    for (&l, &r) in left.iter().zip(right.iter()) {
-        f.write_all(&l.to_le_bytes()).unwrap();
-        f.write_all(&r.to_le_bytes()).unwrap();
-    }
+    let pairs: Vec<(i16, i16)> = left.iter().copied().zip(right.iter().copied()).collect();
+    write_wav(path, &pairs, SAMPLE_RATE).expect("could not write WAV file");
 }
 
 
@@ -581,8 +579,8 @@ fn save_stereo_interleaved(path: &str, left: &[i16], right: &[i16]) {
 // ============================================================
 
 fn main() {
-    println!("SNES APU Comprehensive Test");
-    println!("Output rate: {} Hz, format: signed 16-bit little-endian PCM", SAMPLE_RATE);
+    tracing_subscriber::fmt::init();
+    info!(sample_rate = SAMPLE_RATE, "SNES APU Comprehensive Test, writing 16-bit PCM WAV files");
 
     test1_sine();
     test2_8voices();
@@ -590,11 +588,5 @@ fn main() {
     test4_loop();
     test5_stereo();
 
-    println!("\nAll tests complete.");
-    println!("To listen:");
-    println!("  ffplay -f s16le -ar 32000 -ac 1 test1_sine.raw");
-    println!("  ffplay -f s16le -ar 32000 -ac 1 test2_8voices.raw");
-    println!("  ffplay -f s16le -ar 32000 -ac 1 test3_adsr.raw");
-    println!("  ffplay -f s16le -ar 32000 -ac 1 test4_loop.raw");
-    println!("  ffplay -f s16le -ar 32000 -ac 2 test5_stereo.raw");
+    info!("All tests complete. Open the .wav files in any audio player to listen.");
 }
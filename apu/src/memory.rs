@@ -1,9 +1,33 @@
+use alloc::boxed::Box;
 use crate::dsp::Dsp;
+use common::save_state::{StateError, StateReader, StateWriter};
 use common::u16_split::U16Split;
 
 /// 64 KB APU RAM
 pub type RawARAM = [u8; 64 * 1024];
 
+/// IPL boot ROM: waits for the SNES CPU to upload a program through the
+/// CPUIO ports, then jumps to it.
+pub const IPL_ROM: [u8; 64] = [
+    0xCD, 0xEF, 0xBD, 0xE8, 0x00, 0xC6, 0x1D, 0xD0, 0xFC, 0x8F, 0xAA, 0xF4, 0x8F, 0xBB, 0xF5, 0x78,
+    0xCC, 0xF4, 0xD0, 0xFB, 0x2F, 0x19, 0xEB, 0xF4, 0xD0, 0xFC, 0x7E, 0xF4, 0xD0, 0x0B, 0xE4, 0xF5,
+    0xCB, 0xF4, 0xD7, 0x00, 0xFC, 0xD0, 0xF3, 0xAB, 0x01, 0x10, 0xEF, 0x7E, 0xF4, 0x10, 0xEB, 0xBA,
+    0xF6, 0xDA, 0x00, 0xBA, 0xF4, 0xC4, 0xF4, 0xDD, 0x5D, 0xD0, 0xDB, 0x1F, 0x00, 0x00, 0xC0, 0xFF,
+];
+
+/// Start address of the IPL ROM overlay
+pub const IPL_ROM_START: u16 = 0xFFC0;
+
+/// What an APU address is mapped to, see [`Memory::region`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region {
+    /// SPC700 I/O registers ($00F0–$00FF)
+    Io,
+    /// IPL ROM overlay ($FFC0–$FFFF while enabled), offset in the ROM
+    IplRom(usize),
+    Ram,
+}
+
 /// SPC700 memory map, covering the relevant I/O region `$00F0–$00FF`:
 ///
 /// ```text
@@ -17,26 +41,33 @@ pub type RawARAM = [u8; 64 * 1024];
 /// $FD–$FF    TnOUT     — timer 0/1/2 counter output (read-only, clears on read)
 /// ```
 ///
-/// The direct-mapped range `$F200–$F27F` used by test code is kept alongside
-/// the real port protocol so both can coexist during development.
+/// The DSP registers are only reachable through the `$F2`/`$F3` port pair,
+/// as on hardware: latch a register index in DSPADDR, then read or write it
+/// through DSPDATA. Indexes `$80–$FF` mirror `$00–$7F` for reads and ignore
+/// writes.
+///
+/// While CONTROL bit 7 is set, reads of `$FFC0–$FFFF` return the 64-byte
+/// IPL boot ROM instead of RAM. Writes always reach the RAM underneath. The
+/// bit is set at power-on, so the SPC700 boots through the IPL ROM.
 pub struct Memory {
     /// 64 KB APU RAM.  All addresses that are not intercepted as I/O
     /// read/write from/to this array.
     pub ram: Box<RawARAM>, // 64KB APU RAM,
 
     /// The DSP register file.  Accessed by the CPU exclusively through
-    /// the $F2/$F3 address-latch protocol (real hardware) or the direct
-    /// $F200–$F27F mapping used by test code.
+    /// the $F2/$F3 address-latch protocol.
     pub dsp: Dsp,
 
     /// $F2 — DSP address latch.
-    /// Holds the 7-bit register index for the next $F3 read or write.
+    /// Holds the register index for the next $F3 read or write; all 8 bits
+    /// are kept and read back, bit 7 makes $F3 read-only.
     dsp_addr: u8,
 
     /// $F1 — CONTROL register.
-    ///   bit 7: clear port 3 input latch ($F7)
-    ///   bit 6: clear port 2 input latch ($F6)
-    ///   bit 4: enable timer 2 (64 kHz)
+    ///   bit 7: map the IPL ROM over $FFC0–$FFFF
+    ///   bit 5: clear port 2/3 input latches ($F6/$F7)
+    ///   bit 4: clear port 0/1 input latches ($F4/$F5)
+    ///   bit 2: enable timer 2 (64 kHz)
     ///   bit 1: enable timer 1 (8 kHz)
     ///   bit 0: enable timer 0 (8 kHz)
     /// Publicly readable so Timers::step() can inspect the enable bits.
@@ -70,7 +101,7 @@ impl Memory {
             ram:       Box::new([0; _]),
             dsp:       Dsp::new(),
             dsp_addr:  0,
-            control:   0,
+            control:   0x80, // the IPL ROM is mapped at power-on
             port_in:   [0u8; 4],
             port_out:  [0u8; 4],
             timer_div: [0u8; 3],
@@ -78,10 +109,31 @@ impl Memory {
         }
     }
 
-    pub fn read8(&self, addr: u16) -> u8 {
+    /// Whether CONTROL bit 7 maps the IPL ROM over $FFC0–$FFFF
+    pub fn ipl_rom_enabled(&self) -> bool {
+        self.control & 0x80 != 0
+    }
+
+    fn region(&self, addr: u16) -> Region {
         match addr {
-            // ---- SPC700 I/O ports ($00F0–$00FF) ----
+            0x00F0..=0x00FF => Region::Io,
+            IPL_ROM_START..=0xFFFF if self.ipl_rom_enabled() => {
+                Region::IplRom((addr - IPL_ROM_START) as usize)
+            }
+            _ => Region::Ram,
+        }
+    }
+
+    pub fn read8(&self, addr: u16) -> u8 {
+        match self.region(addr) {
+            Region::Io => self.read_io(addr),
+            Region::IplRom(offset) => IPL_ROM[offset],
+            Region::Ram => self.ram[addr as usize],
+        }
+    }
 
+    fn read_io(&self, addr: u16) -> u8 {
+        match addr {
             // $F0 TEST — write-only; reads return 0xFF.
             0x00F0 => 0xFF,
 
@@ -92,7 +144,8 @@ impl Memory {
             0x00F2 => self.dsp_addr,
 
             // $F3 DSPDATA — read the DSP register selected by $F2.
-            0x00F3 => self.dsp.read_reg(self.dsp_addr),
+            // $80–$FF mirror $00–$7F.
+            0x00F3 => self.dsp.read_reg(self.dsp_addr & 0x7F),
 
             // $F4–$F7 CPUIO — SPC700 reads what the SNES CPU wrote.
             0x00F4 => self.port_in[0],
@@ -116,13 +169,7 @@ impl Memory {
             0x00FE => self.timer_out[1],
             0x00FF => self.timer_out[2],
 
-            // ---- Direct-mapped DSP register window ($F200–$F27F) ----
-            // Kept for test code that constructs Memory directly and writes
-            // DSP registers without going through the $F2/$F3 protocol.
-            0xF200..=0xF27F => self.dsp.read_reg((addr - 0xF200) as u8),
-
-            // ---- Normal RAM ----
-            _ => self.ram[addr as usize],
+            _ => unreachable!(),
         }
     }
 
@@ -145,27 +192,41 @@ impl Memory {
     }
 
     pub fn write8(&mut self, addr: u16, val: u8) {
+        match self.region(addr) {
+            Region::Io => self.write_io(addr, val),
+
+            // The IPL ROM only overlays reads, writes land in the RAM below
+            Region::IplRom(_) | Region::Ram => self.ram[addr as usize] = val,
+        }
+    }
+
+    fn write_io(&mut self, addr: u16, val: u8) {
         match addr {
             // $F0 TEST — only relevant during hardware boot; ignore safely.
             0x00F0 => {}
 
             // $F1 CONTROL
-            // bit 7: clear port 3 ($F7) input latch
-            // bit 6: clear port 2 ($F6) input latch
-            // bits 4/1/0: timer enables (forwarded to Timers via the register)
+            // bit 7: IPL ROM overlay (checked on every access via memory.control)
+            // bit 5: clear port 2/3 ($F6/$F7) input latches
+            // bit 4: clear port 0/1 ($F4/$F5) input latches
+            // bits 2/1/0: timer enables (forwarded to Timers via the register)
             0x00F1 => {
                 self.control = val;
-                if val & 0x80 != 0 { self.port_in[3] = 0; }
-                if val & 0x40 != 0 { self.port_in[2] = 0; }
+                if val & 0x10 != 0 { self.port_in[0] = 0; self.port_in[1] = 0; }
+                if val & 0x20 != 0 { self.port_in[2] = 0; self.port_in[3] = 0; }
                 // Timer enable bits are read by Timers::step() via memory.control.
             }
 
             // $F2 DSPADDR — latch the register index for the next $F3 access.
-            // Only 7 bits are meaningful; the high bit is masked by the DSP.
-            0x00F2 => self.dsp_addr = val & 0x7F,
+            0x00F2 => self.dsp_addr = val,
 
             // $F3 DSPDATA — write to the DSP register selected by $F2.
-            0x00F3 => self.dsp.write_reg(self.dsp_addr, val),
+            // The read-only mirror at $80–$FF ignores writes.
+            0x00F3 => {
+                if self.dsp_addr < 0x80 {
+                    self.dsp.write_reg(self.dsp_addr, val);
+                }
+            }
 
             // $F4–$F7 CPUIO — SPC700 writes; SNES CPU reads these.
             0x00F4 => self.port_out[0] = val,
@@ -184,12 +245,7 @@ impl Memory {
             // $FD–$FF — read-only timer counters; writes are ignored.
             0x00FD..=0x00FF => {}
 
-            // ---- Direct-mapped DSP register window ($F200–$F27F) ----
-            // Kept for test code; real SPC700 programs use $F2/$F3 above.
-            0xF200..=0xF27F => self.dsp.write_reg((addr - 0xF200) as u8, val),
-
-            // ---- Normal RAM ----
-            _ => self.ram[addr as usize] = val,
+            _ => unreachable!(),
         }
     }
 
@@ -211,4 +267,29 @@ impl Memory {
     pub fn cpu_port_read(&self, port: usize) -> u8 {
         if port < 4 { self.port_out[port] } else { 0 }
     }
+
+    /// Writes the RAM, the DSP and the I/O registers to a save state.
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.bytes(&self.ram[..]);
+        self.dsp.save_state(writer);
+        writer.u8(self.dsp_addr);
+        writer.u8(self.control);
+        for bytes in [&self.port_in[..], &self.port_out, &self.timer_div, &self.timer_out] {
+            writer.bytes(bytes);
+        }
+    }
+
+    /// Restores the memory written by [`Self::save_state`].
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        let ram = reader.bytes(self.ram.len())?;
+        self.ram.copy_from_slice(ram);
+        self.dsp.load_state(reader)?;
+        self.dsp_addr = reader.u8()?;
+        self.control = reader.u8()?;
+        self.port_in = reader.array()?;
+        self.port_out = reader.array()?;
+        self.timer_div = reader.array()?;
+        self.timer_out = reader.array()?;
+        Ok(())
+    }
 }
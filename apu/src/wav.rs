@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Size of the RIFF/WAVE header written before the sample data.
+pub const WAV_HEADER_LEN: usize = 44;
+
+const BITS_PER_SAMPLE: u16 = 16;
+
+/// Write stereo `(left, right)` pairs to `path` as a 16-bit PCM WAV file.
+///
+/// The pairs match the output of [`crate::Apu::render_audio`] and
+/// [`crate::dsp::Dsp::render_output`].
+pub fn write_wav<P: AsRef<Path>>(path: P, samples: &[(i16, i16)], sample_rate: u32) -> io::Result<()> {
+    let interleaved: Vec<i16> = samples.iter().flat_map(|&(l, r)| [l, r]).collect();
+    write_wav_file(path, 2, &interleaved, sample_rate)
+}
+
+/// Write mono samples to `path` as a 16-bit PCM WAV file.
+pub fn write_wav_mono<P: AsRef<Path>>(path: P, samples: &[i16], sample_rate: u32) -> io::Result<()> {
+    write_wav_file(path, 1, samples, sample_rate)
+}
+
+fn write_wav_file<P: AsRef<Path>>(path: P, channels: u16, interleaved: &[i16], sample_rate: u32) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_wav_data(&mut writer, channels, interleaved, sample_rate)?;
+    writer.flush()
+}
+
+/// Encode interleaved 16-bit samples as a WAV stream into `writer`.
+///
+/// `interleaved` holds `channels` samples per frame; a trailing partial
+/// frame is dropped so the data chunk always covers whole frames.
+pub fn write_wav_data<W: Write>(writer: &mut W, channels: u16, interleaved: &[i16], sample_rate: u32) -> io::Result<()> {
+    if channels == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "WAV needs at least one channel"));
+    }
+
+    let frames = interleaved.len() / channels as usize;
+    let samples = &interleaved[..frames * channels as usize];
+
+    let block_align = channels * (BITS_PER_SAMPLE / 8);
+    let byte_rate = sample_rate * block_align as u32;
+    let data_len = u32::try_from(samples.len() * 2)
+        .ok()
+        .filter(|len| *len <= u32::MAX - (WAV_HEADER_LEN as u32 - 8))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "too many samples for a WAV file"))?;
+
+    // RIFF chunk
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(data_len + WAV_HEADER_LEN as u32 - 8).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    // fmt chunk: PCM (format tag 1)
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&byte_rate.to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    // data chunk: signed 16-bit little-endian
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())?;
+    let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    writer.write_all(&bytes)
+}
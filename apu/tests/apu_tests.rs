@@ -5,20 +5,27 @@
 ///   - Apu::step(): CPU ticked every cycle, DSP ticked every 32 cycles,
 ///                  total cycle counter advances correctly
 ///   - DSP tick rate: exactly 1 DSP tick per 32 CPU cycles
+///   - Sample output: one queued sample per DSP tick, whatever the step
+///                    sizes, taken by take_samples or the render functions
 ///   - render_audio(): correct output length, advances cycles, produces
 ///                     stereo-interleaved samples, silent when no voices active
 ///   - Component wiring: DSP register writes via Memory reach the DSP,
 ///                       render_audio reflects DSP state
+///   - Output controls: host volume and mute applied after the DSP mix
+///   - Reproducibility: Apu::with_seed + render_frames_to_buffer give
+///                      identical output for identical seeds
+///   - Save states: a loaded APU renders the same audio as the saved one
 
 use apu::Apu;
-use apu::dsp::EnvelopePhase;
+use apu::dsp::{EnvelopePhase, KON_DELAY};
+use common::save_state::SaveState;
 
 // ============================================================
 // Helpers
 // ============================================================
 
 /// Write a NOP sled starting at `addr` so the CPU can execute
-/// `count` steps without hitting an unimplemented!() panic.
+/// `count` steps without halting on an unimplemented opcode.
 /// NOP = opcode 0x00 on the SPC700.
 fn write_nops(apu: &mut Apu, addr: u16, count: usize) {
     for i in 0..count {
@@ -26,8 +33,8 @@ fn write_nops(apu: &mut Apu, addr: u16, count: usize) {
     }
 }
 
-/// Point the reset vector at `addr` and fill that region with NOPs,
-/// then re-run reset so the CPU PC is set correctly.
+/// Unmap the IPL ROM, point the reset vector at `addr` and fill that region
+/// with NOPs, then re-run reset so the CPU PC is set correctly.
 ///
 /// We use $0100 as the default NOP sled start with a count of 0xEFF (3839)
 /// bytes, filling $0100–$0FFF. The audio data lives above this range:
@@ -37,6 +44,7 @@ fn write_nops(apu: &mut Apu, addr: u16, count: usize) {
 /// BRR block at $1000 contains the byte 0x10 (high address byte), which
 /// the CPU would interpret as opcode BPL if it fell inside the sled.
 fn setup_cpu(apu: &mut Apu, start_addr: u16, nop_count: usize) {
+    apu.memory.write8(0x00F1, 0x00);
     apu.memory.write8(0xFFFE, (start_addr & 0xFF) as u8);
     apu.memory.write8(0xFFFF, (start_addr >> 8)   as u8);
     write_nops(apu, start_addr, nop_count);
@@ -155,11 +163,11 @@ fn test_new_cpu_sp_initialised() {
 
 #[test]
 fn test_new_cpu_pc_loaded_from_reset_vector() {
-    // Default memory is zeroed so reset vector $FFFE/$FFFF = 0x0000,
-    // meaning PC should be 0x0000 on a fresh APU.
+    // The IPL ROM is mapped at power-on, so the reset vector at $FFFE/$FFFF
+    // is the one of the ROM, pointing at its start.
     let apu = Apu::new();
-    assert_eq!(apu.cpu.regs.pc, 0x0000,
-        "PC must be loaded from reset vector at $FFFE/$FFFF");
+    assert_eq!(apu.cpu.regs.pc, 0xFFC0,
+        "PC must be loaded from the IPL ROM reset vector at $FFFE/$FFFF");
 }
 
 #[test]
@@ -173,6 +181,42 @@ fn test_new_cpu_pc_reflects_reset_vector() {
         "PC must update when reset vector is changed and reset() re-called");
 }
 
+/// Run the APU until the SPC700 writes `value` to port `port`, as the SNES CPU
+/// polls the ports during the IPL transfer protocol.
+fn wait_for_port(apu: &mut Apu, port: usize, value: u8) {
+    for _ in 0..10_000 {
+        if apu.memory.cpu_port_read(port) == value {
+            return;
+        }
+        apu.step(10);
+    }
+    panic!("port {port} never became {value:#04X}");
+}
+
+#[test]
+fn test_ipl_rom_boots_and_uploads_a_program() {
+    let mut apu = Apu::new();
+    wait_for_port(&mut apu, 0, 0xAA);
+    wait_for_port(&mut apu, 1, 0xBB);
+
+    // MOV A, #$42 ; MOV $F7, A ; BRA -2
+    let program = [0xE8, 0x42, 0xC4, 0xF7, 0x2F, 0xFE];
+    let send = |apu: &mut Apu, ports: [u8; 4]| {
+        for (port, value) in ports.into_iter().enumerate().rev() {
+            apu.memory.cpu_port_write(port, value);
+        }
+        wait_for_port(apu, 0, ports[0]);
+    };
+    send(&mut apu, [0xCC, 0x01, 0x00, 0x03]); // transfer to $0300
+    for (i, &byte) in program.iter().enumerate() {
+        send(&mut apu, [i as u8, byte, 0x00, 0x03]);
+    }
+    send(&mut apu, [program.len() as u8 + 1, 0x00, 0x00, 0x03]); // jump to $0300
+
+    wait_for_port(&mut apu, 3, 0x42);
+    assert_eq!(apu.memory.ram[0x0300..0x0306], program);
+}
+
 #[test]
 fn test_new_dsp_voices_silent() {
     let apu = Apu::new();
@@ -230,8 +274,8 @@ fn test_step_multiple_cycles_advances_pc_multiple_times() {
 
     let pc_before = apu.cpu.regs.pc;
     apu.step(5);
-    // Each NOP advances PC by 1; 5 steps = 5 NOPs = PC + 5
-    assert_eq!(apu.cpu.regs.pc, pc_before.wrapping_add(5));
+    // Each NOP takes 2 cycles: 5 cycles = 2 NOPs + the opcode fetch of a third
+    assert_eq!(apu.cpu.regs.pc, pc_before.wrapping_add(3));
 }
 
 // ============================================================
@@ -260,7 +304,11 @@ fn test_dsp_ticked_exactly_at_32_cycles() {
     setup_cpu(&mut apu, 0x0100, 256);
     setup_voice_silent_sample(&mut apu);
 
-    // After exactly 32 cycles the DSP must have stepped once.
+    // The voice is silent during the key-on delay.
+    apu.step(32 * KON_DELAY as u32);
+    assert_eq!(apu.memory.dsp.voices[0].adsr.envelope_level, 0);
+
+    // After exactly 32 more cycles the DSP must have stepped once.
     // Observable: envelope_level should have advanced from 0
     // (attack_rate=15 → fast attack, +1024 per DSP tick).
     apu.step(32);
@@ -296,6 +344,7 @@ fn test_dsp_tick_count_proportional_to_cycles() {
     let mut apu = Apu::new();
     setup_cpu(&mut apu, 0x0100, 1024);
     setup_voice_silent_sample(&mut apu);
+    apu.step(32 * KON_DELAY as u32);
 
     // 3 DSP ticks = 96 CPU cycles; level should be min(3*1024, 0x7FF)
     apu.step(96);
@@ -307,6 +356,49 @@ fn test_dsp_tick_count_proportional_to_cycles() {
     );
 }
 
+// ============================================================
+// Apu::step() — sample output (1 sample per 32 CPU cycles)
+// ============================================================
+
+#[test]
+fn test_step_outputs_one_sample_per_32_cycles_across_calls() {
+    // The divider carries over between calls: 7-cycle slices
+    // add up to one sample every 32 cycles.
+    let mut apu = Apu::new();
+    setup_cpu(&mut apu, 0x0100, 0xEFF);
+
+    for _ in 0..32 {
+        apu.step(7);
+    }
+    assert_eq!(apu.take_samples().len(), 7, "224 cycles must output 7 samples");
+    assert!(apu.take_samples().is_empty(), "taken samples must not be returned again");
+}
+
+#[test]
+fn test_stepped_samples_match_render_audio() {
+    let mut stepped = Apu::new();
+    setup_cpu(&mut stepped, 0x0100, 0xEFF);
+    setup_voice_silent_sample(&mut stepped);
+    let mut rendered = Apu::new();
+    setup_cpu(&mut rendered, 0x0100, 0xEFF);
+    setup_voice_silent_sample(&mut rendered);
+
+    stepped.step(50);
+    stepped.step(110);
+    assert_eq!(stepped.take_samples(), rendered.render_audio(5));
+}
+
+#[test]
+fn test_render_audio_returns_queued_samples_first() {
+    let mut apu = Apu::new();
+    setup_cpu(&mut apu, 0x0100, 0xEFF);
+
+    apu.step(70); // 2 samples queued, 6 cycles into the third
+    let out = apu.render_audio(3);
+    assert_eq!(out.len(), 3);
+    assert_eq!(apu.cycles, 96, "only the missing sample must be stepped");
+}
+
 // ============================================================
 // Apu::render_audio()
 // ============================================================
@@ -433,11 +525,12 @@ fn test_dsp_register_write_via_f2_f3_reaches_dsp() {
 }
 
 #[test]
-fn test_dsp_register_write_via_direct_window_reaches_dsp() {
+fn test_dsp_not_mapped_at_f200() {
     let mut apu = Apu::new();
 
-    apu.memory.write8(0xF200 + 0x1C, 0x66); // MVOLR via direct window
-    assert_eq!(apu.memory.dsp.read_reg(0x1C), 0x66);
+    apu.memory.write8(0xF200 + 0x1C, 0x66); // plain RAM, not MVOLR
+    assert_eq!(apu.memory.dsp.read_reg(0x1C), 0);
+    assert_eq!(apu.memory.read8(0xF21C), 0x66);
 }
 
 #[test]
@@ -460,3 +553,158 @@ fn test_render_audio_reflects_master_volume() {
     assert!(loud_out.iter().any(|&(l, r)| l != 0 || r != 0),
         "non-zero master volume with active voice must produce output");
 }
+
+// ============================================================
+// Output controls (volume, mute)
+// ============================================================
+
+fn loud_apu() -> Apu {
+    let mut apu = Apu::new();
+    setup_cpu(&mut apu, 0x0100, 0xEFF);
+    setup_voice_nonzero_sample(&mut apu);
+    apu
+}
+
+#[test]
+fn test_output_controls_default() {
+    let apu = Apu::new();
+    assert_eq!(apu.volume(), 1.0);
+    assert!(!apu.is_muted());
+}
+
+#[test]
+fn test_volume_scales_output() {
+    let reference = loud_apu().render_audio(64);
+    let mut apu = loud_apu();
+    apu.set_volume(0.5);
+    let halved = apu.render_audio(64);
+
+    for (&(l, r), &(half_l, half_r)) in reference.iter().zip(&halved) {
+        assert!((half_l as i32 - (l as i32 >> 1)).abs() <= 1);
+        assert!((half_r as i32 - (r as i32 >> 1)).abs() <= 1);
+    }
+    assert!(halved.iter().any(|&(l, _)| l != 0));
+}
+
+#[test]
+fn test_mute_silences_output_but_keeps_running() {
+    let mut apu = loud_apu();
+    apu.set_muted(true);
+    let out = apu.render_audio(64);
+
+    assert!(out.iter().all(|&sample| sample == (0, 0)));
+    assert!(apu.memory.dsp.voices[0].adsr.envelope_level > 0,
+        "the DSP must keep running while muted");
+    assert_eq!(apu.memory.dsp.read_reg(0x0C), 127, "registers must be untouched");
+}
+
+// ============================================================
+// Reproducible output (Apu::with_seed, render_frames_to_buffer)
+// ============================================================
+
+/// FNV-1a over the little-endian bytes of `samples`.
+fn fnv1a(samples: &[i16]) -> u64 {
+    let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+    for byte in samples.iter().flat_map(|s| s.to_le_bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+    }
+    hash
+}
+
+/// Seeded APU playing noise on voice 0 at the fastest noise rate.
+fn noise_apu(seed: u16) -> Apu {
+    let mut apu = Apu::with_seed(seed);
+    setup_cpu(&mut apu, 0x0100, 0xEFF);
+    setup_voice_nonzero_sample(&mut apu);
+    apu.memory.write8(0x00F2, 0x6C);
+    apu.memory.write8(0x00F3, 0x1F); // FLG: noise rate 31
+    apu.memory.write8(0x00F2, 0x3D);
+    apu.memory.write8(0x00F3, 0x01); // NON: voice 0
+    apu
+}
+
+#[test]
+fn test_render_frames_to_buffer_matches_render_audio() {
+    let mut a = noise_apu(0x1234);
+    let mut b = noise_apu(0x1234);
+
+    let pairs = a.render_audio(32);
+    let mut buf = [0i16; 64];
+    assert_eq!(b.render_frames_to_buffer(&mut buf), 32);
+
+    let interleaved: Vec<i16> = pairs.iter().flat_map(|&(l, r)| [l, r]).collect();
+    assert_eq!(&buf[..], &interleaved[..]);
+}
+
+#[test]
+fn test_render_frames_to_buffer_ignores_trailing_odd_sample() {
+    let mut apu = noise_apu(0x1234);
+    let mut buf = [0x7777i16; 5];
+
+    assert_eq!(apu.render_frames_to_buffer(&mut buf), 2);
+    assert_eq!(buf[4], 0x7777, "odd trailing sample must be left untouched");
+    assert_eq!(apu.cycles, 2 * 32);
+}
+
+#[test]
+fn test_same_seed_renders_identical_audio() {
+    let mut a = noise_apu(0x0BAD);
+    let mut b = noise_apu(0x0BAD);
+    let mut buf_a = [0i16; 128];
+    let mut buf_b = [0i16; 128];
+
+    a.render_frames_to_buffer(&mut buf_a);
+    b.render_frames_to_buffer(&mut buf_b);
+
+    assert!(buf_a.iter().any(|&s| s != 0), "noise voice must be audible");
+    assert_eq!(buf_a, buf_b);
+}
+
+#[test]
+fn test_different_seeds_render_different_audio() {
+    let mut a = noise_apu(0x0001);
+    let mut b = noise_apu(0x2000);
+    let mut buf_a = [0i16; 128];
+    let mut buf_b = [0i16; 128];
+
+    a.render_frames_to_buffer(&mut buf_a);
+    b.render_frames_to_buffer(&mut buf_b);
+
+    assert_ne!(buf_a, buf_b);
+}
+
+#[test]
+fn test_seeded_noise_output_matches_golden_hash() {
+    // Pinned output of 64 frames with seed 0x1234. If a deliberate DSP
+    // change alters the output, update the constant from the failure message.
+    const GOLDEN: u64 = 0xA332_28D2_0CBA_D071;
+
+    let mut apu = noise_apu(0x1234);
+    let mut buf = [0i16; 128];
+    apu.render_frames_to_buffer(&mut buf);
+
+    let hash = fnv1a(&buf);
+    assert_eq!(hash, GOLDEN, "seeded noise output changed: new hash {hash:#018X}");
+}
+
+// ============================================================
+// Save states
+// ============================================================
+
+#[test]
+fn test_save_state_resumes_audio() {
+    let mut apu = noise_apu(0x1234);
+    apu.render_audio(40);
+    apu.step(7); // Mid-sample
+    let mut state = SaveState::new();
+    state.save(&apu);
+
+    // Another seed: the noise generator is part of the state
+    let mut loaded = noise_apu(0x0001);
+    state.load(&mut loaded).unwrap();
+    let expected = apu.render_audio(64);
+    assert!(expected.iter().any(|&sample| sample != (0, 0)));
+    assert_eq!(loaded.render_audio(64), expected);
+    assert_eq!(loaded.cycles, apu.cycles);
+}
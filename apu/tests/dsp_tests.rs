@@ -1,30 +1,43 @@
 /// DSP core tests
 ///
 /// Covers Dsp::new, read_reg/write_reg, global registers (KON/KOFF/DIR),
-/// step() BRR playback and looping, render_audio_single mixing/clamping,
-/// ENVX/OUTX/ENDX register updates, and master volume.
+/// KON/KOFF polling every other sample and the key-on delay, step() BRR playback and looping, render_audio_single mixing/clamping,
+/// the render_output output stage (raw and hardware-accurate modes),
+/// ENVX/OUTX/ENDX register updates, master volume, the seedable
+/// noise generator (NON/FLG), the mute/solo/gain/voice_info debug API,
+/// and the decoded DspView snapshot.
 ///
 /// ADSR phase tests → adsr_tests.rs
 /// Voice/register mapping tests → voice_tests.rs
 /// BRR decode tests → brr_tests.rs
 
-use apu::dsp::{Adsr, Brr, Dsp, EnvelopePhase, Voice};
+use apu::dsp::{
+    Adsr, Brr, Dsp, DspView, EnvelopePhase, Noise, OutputMode, Voice, KON_DELAY,
+    NOISE_DEFAULT_SEED,
+};
 use apu::Memory;
 
 // ============================================================
 // Helpers
 // ============================================================
 
-const DSP_BASE: u16 = 0xF200;
-
-/// Write a per-voice DSP register through the Memory bus.
+/// Write a per-voice DSP register through the $F2/$F3 port pair.
 fn dsp_vw(mem: &mut Memory, voice: u8, reg: u8, val: u8) {
-    mem.write8(DSP_BASE + ((voice as u16) << 4) + reg as u16, val);
+    dsp_gw(mem, (voice << 4) + reg, val);
 }
 
-/// Write a global DSP register through the Memory bus.
+/// Write a global DSP register through the $F2/$F3 port pair.
 fn dsp_gw(mem: &mut Memory, reg: u8, val: u8) {
-    mem.write8(DSP_BASE + reg as u16, val);
+    mem.write8(0x00F2, reg);
+    mem.write8(0x00F3, val);
+}
+
+/// Step a fresh DSP through the KON poll (its first tick) and the key-on
+/// delay: voices keyed on before the call play from the next tick.
+fn step_through_kon(mem: &mut Memory) {
+    for _ in 0..KON_DELAY {
+        mem.dsp.step(&mem.ram);
+    }
 }
 
 /// Read a DSP register by its 7-bit index directly.
@@ -60,9 +73,12 @@ fn write_dir_entry(mem: &mut Memory, dir_page: u8, srcn: u8, start: u16, loop_ad
 
 #[test]
 fn test_dsp_registers_zeroed_on_new() {
+    // All zero except FLG ($6C), which powers up with soft reset, mute and
+    // echo writes disabled so that the echo buffer doesn't overwrite RAM.
     let dsp = Dsp::new();
     for i in 0u8..=127 {
-        assert_eq!(dsp.read_reg(i), 0, "register 0x{:02X} not zero", i);
+        let expected = if i == 0x6C { 0xE0 } else { 0 };
+        assert_eq!(dsp.read_reg(i), expected, "register 0x{:02X} not at power-on value", i);
     }
 }
 
@@ -99,8 +115,8 @@ fn test_write_reg_index_masked_to_7_bits() {
 
 #[test]
 fn test_write_reg_unrecognised_global_registers_stored() {
-    // Unimplemented globals ($2C, $3C, $6C, $7D, $0D, $2D, $3D, $4D, $6D)
-    // must store the raw byte without panicking.
+    // Unimplemented or partially decoded globals ($2C, $3C, $6C, $7D, $0D,
+    // $2D, $3D, $4D, $6D) must store the raw byte without panicking.
     let mut mem = Memory::new();
     for &reg in &[0x2Cu8, 0x3C, 0x6C, 0x7D, 0x0D, 0x2D, 0x3D, 0x4D, 0x6D] {
         mem.dsp.write_reg(reg, 0xAB);
@@ -151,6 +167,7 @@ fn test_kon_register_keys_on_specified_voices() {
     }
 
     dsp_gw(&mut mem, 0x4C, 0b00000101); // KON: voices 0 and 2
+    mem.dsp.step(&mem.ram);             // KON poll
 
     assert!(mem.dsp.voices[0].key_on, "voice 0 should be keyed on");
     assert!(mem.dsp.voices[2].key_on, "voice 2 should be keyed on");
@@ -166,6 +183,7 @@ fn test_koff_register_enters_release_phase() {
     mem.dsp.voices[1].adsr.envelope_level = 0x400;
 
     dsp_gw(&mut mem, 0x5C, 0b00000010); // KOFF voice 1
+    mem.dsp.step(&mem.ram);             // KOFF poll
 
     assert_eq!(
         mem.dsp.voices[1].adsr.envelope_phase,
@@ -186,6 +204,7 @@ fn test_kon_resets_brr_state() {
     mem.dsp.voices[0].pitch_counter   = 0x0FFF;
 
     dsp_gw(&mut mem, 0x4C, 0x01);
+    mem.dsp.step(&mem.ram); // KON poll, then the voice waits out the key-on delay
 
     assert_eq!(mem.dsp.voices[0].brr.nibble_idx,  0, "nibble_idx must reset");
     assert_eq!(mem.dsp.voices[0].brr.prev1,       0, "prev1 must reset");
@@ -200,6 +219,7 @@ fn test_kon_resets_current_sample() {
     let mut mem = Memory::new();
     mem.dsp.voices[0].current_sample = 0x7FFF;
     dsp_gw(&mut mem, 0x4C, 0x01);
+    mem.dsp.step(&mem.ram);
     assert_eq!(mem.dsp.voices[0].current_sample, 0, "current_sample must reset on KON");
 }
 
@@ -224,6 +244,7 @@ fn test_kon_all_8_voices_simultaneously() {
     }
     dsp_gw(&mut mem, 0x5D, dir_page);
     dsp_gw(&mut mem, 0x4C, 0xFF);
+    mem.dsp.step(&mem.ram);
 
     for v in 0..8 {
         assert!(mem.dsp.voices[v].key_on,
@@ -255,11 +276,148 @@ fn test_koff_when_voice_already_off_does_not_panic() {
     assert_eq!(mem.dsp.voices[2].adsr.envelope_level, 0);
 }
 
+// ============================================================
+// KON/KOFF latency — polling and key-on delay
+// ============================================================
+
+/// Set up voice `v` on a silent looping BRR block, fast attack, pitch 0.5.
+fn setup_kon_voice(mem: &mut Memory, v: u8) {
+    let dir_page: u8  = 0x01;
+    let brr_addr: u16 = 0x0200;
+    write_silent_brr_block(mem, brr_addr, true, true);
+    write_dir_entry(mem, dir_page, v, brr_addr, brr_addr);
+    dsp_gw(mem, 0x5D, dir_page);
+    dsp_vw(mem, v, 0x2, 0x00); // P(L)
+    dsp_vw(mem, v, 0x3, 0x08); // P(H): 0x0800
+    dsp_vw(mem, v, 0x4, v);    // SRCN
+    dsp_vw(mem, v, 0x5, 0x8F); // ADSR1: attack rate 15
+    dsp_vw(mem, v, 0x6, 0xE0); // ADSR2
+}
+
+#[test]
+fn test_kon_latched_until_poll() {
+    let mut mem = Memory::new();
+    setup_kon_voice(&mut mem, 0);
+    mem.dsp.step(&mem.ram); // poll tick, nothing pending
+
+    dsp_gw(&mut mem, 0x4C, 0x01);
+    assert!(!mem.dsp.voices[0].key_on, "KON must wait for the poll");
+    mem.dsp.step(&mem.ram);
+    assert!(!mem.dsp.voices[0].key_on, "no poll on odd ticks");
+    mem.dsp.step(&mem.ram);
+    assert!(mem.dsp.voices[0].key_on, "polled every other sample");
+}
+
+#[test]
+fn test_kon_second_write_before_poll_replaces_first() {
+    let mut mem = Memory::new();
+    setup_kon_voice(&mut mem, 0);
+    setup_kon_voice(&mut mem, 1);
+
+    dsp_gw(&mut mem, 0x4C, 0x01);
+    dsp_gw(&mut mem, 0x4C, 0x02);
+    mem.dsp.step(&mem.ram);
+
+    assert!(!mem.dsp.voices[0].key_on, "first KON write dropped");
+    assert!(mem.dsp.voices[1].key_on);
+}
+
+#[test]
+fn test_kon_cleared_once_polled() {
+    let mut mem = Memory::new();
+    setup_kon_voice(&mut mem, 0);
+    dsp_gw(&mut mem, 0x4C, 0x01);
+    step_through_kon(&mut mem);
+
+    // Later polls must not key the voice on again
+    mem.dsp.step(&mem.ram);
+    mem.dsp.step(&mem.ram);
+    mem.dsp.step(&mem.ram);
+    assert_eq!(mem.dsp.voices[0].adsr.envelope_level, 0x7FF);
+}
+
+#[test]
+fn test_kon_delay_holds_envelope_and_pitch() {
+    let mut mem = Memory::new();
+    setup_kon_voice(&mut mem, 0);
+    dsp_gw(&mut mem, 0x4C, 0x01);
+
+    // Silent for KON_DELAY ticks, the poll tick included
+    for tick in 0..KON_DELAY {
+        mem.dsp.step(&mem.ram);
+        assert_eq!(mem.dsp.voices[0].adsr.envelope_level, 0, "tick {tick}");
+        assert_eq!(mem.dsp.voices[0].pitch_counter,       0, "tick {tick}");
+    }
+
+    mem.dsp.step(&mem.ram);
+    assert!(mem.dsp.voices[0].adsr.envelope_level > 0, "attack starts after the delay");
+    assert_eq!(mem.dsp.voices[0].pitch_counter, 0x0800);
+}
+
+#[test]
+fn test_kon_restarts_playing_voice() {
+    let mut mem = Memory::new();
+    setup_kon_voice(&mut mem, 0);
+    dsp_gw(&mut mem, 0x4C, 0x01);
+    step_through_kon(&mut mem);
+    for _ in 0..21 {
+        mem.dsp.step(&mem.ram);
+    }
+    assert_eq!(mem.dsp.voices[0].adsr.envelope_level, 0x7FF);
+
+    // Key on again while playing: cut to silence and restart the sample
+    // (KON_DELAY + 21 ticks so far, even: the next tick polls)
+    dsp_gw(&mut mem, 0x4C, 0x01);
+    mem.dsp.step(&mem.ram);
+    let voice = &mem.dsp.voices[0];
+    assert_eq!(voice.adsr.envelope_phase, EnvelopePhase::Attack);
+    assert_eq!(voice.adsr.envelope_level, 0);
+    assert_eq!(voice.brr.nibble_idx,      0);
+    assert_eq!(voice.kon_delay,           KON_DELAY - 1);
+}
+
+#[test]
+fn test_koff_held_releases_voice_keyed_on_later() {
+    let mut mem = Memory::new();
+    setup_kon_voice(&mut mem, 0);
+    dsp_gw(&mut mem, 0x5C, 0x01);
+    dsp_gw(&mut mem, 0x4C, 0x01);
+
+    // KON wins over KOFF within the same poll
+    mem.dsp.step(&mem.ram);
+    assert!(mem.dsp.voices[0].key_on);
+    assert_eq!(mem.dsp.voices[0].adsr.envelope_phase, EnvelopePhase::Attack);
+
+    // KOFF is still set at the next poll
+    mem.dsp.step(&mem.ram);
+    mem.dsp.step(&mem.ram);
+    assert!(!mem.dsp.voices[0].key_on);
+    assert_eq!(mem.dsp.voices[0].adsr.envelope_phase, EnvelopePhase::Release);
+}
+
+#[test]
+fn test_koff_cleared_lets_voice_key_on() {
+    let mut mem = Memory::new();
+    setup_kon_voice(&mut mem, 0);
+    dsp_gw(&mut mem, 0x5C, 0x01);
+    mem.dsp.step(&mem.ram);
+    mem.dsp.step(&mem.ram);
+
+    dsp_gw(&mut mem, 0x5C, 0x00);
+    dsp_gw(&mut mem, 0x4C, 0x01);
+    step_through_kon(&mut mem);
+    mem.dsp.step(&mem.ram);
+    mem.dsp.step(&mem.ram);
+    assert!(mem.dsp.voices[0].key_on);
+    assert!(mem.dsp.voices[0].adsr.envelope_level > 0);
+}
+
 // ============================================================
 // Dsp::step — BRR playback and pitch advance
 // ============================================================
 
-/// Set up voice 0 with a silent, non-looping, end-flagged BRR block and key it on.
+/// Set up voice 0 with a silent, non-looping, end-flagged BRR block and key it on,
+/// stepping through the key-on delay so that it plays from the next tick.
 fn setup_single_voice_end_block(mem: &mut Memory) {
     let dir_page: u8 = 0x01;
     let brr_addr: u16 = 0x0200;
@@ -279,6 +437,7 @@ fn setup_single_voice_end_block(mem: &mut Memory) {
     dsp_vw(mem, 0, 0x6, 0xE0);
 
     dsp_gw(mem, 0x4C, 0x01); // KON voice 0
+    step_through_kon(mem);
 }
 
 #[test]
@@ -577,11 +736,113 @@ fn test_render_zero_envelope_silences_voice() {
     assert_eq!(l, 0, "zero envelope must produce zero output regardless of master vol");
 }
 
+// ============================================================
+// Dsp::render_output — output stage (OutputMode)
+// ============================================================
+
+/// Three full-scale voices: two positive, one negative, all panned left.
+fn dsp_with_overflowing_mix(mode: OutputMode) -> Dsp {
+    let mut dsp = Dsp::new();
+    dsp.set_output_mode(mode);
+    dsp.write_reg(0x0C, 127u8); // MVOLL
+    for (v, vol) in [127i8, 127, -128].into_iter().enumerate() {
+        dsp.voices[v].adsr.envelope_phase = EnvelopePhase::Sustain;
+        dsp.voices[v].adsr.envelope_level = 0x7FF;
+        dsp.voices[v].current_sample      = 0x7FFF;
+        dsp.voices[v].left_vol            = vol;
+    }
+    dsp
+}
+
+/// Drive the output stage with a constant mix of `sample` on the left.
+fn dsp_with_constant_mix(sample: i16) -> Dsp {
+    let mut dsp = Dsp::new();
+    dsp.set_output_mode(OutputMode::Hardware);
+    dsp.write_reg(0x0C, 127u8); // MVOLL
+    dsp.voices[0].adsr.envelope_phase = EnvelopePhase::Sustain;
+    dsp.voices[0].adsr.envelope_level = 0x7FF;
+    dsp.voices[0].current_sample      = sample;
+    dsp.voices[0].left_vol            = 127;
+    dsp
+}
+
+#[test]
+fn test_output_mode_default_raw() {
+    let mut dsp = Dsp::new();
+    assert_eq!(dsp.output_mode(), OutputMode::Raw);
+
+    dsp.write_reg(0x0C, 127u8);
+    dsp.voices[0].adsr.envelope_phase = EnvelopePhase::Sustain;
+    dsp.voices[0].adsr.envelope_level = 0x7FF;
+    dsp.voices[0].current_sample      = 1000;
+    dsp.voices[0].left_vol            = 64;
+    let mix = dsp.render_audio_single();
+    assert_eq!(dsp.render_output(), mix, "raw output is the mix");
+    assert_eq!(dsp.render_output(), mix, "raw output has no filter state");
+}
+
+#[test]
+fn test_hardware_mix_saturates_after_each_voice() {
+    // Raw: 2 × ~32500 − ~32750 fits again once summed.
+    let (raw, _) = dsp_with_overflowing_mix(OutputMode::Raw).render_audio_single();
+    assert!(raw > 30_000, "raw mix keeps the overflow (got {raw})");
+
+    // Hardware: the second voice saturates at 0x7FFF, the third cancels it.
+    let (hw, _) = dsp_with_overflowing_mix(OutputMode::Hardware).render_audio_single();
+    assert!(hw.abs() < 100, "hardware mix saturates per voice (got {hw})");
+}
+
+#[test]
+fn test_hardware_output_halved() {
+    let mut dsp = dsp_with_constant_mix(0x4000);
+    let (mix, _) = dsp.render_audio_single();
+
+    // The first sample is a step from silence: the high-pass lets it through.
+    let (l, r) = dsp.render_output();
+    assert_eq!(l, mix >> 1);
+    assert_eq!(r, 0);
+}
+
+#[test]
+fn test_hardware_output_removes_dc() {
+    let mut dsp = dsp_with_constant_mix(0x4000);
+    let (first, _) = dsp.render_output();
+
+    // 20 Hz pole at 32 kHz: about 1/e every 256 samples.
+    let after: Vec<i16> = (0..4096).map(|_| dsp.render_output().0).collect();
+    assert!(after[255] < first / 2, "DC decays (got {} from {first})", after[255]);
+    assert!(after.windows(2).all(|w| w[1] <= w[0]), "decay is monotonic");
+    assert!(after[4095].abs() <= 1, "DC removed (got {})", after[4095]);
+}
+
+#[test]
+fn test_hardware_output_negative_dc_removed() {
+    let mut dsp = dsp_with_constant_mix(-0x4000);
+    let (first, _) = dsp.render_output();
+    assert!(first < 0);
+
+    let last = (0..4096).map(|_| dsp.render_output().0).last().unwrap();
+    assert!(last.abs() <= 1, "DC removed (got {last})");
+}
+
+#[test]
+fn test_set_output_mode_resets_filters() {
+    let mut dsp = dsp_with_constant_mix(0x4000);
+    let (first, _) = dsp.render_output();
+    for _ in 0..1000 {
+        dsp.render_output();
+    }
+
+    dsp.set_output_mode(OutputMode::Hardware);
+    assert_eq!(dsp.render_output().0, first, "filters restart from silence");
+}
+
 // ============================================================
 // ENVX, OUTX, ENDX register update tests
 //
 // ENVX ($X8): reads back (envelope_level >> 4) as u8 — 7-bit range 0x00–0x7F.
-// OUTX ($X9): reads back (current_sample  >> 8) as u8 — signed top byte.
+// OUTX ($X9): reads back (post-envelope sample >> 8) as u8 — signed top byte.
+// Both are refreshed on every DSP tick, including released and Off voices.
 // ENDX ($7C): bit N set when voice N's BRR end-flag fires; cleared on KON.
 // ============================================================
 
@@ -716,10 +977,28 @@ fn test_outx_reflects_top_byte_of_current_sample() {
 
     // After step the BRR buffer will have been consumed and current_sample
     // updated from decoded data. We test the register reflects *that* value.
-    let sample   = mem.dsp.voices[0].current_sample;
-    let expected = (sample >> 8) as u8;
+    let voice    = &mem.dsp.voices[0];
+    let scaled   = (voice.current_sample as i32 * voice.adsr.envelope_level as i32) >> 11;
+    let expected = (scaled >> 8) as u8;
     let actual   = mem.dsp.read_reg(0x09); // voice 0, offset +9
-    assert_eq!(actual, expected, "OUTX must equal current_sample >> 8");
+    assert_eq!(actual, expected, "OUTX must equal the post-envelope sample >> 8");
+}
+
+#[test]
+fn test_outx_scaled_by_envelope() {
+    // Same sample at half envelope → roughly half the OUTX magnitude.
+    let mut mem = Memory::new();
+    setup_single_voice_end_block(&mut mem);
+    mem.dsp.voices[0].adsr.envelope_phase = EnvelopePhase::Sustain;
+    mem.dsp.voices[0].adsr.envelope_level = 0x400;
+    mem.dsp.voices[0].adsr.sustain_rate   = 0;
+
+    mem.dsp.voices[0].brr.sample_buffer = [0x4000i16; 16];
+    mem.dsp.voices[0].brr.buffer_fill   = 16;
+    mem.dsp.voices[0].brr.nibble_idx    = 0;
+
+    mem.dsp.step(&mem.ram);
+    assert_eq!(mem.dsp.read_reg(0x09), 0x20, "0x4000 * 0x400 >> 11 = 0x2000 → OUTX 0x20");
 }
 
 #[test]
@@ -751,6 +1030,92 @@ fn test_outx_positive_and_negative_samples() {
     assert!(outx_neg < 0, "negative sample → negative OUTX top byte");
 }
 
+// --- Read-back while released / off ---
+
+#[test]
+fn test_envx_tracks_release_after_koff() {
+    // After KOFF the voice is no longer keyed on, but ENVX must keep
+    // following the decaying release envelope.
+    let mut mem = Memory::new();
+    setup_single_voice_end_block(&mut mem);
+    mem.dsp.step(&mem.ram);
+
+    mem.dsp.voices[0].adsr.envelope_level = 0x400;
+    dsp_gw(&mut mem, 0x5C, 0x01); // KOFF voice 0
+
+    let mut previous = mem.dsp.read_reg(0x08);
+    for _ in 0..16 {
+        mem.dsp.step(&mem.ram);
+        let level = mem.dsp.voices[0].adsr.envelope_level;
+        let envx  = mem.dsp.read_reg(0x08);
+        assert_eq!(envx, (level >> 4) as u8, "ENVX stale during release");
+        assert!(envx <= previous, "ENVX must not rise during release");
+        previous = envx;
+    }
+    assert!(previous < 0x40, "ENVX must have decayed below its KOFF value");
+}
+
+#[test]
+fn test_envx_and_outx_cleared_once_voice_off() {
+    let mut mem = Memory::new();
+    setup_single_voice_end_block(&mut mem);
+    mem.dsp.voices[0].adsr.envelope_phase = EnvelopePhase::Sustain;
+    mem.dsp.voices[0].adsr.envelope_level = 0x7FF;
+    mem.dsp.voices[0].adsr.sustain_rate   = 0;
+    mem.dsp.voices[0].brr.sample_buffer   = [0x4000i16; 16];
+    mem.dsp.voices[0].brr.buffer_fill     = 16;
+    mem.dsp.voices[0].brr.nibble_idx      = 0;
+    mem.dsp.step(&mem.ram);
+    assert_ne!(mem.dsp.read_reg(0x08), 0);
+    assert_ne!(mem.dsp.read_reg(0x09), 0);
+
+    dsp_gw(&mut mem, 0x5C, 0x01); // KOFF voice 0
+    // Release subtracts 8 per tick: 0x7FF needs 256 ticks to reach 0.
+    for _ in 0..300 {
+        mem.dsp.step(&mem.ram);
+    }
+
+    assert_eq!(mem.dsp.voices[0].adsr.envelope_phase, EnvelopePhase::Off);
+    assert_eq!(mem.dsp.read_reg(0x08), 0, "ENVX must read 0 once the voice is Off");
+    assert_eq!(mem.dsp.read_reg(0x09), 0, "OUTX must read 0 once the voice is Off");
+}
+
+#[test]
+fn test_envx_outx_readable_through_f2_f3() {
+    // SPC700 programs poll ENVX/OUTX by latching the index at $F2 and
+    // reading $F3.
+    let mut mem = Memory::new();
+    setup_single_voice_end_block(&mut mem);
+    mem.dsp.voices[0].adsr.envelope_phase = EnvelopePhase::Sustain;
+    mem.dsp.voices[0].adsr.envelope_level = 0x400;
+    mem.dsp.voices[0].adsr.sustain_rate   = 0;
+    mem.dsp.voices[0].brr.sample_buffer   = [0x4000i16; 16];
+    mem.dsp.voices[0].brr.buffer_fill     = 16;
+    mem.dsp.voices[0].brr.nibble_idx      = 0;
+    mem.dsp.step(&mem.ram);
+
+    mem.write8(0x00F2, 0x08);
+    assert_eq!(mem.read8(0x00F3), 0x40, "ENVX via $F3");
+    mem.write8(0x00F2, 0x09);
+    assert_eq!(mem.read8(0x00F3), 0x20, "OUTX via $F3");
+}
+
+#[test]
+fn test_cpu_write_to_envx_overwritten_next_tick() {
+    // ENVX/OUTX are read-only from the CPU's point of view: any value
+    // written is replaced by the voice state on the next tick.
+    let mut mem = Memory::new();
+    setup_single_voice_end_block(&mut mem);
+    mem.dsp.voices[0].adsr.envelope_phase = EnvelopePhase::Sustain;
+    mem.dsp.voices[0].adsr.envelope_level = 0x400;
+    mem.dsp.voices[0].adsr.sustain_rate   = 0;
+
+    dsp_vw(&mut mem, 0, 0x8, 0x7F);
+    mem.dsp.step(&mem.ram);
+
+    assert_eq!(dsp_r(&mem, 0x08), 0x40);
+}
+
 // --- ENDX ---
 
 #[test]
@@ -823,8 +1188,11 @@ fn test_endx_cleared_on_kon() {
     }
     assert_eq!(mem.dsp.read_reg(0x7C) & 0x01, 1, "precondition: ENDX bit 0 must be set");
 
-    // Key on voice 0 again — this should clear bit 0.
+    // Key on voice 0 again — this should clear bit 0 at the next KON poll,
+    // within two ticks.
     dsp_gw(&mut mem, 0x4C, 0x01);
+    mem.dsp.step(&mem.ram);
+    mem.dsp.step(&mem.ram);
     assert_eq!(
         mem.dsp.read_reg(0x7C) & 0x01, 0,
         "KON must clear ENDX bit for the keyed-on voice"
@@ -1031,3 +1399,385 @@ fn test_master_vol_written_via_memory_bus_affects_mix() {
     assert!(l > 0, "MVOLL written via bus must produce non-zero left output");
     assert!(r > 0, "MVOLR written via bus must produce non-zero right output");
 }
+
+// ============================================================
+// Noise generator ($3D NON / $6C FLG) tests
+//
+// 15-bit LFSR: new bit 14 = bit 0 XOR bit 1, clocked at the FLG rate
+// (bits 4-0, same period table as ADSR). Output = (lfsr << 1) as i16.
+// ============================================================
+
+#[test]
+fn test_noise_default_seed_on_new() {
+    let dsp = Dsp::new();
+    assert_eq!(dsp.noise.lfsr(), NOISE_DEFAULT_SEED);
+}
+
+#[test]
+fn test_noise_zero_seed_falls_back_to_default() {
+    // An all-zero LFSR never changes, so it must not be accepted.
+    assert_eq!(Noise::new(0).lfsr(), NOISE_DEFAULT_SEED);
+    assert_eq!(Noise::new(0x8000).lfsr(), NOISE_DEFAULT_SEED, "only 15 bits are used");
+}
+
+#[test]
+fn test_noise_rate_zero_never_clocks() {
+    let mut noise = Noise::new(0x1234);
+    for _ in 0..5000 {
+        noise.step(0);
+    }
+    assert_eq!(noise.lfsr(), 0x1234);
+}
+
+#[test]
+fn test_noise_rate_31_clocks_every_tick() {
+    let mut noise = Noise::new(0x0003);
+    noise.step(31);
+    // bit0 ^ bit1 = 0 → 0x0003 >> 1 = 0x0001
+    assert_eq!(noise.lfsr(), 0x0001);
+    noise.step(31);
+    // bit0 ^ bit1 = 1 → (0x0001 >> 1) | 0x4000
+    assert_eq!(noise.lfsr(), 0x4000);
+}
+
+#[test]
+fn test_noise_output_is_lfsr_shifted_left() {
+    let noise = Noise::new(0x4000);
+    assert_eq!(noise.output(), i16::MIN, "0x4000 << 1 = 0x8000");
+    assert_eq!(Noise::new(0x0001).output(), 2);
+}
+
+#[test]
+fn test_noise_sequence_has_maximal_period() {
+    // The LFSR must visit all 32767 non-zero states before repeating.
+    let mut noise = Noise::new(NOISE_DEFAULT_SEED);
+    let mut period = 0u32;
+    loop {
+        noise.step(31);
+        period += 1;
+        if noise.lfsr() == NOISE_DEFAULT_SEED {
+            break;
+        }
+        assert!(period < 40_000, "LFSR never returned to its seed");
+    }
+    assert_eq!(period, 0x7FFF);
+}
+
+#[test]
+fn test_noise_same_seed_same_sequence() {
+    let mut a = Dsp::with_noise_seed(0x2ABC);
+    let mut b = Dsp::with_noise_seed(0x2ABC);
+    let ram = [0u8; 0x10000];
+    a.write_reg(0x6C, 0x1F);
+    b.write_reg(0x6C, 0x1F);
+
+    for _ in 0..256 {
+        a.step(&ram);
+        b.step(&ram);
+        assert_eq!(a.noise.output(), b.noise.output());
+    }
+}
+
+#[test]
+fn test_noise_different_seeds_diverge() {
+    let mut a = Dsp::with_noise_seed(0x0001);
+    let mut b = Dsp::with_noise_seed(0x0002);
+    let ram = [0u8; 0x10000];
+    a.write_reg(0x6C, 0x1F);
+    b.write_reg(0x6C, 0x1F);
+
+    let mut differ = false;
+    for _ in 0..16 {
+        a.step(&ram);
+        b.step(&ram);
+        differ |= a.noise.output() != b.noise.output();
+    }
+    assert!(differ, "different seeds must give different noise");
+}
+
+#[test]
+fn test_seed_noise_restarts_generator() {
+    let mut dsp = Dsp::new();
+    let ram = [0u8; 0x10000];
+    dsp.write_reg(0x6C, 0x1F);
+    for _ in 0..10 {
+        dsp.step(&ram);
+    }
+    dsp.seed_noise(0x1357);
+    assert_eq!(dsp.noise.lfsr(), 0x1357);
+}
+
+#[test]
+fn test_non_voice_plays_noise_instead_of_brr() {
+    let mut mem = Memory::new();
+    setup_single_voice_end_block(&mut mem);
+    dsp_gw(&mut mem, 0x6C, 0x1F); // FLG: noise rate 31
+    dsp_gw(&mut mem, 0x3D, 0x01); // NON: voice 0
+
+    mem.dsp.step(&mem.ram);
+
+    assert_eq!(mem.dsp.voices[0].current_sample, mem.dsp.noise.output(),
+        "NON voice must output the noise sample");
+}
+
+#[test]
+fn test_non_clear_voice_keeps_brr_output() {
+    let mut mem = Memory::new();
+    setup_single_voice_end_block(&mut mem);
+    dsp_gw(&mut mem, 0x6C, 0x1F);
+    dsp_gw(&mut mem, 0x3D, 0x02); // NON: voice 1 only
+
+    mem.dsp.step(&mem.ram);
+
+    // Silent BRR block → voice 0 stays at 0 despite noise running.
+    assert_eq!(mem.dsp.voices[0].current_sample, 0);
+}
+
+#[test]
+fn test_non_voice_off_stays_silent() {
+    // Noise is only substituted while the envelope is active.
+    let mut mem = Memory::new();
+    dsp_gw(&mut mem, 0x6C, 0x1F);
+    dsp_gw(&mut mem, 0x3D, 0x01);
+
+    mem.dsp.step(&mem.ram);
+
+    assert_eq!(mem.dsp.voices[0].current_sample, 0);
+}
+
+// ============================================================
+// Debug API: mute/solo and voice_info
+// ============================================================
+
+/// DSP with voices 0 and 1 sustaining a constant sample, full volume.
+fn two_voice_dsp() -> Dsp {
+    let mut dsp = Dsp::new();
+    dsp.write_reg(0x0C, 127);
+    dsp.write_reg(0x1C, 127);
+    for v in 0..2 {
+        dsp.voices[v].adsr.envelope_phase = EnvelopePhase::Sustain;
+        dsp.voices[v].adsr.envelope_level = 0x7FF;
+        dsp.voices[v].left_vol            = 64;
+        dsp.voices[v].right_vol           = 64;
+    }
+    dsp.voices[0].current_sample = 1000;
+    dsp.voices[1].current_sample = 3000;
+    dsp
+}
+
+#[test]
+fn test_all_voices_audible_by_default() {
+    let dsp = Dsp::new();
+    for v in 0..8 {
+        assert!(dsp.is_voice_audible(v));
+        assert!(!dsp.is_voice_muted(v));
+        assert!(!dsp.is_voice_soloed(v));
+    }
+}
+
+#[test]
+fn test_muted_voice_excluded_from_mix() {
+    let mut dsp = two_voice_dsp();
+    let both = dsp.render_audio_single();
+
+    dsp.set_voice_muted(1, true);
+    let only_0 = dsp.render_audio_single();
+
+    dsp.set_voice_muted(1, false);
+    dsp.set_voice_muted(0, true);
+    let only_1 = dsp.render_audio_single();
+
+    assert!(only_0.0 > 0 && only_0.0 < only_1.0);
+    assert!(only_1.0 < both.0);
+}
+
+#[test]
+fn test_voice_gain_defaults_to_unity() {
+    let dsp = Dsp::new();
+    for v in 0..8 {
+        assert_eq!(dsp.voice_gain(v), 1.0);
+    }
+}
+
+#[test]
+fn test_voice_gain_scales_voice_in_mix() {
+    let mut dsp = two_voice_dsp();
+    dsp.set_voice_muted(1, true);
+    let full = dsp.render_audio_single();
+
+    dsp.set_voice_gain(0, 0.5);
+    let half = dsp.render_audio_single();
+    assert!((half.0 - full.0 / 2).abs() <= 1, "half gain must halve the voice");
+
+    dsp.set_voice_gain(0, 0.0);
+    assert_eq!(dsp.render_audio_single(), (0, 0));
+}
+
+#[test]
+fn test_voice_gain_clamped() {
+    let mut dsp = Dsp::new();
+    dsp.set_voice_gain(0, 100.0);
+    dsp.set_voice_gain(1, -1.0);
+    assert_eq!(dsp.voice_gain(0), 4.0);
+    assert_eq!(dsp.voice_gain(1), 0.0);
+}
+
+#[test]
+fn test_solo_mixes_only_soloed_voices() {
+    let mut dsp = two_voice_dsp();
+    dsp.set_voice_muted(0, true);
+    let only_1 = dsp.render_audio_single();
+    dsp.clear_mute_solo();
+
+    dsp.set_voice_solo(1, true);
+    assert!(!dsp.is_voice_audible(0));
+    assert!(dsp.is_voice_audible(1));
+    assert_eq!(dsp.render_audio_single(), only_1);
+}
+
+#[test]
+fn test_mute_wins_over_solo() {
+    let mut dsp = two_voice_dsp();
+    dsp.set_voice_solo(0, true);
+    dsp.set_voice_muted(0, true);
+    assert!(!dsp.is_voice_audible(0));
+    assert_eq!(dsp.render_audio_single(), (0, 0));
+}
+
+#[test]
+fn test_clear_mute_solo_restores_mix() {
+    let mut dsp = two_voice_dsp();
+    let both = dsp.render_audio_single();
+
+    dsp.set_voice_muted(0, true);
+    dsp.set_voice_solo(1, true);
+    dsp.clear_mute_solo();
+
+    assert_eq!(dsp.render_audio_single(), both);
+}
+
+#[test]
+fn test_mute_does_not_affect_envx_outx() {
+    // Muting is a mixer-only debug feature; the game still sees the voice.
+    let mut mem = Memory::new();
+    setup_single_voice_end_block(&mut mem);
+    mem.dsp.voices[0].adsr.envelope_phase = EnvelopePhase::Sustain;
+    mem.dsp.voices[0].adsr.envelope_level = 0x400;
+    mem.dsp.voices[0].adsr.sustain_rate   = 0;
+    mem.dsp.set_voice_muted(0, true);
+
+    mem.dsp.step(&mem.ram);
+
+    assert_eq!(mem.dsp.read_reg(0x08), 0x40);
+}
+
+#[test]
+fn test_voice_info_reports_voice_state() {
+    let mut mem = Memory::new();
+    setup_single_voice_end_block(&mut mem);
+    dsp_gw(&mut mem, 0x3D, 0x01); // NON voice 0
+    dsp_vw(&mut mem, 0, 0x4, 0x05); // SRCN 5
+    mem.dsp.voices[0].adsr.envelope_phase = EnvelopePhase::Sustain;
+    mem.dsp.voices[0].adsr.envelope_level = 0x400;
+    mem.dsp.voices[0].current_sample      = 0x4000;
+    mem.dsp.set_voice_solo(0, true);
+
+    let info = mem.dsp.voice_info(0);
+    assert_eq!(info.envelope_phase, EnvelopePhase::Sustain);
+    assert_eq!(info.envelope_level, 0x400);
+    assert_eq!(info.pitch, 0x1000);
+    assert_eq!(info.srcn, 5);
+    assert!(info.key_on);
+    assert!(info.noise);
+    assert_eq!(info.output, 0x2000);
+    assert!(!info.muted);
+    assert!(info.soloed);
+    assert!(info.audible);
+}
+
+#[test]
+fn test_voice_infos_indexed_by_voice() {
+    let mut dsp = Dsp::new();
+    for v in 0..8u8 {
+        dsp.write_reg((v << 4) | 0x4, v + 10);
+    }
+    dsp.set_voice_solo(3, true);
+
+    let infos = dsp.voice_infos();
+    for (v, info) in infos.iter().enumerate() {
+        assert_eq!(info.srcn, v as u8 + 10);
+        assert_eq!(info.audible, v == 3, "voice {v}");
+    }
+}
+
+// ============================================================
+// Debug API: DspView
+// ============================================================
+
+#[test]
+fn test_view_decodes_voice_registers() {
+    let mut dsp = Dsp::new();
+    dsp.write_reg(0x30, 0x40);        // voice 3 VOL(L) = +64
+    dsp.write_reg(0x31, 0xC0);        // voice 3 VOL(R) = -64
+    dsp.write_reg(0x32, 0x00);
+    dsp.write_reg(0x33, 0x08);        // pitch 0x0800 = 16 kHz
+    dsp.write_reg(0x34, 0x05);        // SRCN
+    dsp.write_reg(0x35, 0b1010_0111); // ADSR, decay 2, attack 7
+    dsp.write_reg(0x36, 0b1100_0011); // sustain level 6, rate 3
+    dsp.write_reg(0x3D, 0b0000_1000); // NON voice 3
+    dsp.write_reg(0x4D, 0b0000_1001); // EON voices 0 and 3
+
+    let voice = dsp.view().voices[3];
+    assert_eq!(voice.left_vol, 64);
+    assert_eq!(voice.right_vol, -64);
+    assert_eq!(voice.pitch, 0x0800);
+    assert_eq!(voice.sample_rate(), 16_000);
+    assert_eq!(voice.srcn, 5);
+    assert!(voice.adsr_mode);
+    assert_eq!((voice.attack_rate, voice.decay_rate), (7, 2));
+    assert_eq!((voice.sustain_level, voice.sustain_rate), (6, 3));
+    assert!(voice.noise && voice.echo);
+    assert!(!voice.pitch_mod && !voice.ended);
+    assert!(!dsp.view().voices[2].echo);
+}
+
+#[test]
+fn test_view_decodes_global_registers() {
+    let mut dsp = Dsp::new();
+    dsp.write_reg(0x0C, 0x7F);
+    dsp.write_reg(0x1C, 0x80);
+    dsp.write_reg(0x5D, 0x12);
+    dsp.write_reg(0x6C, 0b0110_0101); // mute, echo writes off, noise rate 5
+    dsp.write_reg(0x2C, 0x20);
+    dsp.write_reg(0x0D, 0xF0);
+    dsp.write_reg(0x6D, 0xC0);
+    dsp.write_reg(0x7D, 0x03);
+    dsp.write_reg(0x1F, 0xFF);        // C1 = -1
+
+    let view = dsp.view();
+    assert_eq!((view.master_vol_left, view.master_vol_right), (127, -128));
+    assert_eq!(view.dir_addr, 0x1200);
+    assert!(view.mute && !view.soft_reset);
+    assert_eq!(view.noise_rate, 5);
+    assert!(!view.echo.write_enabled);
+    assert_eq!(view.echo.left_vol, 0x20);
+    assert_eq!(view.echo.feedback, -16);
+    assert_eq!(view.echo.start_addr, 0xC000);
+    assert_eq!(view.echo.buffer_len(), 3 * 0x800);
+    assert_eq!(view.echo.fir, [0, -1, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(view.registers[0x5D], 0x12);
+}
+
+#[test]
+fn test_view_update_tracks_voice_state() {
+    let mut dsp = two_voice_dsp();
+    let mut view = DspView::default();
+    view.update(&dsp);
+    assert_eq!(view.voices[0].envelope_phase, EnvelopePhase::Sustain);
+    assert_eq!(view.voices[0].envelope_level, 0x7FF);
+
+    dsp.voices[0].adsr.envelope_phase = EnvelopePhase::Release;
+    view.update(&dsp);
+    assert_eq!(view.voices[0].envelope_phase, EnvelopePhase::Release);
+    assert_eq!(view, dsp.view());
+}
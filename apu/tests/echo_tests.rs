@@ -0,0 +1,267 @@
+/// Echo unit tests
+///
+/// Covers:
+///   - FLG bit 5 (ECEN): echo buffer writes disabled at power-on and when set
+///   - echo buffer writes overwriting APU RAM at ESA, over EDL * 2 KB
+///   - EDL latched when the buffer offset wraps, 64 KB address wrap
+///   - EON voices written to the buffer, EFB feedback
+///   - FIR filtering and EVOL scaling of the echo output into the mix
+///   - Apu::step driving the echo unit
+
+use apu::dsp::{EnvelopePhase, FLG_ECEN};
+use apu::{Apu, Memory};
+
+// ============================================================
+// Helpers
+// ============================================================
+
+/// Write a DSP register through the $F2/$F3 port pair.
+fn dsp_w(mem: &mut Memory, reg: u8, val: u8) {
+    mem.write8(0x00F2, reg);
+    mem.write8(0x00F3, val);
+}
+
+/// Memory with echo writes enabled, the buffer at page `esa`, `edl` steps long.
+fn echo_mem(esa: u8, edl: u8) -> Memory {
+    let mut mem = Memory::new();
+    dsp_w(&mut mem, 0x6D, esa); // ESA
+    dsp_w(&mut mem, 0x7D, edl); // EDL
+    dsp_w(&mut mem, 0x6C, 0x00); // FLG: echo writes enabled
+    mem
+}
+
+/// Step the echo unit `samples` times.
+fn step_echo(mem: &mut Memory, samples: usize) {
+    for _ in 0..samples {
+        mem.dsp.step_echo(&mut mem.ram);
+    }
+}
+
+fn read16(mem: &Memory, addr: u16) -> i16 {
+    i16::from_le_bytes([mem.ram[addr as usize], mem.ram[addr as usize + 1]])
+}
+
+fn write16(mem: &mut Memory, addr: u16, value: i16) {
+    let [lo, hi] = value.to_le_bytes();
+    mem.ram[addr as usize]     = lo;
+    mem.ram[addr as usize + 1] = hi;
+}
+
+// ============================================================
+// ECEN — echo write enable
+// ============================================================
+
+#[test]
+fn test_echo_writes_disabled_at_power_on() {
+    let mut mem = Memory::new();
+    assert_eq!(mem.dsp.read_reg(0x6C) & FLG_ECEN, FLG_ECEN);
+    mem.ram[0x0000..0x0004].fill(0xAA);
+
+    step_echo(&mut mem, 16);
+    assert_eq!(mem.ram[0x0000..0x0004], [0xAA; 4], "ESA=0, EDL=0 buffer must be left alone");
+}
+
+#[test]
+fn test_echo_writes_disabled_by_flg_bit_5() {
+    let mut mem = echo_mem(0x20, 1);
+    dsp_w(&mut mem, 0x6C, FLG_ECEN);
+    mem.ram[0x2000..0x2800].fill(0x55);
+
+    step_echo(&mut mem, 0x200);
+    assert!(mem.ram[0x2000..0x2800].iter().all(|&b| b == 0x55));
+}
+
+#[test]
+fn test_echo_buffer_overwrites_ram() {
+    // Driver code placed in the echo buffer gets stomped by silence.
+    let mut mem = echo_mem(0x20, 1);
+    mem.ram[0x1FFF..0x2801].fill(0x55);
+
+    step_echo(&mut mem, 1);
+    assert_eq!(mem.ram[0x2000..0x2004], [0; 4]);
+    assert_eq!(mem.ram[0x2004], 0x55, "one sample pair per step");
+
+    step_echo(&mut mem, 0x1FF);
+    assert!(mem.ram[0x2000..0x2800].iter().all(|&b| b == 0), "EDL=1 is 2 KB");
+    assert_eq!(mem.ram[0x1FFF], 0x55, "below ESA untouched");
+    assert_eq!(mem.ram[0x2800], 0x55, "past EDL untouched");
+}
+
+#[test]
+fn test_echo_buffer_re_enabled_mid_pass() {
+    let mut mem = echo_mem(0x20, 1);
+    dsp_w(&mut mem, 0x6C, FLG_ECEN);
+    mem.ram[0x2000..0x2800].fill(0x55);
+
+    // The offset keeps moving while writes are disabled.
+    step_echo(&mut mem, 4);
+    dsp_w(&mut mem, 0x6C, 0x00);
+    step_echo(&mut mem, 1);
+    assert_eq!(mem.ram[0x2000..0x2010], [0x55; 16]);
+    assert_eq!(mem.ram[0x2010..0x2014], [0; 4]);
+}
+
+// ============================================================
+// Buffer length and addressing
+// ============================================================
+
+#[test]
+fn test_echo_edl_zero_is_one_sample_pair() {
+    let mut mem = echo_mem(0x20, 0);
+    mem.ram[0x2000..0x2008].fill(0x55);
+
+    step_echo(&mut mem, 8);
+    assert_eq!(mem.dsp.echo.offset(), 0);
+    assert_eq!(mem.ram[0x2000..0x2004], [0; 4]);
+    assert_eq!(mem.ram[0x2004..0x2008], [0x55; 4]);
+}
+
+#[test]
+fn test_echo_edl_latched_at_wrap() {
+    let mut mem = echo_mem(0x20, 1);
+    step_echo(&mut mem, 1);
+
+    // A longer delay only applies once the current pass is over.
+    dsp_w(&mut mem, 0x7D, 2);
+    step_echo(&mut mem, 0x1FF);
+    assert_eq!(mem.dsp.echo.offset(), 0, "wrapped after 2 KB");
+
+    step_echo(&mut mem, 0x200);
+    assert_eq!(mem.dsp.echo.offset(), 0x800, "new pass is 4 KB");
+}
+
+#[test]
+fn test_echo_buffer_wraps_at_64k() {
+    let mut mem = echo_mem(0xFF, 1);
+    mem.ram[0x0000..0x0004].fill(0x55);
+
+    step_echo(&mut mem, 0x40);
+    assert_eq!(mem.ram[0x0000..0x0004], [0x55; 4]);
+    step_echo(&mut mem, 1);
+    assert_eq!(mem.ram[0x0000..0x0004], [0; 4], "$FF00 + $100 wraps to $0000");
+}
+
+// ============================================================
+// Echo input and feedback
+// ============================================================
+
+#[test]
+fn test_echo_input_from_eon_voices() {
+    let mut mem = echo_mem(0x20, 1);
+    for v in 0..2 {
+        let voice = &mut mem.dsp.voices[v];
+        voice.adsr.envelope_phase = EnvelopePhase::Sustain;
+        voice.adsr.envelope_level = 0x7FF;
+        voice.current_sample      = 0x1000;
+        voice.left_vol            = 64;
+        voice.right_vol           = -64;
+    }
+    dsp_w(&mut mem, 0x4D, 0x01); // EON: voice 0 only
+
+    step_echo(&mut mem, 1);
+    let out = mem.dsp.voices[0].output() as i32;
+    let expected_l = ((out * 64) >> 7) as i16 & !1;
+    let expected_r = ((out * -64) >> 7) as i16 & !1;
+    assert_eq!(read16(&mem, 0x2000), expected_l);
+    assert_eq!(read16(&mem, 0x2002), expected_r);
+}
+
+#[test]
+fn test_echo_input_ignores_host_mute() {
+    let mut mem = echo_mem(0x20, 1);
+    let voice = &mut mem.dsp.voices[0];
+    voice.adsr.envelope_phase = EnvelopePhase::Sustain;
+    voice.adsr.envelope_level = 0x7FF;
+    voice.current_sample      = 0x1000;
+    voice.left_vol            = 127;
+    dsp_w(&mut mem, 0x4D, 0x01);
+    mem.dsp.set_voice_muted(0, true);
+
+    step_echo(&mut mem, 1);
+    assert_ne!(read16(&mem, 0x2000), 0, "APU RAM must not depend on host controls");
+}
+
+#[test]
+fn test_echo_feedback() {
+    // EDL=0: the same sample pair is read back and rewritten every step.
+    let mut mem = echo_mem(0x20, 0);
+    write16(&mut mem, 0x2000, 0x4000);
+    dsp_w(&mut mem, 0x7F, 0x40); // C7 = 64: unity gain on the newest sample
+    dsp_w(&mut mem, 0x0D, 0x40); // EFB = 64: half feedback
+
+    step_echo(&mut mem, 1);
+    // Read back as 0x2000 (15 bits), FIR × 64 >> 6 = 0x2000, × EFB >> 7
+    assert_eq!(read16(&mem, 0x2000), 0x1000);
+    assert_eq!(read16(&mem, 0x2002), 0);
+}
+
+// ============================================================
+// FIR and echo output
+// ============================================================
+
+#[test]
+fn test_echo_output_through_fir_and_evol() {
+    let mut mem = echo_mem(0x20, 0);
+    dsp_w(&mut mem, 0x6C, FLG_ECEN); // keep the sample in place
+    write16(&mut mem, 0x2000, 0x4000);
+    dsp_w(&mut mem, 0x7F, 0x7F); // C7
+    dsp_w(&mut mem, 0x2C, 0x7F); // EVOLL
+
+    step_echo(&mut mem, 1);
+    let fir = (0x2000 * 127) >> 6 & !1;
+    let expected = (fir * 127) >> 7;
+    assert_eq!(mem.dsp.echo.output(), (expected as i16, 0));
+
+    // Added to the mix even with the master volume at 0.
+    assert_eq!(mem.dsp.render_audio_single(), (expected as i16, 0));
+}
+
+#[test]
+fn test_echo_fir_taps_oldest_first() {
+    let mut mem = echo_mem(0x20, 0);
+    dsp_w(&mut mem, 0x6C, FLG_ECEN);
+    dsp_w(&mut mem, 0x0F, 0x40); // C0 = 64: unity gain on the oldest sample
+    dsp_w(&mut mem, 0x2C, 0x7F);
+    write16(&mut mem, 0x2000, 0x4000);
+
+    // The sample reaches the last tap 7 samples after it is read.
+    step_echo(&mut mem, 1);
+    write16(&mut mem, 0x2000, 0);
+    step_echo(&mut mem, 6);
+    assert_eq!(mem.dsp.echo.output().0, 0);
+    write16(&mut mem, 0x2000, 0);
+    step_echo(&mut mem, 1);
+    assert_eq!(mem.dsp.echo.output().0, ((0x2000_i32 * 127) >> 7) as i16);
+}
+
+#[test]
+fn test_echo_fir_saturates() {
+    let mut mem = echo_mem(0x20, 0);
+    dsp_w(&mut mem, 0x6C, FLG_ECEN);
+    write16(&mut mem, 0x2000, i16::MAX);
+    dsp_w(&mut mem, 0x7F, 0x7F);
+    dsp_w(&mut mem, 0x6F, 0x7F);
+    dsp_w(&mut mem, 0x2C, 0x7F);
+
+    step_echo(&mut mem, 2);
+    // Two taps of ~0x7F00 saturate to 0x7FFE before EVOL.
+    assert_eq!(mem.dsp.echo.output().0, ((0x7FFE * 127) >> 7) as i16);
+}
+
+// ============================================================
+// Apu::step
+// ============================================================
+
+#[test]
+fn test_apu_step_writes_echo_buffer() {
+    let mut apu = Apu::new();
+    dsp_w(&mut apu.memory, 0x6D, 0x80);
+    dsp_w(&mut apu.memory, 0x7D, 1);
+    dsp_w(&mut apu.memory, 0x6C, 0x00);
+    apu.memory.ram[0x8000..0x8800].fill(0x55);
+
+    // One DSP tick every 32 SPC700 cycles
+    apu.step(32 * 4);
+    assert_eq!(apu.memory.ram[0x8000..0x8010], [0; 16]);
+    assert_eq!(apu.memory.ram[0x8010], 0x55);
+}
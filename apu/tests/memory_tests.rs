@@ -5,17 +5,20 @@
 ///   - Normal RAM ($0000–$00EF, $0100–$EFFF): read/write/independence
 ///   - $F0 TEST:          write ignored, read returns 0
 ///   - $F1 CONTROL:       write stored, port-clear bits work
-///   - $F2 DSPADDR:       latch stores 7-bit index
-///   - $F3 DSPDATA:       routes through latch to DSP read_reg/write_reg
+///   - $FFC0–$FFFF:       IPL ROM overlay while CONTROL bit 7 is set
+///   - $F2 DSPADDR:       latch stores the 8-bit index
+///   - $F3 DSPDATA:       routes through latch to DSP read_reg/write_reg,
+///                        $80–$FF is a read-only mirror
 ///   - $F4–$F7 CPUIO:     SPC700 write → port_out; SNES write → port_in
 ///   - $F8–$F9 AUXRAM:    normal RAM behaviour
 ///   - $FA–$FC TIMERDIV:  write stored in timer_div, read returns 0xFF
 ///   - $FD–$FF TIMEROUT:  read returns counter, read8_mut clears it
-///   - $F200–$F27F:       direct DSP window (test-code path)
+///   - $F200–$F27F:       plain RAM, the DSP is only behind $F2/$F3
 ///   - read16/write16:    little-endian, correct wrapping at $FFFF
 ///   - cpu_port_write/read: SNES↔APU communication helpers
 
 use apu::Memory;
+use apu::memory::{IPL_ROM, IPL_ROM_START};
 
 // ============================================================
 // Helpers
@@ -115,19 +118,23 @@ fn test_f1_read_returns_zero() {
 }
 
 #[test]
-fn test_f1_bit7_clears_port3_in() {
+fn test_f1_bit4_clears_ports_0_and_1_in() {
     let mut mem = Memory::new();
-    mem.port_in[3] = 0xAB;
-    mem.write8(0x00F1, 0x80); // bit 7 set → clear port 3 input latch
-    assert_eq!(mem.port_in[3], 0, "port_in[3] must be cleared by CONTROL bit 7");
+    mem.port_in[0] = 0xAB;
+    mem.port_in[1] = 0xCD;
+    mem.write8(0x00F1, 0x10); // bit 4 set → clear port 0/1 input latches
+    assert_eq!(mem.port_in[0], 0, "port_in[0] must be cleared by CONTROL bit 4");
+    assert_eq!(mem.port_in[1], 0, "port_in[1] must be cleared by CONTROL bit 4");
 }
 
 #[test]
-fn test_f1_bit6_clears_port2_in() {
+fn test_f1_bit5_clears_ports_2_and_3_in() {
     let mut mem = Memory::new();
-    mem.port_in[2] = 0xCD;
-    mem.write8(0x00F1, 0x40); // bit 6 set → clear port 2 input latch
-    assert_eq!(mem.port_in[2], 0, "port_in[2] must be cleared by CONTROL bit 6");
+    mem.port_in[2] = 0xAB;
+    mem.port_in[3] = 0xCD;
+    mem.write8(0x00F1, 0x20); // bit 5 set → clear port 2/3 input latches
+    assert_eq!(mem.port_in[2], 0, "port_in[2] must be cleared by CONTROL bit 5");
+    assert_eq!(mem.port_in[3], 0, "port_in[3] must be cleared by CONTROL bit 5");
 }
 
 #[test]
@@ -137,13 +144,77 @@ fn test_f1_port_clear_bits_do_not_affect_other_ports() {
     mem.port_in[1] = 0x22;
     mem.port_in[2] = 0x33;
     mem.port_in[3] = 0x44;
-    mem.write8(0x00F1, 0xC0); // clear ports 2 and 3 only
+    mem.write8(0x00F1, 0x20); // clear ports 2 and 3 only
     assert_eq!(mem.port_in[0], 0x11, "port 0 must be unaffected");
     assert_eq!(mem.port_in[1], 0x22, "port 1 must be unaffected");
     assert_eq!(mem.port_in[2], 0,    "port 2 must be cleared");
     assert_eq!(mem.port_in[3], 0,    "port 3 must be cleared");
 }
 
+#[test]
+fn test_f1_bit7_does_not_clear_ports() {
+    let mut mem = Memory::new();
+    mem.port_in = [0x11, 0x22, 0x33, 0x44];
+    mem.write8(0x00F1, 0x80); // IPL ROM enable only
+    assert_eq!(mem.port_in, [0x11, 0x22, 0x33, 0x44]);
+}
+
+// ============================================================
+// $FFC0–$FFFF — IPL ROM overlay
+// ============================================================
+
+#[test]
+fn test_ipl_rom_enabled_at_power_on() {
+    let mem = Memory::new();
+    assert!(mem.ipl_rom_enabled());
+    assert_eq!(mem.read16(0xFFFE), IPL_ROM_START);
+}
+
+#[test]
+fn test_ipl_rom_disabled_reads_ram() {
+    let mut mem = Memory::new();
+    mem.write8(0x00F1, 0x00);
+    mem.write8(0xFFC0, 0x42);
+    assert!(!mem.ipl_rom_enabled());
+    assert_eq!(mem.read8(0xFFC0), 0x42);
+}
+
+#[test]
+fn test_ipl_rom_enabled_reads_rom() {
+    let mut mem = Memory::new();
+    mem.write8(0x00F1, 0x80);
+    assert!(mem.ipl_rom_enabled());
+    for (i, &byte) in IPL_ROM.iter().enumerate() {
+        assert_eq!(mem.read8(IPL_ROM_START + i as u16), byte, "IPL ROM byte {i}");
+    }
+}
+
+#[test]
+fn test_ipl_rom_reset_vector_points_to_rom_start() {
+    let mut mem = Memory::new();
+    mem.write8(0x00F1, 0x80);
+    assert_eq!(mem.read16(0xFFFE), IPL_ROM_START);
+}
+
+#[test]
+fn test_ipl_rom_writes_reach_ram_below() {
+    let mut mem = Memory::new();
+    mem.write8(0x00F1, 0x80);
+    mem.write8(0xFFC0, 0x42);
+    assert_eq!(mem.read8(0xFFC0), IPL_ROM[0], "ROM must still be visible");
+
+    mem.write8(0x00F1, 0x00);
+    assert_eq!(mem.read8(0xFFC0), 0x42, "write must land in RAM under the ROM");
+}
+
+#[test]
+fn test_ipl_rom_does_not_cover_lower_addresses() {
+    let mut mem = Memory::new();
+    mem.write8(0x00F1, 0x80);
+    mem.write8(0xFFBF, 0x42);
+    assert_eq!(mem.read8(0xFFBF), 0x42);
+}
+
 // ============================================================
 // $F2 — DSPADDR latch
 // ============================================================
@@ -160,16 +231,24 @@ fn test_f2_stores_dsp_address_latch() {
 }
 
 #[test]
-fn test_f2_masks_to_7_bits() {
-    // 0xFF masked to 7 bits = 0x7F (EDL register).
-    // Write a value, then latch 0x7F explicitly and confirm we read the same thing —
-    // proving 0xFF and 0x7F select identical registers.
+fn test_f2_high_bit_reads_mirror() {
+    // Indexes $80–$FF read the register of the low 7 bits (0xFF → EDL at 0x7F).
     let mut mem = Memory::new();
-    mem.write8(0x00F2, 0xFF); // should behave identically to 0x7F
+    mem.write8(0x00F2, 0x7F);
     mem.write8(0x00F3, 0xAB);
-    // Now latch 0x7F directly and read back — must return the same value
+    mem.write8(0x00F2, 0xFF);
+    assert_eq!(mem.read8(0x00F3), 0xAB, "0xFF must read the same DSP register as 0x7F");
+    assert_eq!(mem.read8(0x00F2), 0xFF, "the latch must keep its high bit");
+}
+
+#[test]
+fn test_f2_high_bit_makes_f3_read_only() {
+    let mut mem = Memory::new();
     mem.write8(0x00F2, 0x7F);
-    assert_eq!(mem.read8(0x00F3), 0xAB, "0xFF and 0x7F must select the same DSP register");
+    mem.write8(0x00F3, 0xAB);
+    mem.write8(0x00F2, 0xFF);
+    mem.write8(0x00F3, 0x12); // ignored by the read-only mirror
+    assert_eq!(mem.dsp.read_reg(0x7F), 0xAB, "writes through $80–$FF must be ignored");
 }
 
 #[test]
@@ -457,38 +536,16 @@ fn test_timer_out_all_three_independent() {
 }
 
 // ============================================================
-// $F200–$F27F — Direct DSP register window (test-code path)
+// $F200–$F27F — plain RAM
 // ============================================================
 
 #[test]
-fn test_direct_dsp_window_write_read() {
-    let mut mem = Memory::new();
-    mem.write8(0xF200, 0x7F); // voice 0 VOL L via direct window
-    assert_eq!(mem.read8(0xF200), 0x7F);
-}
-
-#[test]
-fn test_direct_dsp_window_reaches_dsp() {
+fn test_f200_range_is_ram() {
     let mut mem = Memory::new();
-    mem.write8(0xF200 + 0x5D, 0x08); // DIR register via direct window
-    assert_eq!(mem.dsp.read_reg(0x5D), 0x08);
-}
-
-#[test]
-fn test_direct_dsp_window_and_f2f3_protocol_share_same_dsp() {
-    // A write via the direct window must be visible through $F3,
-    // and vice versa — they're the same underlying DSP register file.
-    let mut mem = Memory::new();
-
-    // Write via direct window, read via $F2/$F3 protocol
-    mem.write8(0xF200 + 0x0C, 0x55); // MVOLL via direct window
-    mem.write8(0x00F2, 0x0C);
-    assert_eq!(mem.read8(0x00F3), 0x55, "direct-window write must be visible via $F3");
-
-    // Write via $F2/$F3 protocol, read via direct window
-    mem.write8(0x00F2, 0x1C);
-    mem.write8(0x00F3, 0x66); // MVOLR via protocol
-    assert_eq!(mem.read8(0xF200 + 0x1C), 0x66, "$F3 write must be visible via direct window");
+    mem.write8(0xF200 + 0x5D, 0x08); // not the DIR register
+    assert_eq!(mem.read8(0xF200 + 0x5D), 0x08);
+    assert_eq!(mem.ram[0xF25D], 0x08);
+    assert_eq!(mem.dsp.read_reg(0x5D), 0, "$F200–$F27F must not reach the DSP");
 }
 
 // ============================================================
@@ -515,6 +572,7 @@ fn test_write16_little_endian() {
 fn test_read16_wraps_at_0xffff() {
     // A 16-bit read at $FFFF must read $FFFF (lo) and $0000 (hi) without panic.
     let mut mem = Memory::new();
+    mem.write8(0x00F1, 0x00); // unmap the IPL ROM
     mem.write8(0xFFFF, 0x11);
     mem.write8(0x0000, 0x22);
     let val = mem.read16(0xFFFF);
@@ -524,6 +582,7 @@ fn test_read16_wraps_at_0xffff() {
 #[test]
 fn test_write16_wraps_at_0xffff() {
     let mut mem = Memory::new();
+    mem.write8(0x00F1, 0x00); // unmap the IPL ROM
     mem.write16(0xFFFF, 0x5566);
     assert_eq!(mem.read8(0xFFFF), 0x66, "low byte at $FFFF");
     assert_eq!(mem.read8(0x0000), 0x55, "high byte wraps to $0000");
@@ -2,20 +2,22 @@
 ///
 /// Covers every implemented instruction, all flag outcomes, both
 /// dp_base() states (FLAG_P set/clear), cycle counts, PC advancement,
-/// reset(), set_flag/get_flag, and the step() dispatch table.
+/// reset(), set_flag/get_flag, the step() dispatch table and the
+/// per-cycle accesses of cycle().
 
-use apu::cpu::{Spc700, FLAG_C, FLAG_N, FLAG_V, FLAG_Z, FLAG_P, FLAG_H, FLAG_I, FLAG_B};
+use apu::cpu::{CycleResult, Spc700, UnhandledOpcode, FLAG_C, FLAG_N, FLAG_V, FLAG_Z, FLAG_P, FLAG_H, FLAG_I, FLAG_B};
 use apu::Memory;
 
 // ============================================================
 // Helpers
 // ============================================================
 
-/// Build a CPU + Memory with the reset vector pointing at $0200
-/// and a clean slate ready for instruction tests.
+/// Build a CPU + Memory with the IPL ROM unmapped, the reset vector pointing
+/// at $0200 and a clean slate ready for instruction tests.
 fn make_cpu_mem() -> (Spc700, Memory) {
     let mut cpu = Spc700::new();
     let mut mem = Memory::new();
+    mem.write8(0x00F1, 0x00);
     // Point reset vector at $0200
     mem.write8(0xFFFE, 0x00);
     mem.write8(0xFFFF, 0x02);
@@ -43,6 +45,7 @@ fn emit_seq(mem: &mut Memory, pc: u16, bytes: &[u8]) {
 fn test_reset_loads_pc_from_vector() {
     let mut cpu = Spc700::new();
     let mut mem = Memory::new();
+    mem.write8(0x00F1, 0x00); // unmap the IPL ROM
     mem.write8(0xFFFE, 0x34);
     mem.write8(0xFFFF, 0x12);
     cpu.reset(&mut mem);
@@ -66,13 +69,22 @@ fn test_reset_clears_psw() {
 
 #[test]
 fn test_reset_zero_vector_sets_pc_zero() {
-    // Default memory is zeroed, so vector = $0000
+    // Default memory is zeroed, so once the IPL ROM is unmapped vector = $0000
     let mut cpu = Spc700::new();
     let mut mem = Memory::new();
+    mem.write8(0x00F1, 0x00);
     cpu.reset(&mut mem);
     assert_eq!(cpu.regs.pc, 0x0000);
 }
 
+#[test]
+fn test_reset_at_power_on_boots_the_ipl_rom() {
+    let mut cpu = Spc700::new();
+    let mut mem = Memory::new();
+    cpu.reset(&mut mem);
+    assert_eq!(cpu.regs.pc, 0xFFC0);
+}
+
 // ============================================================
 // set_flag / get_flag
 // ============================================================
@@ -892,6 +904,279 @@ fn test_dp_base_set_uses_page_one() {
     assert_eq!(cpu.regs.a, 0xBB);
 }
 
+#[test]
+fn test_register_moves_set_flags() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    cpu.regs.a = 0x00;
+    cpu.regs.y = 0x80;
+    emit_seq(&mut mem, cpu.regs.pc, &[0x5D, 0xDD]); // MOV X, A; MOV A, Y
+    cpu.step(&mut mem);
+    assert!(cpu.get_flag(FLAG_Z) && !cpu.get_flag(FLAG_N));
+    cpu.step(&mut mem);
+    assert!(!cpu.get_flag(FLAG_Z) && cpu.get_flag(FLAG_N));
+}
+
+// ============================================================
+// MOV SP, X / MOV X, SP
+// ============================================================
+
+#[test]
+fn test_mov_sp_x_leaves_flags() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    cpu.regs.x = 0x00;
+    emit(&mut mem, cpu.regs.pc, 0xBD); // MOV SP, X
+    cpu.step(&mut mem);
+    assert_eq!(cpu.regs.sp, 0x00);
+    assert!(!cpu.get_flag(FLAG_Z), "MOV SP, X must not set flags");
+    assert_eq!(cpu.cycles, 2);
+}
+
+#[test]
+fn test_mov_x_sp_sets_flags() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    emit(&mut mem, cpu.regs.pc, 0x9D); // MOV X, SP
+    cpu.step(&mut mem);
+    assert_eq!(cpu.regs.x, 0xFF);
+    assert!(cpu.get_flag(FLAG_N));
+}
+
+// ============================================================
+// INC / DEC registers
+// ============================================================
+
+#[test]
+fn test_inc_dec_registers_wrap_and_set_flags() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    cpu.regs.a = 0xFF;
+    cpu.regs.x = 0x01;
+    cpu.regs.y = 0x7F;
+    emit_seq(&mut mem, cpu.regs.pc, &[0xBC, 0x1D, 0xFC]); // INC A; DEC X; INC Y
+
+    cpu.step(&mut mem);
+    assert_eq!(cpu.regs.a, 0x00);
+    assert!(cpu.get_flag(FLAG_Z));
+    cpu.step(&mut mem);
+    assert_eq!(cpu.regs.x, 0x00);
+    assert!(cpu.get_flag(FLAG_Z));
+    cpu.step(&mut mem);
+    assert_eq!(cpu.regs.y, 0x80);
+    assert!(cpu.get_flag(FLAG_N) && !cpu.get_flag(FLAG_Z));
+    assert_eq!(cpu.cycles, 6);
+}
+
+#[test]
+fn test_dec_a_inc_x_dec_y() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    emit_seq(&mut mem, cpu.regs.pc, &[0x9C, 0x3D, 0xDC]); // DEC A; INC X; DEC Y
+    cpu.step(&mut mem);
+    cpu.step(&mut mem);
+    cpu.step(&mut mem);
+    assert_eq!((cpu.regs.a, cpu.regs.x, cpu.regs.y), (0xFF, 0x01, 0xFF));
+}
+
+// ============================================================
+// Branches
+// ============================================================
+
+#[test]
+fn test_branch_taken_adds_offset_in_4_cycles() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    emit_seq(&mut mem, 0x0200, &[0x2F, 0x10]); // BRA +$10
+    cpu.step(&mut mem);
+    assert_eq!(cpu.regs.pc, 0x0212);
+    assert_eq!(cpu.cycles, 4);
+}
+
+#[test]
+fn test_branch_backwards() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    emit_seq(&mut mem, 0x0200, &[0xD0, 0xFE]); // BNE -2
+    cpu.step(&mut mem);
+    assert_eq!(cpu.regs.pc, 0x0200);
+}
+
+#[test]
+fn test_branch_not_taken_takes_2_cycles() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    cpu.regs.psw = FLAG_Z;
+    emit_seq(&mut mem, 0x0200, &[0xD0, 0x10]); // BNE +$10
+    cpu.step(&mut mem);
+    assert_eq!(cpu.regs.pc, 0x0202);
+    assert_eq!(cpu.cycles, 2);
+    assert!(cpu.at_instruction_boundary());
+}
+
+#[test]
+fn test_branch_conditions() {
+    // (opcode, flags which take the branch, flags which don't)
+    let cases = [
+        (0x10, 0, FLAG_N),      // BPL
+        (0x30, FLAG_N, 0),      // BMI
+        (0x50, 0, FLAG_V),      // BVC
+        (0x70, FLAG_V, 0),      // BVS
+        (0x90, 0, FLAG_C),      // BCC
+        (0xB0, FLAG_C, 0),      // BCS
+        (0xD0, 0, FLAG_Z),      // BNE
+        (0xF0, FLAG_Z, 0),      // BEQ
+    ];
+    for (opcode, taken, not_taken) in cases {
+        for (psw, expected_pc) in [(taken, 0x0206), (not_taken, 0x0202)] {
+            let (mut cpu, mut mem) = make_cpu_mem();
+            cpu.regs.psw = psw;
+            emit_seq(&mut mem, 0x0200, &[opcode, 0x04]);
+            cpu.step(&mut mem);
+            assert_eq!(cpu.regs.pc, expected_pc, "opcode {opcode:#04X} with PSW {psw:#04X}");
+        }
+    }
+}
+
+// ============================================================
+// MOV d, #imm / CMP d, #imm / CMP X, Y
+// ============================================================
+
+#[test]
+fn test_mov_dp_imm_writes_on_last_cycle() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    emit_seq(&mut mem, 0x0200, &[0x8F, 0xAA, 0xF4]); // MOV $F4, #$AA
+    let accesses: Vec<_> = (0..5).map(|_| cpu.cycle(&mut mem)).collect();
+    assert_eq!(accesses[4], CycleResult::Write(0x00F4));
+    assert_eq!(mem.cpu_port_read(0), 0xAA);
+    assert_eq!(cpu.regs.pc, 0x0203);
+    assert!(cpu.at_instruction_boundary());
+}
+
+#[test]
+fn test_cmp_dp_imm_compares_memory_with_immediate() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    mem.write8(0x0030, 0x10);
+    emit_seq(&mut mem, 0x0200, &[0x78, 0x20, 0x30]); // CMP $30, #$20
+    cpu.step(&mut mem);
+    assert!(!cpu.get_flag(FLAG_C), "$10 < $20 must clear C");
+    assert!(cpu.get_flag(FLAG_N));
+    assert_eq!(mem.read8(0x0030), 0x10, "CMP must not write");
+    assert_eq!(cpu.cycles, 5);
+}
+
+#[test]
+fn test_cmp_x_y() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    cpu.regs.x = 0x40;
+    cpu.regs.y = 0x40;
+    mem.write8(0x0030, 0x40);
+    emit_seq(&mut mem, 0x0200, &[0xC8, 0x41, 0x7E, 0x30]); // CMP X, #$41; CMP Y, $30
+    cpu.step(&mut mem);
+    assert!(!cpu.get_flag(FLAG_C) && !cpu.get_flag(FLAG_Z));
+    cpu.step(&mut mem);
+    assert!(cpu.get_flag(FLAG_C) && cpu.get_flag(FLAG_Z));
+}
+
+// ============================================================
+// Indirect moves — (X) and [d]+Y
+// ============================================================
+
+#[test]
+fn test_mov_indirect_x_uses_direct_page() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    cpu.regs.a = 0x42;
+    cpu.regs.x = 0x30;
+    cpu.regs.psw = FLAG_P;
+    emit_seq(&mut mem, 0x0200, &[0xC6, 0xE6]); // MOV (X), A; MOV A, (X)
+    cpu.step(&mut mem);
+    assert_eq!(mem.read8(0x0130), 0x42);
+    assert_eq!(cpu.cycles, 4);
+
+    mem.write8(0x0130, 0x00);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.regs.a, 0x00);
+    assert!(cpu.get_flag(FLAG_Z));
+    assert_eq!(cpu.cycles, 7);
+}
+
+#[test]
+fn test_mov_indirect_indexed_y() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    cpu.regs.a = 0x42;
+    cpu.regs.y = 0x05;
+    mem.write16(0x0010, 0x12FE); // pointer
+    emit_seq(&mut mem, 0x0200, &[0xD7, 0x10]); // MOV [$10]+Y, A
+    let accesses: Vec<_> = (0..7).map(|_| cpu.cycle(&mut mem)).collect();
+    assert_eq!(accesses[2], CycleResult::Read(0x0010));
+    assert_eq!(accesses[3], CycleResult::Read(0x0011));
+    assert_eq!(accesses[6], CycleResult::Write(0x1303));
+    assert_eq!(mem.read8(0x1303), 0x42);
+    assert!(cpu.at_instruction_boundary());
+}
+
+// ============================================================
+// Read-modify-write — INC/DEC d
+// ============================================================
+
+#[test]
+fn test_inc_dp_reads_then_writes() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    mem.write8(0x0001, 0x7F);
+    emit_seq(&mut mem, 0x0200, &[0xAB, 0x01]); // INC $01
+    let accesses: Vec<_> = (0..4).map(|_| cpu.cycle(&mut mem)).collect();
+    assert_eq!(accesses[2..], [CycleResult::Read(0x0001), CycleResult::Write(0x0001)]);
+    assert_eq!(mem.read8(0x0001), 0x80);
+    assert!(cpu.get_flag(FLAG_N));
+}
+
+#[test]
+fn test_dec_dp_sets_zero_flag() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    mem.write8(0x0001, 0x01);
+    emit_seq(&mut mem, 0x0200, &[0x8B, 0x01]); // DEC $01
+    cpu.step(&mut mem);
+    assert_eq!(mem.read8(0x0001), 0x00);
+    assert!(cpu.get_flag(FLAG_Z));
+    assert_eq!(cpu.cycles, 4);
+}
+
+// ============================================================
+// 16-bit moves — MOVW
+// ============================================================
+
+#[test]
+fn test_movw_ya_dp_loads_both_bytes() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    cpu.regs.psw = FLAG_P;
+    mem.write8(0x01FF, 0x00);
+    mem.write8(0x0100, 0x11); // the high byte wraps within the direct page
+    emit_seq(&mut mem, 0x0200, &[0xBA, 0xFF]); // MOVW YA, $FF
+    cpu.step(&mut mem);
+    assert_eq!((cpu.regs.a, cpu.regs.y), (0x00, 0x11));
+    assert!(!cpu.get_flag(FLAG_Z), "Z must cover the 16 bits");
+    assert!(!cpu.get_flag(FLAG_N));
+    assert_eq!(cpu.cycles, 5);
+}
+
+#[test]
+fn test_movw_dp_ya_writes_both_bytes() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    cpu.regs.a = 0x34;
+    cpu.regs.y = 0x12;
+    emit_seq(&mut mem, 0x0200, &[0xDA, 0x20]); // MOVW $20, YA
+    let accesses: Vec<_> = (0..5).map(|_| cpu.cycle(&mut mem)).collect();
+    assert_eq!(accesses[3..], [CycleResult::Write(0x0020), CycleResult::Write(0x0021)]);
+    assert_eq!(mem.read16(0x0020), 0x1234);
+}
+
+// ============================================================
+// JMP [!a+X]
+// ============================================================
+
+#[test]
+fn test_jmp_indexed_indirect() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    cpu.regs.x = 0x04;
+    mem.write16(0x1004, 0x3456);
+    emit_seq(&mut mem, 0x0200, &[0x1F, 0x00, 0x10]); // JMP [!$1000+X]
+    cpu.step(&mut mem);
+    assert_eq!(cpu.regs.pc, 0x3456);
+    assert_eq!(cpu.cycles, 6);
+}
+
 // ============================================================
 // Cumulative cycle counting across multiple instructions
 // ============================================================
@@ -913,6 +1198,92 @@ fn test_cycles_accumulate_across_multiple_steps() {
     assert_eq!(mem.read8(0x0500), 0x42);
 }
 
+// ============================================================
+// Per-cycle stepping
+// ============================================================
+
+#[test]
+fn test_cycle_nop_fetch_then_internal() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    emit(&mut mem, 0x0200, 0x00); // NOP
+    assert_eq!(cpu.cycle(&mut mem), CycleResult::Read(0x0200));
+    assert!(!cpu.at_instruction_boundary());
+    assert_eq!(cpu.cycle(&mut mem), CycleResult::Internal);
+    assert!(cpu.at_instruction_boundary());
+}
+
+#[test]
+fn test_cycle_lda_abs_accesses() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    emit_seq(&mut mem, 0x0200, &[0xE5, 0x34, 0x12]); // LDA !$1234
+    mem.write8(0x1234, 0x77);
+    let accesses: Vec<_> = (0..4).map(|_| cpu.cycle(&mut mem)).collect();
+    assert_eq!(accesses, [
+        CycleResult::Read(0x0200),
+        CycleResult::Read(0x0201),
+        CycleResult::Read(0x0202),
+        CycleResult::Read(0x1234),
+    ]);
+    assert_eq!(cpu.regs.a, 0x77);
+    assert!(cpu.at_instruction_boundary());
+}
+
+#[test]
+fn test_cycle_sta_dp_writes_on_last_cycle() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    cpu.regs.a = 0x42;
+    emit_seq(&mut mem, 0x0200, &[0xC4, 0x50]); // STA $50
+    assert_eq!(cpu.cycle(&mut mem), CycleResult::Read(0x0200));
+    assert_eq!(cpu.cycle(&mut mem), CycleResult::Read(0x0201));
+    assert_eq!(mem.read8(0x0050), 0x00, "the store must wait for its data cycle");
+    assert_eq!(cpu.cycle(&mut mem), CycleResult::Write(0x0050));
+    assert_eq!(mem.read8(0x0050), 0x42);
+}
+
+#[test]
+fn test_cycle_operand_read_sees_port_written_mid_instruction() {
+    // The SNES CPU writes port 0 after the opcode fetch of LDA $F4:
+    // the read of the operand, two cycles later, must see it
+    let (mut cpu, mut mem) = make_cpu_mem();
+    emit_seq(&mut mem, 0x0200, &[0xE4, 0xF4]); // LDA $F4
+    cpu.cycle(&mut mem);
+    mem.cpu_port_write(0, 0xAA);
+    cpu.cycle(&mut mem);
+    cpu.cycle(&mut mem);
+    assert_eq!(cpu.regs.a, 0xAA);
+}
+
+#[test]
+fn test_step_finishes_instruction_in_progress() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    emit_seq(&mut mem, 0x0200, &[0xE8, 0x42, 0x00]); // LDA #$42; NOP
+    cpu.cycle(&mut mem);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.regs.a, 0x42);
+    assert_eq!(cpu.regs.pc, 0x0202);
+    assert_eq!(cpu.cycles, 2);
+}
+
+#[test]
+fn test_unimplemented_opcode_halts() {
+    let (mut cpu, mut mem) = make_cpu_mem();
+    emit_seq(&mut mem, 0x0200, &[0xFF, 0x00]); // STOP; NOP
+    assert_eq!(cpu.cycle(&mut mem), CycleResult::Read(0x0200));
+    let unhandled = UnhandledOpcode { opcode: 0xFF, addr: 0x0200 };
+    assert_eq!(cpu.halted(), Some(unhandled));
+    assert_eq!(cpu.take_unhandled_opcode(), Some(unhandled));
+    assert_eq!(cpu.take_unhandled_opcode(), None, "reported once");
+
+    for _ in 0..10 {
+        assert_eq!(cpu.cycle(&mut mem), CycleResult::Internal);
+    }
+    assert_eq!(cpu.regs.pc, 0x0200, "PC stays on the opcode");
+    assert_eq!(cpu.cycles, 11);
+
+    cpu.reset(&mut mem);
+    assert_eq!(cpu.halted(), None);
+}
+
 // ============================================================
 // PC wrapping
 // ============================================================
@@ -921,6 +1292,7 @@ fn test_cycles_accumulate_across_multiple_steps() {
 fn test_pc_wraps_at_0xffff() {
     let mut cpu = Spc700::new();
     let mut mem = Memory::new();
+    mem.write8(0x00F1, 0x00); // unmap the IPL ROM
     // Place a NOP at $FFFF (after reset vector bytes, which are at $FFFE/$FFFF
     // but we test the fetch wrapping separately from reset)
     cpu.regs.pc = 0xFFFF;
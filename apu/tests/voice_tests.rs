@@ -11,10 +11,10 @@ use apu::Memory;
 // Helpers
 // ============================================================
 
-const DSP_BASE: u16 = 0xF200;
-
+/// Write a per-voice DSP register through the $F2/$F3 port pair.
 fn dsp_vw(mem: &mut Memory, voice: u8, reg: u8, val: u8) {
-    mem.write8(DSP_BASE + ((voice as u16) << 4) + reg as u16, val);
+    mem.write8(0x00F2, (voice << 4) + reg);
+    mem.write8(0x00F3, val);
 }
 
 // ============================================================
@@ -0,0 +1,124 @@
+/// WAV export tests
+///
+/// Covers:
+///   - RIFF/fmt/data header fields for mono and stereo output
+///   - Sample data is little-endian and interleaved L, R
+///   - Trailing partial frames are dropped
+///   - Zero channels is rejected
+///   - write_wav / write_wav_mono produce the same bytes as write_wav_data
+
+use apu::wav::{write_wav, write_wav_data, write_wav_mono, WAV_HEADER_LEN};
+
+// ============================================================
+// Helpers
+// ============================================================
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn encode(channels: u16, samples: &[i16], rate: u32) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_wav_data(&mut out, channels, samples, rate).unwrap();
+    out
+}
+
+/// Unique path in the system temp directory for a file-writing test.
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("apu_wav_{}_{name}.wav", std::process::id()))
+}
+
+// ============================================================
+// Header layout
+// ============================================================
+
+#[test]
+fn test_stereo_header_fields() {
+    let bytes = encode(2, &[1, 2, 3, 4], 32_000);
+
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(u32_at(&bytes, 4), bytes.len() as u32 - 8, "RIFF size");
+    assert_eq!(&bytes[8..12], b"WAVE");
+    assert_eq!(&bytes[12..16], b"fmt ");
+    assert_eq!(u32_at(&bytes, 16), 16, "fmt chunk size");
+    assert_eq!(u16_at(&bytes, 20), 1, "PCM format tag");
+    assert_eq!(u16_at(&bytes, 22), 2, "channels");
+    assert_eq!(u32_at(&bytes, 24), 32_000, "sample rate");
+    assert_eq!(u32_at(&bytes, 28), 32_000 * 4, "byte rate");
+    assert_eq!(u16_at(&bytes, 32), 4, "block align");
+    assert_eq!(u16_at(&bytes, 34), 16, "bits per sample");
+    assert_eq!(&bytes[36..40], b"data");
+    assert_eq!(u32_at(&bytes, 40), 8, "data size");
+    assert_eq!(bytes.len(), WAV_HEADER_LEN + 8);
+}
+
+#[test]
+fn test_mono_header_fields() {
+    let bytes = encode(1, &[0; 10], 44_100);
+
+    assert_eq!(u16_at(&bytes, 22), 1, "channels");
+    assert_eq!(u32_at(&bytes, 28), 44_100 * 2, "byte rate");
+    assert_eq!(u16_at(&bytes, 32), 2, "block align");
+    assert_eq!(u32_at(&bytes, 40), 20, "data size");
+}
+
+#[test]
+fn test_empty_output_is_valid_header_only() {
+    let bytes = encode(2, &[], 32_000);
+    assert_eq!(bytes.len(), WAV_HEADER_LEN);
+    assert_eq!(u32_at(&bytes, 40), 0);
+    assert_eq!(u32_at(&bytes, 4), 36);
+}
+
+// ============================================================
+// Sample data
+// ============================================================
+
+#[test]
+fn test_samples_written_little_endian() {
+    let bytes = encode(1, &[0x1234, -2], 32_000);
+    assert_eq!(&bytes[WAV_HEADER_LEN..], &[0x34, 0x12, 0xFE, 0xFF]);
+}
+
+#[test]
+fn test_partial_trailing_frame_dropped() {
+    let bytes = encode(2, &[1, 2, 3], 32_000);
+    assert_eq!(u32_at(&bytes, 40), 4, "only one whole stereo frame");
+    assert_eq!(bytes.len(), WAV_HEADER_LEN + 4);
+}
+
+#[test]
+fn test_zero_channels_rejected() {
+    let mut out = Vec::new();
+    let err = write_wav_data(&mut out, 0, &[1, 2], 32_000).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(out.is_empty(), "nothing may be written on error");
+}
+
+// ============================================================
+// File writers
+// ============================================================
+
+#[test]
+fn test_write_wav_interleaves_pairs() {
+    let path = temp_path("stereo");
+    write_wav(&path, &[(1, -1), (2, -2)], 32_000).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(bytes, encode(2, &[1, -1, 2, -2], 32_000));
+}
+
+#[test]
+fn test_write_wav_mono_matches_encoder() {
+    let path = temp_path("mono");
+    write_wav_mono(&path, &[7, 8, 9], 32_000).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(bytes, encode(1, &[7, 8, 9], 32_000));
+}
@@ -0,0 +1,195 @@
+use crate::register_info::Register;
+use common::snes_address::SnesAddress;
+use std::fmt;
+
+/// Component driving the bus during an access.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccessSource {
+    #[default]
+    Cpu,
+    /// General purpose DMA transfer, while the CPU is paused
+    Dma,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A memory access seen by the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusAccess {
+    /// CPU cycles run since power on (DMA accesses happen between two CPU cycles)
+    pub cpu_cycle: u64,
+    /// Master clock cycles since power on
+    pub master_cycle: u64,
+    pub source: AccessSource,
+    pub kind: AccessKind,
+    pub addr: SnesAddress,
+    /// Byte read (as patched by cheats) or written
+    pub value: u8,
+}
+
+/// One trace line, naming the I/O register accessed, e.g.
+/// `10 Dma W $00:2118 = $34 VMDATAL`
+impl fmt::Display for BusAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, write) = match self.kind {
+            AccessKind::Read => ("R", false),
+            AccessKind::Write => ("W", true),
+        };
+        write!(
+            f,
+            "{} {:?} {kind} ${:02X}:{:04X} = ${:02X}",
+            self.cpu_cycle, self.source, self.addr.bank, self.addr.addr, self.value
+        )?;
+        if let Some(register) = Register::at_address(self.addr, write) {
+            write!(f, " {register}")?;
+            if !register.info.fields.is_empty() {
+                write!(f, " {}", register.fields(self.value))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Receives every access seen by an [`AccessTap`], in order.
+pub trait AccessConsumer: Send {
+    fn access(&mut self, access: &BusAccess);
+}
+
+impl<F: FnMut(&BusAccess) + Send> AccessConsumer for F {
+    fn access(&mut self, access: &BusAccess) {
+        self(access)
+    }
+}
+
+/// Optional tap streaming the bus accesses into an [`AccessConsumer`], to analyse the
+/// interleaving of CPU, DMA and PPU port accesses or assert it in integration tests.
+///
+/// Disabled until a consumer is set, in which case an access only costs a branch. Block
+/// accesses are split into single bytes while a consumer is set, so that none is missed.
+///
+/// The bus doesn't know who drives it nor when, so the driver reports it through
+/// [`Self::set_context`] before running a CPU cycle or a DMA transfer.
+#[derive(Default)]
+pub struct AccessTap {
+    consumer: Option<Box<dyn AccessConsumer>>,
+    source: AccessSource,
+    cpu_cycle: u64,
+    master_cycle: u64,
+}
+
+impl AccessTap {
+    pub fn enabled(&self) -> bool {
+        self.consumer.is_some()
+    }
+
+    /// Streams the following accesses to `consumer`, replacing the previous one.
+    pub fn set_consumer(&mut self, consumer: impl AccessConsumer + 'static) {
+        self.consumer = Some(Box::new(consumer));
+    }
+
+    /// Stops streaming accesses, returning the consumer.
+    pub fn take_consumer(&mut self) -> Option<Box<dyn AccessConsumer>> {
+        self.consumer.take()
+    }
+
+    /// Origin and timestamp of the following accesses.
+    pub fn set_context(&mut self, source: AccessSource, cpu_cycle: u64, master_cycle: u64) {
+        self.source = source;
+        self.cpu_cycle = cpu_cycle;
+        self.master_cycle = master_cycle;
+    }
+
+    /// Streams an access to the consumer, if any.
+    pub fn record(&mut self, kind: AccessKind, addr: SnesAddress, value: u8) {
+        let Some(consumer) = &mut self.consumer else {
+            return;
+        };
+        consumer.access(&BusAccess {
+            cpu_cycle: self.cpu_cycle,
+            master_cycle: self.master_cycle,
+            source: self.source,
+            kind,
+            addr,
+            value,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::snes_address::snes_addr;
+    use std::sync::{Arc, Mutex};
+
+    fn recording_tap() -> (AccessTap, Arc<Mutex<Vec<BusAccess>>>) {
+        let accesses = Arc::new(Mutex::new(Vec::new()));
+        let mut tap = AccessTap::default();
+        let sink = Arc::clone(&accesses);
+        tap.set_consumer(move |access: &BusAccess| sink.lock().unwrap().push(*access));
+        (tap, accesses)
+    }
+
+    #[test]
+    fn test_disabled_tap_records_nothing() {
+        let mut tap = AccessTap::default();
+        assert!(!tap.enabled());
+        tap.record(AccessKind::Read, snes_addr!(0:0x8000), 0xEA);
+    }
+
+    #[test]
+    fn test_record_uses_context() {
+        let (mut tap, accesses) = recording_tap();
+        assert!(tap.enabled());
+
+        tap.set_context(AccessSource::Cpu, 10, 60);
+        tap.record(AccessKind::Read, snes_addr!(0:0x8000), 0xEA);
+        tap.set_context(AccessSource::Dma, 11, 66);
+        tap.record(AccessKind::Write, snes_addr!(0:0x2118), 0x34);
+
+        assert_eq!(
+            *accesses.lock().unwrap(),
+            [
+                BusAccess { cpu_cycle: 10, master_cycle: 60, source: AccessSource::Cpu, kind: AccessKind::Read, addr: snes_addr!(0:0x8000), value: 0xEA },
+                BusAccess { cpu_cycle: 11, master_cycle: 66, source: AccessSource::Dma, kind: AccessKind::Write, addr: snes_addr!(0:0x2118), value: 0x34 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display_names_registers() {
+        let access = |kind, addr, value| BusAccess {
+            cpu_cycle: 10,
+            master_cycle: 60,
+            source: AccessSource::Cpu,
+            kind,
+            addr,
+            value,
+        };
+        assert_eq!(
+            access(AccessKind::Read, snes_addr!(0x80:0x4212), 0x81).to_string(),
+            "10 Cpu R $80:4212 = $81 HVBJOY vblank=1 hblank=0 auto_joypad_busy=1"
+        );
+        assert_eq!(
+            access(AccessKind::Write, snes_addr!(0:0x2118), 0x34).to_string(),
+            "10 Cpu W $00:2118 = $34 VMDATAL"
+        );
+        assert_eq!(
+            access(AccessKind::Read, snes_addr!(0x7E:0x2118), 0x34).to_string(),
+            "10 Cpu R $7E:2118 = $34"
+        );
+    }
+
+    #[test]
+    fn test_take_consumer_stops_recording() {
+        let (mut tap, accesses) = recording_tap();
+        assert!(tap.take_consumer().is_some());
+        assert!(!tap.enabled());
+
+        tap.record(AccessKind::Write, snes_addr!(0x7E:0x0000), 0x01);
+        assert!(accesses.lock().unwrap().is_empty());
+    }
+}
@@ -1,3 +1,7 @@
+#[cfg(feature = "access-log")]
+use crate::access_log::{AccessKind, AccessTap};
+use crate::cheats::Cheats;
+use crate::coprocessor::{self, Coprocessor};
 use crate::io::Io;
 use crate::rom::Rom;
 use crate::wram::Wram;
@@ -5,6 +9,8 @@ use apu::Apu;
 use common::snes_address::SnesAddress;
 use ppu::ppu::PPU;
 use std::error::Error;
+use std::fs;
+use std::io;
 use std::path::Path;
 
 use duplicate::duplicate;
@@ -13,41 +19,288 @@ pub struct Bus {
     pub wram: Wram,
     pub rom: Rom,
     pub io: Io,
+    pub cheats: Cheats,
+    /// Cartridge coprocessor, whose registers take precedence over the regular regions
+    pub coprocessor: Option<Box<dyn Coprocessor>>,
+    #[cfg(feature = "access-log")]
+    pub access_tap: AccessTap,
 }
 
 impl Bus {
     pub fn new<P: AsRef<Path>>(rom_path: P) -> Result<Self, Box<dyn Error>> {
-        Ok(Self {
-            rom: Rom::load_from_file(rom_path)?,
+        Ok(Self::from_rom(Rom::load_from_file(rom_path)?))
+    }
+
+    pub fn from_rom(rom: Rom) -> Self {
+        Self {
+            coprocessor: coprocessor::for_rom(&rom),
+            rom,
             wram: Wram::new(),
             io: Io::default(),
-        })
+            cheats: Cheats::default(),
+            #[cfg(feature = "access-log")]
+            access_tap: AccessTap::default(),
+        }
+    }
+
+    /// Registers a coprocessor, replacing the one detected from the ROM header.
+    pub fn set_coprocessor(&mut self, coprocessor: Box<dyn Coprocessor>) {
+        self.coprocessor = Some(coprocessor);
+    }
+
+    /// Clocks the coprocessor, if any, for `master_cycles` master clock cycles.
+    pub fn step_coprocessor(&mut self, master_cycles: u32) {
+        if let Some(coprocessor) = &mut self.coprocessor {
+            coprocessor.step(master_cycles);
+        }
+    }
+
+    /// Restores the coprocessor state saved at `path`; a missing file leaves it untouched.
+    pub fn load_coprocessor_data<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let Some(coprocessor) = &mut self.coprocessor else {
+            return Ok(());
+        };
+        match fs::read(path) {
+            Ok(data) => coprocessor.load_save_data(&data),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        Ok(())
+    }
+
+    /// Writes the coprocessor state to `path`, if it has any.
+    pub fn save_coprocessor_data<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        match self.coprocessor.as_ref().and_then(|c| c.save_data()) {
+            Some(data) => fs::write(path, data),
+            None => Ok(()),
+        }
     }
 
     duplicate! {
         [
-            DUP_method  DUP_parameters                                  DUP_return_t    DUP_method_param;
-            [ read ]    [ &mut self, addr: SnesAddress ]                [ u8 ]          [ addr ];
-            [ write ]   [ &mut self, addr: SnesAddress, value: u8 ]     [ () ]          [ addr, value ];
+            DUP_vis DUP_name            DUP_method  DUP_parameters                                  DUP_return_t    DUP_method_param    DUP_open_bus                    DUP_wram_port;
+            [ pub ] [ read_unpatched ]  [ read ]    [ &mut self, addr: SnesAddress ]                [ u8 ]          [ addr ]            [ self.io.open_bus ]            [ read_wram_port ];
+            [ ]     [ write_untapped ]  [ write ]   [ &mut self, addr: SnesAddress, value: u8 ]     [ () ]          [ addr, value ]     [ self.io.open_bus = value ]    [ write_wram_port ];
         ]
-        pub fn DUP_method(DUP_parameters, ppu: &mut PPU, apu: &mut Apu) -> DUP_return_t {
-            match addr.bank {
-                0x00..=0x3F | 0x80..=0xBF => match addr.addr {
-                    0x0000..0x2000 => self.wram.DUP_method(DUP_method_param),
-                    0x2000..0x6000 => self.io.DUP_method(DUP_method_param, ppu, apu),
-                    0x6000..0x8000 => self.rom.DUP_method(DUP_method_param), // TODO : Expansion port
-                    0x8000..=0xFFFF => self.rom.DUP_method(DUP_method_param),
-                },
-                0x7E..=0x7F => self.wram.DUP_method(DUP_method_param),
-                0x40..=0x7D | 0xC0..=0xFF => self.rom.DUP_method(DUP_method_param),
+        DUP_vis fn DUP_name(DUP_parameters, ppu: &mut PPU, apu: &mut Apu) -> DUP_return_t {
+            if let Some(coprocessor) = self.coprocessor.as_mut().filter(|c| c.maps(addr)) {
+                return coprocessor.DUP_method(DUP_method_param);
+            }
+            match Self::region(addr) {
+                Region::Wram => self.wram.DUP_method(DUP_method_param),
+                Region::Io if (0x2180..0x2184).contains(&addr.addr) => {
+                    self.DUP_wram_port(DUP_method_param)
+                }
+                Region::Io => self.io.DUP_method(DUP_method_param, ppu, apu),
+                Region::Rom => self.rom.DUP_method(DUP_method_param),
+                Region::Expansion => DUP_open_bus,
+            }
+        }
+    }
+
+    /// WMDATA (`$2180`) on the B-bus reads WRAM at WMADD; WMADD (`$2181-$2183`) is write only
+    /// and reads the open bus.
+    fn read_wram_port(&mut self, addr: SnesAddress) -> u8 {
+        if addr.addr == 0x2180 {
+            self.io.open_bus = self.wram.read_wmdata();
+        }
+        self.io.open_bus
+    }
+
+    fn write_wram_port(&mut self, addr: SnesAddress, value: u8) {
+        self.io.open_bus = value;
+        self.wram.write_port(addr.addr, value);
+    }
+
+    /// Reads a byte, as patched by the enabled [`cheats`](Self::cheats).
+    pub fn read(&mut self, addr: SnesAddress, ppu: &mut PPU, apu: &mut Apu) -> u8 {
+        let value = self.read_unpatched(addr, ppu, apu);
+        let value = self.cheats.apply(addr, value);
+        #[cfg(feature = "access-log")]
+        self.access_tap.record(AccessKind::Read, addr, value);
+        value
+    }
+
+    pub fn write(&mut self, addr: SnesAddress, value: u8, ppu: &mut PPU, apu: &mut Apu) {
+        #[cfg(feature = "access-log")]
+        self.access_tap.record(AccessKind::Write, addr, value);
+        self.write_untapped(addr, value, ppu, apu);
+    }
+
+    /// Byte the CPU would read at `addr` (cheats included), without the side effects of a
+    /// read, for debugger views. `None` for I/O and coprocessor registers, which only a real
+    /// read gives, and for addresses past the end of the ROM.
+    pub fn peek(&self, addr: SnesAddress) -> Option<u8> {
+        if self.coprocessor.as_ref().is_some_and(|c| c.maps(addr)) {
+            return None;
+        }
+        let value = match Self::region(addr) {
+            Region::Wram => self.wram.read(addr),
+            Region::Rom => self.rom.try_read(addr)?,
+            Region::Io | Region::Expansion => return None,
+        };
+        Some(self.cheats.apply(addr, value))
+    }
+
+    /// Offset in the ROM data of the byte the CPU reads at `addr`, `None` when `addr` is not
+    /// mapped to the ROM or the coprocessor maps it.
+    pub fn rom_offset(&self, addr: SnesAddress) -> Option<usize> {
+        if self.coprocessor.as_ref().is_some_and(|c| c.maps(addr)) {
+            return None;
+        }
+        match Self::region(addr) {
+            Region::Rom => self.rom.offset(addr),
+            Region::Wram | Region::Io | Region::Expansion => None,
+        }
+    }
+
+    fn region(addr: SnesAddress) -> Region {
+        match addr.bank {
+            0x00..=0x3F | 0x80..=0xBF => match addr.addr {
+                0x0000..0x2000 => Region::Wram,
+                0x2000..0x6000 => Region::Io,
+                0x6000..0x8000 => Region::Expansion,
+                0x8000..=0xFFFF => Region::Rom,
+            },
+            0x7E..=0x7F => Region::Wram,
+            0x40..=0x7D | 0xC0..=0xFF => Region::Rom,
+        }
+    }
+
+    /// Speed of a CPU access at `addr`, from the memory map and the FastROM
+    /// setting (MEMSEL). DMA and coprocessors don't go through this.
+    ///
+    /// [SNESdev Wiki - Memory map](https://snes.nesdev.org/wiki/Memory_map)
+    pub fn access_speed(&self, addr: SnesAddress) -> AccessSpeed {
+        let rom_speed = if addr.bank >= 0x80 && (self.io.memsel & 0x01) != 0 {
+            AccessSpeed::Fast
+        } else {
+            AccessSpeed::Slow
+        };
+        match addr.bank {
+            0x00..=0x3F | 0x80..=0xBF => match addr.addr {
+                0x0000..0x2000 => AccessSpeed::Slow,
+                0x2000..0x4000 => AccessSpeed::Fast,
+                0x4000..0x4200 => AccessSpeed::ExtraSlow,
+                0x4200..0x6000 => AccessSpeed::Fast,
+                0x6000..0x8000 => AccessSpeed::Slow,
+                0x8000..=0xFFFF => rom_speed,
+            },
+            0x40..=0x7F => AccessSpeed::Slow,
+            0xC0..=0xFF => rom_speed,
+        }
+    }
+
+    /// Region handling a block of `len` bytes starting at `addr`, if the
+    /// whole block belongs to a single memory region (and not I/O or
+    /// coprocessor registers, which must see every access, nor while the
+    /// access tap is enabled, for the same reason).
+    fn block_region(&self, addr: SnesAddress, len: usize) -> Option<Region> {
+        if len == 0 || self.coprocessor_maps_block(addr, len) {
+            return None;
+        }
+        #[cfg(feature = "access-log")]
+        if self.access_tap.enabled() {
+            return None;
+        }
+        let region = Self::region(addr);
+        let last = SnesAddress::from(usize::from(addr) + len - 1);
+
+        // Both ends in the same bank (or both full WRAM banks), so nothing
+        // else is mapped in between
+        let same_bank = last.bank == addr.bank || (addr.bank == 0x7E && last.bank == 0x7F);
+
+        (same_bank && region != Region::Io && Self::region(last) == region).then_some(region)
+    }
+
+    fn coprocessor_maps_block(&self, addr: SnesAddress, len: usize) -> bool {
+        let Some(coprocessor) = &self.coprocessor else {
+            return false;
+        };
+        let start = usize::from(addr);
+        (start..start + len).any(|a| coprocessor.maps(SnesAddress::from(a)))
+    }
+
+    /// Reads `buf.len()` consecutive bytes starting at `addr`.
+    ///
+    /// Blocks within WRAM or ROM are copied by the region itself,
+    /// anything else is read byte per byte through [`Self::read`].
+    pub fn read_block(&mut self, addr: SnesAddress, buf: &mut [u8], ppu: &mut PPU, apu: &mut Apu) {
+        match self.block_region(addr, buf.len()) {
+            Some(Region::Wram) => {
+                self.wram.read_block(addr, buf);
+                self.cheats.apply_block(addr, buf);
+            }
+            Some(Region::Rom) => {
+                self.rom.read_block(addr, buf);
+                self.cheats.apply_block(addr, buf);
             }
+            _ => {
+                let mut addr = addr;
+                for byte in buf {
+                    *byte = self.read(addr, ppu, apu);
+                    addr.increment();
+                }
+            }
+        }
+    }
+
+    /// Writes `data` to consecutive addresses starting at `addr`.
+    ///
+    /// Blocks within WRAM or ROM are copied by the region itself,
+    /// anything else is written byte per byte through [`Self::write`].
+    pub fn write_block(&mut self, addr: SnesAddress, data: &[u8], ppu: &mut PPU, apu: &mut Apu) {
+        match self.block_region(addr, data.len()) {
+            Some(Region::Wram) => self.wram.write_block(addr, data),
+            Some(Region::Rom) => self.rom.write_block(addr, data),
+            _ => {
+                let mut addr = addr;
+                for &byte in data {
+                    self.write(addr, byte, ppu, apu);
+                    addr.increment();
+                }
+            }
+        }
+    }
+}
+
+/// Speed class of the memory mapped at an address, see [`Bus::access_speed`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessSpeed {
+    /// 3.58 MHz: most I/O registers, and ROM in banks `0x80-0xFF` with FastROM
+    Fast,
+    /// 2.68 MHz: WRAM, SlowROM and the expansion port
+    Slow,
+    /// 1.79 MHz: the serial joypad registers (`0x4000-0x41FF`)
+    ExtraSlow,
+}
+
+impl AccessSpeed {
+    /// Master clock cycles taken by a CPU access of this speed
+    pub fn master_cycles(self) -> u32 {
+        match self {
+            AccessSpeed::Fast => 6,
+            AccessSpeed::Slow => 8,
+            AccessSpeed::ExtraSlow => 12,
         }
     }
 }
 
+/// Memory regions the bus dispatches accesses to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region {
+    Wram,
+    Io,
+    Rom,
+    /// Expansion port (`$6000-$7FFF` of the system banks): nothing is connected, reads
+    /// return the open bus unless a coprocessor maps its registers there
+    Expansion,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::{HEADER_ROM_HARDWARE_OFFSET, LOROM_HEADER_OFFSET};
     use crate::rom::test_rom::*;
     use common::snes_address::snes_addr;
 
@@ -99,6 +352,45 @@ mod tests {
         assert_eq!(read_value, 0x40);
     }
 
+    #[test]
+    fn test_peek() {
+        let mut rom_data = create_valid_lorom(0x20000);
+        rom_data[0x0010] = 0x42;
+        let mut bus = Bus::from_rom(Rom::from_bytes(rom_data).unwrap());
+        bus.wram.data[0x10] = 0x24;
+
+        assert_eq!(bus.peek(snes_addr!(0x80:0x8010)), Some(0x42));
+        assert_eq!(bus.peek(snes_addr!(0:0x0010)), Some(0x24));
+        assert_eq!(bus.peek(snes_addr!(0x7E:0x0010)), Some(0x24));
+        assert_eq!(bus.peek(snes_addr!(0:0x4210)), None);
+        assert_eq!(bus.peek(snes_addr!(0:0x6000)), None);
+    }
+
+    #[test]
+    fn test_rom_offset() {
+        let bus = Bus::from_rom(Rom::from_bytes(create_valid_lorom(0x20000)).unwrap());
+
+        assert_eq!(bus.rom_offset(snes_addr!(0x80:0x8010)), Some(0x0010));
+        assert_eq!(bus.rom_offset(snes_addr!(0x01:0x8000)), Some(0x8000));
+        assert_eq!(bus.rom_offset(snes_addr!(0:0x0010)), None);
+        assert_eq!(bus.rom_offset(snes_addr!(0x7E:0x8000)), None);
+    }
+
+    #[test]
+    fn test_expansion_port_reads_open_bus() {
+        let (mut ppu, mut apu) = init_extern_components();
+        let mut bus = Bus::from_rom(Rom::from_bytes(create_valid_lorom(0x20000)).unwrap());
+
+        bus.io.open_bus = 0x5A;
+        assert_eq!(bus.read(snes_addr!(0:0x6000), &mut ppu, &mut apu), 0x5A);
+        bus.write(snes_addr!(0x80:0x7FFF), 0x33, &mut ppu, &mut apu);
+        assert_eq!(bus.read(snes_addr!(0x3F:0x7FFF), &mut ppu, &mut apu), 0x33);
+
+        let mut buf = [0; 2];
+        bus.read_block(snes_addr!(0:0x6000), &mut buf, &mut ppu, &mut apu);
+        assert_eq!(buf, [0x33, 0x33]);
+    }
+
     #[test]
     fn test_rom_read_write_through_bus() {
         let (mut ppu, mut apu) = init_extern_components();
@@ -131,4 +423,185 @@ mod tests {
         bus.read(addr, &mut ppu, &mut apu);
         // bus.rom.read(addr);
     }
+
+    #[test]
+    fn test_block_read_write_through_bus() {
+        let (mut ppu, mut apu) = init_extern_components();
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, _dir) = create_temp_rom(&rom_data);
+        let mut bus = Bus::new(&rom_path).unwrap();
+
+        bus.write_block(snes_addr!(0:0x0100), &[1, 2, 3], &mut ppu, &mut apu);
+        assert_eq!(bus.read(snes_addr!(0x7E:0x0102), &mut ppu, &mut apu), 3);
+
+        let mut buf = [0; 3];
+        bus.read_block(snes_addr!(0x80:0x0100), &mut buf, &mut ppu, &mut apu);
+        assert_eq!(buf, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_block_across_regions_through_bus() {
+        let (mut ppu, mut apu) = init_extern_components();
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, _dir) = create_temp_rom(&rom_data);
+        let mut bus = Bus::new(&rom_path).unwrap();
+
+        bus.io.open_bus = 0x20;
+        bus.write(snes_addr!(0:0x1FFF), 0x42, &mut ppu, &mut apu);
+
+        // Last WRAM mirror byte, then the first I/O byte (open bus)
+        let mut buf = [0; 2];
+        bus.read_block(snes_addr!(0:0x1FFF), &mut buf, &mut ppu, &mut apu);
+        assert_eq!(buf, [0x42, 0x20]);
+    }
+
+    #[test]
+    fn test_access_speed() {
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, _dir) = create_temp_rom(&rom_data);
+        let bus = Bus::new(&rom_path).unwrap();
+
+        assert_eq!(bus.access_speed(snes_addr!(0:0x0010)), AccessSpeed::Slow);
+        assert_eq!(bus.access_speed(snes_addr!(0:0x2118)), AccessSpeed::Fast);
+        assert_eq!(bus.access_speed(snes_addr!(0:0x4016)), AccessSpeed::ExtraSlow);
+        assert_eq!(bus.access_speed(snes_addr!(0:0x4200)), AccessSpeed::Fast);
+        assert_eq!(bus.access_speed(snes_addr!(0x7E:0x0010)), AccessSpeed::Slow);
+        assert_eq!(bus.access_speed(snes_addr!(0x80:0x8000)), AccessSpeed::Slow);
+        assert_eq!(bus.access_speed(snes_addr!(0xC0:0x0000)), AccessSpeed::Slow);
+    }
+
+    #[test]
+    fn test_access_speed_fastrom() {
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, _dir) = create_temp_rom(&rom_data);
+        let mut bus = Bus::new(&rom_path).unwrap();
+        bus.io.memsel = 0x01;
+
+        // Only the upper mirror of ROM is fast
+        assert_eq!(bus.access_speed(snes_addr!(0x80:0x8000)), AccessSpeed::Fast);
+        assert_eq!(bus.access_speed(snes_addr!(0xC0:0x0000)), AccessSpeed::Fast);
+        assert_eq!(bus.access_speed(snes_addr!(0x00:0x8000)), AccessSpeed::Slow);
+        assert_eq!(bus.access_speed(snes_addr!(0x80:0x0010)), AccessSpeed::Slow);
+        assert_eq!(AccessSpeed::Fast.master_cycles(), 6);
+    }
+
+    #[cfg(feature = "access-log")]
+    #[test]
+    fn test_access_tap_sees_every_block_byte() {
+        use crate::access_log::{AccessKind, BusAccess};
+        use std::sync::{Arc, Mutex};
+
+        let (mut ppu, mut apu) = init_extern_components();
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, _dir) = create_temp_rom(&rom_data);
+        let mut bus = Bus::new(&rom_path).unwrap();
+
+        let accesses = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&accesses);
+        bus.access_tap
+            .set_consumer(move |access: &BusAccess| sink.lock().unwrap().push(*access));
+
+        bus.write_block(snes_addr!(0x7E:0x0100), &[1, 2], &mut ppu, &mut apu);
+        let mut buf = [0; 2];
+        bus.read_block(snes_addr!(0x7E:0x0100), &mut buf, &mut ppu, &mut apu);
+
+        let logged: Vec<_> = accesses
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|access| (access.kind, access.addr.addr, access.value))
+            .collect();
+        assert_eq!(
+            logged,
+            [
+                (AccessKind::Write, 0x0100, 1),
+                (AccessKind::Write, 0x0101, 2),
+                (AccessKind::Read, 0x0100, 1),
+                (AccessKind::Read, 0x0101, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dsp1_registered_from_header() {
+        let (mut ppu, mut apu) = init_extern_components();
+        let mut rom_data = create_valid_lorom(0x100000);
+        rom_data[LOROM_HEADER_OFFSET + HEADER_ROM_HARDWARE_OFFSET] = 0x03; // ROM + DSP
+        let (rom_path, _dir) = create_temp_rom(&rom_data);
+        let mut bus = Bus::new(&rom_path).unwrap();
+
+        assert_eq!(bus.coprocessor.as_ref().unwrap().name(), "DSP-1");
+
+        // Multiply 0.5 by 0.25 through the data register
+        for byte in [0x00, 0x00, 0x40, 0x00, 0x20] {
+            bus.write(snes_addr!(0x30:0x8000), byte, &mut ppu, &mut apu);
+        }
+        assert_eq!(bus.read(snes_addr!(0x30:0xC000), &mut ppu, &mut apu), 0x80);
+
+        let mut result = [0; 2];
+        bus.read_block(snes_addr!(0x30:0x8000), &mut result, &mut ppu, &mut apu);
+        assert_eq!(result, [0x00, 0x10]);
+    }
+
+    #[test]
+    fn test_no_coprocessor_without_header_flag() {
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, _dir) = create_temp_rom(&rom_data);
+        let bus = Bus::new(&rom_path).unwrap();
+
+        assert!(bus.coprocessor.is_none());
+    }
+
+    #[test]
+    fn test_srtc_data_saved_and_loaded() {
+        let mut rom_data = create_valid_lorom(0x20000);
+        rom_data[LOROM_HEADER_OFFSET + HEADER_ROM_HARDWARE_OFFSET] = 0x55; // ROM + S-RTC
+        let (rom_path, dir) = create_temp_rom(&rom_data);
+        let rtc_path = dir.path().join("game.rtc");
+
+        let mut bus = Bus::new(&rom_path).unwrap();
+        assert_eq!(bus.coprocessor.as_ref().unwrap().name(), "S-RTC");
+        bus.load_coprocessor_data(&rtc_path).unwrap();
+        assert!(!rtc_path.exists());
+
+        bus.coprocessor.as_mut().unwrap().load_save_data(&3600i64.to_le_bytes());
+        bus.save_coprocessor_data(&rtc_path).unwrap();
+
+        let mut reloaded = Bus::new(&rom_path).unwrap();
+        reloaded.load_coprocessor_data(&rtc_path).unwrap();
+        let data = reloaded.coprocessor.as_ref().unwrap().save_data().unwrap();
+        assert_eq!(i64::from_le_bytes(data.try_into().unwrap()), 3600);
+    }
+
+    #[test]
+    fn test_no_coprocessor_data_without_coprocessor() {
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, dir) = create_temp_rom(&rom_data);
+        let rtc_path = dir.path().join("game.rtc");
+
+        let bus = Bus::new(&rom_path).unwrap();
+        bus.save_coprocessor_data(&rtc_path).unwrap();
+        assert!(!rtc_path.exists());
+    }
+
+    #[test]
+    fn test_cheats_patch_reads_through_bus() {
+        let (mut ppu, mut apu) = init_extern_components();
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, _dir) = create_temp_rom(&rom_data);
+        let mut bus = Bus::new(&rom_path).unwrap();
+
+        bus.write(snes_addr!(0x7E:0x0DBE), 0x01, &mut ppu, &mut apu);
+        let id = bus.cheats.add("7E0DBE63").unwrap();
+
+        assert_eq!(bus.read(snes_addr!(0x00:0x0DBE), &mut ppu, &mut apu), 0x63);
+        assert_eq!(bus.read_unpatched(snes_addr!(0x7E:0x0DBE), &mut ppu, &mut apu), 0x01);
+
+        let mut buf = [0; 2];
+        bus.read_block(snes_addr!(0x7E:0x0DBD), &mut buf, &mut ppu, &mut apu);
+        assert_eq!(buf, [0x00, 0x63]);
+
+        bus.cheats.set_enabled(id, false);
+        assert_eq!(bus.read(snes_addr!(0x7E:0x0DBE), &mut ppu, &mut apu), 0x01);
+    }
 }
@@ -0,0 +1,368 @@
+use common::snes_address::SnesAddress;
+use std::fmt;
+
+/// Game Genie letters, in the order of the hex digit they stand for.
+const GAME_GENIE_ALPHABET: &str = "DF4709156BC8A23E";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CheatError {
+    /// The code matches none of the supported formats.
+    InvalidFormat(String),
+    /// The code contains a character its format does not allow.
+    InvalidCharacter(char),
+}
+
+impl std::error::Error for CheatError {}
+impl fmt::Display for CheatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheatError::InvalidFormat(code) => write!(
+                f,
+                "Unrecognized cheat code '{}' (expected XXXX-XXXX, AAAAAAVV or AAAAAA:VV[:CC])",
+                code
+            ),
+            CheatError::InvalidCharacter(c) => write!(f, "Invalid character '{}' in cheat code", c),
+        }
+    }
+}
+
+/// A decoded cheat: reads of `addr` return `value`, only when the real value
+/// equals `compare` if one is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheatPatch {
+    pub addr: SnesAddress,
+    pub value: u8,
+    pub compare: Option<u8>,
+}
+
+impl CheatPatch {
+    /// Decodes a cheat code. Supported formats:
+    /// - Game Genie: `XXXX-XXXX`, letters from `DF4709156BC8A23E`
+    /// - Pro Action Replay: `AAAAAAVV`, 24-bit address then value, in hex
+    /// - Raw: `AAAAAA:VV` or `AAAAAA:VV:CC` with a compare value, in hex
+    pub fn decode(code: &str) -> Result<Self, CheatError> {
+        let code = code.trim();
+        if code.contains(':') {
+            Self::decode_raw(code)
+        } else if code.len() == 9 && code.as_bytes()[4] == b'-' {
+            Self::decode_game_genie(code)
+        } else if code.len() == 8 {
+            Self::decode_pro_action_replay(code)
+        } else {
+            Err(CheatError::InvalidFormat(code.to_string()))
+        }
+    }
+
+    /// Game Genie codes encode `VVAAAAAA` with a substituted alphabet and the
+    /// address bits shuffled.
+    fn decode_game_genie(code: &str) -> Result<Self, CheatError> {
+        let mut data: u32 = 0;
+        for c in code.chars().filter(|&c| c != '-') {
+            let digit = GAME_GENIE_ALPHABET
+                .find(c.to_ascii_uppercase())
+                .ok_or(CheatError::InvalidCharacter(c))?;
+            data = (data << 4) | digit as u32;
+        }
+
+        let scrambled = data & 0xFFFFFF;
+        let addr = ((scrambled & 0x003C00) << 10)
+            | ((scrambled & 0x00003C) << 14)
+            | ((scrambled & 0xF00000) >> 8)
+            | ((scrambled & 0x000003) << 10)
+            | ((scrambled & 0x00C000) >> 6)
+            | ((scrambled & 0x0F0000) >> 12)
+            | ((scrambled & 0x0003C0) >> 6);
+
+        Ok(Self {
+            addr: SnesAddress::from(addr as usize),
+            value: (data >> 24) as u8,
+            compare: None,
+        })
+    }
+
+    fn decode_pro_action_replay(code: &str) -> Result<Self, CheatError> {
+        let data = parse_hex(code)?;
+        Ok(Self {
+            addr: SnesAddress::from((data >> 8) as usize),
+            value: data as u8,
+            compare: None,
+        })
+    }
+
+    fn decode_raw(code: &str) -> Result<Self, CheatError> {
+        let invalid = || CheatError::InvalidFormat(code.to_string());
+        let parts: Vec<&str> = code.split(':').collect();
+        let (addr, value, compare) = match parts.as_slice() {
+            [addr, value] => (addr, value, None),
+            [addr, value, compare] => (addr, value, Some(compare)),
+            _ => return Err(invalid()),
+        };
+        if addr.is_empty() || addr.len() > 6 || value.is_empty() || value.len() > 2 {
+            return Err(invalid());
+        }
+        let compare = match compare {
+            Some(c) if c.is_empty() || c.len() > 2 => return Err(invalid()),
+            Some(c) => Some(parse_hex(c)? as u8),
+            None => None,
+        };
+
+        Ok(Self {
+            addr: SnesAddress::from(parse_hex(addr)? as usize),
+            value: parse_hex(value)? as u8,
+            compare,
+        })
+    }
+}
+
+fn parse_hex(digits: &str) -> Result<u32, CheatError> {
+    digits.chars().try_fold(0u32, |acc, c| {
+        let digit = c.to_digit(16).ok_or(CheatError::InvalidCharacter(c))?;
+        Ok((acc << 4) | digit)
+    })
+}
+
+/// Handle to a cheat registered in [`Cheats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CheatId(usize);
+
+#[derive(Debug, Clone)]
+pub struct Cheat {
+    pub code: String,
+    pub patch: CheatPatch,
+    pub enabled: bool,
+}
+
+/// Active cheat codes, applied to bus reads.
+///
+/// A cheat matches its address and the mirrors of it: WRAM mirrored in the
+/// system area of banks `0x00–0x3F`/`0x80–0xBF`, and banks `0x80–0xFD`
+/// mirroring `0x00–0x7D`.
+#[derive(Debug, Default)]
+pub struct Cheats {
+    cheats: Vec<(CheatId, Cheat)>,
+    next_id: usize,
+}
+
+impl Cheats {
+    /// Decodes `code` and adds it, enabled.
+    pub fn add(&mut self, code: &str) -> Result<CheatId, CheatError> {
+        let patch = CheatPatch::decode(code)?;
+        let id = CheatId(self.next_id);
+        self.next_id += 1;
+        self.cheats.push((
+            id,
+            Cheat {
+                code: code.trim().to_string(),
+                patch,
+                enabled: true,
+            },
+        ));
+        Ok(id)
+    }
+
+    pub fn remove(&mut self, id: CheatId) -> Option<Cheat> {
+        let index = self.cheats.iter().position(|(cheat_id, _)| *cheat_id == id)?;
+        Some(self.cheats.remove(index).1)
+    }
+
+    /// Returns `false` if there is no cheat with this id.
+    pub fn set_enabled(&mut self, id: CheatId, enabled: bool) -> bool {
+        match self.cheats.iter_mut().find(|(cheat_id, _)| *cheat_id == id) {
+            Some((_, cheat)) => {
+                cheat.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, id: CheatId) -> Option<&Cheat> {
+        self.cheats.iter().find(|(cheat_id, _)| *cheat_id == id).map(|(_, cheat)| cheat)
+    }
+
+    /// Cheats in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (CheatId, &Cheat)> {
+        self.cheats.iter().map(|(id, cheat)| (*id, cheat))
+    }
+
+    pub fn clear(&mut self) {
+        self.cheats.clear();
+    }
+
+    fn enabled_patches(&self) -> impl Iterator<Item = &CheatPatch> {
+        self.cheats.iter().filter(|(_, cheat)| cheat.enabled).map(|(_, cheat)| &cheat.patch)
+    }
+
+    /// Value seen by a read of `addr` that returned `value` from memory.
+    pub fn apply(&self, addr: SnesAddress, value: u8) -> u8 {
+        let location = canonical(addr);
+        self.enabled_patches()
+            .filter(|patch| canonical(patch.addr) == location)
+            .fold(value, |value, patch| patch_value(patch, value))
+    }
+
+    /// Applies the cheats to `buf`, read from consecutive addresses starting at `addr`.
+    pub fn apply_block(&self, addr: SnesAddress, buf: &mut [u8]) {
+        let start = usize::from(canonical(addr));
+        for patch in self.enabled_patches() {
+            let Some(index) = usize::from(canonical(patch.addr)).checked_sub(start) else {
+                continue;
+            };
+            if let Some(byte) = buf.get_mut(index) {
+                *byte = patch_value(patch, *byte);
+            }
+        }
+    }
+}
+
+fn patch_value(patch: &CheatPatch, value: u8) -> u8 {
+    match patch.compare {
+        Some(compare) if compare != value => value,
+        _ => patch.value,
+    }
+}
+
+/// Maps mirrored addresses to a single representative.
+fn canonical(addr: SnesAddress) -> SnesAddress {
+    let bank = match addr.bank {
+        0x00..=0x3F | 0x80..=0xBF if addr.addr < 0x2000 => 0x7E,
+        0x80..=0xFD => addr.bank & 0x7F,
+        bank => bank,
+    };
+    SnesAddress { bank, addr: addr.addr }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::snes_address::snes_addr;
+
+    fn patch(addr: SnesAddress, value: u8, compare: Option<u8>) -> CheatPatch {
+        CheatPatch { addr, value, compare }
+    }
+
+    // Decoding
+
+    #[test]
+    fn test_decode_game_genie() {
+        assert_eq!(CheatPatch::decode("DD62-3B1D"), Ok(patch(snes_addr!(0xA8:0x83D5), 0x00, None)));
+        assert_eq!(CheatPatch::decode("C264-64D7"), Ok(patch(snes_addr!(0x00:0x8E28), 0xAD, None)));
+        assert_eq!(CheatPatch::decode("1df4-7091"), Ok(patch(snes_addr!(0xD5:0x1821), 0x60, None)));
+    }
+
+    #[test]
+    fn test_game_genie_address_bits_are_a_permutation() {
+        // Every scrambled address bit must land on a distinct real bit
+        let mut seen = 0u32;
+        for bit in 0..24 {
+            let mut data = 1u32 << bit;
+            let mut code = String::new();
+            for _ in 0..8 {
+                code.insert(0, GAME_GENIE_ALPHABET.as_bytes()[(data & 0xF) as usize] as char);
+                data >>= 4;
+            }
+            code.insert(4, '-');
+            let addr = usize::from(CheatPatch::decode(&code).unwrap().addr) as u32;
+            assert_eq!(addr.count_ones(), 1, "bit {}", bit);
+            seen |= addr;
+        }
+        assert_eq!(seen, 0xFFFFFF);
+    }
+
+    #[test]
+    fn test_decode_pro_action_replay() {
+        assert_eq!(CheatPatch::decode("7E0DBE05"), Ok(patch(snes_addr!(0x7E:0x0DBE), 0x05, None)));
+        assert_eq!(CheatPatch::decode(" 00812aff "), Ok(patch(snes_addr!(0x00:0x812A), 0xFF, None)));
+    }
+
+    #[test]
+    fn test_decode_raw() {
+        assert_eq!(CheatPatch::decode("7E1234:99"), Ok(patch(snes_addr!(0x7E:0x1234), 0x99, None)));
+        assert_eq!(CheatPatch::decode("C08000:EA:A9"), Ok(patch(snes_addr!(0xC0:0x8000), 0xEA, Some(0xA9))));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(CheatPatch::decode("123"), Err(CheatError::InvalidFormat("123".to_string())));
+        assert_eq!(CheatPatch::decode("DD62-3B1X"), Err(CheatError::InvalidCharacter('X')));
+        assert_eq!(CheatPatch::decode("7E0DBG05"), Err(CheatError::InvalidCharacter('G')));
+        assert!(matches!(CheatPatch::decode("7E1234:999"), Err(CheatError::InvalidFormat(_))));
+        assert!(matches!(CheatPatch::decode("7E1234:99:1:2"), Err(CheatError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_error_display() {
+        let msg = format!("{}", CheatError::InvalidCharacter('Z'));
+        assert_eq!(msg, "Invalid character 'Z' in cheat code");
+    }
+
+    // Management
+
+    #[test]
+    fn test_add_remove_enable() {
+        let mut cheats = Cheats::default();
+        let a = cheats.add("7E0DBE05").unwrap();
+        let b = cheats.add("7E0DBF06").unwrap();
+        assert_ne!(a, b);
+        assert!(cheats.add("nope").is_err());
+        assert_eq!(cheats.iter().count(), 2);
+
+        assert!(cheats.set_enabled(a, false));
+        assert!(!cheats.get(a).unwrap().enabled);
+
+        assert_eq!(cheats.remove(a).unwrap().code, "7E0DBE05");
+        assert!(cheats.remove(a).is_none());
+        assert!(!cheats.set_enabled(a, true));
+        assert_eq!(cheats.iter().map(|(id, _)| id).collect::<Vec<_>>(), vec![b]);
+
+        cheats.clear();
+        assert_eq!(cheats.iter().count(), 0);
+    }
+
+    // Applying
+
+    #[test]
+    fn test_apply_replaces_value() {
+        let mut cheats = Cheats::default();
+        let id = cheats.add("7E0DBE05").unwrap();
+
+        assert_eq!(cheats.apply(snes_addr!(0x7E:0x0DBE), 0x01), 0x05);
+        assert_eq!(cheats.apply(snes_addr!(0x7E:0x0DBF), 0x01), 0x01);
+
+        cheats.set_enabled(id, false);
+        assert_eq!(cheats.apply(snes_addr!(0x7E:0x0DBE), 0x01), 0x01);
+    }
+
+    #[test]
+    fn test_apply_with_compare() {
+        let mut cheats = Cheats::default();
+        cheats.add("C08000:EA:A9").unwrap();
+
+        assert_eq!(cheats.apply(snes_addr!(0xC0:0x8000), 0xA9), 0xEA);
+        assert_eq!(cheats.apply(snes_addr!(0xC0:0x8000), 0x00), 0x00);
+    }
+
+    #[test]
+    fn test_apply_matches_mirrors() {
+        let mut cheats = Cheats::default();
+        cheats.add("7E0010:42").unwrap();
+        cheats.add("008123:24").unwrap();
+
+        assert_eq!(cheats.apply(snes_addr!(0x00:0x0010), 0), 0x42);
+        assert_eq!(cheats.apply(snes_addr!(0x80:0x0010), 0), 0x42);
+        assert_eq!(cheats.apply(snes_addr!(0x80:0x8123), 0), 0x24);
+        assert_eq!(cheats.apply(snes_addr!(0x7F:0x0010), 0), 0);
+    }
+
+    #[test]
+    fn test_apply_block() {
+        let mut cheats = Cheats::default();
+        cheats.add("7E0102:AA").unwrap();
+        cheats.add("7E0100:BB:FF").unwrap();
+        cheats.add("7E0200:CC").unwrap();
+
+        let mut buf = [0u8; 4];
+        cheats.apply_block(snes_addr!(0x00:0x0100), &mut buf);
+        assert_eq!(buf, [0, 0, 0xAA, 0]);
+    }
+}
@@ -0,0 +1,595 @@
+//! High-level emulation of the DSP-1 math coprocessor (Pilotwings, Super Mario Kart...).
+//!
+//! The DSP-1 is a NEC µPD7725 running fixed firmware. Games talk to it through two
+//! registers: they write a command byte and its 16-bit parameters to the data register,
+//! then read the 16-bit results back from it. The status register always reports the
+//! DSP-1 as ready, since commands are computed instantly.
+//!
+//! Arithmetic, trigonometry and coordinate transform commands are implemented, computing
+//! sines with floating point rather than the firmware tables, so results can be off by one.
+//! The projection commands (parameter, raster, project, target) and gyrate follow the
+//! protocol but return zeros for now.
+
+use crate::coprocessor::Coprocessor;
+use crate::rom::Rom;
+use crate::rom::header::mapping_mode::MappingMode;
+use common::save_state::{StateError, StateReader, StateWriter};
+use common::snes_address::SnesAddress;
+use std::f64::consts::PI;
+
+/// Largest LoROM cartridge using the 1 MiB DSP-1 board
+const LOROM_1MB_MAX_SIZE: usize = 0x10_0000;
+/// Number of words returned by the memory dump command
+const MEMORY_DUMP_LEN: usize = 1024;
+const MEMORY_SIZE: i16 = 0x0100;
+
+/// Where the DSP-1 registers are mapped, depending on the cartridge board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dsp1Mapping {
+    /// Banks $30–$3F: data at $8000–$BFFF, status at $C000–$FFFF
+    LoRom1Mb,
+    /// Banks $60–$6F: data at $0000–$3FFF, status at $4000–$7FFF
+    LoRom2Mb,
+    /// Banks $00–$1F: data at $6000–$6FFF, status at $7000–$7FFF
+    HiRom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    Data,
+    Status,
+}
+
+impl Dsp1Mapping {
+    /// Register at `addr`, mirrored in banks $80–$FF.
+    fn register(self, addr: SnesAddress) -> Option<Register> {
+        let bank = addr.bank & 0x7F;
+        let (data, status) = match self {
+            Self::LoRom1Mb if (0x30..=0x3F).contains(&bank) => (0x8000..0xC000, 0xC000..=0xFFFF),
+            Self::LoRom2Mb if (0x60..=0x6F).contains(&bank) => (0x0000..0x4000, 0x4000..=0x7FFF),
+            Self::HiRom if bank <= 0x1F => (0x6000..0x7000, 0x7000..=0x7FFF),
+            _ => return None,
+        };
+        if data.contains(&addr.addr) {
+            Some(Register::Data)
+        } else if status.contains(&addr.addr) {
+            Some(Register::Status)
+        } else {
+            None
+        }
+    }
+}
+
+/// DSP-1 operations; the 3 attitude matrices are indexed 0 (A) to 2 (C).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Multiply,
+    Multiply2,
+    Inverse,
+    Triangle,
+    Radius,
+    Range,
+    Range2,
+    Distance,
+    Rotate,
+    Polar,
+    Attitude(usize),
+    Objective(usize),
+    Subjective(usize),
+    Scalar(usize),
+    MemoryTest,
+    MemorySize,
+    MemoryDump,
+    /// Projection commands and gyrate, which return zeros
+    Unimplemented,
+}
+
+impl Op {
+    /// Operation of a command byte with its number of parameters and results.
+    fn decode(command: u8) -> Option<(Self, usize, usize)> {
+        let matrix = match (command >> 4) & 3 {
+            3 => 0,
+            n => n as usize,
+        };
+        let decoded = match command {
+            0x00 => (Self::Multiply, 2, 1),
+            0x20 => (Self::Multiply2, 2, 1),
+            0x10 | 0x30 => (Self::Inverse, 2, 2),
+            0x04 | 0x24 => (Self::Triangle, 2, 2),
+            0x08 => (Self::Radius, 3, 2),
+            0x18 => (Self::Range, 4, 1),
+            0x38 => (Self::Range2, 4, 1),
+            0x28 => (Self::Distance, 3, 1),
+            0x0C | 0x2C => (Self::Rotate, 3, 2),
+            0x1C | 0x3C => (Self::Polar, 6, 3),
+            0x01 | 0x05 | 0x11 | 0x15 | 0x21 | 0x25 | 0x31 | 0x35 => (Self::Attitude(matrix), 4, 0),
+            0x09 | 0x0D | 0x19 | 0x1D | 0x29 | 0x2D | 0x39 | 0x3D => (Self::Objective(matrix), 3, 3),
+            0x03 | 0x13 | 0x23 | 0x33 => (Self::Subjective(matrix), 3, 3),
+            0x0B | 0x1B | 0x2B | 0x3B => (Self::Scalar(matrix), 3, 1),
+            0x07 | 0x0F => (Self::MemoryTest, 1, 1),
+            0x27 | 0x2F => (Self::MemorySize, 1, 1),
+            0x17 | 0x1F | 0x37 | 0x3F => (Self::MemoryDump, 1, MEMORY_DUMP_LEN),
+            0x02 | 0x12 | 0x22 | 0x32 => (Self::Unimplemented, 7, 4), // Parameter
+            0x0A | 0x1A | 0x2A | 0x3A => (Self::Unimplemented, 1, 4), // Raster
+            0x06 | 0x16 | 0x26 | 0x36 => (Self::Unimplemented, 3, 3), // Project
+            0x0E | 0x1E | 0x2E | 0x3E => (Self::Unimplemented, 2, 2), // Target
+            0x14 | 0x34 => (Self::Unimplemented, 6, 3),               // Gyrate
+            _ => return None,
+        };
+        Some(decoded)
+    }
+}
+
+type Matrix = [[i16; 3]; 3];
+
+pub struct Dsp1 {
+    mapping: Dsp1Mapping,
+    /// Command byte being fed with parameters, `None` while waiting for a command
+    command: Option<u8>,
+    parameters: Vec<i16>,
+    /// First byte of a parameter being written
+    low_byte: Option<u8>,
+    /// Bytes of the last command's results, read in order
+    results: Vec<u8>,
+    result_index: usize,
+    matrices: [Matrix; 3],
+}
+
+impl Dsp1 {
+    pub fn new(mapping: Dsp1Mapping) -> Self {
+        Self {
+            mapping,
+            command: None,
+            parameters: Vec::new(),
+            low_byte: None,
+            results: Vec::new(),
+            result_index: 0,
+            matrices: [[[0; 3]; 3]; 3],
+        }
+    }
+
+    /// DSP-1 wired the way the board of `rom` does.
+    pub fn for_rom(rom: &Rom) -> Self {
+        let mapping = match rom.map {
+            MappingMode::HiRom => Dsp1Mapping::HiRom,
+            MappingMode::LoRom if rom.data.len() <= LOROM_1MB_MAX_SIZE => Dsp1Mapping::LoRom1Mb,
+            MappingMode::LoRom => Dsp1Mapping::LoRom2Mb,
+        };
+        Self::new(mapping)
+    }
+
+    pub fn mapping(&self) -> Dsp1Mapping {
+        self.mapping
+    }
+
+    fn read_data(&mut self) -> u8 {
+        match self.results.get(self.result_index) {
+            Some(&byte) => {
+                self.result_index += 1;
+                byte
+            }
+            None => 0xFF,
+        }
+    }
+
+    fn write_data(&mut self, value: u8) {
+        let Some((op, parameter_count, result_count)) = self.command.and_then(Op::decode) else {
+            // Bytes which are not commands are ignored while waiting for one
+            self.command = Op::decode(value).map(|_| value);
+            self.parameters.clear();
+            self.low_byte = None;
+            return;
+        };
+
+        let Some(low) = self.low_byte.take() else {
+            self.low_byte = Some(value);
+            return;
+        };
+        self.parameters.push(i16::from_le_bytes([low, value]));
+
+        if self.parameters.len() == parameter_count {
+            let mut results = self.execute(op);
+            results.resize(result_count, 0);
+            self.results = results.iter().flat_map(|r| r.to_le_bytes()).collect();
+            self.result_index = 0;
+            self.command = None;
+        }
+    }
+
+    fn execute(&mut self, op: Op) -> Vec<i16> {
+        let p = &self.parameters;
+        match op {
+            Op::Multiply => vec![mul(p[0], p[1])],
+            Op::Multiply2 => vec![mul(p[0], p[1]).wrapping_add(1)],
+            Op::Inverse => {
+                let (coefficient, exponent) = inverse(p[0], p[1]);
+                vec![coefficient, exponent]
+            }
+            Op::Triangle => vec![mul(sin(p[0]), p[1]), mul(cos(p[0]), p[1])],
+            Op::Radius => {
+                let size = (square_sum(p[0], p[1], p[2]) << 1) as u64;
+                vec![size as u16 as i16, (size >> 16) as u16 as i16]
+            }
+            Op::Range => vec![range(p)],
+            Op::Range2 => vec![range(p).wrapping_add(1)],
+            Op::Distance => vec![distance(p[0], p[1], p[2])],
+            Op::Rotate => {
+                let (x, y) = rotate(p[0], p[1], p[2]);
+                vec![x, y]
+            }
+            Op::Polar => {
+                let (az, ay, ax) = (p[0], p[1], p[2]);
+                let (x, y) = rotate(az, p[3], p[4]);
+                let (z, x) = rotate(ay, p[5], x);
+                let (y, z) = rotate(ax, y, z);
+                vec![x, y, z]
+            }
+            Op::Attitude(m) => {
+                self.matrices[m] = attitude(p[0], p[1], p[2], p[3]);
+                vec![]
+            }
+            Op::Objective(m) => {
+                let matrix = &self.matrices[m];
+                (0..3).map(|row| dot(p, |i| matrix[row][i])).collect()
+            }
+            Op::Subjective(m) => {
+                let matrix = &self.matrices[m];
+                (0..3).map(|column| dot(p, |i| matrix[i][column])).collect()
+            }
+            Op::Scalar(m) => {
+                let matrix = &self.matrices[m];
+                vec![dot(p, |i| matrix[0][i])]
+            }
+            Op::MemoryTest => vec![0],
+            Op::MemorySize => vec![MEMORY_SIZE],
+            Op::MemoryDump | Op::Unimplemented => vec![],
+        }
+    }
+}
+
+impl Coprocessor for Dsp1 {
+    fn name(&self) -> &'static str {
+        "DSP-1"
+    }
+
+    fn maps(&self, addr: SnesAddress) -> bool {
+        self.mapping.register(addr).is_some()
+    }
+
+    fn read(&mut self, addr: SnesAddress) -> u8 {
+        match self.mapping.register(addr) {
+            Some(Register::Data) => self.read_data(),
+            // RQM: ready for the next transfer
+            Some(Register::Status) => 0x80,
+            None => 0xFF,
+        }
+    }
+
+    fn write(&mut self, addr: SnesAddress, value: u8) {
+        // The status register is read-only
+        if self.mapping.register(addr) == Some(Register::Data) {
+            self.write_data(value);
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.mapping);
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.bool(self.command.is_some());
+        writer.u8(self.command.unwrap_or(0));
+        writer.u8(self.parameters.len() as u8);
+        for &parameter in &self.parameters {
+            writer.u16(parameter as u16);
+        }
+        writer.bool(self.low_byte.is_some());
+        writer.u8(self.low_byte.unwrap_or(0));
+        writer.u32(self.results.len() as u32);
+        writer.bytes(&self.results);
+        writer.u32(self.result_index as u32);
+        for &value in self.matrices.iter().flatten().flatten() {
+            writer.u16(value as u16);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        let has_command = reader.bool()?;
+        let command = reader.u8()?;
+        self.command = has_command.then_some(command);
+        let parameter_count = match self.command.map(Op::decode) {
+            Some(Some((_, count, _))) => count,
+            Some(None) => return Err(reader.corrupt()),
+            None => 0,
+        };
+        let len = reader.u8()? as usize;
+        if len > parameter_count {
+            return Err(reader.corrupt());
+        }
+        self.parameters = (0..len)
+            .map(|_| reader.u16().map(|parameter| parameter as i16))
+            .collect::<Result<_, _>>()?;
+        let has_low_byte = reader.bool()?;
+        let low_byte = reader.u8()?;
+        self.low_byte = has_low_byte.then_some(low_byte);
+        let len = reader.u32()? as usize;
+        self.results = reader.bytes(len)?.to_vec();
+        self.result_index = reader.u32()? as usize;
+        for value in self.matrices.iter_mut().flatten().flatten() {
+            *value = reader.u16()? as i16;
+        }
+        Ok(())
+    }
+}
+
+/// 1.15 fixed point multiplication
+fn mul(a: i16, b: i16) -> i16 {
+    ((a as i32 * b as i32) >> 15) as i16
+}
+
+/// Sine of a 16-bit angle (0x10000 is a full turn), in 1.15 fixed point
+fn sin(angle: i16) -> i16 {
+    ((angle as f64 * PI / 32768.0).sin() * 32767.0).round() as i16
+}
+
+fn cos(angle: i16) -> i16 {
+    ((angle as f64 * PI / 32768.0).cos() * 32767.0).round() as i16
+}
+
+fn square_sum(x: i16, y: i16, z: i16) -> i64 {
+    let (x, y, z) = (x as i64, y as i64, z as i64);
+    x * x + y * y + z * z
+}
+
+fn range(p: &[i16]) -> i16 {
+    let r = p[3] as i64;
+    ((square_sum(p[0], p[1], p[2]) - r * r) >> 15) as i16
+}
+
+fn distance(x: i16, y: i16, z: i16) -> i16 {
+    (square_sum(x, y, z) as f64).sqrt().min(i16::MAX as f64) as i16
+}
+
+/// Rotates `(x, y)` by `angle`
+fn rotate(angle: i16, x: i16, y: i16) -> (i16, i16) {
+    let (s, c) = (sin(angle), cos(angle));
+    (mul(y, s).wrapping_add(mul(x, c)), mul(y, c).wrapping_sub(mul(x, s)))
+}
+
+/// Inverse of `coefficient * 2^exponent`, as a normalized coefficient and an exponent
+fn inverse(coefficient: i16, exponent: i16) -> (i16, i16) {
+    if coefficient == 0 {
+        return (0x7FFF, 0x002F);
+    }
+
+    let mut mantissa = (coefficient as f64 / 32768.0).abs();
+    let mut exponent = exponent as i32;
+    while mantissa < 0.5 {
+        mantissa *= 2.0;
+        exponent -= 1;
+    }
+
+    // 1 / mantissa is in (1, 2]: halve it into (0.5, 1]
+    let mut inverse = ((1.0 / mantissa / 2.0) * 32768.0).round() as i32;
+    let mut inverse_exponent = 1 - exponent;
+    if inverse >= 0x8000 {
+        inverse = 0x4000;
+        inverse_exponent += 1;
+    }
+    let inverse = if coefficient < 0 { -inverse } else { inverse };
+    (inverse as i16, inverse_exponent as i16)
+}
+
+/// Rotation matrix for the angles around Z, Y and X, scaled by `scale`
+fn attitude(scale: i16, z: i16, y: i16, x: i16) -> Matrix {
+    let (sin_z, cos_z) = (sin(z), cos(z));
+    let (sin_y, cos_y) = (sin(y), cos(y));
+    let (sin_x, cos_x) = (sin(x), cos(x));
+    let scaled_sin_z = mul(scale, sin_z);
+    let scaled_cos_z = mul(scale, cos_z);
+
+    [
+        [mul(scaled_cos_z, cos_y), -mul(scaled_sin_z, cos_y), mul(scale, sin_y)],
+        [
+            mul(scaled_sin_z, cos_x).wrapping_add(mul(mul(scaled_cos_z, sin_x), sin_y)),
+            mul(scaled_cos_z, cos_x).wrapping_sub(mul(mul(scaled_sin_z, sin_x), sin_y)),
+            -mul(mul(scale, sin_x), cos_y),
+        ],
+        [
+            mul(scaled_sin_z, sin_x).wrapping_sub(mul(mul(scaled_cos_z, cos_x), sin_y)),
+            mul(scaled_cos_z, sin_x).wrapping_add(mul(mul(scaled_sin_z, cos_x), sin_y)),
+            mul(mul(scale, cos_x), cos_y),
+        ],
+    ]
+}
+
+/// Fixed point dot product of the 3 first parameters with a matrix row or column
+fn dot(p: &[i16], element: impl Fn(usize) -> i16) -> i16 {
+    ((0..3).map(|i| p[i] as i32 * element(i) as i32).sum::<i32>() >> 15) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::save_state::SaveState;
+    use common::snes_address::snes_addr;
+
+    const DATA: SnesAddress = snes_addr!(0x30:0x8000);
+    const STATUS: SnesAddress = snes_addr!(0x30:0xC000);
+
+    /// Sends a command with its parameters, then reads `result_count` results.
+    fn run(dsp: &mut Dsp1, command: u8, parameters: &[i16], result_count: usize) -> Vec<i16> {
+        dsp.write(DATA, command);
+        for parameter in parameters {
+            for byte in parameter.to_le_bytes() {
+                dsp.write(DATA, byte);
+            }
+        }
+        (0..result_count)
+            .map(|_| i16::from_le_bytes([dsp.read(DATA), dsp.read(DATA)]))
+            .collect()
+    }
+
+    fn dsp() -> Dsp1 {
+        Dsp1::new(Dsp1Mapping::LoRom1Mb)
+    }
+
+    // ============================================================
+    // Mapping
+    // ============================================================
+
+    #[test]
+    fn test_lorom_1mb_mapping() {
+        let dsp = dsp();
+        assert!(dsp.maps(snes_addr!(0x30:0x8000)));
+        assert!(dsp.maps(snes_addr!(0xBF:0xFFFF)));
+        assert!(!dsp.maps(snes_addr!(0x2F:0x8000)));
+        assert!(!dsp.maps(snes_addr!(0x30:0x7FFF)));
+        assert_eq!(dsp.mapping.register(snes_addr!(0x3F:0xBFFF)), Some(Register::Data));
+        assert_eq!(dsp.mapping.register(snes_addr!(0x3F:0xC000)), Some(Register::Status));
+    }
+
+    #[test]
+    fn test_lorom_2mb_and_hirom_mapping() {
+        let lorom = Dsp1Mapping::LoRom2Mb;
+        assert_eq!(lorom.register(snes_addr!(0x60:0x0000)), Some(Register::Data));
+        assert_eq!(lorom.register(snes_addr!(0xEF:0x4000)), Some(Register::Status));
+        assert_eq!(lorom.register(snes_addr!(0x60:0x8000)), None);
+
+        let hirom = Dsp1Mapping::HiRom;
+        assert_eq!(hirom.register(snes_addr!(0x00:0x6000)), Some(Register::Data));
+        assert_eq!(hirom.register(snes_addr!(0x9F:0x7000)), Some(Register::Status));
+        assert_eq!(hirom.register(snes_addr!(0x20:0x6000)), None);
+        assert_eq!(hirom.register(snes_addr!(0x00:0x8000)), None);
+    }
+
+    // ============================================================
+    // Protocol
+    // ============================================================
+
+    #[test]
+    fn test_status_always_ready() {
+        let mut dsp = dsp();
+        assert_eq!(dsp.read(STATUS), 0x80);
+        dsp.write(STATUS, 0x00);
+        dsp.write(DATA, 0x00);
+        assert_eq!(dsp.read(STATUS), 0x80);
+    }
+
+    #[test]
+    fn test_results_read_once() {
+        let mut dsp = dsp();
+        assert_eq!(run(&mut dsp, 0x0F, &[0], 1), vec![0]);
+        assert_eq!(dsp.read(DATA), 0xFF);
+    }
+
+    #[test]
+    fn test_invalid_command_ignored() {
+        let mut dsp = dsp();
+        dsp.write(DATA, 0x80);
+        assert_eq!(run(&mut dsp, 0x2F, &[0], 1), vec![MEMORY_SIZE]);
+    }
+
+    #[test]
+    fn test_memory_dump_length() {
+        let mut dsp = dsp();
+        let results = run(&mut dsp, 0x1F, &[0], MEMORY_DUMP_LEN);
+        assert_eq!(results.len(), MEMORY_DUMP_LEN);
+        assert_eq!(dsp.read(DATA), 0xFF);
+    }
+
+    #[test]
+    fn test_unimplemented_command_returns_zeros() {
+        let mut dsp = dsp();
+        assert_eq!(run(&mut dsp, 0x02, &[1, 2, 3, 4, 5, 6, 7], 4), vec![0; 4]);
+        assert_eq!(run(&mut dsp, 0x00, &[0x4000, 0x4000], 1), vec![0x2000]);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut dsp = dsp();
+        dsp.write(DATA, 0x00);
+        dsp.write(DATA, 0x12);
+        dsp.reset();
+        assert_eq!(run(&mut dsp, 0x00, &[0x4000, 0x2000], 1), vec![0x1000]);
+    }
+
+    // ============================================================
+    // Commands
+    // ============================================================
+
+    #[test]
+    fn test_multiply() {
+        let mut dsp = dsp();
+        assert_eq!(run(&mut dsp, 0x00, &[0x4000, -0x4000], 1), vec![-0x2000]);
+        assert_eq!(run(&mut dsp, 0x20, &[0x4000, 0x4000], 1), vec![0x2001]);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let mut dsp = dsp();
+        // 1/2 -> 2 = 0.5 * 2^2
+        assert_eq!(run(&mut dsp, 0x10, &[0x4000, 0], 2), vec![0x4000, 2]);
+        // -1/4 -> -4
+        assert_eq!(run(&mut dsp, 0x10, &[-0x4000, -1], 2), vec![-0x4000, 3]);
+        assert_eq!(run(&mut dsp, 0x10, &[0, 0], 2), vec![0x7FFF, 0x002F]);
+    }
+
+    #[test]
+    fn test_triangle() {
+        let mut dsp = dsp();
+        // 90 degrees
+        assert_eq!(run(&mut dsp, 0x04, &[0x4000, 0x7FFF], 2), vec![0x7FFE, 0]);
+        assert_eq!(run(&mut dsp, 0x04, &[0, 0x1000], 2), vec![0, 0x0FFF]);
+    }
+
+    #[test]
+    fn test_radius_range_distance() {
+        let mut dsp = dsp();
+        assert_eq!(run(&mut dsp, 0x08, &[0x100, 0x100, 0x100], 2), vec![0, 0x0006]);
+        assert_eq!(run(&mut dsp, 0x18, &[0x4000, 0, 0, 0x2000], 1), vec![0x1800]);
+        assert_eq!(run(&mut dsp, 0x38, &[0x4000, 0, 0, 0x2000], 1), vec![0x1801]);
+        assert_eq!(run(&mut dsp, 0x28, &[3, 4, 0], 1), vec![5]);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut dsp = dsp();
+        assert_eq!(run(&mut dsp, 0x0C, &[0, 100, 200], 2), vec![99, 199]);
+        // 90 degrees: (x, y) -> (y, -x)
+        assert_eq!(run(&mut dsp, 0x0C, &[0x4000, 0x1000, 0], 2), vec![0, -0x0FFF]);
+    }
+
+    #[test]
+    fn test_attitude_objective_subjective() {
+        let mut dsp = dsp();
+        // Identity matrix A with a scale of 1/2
+        assert!(run(&mut dsp, 0x01, &[0x4000, 0, 0, 0], 0).is_empty());
+        assert_eq!(run(&mut dsp, 0x0D, &[0x1000, 0x2000, -0x1000], 3), vec![0x07FF, 0x0FFF, -0x0800]);
+        assert_eq!(run(&mut dsp, 0x03, &[0x1000, 0x2000, -0x1000], 3), vec![0x07FF, 0x0FFF, -0x0800]);
+        assert_eq!(run(&mut dsp, 0x0B, &[0x1000, 0x2000, -0x1000], 1), vec![0x07FF]);
+
+        // Matrix B is independent
+        assert_eq!(run(&mut dsp, 0x1D, &[0x1000, 0x2000, -0x1000], 3), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_save_state_mid_command() {
+        let mut dsp = dsp();
+        run(&mut dsp, 0x01, &[0x4000, 0, 0, 0], 0);
+        // Objective command with one parameter and a half sent
+        for byte in [0x0D, 0x00, 0x10, 0x00] {
+            dsp.write(DATA, byte);
+        }
+        let mut state = SaveState::new();
+        state.save(&(Box::new(dsp) as Box<dyn Coprocessor>));
+
+        let mut loaded: Box<dyn Coprocessor> = Box::new(self::dsp());
+        state.load(&mut loaded).unwrap();
+        for byte in [0x20, 0x00, 0xF0] {
+            loaded.write(DATA, byte);
+        }
+        let results: Vec<u8> = (0..6).map(|_| loaded.read(DATA)).collect();
+        assert_eq!(results, [0xFF, 0x07, 0xFF, 0x0F, 0x00, 0xF8]);
+    }
+}
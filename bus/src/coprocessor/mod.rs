@@ -0,0 +1,126 @@
+//! Cartridge coprocessors (DSP-1, SA-1, SPC7110...).
+//!
+//! A coprocessor is registered with the [`Bus`](crate::Bus), which routes the accesses it maps
+//! to it before the regular memory regions, and is clocked by the emulation driver.
+
+pub mod dsp1;
+pub mod srtc;
+
+use crate::rom::Rom;
+use crate::rom::header::cartridge_hardware;
+use common::save_state::{StateError, StateReader, StateWriter, Stateful, Tag};
+use common::snes_address::SnesAddress;
+use dsp1::Dsp1;
+use srtc::Srtc;
+
+/// A cartridge chip mapped next to the ROM.
+///
+/// Coprocessors are owned by the [`crate::bus::Bus`], which must stay `Send` so that a console
+/// can be handed to another thread.
+pub trait Coprocessor: Send {
+    fn name(&self) -> &'static str;
+
+    /// Whether the coprocessor handles accesses to `addr`, instead of the ROM or I/O.
+    fn maps(&self, addr: SnesAddress) -> bool;
+
+    fn read(&mut self, addr: SnesAddress) -> u8;
+
+    fn write(&mut self, addr: SnesAddress, value: u8);
+
+    /// Runs the coprocessor for `master_cycles` master clock cycles.
+    ///
+    /// High-level emulated coprocessors complete every command instantly and ignore it.
+    fn step(&mut self, _master_cycles: u32) {}
+
+    fn reset(&mut self) {}
+
+    /// State to keep alongside the save file (e.g. a clock offset), if any.
+    fn save_data(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Restores the state returned by [`Self::save_data`].
+    fn load_save_data(&mut self, _data: &[u8]) {}
+
+    /// Extension of the file next to the ROM that [`Self::save_data`] is kept in.
+    fn save_data_extension(&self) -> &'static str {
+        "sav"
+    }
+
+    /// Runs the coprocessor to a point where [`Self::save_state`] captures its whole state,
+    /// e.g. the end of the instruction its CPU is in, before a save state is taken.
+    fn prepare_save_state(&mut self) {}
+
+    /// Writes the state of the coprocessor to a save state. The ROM is not saved.
+    fn save_state(&self, _writer: &mut StateWriter) {}
+
+    /// Restores the state written by [`Self::save_state`].
+    fn load_state(&mut self, _reader: &mut StateReader) -> Result<(), StateError> {
+        Ok(())
+    }
+}
+
+/// State of the coprocessor of the cartridge, which the save state is loaded back with.
+impl Stateful for Box<dyn Coprocessor> {
+    const TAG: Tag = *b"COPR";
+    const VERSION: u16 = 1;
+
+    fn save(&self, writer: &mut StateWriter) {
+        self.as_ref().save_state(writer);
+    }
+
+    fn load(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.as_mut().load_state(reader)
+    }
+}
+
+/// Creates the coprocessor declared in the ROM header, if it is supported.
+pub fn for_rom(rom: &Rom) -> Option<Box<dyn Coprocessor>> {
+    let hardware = &rom.header.hardware;
+    if !hardware.has_coprocessor() {
+        return None;
+    }
+    match hardware.coprocessor? {
+        cartridge_hardware::Coprocessor::DSP(_) => Some(Box::new(Dsp1::for_rom(rom))),
+        cartridge_hardware::Coprocessor::SRTC => Some(Box::new(Srtc::new())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{HEADER_ROM_HARDWARE_OFFSET, LOROM_HEADER_OFFSET};
+    use crate::rom::test_rom::*;
+
+    fn rom_with_hardware(hardware: u8) -> Rom {
+        let mut data = create_valid_lorom(0x20000);
+        data[LOROM_HEADER_OFFSET + HEADER_ROM_HARDWARE_OFFSET] = hardware;
+        Rom::from_bytes(data).unwrap()
+    }
+
+    #[test]
+    fn test_for_rom_dsp1() {
+        let coprocessor = for_rom(&rom_with_hardware(0x03)).unwrap();
+        assert_eq!(coprocessor.name(), "DSP-1");
+    }
+
+    #[test]
+    fn test_for_rom_srtc() {
+        let coprocessor = for_rom(&rom_with_hardware(0x55)).unwrap();
+        assert_eq!(coprocessor.name(), "S-RTC");
+        assert!(coprocessor.save_data().is_some());
+    }
+
+    #[test]
+    fn test_for_rom_without_coprocessor() {
+        // High nibble 0 also encodes DSP, the layout tells there is no coprocessor
+        assert!(for_rom(&rom_with_hardware(0x02)).is_none());
+    }
+
+    #[test]
+    fn test_for_rom_unsupported_coprocessor() {
+        // GSU
+        assert!(for_rom(&rom_with_hardware(0x13)).is_none());
+    }
+}
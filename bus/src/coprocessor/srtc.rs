@@ -0,0 +1,389 @@
+//! Sharp S-RTC real-time clock (Daikaijuu Monogatari II).
+//!
+//! The clock is a 13-nibble register file accessed serially: games write commands to
+//! `$2801` and read the nibbles back from `$2800`, starting with seconds and ending with
+//! the day of the week. The time is the host time (UTC) shifted by an offset, which
+//! changes when the game sets the clock and is persisted through
+//! [`Coprocessor::save_data`].
+
+use crate::coprocessor::Coprocessor;
+use common::save_state::{StateError, StateReader, StateWriter};
+use common::snes_address::SnesAddress;
+
+const READ_REGISTER: u16 = 0x2800;
+const WRITE_REGISTER: u16 = 0x2801;
+
+/// Nibbles of the register file: seconds, minutes, hours, day and year (2 each), month,
+/// century and day of the week
+const NIBBLES: usize = 13;
+const WEEKDAY: usize = 12;
+/// Value read before and after the nibbles of the register file
+const END_MARKER: u8 = 0x0F;
+/// Year of century 0
+const BASE_YEAR: i64 = 1000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Ready,
+    Command,
+    Read,
+    Write,
+}
+
+pub struct Srtc {
+    mode: Mode,
+    /// Next nibble read or written, -1 before the start marker
+    index: i8,
+    nibbles: [u8; NIBBLES],
+    /// Seconds added to the host time
+    offset: i64,
+    /// Source of the host time, in seconds since the Unix epoch
+    clock: fn() -> i64,
+}
+
+impl Default for Srtc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Srtc {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Ready,
+            index: -1,
+            nibbles: [0; NIBBLES],
+            offset: 0,
+            clock: host_time,
+        }
+    }
+
+    /// Difference between the emulated clock and the host time, in seconds
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    pub fn set_offset(&mut self, offset: i64) {
+        self.offset = offset;
+    }
+
+    /// Current time of the emulated clock, in seconds since the Unix epoch
+    pub fn time(&self) -> i64 {
+        (self.clock)() + self.offset
+    }
+
+    /// Latches the current time into the register file.
+    fn update_nibbles(&mut self) {
+        let time = self.time();
+        let (year, month, day) = civil_from_days(time.div_euclid(SECONDS_PER_DAY));
+        let seconds = time.rem_euclid(SECONDS_PER_DAY);
+        let year = year.clamp(BASE_YEAR, BASE_YEAR + 1599) - BASE_YEAR;
+
+        let digits = [seconds % 60, seconds / 60 % 60, seconds / 3600, day];
+        for (i, value) in digits.into_iter().enumerate() {
+            self.nibbles[i * 2] = (value % 10) as u8;
+            self.nibbles[i * 2 + 1] = (value / 10) as u8;
+        }
+        self.nibbles[8] = month as u8;
+        self.nibbles[9] = (year % 10) as u8;
+        self.nibbles[10] = (year / 10 % 10) as u8;
+        self.nibbles[11] = (year / 100) as u8;
+        self.nibbles[WEEKDAY] = weekday(time.div_euclid(SECONDS_PER_DAY));
+    }
+
+    /// Sets the clock to the time written to the register file.
+    fn apply_nibbles(&mut self) {
+        let n = self.nibbles.map(|nibble| nibble as i64);
+        let year = BASE_YEAR + n[11] * 100 + n[10] * 10 + n[9];
+        let month = n[8].clamp(1, 12);
+        let day = (n[7] * 10 + n[6]).max(1);
+        let days = days_from_civil(year, month, day);
+        let seconds = (n[5] * 10 + n[4]) * 3600 + (n[3] * 10 + n[2]) * 60 + n[1] * 10 + n[0];
+
+        self.nibbles[WEEKDAY] = weekday(days);
+        self.offset = days * SECONDS_PER_DAY + seconds - (self.clock)();
+    }
+
+    fn read_nibble(&mut self) -> u8 {
+        if self.mode != Mode::Read {
+            return 0;
+        }
+        if self.index < 0 {
+            self.update_nibbles();
+            self.index += 1;
+            END_MARKER
+        } else if self.index as usize >= NIBBLES {
+            self.index = -1;
+            END_MARKER
+        } else {
+            let nibble = self.nibbles[self.index as usize];
+            self.index += 1;
+            nibble
+        }
+    }
+
+    fn write_nibble(&mut self, value: u8) {
+        match value & 0x0F {
+            0x0D => {
+                self.mode = Mode::Read;
+                self.index = -1;
+            }
+            0x0E => self.mode = Mode::Command,
+            0x0F => {}
+            value => match self.mode {
+                Mode::Write if (0..WEEKDAY as i8).contains(&self.index) => {
+                    self.nibbles[self.index as usize] = value;
+                    self.index += 1;
+                    // The day of the week is computed by the chip
+                    if self.index as usize == WEEKDAY {
+                        self.apply_nibbles();
+                        self.index += 1;
+                    }
+                }
+                Mode::Command => match value {
+                    0x00 => {
+                        self.mode = Mode::Write;
+                        self.index = 0;
+                    }
+                    0x04 => {
+                        self.mode = Mode::Ready;
+                        self.index = -1;
+                        self.nibbles = [0; NIBBLES];
+                    }
+                    _ => self.mode = Mode::Ready,
+                },
+                _ => {}
+            },
+        }
+    }
+}
+
+impl Coprocessor for Srtc {
+    fn name(&self) -> &'static str {
+        "S-RTC"
+    }
+
+    fn maps(&self, addr: SnesAddress) -> bool {
+        matches!(addr.bank, 0x00..=0x3F | 0x80..=0xBF)
+            && matches!(addr.addr, READ_REGISTER | WRITE_REGISTER)
+    }
+
+    fn read(&mut self, addr: SnesAddress) -> u8 {
+        match addr.addr {
+            READ_REGISTER => self.read_nibble(),
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: SnesAddress, value: u8) {
+        if addr.addr == WRITE_REGISTER {
+            self.write_nibble(value);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.mode = Mode::Ready;
+        self.index = -1;
+    }
+
+    /// The clock offset, as a little-endian `i64`
+    fn save_data(&self) -> Option<Vec<u8>> {
+        Some(self.offset.to_le_bytes().to_vec())
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        if let Ok(bytes) = data.try_into() {
+            self.offset = i64::from_le_bytes(bytes);
+        }
+    }
+
+    fn save_data_extension(&self) -> &'static str {
+        "rtc"
+    }
+
+    /// The register file, where the serial access stands, and the clock offset
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.u8(match self.mode {
+            Mode::Ready => 0,
+            Mode::Command => 1,
+            Mode::Read => 2,
+            Mode::Write => 3,
+        });
+        writer.u8(self.index as u8);
+        writer.bytes(&self.nibbles);
+        writer.u64(self.offset as u64);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.mode = match reader.u8()? {
+            0 => Mode::Ready,
+            1 => Mode::Command,
+            2 => Mode::Read,
+            3 => Mode::Write,
+            _ => return Err(reader.corrupt()),
+        };
+        self.index = reader.u8()? as i8;
+        self.nibbles = reader.array()?;
+        self.offset = reader.u64()? as i64;
+        if !(-1..=NIBBLES as i8).contains(&self.index) {
+            return Err(reader.corrupt());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn host_time() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    }
+}
+
+/// There is no system clock on the web without JavaScript bindings: the clock starts at the
+/// Unix epoch, shifted by the offset set by the game.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn host_time() -> i64 {
+    0
+}
+
+/// Day of the week of a day since the Unix epoch, 0 being Sunday
+fn weekday(days: i64) -> u8 {
+    // 1970-01-01 was a Thursday
+    (days + 4).rem_euclid(7) as u8
+}
+
+/// Days since the Unix epoch of a date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Date (year, month, day) of a day since the Unix epoch
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::snes_address::snes_addr;
+
+    /// 2026-10-18 (a Sunday) 13:45:30 UTC
+    const NOW: i64 = 1_792_331_130;
+
+    fn srtc() -> Srtc {
+        Srtc {
+            clock: || NOW,
+            ..Srtc::new()
+        }
+    }
+
+    fn read_all(srtc: &mut Srtc) -> Vec<u8> {
+        srtc.write(snes_addr!(0:0x2801), 0x0D);
+        (0..NIBBLES + 2)
+            .map(|_| srtc.read(snes_addr!(0:0x2800)))
+            .collect()
+    }
+
+    fn write_time(srtc: &mut Srtc, nibbles: [u8; WEEKDAY]) {
+        srtc.write(snes_addr!(0:0x2801), 0x0E);
+        srtc.write(snes_addr!(0:0x2801), 0x00);
+        for nibble in nibbles {
+            srtc.write(snes_addr!(0:0x2801), nibble);
+        }
+        srtc.write(snes_addr!(0:0x2801), 0x0D);
+    }
+
+    #[test]
+    fn test_calendar_conversions() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(NOW / SECONDS_PER_DAY), (2026, 10, 18));
+        assert_eq!(days_from_civil(1900, 1, 1), -25_567);
+        assert_eq!(weekday(0), 4);
+        assert_eq!(weekday(NOW / SECONDS_PER_DAY), 0);
+    }
+
+    #[test]
+    fn test_read_host_time() {
+        let mut srtc = srtc();
+        let expected = [0x0F, 0, 3, 5, 4, 3, 1, 8, 1, 10, 6, 2, 10, 0, 0x0F];
+        assert_eq!(read_all(&mut srtc), expected);
+    }
+
+    #[test]
+    fn test_read_before_read_command() {
+        let mut srtc = srtc();
+        assert_eq!(srtc.read(snes_addr!(0:0x2800)), 0);
+    }
+
+    #[test]
+    fn test_set_time_changes_offset() {
+        let mut srtc = srtc();
+        // 1999-12-31 23:59:50
+        write_time(&mut srtc, [0, 5, 9, 5, 3, 2, 1, 3, 12, 9, 9, 9]);
+
+        let expected = days_from_civil(1999, 12, 31) * SECONDS_PER_DAY + 86_390;
+        assert_eq!(srtc.time(), expected);
+        assert_eq!(srtc.offset(), expected - NOW);
+
+        // Friday
+        assert_eq!(read_all(&mut srtc)[13], 5);
+    }
+
+    #[test]
+    fn test_clear_command() {
+        let mut srtc = srtc();
+        srtc.write(snes_addr!(0:0x2801), 0x0E);
+        srtc.write(snes_addr!(0:0x2801), 0x04);
+        assert_eq!(srtc.nibbles, [0; NIBBLES]);
+        assert_eq!(srtc.read(snes_addr!(0:0x2800)), 0);
+    }
+
+    #[test]
+    fn test_save_data_round_trip() {
+        let mut srtc = srtc();
+        srtc.set_offset(-12_345);
+        let data = srtc.save_data().unwrap();
+
+        let mut restored = Srtc::new();
+        restored.load_save_data(&data);
+        assert_eq!(restored.offset(), -12_345);
+
+        // Truncated data is ignored
+        restored.load_save_data(&data[..4]);
+        assert_eq!(restored.offset(), -12_345);
+    }
+
+    #[test]
+    fn test_maps() {
+        let srtc = srtc();
+        assert!(srtc.maps(snes_addr!(0x00:0x2800)));
+        assert!(srtc.maps(snes_addr!(0xBF:0x2801)));
+        assert!(!srtc.maps(snes_addr!(0x00:0x2802)));
+        assert!(!srtc.maps(snes_addr!(0x40:0x2800)));
+    }
+}
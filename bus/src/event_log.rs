@@ -0,0 +1,215 @@
+use crate::register_info::Register;
+use common::snes_address::SnesAddress;
+use std::fmt;
+
+/// Kind of register hit by a logged write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// PPU register (`0x2100–0x213F`)
+    Ppu,
+    /// DMA/HDMA enable (`0x420B–0x420C`) or channel register (`0x4300–0x437F`)
+    Dma,
+}
+
+impl EventKind {
+    /// Kind of register at the I/O offset `addr`, `None` if it is not logged.
+    pub fn of(addr: u16) -> Option<Self> {
+        match addr {
+            0x2100..0x2140 => Some(Self::Ppu),
+            0x420B..=0x420C | 0x4300..0x4380 => Some(Self::Dma),
+            _ => None,
+        }
+    }
+}
+
+/// A logged register write, timestamped with the beam position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterEvent {
+    pub scanline: u16,
+    pub dot: u16,
+    pub kind: EventKind,
+    pub addr: SnesAddress,
+    pub value: u8,
+}
+
+/// The write with the register name and bit fields, e.g.
+/// `100:42 BGMODE ($2105) = $01 bg4_16x16=0 bg3_16x16=0 bg2_16x16=0 bg1_16x16=0 bg3_priority=0 mode=1`
+impl fmt::Display for RegisterEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{} ", self.scanline, self.dot)?;
+        let Some(register) = Register::at_address(self.addr, true) else {
+            return write!(f, "${:04X} = ${:02X}", self.addr.addr, self.value);
+        };
+        write!(
+            f,
+            "{register} (${:04X}) = ${:02X}",
+            self.addr.addr, self.value
+        )?;
+        if !register.info.fields.is_empty() {
+            write!(f, " {}", register.fields(self.value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Log of PPU and DMA register writes, for event viewers showing mid-frame raster tricks.
+///
+/// Disabled by default; once enabled, every logged write is kept until [`Self::clear`] or
+/// [`Self::take_events`], typically called once per frame.
+///
+/// The PPU is stepped a scanline at a time and has no horizontal counter: writes are
+/// timestamped with the scanline of the PPU and the dot of the I/O registers' beam position.
+#[derive(Debug, Default)]
+pub struct EventLog {
+    enabled: bool,
+    events: Vec<RegisterEvent>,
+}
+
+impl EventLog {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Logs a write to `addr` made at `scanline` and `dot` (0–339) if logging is enabled and
+    /// it targets a PPU or DMA register.
+    pub fn record(&mut self, addr: SnesAddress, value: u8, scanline: u16, dot: u16) {
+        if !self.enabled {
+            return;
+        }
+        let Some(kind) = EventKind::of(addr.addr) else {
+            return;
+        };
+        self.events.push(RegisterEvent {
+            scanline,
+            dot,
+            kind,
+            addr,
+            value,
+        });
+    }
+
+    /// Writes logged so far, in order.
+    pub fn events(&self) -> &[RegisterEvent] {
+        &self.events
+    }
+
+    /// Writes logged on `scanline`, in order.
+    pub fn events_on_scanline(&self, scanline: u16) -> impl Iterator<Item = &RegisterEvent> {
+        self.events.iter().filter(move |event| event.scanline == scanline)
+    }
+
+    /// Returns the logged writes and starts a new log, e.g. at the end of a frame.
+    pub fn take_events(&mut self) -> Vec<RegisterEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::snes_address::snes_addr;
+
+    fn enabled_log() -> EventLog {
+        let mut log = EventLog::default();
+        log.set_enabled(true);
+        log
+    }
+
+    #[test]
+    fn test_event_kind_ranges() {
+        assert_eq!(EventKind::of(0x2100), Some(EventKind::Ppu));
+        assert_eq!(EventKind::of(0x213F), Some(EventKind::Ppu));
+        assert_eq!(EventKind::of(0x2140), None);
+        assert_eq!(EventKind::of(0x420B), Some(EventKind::Dma));
+        assert_eq!(EventKind::of(0x420C), Some(EventKind::Dma));
+        assert_eq!(EventKind::of(0x4200), None);
+        assert_eq!(EventKind::of(0x4300), Some(EventKind::Dma));
+        assert_eq!(EventKind::of(0x437F), Some(EventKind::Dma));
+        assert_eq!(EventKind::of(0x4380), None);
+    }
+
+    #[test]
+    fn test_display_names_registers() {
+        let event = |addr, value| RegisterEvent {
+            scanline: 100,
+            dot: 42,
+            kind: EventKind::Ppu,
+            addr,
+            value,
+        };
+        assert_eq!(
+            event(snes_addr!(0:0x2100), 0x8F).to_string(),
+            "100:42 INIDISP ($2100) = $8F force_blank=1 brightness=15"
+        );
+        assert_eq!(
+            event(snes_addr!(0:0x4372), 0x34).to_string(),
+            "100:42 A1T7L ($4372) = $34"
+        );
+        assert_eq!(
+            event(snes_addr!(0:0x430C), 0x01).to_string(),
+            "100:42 $430C = $01"
+        );
+    }
+
+    #[test]
+    fn test_disabled_log_records_nothing() {
+        let mut log = EventLog::default();
+        log.record(snes_addr!(0:0x2100), 0x0F, 10, 0);
+        assert!(log.events().is_empty());
+    }
+
+    #[test]
+    fn test_record_timestamps_writes() {
+        let mut log = enabled_log();
+        log.record(snes_addr!(0:0x2105), 0x01, 100, 42);
+        log.record(snes_addr!(0x80:0x4302), 0x34, 101, 200);
+
+        assert_eq!(
+            log.events(),
+            &[
+                RegisterEvent { scanline: 100, dot: 42, kind: EventKind::Ppu, addr: snes_addr!(0:0x2105), value: 0x01 },
+                RegisterEvent { scanline: 101, dot: 200, kind: EventKind::Dma, addr: snes_addr!(0x80:0x4302), value: 0x34 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unlogged_registers_ignored() {
+        let mut log = enabled_log();
+        log.record(snes_addr!(0:0x4200), 0x81, 0, 0);
+        log.record(snes_addr!(0:0x2140), 0x01, 0, 0);
+        assert!(log.events().is_empty());
+    }
+
+    #[test]
+    fn test_events_on_scanline() {
+        let mut log = enabled_log();
+        log.record(snes_addr!(0:0x210D), 1, 5, 0);
+        log.record(snes_addr!(0:0x210D), 2, 6, 0);
+        log.record(snes_addr!(0:0x210D), 3, 6, 0);
+
+        let values: Vec<u8> = log.events_on_scanline(6).map(|e| e.value).collect();
+        assert_eq!(values, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_take_events_starts_new_log() {
+        let mut log = enabled_log();
+        log.record(snes_addr!(0:0x2100), 0x80, 0, 0);
+
+        assert_eq!(log.take_events().len(), 1);
+        assert!(log.events().is_empty());
+
+        log.record(snes_addr!(0:0x2100), 0x0F, 1, 0);
+        log.clear();
+        assert!(log.events().is_empty());
+    }
+}
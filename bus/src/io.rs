@@ -1,8 +1,26 @@
 use crate::constants::{IO_END_ADDRESS, IO_START_ADDRESS};
+#[cfg(feature = "event-log")]
+use crate::event_log::EventLog;
+use crate::joypad::{BeamPosition, Joypads};
 use apu::Apu;
 use common::{snes_addr, snes_address::SnesAddress, u16_split::U16Split};
+use common::save_state::{StateError, StateReader, StateWriter, Stateful, Tag};
+use common::video_standard::VideoStandard;
 use ppu::ppu::PPU;
 
+/// Version of the 5A22 CPU, read in the low bits of RDNMI
+const CPU_VERSION: u8 = 2;
+
+/// Master cycles of the scanline during which HVBJOY reports H-Blank: set at dot 274,
+/// cleared at dot 1 of the next scanline
+const HBLANK_START: u16 = 274 * 4;
+const HBLANK_END: u16 = 4;
+
+/// Joypad auto-read window, in master cycles from the start of V-Blank: it starts at
+/// dot 32.5 of the first V-Blank scanline and clocks 16 bits in 4224 master cycles
+const AUTO_JOYPAD_START: u64 = 130;
+const AUTO_JOYPAD_CYCLES: u64 = 4224;
+
 /// I/O register file of the SNES, mapped to `0x2000–0x5FFF` in banks
 /// `0x00–0x3F` and `0x80–0xBF` (fully mirrored).
 ///
@@ -121,12 +139,12 @@ pub struct Io {
     /// [SNESdev Wiki - TIMEUP](https://snes.nesdev.org/wiki/MMIO_registers#TIMEUP)
     pub timeup: u8,
 
-    /// **HVBJOY** (`0x4212`, R) - Screen/joypad status. Bit 7 = V-Blank,
-    /// bit 6 = H-Blank, bit 0 = joypad auto-read in progress.
-    ///
-    /// # Reference
-    /// [SNESdev Wiki - HVBJOY](https://snes.nesdev.org/wiki/MMIO_registers#HVBJOY)
-    pub hvbjoy: u8,
+    /// Master cycle within the current scanline (0–1363), advanced by the emulation
+    /// loop. Times the H-Blank and joypad auto-read edges of [`Self::hvbjoy`].
+    pub h_cycle: u16,
+
+    /// Whether the joypads were auto-read when the current V-Blank started.
+    auto_joypad_read: bool,
 
     /// **JOY1L/H** (`0x4218–0x4219`, R) - Auto-read result for controller
     /// port 1. Updated once per frame when auto-read is enabled.
@@ -156,6 +174,9 @@ pub struct Io {
     /// [SNESdev Wiki - JOY4](https://snes.nesdev.org/wiki/MMIO_registers#JOY4)
     pub joy4: u16,
 
+    /// Controller ports and their devices, read through JOYSER0/JOYSER1 and auto-read.
+    pub joypads: Joypads,
+
     /// DMA/HDMA register banks for all 8 channels (`0x4300–0x437F`).
     /// Channel `n` occupies `0x43n0–0x43nF`.
     pub dma_channels: [DMAChannel; 8],
@@ -168,6 +189,11 @@ pub struct Io {
     /// # Reference
     /// [SNESdev Wiki — Open bus](https://snes.nesdev.org/wiki/Open_bus)
     pub open_bus: u8,
+
+    /// Log of PPU/DMA register writes for event viewers. Disabled until
+    /// [`EventLog::set_enabled`] is called.
+    #[cfg(feature = "event-log")]
+    pub event_log: EventLog,
 }
 
 /// Register state for a single SNES DMA/HDMA channel.
@@ -288,18 +314,24 @@ impl Default for Io {
             rddiv: 0,
             rdmpy: 0,
 
-            rdnmi: 0,
+            rdnmi: CPU_VERSION,
             timeup: 0,
-            hvbjoy: 0,
+            h_cycle: 0,
+            auto_joypad_read: false,
 
             joy1: 0,
             joy2: 0,
             joy3: 0,
             joy4: 0,
 
+            joypads: Joypads::default(),
+
             dma_channels: Default::default(),
 
             open_bus: 0,
+
+            #[cfg(feature = "event-log")]
+            event_log: EventLog::default(),
         }
     }
 }
@@ -312,55 +344,44 @@ impl Io {
         );
     }
 
-    fn read_cpu(&mut self, addr: SnesAddress, apu: &mut Apu) -> u8 {
+    fn read_cpu(&mut self, addr: SnesAddress, ppu: &PPU, apu: &mut Apu) -> u8 {
         match addr.addr {
-            // Data-from-APU register
-            // TODO : Link with the actual apu component
-            #[cfg(not(tarpaulin_include))]
-            0x2140..0x2180 => {
-                let reg_nb = addr.addr % 4;
-                match reg_nb {
-                    0 => todo!("{} : Implement APU channel n°1 reads", addr.addr),
-                    1 => todo!("{} : Implement APU channel n°2 reads", addr.addr),
-                    2 => todo!("{} : Implement APU channel n°3 reads", addr.addr),
-                    3 => todo!("{} : Implement APU channel n°4 reads", addr.addr),
-                    _ => unreachable!(),
-                }
-            }
+            // Data-from-APU register, mirrored every 4 bytes
+            0x2140..0x2180 => apu.read_port(addr.addr as usize % 4),
 
-            // S-WRAM Data Registers (Expansion port not implemented yet)
-            #[cfg(not(tarpaulin_include))]
-            0x2180 => todo!("0x2180-0x2183 : Implement Rom S-WRAM reads"),
+            // WRAM ports, handled by the bus which owns WRAM, unused B-bus addresses and the
+            // expansion port B-bus ($21C0-$21FF, nothing connected)
+            0x2180..0x2200 => self.open_bus,
 
-            // JOYSER0/JOYSER1 - manual controller reading not implemented
-            #[cfg(not(tarpaulin_include))]
-            0x4016 => todo!("0x4016 : Implement JOYSER0 register read"),
-            #[cfg(not(tarpaulin_include))]
-            0x4017 => todo!("0x4017 : Implement JOYSER1 register read"),
+            // Unused, and the gaps around the serial joypad ports
+            0x2200..0x4016 | 0x4018..0x4200 => self.open_bus,
+
+            // JOYSER0/JOYSER1 - manual controller reading, upper bits are open bus
+            0x4016 => (self.open_bus & 0xFC) | self.joypads.read_joyser(0, self.wrio),
+            0x4017 => (self.open_bus & 0xE0) | 0x1C | self.joypads.read_joyser(1, self.wrio),
 
-            // Vblank flag and CPU version register
-            // TODO : Implement open bus on unused bits
+            // Write only registers
+            0x4200..0x4210 => self.open_bus,
+
+            // Vblank flag and CPU version register, bits 6-4 are open bus
             0x4210 => {
-                let value = self.rdnmi;
+                let value = (self.rdnmi & 0x8F) | (self.open_bus & 0x70);
                 self.rdnmi = self.rdnmi & 0x7F; // Reset V-Blank flag
                 value
             }
 
-            // Timer flag register
-            // TODO : Implement open bus on unused bits
+            // Timer flag register, bits 6-0 are open bus
             0x4211 => {
-                let value = self.timeup;
+                let value = (self.timeup & 0x80) | (self.open_bus & 0x7F);
                 self.timeup = self.timeup & 0x7F; // Reset Timer flag
                 value
             }
 
-            // Screen and Joypad status register
-            // TODO : Implement open bus on unused bits
-            0x4212 => self.hvbjoy,
+            // Screen and Joypad status register, bits 5-1 are open bus
+            0x4212 => self.hvbjoy(ppu) | (self.open_bus & 0x3E),
 
-            // RDIO : manual controller reading not implemented
-            #[cfg(not(tarpaulin_include))]
-            0x4213 => todo!("0x4213 : Implement RDIO register read"),
+            // RDIO : programmable I/O port, as driven by WRIO
+            0x4213 => self.wrio,
 
             // Divison result register
             0x4214 => *self.rddiv.lo(),
@@ -380,6 +401,9 @@ impl Io {
             0x421E => *self.joy4.lo(),
             0x421F => *self.joy4.hi(),
 
+            // Unused
+            0x4220..0x4300 => self.open_bus,
+
             // DMA and HDMA channel registers
             0x4300..0x4380 => {
                 let channel_nb = (addr.addr - 0x4300) / 0x10;
@@ -400,37 +424,26 @@ impl Io {
                     0xA => channel.nltr,
                     0xB | 0xF => channel.unused,
 
-                    _ => self.open_bus, // Open bus I believe, but not sure if this is the correct behavior
+                    // $43nC-$43nE are not connected
+                    _ => self.open_bus,
                 }
             }
 
-            // Open Bus
-            _ => self.open_bus,
+            #[cfg(not(tarpaulin_include))]
+            _ => unreachable!(),
         }
     }
 
     fn write_cpu(&mut self, value: u8, addr: SnesAddress, apu: &mut Apu) {
         match addr.addr {
-            // Data-to-APU register
-            #[cfg(not(tarpaulin_include))]
-            0x2140..0x2180 => {
-                let reg_nb = addr.addr % 4;
-                match reg_nb {
-                    0 => todo!("{} : Implement APU channel n°1 writes", addr.addr),
-                    1 => todo!("{} : Implement APU channel n°2 writes", addr.addr),
-                    2 => todo!("{} : Implement APU channel n°3 writes", addr.addr),
-                    3 => todo!("{} : Implement APU channel n°4 writes", addr.addr),
-                    _ => unreachable!(),
-                }
-            }
+            // Data-to-APU register, mirrored every 4 bytes
+            0x2140..0x2180 => apu.write_port(addr.addr as usize % 4, value),
 
-            // S-WRAM Data Registers (Expansion port not implemented yet)
-            #[cfg(not(tarpaulin_include))]
-            0x2180..=0x2183 => todo!("0x2180-0x2183 : Implement Rom S-WRAM writes"),
+            // WRAM ports, handled by the bus which owns WRAM
+            0x2180..=0x2183 => {}
 
-            // JOYOUT - manual controller reading not implemented
-            #[cfg(not(tarpaulin_include))]
-            0x4016 => todo!("0x4016 : Implement JOYOUT register write"),
+            // JOYOUT - latch line of the controller ports
+            0x4016 => self.joypads.write_joyout(value),
 
             // Register for enabling NMI, H/V-Blank, and joypad auto-read
             0x4200 => self.nmitimen = value,
@@ -506,132 +519,107 @@ impl Io {
         }
     }
 
-    #[cfg(not(tarpaulin_include))]
+    /// PPU ports are handled by the PPU itself, $2100-$2133 being write only.
     fn read_ppu(&mut self, addr: SnesAddress, ppu: &mut PPU) -> u8 {
         match addr.addr {
-            // MPY result (24-bit)
-            0x2134 => todo!("0x2134 : MPYL read"),
-            0x2135 => todo!("0x2135 : MPYM read"),
-            0x2136 => todo!("0x2136 : MPYH read"),
-
-            // Latch H/V counter
-            0x2137 => todo!("0x2137 : SLHV read"),
-
-            // OAM read
-            0x2138 => todo!("0x2138 : OAMDATAREAD"),
-
-            // VRAM read
-            0x2139 => todo!("0x2139 : VMDATALREAD"),
-            0x213A => todo!("0x213A : VMDATAHREAD"),
-
-            // CGRAM read (2-step)
-            0x213B => todo!("0x213B : CGDATAREAD"),
-
-            // H/V counters (2-step reads)
-            0x213C => todo!("0x213C : OPHCT read"),
-            0x213D => todo!("0x213D : OPVCT read"),
-
-            // Status registers
-            0x213E => todo!("0x213E : STAT77 read"),
-            0x213F => todo!("0x213F : STAT78 read"),
-
             // Open bus, may need to have a custom ppu open bus
-            _ => 0,
+            0x2100..0x2134 => self.open_bus,
+            // SLHV: latches the dot and scanline of the beam, while WRIO bit 7 is set
+            0x2137 => {
+                if self.wrio & 0x80 != 0 {
+                    ppu.latch_counters(self.h_cycle / 4, ppu.scanline);
+                }
+                self.open_bus
+            }
+            _ => ppu.read(addr.addr),
         }
     }
 
-    #[cfg(not(tarpaulin_include))]
+    /// PPU ports are handled by the PPU itself, $2134-$213F being read only.
     fn write_ppu(&mut self, value: u8, addr: SnesAddress, ppu: &mut PPU) {
-        match addr.addr {
-            // Display / OBJ
-            0x2100 => todo!("0x2100 : INIDISP write"),
-            0x2101 => todo!("0x2101 : OBJSEL write"),
-
-            // OAM
-            0x2102 => todo!("0x2102 : OAMADDL write"),
-            0x2103 => todo!("0x2103 : OAMADDH write"),
-            0x2104 => todo!("0x2104 : OAMDATA write"),
-
-            // BG mode / mosaic
-            0x2105 => todo!("0x2105 : BGMODE write"),
-            0x2106 => todo!("0x2106 : MOSAIC write"),
-
-            // BG tilemap
-            0x2107 => todo!("0x2107 : BG1SC write"),
-            0x2108 => todo!("0x2108 : BG2SC write"),
-            0x2109 => todo!("0x2109 : BG3SC write"),
-            0x210A => todo!("0x210A : BG4SC write"),
-
-            // BG CHR base
-            0x210B => todo!("0x210B : BG12NBA write"),
-            0x210C => todo!("0x210C : BG34NBA write"),
-
-            // Scroll registers (W8x2)
-            0x210D => todo!("0x210D : BG1HOFS / M7HOFS write"),
-            0x210E => todo!("0x210E : BG1VOFS / M7VOFS write"),
-            0x210F => todo!("0x210F : BG2HOFS write"),
-            0x2110 => todo!("0x2110 : BG2VOFS write"),
-            0x2111 => todo!("0x2111 : BG3HOFS write"),
-            0x2112 => todo!("0x2112 : BG3VOFS write"),
-            0x2113 => todo!("0x2113 : BG4HOFS write"),
-            0x2114 => todo!("0x2114 : BG4VOFS write"),
-
-            // VRAM access
-            0x2115 => todo!("0x2115 : VMAIN write"),
-            0x2116 => todo!("0x2116 : VMADDL write"),
-            0x2117 => todo!("0x2117 : VMADDH write"),
-            0x2118 => todo!("0x2118 : VMDATAL write"),
-            0x2119 => todo!("0x2119 : VMDATAH write"),
-
-            // Mode 7
-            0x211A => todo!("0x211A : M7SEL write"),
-            0x211B => todo!("0x211B : M7A write"),
-            0x211C => todo!("0x211C : M7B write"),
-            0x211D => todo!("0x211D : M7C write"),
-            0x211E => todo!("0x211E : M7D write"),
-            0x211F => todo!("0x211F : M7X write"),
-            0x2120 => todo!("0x2120 : M7Y write"),
-
-            // CGRAM
-            0x2121 => todo!("0x2121 : CGADD write"),
-            0x2122 => todo!("0x2122 : CGDATA write"),
-
-            // Window registers
-            0x2123 => todo!("0x2123 : W12SEL write"),
-            0x2124 => todo!("0x2124 : W34SEL write"),
-            0x2125 => todo!("0x2125 : WOBJSEL write"),
-            0x2126 => todo!("0x2126 : WH0 write"),
-            0x2127 => todo!("0x2127 : WH1 write"),
-            0x2128 => todo!("0x2128 : WH2 write"),
-            0x2129 => todo!("0x2129 : WH3 write"),
-
-            // Window logic
-            0x212A => todo!("0x212A : WBGLOG write"),
-            0x212B => todo!("0x212B : WOBJLOG write"),
-
-            // Screen enable
-            0x212C => todo!("0x212C : TM write"),
-            0x212D => todo!("0x212D : TS write"),
-            0x212E => todo!("0x212E : TMW write"),
-            0x212F => todo!("0x212F : TSW write"),
-
-            // Color math
-            0x2130 => todo!("0x2130 : CGWSEL write"),
-            0x2131 => todo!("0x2131 : CGADSUB write"),
-            0x2132 => todo!("0x2132 : COLDATA write"),
-
-            // Screen settings
-            0x2133 => todo!("0x2133 : SETINI write"),
-
-            _ => {}
+        if addr.addr < 0x2134 {
+            ppu.write(addr.addr, value);
         }
     }
 }
 
 impl Io {
+    /// Updates the controller ports at the start of a scanline: light guns watch the beam
+    /// and latch the PPU counters, and joypads are auto-read into JOY1–JOY4 when V-Blank
+    /// starts if NMITIMEN bit 0 is set.
+    pub fn on_scanline(&mut self, ppu: &mut PPU) {
+        if let Some(BeamPosition { h, v }) = self.joypads.scanline(ppu.scanline, self.wrio) {
+            ppu.latch_counters(h, v);
+        }
+
+        if ppu.vblank_started {
+            self.auto_joypad_read = self.nmitimen & 0x01 != 0;
+            if self.auto_joypad_read {
+                [self.joy1, self.joy2, self.joy3, self.joy4] = self.joypads.auto_read(self.wrio);
+            }
+        }
+    }
+
+    /// **HVBJOY** (`0x4212`, R) - Screen/joypad status at the current beam position,
+    /// bits 5-1 cleared. Bit 7 = V-Blank, from the first V-Blank scanline to the end
+    /// of the frame. Bit 6 = H-Blank, from dot 274 to dot 1 of the next scanline.
+    /// Bit 0 = joypad auto-read in progress: JOY1–JOY4 are already filled, but games
+    /// wait for the bit to clear before reading them.
+    ///
+    /// # Reference
+    /// [SNESdev Wiki - HVBJOY](https://snes.nesdev.org/wiki/MMIO_registers#HVBJOY)
+    pub fn hvbjoy(&self, ppu: &PPU) -> u8 {
+        let mut value = 0;
+        if ppu.in_vblank() {
+            value |= 0x80;
+        }
+        if self.h_cycle >= HBLANK_START || self.h_cycle < HBLANK_END {
+            value |= 0x40;
+        }
+        if self.auto_joypad_busy(ppu) {
+            value |= 0x01;
+        }
+        value
+    }
+
+    fn auto_joypad_busy(&self, ppu: &PPU) -> bool {
+        let Some(lines) = ppu.scanline.checked_sub(ppu.regs.vblank_start_scanline()) else {
+            return false;
+        };
+        let cycles = lines as u64 * VideoStandard::MASTER_CYCLES_PER_SCANLINE + self.h_cycle as u64;
+        self.auto_joypad_read
+            && (AUTO_JOYPAD_START..AUTO_JOYPAD_START + AUTO_JOYPAD_CYCLES).contains(&cycles)
+    }
+
     /// Reads a byte from the I/O memory zone at the given `SnesAddress`.
     ///
-    /// The address is translated to an internal I/O offset using `to_offset`.
+    /// Addresses without a register read back the open bus, i.e. the last value seen on
+    /// the data bus, and so do the unused bits of the status registers:
+    ///
+    /// | Address       | Read                                                          |
+    /// |---------------|---------------------------------------------------------------|
+    /// | `$2000-$20FF` | open bus (nothing mapped)                                     |
+    /// | `$2100-$2133` | open bus (PPU write only registers)                           |
+    /// | `$2134-$213F` | PPU                                                           |
+    /// | `$2140-$217F` | APU ports                                                     |
+    /// | `$2180-$2183` | open bus (WRAM ports, handled by [`crate::bus::Bus`])         |
+    /// | `$2184-$21BF` | open bus (unused B-bus addresses)                             |
+    /// | `$21C0-$21FF` | open bus (expansion port B-bus, nothing connected)            |
+    /// | `$2200-$3FFF` | open bus (nothing mapped)                                     |
+    /// | `$4000-$4015` | open bus (nothing mapped)                                     |
+    /// | `$4016-$4017` | JOYSER0/1, serial bits, `$4017` bits 4-2 set, others open bus |
+    /// | `$4018-$41FF` | open bus (nothing mapped)                                     |
+    /// | `$4200-$420F` | open bus (write only registers)                               |
+    /// | `$4210`       | RDNMI, bits 6-4 open bus, bits 3-0 CPU version (2)            |
+    /// | `$4211`       | TIMEUP, bits 6-0 open bus                                     |
+    /// | `$4212`       | HVBJOY, bits 5-1 open bus                                     |
+    /// | `$4213-$421F` | registers                                                     |
+    /// | `$4220-$42FF` | open bus (nothing mapped)                                     |
+    /// | `$43n0-$43nB` | DMA channel n registers, `$43nF` mirrors `$43nB`              |
+    /// | `$43nC-$43nE` | open bus (nothing connected)                                  |
+    /// | `$4380-$5FFF` | open bus (nothing mapped)                                     |
+    ///
+    /// The expansion port area `$6000-$7FFF` is not I/O: see [`crate::bus::Bus::read`].
     ///
     /// # Panics
     /// Panics if the address does not map to a valid I/O memory location.
@@ -642,9 +630,8 @@ impl Io {
             {
                 match addr.addr {
                     0x2000..0x2100 => self.open_bus,
-                    #[cfg(not(tarpaulin_include))]
                     0x2100..0x2140 => self.read_ppu(addr, ppu),
-                    0x2140..0x4380 => self.read_cpu(addr, apu),
+                    0x2140..0x4380 => self.read_cpu(addr, ppu, apu),
                     0x4380..0x6000 => self.open_bus,
 
                     #[cfg(not(tarpaulin_include))]
@@ -668,9 +655,11 @@ impl Io {
             0x00..=0x3F | 0x80..=0xBF
                 if addr.addr >= IO_START_ADDRESS && addr.addr < IO_END_ADDRESS =>
             {
+                #[cfg(feature = "event-log")]
+                self.event_log.record(addr, value, ppu.scanline, self.h_cycle / 4);
+
                 match addr.addr {
                     0x2000..0x2100 => {}
-                    #[cfg(not(tarpaulin_include))]
                     0x2100..0x2140 => self.write_ppu(value, addr, ppu),
                     0x2140..0x4380 => self.write_cpu(value, addr, apu),
                     0x4380..0x6000 => {}
@@ -684,9 +673,111 @@ impl Io {
     }
 }
 
+/// CPU I/O registers: interrupts and H/V timers, multiplier and divider, joypad auto-read
+/// and ports, and the DMA/HDMA channels. The event log is a debugging aid and is not saved.
+impl Stateful for Io {
+    const TAG: Tag = *b"IO  ";
+    const VERSION: u16 = 1;
+
+    fn save(&self, writer: &mut StateWriter) {
+        for value in [
+            self.nmitimen,
+            self.wrio,
+            self.wrmpya,
+            self.wrmpyb,
+            self.wrdivb,
+            self.mdmaen,
+            self.hdmaen,
+            self.memsel,
+            self.rdnmi,
+            self.timeup,
+            self.open_bus,
+        ] {
+            writer.u8(value);
+        }
+        for value in [
+            self.wrdiv,
+            self.htime,
+            self.vtime,
+            self.rddiv,
+            self.rdmpy,
+            self.h_cycle,
+            self.joy1,
+            self.joy2,
+            self.joy3,
+            self.joy4,
+        ] {
+            writer.u16(value);
+        }
+        writer.bool(self.auto_joypad_read);
+        self.joypads.save_state(writer);
+
+        for ch in &self.dma_channels {
+            for value in [ch.dmap, ch.bbad, ch.a1t.bank, ch.dasb, ch.nltr, ch.unused] {
+                writer.u8(value);
+            }
+            for value in [ch.a1t.addr, ch.das, ch.a2a] {
+                writer.u16(value);
+            }
+        }
+    }
+
+    fn load(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        for value in [
+            &mut self.nmitimen,
+            &mut self.wrio,
+            &mut self.wrmpya,
+            &mut self.wrmpyb,
+            &mut self.wrdivb,
+            &mut self.mdmaen,
+            &mut self.hdmaen,
+            &mut self.memsel,
+            &mut self.rdnmi,
+            &mut self.timeup,
+            &mut self.open_bus,
+        ] {
+            *value = reader.u8()?;
+        }
+        for value in [
+            &mut self.wrdiv,
+            &mut self.htime,
+            &mut self.vtime,
+            &mut self.rddiv,
+            &mut self.rdmpy,
+            &mut self.h_cycle,
+            &mut self.joy1,
+            &mut self.joy2,
+            &mut self.joy3,
+            &mut self.joy4,
+        ] {
+            *value = reader.u16()?;
+        }
+        self.auto_joypad_read = reader.bool()?;
+        self.joypads.load_state(reader)?;
+
+        for ch in &mut self.dma_channels {
+            for value in [
+                &mut ch.dmap,
+                &mut ch.bbad,
+                &mut ch.a1t.bank,
+                &mut ch.dasb,
+                &mut ch.nltr,
+                &mut ch.unused,
+            ] {
+                *value = reader.u8()?;
+            }
+            for value in [&mut ch.a1t.addr, &mut ch.das, &mut ch.a2a] {
+                *value = reader.u16()?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::joypad::{Gamepad, SuperScope, gamepad, super_scope};
     use common::snes_address::snes_addr;
 
     fn init_all() -> (Io, PPU, Apu) {
@@ -876,13 +967,13 @@ mod tests {
         let (mut io, mut ppu, mut apu) = init_all();
 
         let rdnmi_addr = snes_addr!(0:0x4210);
-        let value_rdnmi = 0xFF;
-        io.rdnmi = value_rdnmi;
+        io.rdnmi |= 0x80;
+        io.open_bus = 0x50;
 
         let read_value = io.read(rdnmi_addr, &mut ppu, &mut apu);
-        assert_eq!(read_value, value_rdnmi);
+        assert_eq!(read_value, 0b1101_0010);
         let second_read_value = io.read(rdnmi_addr, &mut ppu, &mut apu);
-        assert_eq!(second_read_value, 0b0111_1111);
+        assert_eq!(second_read_value, 0b0101_0010);
     }
 
     #[test]
@@ -890,13 +981,13 @@ mod tests {
         let (mut io, mut ppu, mut apu) = init_all();
 
         let timeup_addr = snes_addr!(0:0x4211);
-        let value_timeup = 0xFF;
-        io.timeup = value_timeup;
+        io.timeup = 0x80;
+        io.open_bus = 0x21;
 
         let read_value = io.read(timeup_addr, &mut ppu, &mut apu);
-        assert_eq!(read_value, value_timeup);
+        assert_eq!(read_value, 0xA1);
         let second_read_value = io.read(timeup_addr, &mut ppu, &mut apu);
-        assert_eq!(second_read_value, 0b0111_1111);
+        assert_eq!(second_read_value, 0x21);
     }
 
     #[test]
@@ -904,11 +995,83 @@ mod tests {
         let (mut io, mut ppu, mut apu) = init_all();
 
         let hvbjoy_addr = snes_addr!(0:0x4212);
-        let value_hvbjoy = 0xFF;
-        io.hvbjoy = value_hvbjoy;
+        io.write(snes_addr!(0:0x4200), 0x01, &mut ppu, &mut apu);
+        ppu.scanline = ppu.regs.vblank_start_scanline();
+        ppu.vblank_started = true;
+        io.on_scanline(&mut ppu);
+        io.h_cycle = 1200;
+        io.open_bus = 0x00;
+        assert_eq!(io.read(hvbjoy_addr, &mut ppu, &mut apu), 0xC1);
+
+        // Bits 5-1 are open bus
+        io.h_cycle = 600;
+        ppu.scanline += 5;
+        io.open_bus = 0x3E;
+        assert_eq!(io.read(hvbjoy_addr, &mut ppu, &mut apu), 0xBE);
+    }
+
+    #[test]
+    fn test_hvbjoy_hblank_edges() {
+        let (mut io, ppu, _) = init_all();
+        for (h_cycle, hblank) in [(0, true), (3, true), (4, false), (1095, false), (1096, true), (1363, true)] {
+            io.h_cycle = h_cycle;
+            assert_eq!(io.hvbjoy(&ppu) & 0x40 != 0, hblank, "H-Blank at master cycle {h_cycle}");
+        }
+    }
+
+    #[test]
+    fn test_hvbjoy_vblank_edges() {
+        let (mut io, mut ppu, _) = init_all();
+        io.h_cycle = 600;
+        let vblank_start = ppu.regs.vblank_start_scanline();
+        for (scanline, vblank) in [(0, false), (vblank_start - 1, false), (vblank_start, true), (261, true)] {
+            ppu.scanline = scanline;
+            assert_eq!(io.hvbjoy(&ppu) & 0x80 != 0, vblank, "V-Blank on scanline {scanline}");
+        }
+
+        // Overscan moves V-Blank 15 scanlines later
+        ppu.regs.setini = 0x04;
+        ppu.scanline = vblank_start;
+        assert_eq!(io.hvbjoy(&ppu) & 0x80, 0);
+    }
+
+    #[test]
+    fn test_hvbjoy_auto_joypad_busy_window() {
+        let (mut io, mut ppu, mut apu) = init_all();
+        let vblank_start = ppu.regs.vblank_start_scanline();
+        let busy = |io: &mut Io, ppu: &mut PPU, scanline, h_cycle| {
+            ppu.scanline = scanline;
+            io.h_cycle = h_cycle;
+            io.hvbjoy(ppu) & 0x01 != 0
+        };
 
-        let read_value = io.read(hvbjoy_addr, &mut ppu, &mut apu);
-        assert_eq!(read_value, value_hvbjoy);
+        ppu.scanline = vblank_start;
+        ppu.vblank_started = true;
+        io.on_scanline(&mut ppu);
+        assert!(!busy(&mut io, &mut ppu, vblank_start, 200), "auto-read disabled in NMITIMEN");
+
+        io.write(snes_addr!(0:0x4200), 0x01, &mut ppu, &mut apu);
+        io.on_scanline(&mut ppu);
+        assert!(!busy(&mut io, &mut ppu, vblank_start, 129));
+        assert!(busy(&mut io, &mut ppu, vblank_start, 130));
+        // 130 + 4224 master cycles later, on the fourth V-Blank scanline
+        assert!(busy(&mut io, &mut ppu, vblank_start + 3, 261));
+        assert!(!busy(&mut io, &mut ppu, vblank_start + 3, 262));
+        assert!(!busy(&mut io, &mut ppu, 10, 200));
+    }
+
+    #[test]
+    fn test_unused_registers_read_open_bus() {
+        let (mut io, mut ppu, mut apu) = init_all();
+
+        for addr in [
+            0x2181, 0x2184, 0x21BF, 0x21C0, 0x21FF, 0x2200, 0x3FFF, 0x4000, 0x4015, 0x4018,
+            0x41FF, 0x4200, 0x420F, 0x4220, 0x42FF, 0x430C, 0x437E,
+        ] {
+            io.open_bus = addr as u8 ^ 0xA5;
+            let read_value = io.read(snes_addr!(0x80:addr), &mut ppu, &mut apu);
+            assert_eq!(read_value, addr as u8 ^ 0xA5, "${addr:04X}");
+        }
     }
 
     #[test]
@@ -1057,4 +1220,155 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_joyser_manual_read() {
+        let (mut io, mut ppu, mut apu) = init_all();
+        let pad = io.joypads.device_mut::<Gamepad>(0).unwrap();
+        pad.press(gamepad::B | gamepad::Y);
+
+        io.write(snes_addr!(0:0x4016), 0x01, &mut ppu, &mut apu);
+        io.write(snes_addr!(0:0x4016), 0x00, &mut ppu, &mut apu);
+        io.open_bus = 0x00;
+
+        assert_eq!(io.read(snes_addr!(0:0x4016), &mut ppu, &mut apu), 0x01);
+        assert_eq!(io.read(snes_addr!(0:0x4016), &mut ppu, &mut apu), 0x01);
+        assert_eq!(io.read(snes_addr!(0:0x4016), &mut ppu, &mut apu), 0x00);
+        // Nothing on port 2: only the fixed bits
+        assert_eq!(io.read(snes_addr!(0:0x4017), &mut ppu, &mut apu), 0x1C);
+    }
+
+    #[test]
+    fn test_rdio_reads_wrio() {
+        let (mut io, mut ppu, mut apu) = init_all();
+        io.write(snes_addr!(0:0x4201), 0x40, &mut ppu, &mut apu);
+        assert_eq!(io.read(snes_addr!(0:0x4213), &mut ppu, &mut apu), 0x40);
+    }
+
+    #[test]
+    fn test_ppu_ports_reach_the_ppu() {
+        let (mut io, mut ppu, mut apu) = init_all();
+        io.write(snes_addr!(0:0x2115), 0x80, &mut ppu, &mut apu);
+        io.write(snes_addr!(0x80:0x2116), 0x10, &mut ppu, &mut apu);
+        io.write(snes_addr!(0:0x2117), 0x00, &mut ppu, &mut apu);
+        io.write(snes_addr!(0:0x2118), 0xCD, &mut ppu, &mut apu);
+        io.write(snes_addr!(0:0x2119), 0xAB, &mut ppu, &mut apu);
+        assert_eq!(ppu.vram.memory[0x0010], 0xABCD);
+
+        io.write(snes_addr!(0:0x2116), 0x10, &mut ppu, &mut apu);
+        assert_eq!(io.read(snes_addr!(0:0x2139), &mut ppu, &mut apu), 0xCD);
+        assert_eq!(io.read(snes_addr!(0:0x213A), &mut ppu, &mut apu), 0xAB);
+    }
+
+    #[test]
+    fn test_ppu_write_only_ports_read_open_bus() {
+        let (mut io, mut ppu, mut apu) = init_all();
+        io.write(snes_addr!(0:0x2100), 0x8F, &mut ppu, &mut apu);
+        assert_eq!(ppu.regs.inidisp, 0x8F);
+
+        io.write(snes_addr!(0:0x2000), 0x5A, &mut ppu, &mut apu);
+        assert_eq!(io.read(snes_addr!(0:0x2100), &mut ppu, &mut apu), 0x5A);
+    }
+
+    #[test]
+    fn test_ppu_read_only_ports_ignore_writes() {
+        let (mut io, mut ppu, mut apu) = init_all();
+        io.write(snes_addr!(0:0x2134), 0x12, &mut ppu, &mut apu);
+        assert_eq!(ppu.regs.mpyl, 0x00);
+    }
+
+    #[test]
+    fn test_slhv_latches_counters() {
+        let (mut io, mut ppu, mut apu) = init_all();
+        ppu.scanline = 0x105;
+        io.h_cycle = 4 * 200;
+        io.read(snes_addr!(0:0x2137), &mut ppu, &mut apu);
+        assert_eq!(io.read(snes_addr!(0:0x213F), &mut ppu, &mut apu) & 0x40, 0x40);
+        assert_eq!(io.read(snes_addr!(0:0x213C), &mut ppu, &mut apu), 200);
+        assert_eq!(io.read(snes_addr!(0:0x213C), &mut ppu, &mut apu), 0x00);
+        assert_eq!(io.read(snes_addr!(0:0x213D), &mut ppu, &mut apu), 0x05);
+        assert_eq!(io.read(snes_addr!(0:0x213D), &mut ppu, &mut apu), 0x01);
+
+        // No latch while WRIO bit 7 is clear
+        io.write(snes_addr!(0:0x4201), 0x7F, &mut ppu, &mut apu);
+        io.h_cycle = 0;
+        io.read(snes_addr!(0:0x2137), &mut ppu, &mut apu);
+        assert_eq!(io.read(snes_addr!(0:0x213F), &mut ppu, &mut apu) & 0x40, 0);
+        assert_eq!(io.read(snes_addr!(0:0x213C), &mut ppu, &mut apu), 200);
+    }
+
+    #[test]
+    fn test_super_scope_latches_counters() {
+        let (mut io, mut ppu, mut apu) = init_all();
+        let mut scope = SuperScope::default();
+        scope.aim(100, 50);
+        io.joypads.connect(1, Box::new(scope));
+        for _ in 0..ppu.video_standard.scanlines_per_frame() {
+            io.on_scanline(&mut ppu);
+            ppu.step_scanline();
+        }
+        assert_eq!(io.read(snes_addr!(0:0x213F), &mut ppu, &mut apu) & 0x40, 0x40);
+        let h = 100 + super_scope::H_OFFSET;
+        assert_eq!(io.read(snes_addr!(0:0x213C), &mut ppu, &mut apu), h as u8);
+        assert_eq!(io.read(snes_addr!(0:0x213D), &mut ppu, &mut apu), 50 + super_scope::V_OFFSET as u8);
+    }
+
+    #[test]
+    fn test_auto_read_at_vblank() {
+        let (mut io, mut ppu, mut apu) = init_all();
+        io.joypads.device_mut::<Gamepad>(0).unwrap().press(gamepad::START);
+
+        ppu.vblank_started = true;
+        io.on_scanline(&mut ppu);
+        assert_eq!(io.joy1, 0, "auto-read disabled in NMITIMEN");
+
+        io.write(snes_addr!(0:0x4200), 0x01, &mut ppu, &mut apu);
+        io.on_scanline(&mut ppu);
+        assert_eq!(io.read(snes_addr!(0:0x4219), &mut ppu, &mut apu), 0x10);
+        assert_eq!(io.read(snes_addr!(0:0x4218), &mut ppu, &mut apu), 0x00);
+    }
+
+    #[test]
+    fn test_apu_ports_mirrored() {
+        let (mut io, mut ppu, mut apu) = init_all();
+
+        io.write(snes_addr!(0:0x2141), 0x12, &mut ppu, &mut apu);
+        io.write(snes_addr!(0:0x217E), 0x34, &mut ppu, &mut apu);
+        assert_eq!(apu.memory.port_in, [0x00, 0x12, 0x34, 0x00]);
+
+        apu.memory.port_out = [0xAA, 0xBB, 0xCC, 0xDD];
+        assert_eq!(io.read(snes_addr!(0:0x2143), &mut ppu, &mut apu), 0xDD);
+        assert_eq!(io.read(snes_addr!(0:0x2144), &mut ppu, &mut apu), 0xAA);
+    }
+
+    #[test]
+    fn test_apu_port_access_catches_up() {
+        let (mut io, mut ppu, mut apu) = init_all();
+        apu.cpu.regs.pc = 0x0100; // NOPs in the cleared RAM
+
+        apu.advance_master_cycles(apu::apu::NTSC_MASTER_CLOCK_HZ / 1000);
+        assert_eq!(apu.cycles, 0, "the APU runs lazily");
+        io.read(snes_addr!(0:0x2140), &mut ppu, &mut apu);
+        assert_eq!(apu.cycles, 1023, "21477 master cycles are 1023.98 SPC700 cycles");
+    }
+
+    #[cfg(feature = "event-log")]
+    #[test]
+    fn test_event_log_records_dma_register_writes() {
+        let (mut io, mut ppu, mut apu) = init_all();
+        io.event_log.set_enabled(true);
+        ppu.scanline = 37;
+        io.h_cycle = 4 * 120 + 3;
+
+        io.write(snes_addr!(0:0x4200), 0x81, &mut ppu, &mut apu);
+        io.write(snes_addr!(0:0x4301), 0x18, &mut ppu, &mut apu);
+        io.write(snes_addr!(0:0x420B), 0x01, &mut ppu, &mut apu);
+
+        let events = io.event_log.events();
+        assert_eq!(events.len(), 2, "NMITIMEN is not a PPU/DMA register");
+        assert_eq!(events[0].addr, snes_addr!(0:0x4301));
+        assert_eq!(events[0].value, 0x18);
+        assert_eq!((events[0].scanline, events[0].dot), (37, 120));
+        assert_eq!(events[1].addr, snes_addr!(0:0x420B));
+    }
 }
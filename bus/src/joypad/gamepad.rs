@@ -0,0 +1,115 @@
+use crate::joypad::Device;
+use std::any::Any;
+
+// Buttons, in the order of the JOYn registers: the first bit shifted out is B
+pub const B: u16 = 0x8000;
+pub const Y: u16 = 0x4000;
+pub const SELECT: u16 = 0x2000;
+pub const START: u16 = 0x1000;
+pub const UP: u16 = 0x0800;
+pub const DOWN: u16 = 0x0400;
+pub const LEFT: u16 = 0x0200;
+pub const RIGHT: u16 = 0x0100;
+pub const A: u16 = 0x0080;
+pub const X: u16 = 0x0040;
+pub const L: u16 = 0x0020;
+pub const R: u16 = 0x0010;
+
+/// Standard controller: 12 buttons shifted out on D0, followed by a 4-bit ID of 0
+/// and then ones.
+#[derive(Debug, Clone, Default)]
+pub struct Gamepad {
+    /// Buttons currently held, see the constants of this module
+    pub buttons: u16,
+    /// Buttons latched for the current read
+    shift: u16,
+    /// Number of bits shifted out since the latch
+    count: u8,
+    latch: bool,
+}
+
+impl Gamepad {
+    pub fn press(&mut self, buttons: u16) {
+        self.buttons |= buttons;
+    }
+
+    pub fn release(&mut self, buttons: u16) {
+        self.buttons &= !buttons;
+    }
+
+    /// Next bit of the report, shifted out MSB first.
+    pub(crate) fn next_bit(&mut self) -> u8 {
+        if self.latch {
+            return (self.buttons >> 15) as u8;
+        }
+        if self.count >= 16 {
+            return 1;
+        }
+        let bit = (self.shift >> 15) as u8;
+        self.shift <<= 1;
+        self.count += 1;
+        bit
+    }
+}
+
+impl Device for Gamepad {
+    fn name(&self) -> &'static str {
+        "Gamepad"
+    }
+
+    fn set_latch(&mut self, latch: bool) {
+        self.latch = latch;
+        if latch {
+            // The low 4 bits are the controller ID
+            self.shift = self.buttons & 0xFFF0;
+            self.count = 0;
+        }
+    }
+
+    fn read_data(&mut self, _io_bit: bool) -> u8 {
+        self.next_bit()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_bits(pad: &mut Gamepad, count: usize) -> Vec<u8> {
+        (0..count).map(|_| pad.read_data(false)).collect()
+    }
+
+    #[test]
+    fn test_serial_report() {
+        let mut pad = Gamepad::default();
+        pad.press(B | START | A);
+        pad.set_latch(true);
+        pad.set_latch(false);
+
+        let bits = read_bits(&mut pad, 18);
+        assert_eq!(bits, [1, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn test_buttons_latched() {
+        let mut pad = Gamepad::default();
+        pad.press(B);
+        pad.set_latch(true);
+        pad.set_latch(false);
+        pad.release(B);
+
+        assert_eq!(pad.read_data(false), 1);
+    }
+
+    #[test]
+    fn test_latch_high_returns_first_button() {
+        let mut pad = Gamepad::default();
+        pad.press(B);
+        pad.set_latch(true);
+        assert_eq!(read_bits(&mut pad, 3), [1, 1, 1]);
+    }
+}
@@ -0,0 +1,245 @@
+//! Controller ports and the devices plugged into them (gamepad, Multitap, mouse, Super Scope).
+//!
+//! Each port exposes a latch line shared by both ports (JOYOUT bit 0), a clock pulsed by
+//! reads of JOYSER0/JOYSER1, two data lines D0/D1 and a programmable I/O line (IOBit, WRIO
+//! bits 6 and 7). Devices implement their own serial protocol on top of those lines.
+//!
+//! # Reference
+//! [SNESdev Wiki - Standard controller](https://snes.nesdev.org/wiki/Standard_controller)
+
+pub mod gamepad;
+pub mod mouse;
+pub mod multitap;
+pub mod super_scope;
+
+use common::save_state::{StateError, StateReader, StateWriter};
+use std::any::Any;
+
+pub use gamepad::Gamepad;
+pub use mouse::Mouse;
+pub use multitap::Multitap;
+pub use super_scope::SuperScope;
+
+/// Number of bits clocked per controller during joypad auto-read
+const AUTO_READ_BITS: usize = 16;
+
+/// A controller plugged into a port. `Send` like the rest of the console.
+pub trait Device: Any + Send {
+    fn name(&self) -> &'static str;
+
+    /// Latch line: while it is high, the device reloads its shift registers.
+    fn set_latch(&mut self, latch: bool);
+
+    /// Clocks the next bits out of the device: D0 in bit 0, D1 in bit 1.
+    ///
+    /// `io_bit` is the level of the programmable I/O line of the port.
+    fn read_data(&mut self, io_bit: bool) -> u8;
+
+    /// Called on each scanline with the beam position. Returns the horizontal
+    /// position at which the device pulls IOBit low to latch the PPU counters, if any.
+    fn scanline(&mut self, _scanline: u16) -> Option<u16> {
+        None
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// Position of the beam latched by a light gun, as read from OPHCT/OPVCT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeamPosition {
+    pub h: u16,
+    pub v: u16,
+}
+
+/// The two controller ports, with a gamepad plugged into the first one by default.
+pub struct Joypads {
+    ports: [Option<Box<dyn Device>>; 2],
+    latch: bool,
+    /// Last beam position latched by a device on port 2 (Super Scope)
+    pub beam_latch: Option<BeamPosition>,
+}
+
+impl Default for Joypads {
+    fn default() -> Self {
+        Self {
+            ports: [Some(Box::new(Gamepad::default())), None],
+            latch: false,
+            beam_latch: None,
+        }
+    }
+}
+
+impl Joypads {
+    /// Plugs `device` into `port` (0 or 1), returning the device it replaces.
+    pub fn connect(&mut self, port: usize, device: Box<dyn Device>) -> Option<Box<dyn Device>> {
+        self.ports[port].replace(device)
+    }
+
+    pub fn disconnect(&mut self, port: usize) -> Option<Box<dyn Device>> {
+        self.ports[port].take()
+    }
+
+    pub fn device(&self, port: usize) -> Option<&dyn Device> {
+        self.ports[port].as_deref()
+    }
+
+    /// Device plugged into `port`, if it is a `T`.
+    pub fn device_mut<T: Device>(&mut self, port: usize) -> Option<&mut T> {
+        self.ports[port].as_mut()?.as_any_mut().downcast_mut()
+    }
+
+    /// JOYOUT (`$4016`, W): bit 0 drives the latch line of both ports.
+    pub fn write_joyout(&mut self, value: u8) {
+        self.latch = value & 0x01 != 0;
+        for device in self.ports.iter_mut().flatten() {
+            device.set_latch(self.latch);
+        }
+    }
+
+    /// JOYSER0/JOYSER1 (`$4016`/`$4017`, R): data lines of `port` in bits 0–1, clocking it.
+    ///
+    /// Empty ports read 0.
+    pub fn read_joyser(&mut self, port: usize, wrio: u8) -> u8 {
+        let io_bit = io_bit(port, wrio);
+        match &mut self.ports[port] {
+            Some(device) => device.read_data(io_bit) & 0x03,
+            None => 0,
+        }
+    }
+
+    /// Joypad auto-read: latches both ports and shifts 16 bits out of each data line.
+    ///
+    /// Returns JOY1 to JOY4: port 1 D0, port 2 D0, port 1 D1 and port 2 D1.
+    pub fn auto_read(&mut self, wrio: u8) -> [u16; 4] {
+        self.write_joyout(0x01);
+        self.write_joyout(0x00);
+
+        let mut joy = [0u16; 4];
+        for _ in 0..AUTO_READ_BITS {
+            for port in 0..2 {
+                let data = self.read_joyser(port, wrio);
+                joy[port] = (joy[port] << 1) | (data & 0x01) as u16;
+                joy[port + 2] = (joy[port + 2] << 1) | (data >> 1) as u16;
+            }
+        }
+        joy
+    }
+
+    /// Lets the device on port 2 watch the beam; a latch only happens while WRIO bit 7 is set.
+    /// Returns the beam position latched on this scanline, which goes to OPHCT/OPVCT.
+    pub fn scanline(&mut self, scanline: u16, wrio: u8) -> Option<BeamPosition> {
+        let device = self.ports[1].as_mut()?;
+        let h = device.scanline(scanline).filter(|_| io_bit(1, wrio))?;
+        self.beam_latch = Some(BeamPosition { h, v: scanline });
+        self.beam_latch
+    }
+
+    /// Writes the latch line and the beam position latched to a save state. The devices
+    /// are set up by the frontend and are not saved: they reload their shift registers on
+    /// the next latch.
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.bool(self.latch);
+        writer.bool(self.beam_latch.is_some());
+        if let Some(BeamPosition { h, v }) = self.beam_latch {
+            writer.u16(h);
+            writer.u16(v);
+        }
+    }
+
+    /// Restores the state written by [`Self::save_state`], driving the latch line of the
+    /// devices.
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        let latch = reader.bool()?;
+        self.beam_latch = match reader.bool()? {
+            true => Some(BeamPosition {
+                h: reader.u16()?,
+                v: reader.u16()?,
+            }),
+            false => None,
+        };
+        self.write_joyout(latch as u8);
+        Ok(())
+    }
+}
+
+/// Level of the IOBit line of `port`: WRIO bit 6 for port 1, bit 7 for port 2
+fn io_bit(port: usize, wrio: u8) -> bool {
+    wrio & (0x40 << port) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_gamepad_on_port_1() {
+        let mut joypads = Joypads::default();
+        assert_eq!(joypads.device(0).unwrap().name(), "Gamepad");
+        assert!(joypads.device(1).is_none());
+        assert!(joypads.device_mut::<Gamepad>(0).is_some());
+        assert!(joypads.device_mut::<Mouse>(0).is_none());
+    }
+
+    #[test]
+    fn test_empty_port_reads_zero() {
+        let mut joypads = Joypads::default();
+        joypads.write_joyout(0x01);
+        joypads.write_joyout(0x00);
+        assert_eq!(joypads.read_joyser(1, 0xFF), 0);
+    }
+
+    #[test]
+    fn test_auto_read_gamepad() {
+        let mut joypads = Joypads::default();
+        joypads.device_mut::<Gamepad>(0).unwrap().buttons = gamepad::B | gamepad::R;
+
+        let joy = joypads.auto_read(0xFF);
+        assert_eq!(joy, [0x8010, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_auto_read_multitap() {
+        let mut joypads = Joypads::default();
+        let mut multitap = Multitap::default();
+        multitap.pads[0].buttons = gamepad::A;
+        multitap.pads[1].buttons = gamepad::START;
+        multitap.pads[2].buttons = gamepad::UP;
+        joypads.connect(1, Box::new(multitap));
+
+        // IOBit of port 2 high: pads 2 and 3
+        assert_eq!(joypads.auto_read(0xFF), [0, gamepad::A, 0, gamepad::START]);
+        // IOBit low: pads 4 and 5
+        assert_eq!(joypads.auto_read(0x7F), [0, gamepad::UP, 0, 0]);
+    }
+
+    #[test]
+    fn test_swap_devices() {
+        let mut joypads = Joypads::default();
+        let previous = joypads.connect(0, Box::new(Mouse::default()));
+        assert_eq!(previous.unwrap().name(), "Gamepad");
+        assert_eq!(joypads.device(0).unwrap().name(), "Mouse");
+        assert_eq!(joypads.disconnect(0).unwrap().name(), "Mouse");
+        assert!(joypads.device(0).is_none());
+    }
+
+    #[test]
+    fn test_super_scope_latches_beam() {
+        let mut joypads = Joypads::default();
+        let mut scope = SuperScope::default();
+        scope.aim(100, 50);
+        joypads.connect(1, Box::new(scope));
+
+        // No latch while IOBit of port 2 is low
+        for scanline in 0..262 {
+            joypads.scanline(scanline, 0x00);
+        }
+        assert_eq!(joypads.beam_latch, None);
+
+        for scanline in 0..262 {
+            joypads.scanline(scanline, 0x80);
+        }
+        let latch = joypads.beam_latch.unwrap();
+        assert_eq!(latch.v, 50 + super_scope::V_OFFSET);
+        assert_eq!(latch.h, 100 + super_scope::H_OFFSET);
+    }
+}
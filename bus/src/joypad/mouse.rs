@@ -0,0 +1,122 @@
+use crate::joypad::Device;
+use std::any::Any;
+
+/// Largest displacement reported per read, on each axis
+const MAX_DELTA: i32 = 127;
+const SPEEDS: u8 = 3;
+
+/// SNES Mouse: a 32-bit report of buttons, sensitivity and signed displacements.
+///
+/// Displacements accumulate between reads and are reset when the mouse is latched.
+/// Clocking the mouse while the latch is high cycles its sensitivity.
+#[derive(Debug, Clone, Default)]
+pub struct Mouse {
+    pub left: bool,
+    pub right: bool,
+    /// Sensitivity, 0 (slow) to 2 (fast)
+    pub speed: u8,
+    dx: i32,
+    dy: i32,
+    shift: u32,
+    count: u8,
+    latch: bool,
+}
+
+impl Mouse {
+    /// Moves the mouse; positive values go right and down.
+    pub fn move_by(&mut self, dx: i32, dy: i32) {
+        self.dx += dx;
+        self.dy += dy;
+    }
+
+    fn report(&mut self) -> u32 {
+        let dx = self.dx.clamp(-MAX_DELTA, MAX_DELTA);
+        let dy = self.dy.clamp(-MAX_DELTA, MAX_DELTA);
+        self.dx = 0;
+        self.dy = 0;
+
+        // Direction bit (1 = left/up) followed by the 7-bit magnitude
+        let axis = |delta: i32| ((delta < 0) as u32) << 7 | delta.unsigned_abs();
+        (self.right as u32) << 23
+            | (self.left as u32) << 22
+            | (self.speed as u32) << 20
+            | 1 << 16 // Mouse ID
+            | axis(dy) << 8
+            | axis(dx)
+    }
+}
+
+impl Device for Mouse {
+    fn name(&self) -> &'static str {
+        "Mouse"
+    }
+
+    fn set_latch(&mut self, latch: bool) {
+        if latch && !self.latch {
+            self.shift = self.report();
+            self.count = 0;
+        }
+        self.latch = latch;
+    }
+
+    fn read_data(&mut self, _io_bit: bool) -> u8 {
+        if self.latch {
+            self.speed = (self.speed + 1) % SPEEDS;
+            return 0;
+        }
+        if self.count >= 32 {
+            return 1;
+        }
+        let bit = (self.shift >> 31) as u8;
+        self.shift <<= 1;
+        self.count += 1;
+        bit
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_report(mouse: &mut Mouse) -> u32 {
+        mouse.set_latch(true);
+        mouse.set_latch(false);
+        (0..32).fold(0, |report, _| report << 1 | mouse.read_data(false) as u32)
+    }
+
+    #[test]
+    fn test_report() {
+        let mut mouse = Mouse {
+            left: true,
+            speed: 1,
+            ..Mouse::default()
+        };
+        mouse.move_by(5, -3);
+
+        // Buttons and speed, ID, then Y (up by 3) and X (right by 5)
+        assert_eq!(read_report(&mut mouse), 0x0051_8305);
+        // Displacements were consumed
+        assert_eq!(read_report(&mut mouse), 0x0051_0000);
+    }
+
+    #[test]
+    fn test_displacement_clamped() {
+        let mut mouse = Mouse::default();
+        mouse.move_by(-500, 300);
+        assert_eq!(read_report(&mut mouse) & 0xFFFF, 0x7FFF);
+    }
+
+    #[test]
+    fn test_speed_cycles_while_latched() {
+        let mut mouse = Mouse::default();
+        mouse.set_latch(true);
+        for expected in [1, 2, 0] {
+            mouse.read_data(false);
+            assert_eq!(mouse.speed, expected);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::joypad::{Device, Gamepad};
+use std::any::Any;
+
+/// Multitap adapter: 4 gamepads behind one port, for up to 5 players.
+///
+/// IOBit selects the pair of pads read: high for the first two (D0 and D1), low for the
+/// last two. While the latch is high, D1 reads 1 so games can detect the adapter.
+#[derive(Debug, Clone, Default)]
+pub struct Multitap {
+    pub pads: [Gamepad; 4],
+    latch: bool,
+}
+
+impl Device for Multitap {
+    fn name(&self) -> &'static str {
+        "Multitap"
+    }
+
+    fn set_latch(&mut self, latch: bool) {
+        self.latch = latch;
+        for pad in &mut self.pads {
+            pad.set_latch(latch);
+        }
+    }
+
+    fn read_data(&mut self, io_bit: bool) -> u8 {
+        if self.latch {
+            return 0x02;
+        }
+        let first = if io_bit { 0 } else { 2 };
+        let [d0, d1] = [first, first + 1].map(|pad| self.pads[pad].next_bit());
+        d0 | d1 << 1
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::joypad::gamepad;
+
+    #[test]
+    fn test_detection_while_latched() {
+        let mut multitap = Multitap::default();
+        multitap.set_latch(true);
+        assert_eq!(multitap.read_data(true), 0x02);
+    }
+
+    #[test]
+    fn test_pairs_selected_by_io_bit() {
+        let mut multitap = Multitap::default();
+        multitap.pads[1].press(gamepad::B);
+        multitap.pads[2].press(gamepad::B | gamepad::Y);
+        multitap.set_latch(true);
+        multitap.set_latch(false);
+
+        assert_eq!(multitap.read_data(true), 0b10);
+        assert_eq!(multitap.read_data(false), 0b01);
+        // Each pair keeps its own position in the report
+        assert_eq!(multitap.read_data(false), 0b01);
+        assert_eq!(multitap.read_data(true), 0b00);
+    }
+}
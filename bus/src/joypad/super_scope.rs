@@ -0,0 +1,119 @@
+use crate::joypad::Device;
+use std::any::Any;
+
+/// Horizontal counter value at the first visible pixel of a scanline
+pub const H_OFFSET: u16 = 22;
+/// Vertical counter value of the first visible scanline
+pub const V_OFFSET: u16 = 1;
+const SCREEN_WIDTH: u16 = 256;
+const SCREEN_HEIGHT: u16 = 224;
+
+/// Super Scope light gun, on port 2.
+///
+/// Reports an 8-bit status (fire, cursor, turbo, pause, offscreen) followed by ones.
+/// When the beam reaches the aimed pixel, the scope pulls IOBit low, which makes the PPU
+/// latch its H/V counters: games calibrate against those counters, so the offsets only
+/// need to be consistent.
+#[derive(Debug, Clone, Default)]
+pub struct SuperScope {
+    pub fire: bool,
+    pub cursor: bool,
+    pub turbo: bool,
+    pub pause: bool,
+    /// Aimed pixel, `None` when pointing away from the screen
+    target: Option<(u16, u16)>,
+    shift: u8,
+    count: u8,
+    latch: bool,
+}
+
+impl SuperScope {
+    /// Aims at screen pixel (`x`, `y`); positions outside the picture are offscreen.
+    pub fn aim(&mut self, x: u16, y: u16) {
+        self.target = (x < SCREEN_WIDTH && y < SCREEN_HEIGHT).then_some((x, y));
+    }
+
+    pub fn aim_offscreen(&mut self) {
+        self.target = None;
+    }
+
+    fn report(&self) -> u8 {
+        (self.fire as u8) << 7
+            | (self.cursor as u8) << 6
+            | (self.turbo as u8) << 5
+            | (self.pause as u8) << 4
+            | (self.target.is_none() as u8) << 1
+    }
+}
+
+impl Device for SuperScope {
+    fn name(&self) -> &'static str {
+        "Super Scope"
+    }
+
+    fn set_latch(&mut self, latch: bool) {
+        self.latch = latch;
+        if latch {
+            self.shift = self.report();
+            self.count = 0;
+        }
+    }
+
+    fn read_data(&mut self, _io_bit: bool) -> u8 {
+        if self.latch {
+            return self.report() >> 7;
+        }
+        if self.count >= 8 {
+            return 1;
+        }
+        let bit = self.shift >> 7;
+        self.shift <<= 1;
+        self.count += 1;
+        bit
+    }
+
+    fn scanline(&mut self, scanline: u16) -> Option<u16> {
+        let (x, y) = self.target?;
+        (scanline == y + V_OFFSET).then_some(x + H_OFFSET)
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_report(scope: &mut SuperScope) -> Vec<u8> {
+        scope.set_latch(true);
+        scope.set_latch(false);
+        (0..10).map(|_| scope.read_data(true)).collect()
+    }
+
+    #[test]
+    fn test_report() {
+        let mut scope = SuperScope {
+            fire: true,
+            pause: true,
+            ..SuperScope::default()
+        };
+        scope.aim(10, 10);
+        assert_eq!(read_report(&mut scope), [1, 0, 0, 1, 0, 0, 0, 0, 1, 1]);
+
+        scope.aim(300, 10);
+        assert_eq!(read_report(&mut scope), [1, 0, 0, 1, 0, 0, 1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn test_beam_only_on_target_line() {
+        let mut scope = SuperScope::default();
+        scope.aim(0, 0);
+        assert_eq!(scope.scanline(0), None);
+        assert_eq!(scope.scanline(V_OFFSET), Some(H_OFFSET));
+
+        scope.aim_offscreen();
+        assert_eq!(scope.scanline(V_OFFSET), None);
+    }
+}
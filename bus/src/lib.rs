@@ -1,6 +1,14 @@
+#[cfg(feature = "access-log")]
+pub mod access_log;
 pub mod bus;
+pub mod cheats;
 pub mod constants;
+pub mod coprocessor;
+#[cfg(feature = "event-log")]
+pub mod event_log;
 pub mod io;
+pub mod joypad;
+pub mod register_info;
 pub mod rom;
 pub mod wram;
 
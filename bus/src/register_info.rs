@@ -0,0 +1,453 @@
+//! Metadata of the memory-mapped registers the bus implements: address, name, bit fields
+//! and direction, for debug views and trace logs to print register accesses by name.
+//!
+//! A tested invariant keeps the tables honest: every I/O address that reads back something
+//! other than the open bus is listed as readable, and nothing else is.
+
+use crate::constants::{IO_END_ADDRESS, IO_START_ADDRESS};
+use Access::{Read as R, ReadWrite as RW, Write as W};
+use common::snes_address::SnesAddress;
+use std::fmt;
+
+/// Direction in which a register is accessed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    pub fn readable(self) -> bool {
+        matches!(self, Access::Read | Access::ReadWrite)
+    }
+
+    pub fn writable(self) -> bool {
+        matches!(self, Access::Write | Access::ReadWrite)
+    }
+}
+
+/// Bits of a register holding one value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitField {
+    pub name: &'static str,
+    /// Lowest bit of the field
+    pub shift: u8,
+    pub width: u8,
+}
+
+impl BitField {
+    const fn new(name: &'static str, shift: u8, width: u8) -> Self {
+        Self { name, shift, width }
+    }
+
+    const fn flag(name: &'static str, bit: u8) -> Self {
+        Self::new(name, bit, 1)
+    }
+
+    /// Value of the field in the register value `value`
+    pub fn get(&self, value: u8) -> u8 {
+        (value >> self.shift) & ((1u16 << self.width) - 1) as u8
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterInfo {
+    /// Address in the I/O area, that of channel 0 for the DMA channel registers
+    pub addr: u16,
+    /// Name in the usual documentation; `n` stands for the channel of DMA registers
+    pub name: &'static str,
+    pub access: Access,
+    /// Bit fields, empty when the register holds one 8-bit value
+    pub fields: &'static [BitField],
+    pub description: &'static str,
+}
+
+const fn reg(
+    addr: u16,
+    name: &'static str,
+    access: Access,
+    fields: &'static [BitField],
+    description: &'static str,
+) -> RegisterInfo {
+    RegisterInfo {
+        addr,
+        name,
+        access,
+        fields,
+        description,
+    }
+}
+
+const TILE_SIZES: &[BitField] = &[
+    BitField::flag("bg4_16x16", 7),
+    BitField::flag("bg3_16x16", 6),
+    BitField::flag("bg2_16x16", 5),
+    BitField::flag("bg1_16x16", 4),
+    BitField::flag("bg3_priority", 3),
+    BitField::new("mode", 0, 3),
+];
+const SCREEN_BASE: &[BitField] = &[BitField::new("base", 2, 6), BitField::new("size", 0, 2)];
+const LAYERS: &[BitField] = &[
+    BitField::flag("obj", 4),
+    BitField::flag("bg4", 3),
+    BitField::flag("bg3", 2),
+    BitField::flag("bg2", 1),
+    BitField::flag("bg1", 0),
+];
+
+/// PPU registers, `$2100-$2133` write only and `$2134-$213F` read only
+#[rustfmt::skip]
+pub const PPU_REGISTERS: &[RegisterInfo] = &[
+    reg(0x2100, "INIDISP", W, &[BitField::flag("force_blank", 7), BitField::new("brightness", 0, 4)], "Screen display"),
+    reg(0x2101, "OBSEL", W, &[BitField::new("size", 5, 3), BitField::new("gap", 3, 2), BitField::new("base", 0, 3)], "Object size and tile base"),
+    reg(0x2102, "OAMADDL", W, &[], "OAM word address, low byte"),
+    reg(0x2103, "OAMADDH", W, &[BitField::flag("priority_rotation", 7), BitField::flag("table", 0)], "OAM word address, high bit"),
+    reg(0x2104, "OAMDATA", W, &[], "OAM data write"),
+    reg(0x2105, "BGMODE", W, TILE_SIZES, "BG mode and tile size"),
+    reg(0x2106, "MOSAIC", W, &[BitField::new("size", 4, 4), BitField::flag("bg4", 3), BitField::flag("bg3", 2), BitField::flag("bg2", 1), BitField::flag("bg1", 0)], "Mosaic size and layers"),
+    reg(0x2107, "BG1SC", W, SCREEN_BASE, "BG1 tilemap base and size"),
+    reg(0x2108, "BG2SC", W, SCREEN_BASE, "BG2 tilemap base and size"),
+    reg(0x2109, "BG3SC", W, SCREEN_BASE, "BG3 tilemap base and size"),
+    reg(0x210A, "BG4SC", W, SCREEN_BASE, "BG4 tilemap base and size"),
+    reg(0x210B, "BG12NBA", W, &[BitField::new("bg2", 4, 4), BitField::new("bg1", 0, 4)], "BG1/BG2 tile base"),
+    reg(0x210C, "BG34NBA", W, &[BitField::new("bg4", 4, 4), BitField::new("bg3", 0, 4)], "BG3/BG4 tile base"),
+    reg(0x210D, "BG1HOFS", W, &[], "BG1 horizontal scroll (written twice), also M7HOFS"),
+    reg(0x210E, "BG1VOFS", W, &[], "BG1 vertical scroll (written twice), also M7VOFS"),
+    reg(0x210F, "BG2HOFS", W, &[], "BG2 horizontal scroll (written twice)"),
+    reg(0x2110, "BG2VOFS", W, &[], "BG2 vertical scroll (written twice)"),
+    reg(0x2111, "BG3HOFS", W, &[], "BG3 horizontal scroll (written twice)"),
+    reg(0x2112, "BG3VOFS", W, &[], "BG3 vertical scroll (written twice)"),
+    reg(0x2113, "BG4HOFS", W, &[], "BG4 horizontal scroll (written twice)"),
+    reg(0x2114, "BG4VOFS", W, &[], "BG4 vertical scroll (written twice)"),
+    reg(0x2115, "VMAIN", W, &[BitField::flag("after_high", 7), BitField::new("remap", 2, 2), BitField::new("step", 0, 2)], "VRAM address increment mode"),
+    reg(0x2116, "VMADDL", W, &[], "VRAM word address, low byte"),
+    reg(0x2117, "VMADDH", W, &[], "VRAM word address, high byte"),
+    reg(0x2118, "VMDATAL", W, &[], "VRAM data write, low byte"),
+    reg(0x2119, "VMDATAH", W, &[], "VRAM data write, high byte"),
+    reg(0x211A, "M7SEL", W, &[BitField::new("outside", 6, 2), BitField::flag("flip_y", 1), BitField::flag("flip_x", 0)], "Mode 7 settings"),
+    reg(0x211B, "M7A", W, &[], "Mode 7 matrix A (written twice)"),
+    reg(0x211C, "M7B", W, &[], "Mode 7 matrix B (written twice)"),
+    reg(0x211D, "M7C", W, &[], "Mode 7 matrix C (written twice)"),
+    reg(0x211E, "M7D", W, &[], "Mode 7 matrix D (written twice)"),
+    reg(0x211F, "M7X", W, &[], "Mode 7 center X (written twice)"),
+    reg(0x2120, "M7Y", W, &[], "Mode 7 center Y (written twice)"),
+    reg(0x2121, "CGADD", W, &[], "CGRAM word address"),
+    reg(0x2122, "CGDATA", W, &[], "CGRAM data write (written twice)"),
+    reg(0x2123, "W12SEL", W, &[BitField::new("bg2", 4, 4), BitField::new("bg1", 0, 4)], "Window mask settings for BG1/BG2"),
+    reg(0x2124, "W34SEL", W, &[BitField::new("bg4", 4, 4), BitField::new("bg3", 0, 4)], "Window mask settings for BG3/BG4"),
+    reg(0x2125, "WOBJSEL", W, &[BitField::new("color", 4, 4), BitField::new("obj", 0, 4)], "Window mask settings for OBJ/color"),
+    reg(0x2126, "WH0", W, &[], "Window 1 left position"),
+    reg(0x2127, "WH1", W, &[], "Window 1 right position"),
+    reg(0x2128, "WH2", W, &[], "Window 2 left position"),
+    reg(0x2129, "WH3", W, &[], "Window 2 right position"),
+    reg(0x212A, "WBGLOG", W, &[BitField::new("bg4", 6, 2), BitField::new("bg3", 4, 2), BitField::new("bg2", 2, 2), BitField::new("bg1", 0, 2)], "Window mask logic for BGs"),
+    reg(0x212B, "WOBJLOG", W, &[BitField::new("color", 2, 2), BitField::new("obj", 0, 2)], "Window mask logic for OBJ/color"),
+    reg(0x212C, "TM", W, LAYERS, "Main screen layers"),
+    reg(0x212D, "TS", W, LAYERS, "Sub screen layers"),
+    reg(0x212E, "TMW", W, LAYERS, "Window masking on the main screen"),
+    reg(0x212F, "TSW", W, LAYERS, "Window masking on the sub screen"),
+    reg(0x2130, "CGWSEL", W, &[BitField::new("clip", 6, 2), BitField::new("prevent", 4, 2), BitField::flag("subscreen", 1), BitField::flag("direct_color", 0)], "Color math control"),
+    reg(0x2131, "CGADSUB", W, &[BitField::flag("subtract", 7), BitField::flag("half", 6), BitField::flag("backdrop", 5), BitField::flag("obj", 4), BitField::flag("bg4", 3), BitField::flag("bg3", 2), BitField::flag("bg2", 1), BitField::flag("bg1", 0)], "Color math layers"),
+    reg(0x2132, "COLDATA", W, &[BitField::flag("blue", 7), BitField::flag("green", 6), BitField::flag("red", 5), BitField::new("intensity", 0, 5)], "Fixed color"),
+    reg(0x2133, "SETINI", W, &[BitField::flag("external_sync", 7), BitField::flag("extbg", 6), BitField::flag("pseudo_hires", 3), BitField::flag("overscan", 2), BitField::flag("obj_interlace", 1), BitField::flag("interlace", 0)], "Screen mode"),
+    reg(0x2134, "MPYL", R, &[], "Signed multiplication result, low byte"),
+    reg(0x2135, "MPYM", R, &[], "Signed multiplication result, middle byte"),
+    reg(0x2136, "MPYH", R, &[], "Signed multiplication result, high byte"),
+    reg(0x2137, "SLHV", R, &[], "Latch of the H/V counters into OPHCT/OPVCT while WRIO bit 7 is set"),
+    reg(0x2138, "RDOAM", R, &[], "OAM data read"),
+    reg(0x2139, "RDVRAML", R, &[], "VRAM data read, low byte"),
+    reg(0x213A, "RDVRAMH", R, &[], "VRAM data read, high byte"),
+    reg(0x213B, "RDCGRAM", R, &[], "CGRAM data read"),
+    reg(0x213C, "OPHCT", R, &[], "Latched horizontal counter, low byte then bit 8"),
+    reg(0x213D, "OPVCT", R, &[], "Latched vertical counter, low byte then bit 8"),
+    reg(0x213E, "STAT77", R, &[BitField::flag("time_over", 7), BitField::flag("range_over", 6), BitField::new("version", 0, 4)], "PPU1 status, not emulated yet (reads 0)"),
+    reg(0x213F, "STAT78", R, &[BitField::flag("field", 7), BitField::flag("latched", 6), BitField::flag("pal", 4), BitField::new("version", 0, 4)], "PPU2 status, reading it clears the latch flag"),
+];
+
+/// APU ports (`$2140-$2143`, mirrored up to `$217F`) and WRAM ports (`$2180-$2183`)
+#[rustfmt::skip]
+pub const APU_WRAM_REGISTERS: &[RegisterInfo] = &[
+    reg(0x2140, "APUIO0", RW, &[], "APU port 0, the value written by the other side"),
+    reg(0x2141, "APUIO1", RW, &[], "APU port 1, the value written by the other side"),
+    reg(0x2142, "APUIO2", RW, &[], "APU port 2, the value written by the other side"),
+    reg(0x2143, "APUIO3", RW, &[], "APU port 3, the value written by the other side"),
+    reg(0x2180, "WMDATA", RW, &[], "WRAM data at WMADD, which then increments"),
+    reg(0x2181, "WMADDL", W, &[], "WRAM address of WMDATA, low byte"),
+    reg(0x2182, "WMADDM", W, &[], "WRAM address of WMDATA, middle byte"),
+    reg(0x2183, "WMADDH", W, &[BitField::flag("bank", 0)], "WRAM address of WMDATA, bit 16"),
+];
+
+/// CPU registers, `$4016-$4017` and `$4200-$421F`
+#[rustfmt::skip]
+pub const CPU_REGISTERS: &[RegisterInfo] = &[
+    reg(0x4016, "JOYOUT", W, &[BitField::flag("latch", 0)], "Controller latch"),
+    reg(0x4016, "JOYSER0", R, &[BitField::flag("data2", 1), BitField::flag("data1", 0)], "Serial data of controller port 1"),
+    reg(0x4017, "JOYSER1", R, &[BitField::flag("data2", 1), BitField::flag("data1", 0)], "Serial data of controller port 2"),
+    reg(0x4200, "NMITIMEN", W, &[BitField::flag("nmi", 7), BitField::new("irq", 4, 2), BitField::flag("auto_joypad", 0)], "Interrupt and auto joypad read enable"),
+    reg(0x4201, "WRIO", W, &[], "Programmable I/O port output"),
+    reg(0x4202, "WRMPYA", W, &[], "Multiplicand"),
+    reg(0x4203, "WRMPYB", W, &[], "Multiplier, starts the multiplication"),
+    reg(0x4204, "WRDIVL", W, &[], "Dividend, low byte"),
+    reg(0x4205, "WRDIVH", W, &[], "Dividend, high byte"),
+    reg(0x4206, "WRDIVB", W, &[], "Divisor, starts the division"),
+    reg(0x4207, "HTIMEL", W, &[], "H-IRQ dot, low byte"),
+    reg(0x4208, "HTIMEH", W, &[], "H-IRQ dot, high bit"),
+    reg(0x4209, "VTIMEL", W, &[], "V-IRQ scanline, low byte"),
+    reg(0x420A, "VTIMEH", W, &[], "V-IRQ scanline, high bit"),
+    reg(0x420B, "MDMAEN", W, &[], "General purpose DMA channels to run"),
+    reg(0x420C, "HDMAEN", W, &[], "HDMA channels enabled"),
+    reg(0x420D, "MEMSEL", W, &[BitField::flag("fastrom", 0)], "ROM access speed"),
+    reg(0x4210, "RDNMI", R, &[BitField::flag("nmi", 7), BitField::new("version", 0, 4)], "V-Blank NMI flag and CPU version"),
+    reg(0x4211, "TIMEUP", R, &[BitField::flag("irq", 7)], "H/V timer IRQ flag"),
+    reg(0x4212, "HVBJOY", R, &[BitField::flag("vblank", 7), BitField::flag("hblank", 6), BitField::flag("auto_joypad_busy", 0)], "Screen and joypad status"),
+    reg(0x4213, "RDIO", R, &[], "Programmable I/O port input"),
+    reg(0x4214, "RDDIVL", R, &[], "Quotient, low byte"),
+    reg(0x4215, "RDDIVH", R, &[], "Quotient, high byte"),
+    reg(0x4216, "RDMPYL", R, &[], "Product or remainder, low byte"),
+    reg(0x4217, "RDMPYH", R, &[], "Product or remainder, high byte"),
+    reg(0x4218, "JOY1L", R, &[], "Auto read of controller 1, low byte"),
+    reg(0x4219, "JOY1H", R, &[], "Auto read of controller 1, high byte"),
+    reg(0x421A, "JOY2L", R, &[], "Auto read of controller 2, low byte"),
+    reg(0x421B, "JOY2H", R, &[], "Auto read of controller 2, high byte"),
+    reg(0x421C, "JOY3L", R, &[], "Auto read of controller 3, low byte"),
+    reg(0x421D, "JOY3H", R, &[], "Auto read of controller 3, high byte"),
+    reg(0x421E, "JOY4L", R, &[], "Auto read of controller 4, low byte"),
+    reg(0x421F, "JOY4H", R, &[], "Auto read of controller 4, high byte"),
+];
+
+/// Registers of DMA channel 0 (`$4300-$430F`), those of channel `n` being `n * 0x10` higher
+#[rustfmt::skip]
+pub const DMA_CHANNEL_REGISTERS: &[RegisterInfo] = &[
+    reg(0x4300, "DMAPn", RW, &[BitField::flag("to_cpu", 7), BitField::flag("indirect", 6), BitField::new("step", 3, 2), BitField::new("mode", 0, 3)], "Transfer parameters"),
+    reg(0x4301, "BBADn", RW, &[], "B-bus address ($21xx)"),
+    reg(0x4302, "A1TnL", RW, &[], "A-bus address, low byte"),
+    reg(0x4303, "A1TnH", RW, &[], "A-bus address, high byte"),
+    reg(0x4304, "A1Bn", RW, &[], "A-bus bank"),
+    reg(0x4305, "DASnL", RW, &[], "DMA byte count or HDMA indirect address, low byte"),
+    reg(0x4306, "DASnH", RW, &[], "DMA byte count or HDMA indirect address, high byte"),
+    reg(0x4307, "DASBn", RW, &[], "HDMA indirect address bank"),
+    reg(0x4308, "A2AnL", RW, &[], "HDMA table address, low byte"),
+    reg(0x4309, "A2AnH", RW, &[], "HDMA table address, high byte"),
+    reg(0x430A, "NLTRn", RW, &[BitField::flag("repeat", 7), BitField::new("lines", 0, 7)], "HDMA line counter"),
+    reg(0x430B, "UNUSEDn", RW, &[], "Unused byte"),
+    reg(0x430F, "UNUSEDn", RW, &[], "Mirror of $43nB"),
+];
+
+/// Register of the bus, with its DMA channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Register {
+    pub info: &'static RegisterInfo,
+    /// Channel of a DMA channel register
+    pub channel: Option<u8>,
+}
+
+impl Register {
+    /// The register accessed at the I/O address `addr`, reading or writing depending on
+    /// `write`. The mirrors of the APU ports give the port they mirror.
+    pub fn at(addr: u16, write: bool) -> Option<Self> {
+        let matches = |info: &RegisterInfo| {
+            if write {
+                info.access.writable()
+            } else {
+                info.access.readable()
+            }
+        };
+        if (0x4300..0x4380).contains(&addr) {
+            let info = DMA_CHANNEL_REGISTERS
+                .iter()
+                .find(|info| info.addr & 0xF == addr & 0xF && matches(info))?;
+            return Some(Self {
+                info,
+                channel: Some(((addr >> 4) & 0x7) as u8),
+            });
+        }
+        let addr = match addr {
+            0x2140..0x2180 => 0x2140 | (addr & 0x3),
+            _ => addr,
+        };
+        PPU_REGISTERS
+            .iter()
+            .chain(APU_WRAM_REGISTERS)
+            .chain(CPU_REGISTERS)
+            .find(|info| info.addr == addr && matches(info))
+            .map(|info| Self {
+                info,
+                channel: None,
+            })
+    }
+
+    /// The register at `addr` in the banks mapping the I/O area
+    pub fn at_address(addr: SnesAddress, write: bool) -> Option<Self> {
+        match addr.bank {
+            0x00..=0x3F | 0x80..=0xBF
+                if (IO_START_ADDRESS..IO_END_ADDRESS).contains(&addr.addr) =>
+            {
+                Self::at(addr.addr, write)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn addr(&self) -> u16 {
+        self.info.addr + self.channel.map_or(0, |channel| channel as u16 * 0x10)
+    }
+
+    /// The bit fields of `value`, as `name=value` pairs separated by spaces.
+    pub fn fields(&self, value: u8) -> impl fmt::Display + '_ {
+        Fields {
+            fields: self.info.fields,
+            value,
+        }
+    }
+}
+
+/// Name of the register, with the channel number of DMA registers
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.channel {
+            Some(channel) => {
+                let (prefix, suffix) = self
+                    .info
+                    .name
+                    .split_once('n')
+                    .unwrap_or((self.info.name, ""));
+                write!(f, "{prefix}{channel}{suffix}")
+            }
+            None => f.write_str(self.info.name),
+        }
+    }
+}
+
+struct Fields {
+    fields: &'static [BitField],
+    value: u8,
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}={}", field.name, field.get(self.value))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::Bus;
+    use crate::rom::Rom;
+    use crate::rom::test_rom::create_valid_lorom;
+    use apu::Apu;
+    use common::snes_address::snes_addr;
+    use ppu::ppu::PPU;
+
+    #[test]
+    fn test_lookup() {
+        let nmitimen = Register::at(0x4200, true).unwrap();
+        assert_eq!(nmitimen.to_string(), "NMITIMEN");
+        assert_eq!(
+            nmitimen.fields(0x81).to_string(),
+            "nmi=1 irq=0 auto_joypad=1"
+        );
+        assert!(Register::at(0x4200, false).is_none());
+
+        assert_eq!(Register::at(0x4016, true).unwrap().to_string(), "JOYOUT");
+        assert_eq!(Register::at(0x4016, false).unwrap().to_string(), "JOYSER0");
+        assert!(Register::at(0x4380, false).is_none());
+
+        let mirror = Register::at(0x217D, true).unwrap();
+        assert_eq!((mirror.to_string(), mirror.addr()), ("APUIO1".to_string(), 0x2141));
+        assert_eq!(Register::at(0x2180, false).unwrap().to_string(), "WMDATA");
+        assert!(Register::at(0x2181, false).is_none());
+    }
+
+    #[test]
+    fn test_dma_channel_registers() {
+        let register = Register::at(0x4352, false).unwrap();
+        assert_eq!(register.to_string(), "A1T5L");
+        assert_eq!(register.channel, Some(5));
+        assert_eq!(register.addr(), 0x4352);
+        assert_eq!(Register::at(0x437A, true).unwrap().to_string(), "NLTR7");
+        assert!(Register::at(0x430C, false).is_none());
+    }
+
+    #[test]
+    fn test_at_address_only_in_io_banks() {
+        assert!(Register::at_address(snes_addr!(0x80:0x2100), true).is_some());
+        assert!(Register::at_address(snes_addr!(0x7E:0x2100), true).is_none());
+        assert!(Register::at_address(snes_addr!(0x00:0x8000), false).is_none());
+    }
+
+    #[test]
+    fn test_fields_cover_register_once() {
+        for info in PPU_REGISTERS
+            .iter()
+            .chain(APU_WRAM_REGISTERS)
+            .chain(CPU_REGISTERS)
+            .chain(DMA_CHANNEL_REGISTERS)
+        {
+            let mut bits = 0u8;
+            for field in info.fields {
+                let mask = ((1u16 << field.width) - 1) as u8;
+                assert!(
+                    field.shift + field.width <= 8,
+                    "{}.{}",
+                    info.name,
+                    field.name
+                );
+                assert_eq!(
+                    bits & (mask << field.shift),
+                    0,
+                    "{}.{}",
+                    info.name,
+                    field.name
+                );
+                bits |= mask << field.shift;
+            }
+        }
+    }
+
+    fn make_bus() -> (Bus, PPU, Apu) {
+        let rom = Rom::from_bytes(create_valid_lorom(0x20000)).unwrap();
+        (Bus::from_rom(rom), PPU::new(), Apu::new())
+    }
+
+    /// Whether reading `addr` gives something else than the open bus
+    fn drives_data_bus(bus: &mut Bus, ppu: &mut PPU, apu: &mut Apu, addr: u16) -> bool {
+        let addr = snes_addr!(0:addr);
+        bus.io.open_bus = 0x00;
+        let low = bus.read(addr, ppu, apu);
+        bus.io.open_bus = 0xFF;
+        let high = bus.read(addr, ppu, apu);
+        !(low == 0x00 && high == 0xFF)
+    }
+
+    #[test]
+    fn test_readable_registers_match_io() {
+        let (mut bus, mut ppu, mut apu) = make_bus();
+        for addr in 0x2100..0x4380 {
+            // SLHV is a strobe: reading it latches the counters and gives the open bus
+            let listed = Register::at(addr, false).is_some() && addr != 0x2137;
+            assert_eq!(
+                drives_data_bus(&mut bus, &mut ppu, &mut apu, addr),
+                listed,
+                "${addr:04X}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_writable_registers_mapped() {
+        let (mut bus, mut ppu, mut apu) = make_bus();
+        for addr in 0x2100..0x4380 {
+            if Register::at(addr, true).is_some() {
+                bus.write(snes_addr!(0:addr), 0, &mut ppu, &mut apu);
+            }
+        }
+    }
+}
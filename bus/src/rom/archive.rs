@@ -0,0 +1,127 @@
+//! Loading ROMs out of .zip archives, behind the `zip` feature.
+
+use crate::rom::error::RomError;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+#[cfg(feature = "zip")]
+const ROM_EXTENSIONS: [&str; 2] = [".sfc", ".smc"];
+
+/// Checks whether a file is a zip archive from its magic number.
+pub fn is_zip(buffer: &[u8]) -> bool {
+    buffer.starts_with(ZIP_MAGIC)
+}
+
+#[cfg(feature = "zip")]
+fn is_rom_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ROM_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+}
+
+/// Extracts the .sfc/.smc file from a zip archive.
+///
+/// The archive must contain exactly one ROM; other files (readmes, patches...) are ignored.
+#[cfg(feature = "zip")]
+pub fn extract_rom(buffer: &[u8]) -> Result<Vec<u8>, RomError> {
+    use std::io::{Cursor, Read};
+
+    let invalid = |e: zip::result::ZipError| RomError::InvalidArchive(e.to_string());
+    let mut archive = zip::ZipArchive::new(Cursor::new(buffer)).map_err(invalid)?;
+
+    let candidates: Vec<String> = archive
+        .file_names()
+        .filter(|name| is_rom_name(name))
+        .map(String::from)
+        .collect();
+    let name = match candidates.as_slice() {
+        [] => return Err(RomError::InvalidArchive("no .sfc or .smc file found".to_string())),
+        [name] => name,
+        _ => return Err(RomError::InvalidArchive(format!(
+            "multiple ROMs found: {}",
+            candidates.join(", ")
+        ))),
+    };
+
+    let mut file = archive.by_name(name).map_err(invalid)?;
+    let mut rom = Vec::new();
+    file.read_to_end(&mut rom).map_err(RomError::IoError)?;
+    Ok(rom)
+}
+
+#[cfg(not(feature = "zip"))]
+pub fn extract_rom(_buffer: &[u8]) -> Result<Vec<u8>, RomError> {
+    Err(RomError::InvalidArchive(
+        "zip support is disabled, enable the `zip` feature".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_zip() {
+        assert!(is_zip(b"PK\x03\x04rest"));
+        assert!(!is_zip(b"PK"));
+        assert!(!is_zip(&[0; 16]));
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_is_rom_name() {
+        assert!(is_rom_name("game.sfc"));
+        assert!(is_rom_name("dir/GAME.SMC"));
+        assert!(!is_rom_name("readme.txt"));
+        assert!(!is_rom_name("game.ips"));
+    }
+
+    #[cfg(not(feature = "zip"))]
+    #[test]
+    fn test_extract_without_feature() {
+        let result = extract_rom(b"PK\x03\x04");
+        assert!(matches!(result, Err(RomError::InvalidArchive(_))));
+    }
+
+    #[cfg(feature = "zip")]
+    mod zip_archives {
+        use super::*;
+        use std::io::{Cursor, Write};
+        use zip::write::SimpleFileOptions;
+
+        fn make_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+            let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+            for (name, data) in files {
+                writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+                writer.write_all(data).unwrap();
+            }
+            writer.finish().unwrap().into_inner()
+        }
+
+        #[test]
+        fn test_extract_single_rom() {
+            let archive = make_zip(&[("readme.txt", b"hello"), ("Game.smc", &[1, 2, 3, 4])]);
+            assert_eq!(extract_rom(&archive).unwrap(), vec![1, 2, 3, 4]);
+        }
+
+        #[test]
+        fn test_extract_no_rom() {
+            let archive = make_zip(&[("readme.txt", b"hello")]);
+            let result = extract_rom(&archive);
+            assert!(matches!(result, Err(RomError::InvalidArchive(reason)) if reason.contains("no .sfc")));
+        }
+
+        #[test]
+        fn test_extract_multiple_roms() {
+            let archive = make_zip(&[("a.sfc", &[0]), ("b.smc", &[1])]);
+            let result = extract_rom(&archive);
+            assert!(
+                matches!(result, Err(RomError::InvalidArchive(reason)) if reason.contains("a.sfc, b.smc"))
+            );
+        }
+
+        #[test]
+        fn test_extract_corrupted_archive() {
+            let result = extract_rom(b"PK\x03\x04garbage");
+            assert!(matches!(result, Err(RomError::InvalidArchive(_))));
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! ROM identification by hash against a user-provided database.
+//!
+//! Databases are read from Logiqx XML dat files, the format used by No-Intro.
+//! Hashes are computed on the ROM without its copier header, like No-Intro does.
+
+use crate::rom::crc32::crc32;
+use crate::rom::error::RomError;
+use crate::rom::sha1::{SHA1_LEN, sha1};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomHashes {
+    pub crc32: u32,
+    pub sha1: [u8; SHA1_LEN],
+}
+
+impl RomHashes {
+    pub fn of(data: &[u8]) -> Self {
+        Self {
+            crc32: crc32(data),
+            sha1: sha1(data),
+        }
+    }
+
+    pub fn sha1_hex(&self) -> String {
+        self.sha1.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Dump status reported by the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DumpStatus {
+    /// Verified by several dumpers (`status="verified"`)
+    Verified,
+    /// Believed good, no status attribute
+    #[default]
+    Good,
+    /// Known bad dump (`status="baddump"`)
+    BadDump,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomEntry {
+    /// Canonical game name
+    pub name: String,
+    pub size: Option<usize>,
+    pub crc32: Option<u32>,
+    pub sha1: Option<[u8; SHA1_LEN]>,
+    pub status: DumpStatus,
+}
+
+impl RomEntry {
+    /// Checks whether the entry describes a ROM with these hashes and size.
+    ///
+    /// The SHA-1 is preferred when the entry has one, the CRC32 is used otherwise.
+    pub fn matches(&self, hashes: &RomHashes, size: usize) -> bool {
+        if self.size.is_some_and(|s| s != size) {
+            return false;
+        }
+        match (self.sha1, self.crc32) {
+            (Some(sha1), _) => sha1 == hashes.sha1,
+            (None, Some(crc32)) => crc32 == hashes.crc32,
+            (None, None) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RomDatabase {
+    entries: Vec<RomEntry>,
+}
+
+impl RomDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, RomError> {
+        let dat = std::fs::read_to_string(path).map_err(RomError::IoError)?;
+        Self::parse_dat(&dat)
+    }
+
+    /// Parses a Logiqx XML dat: one `<rom>` entry per `<game>` (or `<machine>`).
+    pub fn parse_dat(dat: &str) -> Result<Self, RomError> {
+        let mut database = Self::new();
+        let mut game: Option<String> = None;
+        let mut rest = dat;
+
+        while let Some(start) = rest.find('<') {
+            let end = rest[start..]
+                .find('>')
+                .ok_or_else(|| invalid("unterminated tag"))?;
+            let tag = &rest[start + 1..start + end];
+            rest = &rest[start + end + 1..];
+
+            let (tag_name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+            match tag_name {
+                "game" | "machine" => game = attribute(attributes, "name"),
+                "/game" | "/machine" => game = None,
+                "rom" => {
+                    let rom_name = attribute(attributes, "name");
+                    let name = game
+                        .clone()
+                        .or(rom_name)
+                        .ok_or_else(|| invalid("rom without a name"))?;
+                    database.add(RomEntry {
+                        name,
+                        size: attribute(attributes, "size")
+                            .map(|size| size.parse().map_err(|_| invalid("invalid size")))
+                            .transpose()?,
+                        crc32: attribute(attributes, "crc")
+                            .map(|crc| u32::from_str_radix(&crc, 16).map_err(|_| invalid("invalid crc")))
+                            .transpose()?,
+                        sha1: attribute(attributes, "sha1").map(|sha1| parse_sha1(&sha1)).transpose()?,
+                        status: match attribute(attributes, "status").as_deref() {
+                            Some("verified") => DumpStatus::Verified,
+                            Some("baddump") => DumpStatus::BadDump,
+                            _ => DumpStatus::Good,
+                        },
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(database)
+    }
+
+    pub fn add(&mut self, entry: RomEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> &[RomEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Finds the entry for a ROM of `size` bytes with these hashes.
+    pub fn lookup(&self, hashes: &RomHashes, size: usize) -> Option<&RomEntry> {
+        self.entries.iter().find(|entry| entry.matches(hashes, size))
+    }
+}
+
+/// Result of [`crate::rom::Rom::identify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomIdentity<'a> {
+    pub hashes: RomHashes,
+    /// Database entry, `None` for an unknown ROM (hack, translation, bad or patched dump...)
+    pub entry: Option<&'a RomEntry>,
+}
+
+impl RomIdentity<'_> {
+    pub fn name(&self) -> Option<&str> {
+        self.entry.map(|entry| entry.name.as_str())
+    }
+
+    /// Known to the database and not a bad dump.
+    pub fn is_known_good(&self) -> bool {
+        self.entry.is_some_and(|entry| entry.status != DumpStatus::BadDump)
+    }
+}
+
+fn invalid(reason: &str) -> RomError {
+    RomError::InvalidDatabase(reason.to_string())
+}
+
+/// Value of `key="value"` in a tag's attributes, with XML entities unescaped.
+fn attribute(attributes: &str, key: &str) -> Option<String> {
+    let mut rest = attributes;
+    loop {
+        let eq = rest.find('=')?;
+        let name = rest[..eq].trim();
+        let value_start = rest[eq + 1..].trim_start();
+        let (quote, value_start) = ['"', '\'']
+            .into_iter()
+            .find_map(|quote| Some((quote, value_start.strip_prefix(quote)?)))?;
+        let value_len = value_start.find(quote)?;
+        let value = &value_start[..value_len];
+        if name == key {
+            return Some(unescape(value));
+        }
+        rest = &value_start[value_len + 1..];
+    }
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn parse_sha1(hex: &str) -> Result<[u8; SHA1_LEN], RomError> {
+    if hex.len() != SHA1_LEN * 2 || !hex.is_ascii() {
+        return Err(invalid("invalid sha1"));
+    }
+    let mut sha1 = [0; SHA1_LEN];
+    for (i, byte) in sha1.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid("invalid sha1"))?;
+    }
+    Ok(sha1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAT: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE datafile PUBLIC "-//Logiqx//DTD ROM Management Datafile//EN" "http://www.logiqx.com/dtds/datafile.dtd">
+<datafile>
+    <header>
+        <name>Nintendo - Super Nintendo Entertainment System</name>
+    </header>
+    <game name="Test Game (USA)">
+        <description>Test Game (USA)</description>
+        <rom name="Test Game (USA).sfc" size="3" crc="352441C2" sha1="A9993E364706816ABA3E25717850C26C9CD0D89D" status="verified"/>
+    </game>
+    <game name="Crc Only &amp; Bad (Europe)">
+        <rom name="Crc Only.sfc" size="4" crc="ed82cd11" status="baddump"/>
+    </game>
+</datafile>
+"#;
+
+    #[test]
+    fn test_hashes() {
+        let hashes = RomHashes::of(b"abc");
+        assert_eq!(hashes.crc32, 0x3524_41C2);
+        assert_eq!(hashes.sha1_hex(), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    }
+
+    #[test]
+    fn test_parse_dat() {
+        let database = RomDatabase::parse_dat(DAT).unwrap();
+        assert_eq!(database.len(), 2);
+
+        let first = &database.entries()[0];
+        assert_eq!(first.name, "Test Game (USA)");
+        assert_eq!(first.size, Some(3));
+        assert_eq!(first.crc32, Some(0x3524_41C2));
+        assert_eq!(first.sha1, Some(sha1(b"abc")));
+        assert_eq!(first.status, DumpStatus::Verified);
+
+        let second = &database.entries()[1];
+        assert_eq!(second.name, "Crc Only & Bad (Europe)");
+        assert_eq!(second.sha1, None);
+        assert_eq!(second.status, DumpStatus::BadDump);
+    }
+
+    #[test]
+    fn test_lookup_by_sha1_and_crc() {
+        let database = RomDatabase::parse_dat(DAT).unwrap();
+
+        let found = database.lookup(&RomHashes::of(b"abc"), 3).unwrap();
+        assert_eq!(found.name, "Test Game (USA)");
+
+        let data = b"abcd";
+        let found = database.lookup(&RomHashes::of(data), data.len()).unwrap();
+        assert_eq!(found.name, "Crc Only & Bad (Europe)");
+
+        assert!(database.lookup(&RomHashes::of(b"abd"), 3).is_none());
+    }
+
+    #[test]
+    fn test_lookup_checks_size() {
+        let database = RomDatabase::parse_dat(DAT).unwrap();
+        assert!(database.lookup(&RomHashes::of(b"abc"), 4).is_none());
+    }
+
+    #[test]
+    fn test_identity() {
+        let database = RomDatabase::parse_dat(DAT).unwrap();
+
+        let good = RomIdentity {
+            hashes: RomHashes::of(b"abc"),
+            entry: database.lookup(&RomHashes::of(b"abc"), 3),
+        };
+        assert_eq!(good.name(), Some("Test Game (USA)"));
+        assert!(good.is_known_good());
+
+        let bad = RomIdentity {
+            hashes: RomHashes::of(b"abcd"),
+            entry: database.lookup(&RomHashes::of(b"abcd"), 4),
+        };
+        assert!(!bad.is_known_good());
+
+        let unknown = RomIdentity {
+            hashes: RomHashes::of(b"x"),
+            entry: None,
+        };
+        assert_eq!(unknown.name(), None);
+        assert!(!unknown.is_known_good());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(RomDatabase::parse_dat("<game"), Err(RomError::InvalidDatabase(_))));
+        assert!(matches!(
+            RomDatabase::parse_dat(r#"<rom name="a" crc="XYZ"/>"#),
+            Err(RomError::InvalidDatabase(_))
+        ));
+        assert!(matches!(
+            RomDatabase::parse_dat(r#"<rom name="a" sha1="1234"/>"#),
+            Err(RomError::InvalidDatabase(_))
+        ));
+    }
+
+    #[test]
+    fn test_unquoted_attributes_ignored() {
+        assert_eq!(attribute(r#"name='Single' crc="00""#, "crc").as_deref(), Some("00"));
+        assert_eq!(attribute("name=é size=\"4\"", "size"), None);
+        assert!(matches!(
+            RomDatabase::parse_dat("<rom name=ééé crc=\"00000000\"/>"),
+            Err(RomError::InvalidDatabase(_))
+        ));
+    }
+
+    #[test]
+    fn test_rom_without_game_uses_rom_name() {
+        let database = RomDatabase::parse_dat(r#"<rom name="Loose.sfc" crc="00000000"/>"#).unwrap();
+        assert_eq!(database.entries()[0].name, "Loose.sfc");
+    }
+}
@@ -5,6 +5,10 @@ pub enum RomError {
     IoError(std::io::Error),
     FileTooSmall,
     IncorrectMapping,
+    InvalidPatch(String),
+    InvalidArchive(String),
+    InvalidDatabase(String),
+    InvalidHeader(String),
 }
 
 impl std::error::Error for RomError {}
@@ -14,6 +18,10 @@ impl fmt::Display for RomError {
             RomError::IoError(e) => write!(f, "I/O error: {}", e),
             RomError::FileTooSmall => write!(f, "ROM file too small to be valid."),
             RomError::IncorrectMapping => write!(f, "ROM Mapping unknown"),
+            RomError::InvalidPatch(reason) => write!(f, "Invalid ROM patch: {}", reason),
+            RomError::InvalidArchive(reason) => write!(f, "Invalid ROM archive: {}", reason),
+            RomError::InvalidDatabase(reason) => write!(f, "Invalid ROM database: {}", reason),
+            RomError::InvalidHeader(reason) => write!(f, "Invalid ROM header: {}", reason),
         }
     }
 }
@@ -49,6 +57,30 @@ mod tests {
         assert_eq!(msg, "ROM Mapping unknown");
     }
 
+    #[test]
+    fn test_display_invalid_patch() {
+        let rom_err = RomError::InvalidPatch("unknown patch format".to_string());
+
+        let msg = format!("{}", rom_err);
+        assert_eq!(msg, "Invalid ROM patch: unknown patch format");
+    }
+
+    #[test]
+    fn test_display_invalid_archive() {
+        let rom_err = RomError::InvalidArchive("multiple ROMs found: a.sfc, b.smc".to_string());
+
+        let msg = format!("{}", rom_err);
+        assert_eq!(msg, "Invalid ROM archive: multiple ROMs found: a.sfc, b.smc");
+    }
+
+    #[test]
+    fn test_display_invalid_database() {
+        let rom_err = RomError::InvalidDatabase("invalid crc".to_string());
+
+        let msg = format!("{}", rom_err);
+        assert_eq!(msg, "Invalid ROM database: invalid crc");
+    }
+
     #[test]
     fn test_debug_format() {
         let rom_err = RomError::FileTooSmall;
@@ -59,8 +59,9 @@ impl CartridgeHardware {
     ///     byte: Byte from the ROM header representing hardware configuration.
     ///
     /// Returns:
-    ///     A `CartridgeHardware` struct containing the ROM layout and an Option<Coprocessor>
-    pub fn from_byte(byte: u8) -> CartridgeHardware {
+    ///     A `CartridgeHardware` struct containing the ROM layout and an Option<Coprocessor>,
+    ///     `None` if the layout is unknown
+    pub fn from_byte(byte: u8) -> Option<CartridgeHardware> {
         let layout = match byte & 0x0F {
             0x0 => HardwareLayout::RomOnly,
             0x1 => HardwareLayout::RomRam,
@@ -69,7 +70,7 @@ impl CartridgeHardware {
             0x4 => HardwareLayout::RomCoprocessorRam,
             0x5 => HardwareLayout::RomCoprocessorRamBattery,
             0x6 => HardwareLayout::RomCoprocessorBattery,
-            _ => panic!("ERROR: Could not identify hardware of ROM"),
+            _ => return None,
         };
 
         let coprocessor = match (byte & 0xF0) >> 4 {
@@ -84,10 +85,10 @@ impl CartridgeHardware {
             _ => None,
         };
 
-        CartridgeHardware {
+        Some(CartridgeHardware {
             layout,
             coprocessor,
-        }
+        })
     }
 
     /// Returns true if this cartridge has RAM
@@ -156,7 +157,7 @@ mod tests {
         ];
 
         for (byte, expected) in mappings {
-            assert_eq!(CartridgeHardware::from_byte(byte).layout, expected);
+            assert_eq!(CartridgeHardware::from_byte(byte).unwrap().layout, expected);
         }
     }
 
@@ -164,21 +165,21 @@ mod tests {
     #[test]
     fn test_cartridge_components_availability() {
         let mappings = [
-            (CartridgeHardware::from_byte(0x00), false, false, false),
-            (CartridgeHardware::from_byte(0x01), true, false, false),
-            (CartridgeHardware::from_byte(0x02), true, true, false),
-            (CartridgeHardware::from_byte(0x03), false, false, true),
-            (CartridgeHardware::from_byte(0x04), true, false, true),
-            (CartridgeHardware::from_byte(0x05), true, true, true),
-            (CartridgeHardware::from_byte(0x06), false, true, true),
+            (CartridgeHardware::from_byte(0x00).unwrap(), false, false, false),
+            (CartridgeHardware::from_byte(0x01).unwrap(), true, false, false),
+            (CartridgeHardware::from_byte(0x02).unwrap(), true, true, false),
+            (CartridgeHardware::from_byte(0x03).unwrap(), false, false, true),
+            (CartridgeHardware::from_byte(0x04).unwrap(), true, false, true),
+            (CartridgeHardware::from_byte(0x05).unwrap(), true, true, true),
+            (CartridgeHardware::from_byte(0x06).unwrap(), false, true, true),
             // Tens digit changed
-            (CartridgeHardware::from_byte(0x10), false, false, false),
-            (CartridgeHardware::from_byte(0x11), true, false, false),
-            (CartridgeHardware::from_byte(0x12), true, true, false),
-            (CartridgeHardware::from_byte(0x13), false, false, true),
-            (CartridgeHardware::from_byte(0x14), true, false, true),
-            (CartridgeHardware::from_byte(0x15), true, true, true),
-            (CartridgeHardware::from_byte(0x16), false, true, true),
+            (CartridgeHardware::from_byte(0x10).unwrap(), false, false, false),
+            (CartridgeHardware::from_byte(0x11).unwrap(), true, false, false),
+            (CartridgeHardware::from_byte(0x12).unwrap(), true, true, false),
+            (CartridgeHardware::from_byte(0x13).unwrap(), false, false, true),
+            (CartridgeHardware::from_byte(0x14).unwrap(), true, false, true),
+            (CartridgeHardware::from_byte(0x15).unwrap(), true, true, true),
+            (CartridgeHardware::from_byte(0x16).unwrap(), false, true, true),
         ];
 
         for (hardware, has_ram, has_battery, has_coprocessor) in mappings {
@@ -189,9 +190,8 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "ERROR: Could not identify hardware of ROM")]
     fn test_cartridge_hardware_from_byte_invalid() {
-        CartridgeHardware::from_byte(0x07);
+        assert_eq!(CartridgeHardware::from_byte(0x07), None);
     }
 
     #[test]
@@ -217,7 +217,7 @@ mod tests {
         ];
 
         for (byte, expected) in mappings {
-            assert_eq!(CartridgeHardware::from_byte(byte).coprocessor, expected);
+            assert_eq!(CartridgeHardware::from_byte(byte).unwrap().coprocessor, expected);
         }
     }
 
@@ -225,7 +225,7 @@ mod tests {
     fn test_coprocessor_from_byte_none() {
         let invalid_bytes = [0x60, 0x70, 0x80, 0x90, 0xA0, 0xB0, 0xC0, 0xD0];
         for &byte in &invalid_bytes {
-            assert_eq!(CartridgeHardware::from_byte(byte).coprocessor, None);
+            assert_eq!(CartridgeHardware::from_byte(byte).unwrap().coprocessor, None);
         }
     }
 
@@ -1,5 +1,7 @@
 use strum_macros::Display;
 
+pub use common::video_standard::VideoStandard;
+
 /// Represents the country or region code of a SNES ROM.
 ///
 /// Covers official regions and some miscellaneous/other codes.
@@ -29,16 +31,6 @@ pub enum Country {
     OtherZ,
 }
 
-/// Represents the video standard used by a SNES ROM.
-///
-/// Mainly NTSC or PAL, with an "Other" option for unknown/unsupported regions.
-#[derive(Display, Debug, Clone, Copy, PartialEq)]
-pub enum VideoStandard {
-    NTSC,
-    PAL,
-    Other,
-}
-
 impl Country {
     /// Creates a `Country` value from a byte extracted from the ROM header.
     ///
@@ -46,9 +38,9 @@ impl Country {
     ///     byte: Byte from the ROM header representing the country/region code.
     ///
     /// Returns:
-    ///     A `Country` enum corresponding to the ROM's region.
-    pub fn from_byte(byte: u8) -> Country {
-        match byte {
+    ///     A `Country` enum corresponding to the ROM's region, `None` for an unknown code.
+    pub fn from_byte(byte: u8) -> Option<Country> {
+        let country = match byte {
             0x00 => Country::Japan, // "0x00" sometimes means Japan or "International"
             0x01 => Country::USA,
             0x02 => Country::Europe,
@@ -70,12 +62,13 @@ impl Country {
             0x12 => Country::OtherX,
             0x13 => Country::OtherY,
             0x14 => Country::OtherZ,
-            _ => panic!("ERROR: Could not identify country of ROM"),
-        }
+            _ => return None,
+        };
+        Some(country)
     }
 }
 
-impl VideoStandard {
+impl From<Country> for VideoStandard {
     /// Determines the video standard (NTSC/PAL/Other) based on a given `Country`.
     ///
     /// Args:
@@ -83,7 +76,7 @@ impl VideoStandard {
     ///
     /// Returns:
     ///     A `VideoStandard` enum corresponding to the country's standard.
-    pub fn from_country(country: Country) -> VideoStandard {
+    fn from(country: Country) -> VideoStandard {
         match country {
             Country::Japan
             | Country::USA
@@ -140,14 +133,13 @@ mod tests {
         ];
 
         for (byte, expected) in mappings {
-            assert_eq!(Country::from_byte(byte), expected);
+            assert_eq!(Country::from_byte(byte), Some(expected));
         }
     }
 
     #[test]
-    #[should_panic(expected = "ERROR: Could not identify country of ROM")]
     fn test_country_from_byte_invalid() {
-        Country::from_byte(0xFF);
+        assert_eq!(Country::from_byte(0xFF), None);
     }
 
     #[test]
@@ -182,13 +174,13 @@ mod tests {
         ];
 
         for &c in &ntsc_countries {
-            assert_eq!(VideoStandard::from_country(c), VideoStandard::NTSC);
+            assert_eq!(VideoStandard::from(c), VideoStandard::NTSC);
         }
         for &c in &pal_countries {
-            assert_eq!(VideoStandard::from_country(c), VideoStandard::PAL);
+            assert_eq!(VideoStandard::from(c), VideoStandard::PAL);
         }
         for &c in &other_countries {
-            assert_eq!(VideoStandard::from_country(c), VideoStandard::Other);
+            assert_eq!(VideoStandard::from(c), VideoStandard::Other);
         }
     }
 
@@ -8,6 +8,7 @@ use crate::constants::{
 };
 use crate::rom::header::cartridge_hardware::CartridgeHardware;
 use crate::rom::header::country::{Country, VideoStandard};
+use crate::rom::error::RomError;
 use crate::rom::header::mapping_mode::{MappingMode, RomSpeed, SpeedAndMappingMode};
 
 /// Represents the header of a SNES ROM.
@@ -38,53 +39,59 @@ impl RomHeader {
     ///     mapping_mode: Mapping mode used to locate the header.
     ///
     /// Returns:
-    ///     A `RomHeader` struct populated with all extracted metadata.
-    pub fn load_header(rom_data: &[u8], mapping_mode: MappingMode) -> RomHeader {
+    ///     A `RomHeader` struct populated with all extracted metadata, or an error if the
+    ///     ROM is too small to hold the header or one of its fields has an unknown value.
+    pub fn load_header(rom_data: &[u8], mapping_mode: MappingMode) -> Result<RomHeader, RomError> {
         let h_offset = mapping_mode.get_corresponding_header_offset();
-        let slice = &rom_data[h_offset..h_offset + HEADER_SIZE];
-
-        let header_bytes: [u8; HEADER_SIZE] = slice
-            .try_into()
-            .expect("ERROR: Couldn't extract the header from the ROM"); // Should be safe since multiple verification before
-        let country = Country::from_byte(header_bytes[HEADER_COUNTRY_OFFSET]);
+        let header_bytes: [u8; HEADER_SIZE] = rom_data
+            .get(h_offset..h_offset + HEADER_SIZE)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or(RomError::FileTooSmall)?;
+
+        let invalid = |field: &str, offset: usize| {
+            RomError::InvalidHeader(format!("unknown {} ${:02X}", field, header_bytes[offset]))
+        };
+        let country = Country::from_byte(header_bytes[HEADER_COUNTRY_OFFSET])
+            .ok_or_else(|| invalid("country code", HEADER_COUNTRY_OFFSET))?;
+        let hardware = CartridgeHardware::from_byte(header_bytes[HEADER_ROM_HARDWARE_OFFSET])
+            .ok_or_else(|| invalid("cartridge type", HEADER_ROM_HARDWARE_OFFSET))?;
         let SpeedAndMappingMode {
             rom_speed,
             mapping_mode,
-        } = SpeedAndMappingMode::from_byte(header_bytes[HEADER_SPEED_MAP_OFFSET]);
+        } = SpeedAndMappingMode::from_byte(header_bytes[HEADER_SPEED_MAP_OFFSET])
+            .ok_or_else(|| invalid("map mode", HEADER_SPEED_MAP_OFFSET))?;
 
-        RomHeader {
+        Ok(RomHeader {
             bytes: header_bytes,
             title: String::from_utf8_lossy(&header_bytes[0..HEADER_TITLE_LEN]).to_string(),
             rom_speed: rom_speed,
             mapping_mode: mapping_mode,
-            hardware: CartridgeHardware::from_byte(header_bytes[HEADER_ROM_HARDWARE_OFFSET]),
+            hardware,
             rom_size: header_bytes[HEADER_ROM_SIZE_OFFSET],
             ram_size: header_bytes[HEADER_RAM_SIZE_OFFSET],
             country: country,
-            video_standard: VideoStandard::from_country(country),
+            video_standard: VideoStandard::from(country),
             developer_id: header_bytes[HEADER_DEVELOPER_ID_OFFSET],
             rom_version: header_bytes[HEADER_ROM_VERSION_OFFSET],
-            checksum_complement: u16::from_be_bytes([
+            checksum_complement: u16::from_le_bytes([
                 header_bytes[HEADER_CHECKSUM_COMPLEMENT_OFFSET],
                 header_bytes[HEADER_CHECKSUM_COMPLEMENT_OFFSET + 1],
             ]),
-            checksum: u16::from_be_bytes([
+            checksum: u16::from_le_bytes([
                 header_bytes[HEADER_CHECKSUM_OFFSET],
                 header_bytes[HEADER_CHECKSUM_OFFSET + 1],
             ]),
-        }
+        })
     }
 
-    /// Prints the raw header bytes to the console in hexadecimal format.
+    /// Logs the raw header bytes in hexadecimal format, as debug events.
     ///
-    /// Each line prints 8 bytes for readability.
+    /// Each event holds 8 bytes for readability.
     #[cfg(not(tarpaulin_include))]
-    pub fn print_header_bytes(&self) {
-        for chunk in self.bytes[..HEADER_SIZE].chunks(8) {
-            for byte in chunk {
-                print!("{:02X} ", byte);
-            }
-            println!();
+    pub fn log_header_bytes(&self) {
+        for (line, chunk) in self.bytes[..HEADER_SIZE].chunks(8).enumerate() {
+            let bytes: Vec<String> = chunk.iter().map(|byte| format!("{:02X}", byte)).collect();
+            tracing::debug!(offset = line * 8, "{}", bytes.join(" "));
         }
     }
 }
@@ -110,8 +117,8 @@ impl fmt::Display for RomHeader {
         write!(f, "VideoStandard: {}\n", self.video_standard)?;
         write!(f, "Developer ID: {}\n", self.developer_id)?;
         write!(f, "Rom Version: {}\n", self.rom_version)?;
-        write!(f, "Checksum Complement: {}\n", self.checksum_complement)?;
-        write!(f, "Checksum: {}\n", self.checksum)
+        write!(f, "Checksum Complement: {:04X}\n", self.checksum_complement)?;
+        write!(f, "Checksum: {:04X}\n", self.checksum)
     }
 }
 
@@ -167,7 +174,7 @@ mod tests {
     #[test]
     fn test_rom_header_creation() {
         let fake_rom = create_minimalist_rom(MappingMode::LoRom);
-        let rom_header = RomHeader::load_header(&fake_rom, MappingMode::LoRom);
+        let rom_header = RomHeader::load_header(&fake_rom, MappingMode::LoRom).unwrap();
 
         assert_eq!(rom_header.bytes, *create_custom_header());
         assert_eq!(rom_header.title, "ABABABABABABABABABABA");
@@ -183,4 +190,27 @@ mod tests {
         assert_eq!(rom_header.checksum_complement, 0xFFFF);
         assert_eq!(rom_header.checksum, 0x0000);
     }
+
+    #[test]
+    fn test_rom_header_too_small() {
+        let fake_rom = vec![0; 0x8000];
+        let result = RomHeader::load_header(&fake_rom, MappingMode::HiRom);
+
+        assert!(matches!(result, Err(RomError::FileTooSmall)));
+    }
+
+    #[test]
+    fn test_rom_header_unknown_fields() {
+        let mut fake_rom = create_minimalist_rom(MappingMode::LoRom);
+        fake_rom[0x7FC0 + HEADER_COUNTRY_OFFSET] = 0xFF;
+        let result = RomHeader::load_header(&fake_rom, MappingMode::LoRom);
+        assert!(matches!(result, Err(RomError::InvalidHeader(reason)) if reason.contains("$FF")));
+
+        let mut fake_rom = create_minimalist_rom(MappingMode::LoRom);
+        fake_rom[0x7FC0 + HEADER_ROM_HARDWARE_OFFSET] = 0x09;
+        let result = RomHeader::load_header(&fake_rom, MappingMode::LoRom);
+        assert!(
+            matches!(result, Err(RomError::InvalidHeader(reason)) if reason.contains("cartridge"))
+        );
+    }
 }
@@ -52,25 +52,28 @@ pub enum RomSpeed {
 ///     byte: Byte from the ROM header representing the ROM speed and mapping mode.
 ///
 /// Returns:
-///     A SpeedAndMappingMode struct which contains the rom speed and the mapping mode
+///     A SpeedAndMappingMode struct which contains the rom speed and the mapping mode,
+///     `None` if the mapping mode is unknown
 impl SpeedAndMappingMode {
-    pub fn from_byte(byte: u8) -> SpeedAndMappingMode {
+    pub fn from_byte(byte: u8) -> Option<SpeedAndMappingMode> {
         let mapping_mode = match byte & 0x0F {
             0x0 => MappingMode::LoRom,
             0x1 => MappingMode::HiRom,
-            _ => panic!("ERROR: Could not identify mapping of ROM"),
+            // SA-1 cartridges, whose MMC defaults to the LoROM layout
+            0x3 => MappingMode::LoRom,
+            _ => return None,
         };
 
-        let rom_speed = match (byte >> 4) & 1 {
-            0 => RomSpeed::Slow,
-            1 => RomSpeed::Fast,
-            _ => panic!("ERROR: Could not identify speed of ROM"),
+        let rom_speed = if byte & 0x10 == 0 {
+            RomSpeed::Slow
+        } else {
+            RomSpeed::Fast
         };
 
-        SpeedAndMappingMode {
+        Some(SpeedAndMappingMode {
             mapping_mode,
             rom_speed,
-        }
+        })
     }
 }
 
@@ -100,6 +103,32 @@ impl MappingMode {
         }
     }
 
+    /// Detects an interleaved ("swapped") HiROM dump, as made by old copiers.
+    ///
+    /// Such dumps store the upper 32 KiB halves of all the banks before the lower ones,
+    /// which moves the HiROM header to where the LoROM header is expected. They are
+    /// recognised by a header at the LoROM offset which scores better than the one at
+    /// the HiROM offset, but declares the HiROM mapping.
+    ///
+    /// Args:
+    ///     rom_data: Byte slice containing the full ROM data, without copier header.
+    ///
+    /// Returns:
+    ///     `true` if the ROM should be de-interleaved before mapping detection.
+    pub fn detect_interleaved(rom_data: &[u8]) -> bool {
+        if rom_data.len() < HIROM_BANK_SIZE || !rom_data.len().is_multiple_of(HIROM_BANK_SIZE) {
+            return false;
+        }
+
+        let map_mode =
+            SpeedAndMappingMode::from_byte(rom_data[LOROM_HEADER_OFFSET + HEADER_SPEED_MAP_OFFSET])
+                .map(|speed_and_mode| speed_and_mode.mapping_mode);
+
+        map_mode == Some(MappingMode::HiRom)
+            && Self::score_header(rom_data, LOROM_HEADER_OFFSET)
+                > Self::score_header(rom_data, HIROM_HEADER_OFFSET)
+    }
+
     /// Returns the offset in the ROM where the header for this mapping mode is stored.
     ///
     /// Returns:
@@ -128,7 +157,7 @@ impl MappingMode {
         let mut score: u32 = 0;
 
         let map_mode = SpeedAndMappingMode::from_byte(rom_data[address + HEADER_SPEED_MAP_OFFSET])
-            .mapping_mode;
+            .map(|speed_and_mode| speed_and_mode.mapping_mode);
         let complement = u16::from_le_bytes([
             rom_data[address + HEADER_CHECKSUM_COMPLEMENT_OFFSET],
             rom_data[address + HEADER_CHECKSUM_COMPLEMENT_OFFSET + 1],
@@ -147,10 +176,10 @@ impl MappingMode {
             score += 8;
         }
 
-        if address == LOROM_HEADER_OFFSET && map_mode == MappingMode::LoRom {
+        if address == LOROM_HEADER_OFFSET && map_mode == Some(MappingMode::LoRom) {
             score += 4;
         }
-        if address == HIROM_HEADER_OFFSET && map_mode == MappingMode::HiRom {
+        if address == HIROM_HEADER_OFFSET && map_mode == Some(MappingMode::HiRom) {
             score += 4;
         }
 
@@ -179,6 +208,27 @@ mod tests {
         assert_eq!(mode, Some(MappingMode::HiRom));
     }
 
+    #[test]
+    fn detect_interleaved_hirom() {
+        let rom = create_valid_hirom(2 * HIROM_BANK_SIZE);
+        assert!(!MappingMode::detect_interleaved(&rom));
+
+        let interleaved = interleave(&rom);
+        assert!(MappingMode::detect_interleaved(&interleaved));
+    }
+
+    #[test]
+    fn detect_interleaved_ignores_lorom() {
+        let rom = create_valid_lorom(2 * HIROM_BANK_SIZE);
+        assert!(!MappingMode::detect_interleaved(&rom));
+    }
+
+    #[test]
+    fn detect_interleaved_ignores_partial_banks() {
+        let rom = interleave(&create_valid_hirom(2 * HIROM_BANK_SIZE));
+        assert!(!MappingMode::detect_interleaved(&rom[..3 * HIROM_BANK_SIZE / 2]));
+    }
+
     #[test]
     fn detect_unknown_if_too_small() {
         let rom = vec![0; HIROM_BANK_SIZE - 1];
@@ -211,16 +261,25 @@ mod tests {
     #[test]
     #[rustfmt::skip]
     fn test_from_byte_valid() {
-        assert_eq!(SpeedAndMappingMode::from_byte(0x00).mapping_mode, MappingMode::LoRom);
-        assert_eq!(SpeedAndMappingMode::from_byte(0x01).mapping_mode, MappingMode::HiRom);
-        assert_eq!(SpeedAndMappingMode::from_byte(0x10).mapping_mode, MappingMode::LoRom);
-        assert_eq!(SpeedAndMappingMode::from_byte(0x11).mapping_mode, MappingMode::HiRom);
+        assert_eq!(SpeedAndMappingMode::from_byte(0x00).unwrap().mapping_mode, MappingMode::LoRom);
+        assert_eq!(SpeedAndMappingMode::from_byte(0x01).unwrap().mapping_mode, MappingMode::HiRom);
+        assert_eq!(SpeedAndMappingMode::from_byte(0x10).unwrap().mapping_mode, MappingMode::LoRom);
+        assert_eq!(SpeedAndMappingMode::from_byte(0x11).unwrap().mapping_mode, MappingMode::HiRom);
+        assert_eq!(SpeedAndMappingMode::from_byte(0x23).unwrap().mapping_mode, MappingMode::LoRom);
     }
 
     #[test]
-    #[should_panic(expected = "ERROR: Could not identify mapping of ROM")]
     fn test_from_byte_invalid_mapping_mode() {
-        SpeedAndMappingMode::from_byte(0x02);
+        assert!(SpeedAndMappingMode::from_byte(0x02).is_none());
+    }
+
+    #[test]
+    fn detect_ignores_unknown_mapping_byte() {
+        let mut rom = create_valid_lorom(HIROM_BANK_SIZE);
+        rom[HIROM_HEADER_OFFSET + HEADER_SPEED_MAP_OFFSET] = 0x0F;
+        let mode = MappingMode::detect_rom_mapping(&rom);
+
+        assert_eq!(mode, Some(MappingMode::LoRom));
     }
 
     #[test]
@@ -236,7 +295,7 @@ mod tests {
     fn test_rom_speed_from_byte_slow() {
         let bytes = [0x00, 0x01];
         for &b in &bytes {
-            assert_eq!(SpeedAndMappingMode::from_byte(b).rom_speed, RomSpeed::Slow);
+            assert_eq!(SpeedAndMappingMode::from_byte(b).unwrap().rom_speed, RomSpeed::Slow);
         }
     }
 
@@ -244,7 +303,7 @@ mod tests {
     fn test_rom_speed_from_byte_fast() {
         let bytes = [0x10, 0x11];
         for &b in &bytes {
-            assert_eq!(SpeedAndMappingMode::from_byte(b).rom_speed, RomSpeed::Fast);
+            assert_eq!(SpeedAndMappingMode::from_byte(b).unwrap().rom_speed, RomSpeed::Fast);
         }
     }
 }
@@ -1,6 +1,11 @@
+pub mod archive;
+pub mod crc32;
+pub mod database;
 pub mod error;
 pub mod header;
+pub mod patch;
 pub mod rom;
+pub mod sha1;
 
 pub mod test_rom;
 
@@ -0,0 +1,415 @@
+//! Soft-patching of ROM images with IPS and BPS patches.
+//!
+//! Patches are applied in memory, to the ROM without its copier header, so
+//! the file on disk is never modified.
+
+use crate::rom::crc32::crc32;
+use crate::rom::error::RomError;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const BPS_MAGIC: &[u8] = b"BPS1";
+/// Source, target and patch CRC-32s at the end of a BPS patch
+const BPS_FOOTER_SIZE: usize = 12;
+/// Largest BPS target accepted, twice the largest SNES ROM: the size comes from the patch,
+/// which must not be able to make the loader allocate any amount of memory
+const BPS_MAX_TARGET_SIZE: usize = 16 * 1024 * 1024;
+/// Longest BPS variable-length integer, enough for 64 bits
+const BPS_MAX_VARINT_LEN: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+}
+
+impl PatchFormat {
+    /// Detects the patch format from its magic number.
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(IPS_MAGIC) {
+            Some(Self::Ips)
+        } else if patch.starts_with(BPS_MAGIC) {
+            Some(Self::Bps)
+        } else {
+            None
+        }
+    }
+}
+
+/// Applies an IPS or BPS `patch` to `rom`, returning the patched image.
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, RomError> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => apply_ips(rom, patch),
+        Some(PatchFormat::Bps) => apply_bps(rom, patch),
+        None => Err(invalid("unknown patch format")),
+    }
+}
+
+fn invalid(reason: &str) -> RomError {
+    RomError::InvalidPatch(reason.to_string())
+}
+
+/// Bounds-checked reader over the patch bytes.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], RomError> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| invalid("unexpected end of patch"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, RomError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// Big-endian integer of `len` bytes
+    fn be(&mut self, len: usize) -> Result<usize, RomError> {
+        Ok(self.bytes(len)?.iter().fold(0, |acc, &b| (acc << 8) | b as usize))
+    }
+
+    /// BPS variable-length integer
+    fn varint(&mut self) -> Result<usize, RomError> {
+        let too_large = || invalid("number too large");
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+        for _ in 0..BPS_MAX_VARINT_LEN {
+            let byte = self.byte()?;
+            value = ((byte & 0x7F) as usize)
+                .checked_mul(shift)
+                .and_then(|bits| value.checked_add(bits))
+                .ok_or_else(too_large)?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_shl(7).ok_or_else(too_large)?;
+            value = value.checked_add(shift).ok_or_else(too_large)?;
+        }
+        Err(too_large())
+    }
+}
+
+/// IPS: a list of `offset(3) size(2) data` records, `size == 0` meaning a run
+/// of `count(2) value(1)`, ended by `EOF` and an optional 3-byte truncation size.
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, RomError> {
+    let mut out = rom.to_vec();
+    let mut reader = Reader::new(patch, IPS_MAGIC.len());
+
+    loop {
+        let record = reader.bytes(3)?;
+        if record == IPS_EOF {
+            break;
+        }
+        let offset = record.iter().fold(0, |acc, &b| (acc << 8) | b as usize);
+
+        let size = reader.be(2)?;
+        let (len, data) = if size == 0 {
+            let count = reader.be(2)?;
+            (count, None)
+        } else {
+            (size, Some(reader.bytes(size)?))
+        };
+
+        if out.len() < offset + len {
+            out.resize(offset + len, 0);
+        }
+        match data {
+            Some(data) => out[offset..offset + len].copy_from_slice(data),
+            None => {
+                let value = reader.byte()?;
+                out[offset..offset + len].fill(value);
+            }
+        }
+    }
+
+    if let Ok(truncate) = reader.be(3) {
+        out.truncate(truncate);
+    }
+
+    Ok(out)
+}
+
+/// BPS: sizes and metadata, then copy actions building the target from the
+/// source, the patch, or the target itself, checked by CRC-32s.
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, RomError> {
+    if patch.len() < BPS_MAGIC.len() + BPS_FOOTER_SIZE {
+        return Err(invalid("unexpected end of patch"));
+    }
+    let actions_end = patch.len() - BPS_FOOTER_SIZE;
+    let mut footer = Reader::new(patch, actions_end);
+    let footer_crc = |reader: &mut Reader| -> Result<u32, RomError> {
+        Ok(u32::from_le_bytes(reader.bytes(4)?.try_into().unwrap()))
+    };
+    let source_crc = footer_crc(&mut footer)?;
+    let target_crc = footer_crc(&mut footer)?;
+    let patch_crc = footer_crc(&mut footer)?;
+
+    if crc32(&patch[..patch.len() - 4]) != patch_crc {
+        return Err(invalid("patch checksum mismatch"));
+    }
+    if crc32(rom) != source_crc {
+        return Err(invalid("patch was made for a different ROM"));
+    }
+
+    let mut reader = Reader::new(&patch[..actions_end], BPS_MAGIC.len());
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.bytes(metadata_size)?;
+
+    if source_size != rom.len() {
+        return Err(invalid("patch was made for a different ROM"));
+    }
+    if target_size > BPS_MAX_TARGET_SIZE {
+        return Err(invalid("patched ROM too large"));
+    }
+
+    let mut target: Vec<u8> = Vec::with_capacity(target_size);
+    let mut source_rel: usize = 0;
+    let mut target_rel: usize = 0;
+
+    while reader.pos < actions_end {
+        let command = reader.varint()?;
+        let len = (command >> 2) + 1;
+        if target.len() + len > target_size {
+            return Err(invalid("patch writes past the target size"));
+        }
+
+        match command & 3 {
+            // SourceRead
+            0 => {
+                let start = target.len();
+                let data = rom.get(start..start + len).ok_or_else(|| invalid("source read out of range"))?;
+                target.extend_from_slice(data);
+            }
+            // TargetRead
+            1 => target.extend_from_slice(reader.bytes(len)?),
+            // SourceCopy
+            2 => {
+                source_rel = relative_offset(source_rel, reader.varint()?)?;
+                let data = source_rel
+                    .checked_add(len)
+                    .and_then(|end| rom.get(source_rel..end))
+                    .ok_or_else(|| invalid("source copy out of range"))?;
+                target.extend_from_slice(data);
+                source_rel += len;
+            }
+            // TargetCopy, may overlap the bytes being written
+            _ => {
+                target_rel = relative_offset(target_rel, reader.varint()?)?;
+                if target_rel >= target.len() {
+                    return Err(invalid("target copy out of range"));
+                }
+                for _ in 0..len {
+                    target.push(target[target_rel]);
+                    target_rel += 1;
+                }
+            }
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(invalid("patch output has the wrong size"));
+    }
+    if crc32(&target) != target_crc {
+        return Err(invalid("patched ROM checksum mismatch"));
+    }
+
+    Ok(target)
+}
+
+/// Applies a BPS signed offset: bit 0 is the sign, the other bits the magnitude.
+fn relative_offset(base: usize, encoded: usize) -> Result<usize, RomError> {
+    let delta = encoded >> 1;
+    let offset = if encoded & 1 != 0 { base.checked_sub(delta) } else { base.checked_add(delta) };
+    offset.ok_or_else(|| invalid("copy offset out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut value: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        loop {
+            let x = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(0x80 | x);
+                return out;
+            }
+            out.push(x);
+            value -= 1;
+        }
+    }
+
+    /// Builds a BPS patch from raw action bytes, with valid sizes and checksums.
+    fn make_bps(source: &[u8], target: &[u8], actions: &[u8]) -> Vec<u8> {
+        let mut patch = BPS_MAGIC.to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(target.len()));
+        patch.extend(varint(3));
+        patch.extend_from_slice(b"xyz");
+        patch.extend_from_slice(actions);
+        patch.extend(crc32(source).to_le_bytes());
+        patch.extend(crc32(target).to_le_bytes());
+        let crc = crc32(&patch);
+        patch.extend(crc.to_le_bytes());
+        patch
+    }
+
+    fn action(kind: usize, len: usize) -> Vec<u8> {
+        varint(((len - 1) << 2) | kind)
+    }
+
+    // ============================================================
+    // Format detection
+    // ============================================================
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(PatchFormat::detect(b"PATCHEOF"), Some(PatchFormat::Ips));
+        assert_eq!(PatchFormat::detect(b"BPS1...."), Some(PatchFormat::Bps));
+        assert_eq!(PatchFormat::detect(b"UPS1"), None);
+        assert!(matches!(apply_patch(&[0; 4], b"UPS1"), Err(RomError::InvalidPatch(_))));
+    }
+
+    // ============================================================
+    // IPS
+    // ============================================================
+
+    #[test]
+    fn test_ips_records_and_rle() {
+        let rom = vec![0u8; 8];
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]); // 2 bytes at 1
+        patch.extend_from_slice(&[0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03, 0xCC]); // RLE 3 x CC at 5
+        patch.extend_from_slice(b"EOF");
+
+        let out = apply_patch(&rom, &patch).unwrap();
+        assert_eq!(out, vec![0, 0xAA, 0xBB, 0, 0, 0xCC, 0xCC, 0xCC]);
+    }
+
+    #[test]
+    fn test_ips_grows_and_truncates() {
+        let rom = vec![1u8; 4];
+        let mut grow = b"PATCH".to_vec();
+        grow.extend_from_slice(&[0x00, 0x00, 0x06, 0x00, 0x01, 0x09]);
+        grow.extend_from_slice(b"EOF");
+        assert_eq!(apply_patch(&rom, &grow).unwrap(), vec![1, 1, 1, 1, 0, 0, 9]);
+
+        let mut truncate = b"PATCHEOF".to_vec();
+        truncate.extend_from_slice(&[0x00, 0x00, 0x02]);
+        assert_eq!(apply_patch(&rom, &truncate).unwrap(), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_ips_truncated_patch_rejected() {
+        let patch = b"PATCH\x00\x00\x01\x00\x05\xAA".to_vec();
+        assert!(matches!(apply_patch(&[0; 8], &patch), Err(RomError::InvalidPatch(_))));
+    }
+
+    // ============================================================
+    // BPS
+    // ============================================================
+
+    #[test]
+    fn test_bps_all_actions() {
+        let source = b"ABCDEFGH".to_vec();
+        let target = b"ABxyEFCDCDCD".to_vec();
+
+        let mut actions = action(0, 2); // SourceRead "AB"
+        actions.extend(action(1, 2)); // TargetRead "xy"
+        actions.extend_from_slice(b"xy");
+        actions.extend(action(2, 2)); // SourceCopy "EF" from +4
+        actions.extend(varint(4 << 1));
+        actions.extend(action(2, 2)); // SourceCopy "CD" from 6 - 4 = 2
+        actions.extend(varint((4 << 1) | 1));
+        actions.extend(action(3, 4)); // TargetCopy "CDCD" from 6, overlapping
+        actions.extend(varint(6 << 1));
+
+        let patch = make_bps(&source, &target, &actions);
+        assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn test_bps_wrong_source_rejected() {
+        let source = b"ABCD".to_vec();
+        let patch = make_bps(&source, b"ABCD", &action(0, 4));
+        let result = apply_patch(b"ABCE", &patch);
+        assert!(matches!(result, Err(RomError::InvalidPatch(reason)) if reason.contains("different ROM")));
+    }
+
+    #[test]
+    fn test_bps_corrupted_patch_rejected() {
+        let source = b"ABCD".to_vec();
+        let mut patch = make_bps(&source, b"ABCD", &action(0, 4));
+        patch[5] ^= 0xFF;
+        let result = apply_patch(&source, &patch);
+        assert!(matches!(result, Err(RomError::InvalidPatch(reason)) if reason.contains("checksum")));
+    }
+
+    #[test]
+    fn test_bps_varint_roundtrip() {
+        for value in [0, 1, 0x7F, 0x80, 0x4000, 0x12_3456] {
+            let bytes = varint(value);
+            assert_eq!(Reader::new(&bytes, 0).varint().unwrap(), value);
+        }
+    }
+
+    /// BPS patch of `source` with the given source, target and metadata sizes, checksums
+    /// valid for an unchanged target.
+    fn bps_with_sizes(source: &[u8], sizes: [usize; 3], actions: &[u8]) -> Vec<u8> {
+        let mut patch = BPS_MAGIC.to_vec();
+        for size in sizes {
+            patch.extend(varint(size));
+        }
+        patch.extend_from_slice(actions);
+        patch.extend(crc32(source).to_le_bytes());
+        patch.extend(crc32(source).to_le_bytes());
+        let crc = crc32(&patch);
+        patch.extend(crc.to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn test_bps_huge_length_rejected() {
+        let source = b"ABCD".to_vec();
+        let patch = bps_with_sizes(&source, [4, 4, usize::MAX], &action(0, 4));
+        let result = apply_patch(&source, &patch);
+        assert!(matches!(result, Err(RomError::InvalidPatch(reason)) if reason.contains("end of patch")));
+
+        let mut reader = Reader::new(b"ABCD", 2);
+        assert!(reader.bytes(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_bps_varint_overflow_rejected() {
+        // A continuation byte with all bits set, the last one going past 64 bits
+        let mut bytes = vec![0x7F; 9];
+        bytes.push(0xFF);
+        assert!(Reader::new(&bytes, 0).varint().is_err());
+
+        // Never terminated
+        assert!(Reader::new(&[0x00; 32], 0).varint().is_err());
+    }
+
+    #[test]
+    fn test_bps_huge_target_size_rejected() {
+        let source = b"ABCD".to_vec();
+        let patch = bps_with_sizes(&source, [4, usize::MAX >> 1, 0], &action(0, 4));
+        let result = apply_patch(&source, &patch);
+        assert!(matches!(result, Err(RomError::InvalidPatch(reason)) if reason.contains("too large")));
+    }
+}
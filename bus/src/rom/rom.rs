@@ -1,6 +1,9 @@
 use crate::constants::{BANK_SIZE, COPIER_HEADER_SIZE, LOROM_BANK_SIZE};
+use crate::rom::archive;
+use crate::rom::database::{RomDatabase, RomHashes, RomIdentity};
 use crate::rom::error::RomError;
 use crate::rom::header::RomHeader;
+use crate::rom::patch::apply_patch;
 use crate::rom::header::mapping_mode::MappingMode;
 use common::snes_address::SnesAddress;
 use std::fs::File;
@@ -17,49 +20,144 @@ use std::path::Path;
 ///   Accessible in banks 0x00–0x3F and 0x80–0xBF. Each bank contributes 64 KiB to the ROM.
 ///
 /// Some cartridges may contain a 512-byte copier header at the start of the file,
-/// which is removed on load.
+/// which is removed on load, and interleaved HiROM dumps are de-interleaved.
 /// ROM data is read-only and any write attempts are ignored.
 #[derive(PartialEq)]
 pub struct Rom {
     pub data: Vec<u8>,
     pub map: MappingMode,
     pub header: RomHeader,
+    /// What the loader removed from the file to get `data`
+    pub copier_format: CopierFormat,
+}
+
+/// Copier additions detected in a dump and undone on load.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CopierFormat {
+    /// A 512-byte copier header (SMC, SWC, FIG) was stripped
+    pub header_stripped: bool,
+    /// The dump was an interleaved HiROM one and has been de-interleaved
+    pub deinterleaved: bool,
+}
+
+/// Settings for ROMs that the loader cannot handle on its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LoadOptions {
+    /// Mapping to use instead of the detected one, for ROMs whose LoROM and HiROM headers
+    /// score the same (homebrew with a blank header) or whose header is wrong. The header
+    /// is then read at the offset of this mapping, whatever mapping it declares.
+    pub force_mapping: Option<MappingMode>,
 }
 
 impl Rom {
+    /// Loads a ROM file, or the single ROM of a .zip archive when the `zip` feature is enabled.
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, RomError> {
-        let mut file = File::open(path).map_err(RomError::IoError)?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).map_err(RomError::IoError)?;
+        Self::load_from_file_with_options(path, &LoadOptions::default())
+    }
+
+    /// [`Self::load_from_file`] with `options` overriding the detection.
+    pub fn load_from_file_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &LoadOptions,
+    ) -> Result<Self, RomError> {
+        Self::from_bytes_with_options(read_rom_file(path)?, options)
+    }
+
+    /// Loads a ROM and soft-patches it with an IPS or BPS patch file before mapping detection.
+    ///
+    /// The patch is applied to the ROM without its copier header and de-interleaved; the files
+    /// are left untouched.
+    pub fn load_from_file_with_patch<P: AsRef<Path>, Q: AsRef<Path>>(
+        path: P,
+        patch_path: Option<Q>,
+    ) -> Result<Self, RomError> {
+        let (rom_data, copier_format) = strip_copier_format(read_rom_file(path)?)?;
+        let rom_data = match patch_path {
+            Some(patch_path) => apply_patch(&rom_data, &read_file(patch_path)?)?,
+            None => rom_data,
+        };
+        Self::from_data(rom_data, copier_format, &LoadOptions::default())
+    }
 
-        if buffer.len() < LOROM_BANK_SIZE {
+    /// Loads a ROM from the raw contents of a ROM file, copier header included.
+    pub fn from_bytes(buffer: Vec<u8>) -> Result<Self, RomError> {
+        Self::from_bytes_with_options(buffer, &LoadOptions::default())
+    }
+
+    /// [`Self::from_bytes`] with `options` overriding the detection.
+    pub fn from_bytes_with_options(
+        buffer: Vec<u8>,
+        options: &LoadOptions,
+    ) -> Result<Self, RomError> {
+        let (rom_data, copier_format) = strip_copier_format(buffer)?;
+        Self::from_data(rom_data, copier_format, options)
+    }
+
+    fn from_data(
+        rom_data: Vec<u8>,
+        copier_format: CopierFormat,
+        options: &LoadOptions,
+    ) -> Result<Self, RomError> {
+        if rom_data.len() < LOROM_BANK_SIZE {
             return Err(RomError::FileTooSmall);
         }
 
-        // Check for 512-byte header
-        let rom_data = if buffer.len() % LOROM_BANK_SIZE == COPIER_HEADER_SIZE {
-            buffer[COPIER_HEADER_SIZE..].to_vec() // Remove useless "Copier" 512-byte header
-        } else {
-            buffer.to_vec()
+        let map_mode = match options.force_mapping {
+            Some(map_mode) => map_mode,
+            None => MappingMode::detect_rom_mapping(&rom_data).ok_or(RomError::IncorrectMapping)?,
         };
-
-        // Check map mode
-        let map_mode =
-            MappingMode::detect_rom_mapping(&rom_data).ok_or(RomError::IncorrectMapping)?;
-        let header = RomHeader::load_header(&rom_data, map_mode);
+        let header = RomHeader::load_header(&rom_data, map_mode)?;
 
         // Detect if found mapping and header mapping are different
         if map_mode != header.mapping_mode {
-            return Err(RomError::IncorrectMapping);
+            if options.force_mapping.is_none() {
+                return Err(RomError::IncorrectMapping);
+            }
+            tracing::warn!(
+                forced = %map_mode,
+                header = %header.mapping_mode,
+                "Forced mapping differs from the header one"
+            );
         }
 
         Ok(Rom {
             data: rom_data,
             map: map_mode,
             header: header,
+            copier_format,
         })
     }
 
+    /// CRC32 and SHA-1 of the ROM, without its copier header.
+    pub fn hashes(&self) -> RomHashes {
+        RomHashes::of(&self.data)
+    }
+
+    /// Looks the ROM up in `database` to find its canonical name and dump status.
+    pub fn identify<'a>(&self, database: &'a RomDatabase) -> RomIdentity<'a> {
+        let hashes = self.hashes();
+        RomIdentity {
+            hashes,
+            entry: database.lookup(&hashes, self.data.len()),
+        }
+    }
+
+    /// Checksum of the ROM data, computed the way the header checksum is.
+    ///
+    /// Sizes that are not a power of two are made of a power of two part
+    /// followed by a smaller one, which is mirrored until it fills the same
+    /// size as the first part (a 3 MiB ROM is summed as 2 MiB + 2 x 1 MiB).
+    pub fn checksum(&self) -> u16 {
+        mirrored_sum(&self.data, self.data.len().next_power_of_two())
+    }
+
+    /// Whether the header checksum and its complement match the ROM data.
+    pub fn verify_checksum(&self) -> bool {
+        let header = &self.header;
+        header.checksum ^ header.checksum_complement == 0xFFFF
+            && header.checksum == self.checksum()
+    }
+
     fn panic_invalid_addr(addr: SnesAddress) -> ! {
         panic!(
             "Incorrect access to the ROM at address: {:06X}",
@@ -150,6 +248,30 @@ impl Rom {
         ));
     }
 
+    /// Reads a byte from the ROM, or `None` when `addr` maps outside of it.
+    ///
+    /// Used by tools walking the address space, where unmapped regions are expected.
+    pub fn try_read(&self, addr: SnesAddress) -> Option<u8> {
+        self.offset(addr).map(|offset| self.data[offset])
+    }
+
+    /// Offset in `data` of the byte at `addr`, or `None` when `addr` maps outside of it.
+    pub fn offset(&self, addr: SnesAddress) -> Option<usize> {
+        let mapped = matches!(
+            (addr.bank, addr.addr),
+            | (0x00..=0x7D, 0x8000..=0xFFFF)
+            | (0x80..=0xFF, 0x8000..=0xFFFF)
+            | (0x40..=0x7D, _)
+            | (0xC0..=0xFF, _)
+        );
+
+        if !mapped {
+            return None;
+        }
+        let offset = self.to_offset(addr);
+        (offset < self.data.len()).then_some(offset)
+    }
+
     /// Ignores writes to the ROM.
     ///
     /// ROM is read-only; this function performs no action.
@@ -157,12 +279,127 @@ impl Rom {
         // ROM is read-only, ignore writes
         // TODO : Add a warning ?
     }
+
+    /// Reads `buf.len()` consecutive bytes starting at `addr`.
+    ///
+    /// Copies the whole block at once when it stays within one bank and is
+    /// contiguous in the ROM data, falls back to byte per byte reads otherwise.
+    ///
+    /// # Panics
+    /// Panics if one of the addresses is invalid or out of bounds.
+    pub fn read_block(&self, addr: SnesAddress, buf: &mut [u8]) {
+        let len = buf.len();
+
+        if len > 0 && addr.addr as usize + len <= 0x10000 {
+            let last = SnesAddress { bank: addr.bank, addr: addr.addr + (len - 1) as u16 };
+
+            // The mappings are linear within a bank except at the $8000 edge,
+            // where the offsets of both ends stop being `len` bytes apart
+            let start = self.to_offset(addr);
+            if self.to_offset(last) == start + len - 1 && start + len <= self.data.len() {
+                buf.copy_from_slice(&self.data[start..start + len]);
+                return;
+            }
+        }
+
+        let mut addr = addr;
+        for byte in buf {
+            *byte = self.read(addr);
+            addr.increment();
+        }
+    }
+
+    /// Ignores block writes to the ROM, see [`Self::write`].
+    pub fn write_block(&mut self, _addr: SnesAddress, _data: &[u8]) {}
+}
+
+/// Byte sum of `data` mirrored to fill `size` bytes, `size` being a power of two.
+fn mirrored_sum(data: &[u8], size: usize) -> u16 {
+    if data.is_empty() {
+        return 0;
+    }
+
+    let base = 1 << data.len().ilog2();
+    let sum = if data.len() == base {
+        data.iter().fold(0u16, |acc, &b| acc.wrapping_add(b as u16))
+    } else {
+        let (head, tail) = data.split_at(base);
+        mirrored_sum(head, base).wrapping_add(mirrored_sum(tail, base))
+    };
+    let block = if data.len() == base { base } else { 2 * base };
+    sum.wrapping_mul((size / block) as u16)
+}
+
+fn read_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, RomError> {
+    let mut file = File::open(path).map_err(RomError::IoError)?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).map_err(RomError::IoError)?;
+    Ok(buffer)
+}
+
+/// Reads a ROM file, extracting it first if it is a zip archive.
+fn read_rom_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, RomError> {
+    let buffer = read_file(path)?;
+    if archive::is_zip(&buffer) {
+        archive::extract_rom(&buffer)
+    } else {
+        Ok(buffer)
+    }
+}
+
+/// Returns the ROM without its copier header, and whether there was one.
+fn strip_copier_header(buffer: Vec<u8>) -> Result<(Vec<u8>, bool), RomError> {
+    if buffer.len() < LOROM_BANK_SIZE {
+        return Err(RomError::FileTooSmall);
+    }
+
+    // Check for 512-byte header
+    if buffer.len() % LOROM_BANK_SIZE == COPIER_HEADER_SIZE {
+        Ok((buffer[COPIER_HEADER_SIZE..].to_vec(), true)) // Remove useless "Copier" 512-byte header
+    } else {
+        Ok((buffer, false))
+    }
+}
+
+/// Restores the layout of an interleaved HiROM dump, which stores the upper 32 KiB
+/// halves of all the banks before the lower ones.
+fn deinterleave(rom_data: &[u8]) -> Vec<u8> {
+    let halves: Vec<&[u8]> = rom_data.chunks(LOROM_BANK_SIZE).collect();
+    let (upper, lower) = halves.split_at(halves.len() / 2);
+
+    lower
+        .iter()
+        .zip(upper)
+        .flat_map(|(lower, upper)| [*lower, *upper])
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// Removes what copiers add to a dump: the copier header and the interleaving.
+fn strip_copier_format(buffer: Vec<u8>) -> Result<(Vec<u8>, CopierFormat), RomError> {
+    let (rom_data, header_stripped) = strip_copier_header(buffer)?;
+    let deinterleaved = MappingMode::detect_interleaved(&rom_data);
+    let format = CopierFormat {
+        header_stripped,
+        deinterleaved,
+    };
+
+    if deinterleaved {
+        tracing::debug!(size = rom_data.len(), "De-interleaving HiROM dump");
+        Ok((deinterleave(&rom_data), format))
+    } else {
+        Ok((rom_data, format))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::constants::{COPIER_HEADER_SIZE, HIROM_BANK_SIZE, LOROM_BANK_SIZE};
+    use crate::constants::{
+        COPIER_HEADER_SIZE, HEADER_SPEED_MAP_OFFSET, HIROM_BANK_SIZE, HIROM_HEADER_OFFSET,
+        LOROM_BANK_SIZE, LOROM_HEADER_OFFSET,
+    };
     use crate::rom::header::mapping_mode::MappingMode;
     use crate::rom::test_rom::*;
     use common::snes_address::snes_addr;
@@ -208,6 +445,176 @@ mod tests {
         // Check copier header removed
         assert_eq!(rom.data.len(), HIROM_BANK_SIZE);
         assert_eq!(rom.data[0], 0);
+        assert_eq!(
+            rom.copier_format,
+            CopierFormat {
+                header_stripped: true,
+                deinterleaved: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_load_rom_without_copier_format() {
+        let rom = Rom::from_bytes(create_valid_lorom(HIROM_BANK_SIZE)).unwrap();
+        assert_eq!(rom.copier_format, CopierFormat::default());
+    }
+
+    #[test]
+    fn test_load_interleaved_hirom() {
+        let mut data = create_valid_hirom(4 * HIROM_BANK_SIZE);
+        data[0x0000] = 0x11; // $C0:0000, lower half
+        data[0x8000] = 0x22; // $C0:8000, upper half
+        data[0x38000] = 0x33; // $C3:8000
+        let (path, _dir) = create_temp_rom(&interleave(&data));
+
+        let rom = Rom::load_from_file(&path).unwrap();
+        assert_eq!(rom.map, MappingMode::HiRom);
+        assert_eq!(rom.data, data);
+        assert_eq!(rom.read(snes_addr!(0xC0:0x0000)), 0x11);
+        assert_eq!(rom.read(snes_addr!(0xC0:0x8000)), 0x22);
+        assert_eq!(rom.read(snes_addr!(0xC3:0x8000)), 0x33);
+    }
+
+    #[test]
+    fn test_load_interleaved_hirom_with_copier_header() {
+        let data = create_valid_hirom(2 * HIROM_BANK_SIZE);
+        let mut copier_header_data: Vec<u8> = vec![0x00; COPIER_HEADER_SIZE];
+        copier_header_data.extend_from_slice(&interleave(&data));
+
+        let rom = Rom::from_bytes(copier_header_data).unwrap();
+        assert_eq!(rom.map, MappingMode::HiRom);
+        assert_eq!(rom.data, data);
+        assert!(rom.copier_format.header_stripped && rom.copier_format.deinterleaved);
+    }
+
+    /// ROM whose headers score the same at the LoROM and HiROM offsets: blank, except a
+    /// LoROM header declaring HiROM and a HiROM header declaring LoROM.
+    fn ambiguous_rom() -> Vec<u8> {
+        let mut data = vec![0x00; 2 * HIROM_BANK_SIZE];
+        data[LOROM_HEADER_OFFSET + HEADER_SPEED_MAP_OFFSET] = 0x21;
+        data[HIROM_HEADER_OFFSET + HEADER_SPEED_MAP_OFFSET] = 0x20;
+        data
+    }
+
+    #[test]
+    fn test_ambiguous_mapping_fails_without_override() {
+        let result = Rom::from_bytes(ambiguous_rom());
+        assert!(matches!(result, Err(RomError::IncorrectMapping)));
+    }
+
+    #[test]
+    fn test_forced_mapping() {
+        for map in [MappingMode::LoRom, MappingMode::HiRom] {
+            let options = LoadOptions {
+                force_mapping: Some(map),
+            };
+            let rom = Rom::from_bytes_with_options(ambiguous_rom(), &options).unwrap();
+            assert_eq!(rom.map, map);
+        }
+    }
+
+    #[test]
+    fn test_forced_mapping_overrides_detection() {
+        let options = LoadOptions {
+            force_mapping: Some(MappingMode::HiRom),
+        };
+        let data = create_valid_lorom(HIROM_BANK_SIZE);
+        let rom = Rom::from_bytes_with_options(data, &options).unwrap();
+        assert_eq!(rom.map, MappingMode::HiRom);
+    }
+
+    #[test]
+    fn test_patch_applies_to_deinterleaved_rom() {
+        let data = create_valid_hirom(2 * HIROM_BANK_SIZE);
+        let (path, _dir) = create_temp_rom(&interleave(&data));
+
+        // Offsets are relative to the de-interleaved ROM
+        let patch = b"PATCH\x00\x80\x00\x00\x01\xEAEOF".to_vec();
+        let (patch_path, _patch_dir) = create_temp_rom(&patch);
+
+        let rom = Rom::load_from_file_with_patch(&path, Some(&patch_path)).unwrap();
+        assert_eq!(rom.read(snes_addr!(0xC0:0x8000)), 0xEA);
+    }
+
+    #[test]
+    fn test_deinterleave_reverses_interleave() {
+        let data: Vec<u8> = (0..4 * LOROM_BANK_SIZE).map(|i| (i / LOROM_BANK_SIZE) as u8).collect();
+        assert_eq!(deinterleave(&interleave(&data)), data);
+    }
+
+    #[test]
+    fn test_load_rom_with_ips_patch() {
+        let data = create_valid_lorom(0x10000);
+        let mut copier_header_data: Vec<u8> = vec![0xFF; COPIER_HEADER_SIZE];
+        copier_header_data.extend_from_slice(&data);
+        let (path, _dir) = create_temp_rom(&copier_header_data);
+
+        // Offsets are relative to the ROM without its copier header
+        let patch = b"PATCH\x00\x80\x00\x00\x02\xEA\xEAEOF".to_vec();
+        let (patch_path, _patch_dir) = create_temp_rom(&patch);
+
+        let rom = Rom::load_from_file_with_patch(&path, Some(&patch_path)).unwrap();
+        assert_eq!(rom.data.len(), 0x10000);
+        assert_eq!(rom.read(snes_addr!(0x81:0x8000)), 0xEA);
+        assert_eq!(rom.read(snes_addr!(0x81:0x8001)), 0xEA);
+        assert_eq!(rom.read(snes_addr!(0x81:0x8002)), 0x00);
+    }
+
+    #[test]
+    fn test_load_rom_without_patch() {
+        let data = create_valid_lorom(0x10000);
+        let (path, _dir) = create_temp_rom(&data);
+
+        let rom = Rom::load_from_file_with_patch(&path, None::<&Path>).unwrap();
+        assert_eq!(rom.data, data);
+    }
+
+    #[test]
+    fn test_load_rom_invalid_patch() {
+        let data = create_valid_lorom(0x10000);
+        let (path, _dir) = create_temp_rom(&data);
+        let (patch_path, _patch_dir) = create_temp_rom(b"NOT A PATCH");
+
+        let result = Rom::load_from_file_with_patch(&path, Some(&patch_path));
+        assert!(matches!(result, Err(RomError::InvalidPatch(_))));
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_load_rom_from_zip() {
+        use std::io::{Cursor, Write};
+
+        let data = create_valid_lorom(0x10000);
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file("game.sfc", zip::write::SimpleFileOptions::default()).unwrap();
+        writer.write_all(&data).unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+        let (path, _dir) = create_temp_rom(&archive);
+
+        let rom = Rom::load_from_file(&path).unwrap();
+        assert_eq!(rom.data, data);
+    }
+
+    #[test]
+    fn test_identify() {
+        let data = create_valid_lorom(0x10000);
+        let (path, _dir) = create_temp_rom(&data);
+        let rom = Rom::load_from_file(&path).unwrap();
+        let hashes = rom.hashes();
+
+        let dat = format!(
+            r#"<game name="Test Game (USA)"><rom name="Test.sfc" size="65536" sha1="{}"/></game>"#,
+            hashes.sha1_hex()
+        );
+        let database = RomDatabase::parse_dat(&dat).unwrap();
+
+        let identity = rom.identify(&database);
+        assert_eq!(identity.hashes, hashes);
+        assert_eq!(identity.name(), Some("Test Game (USA)"));
+        assert!(identity.is_known_good());
+
+        assert_eq!(rom.identify(&RomDatabase::new()).name(), None);
     }
 
     #[test]
@@ -493,4 +900,104 @@ mod tests {
         let addr = snes_addr!(0x7E:0x4000);
         assert_eq!(Rom::get_hirom_offset(addr), 0);
     }
+
+    #[test]
+    fn test_read_block_contiguous() {
+        let mut data = create_valid_lorom(0x10000);
+        data[0x8010..0x8014].copy_from_slice(&[1, 2, 3, 4]);
+        let (path, _dir) = create_temp_rom(&data);
+        let rom = Rom::load_from_file(path).unwrap();
+
+        let mut buf = [0; 4];
+        rom.read_block(snes_addr!(0x81:0x8010), &mut buf);
+        assert_eq!(buf, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_read_block_across_lorom_mirror() {
+        let mut data = create_valid_lorom(0x210000);
+        data[0x207FFE..0x208000].copy_from_slice(&[1, 2]);
+        data[0x200000..0x200002].copy_from_slice(&[3, 4]);
+        let (path, _dir) = create_temp_rom(&data);
+        let rom = Rom::load_from_file(path).unwrap();
+
+        // $40:7FFF is followed by $40:8000, which maps back to the bank start
+        let mut buf = [0; 4];
+        rom.read_block(snes_addr!(0x40:0x7FFE), &mut buf);
+        assert_eq!(buf, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_write_block_ignored() {
+        let data = create_valid_lorom(0x10000);
+        let (path, _dir) = create_temp_rom(&data);
+        let mut rom = Rom::load_from_file(path).unwrap();
+
+        rom.write_block(snes_addr!(0:0x8000), &[0xFF; 4]);
+        assert_eq!(rom.read(snes_addr!(0:0x8000)), 0);
+    }
+
+    #[test]
+    fn test_checksum_power_of_two() {
+        let mut data = create_valid_lorom(0x10000);
+        data[0] = 0x12;
+        data[0x9000] = 0x34;
+        let rom = Rom::from_bytes(data.clone()).unwrap();
+
+        let expected = data.iter().fold(0u16, |acc, &b| acc.wrapping_add(b as u16));
+        assert_eq!(rom.checksum(), expected);
+    }
+
+    #[test]
+    fn test_checksum_mirrors_remainder() {
+        // 96 KiB: the last 32 KiB are counted twice to fill 128 KiB
+        let mut data = create_valid_lorom(0x18000);
+        data[0x10000] = 0x01;
+        let rom = Rom::from_bytes(data.clone()).unwrap();
+
+        let head = data[..0x10000].iter().fold(0u16, |acc, &b| acc.wrapping_add(b as u16));
+        assert_eq!(rom.checksum(), head.wrapping_add(2));
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let mut data = create_valid_lorom(0x10000);
+        let rom = Rom::from_bytes(data.clone()).unwrap();
+        assert!(!rom.verify_checksum());
+
+        // The checksum bytes and their complement always add up to 2 * 0xFF
+        let checksum = rom.checksum();
+        let complement = !checksum;
+        let offset = LOROM_HEADER_OFFSET + 28;
+        data[offset..offset + 4].copy_from_slice(&[
+            complement as u8,
+            (complement >> 8) as u8,
+            checksum as u8,
+            (checksum >> 8) as u8,
+        ]);
+        let rom = Rom::from_bytes(data).unwrap();
+        assert!(rom.verify_checksum());
+    }
+
+    #[test]
+    fn test_try_read() {
+        let mut data = create_valid_lorom(0x10000);
+        data[0x8000] = 0x42;
+        let rom = Rom::from_bytes(data).unwrap();
+
+        assert_eq!(rom.try_read(snes_addr!(0x81:0x8000)), Some(0x42));
+        assert_eq!(rom.try_read(snes_addr!(0x00:0x2100)), None);
+        assert_eq!(rom.try_read(snes_addr!(0x10:0x8000)), None);
+    }
+
+    #[test]
+    fn test_offset() {
+        let rom = Rom::from_bytes(create_valid_lorom(0x10000)).unwrap();
+
+        assert_eq!(rom.offset(snes_addr!(0x81:0x8010)), Some(0x8010));
+        assert_eq!(rom.offset(snes_addr!(0x01:0x8010)), Some(0x8010));
+        assert_eq!(rom.offset(snes_addr!(0x41:0x0010)), None);
+        assert_eq!(rom.offset(snes_addr!(0x00:0x2100)), None);
+        assert_eq!(rom.offset(snes_addr!(0x10:0x8000)), None);
+    }
 }
@@ -66,6 +66,16 @@ pub fn create_valid_hirom(size: usize) -> Vec<u8> {
     rom
 }
 
+/// Interleaves a HiROM image like old copiers did: the upper 32 KiB halves of all the
+/// banks, then the lower ones.
+#[cfg(not(tarpaulin_include))]
+pub fn interleave(rom: &[u8]) -> Vec<u8> {
+    let halves: Vec<&[u8]> = rom.chunks(LOROM_BANK_SIZE).collect();
+    let upper = halves.iter().skip(1).step_by(2);
+    let lower = halves.iter().step_by(2);
+    upper.chain(lower).flat_map(|half| half.iter().copied()).collect()
+}
+
 #[cfg(not(tarpaulin_include))]
 pub fn create_temp_rom(data: &[u8]) -> (std::path::PathBuf, tempfile::TempDir) {
     let dir = tempdir().unwrap();
@@ -1,5 +1,7 @@
 use crate::constants::WRAM_SIZE;
 
+use common::power_on::MemoryInit;
+use common::save_state::{Migration, StateError, StateReader, StateWriter, Stateful, Tag};
 use common::snes_address::SnesAddress;
 
 /// WRAM (Work RAM) - 128 KiB (2 full banks)
@@ -12,17 +14,28 @@ use common::snes_address::SnesAddress;
 /// the same memory location.
 ///
 /// Warning: bank 0x7F is not mirrored, so `0x7F1000` is independent.
+///
+/// WRAM is also accessed through its B-bus ports, see [`Self::read_wmdata`] and
+/// [`Self::write_port`].
 pub struct Wram {
     pub data: Box<[u8; WRAM_SIZE]>,
+    /// **WMADD** (`$2181-$2183`) - Address of the WMDATA port, 17 bits
+    pub wmadd: u32,
 }
 
 impl Wram {
     pub fn new() -> Self {
         Self {
             data: Box::new([0; WRAM_SIZE]),
+            wmadd: 0,
         }
     }
 
+    /// Sets the power-on contents.
+    pub fn power_on(&mut self, init: MemoryInit) {
+        init.fill(&mut self.data[..]);
+    }
+
     fn panic_invalid_addr(addr: SnesAddress) -> ! {
         panic!(
             "Incorrect access to the WRAM at address: {:06X}",
@@ -80,6 +93,113 @@ impl Wram {
     }
 }
 
+impl Wram {
+    /// Returns the WRAM offsets of `len` bytes starting at `addr`, if they
+    /// are stored contiguously (no bank end or mirror boundary in between).
+    fn contiguous_range(addr: SnesAddress, len: usize) -> Option<std::ops::Range<usize>> {
+        if len == 0 || addr.addr as usize + len > 0x10000 {
+            return None;
+        }
+        let last = SnesAddress::from(usize::from(addr) + len - 1);
+        if !matches!(addr.bank, 0x7E | 0x7F) && last.addr >= 0x2000 {
+            return None;
+        }
+
+        let start = Self::to_offset(addr);
+        Some(start..start + len)
+    }
+
+    /// Reads `buf.len()` consecutive bytes starting at `addr`.
+    ///
+    /// Copies the whole block at once when it is contiguous in WRAM,
+    /// falls back to byte per byte reads otherwise.
+    ///
+    /// # Panics
+    /// Panics if one of the addresses is invalid.
+    pub fn read_block(&self, addr: SnesAddress, buf: &mut [u8]) {
+        if let Some(range) = Self::contiguous_range(addr, buf.len()) {
+            buf.copy_from_slice(&self.data[range]);
+            return;
+        }
+
+        let mut addr = addr;
+        for byte in buf {
+            *byte = self.read(addr);
+            addr.increment();
+        }
+    }
+
+    /// Writes `data` to consecutive addresses starting at `addr`.
+    ///
+    /// Copies the whole block at once when it is contiguous in WRAM,
+    /// falls back to byte per byte writes otherwise.
+    ///
+    /// # Panics
+    /// Panics if one of the addresses is invalid.
+    pub fn write_block(&mut self, addr: SnesAddress, data: &[u8]) {
+        if let Some(range) = Self::contiguous_range(addr, data.len()) {
+            self.data[range].copy_from_slice(data);
+            return;
+        }
+
+        let mut addr = addr;
+        for &byte in data {
+            self.write(addr, byte);
+            addr.increment();
+        }
+    }
+}
+
+impl Wram {
+    /// **WMDATA** (`$2180`, R) - Byte at WMADD, which then increments.
+    pub fn read_wmdata(&mut self) -> u8 {
+        let value = self.data[self.wmadd as usize];
+        self.increment_wmadd();
+        value
+    }
+
+    /// Writes WMDATA (`$2180`), storing `value` at WMADD which then increments, or a byte
+    /// of WMADD (`$2181-$2183`).
+    pub fn write_port(&mut self, port: u16, value: u8) {
+        let value = value as u32;
+        match port {
+            0x2180 => {
+                self.data[self.wmadd as usize] = value as u8;
+                self.increment_wmadd();
+            }
+            0x2181 => self.wmadd = (self.wmadd & 0x1FF00) | value,
+            0x2182 => self.wmadd = (self.wmadd & 0x100FF) | value << 8,
+            0x2183 => self.wmadd = (self.wmadd & 0x0FFFF) | (value & 0x01) << 16,
+            _ => {}
+        }
+    }
+
+    fn increment_wmadd(&mut self) {
+        self.wmadd = (self.wmadd + 1) % WRAM_SIZE as u32;
+    }
+}
+
+impl Stateful for Wram {
+    const TAG: Tag = *b"WRAM";
+    const VERSION: u16 = 2;
+    /// Version 1 had no WMADD: add it, at 0
+    const MIGRATIONS: &'static [Migration] = &[|data| Ok([data, &[0, 0, 0, 0]].concat())];
+
+    fn save(&self, writer: &mut StateWriter) {
+        writer.bytes(&self.data[..]);
+        writer.u32(self.wmadd);
+    }
+
+    fn load(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.data.copy_from_slice(reader.bytes(WRAM_SIZE)?);
+        self.wmadd = reader.u32()?;
+        if self.wmadd as usize >= WRAM_SIZE {
+            return Err(reader.corrupt());
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,4 +280,71 @@ mod tests {
         wram.write(second_bank_end, 0x45);
         assert_eq!(wram.read(second_bank_end), 0x45);
     }
+
+    #[test]
+    fn test_block_read_write() {
+        let mut wram = Wram::new();
+        let addr = snes_addr!(0x7E:0x1234);
+
+        wram.write_block(addr, &[1, 2, 3, 4]);
+        assert_eq!(wram.read(snes_addr!(0x7E:0x1236)), 3);
+
+        let mut buf = [0; 4];
+        wram.read_block(snes_addr!(0x00:0x1234), &mut buf);
+        assert_eq!(buf, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_block_across_banks() {
+        let mut wram = Wram::new();
+
+        // 7E:FFFF and 7F:0000 are contiguous in WRAM
+        wram.write_block(snes_addr!(0x7E:0xFFFE), &[1, 2, 3, 4]);
+        assert_eq!(wram.read(snes_addr!(0x7E:0xFFFF)), 2);
+        assert_eq!(wram.read(snes_addr!(0x7F:0x0001)), 4);
+
+        let mut buf = [0; 4];
+        wram.read_block(snes_addr!(0x7E:0xFFFE), &mut buf);
+        assert_eq!(buf, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Incorrect access to the WRAM at address: 002000")]
+    fn test_block_past_mirror_panics() {
+        let mut wram = Wram::new();
+
+        wram.write_block(snes_addr!(0x00:0x1FFF), &[1, 2]);
+    }
+
+    #[test]
+    fn test_wmdata_port() {
+        let mut wram = Wram::new();
+        wram.write_port(0x2181, 0xFF);
+        wram.write_port(0x2182, 0xFF);
+        wram.write_port(0x2183, 0xFE); // only bit 0 is used
+        assert_eq!(wram.wmadd, 0xFFFF);
+
+        wram.write_port(0x2180, 0x12);
+        wram.write_port(0x2180, 0x34);
+        assert_eq!(wram.read(snes_addr!(0x7E:0xFFFF)), 0x12);
+        assert_eq!(wram.read(snes_addr!(0x7F:0x0000)), 0x34);
+
+        // WMADD wraps around at the end of WRAM
+        wram.write_port(0x2183, 0x01);
+        wram.write_port(0x2182, 0xFF);
+        wram.write_port(0x2181, 0xFF);
+        wram.data[0x1FFFF] = 0x56;
+        wram.data[0] = 0x78;
+        assert_eq!([wram.read_wmdata(), wram.read_wmdata()], [0x56, 0x78]);
+        assert_eq!(wram.wmadd, 1);
+    }
+
+    #[test]
+    fn test_power_on_pattern() {
+        let mut wram = Wram::new();
+        wram.power_on(MemoryInit::Stripes);
+
+        assert_eq!(wram.read(snes_addr!(0x00:0x0000)), 0x55);
+        assert_eq!(wram.read(snes_addr!(0x7F:0xFFFF)), 0xAA);
+    }
 }
@@ -0,0 +1,77 @@
+/* C API of the r-snes emulator, implemented by the `capi` crate. */
+
+#ifndef RSNES_H
+#define RSNES_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RSNES_API_VERSION 3
+
+/* Return codes */
+#define RSNES_OK 0
+#define RSNES_ERROR_NULL_POINTER -1
+#define RSNES_ERROR_NO_ROM -2
+#define RSNES_ERROR_INVALID_ROM -3
+#define RSNES_ERROR_CRASHED -4
+#define RSNES_ERROR_UNSUPPORTED -5
+#define RSNES_ERROR_INVALID_PORT -6
+#define RSNES_ERROR_BUFFER_TOO_SMALL -7
+#define RSNES_ERROR_INVALID_STATE -8
+
+/* Gamepad buttons, for rsnes_set_buttons */
+#define RSNES_BUTTON_B 0x8000
+#define RSNES_BUTTON_Y 0x4000
+#define RSNES_BUTTON_SELECT 0x2000
+#define RSNES_BUTTON_START 0x1000
+#define RSNES_BUTTON_UP 0x0800
+#define RSNES_BUTTON_DOWN 0x0400
+#define RSNES_BUTTON_LEFT 0x0200
+#define RSNES_BUTTON_RIGHT 0x0100
+#define RSNES_BUTTON_A 0x0080
+#define RSNES_BUTTON_X 0x0040
+#define RSNES_BUTTON_L 0x0020
+#define RSNES_BUTTON_R 0x0010
+
+/* Emulators share no state and can each run on their own thread; a handle must not be
+ * used by two threads at once */
+typedef struct RSnesEmulator RSnesEmulator;
+
+unsigned int rsnes_api_version(void);
+
+RSnesEmulator *rsnes_create(void);
+void rsnes_destroy(RSnesEmulator *emulator);
+
+/* Copies a ROM file (copier header included) and loads it */
+int rsnes_load_rom(RSnesEmulator *emulator, const uint8_t *data, size_t size);
+int rsnes_reset(RSnesEmulator *emulator);
+int rsnes_run_frame(RSnesEmulator *emulator);
+/* Emulates a frame without drawing it, for frame skipping (API version 2) */
+int rsnes_skip_frame(RSnesEmulator *emulator);
+
+/* R, G, B, A bytes; valid until the next call with the same emulator */
+const uint8_t *rsnes_framebuffer(RSnesEmulator *emulator, unsigned int *width,
+                                 unsigned int *height, size_t *pitch);
+/* Interleaved left/right samples; valid until the next call with the same emulator */
+const int16_t *rsnes_audio(RSnesEmulator *emulator, size_t *frames);
+unsigned int rsnes_sample_rate(void);
+double rsnes_frame_rate(RSnesEmulator *emulator);
+
+int rsnes_set_buttons(RSnesEmulator *emulator, unsigned int port, uint16_t buttons);
+
+/* Save states (API version 3). rsnes_state_size is fixed when the ROM is loaded, with room
+ * for the few bytes a coprocessor running a command adds: rsnes_save_state zero-fills the end
+ * of the buffer, which rsnes_load_state accepts */
+size_t rsnes_state_size(RSnesEmulator *emulator);
+int rsnes_save_state(RSnesEmulator *emulator, uint8_t *buffer, size_t size);
+int rsnes_load_state(RSnesEmulator *emulator, const uint8_t *buffer, size_t size);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RSNES_H */
@@ -0,0 +1,467 @@
+//! C API to embed the emulator in non-Rust frontends, declared in `include/rsnes.h`.
+//!
+//! An emulator is an opaque handle created by [`rsnes_create`] and released by
+//! [`rsnes_destroy`]. Functions returning an `int` return [`RSNES_OK`] or a negative
+//! error code. Pointers returned by the emulator stay valid until the next call taking the
+//! same handle.
+//!
+//! Emulators share no state: several can run in the same process, each on its own thread.
+//! A handle can move between threads but must not be used by two threads at once.
+//!
+//! The API only grows: existing functions keep their signature, and [`RSNES_API_VERSION`]
+//! is bumped when functions are added.
+
+use bus::rom::Rom;
+use emulator::save_state::SaveState;
+use emulator::system::{SAMPLE_RATE, System};
+use emulator::{EmulatorOptions, RSnes};
+use ppu::rendering::framebuffer::PixelFormat;
+use std::ffi::{c_int, c_uint};
+use std::slice;
+
+pub const RSNES_API_VERSION: c_uint = 3;
+
+pub const RSNES_OK: c_int = 0;
+/// A required pointer argument is null
+pub const RSNES_ERROR_NULL_POINTER: c_int = -1;
+/// No ROM is loaded
+pub const RSNES_ERROR_NO_ROM: c_int = -2;
+/// The buffer does not hold a valid ROM
+pub const RSNES_ERROR_INVALID_ROM: c_int = -3;
+/// The emulated program reached an unimplemented feature, only a reset can restart it
+pub const RSNES_ERROR_CRASHED: c_int = -4;
+/// The feature is not implemented yet
+pub const RSNES_ERROR_UNSUPPORTED: c_int = -5;
+/// Invalid controller port
+pub const RSNES_ERROR_INVALID_PORT: c_int = -6;
+/// The buffer is too small for the save state
+pub const RSNES_ERROR_BUFFER_TOO_SMALL: c_int = -7;
+/// The buffer does not hold a save state of this emulator, or a corrupted one
+pub const RSNES_ERROR_INVALID_STATE: c_int = -8;
+
+/// Room left in save states for coprocessors, whose state grows by a few bytes while they
+/// run a command
+const STATE_SIZE_MARGIN: usize = 1024;
+
+/// Opaque emulator handle
+pub struct RSnesEmulator {
+    system: Option<System>,
+    /// Size of the save states of the loaded ROM, 0 without a ROM
+    state_size: usize,
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn rsnes_api_version() -> c_uint {
+    RSNES_API_VERSION
+}
+
+/// Creates an emulator without any ROM; release it with [`rsnes_destroy`].
+#[unsafe(no_mangle)]
+pub extern "C" fn rsnes_create() -> *mut RSnesEmulator {
+    Box::into_raw(Box::new(RSnesEmulator {
+        system: None,
+        state_size: 0,
+    }))
+}
+
+/// # Safety
+/// `emulator` must be null or a handle from [`rsnes_create`] not destroyed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_destroy(emulator: *mut RSnesEmulator) {
+    if !emulator.is_null() {
+        drop(unsafe { Box::from_raw(emulator) });
+    }
+}
+
+/// Loads a ROM from the contents of a ROM file (copier header included), replacing the
+/// current one. The buffer is copied.
+///
+/// # Safety
+/// `emulator` must be a valid handle and `data` point to `size` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_load_rom(
+    emulator: *mut RSnesEmulator,
+    data: *const u8,
+    size: usize,
+) -> c_int {
+    let Some(emulator) = (unsafe { emulator.as_mut() }) else {
+        return RSNES_ERROR_NULL_POINTER;
+    };
+    if data.is_null() {
+        return RSNES_ERROR_NULL_POINTER;
+    }
+    let data = unsafe { slice::from_raw_parts(data, size) }.to_vec();
+
+    match Rom::from_bytes(data) {
+        Ok(rom) => {
+            let rsnes = RSnes::from_rom(rom, &EmulatorOptions::default());
+            let mut system = System::new(rsnes, PixelFormat::Rgba8888);
+            emulator.state_size = system.save_state().to_bytes().len() + STATE_SIZE_MARGIN;
+            emulator.system = Some(system);
+            RSNES_OK
+        }
+        Err(_) => RSNES_ERROR_INVALID_ROM,
+    }
+}
+
+/// # Safety
+/// `emulator` must be a valid handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_reset(emulator: *mut RSnesEmulator) -> c_int {
+    match unsafe { system(emulator) } {
+        Ok(system) => {
+            system.reset();
+            RSNES_OK
+        }
+        Err(err) => err,
+    }
+}
+
+/// Emulates one frame. Once [`RSNES_ERROR_CRASHED`] is returned, frames keep the last
+/// picture and are silent.
+///
+/// # Safety
+/// `emulator` must be a valid handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_run_frame(emulator: *mut RSnesEmulator) -> c_int {
+    match unsafe { system(emulator) } {
+        Ok(system) => {
+            system.run_frame();
+            if system.crashed() {
+                RSNES_ERROR_CRASHED
+            } else {
+                RSNES_OK
+            }
+        }
+        Err(err) => err,
+    }
+}
+
+/// [`rsnes_run_frame`] without drawing the picture, for frame skipping: the framebuffer
+/// keeps the previous one.
+///
+/// # Safety
+/// `emulator` must be a valid handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_skip_frame(emulator: *mut RSnesEmulator) -> c_int {
+    match unsafe { system(emulator) } {
+        Ok(system) => {
+            system.skip_frame();
+            if system.crashed() {
+                RSNES_ERROR_CRASHED
+            } else {
+                RSNES_OK
+            }
+        }
+        Err(err) => err,
+    }
+}
+
+/// Picture of the last frame, as R, G, B, A bytes. `width`, `height` (visible lines) and
+/// `pitch` (bytes per line) are written when not null. Returns null without a ROM.
+///
+/// # Safety
+/// `emulator` must be a valid handle, the other pointers null or writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_framebuffer(
+    emulator: *mut RSnesEmulator,
+    width: *mut c_uint,
+    height: *mut c_uint,
+    pitch: *mut usize,
+) -> *const u8 {
+    let Ok(system) = (unsafe { system(emulator) }) else {
+        return std::ptr::null();
+    };
+    let (framebuffer, lines) = system.picture();
+    unsafe {
+        write_out(width, framebuffer.width() as c_uint);
+        write_out(height, lines as c_uint);
+        write_out(pitch, framebuffer.pitch());
+    }
+    framebuffer.as_ptr()
+}
+
+/// Audio of the last frame, as interleaved left/right samples at [`rsnes_sample_rate`].
+/// The number of stereo frames is written to `frames` when not null. Returns null without
+/// a ROM.
+///
+/// # Safety
+/// `emulator` must be a valid handle, `frames` null or writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_audio(
+    emulator: *mut RSnesEmulator,
+    frames: *mut usize,
+) -> *const i16 {
+    let Ok(system) = (unsafe { system(emulator) }) else {
+        return std::ptr::null();
+    };
+    unsafe { write_out(frames, system.audio.len() / 2) };
+    system.audio.as_ptr()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn rsnes_sample_rate() -> c_uint {
+    SAMPLE_RATE as c_uint
+}
+
+/// Frames per second of the loaded ROM (about 60.1 for NTSC, 50.0 for PAL), 0 without a ROM.
+///
+/// # Safety
+/// `emulator` must be a valid handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_frame_rate(emulator: *mut RSnesEmulator) -> f64 {
+    unsafe { system(emulator) }.map_or(0.0, |system| system.video_standard().frame_rate())
+}
+
+/// Sets the buttons held on the gamepad of `port` (0 or 1), as `RSNES_BUTTON_*` flags.
+///
+/// # Safety
+/// `emulator` must be a valid handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_set_buttons(
+    emulator: *mut RSnesEmulator,
+    port: c_uint,
+    buttons: u16,
+) -> c_int {
+    if port > 1 {
+        return RSNES_ERROR_INVALID_PORT;
+    }
+    match unsafe { system(emulator) } {
+        Ok(system) => {
+            system.set_buttons(port as usize, buttons);
+            RSNES_OK
+        }
+        Err(err) => err,
+    }
+}
+
+/// Size of the buffer needed by [`rsnes_save_state`], the same until another ROM is loaded,
+/// 0 without a ROM. It is measured when the ROM is loaded, with room for the few bytes a
+/// coprocessor in the middle of a command adds to later states.
+///
+/// # Safety
+/// `emulator` must be a valid handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_state_size(emulator: *mut RSnesEmulator) -> usize {
+    unsafe { emulator.as_ref() }.map_or(0, |emulator| emulator.state_size)
+}
+
+/// Writes the emulator state to `buffer`, the rest of which is filled with zeros. Fails
+/// with [`RSNES_ERROR_BUFFER_TOO_SMALL`] when the state is larger than `size`, see
+/// [`rsnes_state_size`].
+///
+/// # Safety
+/// `emulator` must be a valid handle and `buffer` point to `size` writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_save_state(
+    emulator: *mut RSnesEmulator,
+    buffer: *mut u8,
+    size: usize,
+) -> c_int {
+    let system = match unsafe { system(emulator) } {
+        Ok(system) => system,
+        Err(err) => return err,
+    };
+    if buffer.is_null() {
+        return RSNES_ERROR_NULL_POINTER;
+    }
+    let state = system.save_state().to_bytes();
+    if state.len() > size {
+        return RSNES_ERROR_BUFFER_TOO_SMALL;
+    }
+    let buffer = unsafe { slice::from_raw_parts_mut(buffer, size) };
+    buffer[..state.len()].copy_from_slice(&state);
+    buffer[state.len()..].fill(0);
+    RSNES_OK
+}
+
+/// Restores a state written by [`rsnes_save_state`] on the same ROM. On
+/// [`RSNES_ERROR_INVALID_STATE`], the console may be partly restored and should be reset
+/// or loaded with another state.
+///
+/// # Safety
+/// `emulator` must be a valid handle and `buffer` point to `size` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rsnes_load_state(
+    emulator: *mut RSnesEmulator,
+    buffer: *const u8,
+    size: usize,
+) -> c_int {
+    let system = match unsafe { system(emulator) } {
+        Ok(system) => system,
+        Err(err) => return err,
+    };
+    if buffer.is_null() {
+        return RSNES_ERROR_NULL_POINTER;
+    }
+    let buffer = unsafe { slice::from_raw_parts(buffer, size) };
+    match SaveState::from_bytes(buffer).and_then(|state| system.load_state(&state)) {
+        Ok(()) => RSNES_OK,
+        Err(_) => RSNES_ERROR_INVALID_STATE,
+    }
+}
+
+/// Loaded system of `emulator`, or the error code to return.
+///
+/// # Safety
+/// `emulator` must be null or a valid handle.
+unsafe fn system<'a>(emulator: *mut RSnesEmulator) -> Result<&'a mut System, c_int> {
+    let emulator = unsafe { emulator.as_mut() }.ok_or(RSNES_ERROR_NULL_POINTER)?;
+    emulator.system.as_mut().ok_or(RSNES_ERROR_NO_ROM)
+}
+
+/// # Safety
+/// `out` must be null or writable.
+unsafe fn write_out<T>(out: *mut T, value: T) {
+    if let Some(out) = unsafe { out.as_mut() } {
+        *out = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bus::joypad::gamepad;
+    use bus::rom::test_rom::*;
+    use std::ptr;
+
+    fn emulator_with_rom() -> *mut RSnesEmulator {
+        let emulator = rsnes_create();
+        let rom = create_valid_lorom(0x20000);
+        assert_eq!(
+            unsafe { rsnes_load_rom(emulator, rom.as_ptr(), rom.len()) },
+            RSNES_OK
+        );
+        emulator
+    }
+
+    #[test]
+    fn test_null_handle() {
+        unsafe {
+            assert_eq!(rsnes_run_frame(ptr::null_mut()), RSNES_ERROR_NULL_POINTER);
+            assert_eq!(rsnes_skip_frame(ptr::null_mut()), RSNES_ERROR_NULL_POINTER);
+            assert!(
+                rsnes_framebuffer(
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut()
+                )
+                .is_null()
+            );
+            rsnes_destroy(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_without_rom() {
+        let emulator = rsnes_create();
+        unsafe {
+            assert_eq!(rsnes_run_frame(emulator), RSNES_ERROR_NO_ROM);
+            assert_eq!(rsnes_skip_frame(emulator), RSNES_ERROR_NO_ROM);
+            assert!(rsnes_audio(emulator, ptr::null_mut()).is_null());
+            assert_eq!(rsnes_frame_rate(emulator), 0.0);
+            rsnes_destroy(emulator);
+        }
+    }
+
+    #[test]
+    fn test_invalid_rom() {
+        let emulator = rsnes_create();
+        let data = [0u8; 16];
+        unsafe {
+            assert_eq!(
+                rsnes_load_rom(emulator, data.as_ptr(), data.len()),
+                RSNES_ERROR_INVALID_ROM
+            );
+            assert_eq!(
+                rsnes_load_rom(emulator, ptr::null(), 0),
+                RSNES_ERROR_NULL_POINTER
+            );
+            rsnes_destroy(emulator);
+        }
+    }
+
+    #[test]
+    fn test_framebuffer() {
+        let emulator = emulator_with_rom();
+        let (mut width, mut height, mut pitch) = (0, 0, 0);
+        unsafe {
+            let pixels = rsnes_framebuffer(emulator, &mut width, &mut height, &mut pitch);
+            assert!(!pixels.is_null());
+            assert_eq!((width, height, pitch), (256, 224, 256 * 4));
+            assert!((rsnes_frame_rate(emulator) - 60.1).abs() < 0.01);
+            rsnes_destroy(emulator);
+        }
+    }
+
+    #[test]
+    fn test_set_buttons() {
+        let emulator = emulator_with_rom();
+        unsafe {
+            assert_eq!(rsnes_set_buttons(emulator, 1, gamepad::START), RSNES_OK);
+            assert_eq!(
+                rsnes_set_buttons(emulator, 2, gamepad::START),
+                RSNES_ERROR_INVALID_PORT
+            );
+            let joypads = &mut (*emulator).system.as_mut().unwrap().rsnes.bus.io.joypads;
+            assert_eq!(joypads.auto_read(0xFF)[1], gamepad::START);
+            rsnes_destroy(emulator);
+        }
+    }
+
+    #[test]
+    fn test_save_state_round_trip() {
+        let emulator = emulator_with_rom();
+        unsafe {
+            let size = rsnes_state_size(emulator);
+            assert!(size > STATE_SIZE_MARGIN);
+            (*emulator).system.as_mut().unwrap().rsnes.bus.wram.data[0] = 0x55;
+            assert_eq!(rsnes_state_size(emulator), size);
+
+            let mut buffer = vec![0xFF; size];
+            assert_eq!(
+                rsnes_save_state(emulator, buffer.as_mut_ptr(), 16),
+                RSNES_ERROR_BUFFER_TOO_SMALL
+            );
+            assert_eq!(
+                rsnes_save_state(emulator, buffer.as_mut_ptr(), buffer.len()),
+                RSNES_OK
+            );
+            assert!(buffer[size - STATE_SIZE_MARGIN..].iter().all(|&byte| byte == 0));
+
+            let system = (*emulator).system.as_mut().unwrap();
+            let wram = system.rsnes.bus.wram.data[..16].to_vec();
+            system.rsnes.bus.wram.data[..16].fill(0x55);
+            assert_eq!(
+                rsnes_load_state(emulator, buffer.as_ptr(), buffer.len()),
+                RSNES_OK
+            );
+            let system = (*emulator).system.as_mut().unwrap();
+            assert_eq!(system.rsnes.bus.wram.data[..16], wram);
+            rsnes_destroy(emulator);
+        }
+    }
+
+    #[test]
+    fn test_invalid_states() {
+        let emulator = emulator_with_rom();
+        let buffer = [0u8; 16];
+        unsafe {
+            assert_eq!(
+                rsnes_load_state(emulator, buffer.as_ptr(), buffer.len()),
+                RSNES_ERROR_INVALID_STATE
+            );
+            assert_eq!(
+                rsnes_load_state(emulator, ptr::null(), 0),
+                RSNES_ERROR_NULL_POINTER
+            );
+            assert_eq!(
+                rsnes_save_state(emulator, ptr::null_mut(), 0),
+                RSNES_ERROR_NULL_POINTER
+            );
+            assert_eq!(rsnes_state_size(ptr::null_mut()), 0);
+            rsnes_destroy(emulator);
+        }
+    }
+}
@@ -1,2 +1,13 @@
+//! Types shared by every component. The crate is `no_std` (with `alloc`) when the default
+//! `std` feature is disabled, which only leaves out file loading.
+
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+extern crate alloc;
+
+pub mod power_on;
+pub mod save_state;
 pub mod snes_address;
+pub mod symbols;
 pub mod u16_split;
+pub mod video_standard;
@@ -0,0 +1,102 @@
+/// Contents of WRAM and VRAM at power on.
+///
+/// The hardware leaves them uninitialized: what they hold depends on the console and how
+/// long it was off. A few games read memory before writing it and behave differently
+/// depending on what they find, so the pattern can be picked to reproduce a given console
+/// or to shake out such reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemoryInit {
+    /// Every byte cleared
+    #[default]
+    Zero,
+    /// Bytes alternating between 0x55 and 0xAA, as commonly found on real consoles
+    Stripes,
+    /// Pseudo-random bytes, identical for the same seed
+    Random(u64),
+}
+
+impl MemoryInit {
+    /// Fills `memory` with the pattern.
+    pub fn fill(&self, memory: &mut [u8]) {
+        for (byte, value) in memory.iter_mut().zip(self.bytes()) {
+            *byte = value;
+        }
+    }
+
+    /// Fills a memory of 16-bit words with the pattern, bytes in little endian order.
+    pub fn fill_words(&self, memory: &mut [u16]) {
+        let mut bytes = self.bytes();
+        for word in memory {
+            let low = bytes.next().unwrap_or(0);
+            let high = bytes.next().unwrap_or(0);
+            *word = u16::from_le_bytes([low, high]);
+        }
+    }
+
+    /// Endless byte sequence of the pattern
+    fn bytes(self) -> impl Iterator<Item = u8> {
+        let mut state = match self {
+            MemoryInit::Random(seed) => seed,
+            _ => 0,
+        };
+        core::iter::repeat_with(move || match self {
+            MemoryInit::Zero => [0; 8],
+            MemoryInit::Stripes => [0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA],
+            MemoryInit::Random(_) => splitmix64(&mut state).to_le_bytes(),
+        })
+        .flatten()
+    }
+}
+
+/// SplitMix64 generator: small, with good enough statistics for filling memory.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero() {
+        let mut memory = [0xFF; 16];
+        MemoryInit::Zero.fill(&mut memory);
+        assert_eq!(memory, [0; 16]);
+    }
+
+    #[test]
+    fn test_stripes() {
+        let mut memory = [0; 4];
+        MemoryInit::Stripes.fill(&mut memory);
+        assert_eq!(memory, [0x55, 0xAA, 0x55, 0xAA]);
+
+        let mut words = [0; 2];
+        MemoryInit::Stripes.fill_words(&mut words);
+        assert_eq!(words, [0xAA55, 0xAA55]);
+    }
+
+    #[test]
+    fn test_random_depends_on_seed() {
+        let fill = |seed| {
+            let mut memory = [0; 64];
+            MemoryInit::Random(seed).fill(&mut memory);
+            memory
+        };
+
+        assert_eq!(fill(1), fill(1));
+        assert_ne!(fill(1), fill(2));
+        // Not a constant pattern
+        assert!(fill(1).iter().any(|&byte| byte != fill(1)[0]));
+    }
+
+    #[test]
+    fn test_random_words_vary() {
+        let mut words = [0; 8];
+        MemoryInit::Random(7).fill_words(&mut words);
+        assert!(words.iter().any(|&word| word != words[0]));
+    }
+}
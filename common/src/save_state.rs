@@ -0,0 +1,437 @@
+//! Save state format: a header followed by one section per subsystem, each versioned on its
+//! own. A subsystem changing the layout of its state bumps its section version and adds a
+//! migration from the previous one, so that states saved by older releases keep loading.
+//!
+//! ```text
+//! "RSNS"                     magic
+//! u16                        format version of the container
+//! per section:
+//!     [u8; 4]                tag of the subsystem
+//!     u16                    version of the section
+//!     u32                    length of the data
+//!     [u8; length]           data
+//! ```
+//!
+//! Integers are little endian. Zero bytes after the last section are padding, for frontends
+//! which keep states in buffers of a fixed size. Each component implements [`Stateful`] for its own state,
+//! and the emulator gathers the sections of the whole console.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Identifies the section of a subsystem, e.g. `*b"WRAM"`
+pub type Tag = [u8; 4];
+
+/// Converts the data of a section from one version to the next.
+pub type Migration = fn(&[u8]) -> Result<Vec<u8>, StateError>;
+
+const MAGIC: &[u8; 4] = b"RSNS";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    /// The data does not start with the save state magic
+    NotAState,
+    /// Container saved by a newer release
+    UnsupportedFormat(u16),
+    /// Section saved by a newer release
+    UnsupportedVersion { tag: Tag, version: u16 },
+    /// No section for a subsystem being loaded
+    MissingSection(Tag),
+    /// The container, or the section data of `tag`, is cut short or has unexpected values
+    Corrupt(Option<Tag>),
+}
+
+impl core::error::Error for StateError {}
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tag = |tag: &Tag| String::from_utf8_lossy(tag).into_owned();
+        match self {
+            StateError::NotAState => write!(f, "Not a save state."),
+            StateError::UnsupportedFormat(version) => {
+                write!(f, "Save state format {version} is newer than supported.")
+            }
+            StateError::UnsupportedVersion { tag: t, version } => write!(
+                f,
+                "Save state section {} version {version} is newer than supported.",
+                tag(t)
+            ),
+            StateError::MissingSection(t) => {
+                write!(f, "Save state section {} is missing.", tag(t))
+            }
+            StateError::Corrupt(None) => write!(f, "Corrupt save state."),
+            StateError::Corrupt(Some(t)) => {
+                write!(f, "Corrupt save state section {}.", tag(t))
+            }
+        }
+    }
+}
+
+/// State of a subsystem, saved in its own section.
+pub trait Stateful {
+    const TAG: Tag;
+    /// Version of the section written by this release
+    const VERSION: u16;
+    /// Migration `i` converts data of version `i + 1` to version `i + 2`: there is one per
+    /// version before [`Self::VERSION`].
+    const MIGRATIONS: &'static [Migration] = &[];
+
+    fn save(&self, writer: &mut StateWriter);
+    fn load(&mut self, reader: &mut StateReader) -> Result<(), StateError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Section {
+    tag: Tag,
+    version: u16,
+    data: Vec<u8>,
+}
+
+/// Sections of a save state, filled by saving each subsystem.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveState {
+    sections: Vec<Section>,
+}
+
+impl SaveState {
+    /// Version of the container written by this release
+    pub const FORMAT_VERSION: u16 = 1;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the section of `subsystem`, replacing a previous one.
+    pub fn save<S: Stateful>(&mut self, subsystem: &S) {
+        let mut writer = StateWriter::default();
+        subsystem.save(&mut writer);
+        self.sections.retain(|section| section.tag != S::TAG);
+        self.sections.push(Section {
+            tag: S::TAG,
+            version: S::VERSION,
+            data: writer.data,
+        });
+    }
+
+    /// Restores `subsystem` from its section, migrating it first if it was saved by an
+    /// older release.
+    pub fn load<S: Stateful>(&self, subsystem: &mut S) -> Result<(), StateError> {
+        debug_assert_eq!(S::MIGRATIONS.len() + 1, S::VERSION as usize);
+        let section = self
+            .sections
+            .iter()
+            .find(|section| section.tag == S::TAG)
+            .ok_or(StateError::MissingSection(S::TAG))?;
+        if section.version == 0 || section.version > S::VERSION {
+            return Err(StateError::UnsupportedVersion {
+                tag: S::TAG,
+                version: section.version,
+            });
+        }
+
+        let mut data = section.data.clone();
+        for migration in &S::MIGRATIONS[section.version as usize - 1..] {
+            data = migration(&data)?;
+        }
+        let mut reader = StateReader::new(S::TAG, &data);
+        subsystem.load(&mut reader)?;
+        reader.finish()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(Self::FORMAT_VERSION.to_le_bytes());
+        for section in &self.sections {
+            bytes.extend(section.tag);
+            bytes.extend(section.version.to_le_bytes());
+            bytes.extend((section.data.len() as u32).to_le_bytes());
+            bytes.extend(&section.data);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StateError> {
+        let data = bytes.strip_prefix(MAGIC).ok_or(StateError::NotAState)?;
+        let mut reader = StateReader {
+            tag: None,
+            data,
+            pos: 0,
+        };
+        let format = reader.u16()?;
+        if format > Self::FORMAT_VERSION {
+            return Err(StateError::UnsupportedFormat(format));
+        }
+
+        let mut sections = Vec::new();
+        while !reader.data[reader.pos..].iter().all(|&byte| byte == 0) {
+            let tag = reader.array()?;
+            let version = reader.u16()?;
+            let len = reader.u32()? as usize;
+            let data = reader.bytes(len)?.to_vec();
+            sections.push(Section { tag, version, data });
+        }
+        Ok(Self { sections })
+    }
+}
+
+/// Serializes the state of a subsystem into its section.
+#[derive(Debug, Default)]
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+}
+
+/// Reads back what a [`StateWriter`] wrote, in the same order.
+pub struct StateReader<'a> {
+    /// Section read, none for the container
+    tag: Option<Tag>,
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(tag: Tag, data: &'a [u8]) -> Self {
+        Self {
+            tag: Some(tag),
+            data,
+            pos: 0,
+        }
+    }
+
+    /// Error for data that cannot be the state of the subsystem
+    pub fn corrupt(&self) -> StateError {
+        StateError::Corrupt(self.tag)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if len > self.remaining() {
+            return Err(self.corrupt());
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(self.corrupt()),
+        }
+    }
+
+    pub fn u16(&mut self) -> Result<u16, StateError> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, StateError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    pub fn u64(&mut self) -> Result<u64, StateError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    /// Checks that the whole section was read.
+    pub fn finish(self) -> Result<(), StateError> {
+        match self.remaining() {
+            0 => Ok(()),
+            _ => Err(self.corrupt()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test subsystem whose layout changed twice: version 1 saved a 16-bit counter, version
+    /// 2 widened it to 32 bits, version 3 added a flag.
+    #[derive(Debug, Default, PartialEq)]
+    struct Counter {
+        count: u32,
+        paused: bool,
+    }
+
+    impl Stateful for Counter {
+        const TAG: Tag = *b"TEST";
+        const VERSION: u16 = 3;
+        const MIGRATIONS: &'static [Migration] = &[
+            |data| {
+                let count = u16::from_le_bytes(data.try_into().map_err(|_| corrupt())?);
+                Ok((count as u32).to_le_bytes().to_vec())
+            },
+            |data| Ok([data, &[0]].concat()),
+        ];
+
+        fn save(&self, writer: &mut StateWriter) {
+            writer.u32(self.count);
+            writer.bool(self.paused);
+        }
+
+        fn load(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+            self.count = reader.u32()?;
+            self.paused = reader.bool()?;
+            Ok(())
+        }
+    }
+
+    /// Test subsystem with an empty section
+    struct Other;
+
+    impl Stateful for Other {
+        const TAG: Tag = *b"OTHR";
+        const VERSION: u16 = 1;
+
+        fn save(&self, _writer: &mut StateWriter) {}
+
+        fn load(&mut self, _reader: &mut StateReader) -> Result<(), StateError> {
+            Ok(())
+        }
+    }
+
+    fn corrupt() -> StateError {
+        StateError::Corrupt(Some(Counter::TAG))
+    }
+
+    /// Container holding one section of `Counter`
+    fn counter_state(version: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"RSNS\x01\x00TEST".to_vec();
+        bytes.extend(version.to_le_bytes());
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    fn load_counter(bytes: &[u8]) -> Result<Counter, StateError> {
+        let mut counter = Counter::default();
+        SaveState::from_bytes(bytes)?.load(&mut counter)?;
+        Ok(counter)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let counter = Counter {
+            count: 0x12345,
+            paused: true,
+        };
+        let mut state = SaveState::new();
+        state.save(&counter);
+
+        let bytes = state.to_bytes();
+        assert_eq!(bytes, counter_state(3, &[0x45, 0x23, 0x01, 0x00, 0x01]));
+        assert_eq!(load_counter(&bytes), Ok(counter));
+    }
+
+    #[test]
+    fn test_zero_padding_ignored() {
+        let mut bytes = counter_state(3, &[0x45, 0x23, 0x01, 0x00, 0x01]);
+        bytes.resize(bytes.len() + 13, 0);
+        assert_eq!(load_counter(&bytes).map(|counter| counter.count), Ok(0x12345));
+        bytes.push(1);
+        assert_eq!(load_counter(&bytes), Err(StateError::Corrupt(None)));
+    }
+
+    #[test]
+    fn test_older_versions_migrated() {
+        let migrated = |version, data: &[u8]| load_counter(&counter_state(version, data));
+        let expected = || {
+            Ok(Counter {
+                count: 0x1234,
+                paused: false,
+            })
+        };
+
+        assert_eq!(migrated(1, &[0x34, 0x12]), expected());
+        assert_eq!(migrated(2, &[0x34, 0x12, 0x00, 0x00]), expected());
+        assert_eq!(migrated(1, &[0x34]), Err(corrupt()));
+    }
+
+    #[test]
+    fn test_newer_versions_rejected() {
+        assert_eq!(
+            load_counter(&counter_state(4, &[])),
+            Err(StateError::UnsupportedVersion {
+                tag: *b"TEST",
+                version: 4
+            })
+        );
+        assert_eq!(
+            load_counter(b"RSNS\x02\x00"),
+            Err(StateError::UnsupportedFormat(2))
+        );
+    }
+
+    #[test]
+    fn test_invalid_states() {
+        assert_eq!(load_counter(b"RIFF\x01\x00"), Err(StateError::NotAState));
+        assert_eq!(
+            load_counter(b"RSNS\x01\x00"),
+            Err(StateError::MissingSection(*b"TEST"))
+        );
+        // Section longer than the data left
+        let mut truncated = counter_state(3, &[0; 5]);
+        truncated.pop();
+        assert_eq!(load_counter(&truncated), Err(StateError::Corrupt(None)));
+        // Data left over, invalid boolean
+        assert_eq!(load_counter(&counter_state(3, &[0; 6])), Err(corrupt()));
+        assert_eq!(
+            load_counter(&counter_state(3, &[0, 0, 0, 0, 2])),
+            Err(corrupt())
+        );
+    }
+
+    #[test]
+    fn test_sections_replaced_and_found_by_tag() {
+        let mut state = SaveState::new();
+        state.save(&Counter::default());
+        state.save(&Other);
+        state.save(&Counter {
+            count: 7,
+            paused: false,
+        });
+
+        let state = SaveState::from_bytes(&state.to_bytes()).unwrap();
+        let mut counter = Counter::default();
+        state.load(&mut counter).unwrap();
+        assert_eq!(counter.count, 7);
+        assert_eq!(state.sections.len(), 2);
+    }
+}
@@ -1,4 +1,4 @@
-use std::convert::From;
+use core::convert::From;
 
 /// Common struct used to represent memory addresses in the global
 /// SNES adddress space.
@@ -30,10 +30,10 @@ impl From<usize> for SnesAddress {
     }
 }
 
-impl std::fmt::Debug for SnesAddress {
+impl core::fmt::Debug for SnesAddress {
     #[cfg(not(tarpaulin_include))]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::write!(f, "SnesAddress {{ ${:x}:{:x} }}", self.bank, self.addr)
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::write!(f, "SnesAddress {{ ${:x}:{:x} }}", self.bank, self.addr)
     }
 }
 
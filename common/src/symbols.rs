@@ -0,0 +1,298 @@
+//! Labels from assembler symbol files, to show `main+3` instead of `$00:8003` in
+//! disassembly and debugger views.
+//!
+//! Two formats are understood:
+//! - WLA-DX `.sym` files: `BB:AAAA name` lines in the `[labels]` section, other sections
+//!   (definitions, breakpoints...) being ignored;
+//! - bass symbol files: `BBAAAA name` lines with a 24-bit address.
+//!
+//! Comments start with `;` in both formats.
+
+use crate::snes_address::SnesAddress;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum SymbolError {
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// Malformed line, numbered from 1
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            SymbolError::Io(err) => write!(f, "Could not read symbol file: {}", err),
+            SymbolError::Parse { line, message } => {
+                write!(f, "Invalid symbol file, line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl core::error::Error for SymbolError {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for SymbolError {
+    fn from(err: std::io::Error) -> Self {
+        SymbolError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolFormat {
+    WlaDx,
+    Bass,
+}
+
+impl SymbolFormat {
+    /// WLA-DX files have sections and `BB:AAAA` addresses, bass files neither.
+    pub fn detect(text: &str) -> SymbolFormat {
+        let wla = text.lines().map(strip_comment).any(|line| {
+            line.starts_with('[')
+                || line
+                    .split_whitespace()
+                    .next()
+                    .is_some_and(|a| a.contains(':'))
+        });
+        if wla {
+            SymbolFormat::WlaDx
+        } else {
+            SymbolFormat::Bass
+        }
+    }
+}
+
+/// Labels keyed by bank:address; several labels may share an address.
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    labels: BTreeMap<usize, Vec<String>>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(feature = "std")]
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, SymbolError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses a symbol file, detecting its format.
+    pub fn parse(text: &str) -> Result<Self, SymbolError> {
+        Self::parse_format(text, SymbolFormat::detect(text))
+    }
+
+    pub fn parse_format(text: &str, format: SymbolFormat) -> Result<Self, SymbolError> {
+        let mut symbols = Self::new();
+        // bass files have no sections: everything is a label
+        let mut in_labels = format == SymbolFormat::Bass;
+
+        for (index, line) in text.lines().enumerate() {
+            let line = strip_comment(line);
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[') {
+                in_labels = section.trim_end_matches(']').eq_ignore_ascii_case("labels");
+                continue;
+            }
+            if !in_labels {
+                continue;
+            }
+
+            let parse_error = |message: &str| SymbolError::Parse {
+                line: index + 1,
+                message: message.to_string(),
+            };
+            let (address, name) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| parse_error("expected an address and a label"))?;
+            let address = match format {
+                SymbolFormat::WlaDx => parse_wla_address(address),
+                SymbolFormat::Bass => parse_hex(address, 6).map(|a| SnesAddress::from(a as usize)),
+            }
+            .ok_or_else(|| parse_error(&format!("invalid address '{}'", address)))?;
+
+            symbols.add(address, name.trim());
+        }
+        Ok(symbols)
+    }
+
+    pub fn add(&mut self, addr: SnesAddress, name: &str) {
+        self.labels
+            .entry(usize::from(addr))
+            .or_default()
+            .push(name.to_string());
+    }
+
+    /// Number of labels
+    pub fn len(&self) -> usize {
+        self.labels.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// First label defined at `addr`.
+    pub fn label(&self, addr: SnesAddress) -> Option<&str> {
+        self.labels
+            .get(&usize::from(addr))
+            .and_then(|names| names.first())
+            .map(String::as_str)
+    }
+
+    /// Every label defined at `addr`.
+    pub fn labels_at(&self, addr: SnesAddress) -> &[String] {
+        self.labels
+            .get(&usize::from(addr))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn address_of(&self, name: &str) -> Option<SnesAddress> {
+        self.labels
+            .iter()
+            .find(|(_, names)| names.iter().any(|n| n == name))
+            .map(|(&addr, _)| SnesAddress::from(addr))
+    }
+
+    /// Closest label at or before `addr` in the same bank, with the offset from it.
+    pub fn nearest(&self, addr: SnesAddress) -> Option<(&str, u16)> {
+        let bank_start = usize::from(addr) & 0xFF_0000;
+        let (&label_addr, names) = self
+            .labels
+            .range(bank_start..=usize::from(addr))
+            .next_back()?;
+        Some((names[0].as_str(), addr.addr - label_addr as u16))
+    }
+
+    /// `label`, `label+offset`, or `$BB:AAAA` when no label precedes `addr` in its bank.
+    pub fn symbolize(&self, addr: SnesAddress) -> String {
+        match self.nearest(addr) {
+            Some((name, 0)) => name.to_string(),
+            Some((name, offset)) => format!("{}+{}", name, offset),
+            None => format!("${:02X}:{:04X}", addr.bank, addr.addr),
+        }
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split(';').next().unwrap_or_default().trim()
+}
+
+/// `BB:AAAA`, as written by WLA-DX
+fn parse_wla_address(text: &str) -> Option<SnesAddress> {
+    let (bank, addr) = text.split_once(':')?;
+    Some(SnesAddress {
+        bank: parse_hex(bank, 2)? as u8,
+        addr: parse_hex(addr, 4)? as u16,
+    })
+}
+
+/// Hexadecimal number of at most `digits` digits
+fn parse_hex(text: &str, digits: usize) -> Option<u32> {
+    if text.is_empty() || text.len() > digits {
+        return None;
+    }
+    u32::from_str_radix(text, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snes_address::snes_addr;
+
+    const WLA_SYM: &str = "\
+; wla symbolic information file
+[information]
+version 2
+
+[labels]
+00:8000 Reset
+00:8010 Main
+00:8010 _main_loop ; local label
+01:C000 Data
+
+[definitions]
+00000010 _sizeof_Data
+";
+
+    const BASS_SYM: &str = "\
+008000 reset
+008020 nmi
+7e0010 frame_counter
+";
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(SymbolFormat::detect(WLA_SYM), SymbolFormat::WlaDx);
+        assert_eq!(SymbolFormat::detect(BASS_SYM), SymbolFormat::Bass);
+    }
+
+    #[test]
+    fn test_parse_wla() {
+        let symbols = Symbols::parse(WLA_SYM).unwrap();
+        assert_eq!(symbols.len(), 4);
+        assert_eq!(symbols.label(snes_addr!(0x00:0x8000)), Some("Reset"));
+        assert_eq!(
+            symbols.labels_at(snes_addr!(0x00:0x8010)),
+            ["Main", "_main_loop"]
+        );
+        assert_eq!(symbols.address_of("Data"), Some(snes_addr!(0x01:0xC000)));
+        // Definitions are not labels
+        assert_eq!(symbols.address_of("_sizeof_Data"), None);
+    }
+
+    #[test]
+    fn test_parse_bass() {
+        let symbols = Symbols::parse(BASS_SYM).unwrap();
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols.label(snes_addr!(0x00:0x8020)), Some("nmi"));
+        assert_eq!(
+            symbols.label(snes_addr!(0x7E:0x0010)),
+            Some("frame_counter")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = Symbols::parse("[labels]\n00:8000 Reset\nzz:8000 Bad\n").unwrap_err();
+        assert!(matches!(err, SymbolError::Parse { line: 3, .. }));
+        assert_eq!(
+            err.to_string(),
+            "Invalid symbol file, line 3: invalid address 'zz:8000'"
+        );
+
+        let err = Symbols::parse_format("008000\n", SymbolFormat::Bass).unwrap_err();
+        assert!(matches!(err, SymbolError::Parse { line: 1, .. }));
+    }
+
+    #[test]
+    fn test_symbolize() {
+        let symbols = Symbols::parse(WLA_SYM).unwrap();
+        assert_eq!(symbols.symbolize(snes_addr!(0x00:0x8000)), "Reset");
+        assert_eq!(symbols.symbolize(snes_addr!(0x00:0x8013)), "Main+3");
+        // Labels from another bank are not used
+        assert_eq!(symbols.symbolize(snes_addr!(0x01:0x8000)), "$01:8000");
+        assert_eq!(symbols.nearest(snes_addr!(0x01:0xC002)), Some(("Data", 2)));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_load_missing_file() {
+        let err = Symbols::load_from_file("/nonexistent/game.sym").unwrap_err();
+        assert!(matches!(err, SymbolError::Io(_)));
+    }
+}
@@ -0,0 +1,143 @@
+use core::fmt;
+
+/// Represents the video standard used by a SNES ROM.
+///
+/// Mainly NTSC or PAL, with an "Other" option for unknown/unsupported regions.
+/// It determines the frame timing of the console: number of scanlines per
+/// frame and master clock frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VideoStandard {
+    /// 262 scanlines per frame, ~60 Hz
+    #[default]
+    NTSC,
+    /// 312 scanlines per frame, ~50 Hz
+    PAL,
+    /// Unknown standard, timed like NTSC
+    Other,
+}
+
+impl VideoStandard {
+    /// Number of master cycles in a scanline, identical for both standards
+    pub const MASTER_CYCLES_PER_SCANLINE: u64 = 1364;
+
+    /// Number of scanlines (visible and V-blank) in a frame
+    pub fn scanlines_per_frame(&self) -> u16 {
+        match self {
+            VideoStandard::PAL => 312,
+            VideoStandard::NTSC | VideoStandard::Other => 262,
+        }
+    }
+
+    /// Frequency of the master clock driving every component, in Hz
+    pub fn master_clock_hz(&self) -> u64 {
+        match self {
+            VideoStandard::PAL => 21_281_370,
+            VideoStandard::NTSC | VideoStandard::Other => 21_477_272,
+        }
+    }
+
+    /// Number of master cycles in a full frame
+    pub fn master_cycles_per_frame(&self) -> u64 {
+        self.scanlines_per_frame() as u64 * Self::MASTER_CYCLES_PER_SCANLINE
+    }
+
+    /// Frame rate in Hz (about 60.1 for NTSC and 50.0 for PAL)
+    pub fn frame_rate(&self) -> f64 {
+        self.master_clock_hz() as f64 / self.master_cycles_per_frame() as f64
+    }
+}
+
+impl fmt::Display for VideoStandard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VideoStandard::NTSC => write!(f, "NTSC"),
+            VideoStandard::PAL => write!(f, "PAL"),
+            VideoStandard::Other => write!(f, "Other"),
+        }
+    }
+}
+
+/// Video standard emulated for a cartridge: the one detected from its header, unless the
+/// user forces another one to play imports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionSelection {
+    /// Standard derived from the header country
+    pub detected: VideoStandard,
+    /// Standard driving the console timing and PPU
+    pub effective: VideoStandard,
+}
+
+impl RegionSelection {
+    pub fn new(detected: VideoStandard, forced: Option<VideoStandard>) -> Self {
+        Self {
+            detected,
+            effective: forced.unwrap_or(detected),
+        }
+    }
+
+    /// Whether the console runs at another frame rate than the cartridge expects, which
+    /// the region lockout of a real console would have refused.
+    pub fn is_mismatch(&self) -> bool {
+        self.detected.scanlines_per_frame() != self.effective.scanlines_per_frame()
+    }
+}
+
+impl fmt::Display for RegionSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.detected == self.effective {
+            write!(f, "{}", self.effective)
+        } else {
+            write!(f, "{} (cartridge: {})", self.effective, self.detected)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scanlines_per_frame() {
+        assert_eq!(VideoStandard::NTSC.scanlines_per_frame(), 262);
+        assert_eq!(VideoStandard::PAL.scanlines_per_frame(), 312);
+        assert_eq!(VideoStandard::Other.scanlines_per_frame(), 262);
+    }
+
+    #[test]
+    fn test_master_cycles_per_frame() {
+        assert_eq!(VideoStandard::NTSC.master_cycles_per_frame(), 262 * 1364);
+        assert_eq!(VideoStandard::PAL.master_cycles_per_frame(), 312 * 1364);
+    }
+
+    #[test]
+    fn test_frame_rate() {
+        assert!((VideoStandard::NTSC.frame_rate() - 60.1).abs() < 0.05);
+        assert!((VideoStandard::PAL.frame_rate() - 50.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_default_is_ntsc() {
+        assert_eq!(VideoStandard::default(), VideoStandard::NTSC);
+    }
+
+    #[test]
+    fn test_region_selection_detected() {
+        let region = RegionSelection::new(VideoStandard::PAL, None);
+        assert_eq!(region.effective, VideoStandard::PAL);
+        assert!(!region.is_mismatch());
+        assert_eq!(region.to_string(), "PAL");
+    }
+
+    #[test]
+    fn test_region_selection_forced() {
+        let region = RegionSelection::new(VideoStandard::NTSC, Some(VideoStandard::PAL));
+        assert_eq!(region.effective, VideoStandard::PAL);
+        assert!(region.is_mismatch());
+        assert_eq!(region.to_string(), "PAL (cartridge: NTSC)");
+
+        // Unknown regions are timed like NTSC
+        assert!(
+            !RegionSelection::new(VideoStandard::Other, Some(VideoStandard::NTSC)).is_mismatch()
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! CPU stepping benchmarks
+//!
+//! Runs small programs out of a flat 16 MiB memory to measure the cost of
+//! `CPU::cycle`, opcode dispatch included. Compare dispatch strategies with:
+//!
+//! ```text
+//! cargo bench -p cpu
+//! cargo bench -p cpu --features jump-table-dispatch
+//! ```
+
+use std::hint::black_box;
+
+use cpu::cpu::{CPU, CycleResult};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+const PROGRAM_START: u16 = 0x8000;
+
+/// Flat memory with `program` at 00:8000, pointed to by the reset vector.
+fn make_memory(program: &[u8]) -> Vec<u8> {
+    let mut memory = vec![0; 0x100_0000];
+    memory[PROGRAM_START as usize..PROGRAM_START as usize + program.len()].copy_from_slice(program);
+    memory[0xFFFC] = PROGRAM_START as u8;
+    memory[0xFFFD] = (PROGRAM_START >> 8) as u8;
+    memory
+}
+
+fn run_cycles(cpu: &mut CPU, memory: &mut [u8], cycles: usize) {
+    for _ in 0..cycles {
+        let result = cpu.cycle();
+        let addr = usize::from(*cpu.addr_bus());
+
+        match result {
+            CycleResult::Read => cpu.data_bus = memory[addr],
+            CycleResult::Write => memory[addr] = cpu.data_bus,
+            CycleResult::Internal => {}
+        }
+    }
+}
+
+fn bench_program(c: &mut Criterion, name: &str, program: &[u8]) {
+    let mut memory = make_memory(program);
+    let mut cpu = CPU::poweron();
+
+    c.bench_function(name, |b| {
+        b.iter(|| run_cycles(black_box(&mut cpu), &mut memory, 10_000))
+    });
+}
+
+/// Single-cycle instructions: dominated by opcode fetch and dispatch
+fn bench_nop_loop(c: &mut Criterion) {
+    // loop: NOP; INX; INY; DEX; BRA loop
+    bench_program(c, "nop_loop", &[0xEA, 0xE8, 0xC8, 0xCA, 0x80, 0xFA]);
+}
+
+/// Mix of immediate, absolute and branch instructions
+fn bench_mixed_loop(c: &mut Criterion) {
+    // loop: CLC; LDA #$12; ADC #$34; STA $0200; DEX; BNE loop; BRA loop
+    bench_program(
+        c,
+        "mixed_loop",
+        &[
+            0x18, 0xA9, 0x12, 0x69, 0x34, 0x8D, 0x00, 0x02, 0xCA, 0xD0, 0xF5, 0x80, 0xF3,
+        ],
+    );
+}
+
+criterion_group!(benches, bench_nop_loop, bench_mixed_loop);
+criterion_main!(benches);
@@ -4,7 +4,11 @@ use parser::{Cycle, Instr, InstrBody, VarWidth};
 use proc_macro2::{TokenStream, Ident};
 use quote::{format_ident, quote, ToTokens};
 
-fn gen_cycle_functions(name: &Ident, instr_body: InstrBody) -> TokenStream {
+/// Generates the body of each cycle function of an instruction.
+///
+/// `cycle_ref` gives the expression used to refer to the function of the
+/// cycle with the given number (starting at 1).
+fn gen_cycle_bodies(cycle_ref: impl Fn(usize) -> TokenStream, instr_body: &InstrBody) -> Vec<TokenStream> {
     let cycles = &instr_body.cycles;
     let post_instr = &instr_body.post_instr;
 
@@ -12,9 +16,8 @@ fn gen_cycle_functions(name: &Ident, instr_body: InstrBody) -> TokenStream {
         .iter()
         .enumerate()
         .map(|(i, cyc)| {
-            let func_name = format_ident!("{}_cyc{}", name, i + 1);
             let next_func_name: TokenStream = if i != cycles.len() - 1 {
-                format_ident!("{}_cyc{}", name, i + 2).into_token_stream()
+                cycle_ref(i + 2)
             } else {
                 if post_instr.is_empty() {
                     format_ident!("opcode_fetch").into_token_stream()
@@ -44,14 +47,69 @@ fn gen_cycle_functions(name: &Ident, instr_body: InstrBody) -> TokenStream {
                 ),
             };
 
+            quote! {
+                #body
+
+                (#cyc_type, InstrCycle(#next_func_name))
+            }
+        }).collect()
+}
+
+fn gen_cycle_functions(name: &Ident, instr_body: InstrBody) -> TokenStream {
+    let cycle_ref = |n| format_ident!("{}_cyc{}", name, n).into_token_stream();
+
+    gen_cycle_bodies(cycle_ref, &instr_body)
+        .into_iter()
+        .enumerate()
+        .map(|(i, body)| {
+            let func_name = format_ident!("{}_cyc{}", name, i + 1);
+
             quote! {
                 pub(crate) fn #func_name(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
                     #body
+                }
+            }
+        }).collect()
+}
 
-                    (#cyc_type, InstrCycle(#next_func_name))
+/// Generates the cycle functions of a variable-width instruction, with the
+/// operand width as a `const LONG: bool` parameter (`true` for 16 bits).
+///
+/// Cycles whose code is the same for both widths are only generated once,
+/// the others select the code of the right width with `if LONG`, which
+/// is resolved at compile time.
+fn gen_width_generic_functions(name: &Ident, short: InstrBody, long: InstrBody) -> TokenStream {
+    let cycle_ref = |n| {
+        let func_name = format_ident!("{}_cyc{}", name, n);
+        quote!(#func_name::<LONG>)
+    };
+    let short_bodies = gen_cycle_bodies(cycle_ref, &short);
+    let long_bodies = gen_cycle_bodies(cycle_ref, &long);
+
+    (0..short_bodies.len().max(long_bodies.len()))
+        .map(|i| {
+            let func_name = format_ident!("{}_cyc{}", name, i + 1);
+            let body = match (short_bodies.get(i), long_bodies.get(i)) {
+                (Some(short), Some(long)) if short.to_string() == long.to_string() => short.clone(),
+                (short, long) => {
+                    let short = short.cloned().unwrap_or(quote!(unreachable!()));
+                    let long = long.cloned().unwrap_or(quote!(unreachable!()));
+                    quote! {
+                        if LONG {
+                            #long
+                        } else {
+                            #short
+                        }
+                    }
+                }
+            };
+
+            quote! {
+                pub(crate) fn #func_name<const LONG: bool>(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
+                    #body
                 }
             }
-        }).collect::<TokenStream>()
+        }).collect()
 }
 
 /// Function that actually implements all the logic for the proc macro,
@@ -68,8 +126,7 @@ pub(crate) fn cpu_instr2(input: TokenStream, inc_pc: bool) -> TokenStream {
     let cycle_funcs = match body {
         VarWidth::ConstWidth(instr_body) => gen_cycle_functions(&name, instr_body),
         VarWidth::VarWidth{short, long, data} => {
-            let cyc_funcs8 = gen_cycle_functions(&name, short);
-            let cyc_funcs16 = gen_cycle_functions(&name, long);
+            let cyc_funcs = gen_width_generic_functions(&name, short, long);
             let condition = data;
 
             let first_cyc_name = format_ident!("{}_cyc1", name);
@@ -77,23 +134,17 @@ pub(crate) fn cpu_instr2(input: TokenStream, inc_pc: bool) -> TokenStream {
             quote! {
                 pub(crate) fn #first_cyc_name(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
                     if #condition {
-                        self::_16::#first_cyc_name(cpu)
+                        self::width::#first_cyc_name::<true>(cpu)
                     } else {
-                        self::_8::#first_cyc_name(cpu)
+                        self::width::#first_cyc_name::<false>(cpu)
                     }
                 }
 
-                pub(crate) mod _8 {
-                    use crate::instrs::prelude::*;
-                    use super::*;
-
-                    #cyc_funcs8
-                }
-                pub(crate) mod _16 {
+                pub(crate) mod width {
                     use crate::instrs::prelude::*;
                     use super::*;
 
-                    #cyc_funcs16
+                    #cyc_funcs
                 }
             }
         },
@@ -387,40 +438,40 @@ mod test {
 
                     pub(crate) fn varwidth_cyc1(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
                         if !cpu.registers.E && !cpu.registers.P.M {
-                            self::_16::varwidth_cyc1(cpu)
+                            self::width::varwidth_cyc1::<true>(cpu)
                         } else {
-                            self::_8::varwidth_cyc1(cpu)
-                        }
-                    }
-
-                    pub(crate) mod _8 {
-                        use crate::instrs::prelude::*;
-                        use super::*;
-                        pub(crate) fn varwidth_cyc1(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
-                            cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(1u16);
-                            (Read, InstrCycle(|cpu| {
-                                *cpu.internal_data_bus.lo_mut() = cpu.data_bus;
-                                opcode_fetch(cpu)
-                            }))
+                            self::width::varwidth_cyc1::<false>(cpu)
                         }
                     }
 
-                    pub(crate) mod _16 {
+                    pub(crate) mod width {
                         use crate::instrs::prelude::*;
                         use super::*;
-                        pub(crate) fn varwidth_cyc1(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
-                            cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(1u16);
-                            (Read, InstrCycle(varwidth_cyc2))
+                        pub(crate) fn varwidth_cyc1<const LONG: bool>(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
+                            if LONG {
+                                cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(1u16);
+                                (Read, InstrCycle(varwidth_cyc2::<LONG>))
+                            } else {
+                                cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(1u16);
+                                (Read, InstrCycle(|cpu| {
+                                    *cpu.internal_data_bus.lo_mut() = cpu.data_bus;
+                                    opcode_fetch(cpu)
+                                }))
+                            }
                         }
 
-                        pub(crate) fn varwidth_cyc2(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
-                            *cpu.internal_data_bus.lo_mut() = cpu.data_bus;
-                            cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(1);
-
-                            (Read, InstrCycle(|cpu| {
-                                *cpu.internal_data_bus.hi_mut() = cpu.data_bus;
-                                opcode_fetch(cpu)
-                            }))
+                        pub(crate) fn varwidth_cyc2<const LONG: bool>(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
+                            if LONG {
+                                *cpu.internal_data_bus.lo_mut() = cpu.data_bus;
+                                cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(1);
+
+                                (Read, InstrCycle(|cpu| {
+                                    *cpu.internal_data_bus.hi_mut() = cpu.data_bus;
+                                    opcode_fetch(cpu)
+                                }))
+                            } else {
+                                unreachable!()
+                            }
                         }
                     }
                 }
@@ -1,6 +1,6 @@
 use pm2::{Ident, TokenStream, TokenTree};
 use proc_macro2 as pm2;
-use quote::quote;
+use quote::{format_ident, quote};
 use std::ops::AddAssign;
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
@@ -39,6 +39,19 @@ pub(crate) struct ParserState {
 
     /// Size of read/written operands of the instruction
     pub operand_size: OpSize,
+
+    /// Whether the instruction writes to its operand (stores and
+    /// read-modify-write instructions)
+    pub writes: bool,
+
+    /// Whether the instruction pushes or pulls without preserving the high
+    /// byte of S in emulation mode (see note 2 of [`MetaInstruction`]), in
+    /// which case it is set back to 0x01 at the end of the instruction
+    pub native_stack: bool,
+
+    /// Whether the address bus holds a 24-bit data address, whose increments
+    /// and indexing carry into the bank (see note 4 of [`MetaInstruction`])
+    pub long_addr: bool,
 }
 
 #[derive(PartialEq, Eq)]
@@ -60,6 +73,9 @@ impl Default for ParserState {
             addrmode: AddrBusPosition::Opcode, // at instr start, addrbus is on PC
             imm_offset: VarWidth::constw(1), // at instr start, the first imm value is 1 after PC
             operand_size: OpSize::Constant,
+            writes: false,
+            native_stack: false,
+            long_addr: false,
         }
     }
 }
@@ -74,6 +90,59 @@ impl ParserState {
             quote! {}
         }
     }
+
+    /// Code moving the address bus to the second byte of a 16-bit operand
+    fn addr_increment(&self) -> TokenStream {
+        if self.long_addr {
+            quote! {
+                cpu.addr_bus.increment();
+            }
+        } else {
+            quote! {
+                cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(1);
+            }
+        }
+    }
+
+    /// Code to run at the end of the last cycle of the instruction
+    fn end_of_instr(&self, inc: u16) -> TokenStream {
+        let mut ret = self.conditionally_inc_pc(inc);
+        if self.native_stack {
+            ret.extend(quote! {
+                // the stack went past page 1, put it back there in emulation mode
+                if cpu.registers.E {
+                    *cpu.registers.S.hi_mut() = 1;
+                }
+            });
+        }
+        ret
+    }
+}
+
+/// Adds `offset` to the address bus, which points in the direct page.
+///
+/// In emulation mode, when DL is 0, the address wraps within the direct page
+/// (only the low byte is incremented) as it would on a 6502.
+/// Instructions and addressing modes which didn't exist on the 6502 (e.g. PEI,
+/// direct indirect long) don't have this behaviour.
+fn direct_page_add(offset: TokenStream) -> TokenStream {
+    quote! {
+        if cpu.registers.E && *cpu.registers.D.lo() == 0 {
+            *cpu.addr_bus.addr.lo_mut() = cpu.addr_bus.addr.lo().wrapping_add((#offset) as u8);
+        } else {
+            cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(#offset);
+        }
+    }
+}
+
+/// Adds `offset` to a 24-bit data address: the carry goes into the bank
+/// (see note 4 of [`MetaInstruction`])
+fn long_addr_add(offset: TokenStream) -> TokenStream {
+    quote! {
+        cpu.addr_bus = SnesAddress::from(
+            usize::from(cpu.addr_bus).wrapping_add((#offset) as usize)
+        );
+    }
 }
 
 pub struct Binding {
@@ -146,7 +215,19 @@ impl Binding {
 ///    However, a few instructions may increment or decrement S past its
 ///    "forced" 0x01. PullN and PushN meta-instructions allow this behaviour:
 ///    they will always push/pull as in native mode, (without preserving the
-///    high order byte of S in emulation mode)
+///    high order byte of S in emulation mode) while the instruction executes,
+///    and set it back to 0x01 once it is done.
+/// 3. In emulation mode, when the low byte of D is 0, direct page accesses of
+///    the addressing modes inherited from the 6502 wrap within the direct
+///    page: X/Y indexing and the read of the second byte of an indirect
+///    address never carry into the high byte of the address.
+/// 4. Data addresses built from DB or from a 24-bit address (absolute, long
+///    and indirect addressing modes) are linear: the second byte of a 16-bit
+///    operand at $xx:FFFF is at $xx+1:0000, and X/Y indexing carries into the
+///    bank as well. Direct page and stack relative addresses (always in bank
+///    0), the stack and the program counter wrap within their bank instead.
+///    Fetch16Into, Write16 and RMW follow this, using the
+///    `long_addr` field of the parser state set by the addressing modes.
 pub(crate) enum MetaInstruction {
     /// Manually delimit the end of a cycle,
     /// with the CycleResult (cycle type) produced by the token stream
@@ -155,6 +236,11 @@ pub(crate) enum MetaInstruction {
     /// Sets the operand size for variable width instructions
     SetOperandSize(TokenTree),
 
+    /// Marks the instruction as writing to its operand, which must be done
+    /// before setting the addressing mode: indexed addressing modes then
+    /// always spend the extra cycle of cpu doc note 4
+    SetWrite,
+
     /// Spend an internal cycle idling if the tokenstream evaluates to true
     IdleIf(TokenStream),
 
@@ -239,6 +325,11 @@ pub(crate) enum MetaInstruction {
     /// contained in <tokstream>
     Fetch16Into(TokenStream),
 
+    /// Same as Fetch16Into, for an address in the direct page
+    ///
+    /// See note 3 for differences with Fetch16Into
+    Fetch16DirectInto(TokenStream),
+
     /// Fetches the operand of the instruction
     /// (variable width must be set with SetOperandSize)
     FetchOperandInto(TokenStream),
@@ -318,6 +409,17 @@ pub(crate) enum MetaInstruction {
     /// Generates a Push8 or a Push16 depending on operand size
     PushOp(TokenStream),
 
+    /// Read-modify-write of the operand at the current address bus
+    /// (variable width must be set with SetOperandSize)
+    ///
+    /// `meta RMW <algorithm>, <args>...;` reads the operand, calls
+    /// `<algorithm>(&mut operand, <args>..., &mut cpu.registers.P)`
+    /// (the optional args are u16 registers, passed with the operand width),
+    /// then writes the modified operand back, high byte first.
+    /// The modify cycle is an internal cycle in native mode, and a dummy
+    /// write of the unmodified operand in emulation mode (as on a 6502).
+    ReadModifyWrite(TokenStream),
+
     /// Sets the CPU flags N and Z for an 8-bit value
     SetNZ8(TokenStream),
 
@@ -359,6 +461,7 @@ impl MetaInstruction {
             "END_CYCLE" => MetaInstruction::EndCycle(it.by_ref().collect()),
 
             "SET_OP_SIZE" => MetaInstruction::SetOperandSize(it.next().expect("size")),
+            "SET_WRITE" => MetaInstruction::SetWrite,
 
             "IDLE_IF" => MetaInstruction::IdleIf(it.by_ref().collect()),
 
@@ -382,6 +485,7 @@ impl MetaInstruction {
 
             "FETCH8_INTO" => MetaInstruction::Fetch8Into(it.by_ref().collect()),
             "FETCH16_INTO" => MetaInstruction::Fetch16Into(it.by_ref().collect()),
+            "FETCH16_DIRECT_INTO" => MetaInstruction::Fetch16DirectInto(it.by_ref().collect()),
 
             "FETCH_OP_INTO" => MetaInstruction::FetchOperandInto(it.by_ref().collect()),
 
@@ -408,6 +512,8 @@ impl MetaInstruction {
             "PUSHN16" => MetaInstruction::PushN16(it.by_ref().collect()),
             "PUSH_OP" => MetaInstruction::PushOp(it.by_ref().collect()),
 
+            "RMW" => MetaInstruction::ReadModifyWrite(it.by_ref().collect()),
+
             "SET_NZ8" => MetaInstruction::SetNZ8(it.by_ref().collect()),
             "SET_NZ16" => MetaInstruction::SetNZ16(it.by_ref().collect()),
             "SET_NZ_OP" => MetaInstruction::SetNZOperand(it.by_ref().collect()),
@@ -455,6 +561,10 @@ impl MetaInstruction {
                 }
             }
 
+            Self::SetWrite => {
+                pstate.writes = true;
+            }
+
             Self::IdleIf(condition) => {
                 ret += InstrBody::cycles(vec![Cycle::conditional(condition)]);
             }
@@ -476,6 +586,7 @@ impl MetaInstruction {
                     }
                 }
                 pstate.addrmode = AddrBusPosition::Immediate;
+                pstate.long_addr = false;
             }
             Self::SetAddrModeAbsolute => {
                 // start by fetching the address at which we'll be reading/writing
@@ -486,6 +597,7 @@ impl MetaInstruction {
                     cpu.addr_bus.bank = cpu.registers.DB;
                 });
                 pstate.addrmode = AddrBusPosition::Unaligned;
+                pstate.long_addr = true;
             }
             Self::SetAddrModeAbsoluteLong => {
                 ret += Self::Fetch16ImmInto(quote!(cpu.internal_data_bus)).expand(pstate);
@@ -496,30 +608,25 @@ impl MetaInstruction {
                     cpu.addr_bus.bank = cpu.data_bus;
                 };
                 pstate.addrmode = AddrBusPosition::Unaligned;
+                pstate.long_addr = true;
             }
             Self::SetAddrModeAbsLongX => {
                 ret += Self::SetAddrModeAbsoluteLong.expand(pstate);
-                ret += quote! {
-                    cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(cpu.registers.X);
-                }
+                ret += long_addr_add(quote!(cpu.registers.X));
             }
             Self::SetAddrModeAbsoluteX => {
                 ret += Self::SetAddrModeAbsolute.expand(pstate);
 
                 let new_addr = quote!(cpu.addr_bus.addr.wrapping_add(cpu.registers.X));
-                ret += InstrBody::note4(new_addr.clone());
-                ret += quote! {
-                    cpu.addr_bus.addr = #new_addr;
-                }
+                ret += InstrBody::note4(new_addr, pstate.writes);
+                ret += long_addr_add(quote!(cpu.registers.X));
             }
             Self::SetAddrModeAbsoluteY => {
                 ret += Self::SetAddrModeAbsolute.expand(pstate);
 
                 let new_addr = quote!(cpu.addr_bus.addr.wrapping_add(cpu.registers.Y));
-                ret += InstrBody::note4(new_addr.clone());
-                ret += quote! {
-                    cpu.addr_bus.addr = #new_addr;
-                }
+                ret += InstrBody::note4(new_addr, pstate.writes);
+                ret += long_addr_add(quote!(cpu.registers.Y));
             }
             Self::SetAddrModeDirect => {
                 ret += Self::Fetch8Imm.expand(pstate);
@@ -529,41 +636,36 @@ impl MetaInstruction {
                     cpu.addr_bus = snes_addr!(0:cpu.registers.D.wrapping_add(cpu.data_bus as u16));
                 };
                 pstate.addrmode = AddrBusPosition::Unaligned;
+                pstate.long_addr = false;
             }
             Self::SetAddrModeDirectXIndirect => {
-                ret += Self::SetAddrModeDirect.expand(pstate);
-                ret += Self::EndCycle(quote!(Internal)).expand(pstate);
-                ret += quote! {
-                    cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(cpu.registers.X);
-                };
-                ret += Self::Fetch16Into(quote!(cpu.internal_data_bus)).expand(pstate);
+                ret += Self::SetAddrModeDirectX.expand(pstate);
+                ret += Self::Fetch16DirectInto(quote!(cpu.internal_data_bus)).expand(pstate);
                 ret += quote! {
                     cpu.addr_bus.bank = cpu.registers.DB;
                     cpu.addr_bus.addr = cpu.internal_data_bus;
                 };
+                pstate.long_addr = true;
             }
             Self::SetAddrModeDirectIndirect => {
                 ret += Self::SetAddrModeDirect.expand(pstate);
-                ret += Self::Fetch16Into(quote!(cpu.internal_data_bus)).expand(pstate);
+                ret += Self::Fetch16DirectInto(quote!(cpu.internal_data_bus)).expand(pstate);
                 ret += quote! {
                     cpu.addr_bus.bank = cpu.registers.DB;
                     cpu.addr_bus.addr = cpu.internal_data_bus;
                 };
+                pstate.long_addr = true;
             }
             Self::SetAddrModeDirectIndirectY => {
                 ret += Self::SetAddrModeDirectIndirect.expand(pstate);
 
                 let new_addr = quote!(cpu.addr_bus.addr.wrapping_add(cpu.registers.Y));
-                ret += InstrBody::note4(new_addr.clone());
-                ret += quote! {
-                    cpu.addr_bus.addr = #new_addr;
-                }
+                ret += InstrBody::note4(new_addr, pstate.writes);
+                ret += long_addr_add(quote!(cpu.registers.Y));
             }
             Self::SetAddrModeDirectIndirectLongY => {
                 ret += Self::SetAddrModeDirectIndirectLong.expand(pstate);
-                ret += quote! {
-                    cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(cpu.registers.Y);
-                }
+                ret += long_addr_add(quote!(cpu.registers.Y));
             }
             Self::SetAddrModeDirectIndirectLong => {
                 ret += Self::SetAddrModeDirect.expand(pstate);
@@ -575,21 +677,18 @@ impl MetaInstruction {
                 ret += quote! {
                     cpu.addr_bus.bank = cpu.data_bus;
                     cpu.addr_bus.addr = cpu.internal_data_bus;
-                }
+                };
+                pstate.long_addr = true;
             }
             Self::SetAddrModeDirectX => {
                 ret += Self::SetAddrModeDirect.expand(pstate);
                 ret += Self::EndCycle(quote!(Internal)).expand(pstate);
-                ret += quote! {
-                    cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(cpu.registers.X);
-                }
+                ret += direct_page_add(quote!(cpu.registers.X));
             }
             Self::SetAddrModeDirectY => {
                 ret += Self::SetAddrModeDirect.expand(pstate);
                 ret += Self::EndCycle(quote!(Internal)).expand(pstate);
-                ret += quote! {
-                    cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(cpu.registers.Y);
-                }
+                ret += direct_page_add(quote!(cpu.registers.Y));
             }
             Self::SetAddrModeStack => {
                 ret += InstrBody::post(quote! {
@@ -597,6 +696,7 @@ impl MetaInstruction {
                     cpu.addr_bus.bank = 0;
                 });
                 pstate.addrmode = AddrBusPosition::Unaligned;
+                pstate.long_addr = false;
             }
             Self::SetAddrModeStackRelative => {
                 ret += Self::Fetch8Imm.expand(pstate); // read stack offset
@@ -606,6 +706,7 @@ impl MetaInstruction {
                     cpu.addr_bus = snes_addr!(0:cpu.registers.S.wrapping_add(cpu.data_bus as u16));
                 };
                 pstate.addrmode = AddrBusPosition::Unaligned;
+                pstate.long_addr = false;
             }
             Self::SetAddrModeStackRelativeIndirectY => {
                 ret += Self::SetAddrModeStackRelative.expand(pstate);
@@ -613,8 +714,10 @@ impl MetaInstruction {
                 ret += Self::EndCycle(quote!(Internal)).expand(pstate);
                 ret += quote! {
                     cpu.addr_bus.bank = cpu.registers.DB;
-                    cpu.addr_bus.addr = cpu.internal_data_bus.wrapping_add(cpu.registers.Y);
-                }
+                    cpu.addr_bus.addr = cpu.internal_data_bus;
+                };
+                ret += long_addr_add(quote!(cpu.registers.Y));
+                pstate.long_addr = true;
             }
 
             Self::Fetch8Into(dest) => {
@@ -629,14 +732,17 @@ impl MetaInstruction {
                 let is_imm = pstate.addrmode == AddrBusPosition::Immediate;
 
                 ret += Self::Fetch8Into(quote! { *#into.lo_mut() }).expand(pstate);
-                ret += InstrBody::post(quote! {
-                    cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(1);
-                });
+                ret += InstrBody::post(pstate.addr_increment());
                 if is_imm { // if we started as imm, now we are imm again
                     pstate.addrmode = AddrBusPosition::Immediate;
                 }
                 ret += Self::Fetch8Into(quote! { *#into.hi_mut() }).expand(pstate);
             }
+            Self::Fetch16DirectInto(into) => {
+                ret += Self::Fetch8Into(quote! { *#into.lo_mut() }).expand(pstate);
+                ret += InstrBody::post(direct_page_add(quote!(1)));
+                ret += Self::Fetch8Into(quote! { *#into.hi_mut() }).expand(pstate);
+            }
 
             Self::FetchOperandInto(into) => {
                 let is_imm = pstate.addrmode == AddrBusPosition::Immediate;
@@ -688,6 +794,7 @@ impl MetaInstruction {
                 ret += Self::EndCycle(quote! { Read }).expand(pstate);
             }
             Self::PullN8 => {
+                pstate.native_stack = true;
                 ret += InstrBody::post(quote! {
                     // stack grows downwards
                     cpu.registers.S = cpu.registers.S.wrapping_add(1);
@@ -731,9 +838,7 @@ impl MetaInstruction {
             }
             Self::Write16(data) => {
                 ret += Self::Write8(quote! { *#data.lo() }).expand(pstate);
-                ret += InstrBody::post(quote! {
-                    cpu.addr_bus.addr = cpu.addr_bus.addr.wrapping_add(1);
-                });
+                ret += InstrBody::post(pstate.addr_increment());
                 ret += Self::Write8(quote! { *#data.hi() }).expand(pstate);
             }
 
@@ -758,6 +863,7 @@ impl MetaInstruction {
                 ret += Self::Write8(data).expand(pstate);
             }
             Self::PushN8(data) => {
+                pstate.native_stack = true;
                 ret += Self::SetAddrModeStack.expand(pstate);
                 // stack grows downwards
                 ret += InstrBody::post(quote! {
@@ -783,6 +889,66 @@ impl MetaInstruction {
                     data: (),
                 };
             }
+            Self::ReadModifyWrite(args) => {
+                let mut args = args.into_iter().peekable();
+                let algorithm: TokenStream = args
+                    .by_ref()
+                    .take_while(|token| !matches!(token, TokenTree::Punct(p) if p.as_char() == ','))
+                    .collect();
+
+                // bind the operand and the args with the operand width in the modify cycle
+                let mut bindings = Vec::new();
+                while args.peek().is_some() {
+                    bindings.push(Binding {
+                        name: format_ident!("rmw_arg{}", bindings.len()),
+                        value: args
+                            .by_ref()
+                            .take_while(|token| !matches!(token, TokenTree::Punct(p) if p.as_char() == ','))
+                            .collect(),
+                    });
+                }
+                let arg_names = bindings.iter().map(|binding| &binding.name);
+                let call = quote! {
+                    #algorithm(rmw_data, #(#arg_names,)* &mut cpu.registers.P);
+                };
+
+                // copy the original addrbus to restore it in the 16-bit write
+                ret += VarWidth::long(quote! {
+                    cpu.addr_bus2 = cpu.addr_bus;
+                });
+                ret += Self::FetchOperandInto(quote!(cpu.internal_data_bus)).expand(pstate);
+
+                // in emulation mode (8-bit only), the unmodified operand is written back
+                // during the modify cycle
+                ret += quote! {
+                    cpu.data_bus = *cpu.internal_data_bus.lo();
+                };
+                for binding in bindings {
+                    ret += binding.expand();
+                }
+                ret += Binding {
+                    name: format_ident!("rmw_data"),
+                    value: quote!(cpu.internal_data_bus),
+                }.expand_mut();
+                ret += call;
+                ret += Self::EndCycle(quote! {
+                    if cpu.registers.E { Write } else { Internal }
+                }).expand(pstate);
+
+                let mut long = Self::Write8(quote! { *cpu.internal_data_bus.hi() })
+                    .expand(pstate)
+                    .expect_const();
+                // the low byte is written last, where it was read
+                long += quote! {
+                    cpu.addr_bus = cpu.addr_bus2;
+                };
+                long += Self::Write8(quote! { *cpu.internal_data_bus.lo() }).expand(pstate).expect_const();
+                ret += MetaInstrExpansion::VarWidth{
+                    short: Self::Write8(quote! { *cpu.internal_data_bus.lo() }).expand(pstate).expect_const(),
+                    long,
+                    data: (),
+                };
+            }
             Self::SetNZ8(data) => {
                 ret += quote! {
                     cpu.registers.P.Z = (#data) == 0;
@@ -994,7 +1160,7 @@ impl Instr {
         ret.body += InstrBody::parse(body.stream(), &mut pstate)?;
 
         // Set PC to point at the next opcode
-        match (&mut ret.body, pstate.imm_offset.map_into(|i| pstate.conditionally_inc_pc(*i))) {
+        match (&mut ret.body, pstate.imm_offset.map_into(|i| pstate.end_of_instr(*i))) {
             (VarWidth::ConstWidth(ib), VarWidth::ConstWidth(offs)) => {
                 *ib.cycles.last_mut().expect("at least 1 cycle") += offs;
             }
@@ -1131,10 +1297,14 @@ impl InstrBody {
     /// the X flag is clear (when it allows index registers to be 16-bit long)
     ///
     /// The optional cycle happens right before the I/O cycle which uses the
-    /// indexed address.
+    /// indexed address. Instructions which write to the indexed address
+    /// always spend this cycle.
     ///
     /// This should be called before setting the new address in the address bus.
-    pub fn note4(new_address: TokenStream) -> Self {
+    pub fn note4(new_address: TokenStream, writes: bool) -> Self {
+        if writes {
+            return Self::cycles(vec![Cycle::new(quote!(), quote!(Internal))]);
+        }
         Self::cycles(vec![Cycle::conditional(
             quote!(!cpu.registers.P.X || *cpu.addr_bus.addr.hi() != *#new_address.hi())
         )])
@@ -2,9 +2,16 @@ use crate::{
     instrs::instr_tab::*,
     registers::Registers,
 };
-use common::snes_address::SnesAddress;
+use common::save_state::{StateError, StateReader, StateWriter};
+use common::snes_address::{SnesAddress, snes_addr};
+use common::u16_split::U16Split;
 use instr_metalang_procmacro::cpu_instr_no_inc_pc;
 
+/// Next cycle of a saved CPU, see [`CPU::save_state`]
+const NEXT_FETCH: u8 = 0;
+const NEXT_RESET: u8 = 1;
+const NEXT_DECODE: u8 = 2;
+
 /// Resumable main CPU of the SNES, a 65C816
 ///
 /// The primary way to use this CPU is through the [`Self::cycle`] function,
@@ -38,6 +45,101 @@ pub struct CPU {
     /// Member variable that holds a function pointer that will be called the next
     /// time time [`Self::cycle`] is called.
     pub(crate) next_cycle: InstrCycle,
+
+    /// Set by the opcode fetch cycle: the next cycle is the first cycle of
+    /// the instruction in the data bus, looked up directly in the opcode table.
+    #[cfg(feature = "jump-table-dispatch")]
+    pub(crate) decode_pending: bool,
+
+    /// Whether the last cycle was an opcode fetch, see [`Self::fetched_opcode`]
+    pub(crate) opcode_fetched: bool,
+
+    /// What to do on WDM and on opcodes which are not implemented
+    pub(crate) opcode_policy: OpcodePolicy,
+
+    /// Set when the CPU doesn't execute instructions anymore
+    pub(crate) halt: Option<Halt>,
+
+    /// Last WDM or unimplemented opcode reached, until taken by
+    /// [`Self::take_unhandled_opcode`]
+    pub(crate) unhandled_opcode: Option<UnhandledOpcode>,
+}
+
+/// Behaviour of the CPU when it reaches WDM (reserved for future use) or an
+/// opcode which is not implemented yet.
+///
+/// In both cases, the opcode is reported by [`CPU::take_unhandled_opcode`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OpcodePolicy {
+    /// Skip the instruction as a NOP of the same length, which is what
+    /// the hardware does for WDM
+    #[default]
+    Nop,
+
+    /// Halt the CPU before the instruction (see [`Halt::Trap`]), for a
+    /// debugger to inspect the state and [`CPU::resume`]
+    Trap,
+}
+
+/// WDM or unimplemented opcode reached by the CPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnhandledOpcode {
+    pub opcode: u8,
+
+    /// Address of the opcode
+    pub addr: SnesAddress,
+
+    /// Length of the instruction in bytes, skipped when it is treated as a NOP
+    pub len: u16,
+}
+
+/// Reason why the CPU stopped executing instructions; it only runs internal
+/// cycles until it is resumed or reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    /// Stopped by a STP instruction, until the next reset
+    Stopped,
+
+    /// Trapped on an opcode with [`OpcodePolicy::Trap`]
+    Trap(UnhandledOpcode),
+}
+
+/// Memory as seen by the CPU, for operations run outside of [`CPU::cycle`] such as
+/// [`CPU::reset`].
+pub trait CpuBus {
+    fn read(&mut self, addr: SnesAddress) -> u8;
+}
+
+/// Entries of the vector table at the end of bank 0, each holding the 16-bit address
+/// of a handler in bank 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vector {
+    Cop,
+    Brk,
+    Abort,
+    Nmi,
+    Reset,
+    Irq,
+}
+
+impl Vector {
+    /// Address of the vector for a CPU in emulation mode (`emulation`) or native mode.
+    /// In emulation mode BRK shares the IRQ vector, and a reset always switches to
+    /// emulation mode, so its vector is the same in both.
+    pub const fn addr(self, emulation: bool) -> u16 {
+        match (self, emulation) {
+            (Vector::Cop, false) => 0xFFE4,
+            (Vector::Brk, false) => 0xFFE6,
+            (Vector::Abort, false) => 0xFFE8,
+            (Vector::Nmi, false) => 0xFFEA,
+            (Vector::Irq, false) => 0xFFEE,
+            (Vector::Cop, true) => 0xFFF4,
+            (Vector::Abort, true) => 0xFFF8,
+            (Vector::Nmi, true) => 0xFFFA,
+            (Vector::Reset, _) => 0xFFFC,
+            (Vector::Brk | Vector::Irq, true) => 0xFFFE,
+        }
+    }
 }
 
 /// The result of a CPU cycle.
@@ -68,6 +170,41 @@ impl CPU {
             data_bus: 0,
             internal_data_bus: 0,
             next_cycle: InstrCycle(opcode_fetch),
+            #[cfg(feature = "jump-table-dispatch")]
+            decode_pending: false,
+            opcode_fetched: false,
+            opcode_policy: OpcodePolicy::default(),
+            halt: None,
+            unhandled_opcode: None,
+        }
+    }
+
+    pub fn opcode_policy(&self) -> OpcodePolicy {
+        self.opcode_policy
+    }
+
+    pub fn set_opcode_policy(&mut self, policy: OpcodePolicy) {
+        self.opcode_policy = policy;
+    }
+
+    /// Why the CPU is halted, `None` while it executes instructions
+    pub fn halted(&self) -> Option<Halt> {
+        self.halt
+    }
+
+    /// Returns the last WDM or unimplemented opcode reached since the previous
+    /// call, skipped or trapped depending on the [`OpcodePolicy`].
+    pub fn take_unhandled_opcode(&mut self) -> Option<UnhandledOpcode> {
+        self.unhandled_opcode.take()
+    }
+
+    /// Continues execution after a trap, skipping the trapped instruction
+    /// as a NOP. Does nothing if the CPU isn't trapped.
+    pub fn resume(&mut self) {
+        if let Some(Halt::Trap(trap)) = self.halt {
+            self.halt = None;
+            self.registers.PC = trap.addr.addr.wrapping_add(trap.len);
+            self.next_cycle = InstrCycle(opcode_fetch);
         }
     }
 
@@ -82,6 +219,123 @@ impl CPU {
         &self.addr_bus
     }
 
+    /// Whether the last cycle read the opcode of an instruction, rather than an operand or
+    /// data (the real chip signals it with both VDA and VPA high).
+    pub fn fetched_opcode(&self) -> bool {
+        self.opcode_fetched
+    }
+
+    /// Whether the CPU stopped in the middle of an instruction (or of the reset sequence),
+    /// its registers being final only once it is not. An instruction ending with a read
+    /// finishes at the start of the next opcode fetch, which stores the byte read, so the
+    /// CPU is still in it until then. A halted CPU is not.
+    pub fn mid_instruction(&self) -> bool {
+        #[cfg(feature = "jump-table-dispatch")]
+        if self.decode_pending {
+            return true;
+        }
+        let fetch: fn(&mut CPU) -> (CycleResult, InstrCycle) = opcode_fetch;
+        self.halt.is_none() && !core::ptr::fn_addr_eq(self.next_cycle.0, fetch)
+    }
+
+    /// Whether the next cycle starts the reset sequence, see [`Self::start_reset`]
+    pub fn reset_pending(&self) -> bool {
+        let reset: fn(&mut CPU) -> (CycleResult, InstrCycle) = reset_cyc1;
+        core::ptr::fn_addr_eq(self.next_cycle.0, reset)
+    }
+
+    /// Whether the next cycle decodes the opcode just fetched into the data bus
+    fn opcode_decode_pending(&self) -> bool {
+        #[cfg(feature = "jump-table-dispatch")]
+        return self.decode_pending;
+
+        #[cfg(not(feature = "jump-table-dispatch"))]
+        {
+            let decode: fn(&mut CPU) -> (CycleResult, InstrCycle) = decode;
+            core::ptr::fn_addr_eq(self.next_cycle.0, decode)
+        }
+    }
+
+    /// Whether [`Self::save_state`] can save the CPU: between two instructions, right after
+    /// an opcode fetch, or with a reset pending. The rest of an instruction in progress is
+    /// not kept, callers run the CPU cycle by cycle until it can be saved. Instructions
+    /// ending with a read go straight into the next opcode fetch, so a loop of them is only
+    /// ever past an opcode fetch.
+    pub fn can_save_state(&self) -> bool {
+        !self.mid_instruction() || self.opcode_decode_pending() || self.reset_pending()
+    }
+
+    /// Writes the CPU to a save state, see [`Self::can_save_state`]. The opcode policy is a
+    /// setting of the emulator and is not saved.
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        debug_assert!(self.can_save_state());
+        self.registers.save_state(writer);
+        writer.u8(self.data_bus);
+        writer.u8(if self.reset_pending() {
+            NEXT_RESET
+        } else if self.opcode_decode_pending() {
+            NEXT_DECODE
+        } else {
+            NEXT_FETCH
+        });
+        match self.halt {
+            None => writer.u8(0),
+            Some(Halt::Stopped) => writer.u8(1),
+            Some(Halt::Trap(trap)) => {
+                writer.u8(2);
+                writer.u8(trap.opcode);
+                writer.u8(trap.addr.bank);
+                writer.u16(trap.addr.addr);
+                writer.u16(trap.len);
+            }
+        }
+    }
+
+    /// Restores a CPU written by [`Self::save_state`], which resumes with the next opcode
+    /// fetch, the instruction of the opcode in the data bus, the reset sequence or halted.
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.registers.load_state(reader)?;
+        self.data_bus = reader.u8()?;
+        let next = reader.u8()?;
+        self.halt = match reader.u8()? {
+            0 => None,
+            1 => Some(Halt::Stopped),
+            2 => Some(Halt::Trap(UnhandledOpcode {
+                opcode: reader.u8()?,
+                addr: SnesAddress {
+                    bank: reader.u8()?,
+                    addr: reader.u16()?,
+                },
+                len: reader.u16()?,
+            })),
+            _ => return Err(reader.corrupt()),
+        };
+        self.next_cycle = match (next, self.halt) {
+            (NEXT_FETCH, None) => InstrCycle(opcode_fetch),
+            (NEXT_FETCH, Some(_)) => InstrCycle(halted),
+            #[cfg(not(feature = "jump-table-dispatch"))]
+            (NEXT_DECODE, None) => InstrCycle(decode),
+            #[cfg(feature = "jump-table-dispatch")]
+            (NEXT_DECODE, None) => InstrCycle(opcode_fetch),
+            (NEXT_RESET, None) => InstrCycle(reset_cyc1),
+            _ => return Err(reader.corrupt()),
+        };
+        #[cfg(feature = "jump-table-dispatch")]
+        {
+            self.decode_pending = next == NEXT_DECODE;
+        }
+        self.opcode_fetched = next == NEXT_DECODE;
+        if self.opcode_fetched {
+            // The operands are read past the address of the opcode
+            self.addr_bus = SnesAddress {
+                bank: self.registers.PB,
+                addr: self.registers.PC,
+            };
+        }
+        self.unhandled_opcode = None;
+        Ok(())
+    }
+
     /// Execute a single CPU cycle.
     ///
     /// This function is the core part of the public API to this struct.
@@ -137,45 +391,84 @@ impl CPU {
     /// See [`CycleResult`] for more information about the return value of
     /// this function.
     pub fn cycle(&mut self) -> CycleResult {
+        self.opcode_fetched = false;
+
+        #[cfg(not(feature = "jump-table-dispatch"))]
         let (ret, next_cycle) = (self.next_cycle.0)(self);
 
+        #[cfg(feature = "jump-table-dispatch")]
+        let (ret, next_cycle) = if self.decode_pending {
+            self.decode_pending = false;
+            decode(self)
+        } else {
+            (self.next_cycle.0)(self)
+        };
+
         self.next_cycle = next_cycle;
         ret
     }
 
     /// Resets the CPU as with the RESB input signal
     ///
-    /// This resets some CPU registers and jumps program execution to
-    /// the address contained at 0:FFFC in bank 0
-    pub fn reset(&mut self) {
+    /// The next cycles run the reset sequence: they reset some CPU registers
+    /// (see [`Self::reset`]) and read the reset vector, to which program
+    /// execution then jumps.
+    pub fn start_reset(&mut self) {
         // set the next cycle to be the reset sequence defined below
         self.next_cycle = InstrCycle(reset_cyc1);
+        self.halt = None;
+        #[cfg(feature = "jump-table-dispatch")]
+        {
+            self.decode_pending = false;
+        }
+    }
+
+    /// Runs the whole reset sequence at once, reading the reset vector from `bus`:
+    /// the CPU switches to emulation mode with 8-bit registers (clearing the high
+    /// bytes of X and Y), the stack at $01FF, D, DB and PB cleared, IRQs disabled and
+    /// decimal mode off. The next cycle fetches the first opcode.
+    pub fn reset(&mut self, bus: &mut impl CpuBus) {
+        self.start_reset();
+        self.reset_registers();
+
+        let vector = Vector::Reset.addr(true);
+        let lo = bus.read(snes_addr!(0:vector));
+        self.data_bus = bus.read(snes_addr!(0:vector.wrapping_add(1)));
+        self.registers.PC = u16::from_le_bytes([lo, self.data_bus]);
+        self.next_cycle = InstrCycle(opcode_fetch);
+    }
+
+    /// Register state set by the reset sequence, before the vector is read
+    fn reset_registers(&mut self) {
+        let regs = &mut self.registers;
+        regs.DB = 0;
+        regs.D = 0;
+        regs.PB = 0;
+        regs.S = 0x01FF;
+
+        *regs.X.hi_mut() = 0;
+        *regs.Y.hi_mut() = 0;
+
+        regs.P.M = true;
+        regs.P.X = true;
+        regs.P.D = false;
+        regs.P.I = true;
+        regs.E = true;
     }
 
     /// Construct a freshly reset CPU, as it would be on power-on
     pub fn poweron() -> Self {
         let mut ret = Self::new(Registers::default());
 
-        ret.reset();
+        ret.start_reset();
         ret
     }
 }
 
 cpu_instr_no_inc_pc!(reset {
-    cpu.registers.DB = 0;
-    cpu.registers.D = 0;
-    cpu.registers.PB = 0;
-
-    *cpu.registers.X.hi_mut() = 0;
-    *cpu.registers.Y.hi_mut() = 0;
-
-    cpu.registers.P.M = true;
-    cpu.registers.P.X = true;
-    cpu.registers.P.D = false;
-    cpu.registers.P.I = true;
-    cpu.registers.E = true;
+    cpu.reset_registers();
 
-    cpu.addr_bus = snes_addr!(0:0xfffc);
+    cpu.addr_bus = snes_addr!(0:Vector::Reset.addr(true));
     meta FETCH16_INTO cpu.registers.PC;
 });
 
@@ -195,4 +488,88 @@ mod tests {
         assert_eq!(cpu.regs().PC, 0x2468);
         assert_eq!(cpu.regs().PB, 0);
     }
+
+    struct Vectors;
+
+    impl super::CpuBus for Vectors {
+        fn read(&mut self, addr: SnesAddress) -> u8 {
+            match (addr.bank, addr.addr) {
+                (0, 0xfffc) => 0x00,
+                (0, 0xfffd) => 0x80,
+                _ => panic!("unexpected read at {addr:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn reset_with_bus() {
+        let mut regs = Registers {
+            PB: 0x12,
+            DB: 0x7e,
+            D: 0x1234,
+            S: 0x1fe0,
+            X: 0xabcd,
+            ..Registers::default()
+        };
+        regs.P.D = true;
+        let mut cpu = super::CPU::new(regs);
+
+        cpu.reset(&mut Vectors);
+        let regs = cpu.regs();
+        assert_eq!((regs.PB, regs.PC), (0, 0x8000));
+        assert_eq!((regs.DB, regs.D, regs.S, regs.X), (0, 0, 0x01ff, 0x00cd));
+        assert!(regs.E && regs.P.M && regs.P.X && regs.P.I && !regs.P.D);
+        expect_opcode_fetch_cycle(&mut cpu);
+        assert_eq!(cpu.addr_bus().addr, 0x8000);
+    }
+
+    #[test]
+    fn mid_instruction() {
+        let mut cpu = super::CPU::poweron();
+        assert!(cpu.mid_instruction(), "reset sequence");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0xfffc), 0x00, "start address lo");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0xfffd), 0x80, "start address hi");
+
+        // INX: done after its internal cycle
+        expect_opcode_fetch(&mut cpu, 0xe8);
+        assert!(cpu.mid_instruction());
+        expect_internal_cycle(&mut cpu, "increment");
+        assert!(!cpu.mid_instruction());
+
+        // LDA #$42: A is only written by the next opcode fetch
+        expect_opcode_fetch(&mut cpu, 0xa9);
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x8002), 0x42, "immediate");
+        assert!(cpu.mid_instruction());
+        expect_opcode_fetch_cycle(&mut cpu);
+        assert_eq!(cpu.regs().A & 0xff, 0x42);
+    }
+
+    #[test]
+    fn can_save_state() {
+        let mut cpu = super::CPU::poweron();
+        assert!(cpu.can_save_state(), "reset pending");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0xfffc), 0x00, "start address lo");
+        assert!(!cpu.can_save_state());
+        expect_read_cycle(&mut cpu, snes_addr!(0:0xfffd), 0x80, "start address hi");
+
+        // BRA -2 never stops between two instructions, but goes through opcode fetches
+        expect_opcode_fetch(&mut cpu, 0x80);
+        assert!(cpu.mid_instruction() && cpu.can_save_state());
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x8001), 0xfe, "offset");
+        assert!(!cpu.can_save_state());
+        expect_internal_cycle(&mut cpu, "branch taken");
+        expect_opcode_fetch(&mut cpu, 0x80);
+        assert!(cpu.mid_instruction() && cpu.can_save_state());
+    }
+
+    #[test]
+    fn vector_table() {
+        use super::Vector;
+
+        assert_eq!(Vector::Reset.addr(false), 0xfffc);
+        assert_eq!(Vector::Nmi.addr(true), 0xfffa);
+        assert_eq!(Vector::Nmi.addr(false), 0xffea);
+        assert_eq!(Vector::Brk.addr(true), Vector::Irq.addr(true));
+        assert_eq!(Vector::Brk.addr(false), 0xffe6);
+    }
 }
@@ -0,0 +1,426 @@
+//! A static 65C816 disassembler
+//!
+//! Decoding an instruction only needs the opcode table and the current
+//! width of the accumulator and index registers, since `#imm` operands are
+//! one or two bytes long depending on the `M` and `X` status flags. The
+//! caller tracks those flags through [`RegisterWidths`], which follows
+//! `REP` and `SEP` when the immediate value is known.
+
+use core::fmt;
+
+/// How an instruction's operand is interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddrMode {
+    Implied,
+    Accumulator,
+    /// `#imm`, sized by the `M` flag
+    ImmediateM,
+    /// `#imm`, sized by the `X` flag
+    ImmediateX,
+    /// `#imm`, always one byte (`BRK`, `COP`, `REP`, ...)
+    Immediate8,
+    Direct,
+    DirectX,
+    DirectY,
+    DirectIndirect,
+    DirectIndirectLong,
+    DirectXIndirect,
+    DirectIndirectY,
+    DirectIndirectLongY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    AbsoluteLong,
+    AbsoluteLongX,
+    AbsoluteIndirect,
+    AbsoluteIndirectLong,
+    AbsoluteXIndirect,
+    StackRelative,
+    StackRelativeIndirectY,
+    /// 8-bit signed branch offset
+    Relative,
+    /// 16-bit signed branch offset (`BRL`, `PER`)
+    RelativeLong,
+    /// `MVN`/`MVP`: destination bank then source bank
+    BlockMove,
+}
+
+use AddrMode::*;
+
+#[rustfmt::skip]
+static OPCODES: [(&str, AddrMode); 256] = [
+    // $0x
+    ("BRK", Immediate8), ("ORA", DirectXIndirect), ("COP", Immediate8), ("ORA", StackRelative),
+    ("TSB", Direct), ("ORA", Direct), ("ASL", Direct), ("ORA", DirectIndirectLong),
+    ("PHP", Implied), ("ORA", ImmediateM), ("ASL", Accumulator), ("PHD", Implied),
+    ("TSB", Absolute), ("ORA", Absolute), ("ASL", Absolute), ("ORA", AbsoluteLong),
+    // $1x
+    ("BPL", Relative), ("ORA", DirectIndirectY), ("ORA", DirectIndirect), ("ORA", StackRelativeIndirectY),
+    ("TRB", Direct), ("ORA", DirectX), ("ASL", DirectX), ("ORA", DirectIndirectLongY),
+    ("CLC", Implied), ("ORA", AbsoluteY), ("INC", Accumulator), ("TCS", Implied),
+    ("TRB", Absolute), ("ORA", AbsoluteX), ("ASL", AbsoluteX), ("ORA", AbsoluteLongX),
+    // $2x
+    ("JSR", Absolute), ("AND", DirectXIndirect), ("JSL", AbsoluteLong), ("AND", StackRelative),
+    ("BIT", Direct), ("AND", Direct), ("ROL", Direct), ("AND", DirectIndirectLong),
+    ("PLP", Implied), ("AND", ImmediateM), ("ROL", Accumulator), ("PLD", Implied),
+    ("BIT", Absolute), ("AND", Absolute), ("ROL", Absolute), ("AND", AbsoluteLong),
+    // $3x
+    ("BMI", Relative), ("AND", DirectIndirectY), ("AND", DirectIndirect), ("AND", StackRelativeIndirectY),
+    ("BIT", DirectX), ("AND", DirectX), ("ROL", DirectX), ("AND", DirectIndirectLongY),
+    ("SEC", Implied), ("AND", AbsoluteY), ("DEC", Accumulator), ("TSC", Implied),
+    ("BIT", AbsoluteX), ("AND", AbsoluteX), ("ROL", AbsoluteX), ("AND", AbsoluteLongX),
+    // $4x
+    ("RTI", Implied), ("EOR", DirectXIndirect), ("WDM", Immediate8), ("EOR", StackRelative),
+    ("MVP", BlockMove), ("EOR", Direct), ("LSR", Direct), ("EOR", DirectIndirectLong),
+    ("PHA", Implied), ("EOR", ImmediateM), ("LSR", Accumulator), ("PHK", Implied),
+    ("JMP", Absolute), ("EOR", Absolute), ("LSR", Absolute), ("EOR", AbsoluteLong),
+    // $5x
+    ("BVC", Relative), ("EOR", DirectIndirectY), ("EOR", DirectIndirect), ("EOR", StackRelativeIndirectY),
+    ("MVN", BlockMove), ("EOR", DirectX), ("LSR", DirectX), ("EOR", DirectIndirectLongY),
+    ("CLI", Implied), ("EOR", AbsoluteY), ("PHY", Implied), ("TCD", Implied),
+    ("JML", AbsoluteLong), ("EOR", AbsoluteX), ("LSR", AbsoluteX), ("EOR", AbsoluteLongX),
+    // $6x
+    ("RTS", Implied), ("ADC", DirectXIndirect), ("PER", RelativeLong), ("ADC", StackRelative),
+    ("STZ", Direct), ("ADC", Direct), ("ROR", Direct), ("ADC", DirectIndirectLong),
+    ("PLA", Implied), ("ADC", ImmediateM), ("ROR", Accumulator), ("RTL", Implied),
+    ("JMP", AbsoluteIndirect), ("ADC", Absolute), ("ROR", Absolute), ("ADC", AbsoluteLong),
+    // $7x
+    ("BVS", Relative), ("ADC", DirectIndirectY), ("ADC", DirectIndirect), ("ADC", StackRelativeIndirectY),
+    ("STZ", DirectX), ("ADC", DirectX), ("ROR", DirectX), ("ADC", DirectIndirectLongY),
+    ("SEI", Implied), ("ADC", AbsoluteY), ("PLY", Implied), ("TDC", Implied),
+    ("JMP", AbsoluteXIndirect), ("ADC", AbsoluteX), ("ROR", AbsoluteX), ("ADC", AbsoluteLongX),
+    // $8x
+    ("BRA", Relative), ("STA", DirectXIndirect), ("BRL", RelativeLong), ("STA", StackRelative),
+    ("STY", Direct), ("STA", Direct), ("STX", Direct), ("STA", DirectIndirectLong),
+    ("DEY", Implied), ("BIT", ImmediateM), ("TXA", Implied), ("PHB", Implied),
+    ("STY", Absolute), ("STA", Absolute), ("STX", Absolute), ("STA", AbsoluteLong),
+    // $9x
+    ("BCC", Relative), ("STA", DirectIndirectY), ("STA", DirectIndirect), ("STA", StackRelativeIndirectY),
+    ("STY", DirectX), ("STA", DirectX), ("STX", DirectY), ("STA", DirectIndirectLongY),
+    ("TYA", Implied), ("STA", AbsoluteY), ("TXS", Implied), ("TXY", Implied),
+    ("STZ", Absolute), ("STA", AbsoluteX), ("STZ", AbsoluteX), ("STA", AbsoluteLongX),
+    // $Ax
+    ("LDY", ImmediateX), ("LDA", DirectXIndirect), ("LDX", ImmediateX), ("LDA", StackRelative),
+    ("LDY", Direct), ("LDA", Direct), ("LDX", Direct), ("LDA", DirectIndirectLong),
+    ("TAY", Implied), ("LDA", ImmediateM), ("TAX", Implied), ("PLB", Implied),
+    ("LDY", Absolute), ("LDA", Absolute), ("LDX", Absolute), ("LDA", AbsoluteLong),
+    // $Bx
+    ("BCS", Relative), ("LDA", DirectIndirectY), ("LDA", DirectIndirect), ("LDA", StackRelativeIndirectY),
+    ("LDY", DirectX), ("LDA", DirectX), ("LDX", DirectY), ("LDA", DirectIndirectLongY),
+    ("CLV", Implied), ("LDA", AbsoluteY), ("TSX", Implied), ("TYX", Implied),
+    ("LDY", AbsoluteX), ("LDA", AbsoluteX), ("LDX", AbsoluteY), ("LDA", AbsoluteLongX),
+    // $Cx
+    ("CPY", ImmediateX), ("CMP", DirectXIndirect), ("REP", Immediate8), ("CMP", StackRelative),
+    ("CPY", Direct), ("CMP", Direct), ("DEC", Direct), ("CMP", DirectIndirectLong),
+    ("INY", Implied), ("CMP", ImmediateM), ("DEX", Implied), ("WAI", Implied),
+    ("CPY", Absolute), ("CMP", Absolute), ("DEC", Absolute), ("CMP", AbsoluteLong),
+    // $Dx
+    ("BNE", Relative), ("CMP", DirectIndirectY), ("CMP", DirectIndirect), ("CMP", StackRelativeIndirectY),
+    ("PEI", DirectIndirect), ("CMP", DirectX), ("DEC", DirectX), ("CMP", DirectIndirectLongY),
+    ("CLD", Implied), ("CMP", AbsoluteY), ("PHX", Implied), ("STP", Implied),
+    ("JML", AbsoluteIndirectLong), ("CMP", AbsoluteX), ("DEC", AbsoluteX), ("CMP", AbsoluteLongX),
+    // $Ex
+    ("CPX", ImmediateX), ("SBC", DirectXIndirect), ("SEP", Immediate8), ("SBC", StackRelative),
+    ("CPX", Direct), ("SBC", Direct), ("INC", Direct), ("SBC", DirectIndirectLong),
+    ("INX", Implied), ("SBC", ImmediateM), ("NOP", Implied), ("XBA", Implied),
+    ("CPX", Absolute), ("SBC", Absolute), ("INC", Absolute), ("SBC", AbsoluteLong),
+    // $Fx
+    ("BEQ", Relative), ("SBC", DirectIndirectY), ("SBC", DirectIndirect), ("SBC", StackRelativeIndirectY),
+    ("PEA", Absolute), ("SBC", DirectX), ("INC", DirectX), ("SBC", DirectIndirectLongY),
+    ("SED", Implied), ("SBC", AbsoluteY), ("PLX", Implied), ("XCE", Implied),
+    ("JSR", AbsoluteXIndirect), ("SBC", AbsoluteX), ("INC", AbsoluteX), ("SBC", AbsoluteLongX),];
+
+/// Opcode of `mnemonic` (case insensitive) in addressing `mode`, the reverse lookup of the
+/// opcode table
+pub fn opcode(mnemonic: &str, mode: AddrMode) -> Option<u8> {
+    (0..=0xFF).find(|&opcode| {
+        let (name, opcode_mode) = OPCODES[opcode as usize];
+        opcode_mode == mode && name.eq_ignore_ascii_case(mnemonic)
+    })
+}
+
+/// Mnemonic of `opcode`, e.g. `"LDA"`
+pub fn mnemonic(opcode: u8) -> &'static str {
+    OPCODES[opcode as usize].0
+}
+
+impl AddrMode {
+    /// Addressing mode of `opcode`
+    pub fn of(opcode: u8) -> Self {
+        OPCODES[opcode as usize].1
+    }
+
+    /// Size of the operand in bytes
+    pub fn operand_len(self, widths: RegisterWidths) -> usize {
+        match self {
+            Implied | Accumulator => 0,
+            ImmediateM => {
+                if widths.m8 {
+                    1
+                } else {
+                    2
+                }
+            }
+            ImmediateX => {
+                if widths.x8 {
+                    1
+                } else {
+                    2
+                }
+            }
+            Immediate8
+            | Direct
+            | DirectX
+            | DirectY
+            | DirectIndirect
+            | DirectIndirectLong
+            | DirectXIndirect
+            | DirectIndirectY
+            | DirectIndirectLongY
+            | StackRelative
+            | StackRelativeIndirectY
+            | Relative => 1,
+            Absolute | AbsoluteX | AbsoluteY | AbsoluteIndirect | AbsoluteIndirectLong
+            | AbsoluteXIndirect | RelativeLong | BlockMove => 2,
+            AbsoluteLong | AbsoluteLongX => 3,
+        }
+    }
+}
+
+/// Width of the accumulator (`M` flag) and index registers (`X` flag)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterWidths {
+    pub m8: bool,
+    pub x8: bool,
+}
+
+impl Default for RegisterWidths {
+    /// Both registers are 8 bits wide after reset (emulation mode)
+    fn default() -> Self {
+        Self { m8: true, x8: true }
+    }
+}
+
+impl RegisterWidths {
+    /// Follow the effect of `REP #imm` and `SEP #imm` on the `M` and `X` flags
+    pub fn update(&mut self, instr: &Instruction) {
+        const M: u32 = 0x20;
+        const X: u32 = 0x10;
+
+        match instr.opcode {
+            0xC2 => {
+                self.m8 &= instr.operand & M == 0;
+                self.x8 &= instr.operand & X == 0;
+            }
+            0xE2 => {
+                self.m8 |= instr.operand & M != 0;
+                self.x8 |= instr.operand & X != 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A single decoded instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    /// 24-bit address of the opcode
+    pub addr: u32,
+    pub opcode: u8,
+    /// Little-endian operand value, `len - 1` bytes wide
+    pub operand: u32,
+    pub mode: AddrMode,
+    /// Total length in bytes, opcode included
+    pub len: usize,
+}
+
+impl Instruction {
+    /// Decode the instruction at the start of `bytes`, which lives at `addr`
+    ///
+    /// Returns `None` when `bytes` is too short to hold the whole instruction.
+    pub fn decode(addr: u32, bytes: &[u8], widths: RegisterWidths) -> Option<Self> {
+        let opcode = *bytes.first()?;
+        let mode = OPCODES[opcode as usize].1;
+        let len = 1 + mode.operand_len(widths);
+        let operand = bytes
+            .get(1..len)?
+            .iter()
+            .rev()
+            .fold(0, |acc, &b| (acc << 8) | b as u32);
+
+        Some(Self {
+            addr,
+            opcode,
+            operand,
+            mode,
+            len,
+        })
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        mnemonic(self.opcode)
+    }
+
+    /// Address reached by a branch or a jump with a constant operand
+    ///
+    /// Relative branches and 16-bit jumps stay in the bank of the instruction.
+    pub fn target(&self) -> Option<u32> {
+        let bank = self.addr & 0xFF_0000;
+        let next = self.addr.wrapping_add(self.len as u32) & 0xFFFF;
+
+        match (self.mode, self.opcode) {
+            (Relative, _) => {
+                let offset = self.operand as u8 as i8 as u32;
+                Some(bank | (next.wrapping_add(offset) & 0xFFFF))
+            }
+            (RelativeLong, 0x82) => {
+                let offset = self.operand as u16 as i16 as u32;
+                Some(bank | (next.wrapping_add(offset) & 0xFFFF))
+            }
+            // JMP abs, JSR abs
+            (Absolute, 0x4C | 0x20) => Some(bank | self.operand),
+            // JML long, JSL long
+            (AbsoluteLong, 0x5C | 0x22) => Some(self.operand),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = self.operand;
+        let width = 2 * (self.len.max(2) - 1);
+
+        write!(f, "{}", self.mnemonic())?;
+        match self.mode {
+            Implied => Ok(()),
+            Accumulator => write!(f, " A"),
+            ImmediateM | ImmediateX | Immediate8 => write!(f, " #${op:0width$X}"),
+            Relative | RelativeLong => match self.target() {
+                Some(target) => write!(f, " ${:04X}", target & 0xFFFF),
+                // PER pushes the address it computes
+                None => {
+                    let next = self.addr.wrapping_add(self.len as u32);
+                    let value = next.wrapping_add(op as u16 as i16 as u32) & 0xFFFF;
+                    write!(f, " ${value:04X}")
+                }
+            },
+            Direct | Absolute | AbsoluteLong => write!(f, " ${op:0width$X}"),
+            DirectX | AbsoluteX | AbsoluteLongX => write!(f, " ${op:0width$X},X"),
+            DirectY | AbsoluteY => write!(f, " ${op:0width$X},Y"),
+            DirectIndirect | AbsoluteIndirect => write!(f, " (${op:0width$X})"),
+            DirectIndirectLong | AbsoluteIndirectLong => write!(f, " [${op:0width$X}]"),
+            DirectXIndirect | AbsoluteXIndirect => write!(f, " (${op:0width$X},X)"),
+            DirectIndirectY => write!(f, " (${op:02X}),Y"),
+            DirectIndirectLongY => write!(f, " [${op:02X}],Y"),
+            StackRelative => write!(f, " ${op:02X},S"),
+            StackRelativeIndirectY => write!(f, " (${op:02X},S),Y"),
+            BlockMove => write!(f, " ${:02X},${:02X}", op >> 8, op & 0xFF),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], widths: RegisterWidths) -> Instruction {
+        Instruction::decode(0x00_8000, bytes, widths).unwrap()
+    }
+
+    #[test]
+    fn test_immediate_follows_register_widths() {
+        let short = RegisterWidths::default();
+        let long = RegisterWidths {
+            m8: false,
+            x8: false,
+        };
+
+        assert_eq!(decode(&[0xA9, 0x12, 0x34], short).to_string(), "LDA #$12");
+        assert_eq!(decode(&[0xA9, 0x12, 0x34], long).to_string(), "LDA #$3412");
+        assert_eq!(decode(&[0xA2, 0x12, 0x34], long).len, 3);
+        assert_eq!(decode(&[0xC2, 0x30], long).len, 2);
+    }
+
+    #[test]
+    fn test_addressing_modes_display() {
+        let widths = RegisterWidths::default();
+        let cases: &[(&[u8], &str)] = &[
+            (&[0xEA], "NOP"),
+            (&[0x0A], "ASL A"),
+            (&[0xBD, 0x34, 0x12], "LDA $1234,X"),
+            (&[0xAF, 0x56, 0x34, 0x12], "LDA $123456"),
+            (&[0xB7, 0x10], "LDA [$10],Y"),
+            (&[0xB3, 0x03], "LDA ($03,S),Y"),
+            (&[0x7C, 0x00, 0x90], "JMP ($9000,X)"),
+            (&[0x54, 0x7E, 0x7F], "MVN $7F,$7E"),
+        ];
+
+        for (bytes, expected) in cases {
+            assert_eq!(decode(bytes, widths).to_string(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_branch_targets() {
+        let widths = RegisterWidths::default();
+
+        assert_eq!(decode(&[0xD0, 0x0E], widths).target(), Some(0x00_8010));
+        assert_eq!(decode(&[0xD0, 0xFE], widths).target(), Some(0x00_8000));
+        assert_eq!(decode(&[0xD0, 0x0E], widths).to_string(), "BNE $8010");
+        assert_eq!(
+            decode(&[0x82, 0x00, 0x10], widths).target(),
+            Some(0x00_9003)
+        );
+        assert_eq!(
+            decode(&[0x22, 0x00, 0x80, 0xC0], widths).target(),
+            Some(0xC0_8000)
+        );
+        assert_eq!(decode(&[0xAD, 0x00, 0x80], widths).target(), None);
+    }
+
+    #[test]
+    fn test_rep_sep_update_widths() {
+        let mut widths = RegisterWidths::default();
+
+        widths.update(&decode(&[0xC2, 0x20], widths));
+        assert_eq!(
+            widths,
+            RegisterWidths {
+                m8: false,
+                x8: true
+            }
+        );
+        widths.update(&decode(&[0xC2, 0x10], widths));
+        assert_eq!(
+            widths,
+            RegisterWidths {
+                m8: false,
+                x8: false
+            }
+        );
+        widths.update(&decode(&[0xE2, 0x30], widths));
+        assert_eq!(widths, RegisterWidths::default());
+    }
+
+    #[test]
+    fn test_opcode_lookup() {
+        assert_eq!(opcode("LDA", ImmediateM), Some(0xA9));
+        assert_eq!(opcode("jml", AbsoluteIndirectLong), Some(0xDC));
+        assert_eq!(opcode("STP", Implied), Some(0xDB));
+        assert_eq!(opcode("JMP", Direct), None);
+        assert_eq!(opcode("FOO", Implied), None);
+        for byte in 0..=0xFF {
+            let instr = decode(&[byte, 0, 0, 0], RegisterWidths::default());
+            assert_eq!(opcode(instr.mnemonic(), instr.mode), Some(byte));
+        }
+    }
+
+    #[test]
+    fn test_truncated_input() {
+        let widths = RegisterWidths::default();
+
+        assert_eq!(Instruction::decode(0, &[], widths), None);
+        assert_eq!(Instruction::decode(0, &[0xAD, 0x00], widths), None);
+    }
+}
@@ -475,3 +475,153 @@ mod tests {
         assert_eq!(op, DUP_res);
     }
 }
+
+/// Random 8- and 16-bit inputs checked against a reference model computing on `i32`, so that
+/// carries and signed overflows are plain comparisons instead of bit tricks.
+#[cfg(test)]
+mod proptests {
+    use crate::registers::RegisterP;
+    use duplicate::duplicate_item;
+    use proptest::prelude::*;
+
+    /// Flags expected after an operation, `None` for the flags it leaves untouched
+    struct Expected {
+        res: i32,
+        c: bool,
+        v: Option<bool>,
+    }
+
+    fn flags(carry: bool) -> RegisterP {
+        RegisterP {
+            C: carry,
+            // Sentinel to check that V is left untouched
+            V: true,
+            ..RegisterP::default()
+        }
+    }
+
+    fn signed(value: i32, bits: u32) -> i32 {
+        (value << (32 - bits)) >> (32 - bits)
+    }
+
+    fn in_signed_range(value: i32, bits: u32) -> bool {
+        let half = 1 << (bits - 1);
+        (-half..half).contains(&value)
+    }
+
+    fn adc_model(a: i32, b: i32, carry: bool, bits: u32) -> Expected {
+        let sum = a + b + carry as i32;
+        let signed_sum = signed(a, bits) + signed(b, bits) + carry as i32;
+        Expected {
+            res: sum & ((1 << bits) - 1),
+            c: sum >> bits != 0,
+            v: Some(!in_signed_range(signed_sum, bits)),
+        }
+    }
+
+    fn sbc_model(a: i32, b: i32, carry: bool, bits: u32) -> Expected {
+        let borrow = !carry as i32;
+        let diff = a - b - borrow;
+        let signed_diff = signed(a, bits) - signed(b, bits) - borrow;
+        Expected {
+            res: diff & ((1 << bits) - 1),
+            c: diff >= 0,
+            v: Some(!in_signed_range(signed_diff, bits)),
+        }
+    }
+
+    fn cmp_model(a: i32, b: i32, bits: u32) -> Expected {
+        Expected {
+            res: (a - b) & ((1 << bits) - 1),
+            c: a >= b,
+            v: None,
+        }
+    }
+
+    fn rol_model(a: i32, carry: bool, bits: u32) -> Expected {
+        let shifted = (a << 1) | carry as i32;
+        Expected {
+            res: shifted & ((1 << bits) - 1),
+            c: shifted >> bits != 0,
+            v: None,
+        }
+    }
+
+    fn ror_model(a: i32, carry: bool, bits: u32) -> Expected {
+        Expected {
+            res: (a >> 1) | ((carry as i32) << (bits - 1)),
+            c: a & 1 != 0,
+            v: None,
+        }
+    }
+
+    fn check(p: &RegisterP, expected: &Expected, bits: u32) -> Result<(), TestCaseError> {
+        prop_assert_eq!(p.C, expected.c, "C");
+        prop_assert_eq!(p.Z, expected.res == 0, "Z");
+        prop_assert_eq!(p.N, expected.res >> (bits - 1) != 0, "N");
+        prop_assert_eq!(p.V, expected.v.unwrap_or(true), "V");
+        Ok(())
+    }
+
+    #[duplicate_item(
+        width_mod   T;
+        [bits8]     [u8];
+        [bits16]    [u16];
+    )]
+    mod width_mod {
+        use super::*;
+
+        const BITS: u32 = T::BITS;
+
+        proptest! {
+            #[test]
+            fn adc_matches_model(a: T, b: T, carry: bool) {
+                let (mut acc, mut p) = (a, flags(carry));
+                crate::instrs::algorithms::adc(&mut acc, b, &mut p);
+
+                let expected = adc_model(a as i32, b as i32, carry, BITS);
+                prop_assert_eq!(acc as i32, expected.res);
+                check(&p, &expected, BITS)?;
+            }
+
+            #[test]
+            fn sbc_matches_model(a: T, b: T, carry: bool) {
+                let (mut acc, mut p) = (a, flags(carry));
+                crate::instrs::algorithms::sbc(&mut acc, b, &mut p);
+
+                let expected = sbc_model(a as i32, b as i32, carry, BITS);
+                prop_assert_eq!(acc as i32, expected.res);
+                check(&p, &expected, BITS)?;
+            }
+
+            #[test]
+            fn cmp_matches_model(a: T, b: T, carry: bool) {
+                let (mut acc, mut p) = (a, flags(carry));
+                crate::instrs::algorithms::cmp(&mut acc, b, &mut p);
+
+                prop_assert_eq!(acc, a, "A is left untouched");
+                check(&p, &cmp_model(a as i32, b as i32, BITS), BITS)?;
+            }
+
+            #[test]
+            fn rol_matches_model(a: T, carry: bool) {
+                let (mut op, mut p) = (a, flags(carry));
+                crate::instrs::algorithms::rol(&mut op, &mut p);
+
+                let expected = rol_model(a as i32, carry, BITS);
+                prop_assert_eq!(op as i32, expected.res);
+                check(&p, &expected, BITS)?;
+            }
+
+            #[test]
+            fn ror_matches_model(a: T, carry: bool) {
+                let (mut op, mut p) = (a, flags(carry));
+                crate::instrs::algorithms::ror(&mut op, &mut p);
+
+                let expected = ror_model(a as i32, carry, BITS);
+                prop_assert_eq!(op as i32, expected.res);
+                check(&p, &expected, BITS)?;
+            }
+        }
+    }
+}
@@ -108,7 +108,7 @@ duplicate! {
 // share the same cycle layout, and overall logic
 //
 // TRB and TSB are available for fewer addr modes, and also read the
-// accumulator, so we do some weird duplication for things to work
+// accumulator, which is passed as an extra argument of their algorithm
 duplicate! {
     [
         DUP_name    DUP_algo    DUP_addrmode            DUP_trb_tsb_arg;
@@ -142,39 +142,18 @@ duplicate! {
         [ror_d]     [ror]      [SET_ADDRMODE_DIRECT]    [];
         [ror_dx]    [ror]      [SET_ADDRMODE_DIRECTX]   [];
 
-        [tsb_abs]   [tsb]      [SET_ADDRMODE_ABS]       [_a, ];
-        [tsb_d]     [tsb]      [SET_ADDRMODE_DIRECT]    [_a, ];
+        [tsb_abs]   [tsb]      [SET_ADDRMODE_ABS]       [, cpu.registers.A];
+        [tsb_d]     [tsb]      [SET_ADDRMODE_DIRECT]    [, cpu.registers.A];
 
-        [trb_abs]   [trb]      [SET_ADDRMODE_ABS]       [_a, ];
-        [trb_d]     [trb]      [SET_ADDRMODE_DIRECT]    [_a, ];
+        [trb_abs]   [trb]      [SET_ADDRMODE_ABS]       [, cpu.registers.A];
+        [trb_d]     [trb]      [SET_ADDRMODE_DIRECT]    [, cpu.registers.A];
     ]
     cpu_instr!(DUP_name {
         meta SET_OP_SIZE AccMem;
+        meta SET_WRITE;
         meta DUP_addrmode;
 
-        meta IF_16 {
-            // copy the original addrbus to restore it in the 16-bit write
-            cpu.addr_bus2 = cpu.addr_bus;
-        };
-
-        meta FETCH_OP_INTO cpu.internal_data_bus;
-        meta LET_VARWIDTH _a = cpu.registers.A;
-        meta LET_VARWIDTH_MUT idb = cpu.internal_data_bus;
-
-        algorithms::DUP_algo(idb, DUP_trb_tsb_arg &mut cpu.registers.P);
-        meta END_CYCLE Internal;
-
-        meta IF_16 {
-            // if 16-bit, write the high byte first, at the current
-            // addrbus, which is where the high byte was read.
-            meta WRITE8 *cpu.internal_data_bus.hi();
-
-            // then reset addrbus to where the low byte was read
-            // so that it ends up being written at the correct addr
-            cpu.addr_bus = cpu.addr_bus2;
-        };
-
-        meta WRITE8 *cpu.internal_data_bus.lo();
+        meta RMW algorithms::DUP_algo DUP_trb_tsb_arg;
     });
 }
 
@@ -208,6 +187,7 @@ duplicate! {
 #[cfg(test)]
 mod tests {
     use crate::instrs::test_prelude::*;
+    use duplicate::duplicate_item;
 
     #[test]
     fn adc_imm8() {
@@ -369,7 +349,8 @@ mod tests {
         expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x89, "AAL");
         expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3458), 0x67, "AAH");
         expect_read_cycle(&mut cpu, snes_addr!(0:0x6789), 0x0f, "operand");
-        expect_internal_cycle(&mut cpu, "modify");
+        // emulation mode writes the unmodified operand back while modifying it
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x6789), 0x0f, "dummy write");
         expect_write_cycle(&mut cpu, snes_addr!(0:0x6789), 0x1e, "operand");
         expect_opcode_fetch_cycle(&mut cpu);
 
@@ -407,4 +388,457 @@ mod tests {
         expected_regs.P.C = false;
         assert_eq!(*cpu.regs(), expected_regs);
     }
+
+    // all RMW instructions on absolute operands, to check each opcode runs
+    // the right algorithm
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_result;
+        [asl_abs]   [0x0e]      [0x02];
+        [lsr_abs]   [0x4e]      [0x40];
+        [rol_abs]   [0x2e]      [0x02];
+        [ror_abs]   [0x6e]      [0x40];
+        [inc_abs]   [0xee]      [0x82];
+        [dec_abs]   [0xce]      [0x80];
+        [tsb_abs]   [0x0c]      [0x8f];
+        [trb_abs]   [0x1c]      [0x80];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false;
+        regs.P.M = true; // 8-bit operand
+        regs.A = 0x0f; // for TSB and TRB
+        regs.DB = 0xdb;
+
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x89, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3458), 0x67, "AAH");
+        expect_read_cycle(&mut cpu, snes_addr!(0xdb:0x6789), 0x81, "operand");
+        expect_internal_cycle(&mut cpu, "modify");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0x6789), DUP_result, "operand");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        assert_eq!(cpu.regs().PC, 0x3459);
+    }
+
+    // RMW instructions on direct operands
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_result;
+        [asl_d]     [0x06]      [0x02];
+        [lsr_d]     [0x46]      [0x40];
+        [rol_d]     [0x26]      [0x02];
+        [ror_d]     [0x66]      [0x40];
+        [inc_d]     [0xe6]      [0x82];
+        [dec_d]     [0xc6]      [0x80];
+        [tsb_d]     [0x04]      [0x8f];
+        [trb_d]     [0x14]      [0x80];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false;
+        regs.P.M = true; // 8-bit operand
+        regs.A = 0x0f; // for TSB and TRB
+        regs.D = 0x0500;
+
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x40, "direct offset");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0540), 0x81, "operand");
+        expect_internal_cycle(&mut cpu, "modify");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0540), DUP_result, "operand");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        assert_eq!(cpu.regs().PC, 0x3458);
+    }
+
+    // RMW instructions on direct X-indexed operands
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_result;
+        [asl_dx]    [0x16]      [0x02];
+        [lsr_dx]    [0x56]      [0x40];
+        [rol_dx]    [0x36]      [0x02];
+        [ror_dx]    [0x76]      [0x40];
+        [inc_dx]    [0xf6]      [0x82];
+        [dec_dx]    [0xd6]      [0x80];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false;
+        regs.P.M = true; // 8-bit operand
+        regs.D = 0x0500;
+        regs.X = 0x0004;
+
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x40, "direct offset");
+        expect_internal_cycle(&mut cpu, "indexing");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0544), 0x81, "operand");
+        expect_internal_cycle(&mut cpu, "modify");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0544), DUP_result, "operand");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        assert_eq!(cpu.regs().PC, 0x3458);
+    }
+
+    // RMW instructions on absolute X-indexed operands: as for stores,
+    // the indexing cycle is always spent
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_result;
+        [asl_absx]  [0x1e]      [0x02];
+        [lsr_absx]  [0x5e]      [0x40];
+        [rol_absx]  [0x3e]      [0x02];
+        [ror_absx]  [0x7e]      [0x40];
+        [inc_absx]  [0xfe]      [0x82];
+        [dec_absx]  [0xde]      [0x80];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false;
+        regs.P.M = true; // 8-bit operand
+        regs.P.X = true; // 8-bit index, no page crossing: loads wouldn't idle
+        regs.X = 0x04;
+        regs.DB = 0xdb;
+
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x89, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3458), 0x67, "AAH");
+        expect_internal_cycle(&mut cpu, "indexing");
+        expect_read_cycle(&mut cpu, snes_addr!(0xdb:0x678d), 0x81, "operand");
+        expect_internal_cycle(&mut cpu, "modify");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0x678d), DUP_result, "operand");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        assert_eq!(cpu.regs().PC, 0x3459);
+    }
+
+    #[test]
+    fn inc_absx16() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false;
+        regs.P.M = false;
+        regs.X = 0x0100;
+        regs.DB = 0xdb;
+
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0xfe);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x89, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3458), 0x67, "AAH");
+        expect_internal_cycle(&mut cpu, "indexing");
+        expect_read_cycle(&mut cpu, snes_addr!(0xdb:0x6889), 0xff, "operand lo");
+        expect_read_cycle(&mut cpu, snes_addr!(0xdb:0x688a), 0x12, "operand hi");
+        expect_internal_cycle(&mut cpu, "modify");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0x688a), 0x13, "operand hi");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0x6889), 0x00, "operand lo");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        assert_eq!(cpu.regs().PC, 0x3459);
+    }
+
+    // the dummy write of emulation mode on every RMW instruction
+    #[duplicate_item(
+        DUP_name        DUP_opcode  DUP_result;
+        [asl_d_emu]     [0x06]      [0x02];
+        [lsr_d_emu]     [0x46]      [0x40];
+        [rol_d_emu]     [0x26]      [0x02];
+        [ror_d_emu]     [0x66]      [0x40];
+        [inc_d_emu]     [0xe6]      [0x82];
+        [dec_d_emu]     [0xc6]      [0x80];
+        [tsb_d_emu]     [0x04]      [0x8f];
+        [trb_d_emu]     [0x14]      [0x80];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true;
+        regs.A = 0x0f; // for TSB and TRB
+        regs.D = 0x0500;
+
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x40, "direct offset");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0540), 0x81, "operand");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0540), 0x81, "dummy write");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0540), DUP_result, "operand");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        assert_eq!(cpu.regs().PC, 0x3458);
+    }
+
+    // TSB and TRB only use the low byte of A with an 8-bit operand
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_result  DUP_z;
+        [tsb_d8]    [0x04]      [0x8f]      [false];
+        [trb_d8]    [0x14]      [0x80]      [false];
+        [tsb_d8_z]  [0x04]      [0x7f]      [true];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false;
+        regs.P.M = true;
+        regs.A = 0x800f;
+
+        let mut cpu = CPU::new(regs);
+        let operand = if DUP_z { 0x70 } else { 0x81 };
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x40, "direct offset");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0040), operand, "operand");
+        expect_internal_cycle(&mut cpu, "modify");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0040), DUP_result, "operand");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        // Z is set from A & operand, before the modification
+        assert_eq!(cpu.regs().P.Z, DUP_z);
+    }
+
+    // Memory for the tests below which only check the result of instructions,
+    // for each addressing mode: operand bytes after the opcode at $00:8000,
+    // pointers, and the value $3c at the effective address
+    // (with D = $1000, DB = $12, X = 2, Y = 3, S = $01f0)
+    const MEM_IMM: &[(SnesAddress, u8)] = &[(snes_addr!(0:0x8001), 0x3c)];
+    const MEM_D: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x10),
+        (snes_addr!(0:0x1010), 0x3c),
+    ];
+    const MEM_DX: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x10),
+        (snes_addr!(0:0x1012), 0x3c),
+    ];
+    const MEM_DXIND: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x10),
+        (snes_addr!(0:0x1012), 0x56),
+        (snes_addr!(0:0x1013), 0x34),
+        (snes_addr!(0x12:0x3456), 0x3c),
+    ];
+    const MEM_DIND: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x10),
+        (snes_addr!(0:0x1010), 0x56),
+        (snes_addr!(0:0x1011), 0x34),
+        (snes_addr!(0x12:0x3456), 0x3c),
+    ];
+    const MEM_DINDY: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x10),
+        (snes_addr!(0:0x1010), 0x56),
+        (snes_addr!(0:0x1011), 0x34),
+        (snes_addr!(0x12:0x3459), 0x3c),
+    ];
+    const MEM_DINDL: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x10),
+        (snes_addr!(0:0x1010), 0x56),
+        (snes_addr!(0:0x1011), 0x34),
+        (snes_addr!(0:0x1012), 0x7f),
+        (snes_addr!(0x7f:0x3456), 0x3c),
+    ];
+    const MEM_DINDLY: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x10),
+        (snes_addr!(0:0x1010), 0x56),
+        (snes_addr!(0:0x1011), 0x34),
+        (snes_addr!(0:0x1012), 0x7f),
+        (snes_addr!(0x7f:0x3459), 0x3c),
+    ];
+    const MEM_ABS: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x56),
+        (snes_addr!(0:0x8002), 0x34),
+        (snes_addr!(0x12:0x3456), 0x3c),
+    ];
+    const MEM_ABSX: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x56),
+        (snes_addr!(0:0x8002), 0x34),
+        (snes_addr!(0x12:0x3458), 0x3c),
+    ];
+    const MEM_ABSY: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x56),
+        (snes_addr!(0:0x8002), 0x34),
+        (snes_addr!(0x12:0x3459), 0x3c),
+    ];
+    const MEM_ABSL: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x56),
+        (snes_addr!(0:0x8002), 0x34),
+        (snes_addr!(0:0x8003), 0x7f),
+        (snes_addr!(0x7f:0x3456), 0x3c),
+    ];
+    const MEM_ABSLX: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x56),
+        (snes_addr!(0:0x8002), 0x34),
+        (snes_addr!(0:0x8003), 0x7f),
+        (snes_addr!(0x7f:0x3458), 0x3c),
+    ];
+    const MEM_SR: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x05),
+        (snes_addr!(0:0x01f5), 0x3c),
+    ];
+    const MEM_SRY: &[(SnesAddress, u8)] = &[
+        (snes_addr!(0:0x8001), 0x05),
+        (snes_addr!(0:0x01f5), 0x56),
+        (snes_addr!(0:0x01f6), 0x34),
+        (snes_addr!(0x12:0x3459), 0x3c),
+    ];
+
+    fn regs_8bit() -> Registers {
+        let mut regs = Registers::default();
+        regs.PB = 0;
+        regs.PC = 0x8000;
+        regs.P.M = true;
+        regs.P.X = true;
+        regs.D = 0x1000;
+        regs.DB = 0x12;
+        regs.X = 2;
+        regs.Y = 3;
+        regs.S = 0x01f0;
+        regs
+    }
+
+    // all 6 ALU instructions in all of their addressing modes, with an 8-bit
+    // accumulator, to check each opcode runs the right operation on the right
+    // operand (reading 0 from another address gives another result)
+    #[duplicate_item(
+        DUP1_mod    DUP1_opcode DUP1_result DUP1_carry;
+        [ora_modes] [DUP2_ora]  [0x3f]      [true];
+        [and_modes] [DUP2_and]  [0x0c]      [true];
+        [eor_modes] [DUP2_eor]  [0x33]      [true];
+        [adc_modes] [DUP2_adc]  [0x4c]      [false];
+        [cmp_modes] [DUP2_cmp]  [0x0f]      [false];
+        [sbc_modes] [DUP2_sbc]  [0xd3]      [false];
+    )]
+    mod DUP1_mod {
+        use super::*;
+
+        #[duplicate_item(
+            DUP2_name   DUP2_memory DUP2_ora DUP2_and DUP2_eor DUP2_adc DUP2_cmp DUP2_sbc;
+            [dxind]     [MEM_DXIND] [0x01]   [0x21]   [0x41]   [0x61]   [0xc1]   [0xe1];
+            [sr]        [MEM_SR]    [0x03]   [0x23]   [0x43]   [0x63]   [0xc3]   [0xe3];
+            [d]         [MEM_D]     [0x05]   [0x25]   [0x45]   [0x65]   [0xc5]   [0xe5];
+            [dindl]     [MEM_DINDL] [0x07]   [0x27]   [0x47]   [0x67]   [0xc7]   [0xe7];
+            [imm]       [MEM_IMM]   [0x09]   [0x29]   [0x49]   [0x69]   [0xc9]   [0xe9];
+            [abs]       [MEM_ABS]   [0x0d]   [0x2d]   [0x4d]   [0x6d]   [0xcd]   [0xed];
+            [absl]      [MEM_ABSL]  [0x0f]   [0x2f]   [0x4f]   [0x6f]   [0xcf]   [0xef];
+            [dindy]     [MEM_DINDY] [0x11]   [0x31]   [0x51]   [0x71]   [0xd1]   [0xf1];
+            [dind]      [MEM_DIND]  [0x12]   [0x32]   [0x52]   [0x72]   [0xd2]   [0xf2];
+            [sry]       [MEM_SRY]   [0x13]   [0x33]   [0x53]   [0x73]   [0xd3]   [0xf3];
+            [dx]        [MEM_DX]    [0x15]   [0x35]   [0x55]   [0x75]   [0xd5]   [0xf5];
+            [dindly]    [MEM_DINDLY][0x17]   [0x37]   [0x57]   [0x77]   [0xd7]   [0xf7];
+            [absy]      [MEM_ABSY]  [0x19]   [0x39]   [0x59]   [0x79]   [0xd9]   [0xf9];
+            [absx]      [MEM_ABSX]  [0x1d]   [0x3d]   [0x5d]   [0x7d]   [0xdd]   [0xfd];
+            [abslx]     [MEM_ABSLX] [0x1f]   [0x3f]   [0x5f]   [0x7f]   [0xdf]   [0xff];
+        )]
+        #[test]
+        fn DUP2_name() {
+            let mut regs = regs_8bit();
+            regs.A = 0x0f;
+            regs.P.C = true;
+            let mut cpu = CPU::new(regs);
+
+            expect_opcode_fetch(&mut cpu, DUP1_opcode);
+            run_instr(&mut cpu, DUP2_memory);
+
+            assert_eq!(cpu.regs().A, DUP1_result);
+            assert_eq!(cpu.regs().P.C, DUP1_carry);
+        }
+    }
+
+    // BIT in its indexed and direct modes: only Z depends on A
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_memory;
+        [bit_d]     [0x24]      [MEM_D];
+        [bit_dx]    [0x34]      [MEM_DX];
+        [bit_absx]  [0x3c]      [MEM_ABSX];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = regs_8bit();
+        regs.A = 0x04;
+        regs.P.Z = true;
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        run_instr(&mut cpu, DUP_memory);
+
+        assert_eq!(cpu.regs().P.Z, false);
+        assert_eq!(cpu.regs().P.N, false);
+        assert_eq!(cpu.regs().P.V, false);
+    }
+
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_reg DUP_memory;
+        [cpx_imm]   [0xe0]      [X]     [MEM_IMM];
+        [cpx_d]     [0xe4]      [X]     [MEM_D];
+        [cpx_abs]   [0xec]      [X]     [MEM_ABS];
+        [cpy_imm]   [0xc0]      [Y]     [MEM_IMM];
+        [cpy_d]     [0xc4]      [Y]     [MEM_D];
+        [cpy_abs]   [0xcc]      [Y]     [MEM_ABS];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = regs_8bit();
+        regs.DUP_reg = 0x3c;
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        run_instr(&mut cpu, DUP_memory);
+
+        assert_eq!(cpu.regs().P.Z, true);
+        assert_eq!(cpu.regs().P.C, true);
+        assert_eq!(cpu.regs().DUP_reg, 0x3c, "the register is only compared");
+    }
+
+    // RMW instructions on registers, in 8-bit mode
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_reg DUP_result  DUP_carry;
+        [inc_acc]   [0x1a]      [A]     [0x42]      [true];
+        [dec_acc]   [0x3a]      [A]     [0x40]      [true];
+        [rol_acc]   [0x2a]      [A]     [0x83]      [false];
+        [lsr_acc]   [0x4a]      [A]     [0x20]      [true];
+        [ror_acc]   [0x6a]      [A]     [0xa0]      [true];
+        [iny]       [0xc8]      [Y]     [0x42]      [true];
+        [dex]       [0xca]      [X]     [0x40]      [true];
+        [dey]       [0x88]      [Y]     [0x40]      [true];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true;
+        regs.P.C = true;
+        regs.DUP_reg = 0x41;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_internal_cycle(&mut cpu, "modify");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3457;
+        expected_regs.DUP_reg = DUP_result;
+        expected_regs.P.C = DUP_carry;
+        expected_regs.P.N = DUP_result & 0x80 != 0;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
 }
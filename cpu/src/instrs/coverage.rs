@@ -0,0 +1,297 @@
+//! Opcode coverage of the instruction tests
+//!
+//! Walks the opcode table in `instr_tab.rs` and the test modules of the
+//! instructions to find which opcodes are implemented, and which of those
+//! are run by at least one test (an opcode is considered tested when it is
+//! fed to `expect_opcode_fetch`, directly or through a `duplicate_item`
+//! column).
+//!
+//! Run `cargo test -p cpu coverage -- --nocapture` to print the coverage
+//! matrix of mnemonics by addressing mode.
+
+use crate::disasm::{AddrMode, Instruction, RegisterWidths};
+
+/// Sources of the modules which may contain instruction tests
+const TEST_SOURCES: [&str; 10] = [
+    include_str!("arithmetic.rs"),
+    include_str!("branches.rs"),
+    include_str!("flags.rs"),
+    include_str!("jumps.rs"),
+    include_str!("loads.rs"),
+    include_str!("stack.rs"),
+    include_str!("stores.rs"),
+    include_str!("transfers.rs"),
+    include_str!("uncategorised.rs"),
+    include_str!("../cpu.rs"),
+];
+
+/// Addressing modes in the order of the matrix columns, with their label
+const MODES: [(AddrMode, &str); 26] = [
+    (AddrMode::Implied, "imp"),
+    (AddrMode::Accumulator, "acc"),
+    (AddrMode::ImmediateM, "#m"),
+    (AddrMode::ImmediateX, "#x"),
+    (AddrMode::Immediate8, "#8"),
+    (AddrMode::Direct, "d"),
+    (AddrMode::DirectX, "d,x"),
+    (AddrMode::DirectY, "d,y"),
+    (AddrMode::DirectIndirect, "(d)"),
+    (AddrMode::DirectIndirectLong, "[d]"),
+    (AddrMode::DirectXIndirect, "(d,x)"),
+    (AddrMode::DirectIndirectY, "(d),y"),
+    (AddrMode::DirectIndirectLongY, "[d],y"),
+    (AddrMode::Absolute, "a"),
+    (AddrMode::AbsoluteX, "a,x"),
+    (AddrMode::AbsoluteY, "a,y"),
+    (AddrMode::AbsoluteLong, "al"),
+    (AddrMode::AbsoluteLongX, "al,x"),
+    (AddrMode::AbsoluteIndirect, "(a)"),
+    (AddrMode::AbsoluteIndirectLong, "[a]"),
+    (AddrMode::AbsoluteXIndirect, "(a,x)"),
+    (AddrMode::StackRelative, "sr"),
+    (AddrMode::StackRelativeIndirectY, "(sr),y"),
+    (AddrMode::Relative, "r"),
+    (AddrMode::RelativeLong, "rl"),
+    (AddrMode::BlockMove, "xyc"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Missing,
+    Untested,
+    Tested,
+}
+
+impl Status {
+    fn symbol(self) -> char {
+        match self {
+            Status::Missing => '-',
+            Status::Untested => '!',
+            Status::Tested => '+',
+        }
+    }
+}
+
+fn parse_opcode(text: &str) -> Option<u8> {
+    u8::from_str_radix(text.trim().strip_prefix("0x")?, 16).ok()
+}
+
+/// Opcodes whose entry in the opcode table is an actual instruction
+fn implemented_opcodes() -> [bool; 256] {
+    let mut implemented = [false; 256];
+    let mut entries = 0;
+
+    for line in include_str!("instr_tab.rs").lines() {
+        let Some(entry) = line.trim().strip_prefix("/* ") else {
+            continue;
+        };
+        let Some((opcode, cycle)) = entry.split_once(" */") else {
+            continue;
+        };
+        let opcode = u8::from_str_radix(opcode, 16).expect("opcode table comment");
+        implemented[opcode as usize] = !cycle.contains("unimplemented_opcode!");
+        entries += 1;
+    }
+
+    assert_eq!(entries, 256, "Every opcode should have an entry in the opcode table");
+    implemented
+}
+
+/// Values of the `column` column in the `duplicate_item` tables of `source`
+fn duplicate_column(source: &str, column: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut lines = source.lines();
+
+    while let Some(line) = lines.next() {
+        if !line.contains("duplicate_item(") {
+            continue;
+        }
+        // rows and header may be preceded by comments
+        let mut table = lines
+            .by_ref()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("//"));
+        let Some(header) = table.next() else { break };
+        let Some(index) = header
+            .trim_end_matches(';')
+            .split_whitespace()
+            .position(|name| name == column)
+        else {
+            continue;
+        };
+
+        for row in table {
+            if !row.starts_with('[') {
+                break;
+            }
+            let cell = row
+                .split('[')
+                .skip(1)
+                .nth(index)
+                .and_then(|cell| cell.split(']').next());
+            values.extend(cell.map(str::to_owned));
+        }
+    }
+    values
+}
+
+/// Opcodes designated by `arg`: an hexadecimal literal, or a column of a
+/// `duplicate_item` table in `source`, whose values may themselves be
+/// columns of a nested table
+fn resolve_opcodes(source: &str, arg: &str, depth: u32) -> Vec<u8> {
+    match parse_opcode(arg) {
+        Some(opcode) => vec![opcode],
+        None if depth == 0 => vec![],
+        None => duplicate_column(source, arg.trim())
+            .iter()
+            .flat_map(|value| resolve_opcodes(source, value, depth - 1))
+            .collect(),
+    }
+}
+
+/// Opcodes fetched by at least one test
+fn tested_opcodes() -> [bool; 256] {
+    let mut tested = [false; 256];
+
+    for source in TEST_SOURCES {
+        for call in source.split("expect_opcode_fetch(").skip(1) {
+            let Some(arg) = call
+                .split(')')
+                .next()
+                .and_then(|args| args.split(',').nth(1))
+            else {
+                continue;
+            };
+
+            for opcode in resolve_opcodes(source, arg, 3) {
+                tested[opcode as usize] = true;
+            }
+        }
+    }
+    tested
+}
+
+fn decode(opcode: u8) -> Instruction {
+    Instruction::decode(0, &[opcode, 0, 0, 0], RegisterWidths::default())
+        .expect("every opcode can be decoded")
+}
+
+fn statuses() -> [Status; 256] {
+    let implemented = implemented_opcodes();
+    let tested = tested_opcodes();
+    core::array::from_fn(|opcode| match (implemented[opcode], tested[opcode]) {
+        (false, _) => Status::Missing,
+        (true, false) => Status::Untested,
+        (true, true) => Status::Tested,
+    })
+}
+
+/// Mnemonics by addressing modes, each cell being `+` (tested), `!`
+/// (implemented but untested), `-` (not implemented) or empty (no such
+/// instruction)
+fn coverage_matrix(statuses: &[Status; 256]) -> String {
+    let mut mnemonics: Vec<&str> = (0..=255).map(|opcode| decode(opcode).mnemonic()).collect();
+    mnemonics.sort_unstable();
+    mnemonics.dedup();
+
+    let mut matrix = format!("{:<5}", "");
+    for (_, label) in MODES {
+        matrix += &format!("{label:>7}");
+    }
+    matrix.push('\n');
+
+    for mnemonic in mnemonics {
+        matrix += &format!("{mnemonic:<5}");
+        for (mode, _) in MODES {
+            let cell = (0..=255u8)
+                .filter(|&opcode| {
+                    let instr = decode(opcode);
+                    instr.mnemonic() == mnemonic && instr.mode == mode
+                })
+                .map(|opcode| statuses[opcode as usize].symbol())
+                .collect::<String>();
+            matrix += &format!("{cell:>7}");
+        }
+        matrix.push('\n');
+    }
+
+    let count = |status| statuses.iter().filter(|&&s| s == status).count();
+    matrix += &format!(
+        "{} tested, {} untested, {} not implemented\n",
+        count(Status::Tested),
+        count(Status::Untested),
+        count(Status::Missing),
+    );
+    matrix
+}
+
+/// Opcodes the CPU doesn't implement yet: implementing one must remove it
+/// from this list
+const MISSING_OPCODES: [u8; 4] = [
+    0x00, // BRK
+    0x02, // COP
+    0x40, // RTI
+    0xcb, // WAI
+];
+
+#[test]
+fn coverage_report() {
+    let statuses = statuses();
+    println!("{}", coverage_matrix(&statuses));
+
+    let missing: Vec<u8> = (0..=255u8)
+        .filter(|&opcode| statuses[opcode as usize] == Status::Missing)
+        .collect();
+    assert_eq!(missing, MISSING_OPCODES);
+    assert!(coverage_matrix(&statuses).ends_with("252 tested, 0 untested, 4 not implemented\n"));
+}
+
+#[test]
+fn implemented_opcodes_are_tested() {
+    let untested: Vec<String> = statuses()
+        .iter()
+        .enumerate()
+        .filter(|(_, status)| **status == Status::Untested)
+        .map(|(opcode, _)| {
+            let instr = decode(opcode as u8);
+            format!("{opcode:#04x} ({} {:?})", instr.mnemonic(), instr.mode)
+        })
+        .collect();
+
+    assert!(
+        untested.is_empty(),
+        "Implemented opcodes without a test: {}",
+        untested.join(", "),
+    );
+}
+
+#[test]
+fn test_references_are_found() {
+    let source = "
+        expect_opcode_fetch(&mut cpu, 0xea);
+
+        #[duplicate_item(
+            // comment
+            DUP1_name   DUP1_opcode;
+            [ora]       [DUP2_ora];
+            [and]       [DUP2_and];
+        )]
+        mod DUP1_name {
+            #[duplicate_item(
+                DUP2_name   DUP2_ora    DUP2_and;
+                [d]         [0x05]      [0x25];
+
+                [abs]       [0x0d]      [0x2d];
+            )]
+            fn DUP2_name() {
+                expect_opcode_fetch(&mut cpu, DUP1_opcode);
+            }
+        }
+    ";
+
+    assert_eq!(resolve_opcodes(source, " 0xea", 3), [0xea]);
+    assert_eq!(resolve_opcodes(source, " DUP2_ora", 3), [0x05, 0x0d]);
+    assert_eq!(resolve_opcodes(source, " DUP1_opcode", 3), [0x05, 0x0d, 0x25, 0x2d]);
+    assert_eq!(resolve_opcodes(source, " DUP1_opcode", 1), []);
+    assert_eq!(resolve_opcodes(source, " unknown", 3), []);
+}
@@ -1,4 +1,4 @@
-use crate::cpu::{CPU, CycleResult};
+use crate::cpu::{CPU, CycleResult, Halt, OpcodePolicy, UnhandledOpcode};
 use common::snes_address::SnesAddress;
 
 use crate::instrs::{
@@ -26,25 +26,78 @@ pub(crate) fn opcode_fetch(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
         bank: cpu.registers.PB,
         addr: cpu.registers.PC,
     };
+    cpu.opcode_fetched = true;
 
-    (
-        CycleResult::Read,
-        InstrCycle(|next_cyc_cpu| (INSTR_CYC1[next_cyc_cpu.data_bus as usize].0)(next_cyc_cpu)),
-    )
+    #[cfg(not(feature = "jump-table-dispatch"))]
+    return (CycleResult::Read, InstrCycle(decode));
+
+    // The next cycle is decoded by CPU::cycle itself, `next_cycle` is unused
+    #[cfg(feature = "jump-table-dispatch")]
+    {
+        cpu.decode_pending = true;
+        (CycleResult::Read, InstrCycle(opcode_fetch))
+    }
+}
+
+/// Runs the first cycle of the instruction whose opcode was just fetched
+/// in the data bus. Only inlined for the jump table: the function is otherwise compared
+/// by address, which inlining could duplicate.
+#[cfg_attr(feature = "jump-table-dispatch", inline(always))]
+pub(crate) fn decode(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
+    (INSTR_CYC1[cpu.data_bus as usize].0)(cpu)
+}
+
+/// Cycle run by the CPU while it is halted (see [`Halt`])
+pub(crate) fn halted(_cpu: &mut CPU) -> (CycleResult, InstrCycle) {
+    (CycleResult::Internal, InstrCycle(halted))
+}
+
+/// First cycle of WDM and unimplemented opcodes, which reports the opcode
+/// and applies the [`OpcodePolicy`].
+///
+/// Returns `None` when the instruction should be skipped.
+fn unhandled_opcode(cpu: &mut CPU, len: u16) -> Option<(CycleResult, InstrCycle)> {
+    let unhandled = UnhandledOpcode {
+        opcode: cpu.data_bus,
+        addr: SnesAddress {
+            bank: cpu.registers.PB,
+            addr: cpu.registers.PC,
+        },
+        len,
+    };
+    cpu.unhandled_opcode = Some(unhandled);
+
+    match cpu.opcode_policy {
+        OpcodePolicy::Nop => None,
+        OpcodePolicy::Trap => {
+            cpu.halt = Some(Halt::Trap(unhandled));
+            Some((CycleResult::Internal, InstrCycle(halted)))
+        }
+    }
 }
 
-macro_rules! todo_opcode {
-    ($oc:tt) => {
-        |_| {
-            todo!("opcode {:#2x} not yet implemented!", $oc);
+/// Opcodes which are not implemented yet: NOPs of `$len` bytes taking one
+/// internal cycle, unless the CPU traps on them
+macro_rules! unimplemented_opcode {
+    ($len:expr) => {
+        |cpu| {
+            unhandled_opcode(cpu, $len).unwrap_or_else(|| {
+                cpu.registers.PC = cpu.registers.PC.wrapping_add($len);
+                (CycleResult::Internal, InstrCycle(opcode_fetch))
+            })
         }
     }
 }
 
+/// WDM is a 2-byte NOP, reported like unimplemented opcodes
+fn wdm(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
+    unhandled_opcode(cpu, 2).unwrap_or_else(|| wdm_cyc1(cpu))
+}
+
 const INSTR_CYC1: [InstrCycle; 256] = [
-    /* 00 */ InstrCycle(todo_opcode!(0x00)),
+    /* 00 */ InstrCycle(unimplemented_opcode!(2)),
     /* 01 */ InstrCycle(ora::dxind_cyc1),
-    /* 02 */ InstrCycle(todo_opcode!(0x02)),
+    /* 02 */ InstrCycle(unimplemented_opcode!(2)),
     /* 03 */ InstrCycle(ora::sr_cyc1),
     /* 04 */ InstrCycle(tsb_d_cyc1),
     /* 05 */ InstrCycle(ora::d_cyc1),
@@ -106,9 +159,9 @@ const INSTR_CYC1: [InstrCycle; 256] = [
     /* 3d */ InstrCycle(and::absx_cyc1),
     /* 3e */ InstrCycle(rol_absx_cyc1),
     /* 3f */ InstrCycle(and::abslx_cyc1),
-    /* 40 */ InstrCycle(todo_opcode!(0x40)),
+    /* 40 */ InstrCycle(unimplemented_opcode!(1)),
     /* 41 */ InstrCycle(eor::dxind_cyc1),
-    /* 42 */ InstrCycle(wdm_cyc1),
+    /* 42 */ InstrCycle(wdm),
     /* 43 */ InstrCycle(eor::sr_cyc1),
     /* 44 */ InstrCycle(mvp_cyc1),
     /* 45 */ InstrCycle(eor::d_cyc1),
@@ -120,7 +173,7 @@ const INSTR_CYC1: [InstrCycle; 256] = [
     /* 4b */ InstrCycle(phk_cyc1),
     /* 4c */ InstrCycle(jmp_abs_cyc1),
     /* 4d */ InstrCycle(eor::abs_cyc1),
-    /* 4e */ InstrCycle(lsr_d_cyc1),
+    /* 4e */ InstrCycle(lsr_abs_cyc1),
     /* 4f */ InstrCycle(eor::absl_cyc1),
     /* 50 */ InstrCycle(bvc_cyc1),
     /* 51 */ InstrCycle(eor::dindy_cyc1),
@@ -245,7 +298,7 @@ const INSTR_CYC1: [InstrCycle; 256] = [
     /* c8 */ InstrCycle(iny_cyc1),
     /* c9 */ InstrCycle(cmp::imm_cyc1),
     /* ca */ InstrCycle(dex_cyc1),
-    /* cb */ InstrCycle(todo_opcode!(0xcb)),
+    /* cb */ InstrCycle(unimplemented_opcode!(1)),
     /* cc */ InstrCycle(cpy_abs_cyc1),
     /* cd */ InstrCycle(cmp::abs_cyc1),
     /* ce */ InstrCycle(dec_abs_cyc1),
@@ -261,7 +314,7 @@ const INSTR_CYC1: [InstrCycle; 256] = [
     /* d8 */ InstrCycle(cld_cyc1),
     /* d9 */ InstrCycle(cmp::absy_cyc1),
     /* da */ InstrCycle(phx_cyc1),
-    /* db */ InstrCycle(todo_opcode!(0xdb)),
+    /* db */ InstrCycle(stp_cyc1),
     /* dc */ InstrCycle(jml_cyc1),
     /* dd */ InstrCycle(cmp::absx_cyc1),
     /* de */ InstrCycle(dec_absx_cyc1),
@@ -553,6 +553,104 @@ mod tests {
         }
     }
 
+    // in emulation mode, direct indexing wraps within the direct page when DL == 0
+    #[duplicate_item(
+        DUP_name            DUP_E       DUP_D       DUP_idle    DUP_addr;
+        [lda_dx_emu_wrap]   [true]      [0x0500]    [false]     [0x0510];
+        [lda_dx_emu_dl]     [true]      [0x0510]    [true]      [0x0620]; // no wrap when DL != 0
+        [lda_dx_native]     [false]     [0x0500]    [false]     [0x0610]; // no wrap in native mode
+        [lda_dx_native_dl]  [false]     [0x0510]    [true]      [0x0620];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = DUP_E;
+        regs.P.M = true; // 8-bit loads in both modes
+        regs.P.X = true;
+        regs.A = 0x9999; // only the low byte will be overwritten
+        regs.X = 0x20;
+        regs.D = DUP_D;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0xb5);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0xf0, "direct offset");
+        if DUP_idle {
+            expect_internal_cycle(&mut cpu, "idle when DL != 0");
+        }
+        expect_internal_cycle(&mut cpu, "indexing");
+        expect_read_cycle(&mut cpu, snes_addr!(0:DUP_addr), 0x42, "8-bit load");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.A = 0x9942;
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // the indirect address is also read within the direct page
+    #[duplicate_item(
+        DUP_name                DUP_opcode  DUP_x   DUP_offset  DUP_indexed;
+        [lda_dxind_emu_wrap]    [0xa1]      [0x0f]  [0xf0]      [true];
+        [lda_dind_emu_wrap]     [0xb2]      [0]     [0xff]      [false];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true;
+        regs.A = 0x9999; // only the low byte will be overwritten
+        regs.X = DUP_x;
+        regs.D = 0x0500;
+        regs.DB = 0xee;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), DUP_offset, "direct offset");
+        if DUP_indexed {
+            expect_internal_cycle(&mut cpu, "indexing");
+        }
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x05ff), 0x88, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0500), 0x77, "AAH, wrapped");
+        expect_read_cycle(&mut cpu, snes_addr!(0xee:0x7788), 0x42, "8-bit load");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.A = 0x9942;
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // direct indirect long is not a 6502 addressing mode: it doesn't wrap
+    #[test]
+    fn lda_dindl_emu_no_wrap() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true;
+        regs.A = 0x9999; // only the low byte will be overwritten
+        regs.D = 0x0500;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0xa7);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0xff, "direct offset");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x05ff), 0x88, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0600), 0x77, "AAH");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0601), 0x66, "AAB");
+        expect_read_cycle(&mut cpu, snes_addr!(0x66:0x7788), 0x42, "8-bit load");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.A = 0x9942;
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
     // stack relative only exists for LDA
     #[test]
     fn lda_sr() {
@@ -609,4 +707,122 @@ mod tests {
 
         assert_eq!(*cpu.regs(), expected_regs);
     }
+
+    // 16-bit operands at $xx:FFFF: data addresses carry into the next bank,
+    // direct page and program addresses wrap within their bank
+    #[duplicate_item(
+        DUP_name                DUP_opcode  DUP_operand             DUP_low             DUP_high;
+        [lda_abs_bank_cross]    [0xad]      [[0xff, 0xff]]          [0x7e:0xffff]       [0x7f:0x0000];
+        [lda_absl_bank_cross]   [0xaf]      [[0xff, 0xff, 0x12]]    [0x12:0xffff]       [0x13:0x0000];
+        [lda_absx_bank_cross]   [0xbd]      [[0xf0, 0xff]]          [0x7f:0x0001]       [0x7f:0x0002];
+        [lda_d_bank_wrap]       [0xa5]      [[0xff]]                [0:0xffff]          [0:0x0000];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false; // non-emu mode for 16-bit instr
+        regs.P.X = false;
+        regs.P.M = false;
+        regs.A = 0x9999; // value which will be overwritten
+        regs.X = 0x0011;
+        regs.DB = 0x7e;
+        regs.D = 0xff00;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        let operand: &[u8] = &DUP_operand;
+        for (i, byte) in operand.iter().enumerate() {
+            expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457 + i as u16), *byte, "operand");
+        }
+        if DUP_opcode == 0xbd {
+            expect_internal_cycle(&mut cpu, "indexing");
+        }
+        expect_read_cycle(&mut cpu, snes_addr!(DUP_low), 0x44, "value low");
+        expect_read_cycle(&mut cpu, snes_addr!(DUP_high), 0x33, "value high");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.A = 0x3344;
+        expected_regs.PC = 0x3457 + operand.len() as u16;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // long indexing carries into the bank
+    #[test]
+    fn lda_abslx_bank_cross() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false;
+        regs.P.X = false;
+        regs.P.M = false;
+        regs.X = 0x0102;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0xbf);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x00, "address low");
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3458), 0xff, "address high");
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3459), 0xee, "address bank");
+        expect_load16_read(&mut cpu, snes_addr!(0xef:0x0002), 0x3344);
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.A = 0x3344;
+        expected_regs.PC = 0x345a;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // (d),y indexing of the indirect address carries into the bank after DB
+    #[test]
+    fn lda_dindy_bank_cross() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false;
+        regs.P.X = false;
+        regs.P.M = false;
+        regs.DB = 0x7e;
+        regs.Y = 0x0010;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0xb1);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x20, "direct offset");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0020), 0xf8, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0021), 0xff, "AAH");
+        expect_internal_cycle(&mut cpu, "indexing");
+        expect_load16_read(&mut cpu, snes_addr!(0x7f:0x0008), 0x3344);
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.A = 0x3344;
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // immediate operands wrap within the program bank
+    #[test]
+    fn lda_imm_bank_wrap() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0xfffe;
+        regs.E = false;
+        regs.P.M = false;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0xa9);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0xffff), 0x44, "immediate low");
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x0000), 0x33, "immediate high");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.A = 0x3344;
+        expected_regs.PC = 0x0001;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
 }
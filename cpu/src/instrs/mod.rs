@@ -3,6 +3,8 @@ pub(crate) mod instr_tab;
 pub(crate) mod prelude;
 #[cfg(test)]
 pub(crate) mod test_prelude;
+#[cfg(test)]
+mod coverage;
 
 mod arithmetic;
 mod algorithms;
@@ -129,19 +129,25 @@ cpu_instr!(pei {
 #[cfg(test)]
 mod tests {
     use super::super::test_prelude::*;
-
-    // we only test pha for pha, phx, and phy since they are duplicated
+    use duplicate::duplicate_item;
+
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_reg;
+        [pha]       [0x48]      [A];
+        [phx]       [0xda]      [X];
+        [phy]       [0x5a]      [Y];
+    )]
     #[test]
-    fn pha() {
+    fn DUP_name() {
         let mut regs = Registers::default();
-        regs.A = 0x5566;
+        regs.DUP_reg = 0x5566;
         regs.S = 0x0477;
         regs.PC = 0;
         regs.PB = 0;
         let mut expected_regs = regs;
         let mut cpu = CPU::new(regs);
 
-        expect_opcode_fetch(&mut cpu, 0x48);
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
         expect_internal_cycle(&mut cpu, "stack alignment");
         expect_write_cycle(&mut cpu, snes_addr!(0:0x0477), 0x55, "push hi");
         expect_write_cycle(&mut cpu, snes_addr!(0:0x0476), 0x66, "push lo");
@@ -152,9 +158,14 @@ mod tests {
         assert_eq!(*cpu.regs(), expected_regs);
     }
 
-    // we only test pla for pla, plx, and ply since they are duplicated
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_reg;
+        [pla]       [0x68]      [A];
+        [plx]       [0xfa]      [X];
+        [ply]       [0x7a]      [Y];
+    )]
     #[test]
-    fn pla() {
+    fn DUP_name() {
         let mut regs = Registers::default();
         regs.S = 0x0475;
         regs.PC = 0;
@@ -162,7 +173,7 @@ mod tests {
         let mut expected_regs = regs;
         let mut cpu = CPU::new(regs);
 
-        expect_opcode_fetch(&mut cpu, 0x68);
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
         expect_internal_cycle(&mut cpu, "stack alignment (1)");
         expect_internal_cycle(&mut cpu, "stack alignment (2)");
         expect_read_cycle(&mut cpu, snes_addr!(0:0x0476), 0x66, "pull lo");
@@ -170,7 +181,7 @@ mod tests {
         expect_opcode_fetch_cycle(&mut cpu);
 
         expected_regs.PC = 1;
-        expected_regs.A = 0x5566;
+        expected_regs.DUP_reg = 0x5566;
         expected_regs.S = 0x0477;
         assert_eq!(*cpu.regs(), expected_regs);
     }
@@ -195,18 +206,23 @@ mod tests {
         assert_eq!(*cpu.regs(), expected_regs);
     }
 
-    // we only test phb for phb and phk (8 bit pushes)
+    // 8 bit pushes
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_reg;
+        [phb]       [0x8b]      [DB];
+        [phk]       [0x4b]      [PB];
+    )]
     #[test]
-    fn phb() {
+    fn DUP_name() {
         let mut regs = Registers::default();
-        regs.DB = 0x42;
         regs.S = 0x0477;
         regs.PC = 0;
         regs.PB = 0;
+        regs.DUP_reg = 0x42;
         let mut expected_regs = regs;
         let mut cpu = CPU::new(regs);
 
-        expect_opcode_fetch(&mut cpu, 0x8b);
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
         expect_internal_cycle(&mut cpu, "stack alignment");
         expect_write_cycle(&mut cpu, snes_addr!(0:0x0477), 0x42, "push");
         expect_opcode_fetch_cycle(&mut cpu);
@@ -348,4 +364,120 @@ mod tests {
 
         assert_eq!(*cpu.regs(), expected_regs);
     }
+
+    // in emulation mode, pushes and pulls wrap within page 1
+    #[test]
+    fn pha_emu_wrap() {
+        let mut regs = Registers::default();
+        regs.E = true;
+        regs.A = 0x5566;
+        regs.S = 0x0100;
+        regs.PC = 0;
+        regs.PB = 0;
+        let mut expected_regs = regs;
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0x48);
+        expect_internal_cycle(&mut cpu, "stack alignment");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0100), 0x66, "push");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 1;
+        expected_regs.S = 0x01ff;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    #[test]
+    fn pla_emu_wrap() {
+        let mut regs = Registers::default();
+        regs.E = true;
+        regs.A = 0x5500;
+        regs.S = 0x01ff;
+        regs.PC = 0;
+        regs.PB = 0;
+        let mut expected_regs = regs;
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0x68);
+        expect_internal_cycle(&mut cpu, "stack alignment (1)");
+        expect_internal_cycle(&mut cpu, "stack alignment (2)");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0100), 0x66, "pull");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 1;
+        expected_regs.A = 0x5566;
+        expected_regs.S = 0x0100;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // 16-bit pushes of the 65816 go past page 1, S is put back in page 1 after
+    #[test]
+    fn phd_emu() {
+        let mut regs = Registers::default();
+        regs.E = true;
+        regs.D = 0x1234;
+        regs.S = 0x0100;
+        regs.PC = 0;
+        regs.PB = 0;
+        let mut expected_regs = regs;
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0x0b);
+        expect_internal_cycle(&mut cpu, "stack alignment");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0100), 0x12, "push hi");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x00ff), 0x34, "push lo, outside page 1");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 1;
+        expected_regs.S = 0x01fe;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    #[test]
+    fn pld_emu() {
+        let mut regs = Registers::default();
+        regs.E = true;
+        regs.S = 0x01ff;
+        regs.PC = 0;
+        regs.PB = 0;
+        let mut expected_regs = regs;
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0x2b);
+        expect_internal_cycle(&mut cpu, "stack alignment (1)");
+        expect_internal_cycle(&mut cpu, "stack alignment (2)");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0200), 0x11, "pull lo, outside page 1");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0201), 0x22, "pull hi");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 1;
+        expected_regs.D = 0x2211;
+        expected_regs.S = 0x0101;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // PEI reads the address as in native mode, even with DL == 0
+    #[test]
+    fn pei_emu() {
+        let mut regs = Registers::default();
+        regs.E = true;
+        regs.S = 0x0100;
+        regs.PC = 0x100;
+        regs.PB = 0;
+        regs.D = 0x1200;
+        let mut expected_regs = regs;
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0xd4);
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x101), 0xff, "direct offset");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x12ff), 0x88, "address lo");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x1300), 0x99, "address hi");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0100), 0x99, "address hi");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x00ff), 0x88, "address lo");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x102;
+        expected_regs.S = 0x01fe;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
 }
@@ -38,6 +38,7 @@ duplicate! {
     ]
     cpu_instr!(DUP_name {
         meta SET_OP_SIZE DUP_opsize;
+        meta SET_WRITE;
 
         meta DUP_addrmode;
         meta WRITE_OP DUP_src;
@@ -135,4 +136,341 @@ mod tests {
         expected_regs.PC = 0x3459;
         assert_eq!(*cpu.regs(), expected_regs);
     }
+
+    // the second byte of a 16-bit write at $xx:FFFF goes to the next bank
+    #[test]
+    fn sta16_abs_bank_cross() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.A = 0x5544;
+        regs.E = false;
+        regs.P.M = false;
+        regs.DB = 0xdb;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0x8d);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0xff, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3458), 0xff, "AAH");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0xffff), 0x44, "AL");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdc:0x0000), 0x55, "AH");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3459;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // all the stores in direct addressing mode
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_value;
+        [sta_d]     [0x85]      [0x11];
+        [stx_d]     [0x86]      [0x22];
+        [sty_d]     [0x84]      [0x33];
+        [stz_d]     [0x64]      [0];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true; // 8-bit mode
+        regs.D = 0x0500;
+        regs.A = 0x11;
+        regs.X = 0x22;
+        regs.Y = 0x33;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x40, "direct offset");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0540), DUP_value, "write");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // all the stores in direct indexed addressing modes
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_value;
+        [sta_dx]    [0x95]      [0x11];
+        [stx_dy]    [0x96]      [0x22];
+        [sty_dx]    [0x94]      [0x33];
+        [stz_dx]    [0x74]      [0];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true; // 8-bit mode
+        regs.D = 0x0500;
+        regs.A = 0x11;
+        // STX is the only Y-indexed store: X holds the value and Y the index
+        regs.X = if DUP_opcode == 0x96 { 0x22 } else { 0x04 };
+        regs.Y = if DUP_opcode == 0x96 { 0x04 } else { 0x33 };
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x40, "direct offset");
+        expect_internal_cycle(&mut cpu, "indexing");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0544), DUP_value, "write");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // absolute stores not tested above
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_value;
+        [stx_abs]   [0x8e]      [0x22];
+        [sty_abs]   [0x8c]      [0x33];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false;
+        regs.P.X = false;
+        regs.X = 0x4422;
+        regs.Y = 0x4433;
+        regs.DB = 0xdb;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x22, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3458), 0x11, "AAH");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0x1122), DUP_value, "write lo");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0x1123), 0x44, "write hi");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3459;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // contrary to loads, indexed stores always spend a cycle for indexing,
+    // even without crossing a page boundary and with 8-bit index registers
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_value;
+        [sta_absx]  [0x9d]      [0x11];
+        [sta_absy]  [0x99]      [0x11];
+        [stz_absx]  [0x9e]      [0];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true; // 8-bit mode
+        regs.A = 0x11;
+        regs.X = 0x04;
+        regs.Y = 0x04;
+        regs.DB = 0xdb;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x22, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3458), 0x11, "AAH");
+        expect_internal_cycle(&mut cpu, "indexing");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0x1126), DUP_value, "write");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3459;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    #[test]
+    fn sta_dindy() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true; // 8-bit mode
+        regs.A = 0x11;
+        regs.Y = 0x04;
+        regs.DB = 0xdb;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0x91);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x40, "direct offset");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0040), 0x22, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0041), 0x11, "AAH");
+        expect_internal_cycle(&mut cpu, "indexing");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0x1126), 0x11, "write");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    #[test]
+    fn sta_dxind() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true; // 8-bit mode
+        regs.A = 0x11;
+        regs.X = 0x04;
+        regs.DB = 0xdb;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0x81);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x40, "direct offset");
+        expect_internal_cycle(&mut cpu, "indexing");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0044), 0x22, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0045), 0x11, "AAH");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0x1122), 0x11, "write");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    #[test]
+    fn sta_dind() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true; // 8-bit mode
+        regs.A = 0x11;
+        regs.DB = 0xdb;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0x92);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x40, "direct offset");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0040), 0x22, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0041), 0x11, "AAH");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0x1122), 0x11, "write");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // long indexing never spends an extra cycle
+    #[duplicate_item(
+        DUP_name        DUP_opcode  DUP_addr;
+        [sta_dindl]     [0x87]      [0x1122];
+        [sta_dindly]    [0x97]      [0x1126];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true; // 8-bit mode
+        regs.A = 0x11;
+        regs.Y = 0x04;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x40, "direct offset");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0040), 0x22, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0041), 0x11, "AAH");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0042), 0x7e, "AAB");
+        expect_write_cycle(&mut cpu, snes_addr!(0x7e:DUP_addr), 0x11, "write");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    #[duplicate_item(
+        DUP_name        DUP_opcode  DUP_addr;
+        [sta_absl]      [0x8f]      [0x1122];
+        [sta_abslx]     [0x9f]      [0x1126];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = true; // 8-bit mode
+        regs.A = 0x11;
+        regs.X = 0x04;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x22, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3458), 0x11, "AAH");
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3459), 0x7e, "AAB");
+        expect_write_cycle(&mut cpu, snes_addr!(0x7e:DUP_addr), 0x11, "write");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x345a;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    #[test]
+    fn sta_sr() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false;
+        regs.P.M = false;
+        regs.A = 0x5544;
+        regs.S = 0x0402;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0x83);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x80, "stack offset");
+        expect_internal_cycle(&mut cpu, "indexing");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0482), 0x44, "AL");
+        expect_write_cycle(&mut cpu, snes_addr!(0:0x0483), 0x55, "AH");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    #[test]
+    fn sta_sry() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.E = false;
+        regs.P.M = false;
+        regs.A = 0x5544;
+        regs.S = 0x0402;
+        regs.DB = 0xdb;
+        regs.Y = 0x3030;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0x93);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x80, "stack offset");
+        expect_internal_cycle(&mut cpu, "indexing");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0482), 0xaa, "AAL");
+        expect_read_cycle(&mut cpu, snes_addr!(0:0x0483), 0xbb, "AAH");
+        expect_internal_cycle(&mut cpu, "setting addrbus to abs address");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0xebda), 0x44, "AL");
+        expect_write_cycle(&mut cpu, snes_addr!(0xdb:0xebdb), 0x55, "AH");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3458;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
 }
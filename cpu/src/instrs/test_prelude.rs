@@ -28,6 +28,7 @@ pub(crate) fn expect_opcode_fetch_cycle(cpu: &mut CPU) {
         "Opcode fetch should be from {:#?} (current PB:PC)",
         expected_address
     );
+    assert!(cpu.fetched_opcode(), "Opcode fetch should be signaled");
 }
 
 /// Expects that the CPU does an opcode fetch cycle (a read cycle reading
@@ -66,6 +67,7 @@ pub(crate) fn expect_read_cycle(
         "Read cycle for {reason} should be from {:#?}",
         expected_address
     );
+    assert!(!cpu.fetched_opcode(), "Read cycle for {reason} is not an opcode fetch");
     cpu.data_bus = value;
 }
 
@@ -94,3 +96,30 @@ pub(crate) fn expect_write_cycle(
         expected_address, expected_value,
     )
 }
+
+/// Runs the instruction whose opcode was just fetched (see
+/// [`expect_opcode_fetch`]) up to the next opcode fetch, serving reads from
+/// `memory` (0 from any other address) and ignoring writes.
+///
+/// This is for tests of what an instruction does rather than of its cycles.
+pub(crate) fn run_instr(cpu: &mut CPU, memory: &[(SnesAddress, u8)]) {
+    for _ in 0..16 {
+        let cycle = cpu.cycle();
+        let address = *cpu.addr_bus();
+        if cycle != CycleResult::Read {
+            continue;
+        }
+        let opcode_address = SnesAddress {
+            bank: cpu.registers.PB,
+            addr: cpu.registers.PC,
+        };
+        if address == opcode_address {
+            return;
+        }
+        cpu.data_bus = memory
+            .iter()
+            .find(|(addr, _)| *addr == address)
+            .map_or(0, |(_, value)| *value);
+    }
+    panic!("Instruction should end within 16 cycles");
+}
@@ -75,6 +75,7 @@ duplicate! {
 #[cfg(test)]
 mod tests {
     use super::super::test_prelude::*;
+    use duplicate::duplicate_item;
 
     // we only test txs for txs and tcs since they do the exact same
     #[test]
@@ -185,4 +186,126 @@ mod tests {
         expected_regs.P.N = true; // 0xff44 is negative
         assert_eq!(*cpu.regs(), expected_regs);
     }
+
+    // flags of all variable width transfers, with the width of the destination
+    #[duplicate_item(
+        DUP1_name   DUP1_opcode DUP1_src    DUP1_dest   DUP1_flag   DUP1_dest_value;
+        [tax]       [0xaa]      [A]         [X]         [X]         [0x0034];
+        [tay]       [0xa8]      [A]         [Y]         [X]         [0x0034];
+        [tsx]       [0xba]      [S]         [X]         [X]         [0x0034];
+        [txa]       [0x8a]      [X]         [A]         [M]         [0x1234];
+        [txy]       [0x9b]      [X]         [Y]         [X]         [0x0034];
+        [tya]       [0x98]      [Y]         [A]         [M]         [0x1234];
+        [tyx]       [0xbb]      [Y]         [X]         [X]         [0x0034];
+    )]
+    mod DUP1_name {
+        use super::*;
+
+        #[duplicate_item(
+            DUP2_name       DUP2_short  DUP2_src    DUP2_result                 DUP2_n      DUP2_z      DUP2_not_n  DUP2_not_z;
+            [flags16_n]     [false]     [0x8000]    [0x8000]                    [true]      [false]     [false]     [true];
+            [flags16]       [false]     [0x0080]    [0x0080]                    [false]     [false]     [true]      [true];
+            [flags16_z]     [false]     [0x0000]    [0x0000]                    [false]     [true]      [true]      [false];
+            [flags8_n]      [true]      [0x0080]    [(dest & 0xff00) | 0x0080]  [true]      [false]     [false]     [true];
+            [flags8_z]      [true]      [0x8000]    [dest & 0xff00]             [false]     [true]      [true]      [false];
+        )]
+        #[test]
+        fn DUP2_name() {
+            let mut regs = Registers::default();
+            regs.PB = 0x55;
+            regs.PC = 0x7777;
+            regs.P.DUP1_flag = DUP2_short;
+            regs.P.N = DUP2_not_n;
+            regs.P.Z = DUP2_not_z;
+            regs.P.C = true; // other flags are not affected
+            regs.P.V = true;
+            // 8-bit index registers have a zero high byte
+            let dest = DUP1_dest_value;
+            regs.DUP1_dest = dest;
+            regs.DUP1_src = DUP2_src;
+
+            let mut expected_regs = regs.clone();
+            let mut cpu = CPU::new(regs);
+
+            expect_opcode_fetch(&mut cpu, DUP1_opcode);
+            expect_internal_cycle(&mut cpu, "transfer");
+            expect_opcode_fetch_cycle(&mut cpu);
+
+            expected_regs.PC = 0x7778;
+            // an 8-bit destination keeps its high byte
+            expected_regs.DUP1_dest = DUP2_result;
+            expected_regs.P.N = DUP2_n;
+            expected_regs.P.Z = DUP2_z;
+            assert_eq!(*cpu.regs(), expected_regs);
+        }
+    }
+
+    // transfers with D, and from S to A are always 16-bit, even in emulation mode
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_src DUP_dest    DUP_value   DUP_n   DUP_z   DUP_not_n   DUP_not_z;
+        [tcd_emu]   [0x5b]      [A]     [D]         [0x8000]    [true]  [false] [false]     [true];
+        [tdc_emu]   [0x7b]      [D]     [A]         [0x0000]    [false] [true]  [true]      [false];
+        [tdc_emu_n] [0x7b]      [D]     [A]         [0xff00]    [true]  [false] [false]     [true];
+        [tsc_emu]   [0x3b]      [S]     [A]         [0x0100]    [false] [false] [true]      [true];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x55;
+        regs.PC = 0x7777;
+        regs.E = true;
+        regs.P.M = true;
+        regs.P.X = true;
+        regs.P.N = DUP_not_n;
+        regs.P.Z = DUP_not_z;
+        regs.S = 0x01ff;
+        regs.A = 0x1234;
+        regs.D = 0x1234;
+        regs.DUP_src = DUP_value;
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_internal_cycle(&mut cpu, "transfer");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x7778;
+        expected_regs.DUP_dest = DUP_value;
+        expected_regs.P.N = DUP_n;
+        expected_regs.P.Z = DUP_z;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
+    // transfers to S don't affect flags, and keep S in page 1 in emulation mode
+    #[duplicate_item(
+        DUP_name    DUP_opcode  DUP_src DUP_e   DUP_s;
+        [tcs_emu]   [0x1b]      [A]     [true]  [0x0100];
+        [tcs]       [0x1b]      [A]     [false] [0x0000];
+        [txs_emu]   [0x9a]      [X]     [true]  [0x0100];
+    )]
+    #[test]
+    fn DUP_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x55;
+        regs.PC = 0x7777;
+        regs.E = DUP_e;
+        regs.P.M = DUP_e;
+        regs.P.X = DUP_e;
+        regs.P.N = true;
+        regs.P.Z = false;
+        regs.S = 0x01ff;
+        regs.DUP_src = 0; // would set Z if it affected flags
+
+        let mut expected_regs = regs.clone();
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_internal_cycle(&mut cpu, "transfer");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x7778;
+        expected_regs.S = DUP_s;
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
 }
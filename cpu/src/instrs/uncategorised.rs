@@ -8,6 +8,9 @@ use instr_metalang_procmacro::{
 };
 use duplicate::duplicate;
 
+use crate::cpu::{CPU, CycleResult, Halt};
+use crate::instrs::instr_tab::{InstrCycle, halted};
+
 // `NOP`: "no-op" (no operation). Literally does nothing
 cpu_instr!(nop {
     meta END_CYCLE Internal;
@@ -21,11 +24,19 @@ cpu_instr!(wdm {
     meta FETCH8_IMM;
 });
 
+// `STP`: SToP the clock
+// The CPU doesn't execute any instruction anymore until it is reset,
+// PC is left pointing at the STP itself
+pub(crate) fn stp_cyc1(cpu: &mut CPU) -> (CycleResult, InstrCycle) {
+    cpu.halt = Some(Halt::Stopped);
+    (CycleResult::Internal, InstrCycle(halted))
+}
+
 // `XCE`: eXchange Carry and Emulation
 // Swaps the carry bit with the emulation bit.
 // This is the only instruction which can toggle emulation on and off
 cpu_instr!(xce {
-    std::mem::swap(&mut cpu.registers.P.C, &mut cpu.registers.E);
+    core::mem::swap(&mut cpu.registers.P.C, &mut cpu.registers.E);
 
     // switching to (or already in) emulation mode
     if cpu.registers.E {
@@ -144,6 +155,8 @@ duplicate! {
 #[cfg(test)]
 mod tests {
     use crate::instrs::test_prelude::*;
+    use crate::cpu::{Halt, OpcodePolicy, UnhandledOpcode};
+    use duplicate::duplicate_item;
 
     #[test]
     fn test_1_plus_1_is_2_cycle_api() {
@@ -197,6 +210,128 @@ mod tests {
         assert_eq!(*cpu.regs(), expected_regs);
     }
 
+    #[test]
+    fn wdm_reported() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0x42);
+        expect_read_cycle(&mut cpu, snes_addr!(0x12:0x3457), 0x00, "idle (ignored read)");
+
+        assert_eq!(
+            cpu.take_unhandled_opcode(),
+            Some(UnhandledOpcode { opcode: 0x42, addr: snes_addr!(0x12:0x3456), len: 2 }),
+        );
+        assert_eq!(cpu.take_unhandled_opcode(), None, "Opcode should be reported once");
+        assert_eq!(cpu.halted(), None);
+    }
+
+    #[test]
+    fn wdm_trap() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        let expected_regs = regs.clone();
+
+        let mut cpu = CPU::new(regs);
+        cpu.set_opcode_policy(OpcodePolicy::Trap);
+
+        expect_opcode_fetch(&mut cpu, 0x42);
+        for _ in 0..3 {
+            expect_internal_cycle(&mut cpu, "trapped");
+        }
+
+        let trap = UnhandledOpcode { opcode: 0x42, addr: snes_addr!(0x12:0x3456), len: 2 };
+        assert_eq!(cpu.halted(), Some(Halt::Trap(trap)));
+        assert_eq!(cpu.take_unhandled_opcode(), Some(trap));
+        assert_eq!(*cpu.regs(), expected_regs, "Trapped instruction shouldn't run");
+
+        // resuming skips the instruction
+        cpu.resume();
+        assert_eq!(cpu.halted(), None);
+        assert_eq!(cpu.regs().PC, 0x3458);
+        expect_opcode_fetch_cycle(&mut cpu);
+    }
+
+    #[duplicate_item(
+        test_name   DUP_opcode  DUP_len;
+        [brk_nop]   [0x00]      [2];
+        [cop_nop]   [0x02]      [2];
+        [rti_nop]   [0x40]      [1];
+        [wai_nop]   [0xcb]      [1];
+    )]
+    #[test]
+    fn test_name() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        let mut expected_regs = regs.clone();
+
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, DUP_opcode);
+        expect_internal_cycle(&mut cpu, "skipped instruction");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3456 + DUP_len;
+        assert_eq!(*cpu.regs(), expected_regs, "Only PC should have been touched");
+        assert_eq!(
+            cpu.take_unhandled_opcode(),
+            Some(UnhandledOpcode {
+                opcode: DUP_opcode,
+                addr: snes_addr!(0x12:0x3456),
+                len: DUP_len,
+            }),
+        );
+    }
+
+    #[test]
+    fn unimplemented_trap() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        let mut cpu = CPU::new(regs);
+        cpu.set_opcode_policy(OpcodePolicy::Trap);
+
+        expect_opcode_fetch(&mut cpu, 0x00);
+        expect_internal_cycle(&mut cpu, "trapped");
+        expect_internal_cycle(&mut cpu, "trapped");
+
+        let trap = UnhandledOpcode { opcode: 0x00, addr: snes_addr!(0x12:0x3456), len: 2 };
+        assert_eq!(cpu.halted(), Some(Halt::Trap(trap)));
+        assert_eq!(cpu.regs().PC, 0x3456);
+
+        cpu.resume();
+        assert_eq!(cpu.regs().PC, 0x3458);
+        expect_opcode_fetch_cycle(&mut cpu);
+    }
+
+    #[test]
+    fn stp() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        let expected_regs = regs.clone();
+
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0xdb);
+        for _ in 0..3 {
+            expect_internal_cycle(&mut cpu, "stopped clock");
+        }
+        assert_eq!(cpu.halted(), Some(Halt::Stopped));
+        assert_eq!(*cpu.regs(), expected_regs);
+        assert_eq!(cpu.take_unhandled_opcode(), None, "STP is a regular instruction");
+
+        // only a reset restarts the CPU
+        cpu.resume();
+        expect_internal_cycle(&mut cpu, "stopped clock");
+        cpu.start_reset();
+        assert_eq!(cpu.halted(), None);
+    }
+
     #[test]
     fn xce_to_emu() {
         let mut regs = Registers::default();
@@ -309,6 +444,31 @@ mod tests {
         assert_eq!(*cpu.regs(), expected_regs);
     }
 
+    // flags are set from the new low byte even with a 16-bit accumulator
+    #[test]
+    fn xba_16() {
+        let mut regs = Registers::default();
+        regs.PB = 0x12;
+        regs.PC = 0x3456;
+        regs.A = 0x0080;
+        regs.P.M = false;
+        regs.P.N = true;
+        let mut expected_regs = regs.clone();
+
+        let mut cpu = CPU::new(regs);
+
+        expect_opcode_fetch(&mut cpu, 0xeb);
+        expect_internal_cycle(&mut cpu, "swap");
+        expect_internal_cycle(&mut cpu, "swap (2)");
+        expect_opcode_fetch_cycle(&mut cpu);
+
+        expected_regs.PC = 0x3457;
+        expected_regs.A = 0x8000;
+        expected_regs.P.N = false;
+        expected_regs.P.Z = true; // because the new low byte is 0
+        assert_eq!(*cpu.regs(), expected_regs);
+    }
+
     #[test]
     fn mvn() {
         let mut regs = Registers::default();
@@ -1,7 +1,10 @@
 #![doc = include_str!("../README.md")]
+// Only the core library is used, the CPU runs on embedded targets
+#![cfg_attr(not(test), no_std)]
 
 pub mod registers;
 pub mod cpu;
+pub mod disasm;
 mod instrs;
 mod reg;
 
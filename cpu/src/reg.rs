@@ -1,4 +1,4 @@
-use std::ops::{
+use core::ops::{
     Add,
     AddAssign,
     BitAnd,
@@ -15,7 +15,7 @@ use std::ops::{
     SubAssign,
     Not,
 };
-use std::cmp::Eq;
+use core::cmp::Eq;
 use duplicate::duplicate;
 
 /// Trait describing values which the CPU operates on: u8 and u16
@@ -1,4 +1,5 @@
-use std::fmt;
+use common::save_state::{StateError, StateReader, StateWriter};
+use core::fmt;
 
 /// A struct which represents the WDC 65C816's registers
 #[allow(non_snake_case, reason = "We are naming register in all caps")]
@@ -140,11 +141,43 @@ impl Into<u8> for RegisterP {
     }
 }
 
+impl Registers {
+    /// Writes the registers to a save state.
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        for value in [self.A, self.X, self.Y, self.D, self.S, self.PC] {
+            writer.u16(value);
+        }
+        writer.u8(self.DB);
+        writer.u8(self.PB);
+        writer.u8(self.P.into());
+        writer.bool(self.E);
+    }
+
+    /// Restores the registers written by [`Self::save_state`].
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        for value in [
+            &mut self.A,
+            &mut self.X,
+            &mut self.Y,
+            &mut self.D,
+            &mut self.S,
+            &mut self.PC,
+        ] {
+            *value = reader.u16()?;
+        }
+        self.DB = reader.u8()?;
+        self.PB = reader.u8()?;
+        self.P = reader.u8()?.into();
+        self.E = reader.bool()?;
+        Ok(())
+    }
+}
+
 impl fmt::Debug for Registers {
     #[cfg(not(tarpaulin_include))]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        std::write!(f, "{} ", if self.E { "Emu" } else { "Nat" })?;
-        std::write!(
+        core::write!(f, "{} ", if self.E { "Emu" } else { "Nat" })?;
+        core::write!(
             f,
             "{{ A: {:#06x}, X: {:#06x}, Y: {:#06x}, DB: {:#04x}, D: {:#06x}, S: {:#06x}, PB: {:#04x}, PC: {:#06x}, P: ({:?}) }}",
             self.A,
@@ -173,7 +206,7 @@ impl fmt::Debug for RegisterP {
             (self.Z, 'Z'),
             (self.C, 'C'),
         ] {
-            std::write!(f, "{}", if flag { c } else { '-' })?;
+            core::write!(f, "{}", if flag { c } else { '-' })?;
         };
         Ok(())
     }
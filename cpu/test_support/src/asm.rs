@@ -0,0 +1,402 @@
+//! A small 65C816 assembler, reading the syntax the disassembler (`cpu::disasm`) prints so
+//! that listings can be pasted back:
+//!
+//! - One instruction per line, `;` starting a comment, and `name:` defining a label at the
+//!   start of a line
+//! - Numbers are hexadecimal (`$1F`) or decimal (`31`). The number of hexadecimal digits
+//!   gives the operand size: `LDA $10` is direct page, `LDA $0010` absolute and
+//!   `LDA $000010` long. Immediates follow the same rule, `#$12` being 8 bits and `#$0012`
+//!   16 bits, whatever the register widths
+//! - Branches take the target address or a label. Other instructions use the absolute
+//!   address of a label, or its long address when they have no absolute mode (`JML`, `JSL`)
+//! - `.db`, `.dw` and `.dl` emit bytes, words and longs, separated by commas
+
+use cpu::disasm::{AddrMode, RegisterWidths, opcode};
+use std::collections::HashMap;
+use std::fmt;
+
+use AddrMode::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// Line of the error, starting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::error::Error for AsmError {}
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Operand value, resolved once every label is known
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    /// Number, with its size in bytes
+    Number(u32, usize),
+    Label(String),
+}
+
+impl Value {
+    /// Size in bytes written in the source, labels being absolute addresses
+    fn size(&self) -> usize {
+        match self {
+            Value::Number(_, size) => *size,
+            Value::Label(_) => 2,
+        }
+    }
+}
+
+/// Addressing modes an operand syntax may stand for, from the smallest operand
+fn candidate_modes(operand: &str) -> (&'static [AddrMode], &str) {
+    let upper = operand.to_ascii_uppercase();
+    let strip = |prefix: &str, suffix: &str| {
+        let inside = upper.len() > prefix.len() + suffix.len();
+        (inside && upper.starts_with(prefix) && upper.ends_with(suffix))
+            .then(|| &operand[prefix.len()..operand.len() - suffix.len()])
+    };
+
+    if operand.is_empty() {
+        (&[Implied, Accumulator], operand)
+    } else if upper == "A" {
+        (&[Accumulator], operand)
+    } else if let Some(value) = strip("#", "") {
+        (&[ImmediateM, ImmediateX, Immediate8], value)
+    } else if let Some(value) = strip("(", ",S),Y") {
+        (&[StackRelativeIndirectY], value)
+    } else if let Some(value) = strip("(", "),Y") {
+        (&[DirectIndirectY], value)
+    } else if let Some(value) = strip("(", ",X)") {
+        (&[DirectXIndirect, AbsoluteXIndirect], value)
+    } else if let Some(value) = strip("(", ")") {
+        (&[DirectIndirect, AbsoluteIndirect], value)
+    } else if let Some(value) = strip("[", "],Y") {
+        (&[DirectIndirectLongY], value)
+    } else if let Some(value) = strip("[", "]") {
+        (&[DirectIndirectLong, AbsoluteIndirectLong], value)
+    } else if let Some(value) = strip("", ",X") {
+        (&[DirectX, AbsoluteX, AbsoluteLongX], value)
+    } else if let Some(value) = strip("", ",Y") {
+        (&[DirectY, AbsoluteY], value)
+    } else if let Some(value) = strip("", ",S") {
+        (&[StackRelative], value)
+    } else if operand.contains(',') {
+        (&[BlockMove], operand)
+    } else {
+        (
+            &[Relative, RelativeLong, Direct, Absolute, AbsoluteLong],
+            operand,
+        )
+    }
+}
+
+/// Operand size of `mode`, immediates taking the size of their value
+fn operand_len(mode: AddrMode, value_size: usize) -> usize {
+    match mode {
+        ImmediateM | ImmediateX => value_size,
+        _ => mode.operand_len(RegisterWidths::default()),
+    }
+}
+
+/// A line producing bytes
+#[derive(Debug)]
+enum Item {
+    Instruction {
+        opcode: u8,
+        mode: AddrMode,
+        values: Vec<Value>,
+        len: usize,
+    },
+    /// `.db`, `.dw` or `.dl`, with the size of each value
+    Data { values: Vec<Value>, size: usize },
+}
+
+struct Assembler {
+    labels: HashMap<String, u32>,
+    /// Items with the address following them and their source line
+    items: Vec<(u32, usize, Item)>,
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if let Some(digits) = text.strip_prefix('$') {
+        let value = u32::from_str_radix(digits, 16)
+            .ok()
+            .filter(|_| (1..=6).contains(&digits.len()))
+            .ok_or_else(|| format!("invalid hexadecimal number `{text}`"))?;
+        Ok(Value::Number(value, digits.len().div_ceil(2)))
+    } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+        let value: u32 = text
+            .parse()
+            .ok()
+            .filter(|&value| value <= 0xFF_FFFF)
+            .ok_or_else(|| format!("invalid number `{text}`"))?;
+        let size = match value {
+            0..=0xFF => 1,
+            0x100..=0xFFFF => 2,
+            _ => 3,
+        };
+        Ok(Value::Number(value, size))
+    } else if is_label(text) {
+        Ok(Value::Label(text.to_string()))
+    } else {
+        Err(format!("invalid operand `{text}`"))
+    }
+}
+
+fn is_label(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Assembler {
+    /// First pass: sizes every line to place the labels.
+    fn parse_line(&mut self, addr: u32, line: &str) -> Result<Option<Item>, String> {
+        let mut line = line.split(';').next().unwrap_or_default().trim();
+        if let Some((label, rest)) = line.split_once(':')
+            && is_label(label.trim())
+        {
+            let label = label.trim();
+            if self.labels.insert(label.to_string(), addr).is_some() {
+                return Err(format!("label `{label}` defined twice"));
+            }
+            line = rest.trim();
+        }
+        if line.is_empty() {
+            return Ok(None);
+        }
+
+        let (mnemonic, operand) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let operand: String = operand.chars().filter(|c| !c.is_whitespace()).collect();
+
+        let data_size = match mnemonic.to_ascii_lowercase().as_str() {
+            ".db" => Some(1),
+            ".dw" => Some(2),
+            ".dl" => Some(3),
+            _ if mnemonic.starts_with('.') => {
+                return Err(format!("unknown directive `{mnemonic}`"));
+            }
+            _ => None,
+        };
+        if let Some(size) = data_size {
+            let values = operand
+                .split(',')
+                .map(parse_value)
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(Some(Item::Data { values, size }));
+        }
+
+        let (modes, value_text) = candidate_modes(&operand);
+        let values = match modes {
+            [Implied, ..] | [Accumulator] => Vec::new(),
+            // Printed source bank first, encoded destination bank first
+            [BlockMove] => {
+                let (source, destination) = value_text.split_once(',').unwrap_or_default();
+                vec![parse_value(destination)?, parse_value(source)?]
+            }
+            _ => vec![parse_value(value_text)?],
+        };
+        let value_size = values.first().map_or(0, Value::size);
+
+        for &mode in modes {
+            let Some(opcode) = opcode(mnemonic, mode) else {
+                continue;
+            };
+            let len = 1 + operand_len(mode, value_size);
+            let fits = match mode {
+                Relative | RelativeLong | BlockMove => true,
+                ImmediateM | ImmediateX => value_size <= 2,
+                _ => value_size < len,
+            };
+            if fits {
+                return Ok(Some(Item::Instruction {
+                    opcode,
+                    mode,
+                    values,
+                    len,
+                }));
+            }
+        }
+        Err(format!(
+            "no `{mnemonic}` instruction for operand `{operand}`"
+        ))
+    }
+
+    fn resolve(&self, value: &Value) -> Result<u32, String> {
+        match value {
+            Value::Number(value, _) => Ok(*value),
+            Value::Label(label) => self
+                .labels
+                .get(label)
+                .copied()
+                .ok_or_else(|| format!("unknown label `{label}`")),
+        }
+    }
+
+    /// Second pass: encodes an item placed at `addr`.
+    fn encode(&self, addr: u32, item: &Item, output: &mut Vec<u8>) -> Result<(), String> {
+        match item {
+            Item::Data { values, size } => {
+                for value in values {
+                    let value = self.resolve(value)?;
+                    output.extend_from_slice(&value.to_le_bytes()[..*size]);
+                }
+            }
+            Item::Instruction {
+                opcode,
+                mode,
+                values,
+                len,
+            } => {
+                output.push(*opcode);
+                let operand = match mode {
+                    Relative | RelativeLong => {
+                        let target = self.resolve(&values[0])?;
+                        let next = addr.wrapping_add(*len as u32);
+                        let offset = (target as u16).wrapping_sub(next as u16) as i16;
+                        if *mode == Relative && i8::try_from(offset).is_err() {
+                            return Err(format!("branch target ${target:04X} out of range"));
+                        }
+                        offset as u16 as u32
+                    }
+                    BlockMove => self.resolve(&values[0])? | (self.resolve(&values[1])? << 8),
+                    _ => values.first().map_or(Ok(0), |value| self.resolve(value))?,
+                };
+                output.extend_from_slice(&operand.to_le_bytes()[..len - 1]);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Assembles `source` into the machine code to load at the 24-bit address `origin`.
+pub fn assemble(origin: u32, source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut assembler = Assembler {
+        labels: HashMap::new(),
+        items: Vec::new(),
+    };
+
+    let mut addr = origin;
+    for (index, line) in source.lines().enumerate() {
+        let item = assembler
+            .parse_line(addr, line)
+            .map_err(|message| AsmError {
+                line: index + 1,
+                message,
+            })?;
+        if let Some(item) = item {
+            addr += match &item {
+                Item::Instruction { len, .. } => *len,
+                Item::Data { values, size } => values.len() * size,
+            } as u32;
+            assembler.items.push((addr, index + 1, item));
+        }
+    }
+
+    let mut output = Vec::new();
+    let mut addr = origin;
+    for (end, line, item) in &assembler.items {
+        assembler
+            .encode(addr, item, &mut output)
+            .map_err(|message| AsmError {
+                line: *line,
+                message,
+            })?;
+        addr = *end;
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpu::disasm::Instruction;
+
+    fn assemble_line(source: &str) -> Vec<u8> {
+        assemble(0x00_8000, source).unwrap()
+    }
+
+    fn error_of(source: &str) -> AsmError {
+        assemble(0x00_8000, source).unwrap_err()
+    }
+
+    #[test]
+    fn test_operand_size_picks_mode() {
+        assert_eq!(assemble_line("LDA $10"), [0xA5, 0x10]);
+        assert_eq!(assemble_line("LDA $0010"), [0xAD, 0x10, 0x00]);
+        assert_eq!(assemble_line("LDA $7E0010"), [0xAF, 0x10, 0x00, 0x7E]);
+        assert_eq!(assemble_line("lda 16,x"), [0xB5, 0x10]);
+        // No direct page JMP
+        assert_eq!(assemble_line("JMP $10"), [0x4C, 0x10, 0x00]);
+    }
+
+    #[test]
+    fn test_immediate_size() {
+        assert_eq!(assemble_line("LDA #$12"), [0xA9, 0x12]);
+        assert_eq!(assemble_line("LDX #$0012"), [0xA2, 0x12, 0x00]);
+        assert_eq!(assemble_line("REP #$30"), [0xC2, 0x30]);
+        assert_eq!(error_of("REP #$1234").line, 1);
+    }
+
+    #[test]
+    fn test_labels_and_branches() {
+        let code = assemble_line(
+            "
+            start:  LDX #$03
+            loop:   DEX         ; counts down
+                    BNE loop
+                    BRL start
+                    JSL far
+            far:    .db $01, $02
+                    .dw far
+            ",
+        );
+        assert_eq!(
+            code,
+            [
+                0xA2, 0x03, 0xCA, 0xD0, 0xFD, 0x82, 0xF8, 0xFF, 0x22, 0x0C, 0x80, 0x00, 0x01, 0x02,
+                0x0C, 0x80,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            error_of("NOP\nFOO $12"),
+            AsmError {
+                line: 2,
+                message: "no `FOO` instruction for operand `$12`".to_string()
+            }
+        );
+        assert!(error_of("BRA missing").message.contains("unknown label"));
+        assert!(error_of("x: NOP\nx: NOP").message.contains("defined twice"));
+        assert!(error_of("BRA $9000").message.contains("out of range"));
+        assert!(error_of(".org $8000").message.contains("unknown directive"));
+    }
+
+    /// Every instruction printed by the disassembler must assemble back to its bytes.
+    #[test]
+    fn test_disassembly_round_trip() {
+        let bytes = [0x12, 0x34, 0x56];
+        for widths in [
+            RegisterWidths { m8: true, x8: true },
+            RegisterWidths {
+                m8: false,
+                x8: false,
+            },
+        ] {
+            for opcode in 0..=0xFF {
+                let code = [opcode, bytes[0], bytes[1], bytes[2]];
+                let instr = Instruction::decode(0x00_8000, &code, widths).unwrap();
+                let source = instr.to_string();
+                assert_eq!(
+                    assemble(0x00_8000, &source).as_deref(),
+                    Ok(&code[..instr.len]),
+                    "{source}"
+                );
+            }
+        }
+    }
+}
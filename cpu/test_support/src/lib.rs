@@ -0,0 +1,20 @@
+//! Support for the CPU tests: [`assemble`] turns 65C816 assembly into machine code and
+//! [`Machine`] runs it, so that tests are written as programs rather than opcode bytes.
+//!
+//! ```
+//! use cpu_test_support::Machine;
+//!
+//! let mut machine = Machine::new(0x00_8000, "
+//!     LDA #$42
+//!     STA $10
+//!     STP
+//! ");
+//! machine.run();
+//! assert_eq!(machine.memory[0x10], 0x42);
+//! ```
+
+mod asm;
+mod machine;
+
+pub use asm::{AsmError, assemble};
+pub use machine::Machine;
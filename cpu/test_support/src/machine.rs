@@ -0,0 +1,97 @@
+use crate::asm::assemble;
+use common::snes_address::SnesAddress;
+use cpu::cpu::{CPU, CpuBus, CycleResult, Halt, OpcodePolicy};
+use cpu::registers::Registers;
+
+/// Cycles after which [`Machine::run`] gives up on reaching `STP`
+const MAX_CYCLES: u64 = 1_000_000;
+
+/// Memory of a [`Machine`], read by [`CPU::reset`]
+struct FlatBus<'a>(&'a [u8]);
+
+impl CpuBus for FlatBus<'_> {
+    fn read(&mut self, addr: SnesAddress) -> u8 {
+        self.0[usize::from(addr)]
+    }
+}
+
+/// A CPU wired to a flat 16 MiB memory, every address being plain RAM.
+pub struct Machine {
+    pub cpu: CPU,
+    /// Memory indexed by 24-bit address
+    pub memory: Vec<u8>,
+}
+
+impl Machine {
+    /// Loads the program `source` at `origin` in bank 0, points the reset vector to it and
+    /// resets the CPU: emulation mode, 8-bit registers and the stack at $01FF. Unimplemented
+    /// opcodes trap rather than being skipped, for [`Self::run`] to report them.
+    ///
+    /// # Panics
+    /// Panics if `origin` is not in bank 0 or `source` does not assemble.
+    pub fn new(origin: u32, source: &str) -> Self {
+        assert!(origin <= 0xFFFF, "reset jumps to bank 0, not to ${origin:06X}");
+        let mut machine = Self::with_registers(Registers::default(), "");
+        machine.load(origin, source);
+        machine.memory[0xFFFC..=0xFFFD].copy_from_slice(&(origin as u16).to_le_bytes());
+        machine.cpu.reset(&mut FlatBus(&machine.memory));
+        machine
+    }
+
+    /// Loads the program `source` at PB:PC and starts the CPU with `registers`. Unimplemented
+    /// opcodes trap rather than being skipped, for [`Self::run`] to report them.
+    ///
+    /// # Panics
+    /// Panics if `source` does not assemble.
+    pub fn with_registers(registers: Registers, source: &str) -> Self {
+        let origin = (registers.PB as u32) << 16 | registers.PC as u32;
+        let mut cpu = CPU::new(registers);
+        cpu.set_opcode_policy(OpcodePolicy::Trap);
+        let mut machine = Self {
+            cpu,
+            memory: vec![0; 1 << 24],
+        };
+        machine.load(origin, source);
+        machine
+    }
+
+    fn load(&mut self, origin: u32, source: &str) {
+        let code = assemble(origin, source).unwrap_or_else(|err| panic!("{err}"));
+        let origin = origin as usize;
+        self.memory[origin..origin + code.len()].copy_from_slice(&code);
+    }
+
+    /// Runs the program until it executes `STP`, returning the number of cycles run.
+    ///
+    /// # Panics
+    /// Panics if the CPU traps on an unimplemented opcode or runs for too long.
+    pub fn run(&mut self) -> u64 {
+        for cycles in 0..MAX_CYCLES {
+            match self.cpu.halted() {
+                Some(Halt::Stopped) => return cycles,
+                Some(Halt::Trap(trap)) => panic!("CPU trapped on {trap:?}"),
+                None => {}
+            }
+            self.cycle();
+        }
+        panic!("STP not reached after {MAX_CYCLES} cycles");
+    }
+
+    /// Runs one CPU cycle, serving its memory access.
+    pub fn cycle(&mut self) -> CycleResult {
+        let cycle = self.cpu.cycle();
+        let addr = usize::from(*self.cpu.addr_bus());
+        match cycle {
+            CycleResult::Read => self.cpu.data_bus = self.memory[addr],
+            CycleResult::Write => self.memory[addr] = self.cpu.data_bus,
+            CycleResult::Internal => {}
+        }
+        cycle
+    }
+
+    /// Little-endian 16-bit word at `addr`
+    pub fn read_word(&self, addr: SnesAddress) -> u16 {
+        let addr = usize::from(addr);
+        u16::from_le_bytes([self.memory[addr], self.memory[(addr + 1) & 0xFF_FFFF]])
+    }
+}
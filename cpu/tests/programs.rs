@@ -0,0 +1,169 @@
+/// CPU program tests
+///
+/// Whole programs written as assembly snippets and run on a flat memory
+/// until STP, checking the registers and memory they leave. The cycle-level
+/// behaviour of each instruction is tested next to it, in src/instrs.
+use common::snes_address::{SnesAddress, snes_addr};
+use cpu::registers::Registers;
+use cpu_test_support::Machine;
+
+// ============================================================
+// Loops and branches
+// ============================================================
+
+#[test]
+fn test_count_down_loop() {
+    let mut machine = Machine::new(
+        0x00_8000,
+        "
+            LDX #$05
+            LDA #$00
+        loop:
+            CLC
+            ADC #$03
+            DEX
+            BNE loop
+            STA $10
+            STP
+        ",
+    );
+    machine.run();
+
+    assert_eq!(machine.memory[0x10], 15);
+    assert_eq!(machine.cpu.regs().X, 0);
+    assert!(machine.cpu.regs().P.Z);
+}
+
+#[test]
+fn test_compare_and_branch() {
+    let mut machine = Machine::new(
+        0x00_8000,
+        "
+            LDA #$20
+            CMP #$10
+            BCC below
+            LDA #$01
+            BRA done
+        below:
+            LDA #$02
+        done:
+            STA $10
+            STP
+        ",
+    );
+    machine.run();
+
+    assert_eq!(machine.memory[0x10], 0x01);
+}
+
+// ============================================================
+// Subroutines and the stack
+// ============================================================
+
+#[test]
+fn test_jsr_rts() {
+    let mut machine = Machine::new(
+        0x00_8000,
+        "
+            LDA #$01
+            JSR double
+            JSR double
+            STA $10
+            STP
+        double:
+            ASL A
+            RTS
+        ",
+    );
+    machine.run();
+
+    assert_eq!(machine.memory[0x10], 0x04);
+    assert_eq!(machine.cpu.regs().S, 0x01FF);
+}
+
+#[test]
+fn test_jsl_rtl_across_banks() {
+    let mut machine = Machine::new(
+        0x00_8000,
+        "
+            JSL $018000
+            STA $10
+            STP
+        ",
+    );
+    // LDA #$5A; RTL
+    machine.memory[0x01_8000..0x01_8003].copy_from_slice(&[0xA9, 0x5A, 0x6B]);
+    machine.run();
+
+    assert_eq!(machine.memory[0x10], 0x5A);
+    assert_eq!(machine.cpu.regs().PB, 0x00);
+    assert_eq!(machine.cpu.regs().S, 0x01FF);
+}
+
+#[test]
+fn test_push_pull() {
+    let mut machine = Machine::new(
+        0x00_8000,
+        "
+            LDA #$33
+            PHA
+            LDA #$00
+            PLA
+            STA $10
+            STP
+        ",
+    );
+    machine.run();
+
+    assert_eq!(machine.memory[0x10], 0x33);
+    assert_eq!(machine.memory[0x01FF], 0x33);
+}
+
+// ============================================================
+// Native mode and 16-bit registers
+// ============================================================
+
+#[test]
+fn test_native_mode_16_bit_arithmetic() {
+    let mut machine = Machine::new(
+        0x00_8000,
+        "
+            CLC
+            XCE
+            REP #$30
+            LDA #$12FF
+            CLC
+            ADC #$0001
+            STA $10
+            STP
+        ",
+    );
+    machine.run();
+
+    assert!(!machine.cpu.regs().E);
+    assert_eq!(machine.read_word(snes_addr!(0:0x10)), 0x1300);
+}
+
+#[test]
+fn test_block_move() {
+    let registers = Registers {
+        PC: 0x8000,
+        ..Registers::default()
+    };
+    let mut machine = Machine::with_registers(
+        registers,
+        "
+            LDX #$2000
+            LDY #$3000
+            LDA #$0003
+            MVN $7E, $7F
+            STP
+        ",
+    );
+    machine.memory[0x7E_2000..0x7E_2004].copy_from_slice(&[1, 2, 3, 4]);
+    machine.run();
+
+    assert_eq!(machine.memory[0x7F_3000..0x7F_3005], [1, 2, 3, 4, 0]);
+    assert_eq!(machine.cpu.regs().DB, 0x7F);
+    assert_eq!(machine.cpu.regs().A, 0xFFFF);
+}
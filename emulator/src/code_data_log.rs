@@ -0,0 +1,235 @@
+//! Code/data logger (CDL): records which ROM bytes the CPU ran as instructions and which
+//! were read as data during a session, so that a disassembler decodes only code, with the
+//! register widths it actually ran with.
+//!
+//! The log is saved in the usage map format of the bsnes-plus debugger: one byte per ROM
+//! byte (copier header excluded), combining the [`usage`] flags.
+//!
+//! Only the main CPU and DMA transfers are logged: reads through a coprocessor (SA-1 ROM
+//! mapping, coprocessor registers) are left out.
+
+use cpu::disasm::RegisterWidths;
+use cpu::registers::Registers;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+/// Flags of a usage map byte.
+pub mod usage {
+    /// Read as data, by an instruction or a DMA transfer
+    pub const READ: u8 = 0x80;
+    /// Written, never set for the ROM
+    pub const WRITE: u8 = 0x40;
+    /// Part of an executed instruction, opcode or operand
+    pub const EXEC: u8 = 0x20;
+    /// First byte of an executed instruction
+    pub const OPCODE: u8 = 0x10;
+    /// The instruction last ran in emulation mode
+    pub const FLAG_E: u8 = 0x04;
+    /// The instruction last ran with an 8-bit accumulator
+    pub const FLAG_M: u8 = 0x02;
+    /// The instruction last ran with 8-bit index registers
+    pub const FLAG_X: u8 = 0x01;
+}
+
+/// Register widths an opcode logged with `usage` ran with.
+pub fn register_widths(usage: u8) -> RegisterWidths {
+    RegisterWidths {
+        m8: usage & usage::FLAG_M != 0,
+        x8: usage & usage::FLAG_X != 0,
+    }
+}
+
+/// Usage flags of an opcode run with `regs`
+fn register_flags(regs: &Registers) -> u8 {
+    let mut flags = 0;
+    if regs.E {
+        flags |= usage::FLAG_E;
+    }
+    // In emulation mode, both widths are 8 bits and `P.X` is the B flag
+    if regs.E || regs.P.M {
+        flags |= usage::FLAG_M;
+    }
+    if regs.E || regs.P.X {
+        flags |= usage::FLAG_X;
+    }
+    flags
+}
+
+/// Usage of each ROM byte, filled by the emulation loop while enabled.
+///
+/// Disabled by default, a CPU read then only costs a branch. The log is kept when disabled
+/// again, until [`Self::clear`].
+#[derive(Debug, Default)]
+pub struct CodeDataLog {
+    enabled: bool,
+    usage: Vec<u8>,
+    /// ROM offsets of the instruction being run, whose operand fetches are not data reads
+    instruction: Range<usize>,
+}
+
+impl CodeDataLog {
+    pub fn new(rom_size: usize) -> Self {
+        Self {
+            usage: vec![0; rom_size],
+            ..Self::default()
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Logs the fetch of an opcode at ROM `offset`, starting an instruction of `len` bytes
+    /// run with `regs`.
+    pub fn log_instruction(&mut self, offset: usize, len: usize, regs: &Registers) {
+        let end = (offset + len).min(self.usage.len());
+        self.instruction = offset..end;
+        let Some(opcode) = self.usage.get_mut(offset) else {
+            return;
+        };
+        // The flags are those of the last run, like bsnes-plus does
+        *opcode &= !(usage::FLAG_E | usage::FLAG_M | usage::FLAG_X);
+        *opcode |= usage::OPCODE | register_flags(regs);
+        for byte in &mut self.usage[offset..end] {
+            *byte |= usage::EXEC;
+        }
+    }
+
+    /// Logs a read at ROM `offset` other than an opcode fetch. Reads of the operand of the
+    /// instruction being run are part of its execution, the others are data.
+    pub fn log_read(&mut self, offset: usize) {
+        if self.instruction.contains(&offset) {
+            return;
+        }
+        if let Some(byte) = self.usage.get_mut(offset) {
+            *byte |= usage::READ;
+        }
+    }
+
+    /// Usage flags of each ROM byte
+    pub fn usage(&self) -> &[u8] {
+        &self.usage
+    }
+
+    pub fn clear(&mut self) {
+        self.usage.fill(0);
+        self.instruction = 0..0;
+    }
+
+    /// Writes the usage map to `path`, usually the ROM path with the `cdl` extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, &self.usage)
+    }
+
+    /// Adds the usage map saved at `path` to the log, to build it over several sessions.
+    pub fn merge_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let saved = fs::read(path)?;
+        if saved.len() != self.usage.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "usage map of {} bytes for a ROM of {} bytes",
+                    saved.len(),
+                    self.usage.len()
+                ),
+            ));
+        }
+        for (byte, saved) in self.usage.iter_mut().zip(saved) {
+            *byte |= saved;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regs(e: bool, m: bool, x: bool) -> Registers {
+        let mut regs = Registers::default();
+        regs.E = e;
+        regs.P.M = m;
+        regs.P.X = x;
+        regs
+    }
+
+    #[test]
+    fn test_instruction_bytes_are_code() {
+        let mut log = CodeDataLog::new(8);
+        log.log_instruction(1, 3, &regs(false, true, false));
+        log.log_read(2);
+        log.log_read(3);
+
+        assert_eq!(
+            log.usage()[..5],
+            [
+                0,
+                usage::EXEC | usage::OPCODE | usage::FLAG_M,
+                usage::EXEC,
+                usage::EXEC,
+                0
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reads_outside_instruction_are_data() {
+        let mut log = CodeDataLog::new(8);
+        log.log_instruction(0, 3, &regs(true, true, false));
+        log.log_read(6);
+
+        assert_eq!(log.usage()[6], usage::READ);
+        assert_eq!(
+            log.usage()[0],
+            usage::EXEC | usage::OPCODE | usage::FLAG_E | usage::FLAG_M | usage::FLAG_X
+        );
+    }
+
+    #[test]
+    fn test_opcode_flags_of_last_run() {
+        let mut log = CodeDataLog::new(4);
+        log.log_instruction(0, 2, &regs(false, true, true));
+        log.log_instruction(0, 3, &regs(false, false, false));
+
+        assert_eq!(log.usage()[0], usage::EXEC | usage::OPCODE);
+        assert_eq!(
+            register_widths(log.usage()[0]),
+            RegisterWidths {
+                m8: false,
+                x8: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_offsets_past_rom_ignored() {
+        let mut log = CodeDataLog::new(4);
+        log.log_instruction(3, 4, &regs(true, true, true));
+        log.log_read(10);
+
+        assert_eq!(log.usage()[3] & usage::OPCODE, usage::OPCODE);
+        assert_eq!(log.usage().len(), 4);
+    }
+
+    #[test]
+    fn test_save_and_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.cdl");
+        let mut log = CodeDataLog::new(4);
+        log.log_read(1);
+        log.save(&path).unwrap();
+
+        let mut next = CodeDataLog::new(4);
+        next.log_read(2);
+        next.merge_file(&path).unwrap();
+        assert_eq!(next.usage(), [0, usage::READ, usage::READ, 0]);
+
+        assert!(CodeDataLog::new(8).merge_file(&path).is_err());
+    }
+}
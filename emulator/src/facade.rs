@@ -0,0 +1,405 @@
+//! The simplest way to run a ROM: [`Emulator`] takes the ROM file contents and the buttons
+//! held, and gives back pictures, audio and save states.
+//!
+//! ```ignore
+//! let mut emulator = Emulator::new(fs::read("game.sfc")?)?;
+//! let mut input = InputState::default();
+//! loop {
+//!     input.buttons[0] = held_buttons();
+//!     let frame = emulator.run_frame(&input);
+//!     present(&frame.pixels, frame.width, frame.height);
+//!     queue_audio(&emulator.audio_samples());
+//! }
+//! ```
+//!
+//! With the emulation on its own thread, the UI thread updates a [`SharedInput`] instead and
+//! the emulation thread runs each frame with `emulator.run_frame(&input.latch())`.
+//!
+//! Frontends needing more control (pixel format, frame skipping, overlays, recording) drive
+//! a [`System`] instead, which [`Emulator::system`] exposes.
+
+use crate::rsnes::{EmulatorOptions, RSnes};
+use crate::save_state::SaveState;
+pub use crate::save_state::StateError;
+use crate::system::System;
+use bus::rom::Rom;
+use bus::rom::error::RomError;
+use ppu::rendering::framebuffer::PixelFormat;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Buttons held on both controller ports for one frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputState {
+    /// Gamepad of each port, as `bus::joypad::gamepad` flags
+    pub buttons: [u16; 2],
+}
+
+/// [`InputState`] shared between a UI thread updating the controls and the emulation thread,
+/// e.g. in an `Arc`.
+///
+/// The state is double-buffered: the UI thread edits a pending state at any time, which the
+/// emulation thread copies once per frame with [`Self::latch`]. The frame then runs with that
+/// snapshot, so it never sees half of an update, nor controls changing while it runs.
+#[derive(Debug, Default)]
+pub struct SharedInput {
+    /// Edited by the UI thread
+    pending: Mutex<InputState>,
+    /// Copy of `pending` taken at the start of the current frame
+    latched: Mutex<InputState>,
+}
+
+impl SharedInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Edits the pending state. The changes made by `edit` reach the same frame together.
+    pub fn update(&self, edit: impl FnOnce(&mut InputState)) {
+        edit(&mut Self::lock(&self.pending));
+    }
+
+    pub fn set_buttons(&self, port: usize, buttons: u16) {
+        self.update(|input| input.buttons[port] = buttons);
+    }
+
+    /// Takes the pending state as the snapshot of the next frame, and returns it. Called by
+    /// the emulation thread before each frame.
+    pub fn latch(&self) -> InputState {
+        let input = *Self::lock(&self.pending);
+        *Self::lock(&self.latched) = input;
+        input
+    }
+
+    /// Snapshot taken by the last [`Self::latch`].
+    pub fn snapshot(&self) -> InputState {
+        *Self::lock(&self.latched)
+    }
+
+    /// A thread panicking while holding a lock cannot leave a plain copy half written, so a
+    /// poisoned lock is still used.
+    fn lock(state: &Mutex<InputState>) -> MutexGuard<'_, InputState> {
+        state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Picture of a frame, as R, G, B, A bytes.
+#[derive(Debug, Clone, Default)]
+pub struct Frame {
+    pub width: usize,
+    /// Visible lines (224 or 239)
+    pub height: usize,
+    /// `width` × `height` pixels, line by line
+    pub pixels: Vec<u8>,
+}
+
+/// Hashes of the emulated output, for tests and bisect scripts to check that a run is
+/// deterministic without storing its frames.
+///
+/// The hash is 64-bit FNV-1a, stable across platforms and Rust versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputHashes {
+    /// Picture of the last frame
+    pub frame: u64,
+    /// Audio samples of the last frame
+    pub audio: u64,
+    /// Every frame picture since the emulator was created, in order
+    pub frames: u64,
+    /// Every audio sample since the emulator was created, in order
+    pub audio_stream: u64,
+}
+
+impl Default for OutputHashes {
+    fn default() -> Self {
+        Self {
+            frame: FNV_OFFSET,
+            audio: FNV_OFFSET,
+            frames: FNV_OFFSET,
+            audio_stream: FNV_OFFSET,
+        }
+    }
+}
+
+impl OutputHashes {
+    fn add_frame(&mut self, pixels: &[u8], audio: &[i16]) {
+        self.frame = fnv1a(FNV_OFFSET, pixels.iter().copied());
+        self.audio = fnv1a(
+            FNV_OFFSET,
+            audio.iter().flat_map(|sample| sample.to_le_bytes()),
+        );
+        self.frames = fnv1a(self.frames, self.frame.to_le_bytes());
+        self.audio_stream = fnv1a(self.audio_stream, self.audio.to_le_bytes());
+    }
+}
+
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+fn fnv1a(hash: u64, bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(hash, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// A console running one ROM, with the default [`EmulatorOptions`].
+pub struct Emulator {
+    system: System,
+    frame: Frame,
+    /// Interleaved stereo samples not taken by [`Self::audio_samples`] yet
+    audio: Vec<i16>,
+    hashes: OutputHashes,
+}
+
+impl Emulator {
+    /// Loads the contents of a ROM file, copier header included.
+    pub fn new(rom_bytes: Vec<u8>) -> Result<Self, RomError> {
+        let rom = Rom::from_bytes(rom_bytes)?;
+        let rsnes = RSnes::from_rom(rom, &EmulatorOptions::default());
+        Ok(Self {
+            system: System::new(rsnes, PixelFormat::Rgba8888),
+            frame: Frame::default(),
+            audio: Vec::new(),
+            hashes: OutputHashes::default(),
+        })
+    }
+
+    /// Emulates one frame with the buttons of `input` held, and returns its picture.
+    ///
+    /// Once the emulation stopped on an unimplemented feature (see [`Self::crashed`]),
+    /// frames keep the last picture and are silent.
+    pub fn run_frame(&mut self, input: &InputState) -> &Frame {
+        for (port, &buttons) in input.buttons.iter().enumerate() {
+            self.system.set_buttons(port, buttons);
+        }
+        self.system.run_frame();
+        self.audio.extend_from_slice(&self.system.audio);
+
+        let framebuffer = &self.system.renderer.framebuffer;
+        let height = self.system.renderer.active_height;
+        self.frame.width = framebuffer.width();
+        self.frame.height = height;
+        self.frame.pixels.clear();
+        self.frame
+            .pixels
+            .extend_from_slice(&framebuffer[..framebuffer.pitch() * height]);
+        self.hashes
+            .add_frame(&self.frame.pixels, &self.system.audio);
+        &self.frame
+    }
+
+    /// Audio of the frames run since the last call, as interleaved left/right samples at
+    /// [`crate::system::SAMPLE_RATE`].
+    pub fn audio_samples(&mut self) -> Vec<i16> {
+        std::mem::take(&mut self.audio)
+    }
+
+    /// Hashes of the last frame and of the whole run so far.
+    pub fn hashes(&self) -> OutputHashes {
+        self.hashes
+    }
+
+    /// Snapshot of the whole console, to restore with [`Self::load_state`]. The console may
+    /// run a few more master cycles first, to the end of the CPU instruction in progress.
+    pub fn save_state(&mut self) -> Vec<u8> {
+        self.system.save_state().to_bytes()
+    }
+
+    /// Restores a snapshot taken by [`Self::save_state`] on the same game.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        self.system.load_state(&SaveState::from_bytes(state)?)
+    }
+
+    pub fn reset(&mut self) {
+        self.system.reset();
+    }
+
+    /// Whether the emulated program reached an unimplemented feature, only a reset can
+    /// restart it.
+    pub fn crashed(&self) -> bool {
+        self.system.crashed()
+    }
+
+    pub fn system(&mut self) -> &mut System {
+        &mut self.system
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bus::joypad::gamepad;
+    use bus::rom::test_rom::*;
+
+    fn emulator() -> Emulator {
+        Emulator::new(create_valid_lorom(0x20000)).unwrap()
+    }
+
+    #[test]
+    fn test_invalid_rom() {
+        assert!(matches!(
+            Emulator::new(vec![0; 16]),
+            Err(RomError::FileTooSmall)
+        ));
+    }
+
+    #[test]
+    fn test_frame_picture() {
+        let mut emulator = emulator();
+        let frame = emulator.run_frame(&InputState::default());
+
+        assert_eq!(frame.width, 256);
+        assert_eq!(frame.height, 224);
+        assert_eq!(frame.pixels.len(), 256 * 224 * 4);
+    }
+
+    #[test]
+    fn test_input_applied_to_both_ports() {
+        let mut emulator = emulator();
+        emulator.run_frame(&InputState {
+            buttons: [gamepad::START, gamepad::B],
+        });
+
+        let joypads = &mut emulator.system().rsnes.bus.io.joypads;
+        assert_eq!(joypads.auto_read(0xFF), [gamepad::START, gamepad::B, 0, 0]);
+    }
+
+    #[test]
+    fn test_shared_input_latched_per_frame() {
+        let input = SharedInput::new();
+        input.set_buttons(1, gamepad::A);
+        assert_eq!(input.snapshot(), InputState::default());
+
+        assert_eq!(input.latch().buttons, [0, gamepad::A]);
+        input.set_buttons(0, gamepad::START);
+        assert_eq!(input.snapshot().buttons, [0, gamepad::A], "stable until the next latch");
+        assert_eq!(input.latch().buttons, [gamepad::START, gamepad::A]);
+    }
+
+    #[test]
+    fn test_shared_input_updates_not_torn() {
+        use std::sync::Arc;
+
+        let input = Arc::new(SharedInput::new());
+        let ui = {
+            let input = Arc::clone(&input);
+            std::thread::spawn(move || {
+                for buttons in 0..10_000u16 {
+                    input.update(|state| state.buttons = [buttons; 2]);
+                }
+            })
+        };
+
+        while !ui.is_finished() {
+            let [port0, port1] = input.latch().buttons;
+            assert_eq!(port0, port1);
+        }
+        ui.join().unwrap();
+        assert_eq!(input.latch().buttons, [9_999; 2]);
+    }
+
+    #[test]
+    fn test_shared_input_drives_emulation_thread() {
+        use std::sync::Arc;
+
+        let input = Arc::new(SharedInput::new());
+        input.set_buttons(0, gamepad::START);
+        let emulation = {
+            let input = Arc::clone(&input);
+            std::thread::spawn(move || {
+                let mut emulator = emulator();
+                emulator.run_frame(&input.latch());
+                emulator.system().rsnes.bus.io.joypads.auto_read(0xFF)
+            })
+        };
+
+        assert_eq!(emulation.join().unwrap(), [gamepad::START, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_audio_accumulated_until_taken() {
+        let mut emulator = emulator();
+        emulator.run_frame(&InputState::default());
+        let first = emulator.system().audio.len();
+        emulator.run_frame(&InputState::default());
+        let second = emulator.system().audio.len();
+
+        assert_eq!(emulator.audio_samples().len(), first + second);
+        assert!(emulator.audio_samples().is_empty());
+    }
+
+    #[test]
+    fn test_hashes_deterministic() {
+        let run = |frames| {
+            let mut emulator = emulator();
+            for _ in 0..frames {
+                emulator.run_frame(&InputState::default());
+            }
+            emulator.hashes()
+        };
+
+        assert_eq!(run(3), run(3));
+        assert_ne!(run(3).frames, run(2).frames);
+        assert_ne!(run(1), OutputHashes::default());
+    }
+
+    #[test]
+    fn test_hashes_of_last_frame() {
+        let mut emulator = emulator();
+        let pixels = emulator.run_frame(&InputState::default()).pixels.clone();
+        let samples = emulator.audio_samples();
+
+        let hashes = emulator.hashes();
+        assert_eq!(hashes.frame, fnv1a(FNV_OFFSET, pixels));
+        assert_eq!(
+            hashes.audio,
+            fnv1a(FNV_OFFSET, samples.iter().flat_map(|s| s.to_le_bytes()))
+        );
+        assert_eq!(fnv1a(FNV_OFFSET, *b"a"), 0xAF63_DC4C_8601_EC8C);
+    }
+
+    /// Emulator whose CPU loops on a branch, and whose SPC700 runs the NOPs of the empty
+    /// ARAM from $0100 for a few frames, instead of reaching an opcode it does not implement
+    /// yet.
+    fn running_emulator() -> Emulator {
+        let mut rom = create_valid_lorom(0x20000);
+        rom[..2].copy_from_slice(&[0x80, 0xFE]); // BRA -2
+        rom[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0x80]);
+        let mut emulator = Emulator::new(rom).unwrap();
+        emulator.system.rsnes.apu.cpu.regs.pc = 0x0100;
+        emulator
+    }
+
+    #[test]
+    fn test_save_state_round_trip() {
+        let run = |emulator: &mut Emulator| {
+            for _ in 0..3 {
+                emulator.run_frame(&InputState::default());
+            }
+            assert!(!emulator.crashed());
+            (emulator.frame.pixels.clone(), emulator.audio_samples())
+        };
+        let mut emulator = running_emulator();
+        emulator.run_frame(&InputState::default());
+        let state = emulator.save_state();
+        emulator.audio_samples();
+        let expected = run(&mut emulator);
+
+        // The number of samples per frame is paced by the frames run, not saved
+        let mut loaded = running_emulator();
+        loaded.run_frame(&InputState::default());
+        loaded.audio_samples();
+        loaded.system.rsnes.bus.wram.data.fill(0x55);
+        loaded.system.rsnes.cpu.data_bus = 0x55;
+        loaded.load_state(&state).unwrap();
+        assert_eq!(run(&mut loaded), expected);
+        assert_eq!(loaded.save_state(), emulator.save_state());
+    }
+
+    #[test]
+    fn test_invalid_states_rejected() {
+        let mut emulator = emulator();
+        assert_eq!(emulator.load_state(&[]), Err(StateError::NotAState));
+        let mut state = emulator.save_state();
+        state.truncate(state.len() - 1);
+        assert_eq!(emulator.load_state(&state), Err(StateError::Corrupt(None)));
+    }
+}
@@ -0,0 +1,186 @@
+//! Hooks run at points of the emulated frame, for integrations that need to act in step
+//! with the video timing (taking screenshots once the picture is complete, measuring input
+//! latency) without patching the emulation loop.
+//!
+//! ```ignore
+//! system.rsnes.frame_hooks.subscribe(FrameEvent::VblankStart, |_, context: &FrameEventContext| {
+//!     if let Some(renderer) = context.renderer {
+//!         save_screenshot(&renderer.framebuffer, context.ppu.regs.visible_scanlines());
+//!     }
+//! });
+//! ```
+
+use ppu::ppu::PPU;
+use ppu::rendering::renderer::Renderer;
+
+/// Point of the frame at which hooks run, checked once per scanline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameEvent {
+    /// The last visible scanline was drawn and NMI time begins
+    VblankStart,
+    /// The frame is complete, the next one starts at scanline 0
+    VblankEnd,
+    /// Scanline `n` starts, the previous ones having been emulated and drawn
+    Scanline(u16),
+}
+
+/// State of the console when an event happens.
+pub struct FrameEventContext<'a> {
+    pub ppu: &'a PPU,
+    /// Master clock cycles since power on
+    pub master_cycles: u64,
+    /// Renderer drawing the frame, `None` when the frame is emulated without being drawn.
+    /// Frame blending and overlays are applied after the frame, so they are not in its
+    /// framebuffer yet.
+    pub renderer: Option<&'a Renderer>,
+}
+
+/// Called for the events it was subscribed to, see [`FrameHooks::subscribe`].
+pub trait FrameHook: Send {
+    fn event(&mut self, event: FrameEvent, context: &FrameEventContext);
+}
+
+impl<F: FnMut(FrameEvent, &FrameEventContext) + Send> FrameHook for F {
+    fn event(&mut self, event: FrameEvent, context: &FrameEventContext) {
+        self(event, context)
+    }
+}
+
+/// Identifies a subscription, to cancel it with [`FrameHooks::unsubscribe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HookId(u64);
+
+/// Hooks subscribed to frame events, run by the emulation loop after each scanline.
+///
+/// Without hooks, a scanline only costs a branch.
+#[derive(Default)]
+pub struct FrameHooks {
+    hooks: Vec<(HookId, FrameEvent, Box<dyn FrameHook>)>,
+    next_id: u64,
+}
+
+impl FrameHooks {
+    /// Runs `hook` every time `event` happens, after the hooks subscribed before.
+    pub fn subscribe(&mut self, event: FrameEvent, hook: impl FrameHook + 'static) -> HookId {
+        let id = HookId(self.next_id);
+        self.next_id += 1;
+        self.hooks.push((id, event, Box::new(hook)));
+        id
+    }
+
+    /// Cancels a subscription, returning its hook if it was still subscribed.
+    pub fn unsubscribe(&mut self, id: HookId) -> Option<Box<dyn FrameHook>> {
+        let index = self
+            .hooks
+            .iter()
+            .position(|(hook_id, _, _)| *hook_id == id)?;
+        Some(self.hooks.remove(index).2)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Runs the hooks of the events the PPU reached with its last
+    /// [`PPU::step_scanline`]: the V-blank boundary first, then the new scanline.
+    pub fn scanline_stepped(&mut self, context: &FrameEventContext) {
+        if self.hooks.is_empty() {
+            return;
+        }
+
+        let ppu = context.ppu;
+        let boundary = if ppu.frame_ready {
+            Some(FrameEvent::VblankEnd)
+        } else if ppu.vblank_started {
+            Some(FrameEvent::VblankStart)
+        } else {
+            None
+        };
+        for event in boundary
+            .into_iter()
+            .chain([FrameEvent::Scanline(ppu.scanline)])
+        {
+            for (_, subscribed, hook) in &mut self.hooks {
+                if *subscribed == event {
+                    hook.event(event, context);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn recording_hook(events: &Arc<Mutex<Vec<(FrameEvent, u16)>>>) -> impl FrameHook + 'static {
+        let events = Arc::clone(events);
+        move |event, context: &FrameEventContext| {
+            events.lock().unwrap().push((event, context.ppu.scanline))
+        }
+    }
+
+    /// Steps `ppu` through a whole frame, reporting each scanline to `hooks`.
+    fn run_frame(hooks: &mut FrameHooks, ppu: &mut PPU) {
+        loop {
+            ppu.step_scanline();
+            hooks.scanline_stepped(&FrameEventContext {
+                ppu,
+                master_cycles: 0,
+                renderer: None,
+            });
+            if ppu.frame_ready {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_events_fire_once_per_frame() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut hooks = FrameHooks::default();
+        hooks.subscribe(FrameEvent::VblankStart, recording_hook(&events));
+        hooks.subscribe(FrameEvent::VblankEnd, recording_hook(&events));
+        hooks.subscribe(FrameEvent::Scanline(100), recording_hook(&events));
+
+        let mut ppu = PPU::new();
+        run_frame(&mut hooks, &mut ppu);
+        run_frame(&mut hooks, &mut ppu);
+
+        let frame = [
+            (FrameEvent::Scanline(100), 100),
+            (FrameEvent::VblankStart, 225),
+            (FrameEvent::VblankEnd, 0),
+        ];
+        assert_eq!(*events.lock().unwrap(), [frame, frame].concat());
+    }
+
+    #[test]
+    fn test_vblank_end_before_scanline_zero() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut hooks = FrameHooks::default();
+        hooks.subscribe(FrameEvent::Scanline(0), recording_hook(&events));
+        hooks.subscribe(FrameEvent::VblankEnd, recording_hook(&events));
+
+        run_frame(&mut hooks, &mut PPU::new());
+        assert_eq!(
+            *events.lock().unwrap(),
+            [(FrameEvent::VblankEnd, 0), (FrameEvent::Scanline(0), 0)]
+        );
+    }
+
+    #[test]
+    fn test_unsubscribe() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut hooks = FrameHooks::default();
+        let id = hooks.subscribe(FrameEvent::VblankStart, recording_hook(&events));
+        assert!(!hooks.is_empty());
+
+        assert!(hooks.unsubscribe(id).is_some());
+        assert!(hooks.unsubscribe(id).is_none());
+        assert!(hooks.is_empty());
+        run_frame(&mut hooks, &mut PPU::new());
+        assert!(events.lock().unwrap().is_empty());
+    }
+}
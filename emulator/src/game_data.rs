@@ -0,0 +1,438 @@
+//! Data kept per game by frontends, under a directory of their choice with one
+//! subdirectory per ROM named after its SHA-1 (so renaming or moving the ROM keeps it):
+//!
+//! - `settings.cfg`: overrides of the [`EmulatorOptions`] and of the [`System`] picture
+//!   settings for this game, as `key = value` lines (`video_standard = PAL`,
+//!   `vram_access = accurate`, `opcode_policy = trap`, `memory_init = stripes` or
+//!   `memory_init = random:<seed>`, `frame_blend = 50`), applied by
+//!   [`crate::rsnes::RSnes::load_rom_with_game_data`]
+//! - `slot-<n>.state` and `slot-<n>.png`: [`SLOT_COUNT`] numbered save-state slots, each
+//!   with an optional screenshot thumbnail. A slot is dated by its file modification time.
+//!
+//! State contents are opaque here: they are whatever the frontend saved.
+
+use crate::rsnes::EmulatorOptions;
+use crate::system::System;
+use bus::rom::database::RomHashes;
+use common::power_on::MemoryInit;
+use common::video_standard::VideoStandard;
+use cpu::cpu::OpcodePolicy;
+use ppu::headless::write_thumbnail_png;
+use ppu::ppu::VramAccess;
+use ppu::rendering::renderer::Renderer;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Number of save-state slots, numbered from 0
+pub const SLOT_COUNT: usize = 10;
+
+const SETTINGS_FILE: &str = "settings.cfg";
+
+/// Per-game overrides of the user settings; unset fields keep the global value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameSettings {
+    pub video_standard: Option<VideoStandard>,
+    pub vram_access: Option<VramAccess>,
+    pub opcode_policy: Option<OpcodePolicy>,
+    pub memory_init: Option<MemoryInit>,
+    /// Share of the previous frame in percent, see [`System::set_frame_blend`]
+    pub frame_blend: Option<u8>,
+}
+
+impl GameSettings {
+    /// Overrides the fields of `options` set for this game.
+    pub fn apply(&self, options: &mut EmulatorOptions) {
+        if let Some(video_standard) = self.video_standard {
+            options.force_video_standard = Some(video_standard);
+        }
+        if let Some(vram_access) = self.vram_access {
+            options.vram_access = vram_access;
+        }
+        if let Some(opcode_policy) = self.opcode_policy {
+            options.opcode_policy = opcode_policy;
+        }
+        if let Some(memory_init) = self.memory_init {
+            options.memory_init = memory_init;
+        }
+    }
+
+    /// Overrides the picture settings of `system` set for this game.
+    pub fn apply_to_system(&self, system: &mut System) {
+        if let Some(frame_blend) = self.frame_blend {
+            system.set_frame_blend(frame_blend as f32 / 100.0);
+        }
+    }
+
+    /// Parses `key = value` lines; empty lines and `#` comments are skipped, and unknown
+    /// keys ignored so that files written by newer versions still load.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut settings = Self::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(invalid(format!("expected `key = value`, found `{line}`")));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "video_standard" => {
+                    settings.video_standard = Some(match value {
+                        "NTSC" => VideoStandard::NTSC,
+                        "PAL" => VideoStandard::PAL,
+                        "Other" => VideoStandard::Other,
+                        _ => return Err(invalid_value(key, value)),
+                    })
+                }
+                "vram_access" => {
+                    settings.vram_access = Some(match value {
+                        "permissive" => VramAccess::Permissive,
+                        "accurate" => VramAccess::Accurate,
+                        _ => return Err(invalid_value(key, value)),
+                    })
+                }
+                "opcode_policy" => {
+                    settings.opcode_policy = Some(match value {
+                        "nop" => OpcodePolicy::Nop,
+                        "trap" => OpcodePolicy::Trap,
+                        _ => return Err(invalid_value(key, value)),
+                    })
+                }
+                "memory_init" => {
+                    settings.memory_init = Some(match value {
+                        "zero" => MemoryInit::Zero,
+                        "stripes" => MemoryInit::Stripes,
+                        _ => match value.strip_prefix("random:").map(str::parse) {
+                            Some(Ok(seed)) => MemoryInit::Random(seed),
+                            _ => return Err(invalid_value(key, value)),
+                        },
+                    })
+                }
+                "frame_blend" => {
+                    settings.frame_blend = match value.parse() {
+                        Ok(percent @ 0..=100) => Some(percent),
+                        _ => return Err(invalid_value(key, value)),
+                    }
+                }
+                _ => tracing::warn!(key, "Unknown game setting ignored"),
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Formats the settings in the format read by [`Self::parse`], unset fields omitted.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if let Some(video_standard) = self.video_standard {
+            let _ = writeln!(text, "video_standard = {video_standard}");
+        }
+        if let Some(vram_access) = self.vram_access {
+            let value = match vram_access {
+                VramAccess::Permissive => "permissive",
+                VramAccess::Accurate => "accurate",
+            };
+            let _ = writeln!(text, "vram_access = {value}");
+        }
+        if let Some(opcode_policy) = self.opcode_policy {
+            let value = match opcode_policy {
+                OpcodePolicy::Nop => "nop",
+                OpcodePolicy::Trap => "trap",
+            };
+            let _ = writeln!(text, "opcode_policy = {value}");
+        }
+        if let Some(memory_init) = self.memory_init {
+            let _ = match memory_init {
+                MemoryInit::Zero => writeln!(text, "memory_init = zero"),
+                MemoryInit::Stripes => writeln!(text, "memory_init = stripes"),
+                MemoryInit::Random(seed) => writeln!(text, "memory_init = random:{seed}"),
+            };
+        }
+        if let Some(frame_blend) = self.frame_blend {
+            let _ = writeln!(text, "frame_blend = {frame_blend}");
+        }
+        text
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn invalid_value(key: &str, value: &str) -> io::Error {
+    invalid(format!("invalid value `{value}` for `{key}`"))
+}
+
+/// A used save-state slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotInfo {
+    pub slot: usize,
+    pub saved_at: SystemTime,
+    pub has_thumbnail: bool,
+}
+
+/// Settings and save-state slots of one game.
+pub struct GameData {
+    dir: PathBuf,
+}
+
+impl GameData {
+    /// Data of the ROM with `hashes`, under `root`. Nothing is created until saved.
+    pub fn new<P: AsRef<Path>>(root: P, hashes: &RomHashes) -> Self {
+        Self {
+            dir: root.as_ref().join(hashes.sha1_hex()),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Settings of the game, all unset if none were saved.
+    pub fn load_settings(&self) -> io::Result<GameSettings> {
+        match fs::read_to_string(self.dir.join(SETTINGS_FILE)) {
+            Ok(text) => GameSettings::parse(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(GameSettings::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save_settings(&self, settings: &GameSettings) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(SETTINGS_FILE), settings.to_text())
+    }
+
+    fn slot_path(&self, slot: usize, extension: &str) -> io::Result<PathBuf> {
+        if slot >= SLOT_COUNT {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no save-state slot {slot}, there are {SLOT_COUNT}"),
+            ));
+        }
+        Ok(self.dir.join(format!("slot-{slot}.{extension}")))
+    }
+
+    /// Writes `state` to `slot`, replacing what it held, with a thumbnail of the picture of
+    /// `screenshot` if given.
+    pub fn save_slot(
+        &self,
+        slot: usize,
+        state: &[u8],
+        screenshot: Option<&Renderer>,
+    ) -> io::Result<()> {
+        let state_path = self.slot_path(slot, "state")?;
+        let thumbnail_path = self.slot_path(slot, "png")?;
+        fs::create_dir_all(&self.dir)?;
+
+        match screenshot {
+            Some(renderer) => {
+                let mut png = Vec::new();
+                write_thumbnail_png(renderer, &mut png)
+                    .map_err(|err| io::Error::other(err.to_string()))?;
+                fs::write(thumbnail_path, png)?;
+            }
+            None => remove_if_exists(&thumbnail_path)?,
+        }
+        fs::write(state_path, state)
+    }
+
+    pub fn load_slot(&self, slot: usize) -> io::Result<Vec<u8>> {
+        fs::read(self.slot_path(slot, "state")?)
+    }
+
+    /// PNG thumbnail of `slot`, if it has one.
+    pub fn thumbnail(&self, slot: usize) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.slot_path(slot, "png")?) {
+            Ok(png) => Ok(Some(png)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn delete_slot(&self, slot: usize) -> io::Result<()> {
+        remove_if_exists(&self.slot_path(slot, "state")?)?;
+        remove_if_exists(&self.slot_path(slot, "png")?)
+    }
+
+    /// Used slots, e.g. to show a slot picker.
+    pub fn slots(&self) -> io::Result<Vec<SlotInfo>> {
+        let mut slots = Vec::new();
+        for slot in 0..SLOT_COUNT {
+            let metadata = match fs::metadata(self.slot_path(slot, "state")?) {
+                Ok(metadata) => metadata,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            slots.push(SlotInfo {
+                slot,
+                saved_at: metadata.modified()?,
+                has_thumbnail: self.slot_path(slot, "png")?.exists(),
+            });
+        }
+        Ok(slots)
+    }
+
+    /// Most recently saved slot, the one a "load last state" key resumes.
+    pub fn latest_slot(&self) -> io::Result<Option<usize>> {
+        let slots = self.slots()?;
+        Ok(slots
+            .iter()
+            .max_by_key(|info| info.saved_at)
+            .map(|info| info.slot))
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsnes::RSnes;
+    use bus::rom::Rom;
+    use bus::rom::test_rom::create_valid_lorom;
+    use ppu::rendering::framebuffer::PixelFormat;
+    use tempfile::tempdir;
+
+    fn hashes() -> RomHashes {
+        RomHashes::of(b"rom")
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let settings = GameSettings {
+            video_standard: Some(VideoStandard::PAL),
+            vram_access: Some(VramAccess::Accurate),
+            opcode_policy: Some(OpcodePolicy::Trap),
+            memory_init: Some(MemoryInit::Random(1234)),
+            frame_blend: Some(50),
+        };
+        assert_eq!(GameSettings::parse(&settings.to_text()).unwrap(), settings);
+        assert_eq!(GameSettings::default().to_text(), "");
+    }
+
+    #[test]
+    fn test_settings_parse() {
+        let text = "# Needs the PAL timings\n\nvideo_standard = PAL\nfuture_option = 1\n";
+        let settings = GameSettings::parse(text).unwrap();
+        assert_eq!(settings.video_standard, Some(VideoStandard::PAL));
+        assert_eq!(settings.vram_access, None);
+
+        assert!(GameSettings::parse("vram_access = sometimes").is_err());
+        assert!(GameSettings::parse("video_standard").is_err());
+        assert!(GameSettings::parse("memory_init = random:soon").is_err());
+        assert!(GameSettings::parse("frame_blend = 101").is_err());
+    }
+
+    #[test]
+    fn test_settings_override_options() {
+        let mut options = EmulatorOptions {
+            opcode_policy: OpcodePolicy::Trap,
+            ..Default::default()
+        };
+        let settings = GameSettings {
+            vram_access: Some(VramAccess::Accurate),
+            ..Default::default()
+        };
+        settings.apply(&mut options);
+
+        assert_eq!(options.vram_access, VramAccess::Accurate);
+        assert_eq!(options.opcode_policy, OpcodePolicy::Trap);
+        assert_eq!(options.force_video_standard, None);
+    }
+
+    #[test]
+    fn test_settings_override_system() {
+        let rom = Rom::from_bytes(create_valid_lorom(0x20000)).unwrap();
+        let rsnes = RSnes::from_rom(rom, &EmulatorOptions::default());
+        let mut system = System::new(rsnes, PixelFormat::Rgb565);
+        GameSettings::default().apply_to_system(&mut system);
+        assert_eq!(system.frame_blend(), 0.0);
+
+        let settings = GameSettings {
+            frame_blend: Some(50),
+            ..Default::default()
+        };
+        settings.apply_to_system(&mut system);
+        assert_eq!(system.frame_blend(), 0.5);
+    }
+
+    #[test]
+    fn test_settings_stored_per_rom() {
+        let root = tempdir().unwrap();
+        let data = GameData::new(root.path(), &hashes());
+        assert_eq!(data.load_settings().unwrap(), GameSettings::default());
+
+        let settings = GameSettings {
+            video_standard: Some(VideoStandard::NTSC),
+            ..Default::default()
+        };
+        data.save_settings(&settings).unwrap();
+        assert_eq!(data.load_settings().unwrap(), settings);
+        assert_eq!(data.dir(), root.path().join(hashes().sha1_hex()));
+
+        let other = GameData::new(root.path(), &RomHashes::of(b"other rom"));
+        assert_eq!(other.load_settings().unwrap(), GameSettings::default());
+    }
+
+    #[test]
+    fn test_slots() {
+        let root = tempdir().unwrap();
+        let data = GameData::new(root.path(), &hashes());
+        assert!(data.slots().unwrap().is_empty());
+        assert_eq!(data.latest_slot().unwrap(), None);
+
+        data.save_slot(3, b"state 3", Some(&Renderer::new()))
+            .unwrap();
+        data.save_slot(7, b"state 7", None).unwrap();
+
+        assert_eq!(data.load_slot(3).unwrap(), b"state 3");
+        let slots: Vec<(usize, bool)> = data
+            .slots()
+            .unwrap()
+            .iter()
+            .map(|info| (info.slot, info.has_thumbnail))
+            .collect();
+        assert_eq!(slots, [(3, true), (7, false)]);
+        assert!(data.thumbnail(3).unwrap().unwrap().starts_with(b"\x89PNG"));
+        assert_eq!(data.thumbnail(7).unwrap(), None);
+    }
+
+    #[test]
+    fn test_overwriting_slot_without_screenshot_drops_thumbnail() {
+        let root = tempdir().unwrap();
+        let data = GameData::new(root.path(), &hashes());
+        data.save_slot(0, b"old", Some(&Renderer::new())).unwrap();
+        data.save_slot(0, b"new", None).unwrap();
+
+        assert_eq!(data.load_slot(0).unwrap(), b"new");
+        assert_eq!(data.thumbnail(0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_delete_slot() {
+        let root = tempdir().unwrap();
+        let data = GameData::new(root.path(), &hashes());
+        data.save_slot(1, b"state", Some(&Renderer::new())).unwrap();
+        data.delete_slot(1).unwrap();
+        data.delete_slot(1).unwrap();
+
+        assert!(data.slots().unwrap().is_empty());
+        assert_eq!(data.thumbnail(1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_slot_out_of_range() {
+        let root = tempdir().unwrap();
+        let data = GameData::new(root.path(), &hashes());
+        let err = data.save_slot(SLOT_COUNT, b"state", None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(data.load_slot(SLOT_COUNT).is_err());
+    }
+}
@@ -0,0 +1,22 @@
+//! The whole console (CPU, PPU, APU, bus and cartridge) without any frontend, shared by the
+//! SDL application and the embedding crates. [`Emulator`] is the entry point for new
+//! frontends and examples.
+//!
+//! Components report through `tracing` events (with the PPU scanline or master cycle as
+//! fields) inside a span per frame: frontends install the subscriber of their choice.
+
+pub mod code_data_log;
+pub mod facade;
+pub mod frame_events;
+pub mod game_data;
+pub mod profiler;
+pub mod rsnes;
+pub mod save_state;
+pub mod scheduler;
+#[cfg(feature = "stats")]
+pub mod stats;
+pub mod system;
+pub mod watch;
+
+pub use facade::{Emulator, Frame, InputState, OutputHashes, SharedInput, StateError};
+pub use rsnes::{EmulatorOptions, Overclock, RSnes};
@@ -0,0 +1,293 @@
+//! Instruction profiler: counts the instructions run by the CPU by opcode and by page of the
+//! address space, to find the opcodes worth optimizing in the emulator and the routines a
+//! game spends its time in.
+//!
+//! Only the main CPU is profiled, a coprocessor running its own code is left out.
+
+use common::snes_address::SnesAddress;
+use cpu::disasm;
+use std::cmp::Reverse;
+use std::fmt;
+
+/// Size in bytes of the pages instructions are counted by
+pub const PAGE_SIZE: usize = 0x100;
+
+const PAGES: usize = 0x100_0000 / PAGE_SIZE;
+
+/// Instructions run by opcode and by page, filled by the emulation loop while enabled.
+///
+/// Disabled by default, an opcode fetch then only costs a branch. The counts are kept when
+/// disabled again, until [`Self::clear`].
+#[derive(Debug)]
+pub struct Profiler {
+    enabled: bool,
+    instructions: u64,
+    opcodes: [u64; 256],
+    /// Instructions whose opcode lies in each page of the 24-bit address space
+    pages: Vec<u64>,
+}
+
+/// Instructions run at the same address range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HotRange {
+    /// Start of the first page of the range
+    pub start: SnesAddress,
+    /// Last byte of the last page of the range, in the bank of `start`
+    pub end: SnesAddress,
+    pub instructions: u64,
+}
+
+/// The most run opcodes, banks and address ranges, see [`Profiler::report`]. Its `Display`
+/// implementation writes a plain text report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileReport {
+    /// Instructions run since the profiler was last cleared
+    pub instructions: u64,
+    /// Opcodes with their count, most run first
+    pub opcodes: Vec<(u8, u64)>,
+    /// Banks with their count, most run first
+    pub banks: Vec<(u8, u64)>,
+    /// Runs of consecutive pages with instructions, within a bank, most run first
+    pub ranges: Vec<HotRange>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            instructions: 0,
+            opcodes: [0; 256],
+            pages: vec![0; PAGES],
+        }
+    }
+}
+
+impl Profiler {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Counts the fetch of `opcode` at `addr`.
+    pub fn log_instruction(&mut self, addr: SnesAddress, opcode: u8) {
+        self.instructions += 1;
+        self.opcodes[opcode as usize] += 1;
+        self.pages[usize::from(addr) / PAGE_SIZE] += 1;
+    }
+
+    /// Instructions run since the profiler was last cleared
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Times `opcode` was run
+    pub fn opcode_count(&self, opcode: u8) -> u64 {
+        self.opcodes[opcode as usize]
+    }
+
+    /// Instructions run in each bank
+    pub fn bank_counts(&self) -> [u64; 256] {
+        let pages_per_bank = PAGES / 256;
+        core::array::from_fn(|bank| {
+            self.pages[bank * pages_per_bank..(bank + 1) * pages_per_bank]
+                .iter()
+                .sum()
+        })
+    }
+
+    /// Ranges of consecutive pages with instructions, never across a bank boundary, in
+    /// address order.
+    pub fn ranges(&self) -> Vec<HotRange> {
+        let pages_per_bank = PAGES / 256;
+        let mut ranges = Vec::new();
+        let mut current: Option<(usize, usize, u64)> = None;
+        for (page, &count) in self.pages.iter().enumerate() {
+            current = match current {
+                Some((first, _, total)) if count > 0 && page % pages_per_bank != 0 => {
+                    Some((first, page, total + count))
+                }
+                _ => {
+                    if let Some(range) = current {
+                        ranges.push(range);
+                    }
+                    (count > 0).then_some((page, page, count))
+                }
+            };
+        }
+        ranges.extend(current);
+        ranges
+            .into_iter()
+            .map(|(first, last, instructions)| HotRange {
+                start: SnesAddress::from(first * PAGE_SIZE),
+                end: SnesAddress::from((last + 1) * PAGE_SIZE - 1),
+                instructions,
+            })
+            .collect()
+    }
+
+    /// The `top` most run opcodes and ranges, and every bank with instructions.
+    pub fn report(&self, top: usize) -> ProfileReport {
+        let mut opcodes: Vec<_> = (0..=255)
+            .map(|opcode| (opcode, self.opcode_count(opcode)))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        opcodes.sort_by_key(|&(_, count)| Reverse(count));
+        opcodes.truncate(top);
+
+        let mut banks: Vec<_> = (0..=255)
+            .zip(self.bank_counts())
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        banks.sort_by_key(|&(_, count)| Reverse(count));
+
+        let mut ranges = self.ranges();
+        ranges.sort_by_key(|range| Reverse(range.instructions));
+        ranges.truncate(top);
+
+        ProfileReport {
+            instructions: self.instructions,
+            opcodes,
+            banks,
+            ranges,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.instructions = 0;
+        self.opcodes.fill(0);
+        self.pages.fill(0);
+    }
+}
+
+impl ProfileReport {
+    /// Share of the instructions in percent
+    fn percent(&self, count: u64) -> f64 {
+        count as f64 * 100.0 / self.instructions.max(1) as f64
+    }
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Instructions: {}", self.instructions)?;
+
+        writeln!(f, "\nOpcodes:")?;
+        for &(opcode, count) in &self.opcodes {
+            let mnemonic = disasm::mnemonic(opcode);
+            let mode = disasm::AddrMode::of(opcode);
+            let percent = self.percent(count);
+            writeln!(
+                f,
+                "  ${opcode:02X} {mnemonic} {:<24} {count:>12} {percent:6.2}%",
+                format!("{mode:?}")
+            )?;
+        }
+
+        writeln!(f, "\nBanks:")?;
+        for &(bank, count) in &self.banks {
+            let percent = self.percent(count);
+            writeln!(f, "  ${bank:02X} {count:>12} {percent:6.2}%")?;
+        }
+
+        writeln!(f, "\nHot ranges:")?;
+        for range in &self.ranges {
+            let (start, end) = (range.start, range.end);
+            let percent = self.percent(range.instructions);
+            writeln!(
+                f,
+                "  ${:02X}:{:04X}-{:04X} {:>12} {percent:6.2}%",
+                start.bank, start.addr, end.addr, range.instructions
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::snes_addr;
+
+    #[test]
+    fn test_disabled_by_default() {
+        assert!(!Profiler::default().enabled());
+    }
+
+    #[test]
+    fn test_counts_opcodes_and_banks() {
+        let mut profiler = Profiler::default();
+        profiler.log_instruction(snes_addr!(0x80:0x8000), 0xEA);
+        profiler.log_instruction(snes_addr!(0x80:0x8001), 0xA9);
+        profiler.log_instruction(snes_addr!(0xC0:0x0000), 0xEA);
+
+        assert_eq!(profiler.instructions(), 3);
+        assert_eq!(profiler.opcode_count(0xEA), 2);
+        assert_eq!(profiler.opcode_count(0x00), 0);
+        let banks = profiler.bank_counts();
+        assert_eq!((banks[0x80], banks[0xC0], banks[0x00]), (2, 1, 0));
+    }
+
+    #[test]
+    fn test_ranges_merge_pages_within_bank() {
+        let mut profiler = Profiler::default();
+        profiler.log_instruction(snes_addr!(0x00:0x80FF), 0xEA);
+        profiler.log_instruction(snes_addr!(0x00:0x8100), 0xEA);
+        profiler.log_instruction(snes_addr!(0x00:0xFFFF), 0xEA);
+        profiler.log_instruction(snes_addr!(0x01:0x0000), 0xEA);
+
+        assert_eq!(
+            profiler.ranges(),
+            [
+                HotRange {
+                    start: snes_addr!(0x00:0x8000),
+                    end: snes_addr!(0x00:0x81FF),
+                    instructions: 2
+                },
+                HotRange {
+                    start: snes_addr!(0x00:0xFF00),
+                    end: snes_addr!(0x00:0xFFFF),
+                    instructions: 1
+                },
+                HotRange {
+                    start: snes_addr!(0x01:0x0000),
+                    end: snes_addr!(0x01:0x00FF),
+                    instructions: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_most_run_first() {
+        let mut profiler = Profiler::default();
+        profiler.log_instruction(snes_addr!(0x00:0x8000), 0xEA);
+        for _ in 0..3 {
+            profiler.log_instruction(snes_addr!(0x80:0x9000), 0xA9);
+        }
+        profiler.log_instruction(snes_addr!(0x7E:0x2000), 0x60);
+
+        let report = profiler.report(2);
+        assert_eq!(report.opcodes, [(0xA9, 3), (0x60, 1)]);
+        assert_eq!(report.banks, [(0x80, 3), (0x00, 1), (0x7E, 1)]);
+        assert_eq!(report.ranges.len(), 2);
+        assert_eq!(report.ranges[0].start, snes_addr!(0x80:0x9000));
+
+        let text = report.to_string();
+        assert!(text.starts_with("Instructions: 5\n"));
+        assert!(text.contains("  $A9 LDA ImmediateM"));
+        assert!(text.contains("  $80:9000-90FF            3  60.00%"));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut profiler = Profiler::default();
+        profiler.log_instruction(snes_addr!(0x00:0x8000), 0xEA);
+        profiler.clear();
+
+        assert_eq!(profiler.instructions(), 0);
+        assert_eq!(profiler.report(10).opcodes, []);
+        assert!(profiler.ranges().is_empty());
+    }
+}
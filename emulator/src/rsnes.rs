@@ -0,0 +1,1522 @@
+use apu::Apu;
+use apu::dsp::OutputMode;
+use bus::Bus;
+#[cfg(feature = "access-log")]
+use bus::access_log::AccessSource;
+use bus::rom::Rom;
+use bus::rom::header::mapping_mode::MappingMode;
+use bus::rom::rom::LoadOptions;
+use bus::rom::header::cartridge_hardware::Coprocessor;
+use common::power_on::MemoryInit;
+use common::snes_address::SnesAddress;
+use common::video_standard::{RegionSelection, VideoStandard};
+use cpu::cpu::CPU;
+use cpu::cpu::CycleResult;
+use cpu::cpu::{Halt, OpcodePolicy};
+use cpu::disasm::{AddrMode, RegisterWidths};
+use ppu::ppu::{PPU, VramAccess};
+use ppu::rendering::renderer::Renderer;
+use sa1::Sa1;
+use crate::code_data_log::CodeDataLog;
+use crate::frame_events::{FrameEventContext, FrameHooks};
+use crate::game_data::{GameData, GameSettings};
+use crate::profiler::Profiler;
+use crate::save_state::{CpuSection, SaveState, StateError, Timing};
+use crate::watch::Breakpoint;
+#[cfg(feature = "stats")]
+use crate::stats::{FrameStats, Subsystem};
+use std::error::Error;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Extension of the file storing the code/data log next to the ROM
+const CODE_DATA_LOG_EXTENSION: &str = "cdl";
+/// Extension of the file storing the instruction profile report next to the ROM
+const PROFILE_EXTENSION: &str = "profile";
+/// Opcodes and address ranges listed in the saved profile report
+const PROFILE_REPORT_TOP: usize = 32;
+
+/// User settings applied when loading a ROM.
+#[derive(Debug, Clone, Default)]
+pub struct EmulatorOptions {
+    /// Video standard to emulate instead of the one from the ROM header
+    pub force_video_standard: Option<VideoStandard>,
+    /// What the CPU does on WDM and unimplemented opcodes, which are logged either way
+    pub opcode_policy: OpcodePolicy,
+    /// Whether VRAM and CGRAM writes during active display are dropped, like on hardware
+    pub vram_access: VramAccess,
+    /// Whether the audio goes through the DAC model of the console or is the raw DSP mix
+    pub audio_output: OutputMode,
+    /// ROM mapping to use instead of the detected one, for ROMs the detection gets wrong or
+    /// cannot decide on
+    pub force_mapping: Option<MappingMode>,
+    /// Contents of WRAM and VRAM at power on
+    pub memory_init: MemoryInit,
+    /// Whether to log the ROM bytes run as code or read as data, adding to the log saved
+    /// next to the ROM by previous sessions
+    pub code_data_log: bool,
+    /// Whether to count the instructions run by opcode and address, saving a report next
+    /// to the ROM
+    pub profile: bool,
+    /// Clock rates above the hardware ones, to reduce slowdown in games
+    pub overclock: Overclock,
+}
+
+/// Extra clock given to the CPU and the SA-1, which run faster than on hardware while the
+/// PPU, the APU and DMA keep their timing: games that drop frames because the CPU cannot
+/// finish its work in time slow down less.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overclock {
+    /// Master cycles run by the CPU at the end of each scanline, while the rest of the
+    /// console is stopped. They do not run during DMA transfers.
+    pub cpu_extra_cycles: u32,
+    /// Master cycles run by the SA-1 for each master cycle of the console, 1 being the
+    /// hardware rate
+    pub sa1_multiplier: u32,
+}
+
+impl Overclock {
+    /// Hardware clock rates.
+    pub const NONE: Self = Self {
+        cpu_extra_cycles: 0,
+        sa1_multiplier: 1,
+    };
+}
+
+impl Default for Overclock {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// Where a bounded run ([`RSnes::run_until`], [`RSnes::run_for`]) stopped. The next run
+/// resumes from there, the CPU included: it is stepped cycle by cycle, so it can stop in the
+/// middle of an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunPosition {
+    /// Master cycles run since power on
+    pub master_cycles: u64,
+    /// PPU scanline of the next master cycle
+    pub scanline: u16,
+    /// Master cycle within the scanline (0-1363) of the next master cycle
+    pub line_cycle: u16,
+    /// Whether the CPU is in the middle of an instruction, see [`CPU::mid_instruction`]
+    pub mid_instruction: bool,
+    /// Frames completed during the run
+    pub frames: u32,
+    /// Why the CPU halted, when the run stopped early because it did
+    pub halted: Option<Halt>,
+    /// Index in [`RSnes::breakpoints`] of the breakpoint the run stopped at
+    pub breakpoint: Option<usize>,
+}
+
+/// The whole console. Components are owned here and lent to each other for the duration of
+/// a call (e.g. the bus gets the PPU and APU on each access), so there is no shared
+/// ownership or interior mutability and a console can be moved to another thread.
+pub struct RSnes {
+    pub rom_path: Option<PathBuf>,
+    pub bus: Bus,
+    pub cpu: CPU,
+    pub ppu: PPU,
+    pub apu: Apu,
+    pub video_standard: VideoStandard,
+    pub region: RegionSelection,
+    pub master_cycles: u64,
+    /// Master cycle within the current scanline of the next master cycle, so that frames
+    /// and bounded runs can stop and resume anywhere in a scanline
+    line_cycle: u16,
+    /// CPU cycles run since power on
+    pub cpu_cycles: u64,
+    pub cpu_master_cycles_to_wait: u32,
+    /// Master cycles the CPU is stopped for by the last DMA transfer
+    pub dma_master_cycles_to_wait: u32,
+    overclock: Overclock,
+    /// Master cycles run by the coprocessor for each master cycle of the console
+    coprocessor_cycles: u32,
+    /// Run by [`Self::run_frame`] at the frame events they subscribed to
+    pub frame_hooks: FrameHooks,
+    /// ROM bytes run as code or read as data, see [`CodeDataLog::set_enabled`]
+    pub code_data_log: CodeDataLog,
+    /// Instructions run by opcode and address, see [`Profiler::set_enabled`]
+    pub profiler: Profiler,
+    /// Bounded runs stop at them, see [`Self::run_until`]; whole frames do not
+    pub breakpoints: Vec<Breakpoint>,
+    /// First breakpoint hit since the start of the current run
+    breakpoint_hit: Option<usize>,
+    #[cfg(feature = "stats")]
+    pub stats: FrameStats,
+}
+
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    assert_send::<RSnes>();
+};
+
+impl RSnes {
+    pub fn load_rom<P: AsRef<Path>>(rom_path: &P) -> Result<Self, Box<dyn Error>> {
+        Self::load_rom_with_options(rom_path, &EmulatorOptions::default())
+    }
+
+    pub fn load_rom_with_options<P: AsRef<Path>>(
+        rom_path: &P,
+        options: &EmulatorOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        let rom = Self::read_rom(rom_path.as_ref(), options)?;
+        Self::open_rom(rom, rom_path.as_ref(), options)
+    }
+
+    /// [`Self::load_rom_with_options`] with `options` overridden by the settings saved for
+    /// the game under `game_data_root` (see [`GameData`]). The settings are returned for
+    /// the frontend to apply the picture ones, with [`GameSettings::apply_to_system`].
+    ///
+    /// Unreadable settings are logged and ignored rather than keeping the game from loading.
+    pub fn load_rom_with_game_data<P: AsRef<Path>>(
+        rom_path: &P,
+        options: &EmulatorOptions,
+        game_data_root: &Path,
+    ) -> Result<(Self, GameSettings), Box<dyn Error>> {
+        let rom = Self::read_rom(rom_path.as_ref(), options)?;
+        let game_data = GameData::new(game_data_root, &rom.hashes());
+        let settings = game_data.load_settings().unwrap_or_else(|err| {
+            tracing::warn!(%err, dir = %game_data.dir().display(), "Game settings ignored");
+            GameSettings::default()
+        });
+        let mut options = options.clone();
+        settings.apply(&mut options);
+        Ok((Self::open_rom(rom, rom_path.as_ref(), &options)?, settings))
+    }
+
+    fn read_rom(rom_path: &Path, options: &EmulatorOptions) -> Result<Rom, Box<dyn Error>> {
+        let load_options = LoadOptions {
+            force_mapping: options.force_mapping,
+        };
+        Ok(Rom::load_from_file_with_options(rom_path, &load_options)?)
+    }
+
+    /// Builds the console around `rom`, read from `rom_path`, with the files kept next to it.
+    fn open_rom(
+        rom: Rom,
+        rom_path: &Path,
+        options: &EmulatorOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut rsnes = Self::from_rom(rom, options);
+        rsnes.rom_path = Some(rom_path.to_path_buf());
+        let coprocessor_data_path = rsnes.coprocessor_data_path(rom_path);
+        rsnes.bus.load_coprocessor_data(coprocessor_data_path)?;
+        if options.code_data_log {
+            match rsnes
+                .code_data_log
+                .merge_file(rom_path.with_extension(CODE_DATA_LOG_EXTENSION))
+            {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+        }
+        Ok(rsnes)
+    }
+
+    /// Builds the console around a ROM already in memory; nothing is read from or saved
+    /// to disk.
+    pub fn from_rom(rom: Rom, options: &EmulatorOptions) -> Self {
+        let mut bus = Bus::from_rom(rom);
+        bus.wram.power_on(options.memory_init);
+        let hardware = &bus.rom.header.hardware;
+        if hardware.has_coprocessor() && hardware.coprocessor == Some(Coprocessor::SA1) {
+            bus.set_coprocessor(Box::new(Sa1::new(&bus.rom)));
+        }
+        let region =
+            RegionSelection::new(bus.rom.header.video_standard, options.force_video_standard);
+        let video_standard = region.effective;
+        let mut cpu = CPU::poweron();
+        cpu.set_opcode_policy(options.opcode_policy);
+        let mut ppu = PPU::with_video_standard(video_standard);
+        ppu.vram_access = options.vram_access;
+        ppu.vram.power_on(options.memory_init);
+        let mut apu = Apu::new();
+        apu.set_master_clock_hz(video_standard.master_clock_hz());
+        apu.memory.dsp.set_output_mode(options.audio_output);
+        let mut code_data_log = CodeDataLog::new(bus.rom.data.len());
+        code_data_log.set_enabled(options.code_data_log);
+        let mut profiler = Profiler::default();
+        profiler.set_enabled(options.profile);
+
+        let mut rsnes = Self {
+            rom_path: None,
+            bus,
+            cpu,
+            ppu,
+            apu,
+            video_standard,
+            region,
+            master_cycles: 0,
+            line_cycle: 0,
+            cpu_cycles: 0,
+            cpu_master_cycles_to_wait: 0,
+            dma_master_cycles_to_wait: 0,
+            overclock: Overclock::NONE,
+            coprocessor_cycles: 1,
+            frame_hooks: FrameHooks::default(),
+            code_data_log,
+            profiler,
+            breakpoints: Vec::new(),
+            breakpoint_hit: None,
+            #[cfg(feature = "stats")]
+            stats: FrameStats::default(),
+        };
+        rsnes.set_overclock(options.overclock);
+        rsnes
+    }
+
+    pub fn overclock(&self) -> Overclock {
+        self.overclock
+    }
+
+    /// Changes the clock rates from the next master cycle. The SA-1 multiplier is ignored
+    /// without an SA-1, and clamped to at least 1.
+    pub fn set_overclock(&mut self, overclock: Overclock) {
+        self.overclock = overclock;
+        let hardware = &self.bus.rom.header.hardware;
+        self.coprocessor_cycles =
+            if hardware.has_coprocessor() && hardware.coprocessor == Some(Coprocessor::SA1) {
+                overclock.sa1_multiplier.max(1)
+            } else {
+                1
+            };
+    }
+
+    /// File next to the ROM storing the coprocessor state (S-RTC clock offset, SA-1 BW-RAM),
+    /// with an extension depending on the coprocessor.
+    fn coprocessor_data_path(&self, rom_path: &Path) -> PathBuf {
+        let extension = self.bus.coprocessor.as_ref().map_or("sav", |c| c.save_data_extension());
+        rom_path.with_extension(extension)
+    }
+
+    /// Writes the state to keep between sessions next to the ROM, if it was loaded from a file.
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(rom_path) = &self.rom_path else {
+            return Ok(());
+        };
+        self.bus
+            .save_coprocessor_data(self.coprocessor_data_path(rom_path))?;
+        if self.code_data_log.enabled() {
+            self.code_data_log
+                .save(rom_path.with_extension(CODE_DATA_LOG_EXTENSION))?;
+        }
+        if self.profiler.enabled() {
+            let report = self.profiler.report(PROFILE_REPORT_TOP);
+            std::fs::write(rom_path.with_extension(PROFILE_EXTENSION), report.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Duration of a master cycle in seconds, which depends on the video standard
+    pub fn master_cycle_duration(&self) -> f64 {
+        1.0 / self.video_standard.master_clock_hz() as f64
+    }
+
+    /// Timestamps the following bus accesses for the access tap.
+    #[cfg(feature = "access-log")]
+    fn set_access_source(&mut self, source: AccessSource) {
+        self.bus
+            .access_tap
+            .set_context(source, self.cpu_cycles, self.master_cycles);
+    }
+
+    fn dma_transfer(&mut self) {
+        #[cfg(feature = "stats")]
+        let start = self.stats.start();
+        #[cfg(feature = "access-log")]
+        self.set_access_source(AccessSource::Dma);
+        let mdmaen = self.bus.io.mdmaen;
+
+        for channel_nb in 0..8 {
+            if mdmaen & (1 << channel_nb) == 0 {
+                continue;
+            }
+            self.execute_dma_channel(channel_nb);
+        }
+
+        self.bus.io.mdmaen = 0;
+        #[cfg(feature = "stats")]
+        self.stats.record(Subsystem::Dma, start);
+    }
+
+    fn execute_dma_channel(&mut self, channel_nb: u8) {
+        let ch = &self.bus.io.dma_channels[channel_nb as usize];
+
+        // Get transfer parameters from channel DMAP register
+        let direction = (ch.dmap >> 7) & 1;
+        let fixed = (ch.dmap >> 3) & 1;
+        let decrement = (ch.dmap >> 4) & 1;
+        let mode = ch.dmap & 0x07;
+        let ch_b_addr = ch.bbad;
+
+        let mut a_addr = ch.a1t;
+
+        // 0x0000 means 65536 bytes, u32 needed to not overflow
+        let mut remaining: u32 = {
+            let raw = ch.das;
+            if raw == 0 { 0x10000 } else { raw as u32 }
+        };
+
+        let b_offsets: &[u8] = match mode {
+            0 => &[0],
+            1 => &[0, 1],
+            2 | 6 => &[0, 0],
+            3 | 7 => &[0, 0, 1, 1],
+            4 => &[0, 1, 2, 3],
+            5 => &[0, 1, 0, 1],
+            _ => unreachable!(),
+        };
+
+        let b_addr = |pattern_idx: usize| SnesAddress {
+            bank: 0x00,
+            addr: 0x2100 | (ch_b_addr as u16 + b_offsets[pattern_idx % b_offsets.len()] as u16),
+        };
+
+        if fixed == 0 && decrement == 0 {
+            // A-bus addresses are consecutive: move that side as one block
+            let mut data = vec![0; remaining as usize];
+
+            if direction == 0 {
+                self.bus.read_block(a_addr, &mut data, &mut self.ppu, &mut self.apu);
+                if self.code_data_log.enabled() {
+                    for i in 0..data.len() {
+                        self.log_data_read(SnesAddress::from(usize::from(a_addr) + i));
+                    }
+                }
+                for (pattern_idx, &byte) in data.iter().enumerate() {
+                    self.bus.write(b_addr(pattern_idx), byte, &mut self.ppu, &mut self.apu);
+                }
+            } else {
+                for (pattern_idx, byte) in data.iter_mut().enumerate() {
+                    *byte = self.bus.read(b_addr(pattern_idx), &mut self.ppu, &mut self.apu);
+                }
+                self.bus.write_block(a_addr, &data, &mut self.ppu, &mut self.apu);
+            }
+
+            a_addr = SnesAddress::from(usize::from(a_addr) + remaining as usize);
+        } else {
+            for pattern_idx in 0..remaining as usize {
+                let (src, dest) = if direction == 0 {
+                    (a_addr, b_addr(pattern_idx))
+                } else {
+                    (b_addr(pattern_idx), a_addr)
+                };
+                let byte = self.bus.read(src, &mut self.ppu, &mut self.apu);
+                self.bus.write(dest, byte, &mut self.ppu, &mut self.apu);
+                if direction == 0 && self.code_data_log.enabled() {
+                    self.log_data_read(src);
+                }
+
+                if fixed == 0 {
+                    a_addr.decrement();
+                }
+            }
+        }
+
+        tracing::trace!(
+            channel = channel_nb,
+            mode,
+            bytes = remaining,
+            b_addr = format_args!("$21{:02X}", ch_b_addr),
+            master_cycles = self.master_cycles,
+            "DMA transfer"
+        );
+
+        // Each byte transferred takes 8 master cycles - ROUGH WAY TO HANDLE IT, TO CHANGE LATER
+        self.dma_master_cycles_to_wait += 8 * remaining;
+
+        // Reset DMA channel registers
+        let ch = &mut self.bus.io.dma_channels[channel_nb as usize];
+        ch.das = 0;
+        ch.a1t.addr = a_addr.addr;
+    }
+
+    /// This function will be called every master cycle, it will either decrease the
+    /// number of master cycles to wait or execute a cpu cycle
+    ///
+    /// With `renderer`, the current scanline is drawn up to the beam before the CPU or a DMA
+    /// transfer writes to the PPU, see [`Renderer::render_until`].
+    fn update_cpu_cycles(&mut self, mut renderer: Option<&mut Renderer>) {
+        if self.dma_master_cycles_to_wait > 0 {
+            self.dma_master_cycles_to_wait -= 1;
+            return;
+        }
+        if self.cpu_master_cycles_to_wait > 0 {
+            self.cpu_master_cycles_to_wait -= 1;
+            return;
+        }
+
+        // Check for DMA start
+        if self.bus.io.mdmaen != 0 {
+            if let Some(renderer) = renderer.as_deref_mut() {
+                self.catch_up_rendering(renderer);
+            }
+            self.dma_transfer();
+        }
+
+        #[cfg(feature = "access-log")]
+        self.set_access_source(AccessSource::Cpu);
+
+        let cycle = self.cpu.cycle();
+        self.cpu_cycles += 1;
+        match cycle {
+            CycleResult::Internal => {
+                self.cpu_master_cycles_to_wait = 6; // TODO : Confirm internal cpu cycle is 6 master cycles
+            }
+            CycleResult::Read => {
+                let addr = *self.cpu.addr_bus();
+                let byte = self.bus.read(addr, &mut self.ppu, &mut self.apu);
+
+                self.cpu.data_bus = byte;
+                self.cpu_master_cycles_to_wait = self.bus.access_speed(addr).master_cycles();
+                if self.code_data_log.enabled() {
+                    self.log_cpu_read(addr, byte);
+                }
+                if self.profiler.enabled() && self.cpu.fetched_opcode() {
+                    self.profiler.log_instruction(addr, byte);
+                }
+                if self.cpu.fetched_opcode() && self.breakpoint_hit.is_none() {
+                    self.breakpoint_hit = self.breakpoints.iter().position(|breakpoint| {
+                        breakpoint.hit(&*self) != Ok(false)
+                    });
+                }
+            }
+            CycleResult::Write => {
+                let addr = *self.cpu.addr_bus();
+                let byte = self.cpu.data_bus;
+
+                if let Some(renderer) = renderer
+                    && Self::is_ppu_register(addr)
+                {
+                    self.catch_up_rendering(renderer);
+                }
+                self.bus.write(addr, byte, &mut self.ppu, &mut self.apu);
+                self.cpu_master_cycles_to_wait = self.bus.access_speed(addr).master_cycles();
+            }
+        }
+
+        if let Some(unhandled) = self.cpu.take_unhandled_opcode() {
+            let opcode = format_args!("${:02X}", unhandled.opcode);
+            let addr = format_args!("${:02X}:{:04X}", unhandled.addr.bank, unhandled.addr.addr);
+            if let Some(Halt::Trap(_)) = self.cpu.halted() {
+                tracing::error!(%opcode, %addr, "CPU trapped on unhandled opcode");
+            } else {
+                tracing::warn!(%opcode, %addr, "Unhandled opcode skipped as a NOP");
+            }
+        }
+        if let Some(unhandled) = self.apu.cpu.take_unhandled_opcode() {
+            let opcode = format_args!("${:02X}", unhandled.opcode);
+            let addr = format_args!("${:04X}", unhandled.addr);
+            tracing::error!(%opcode, %addr, "SPC700 halted on unimplemented opcode");
+        }
+    }
+
+    /// Draws the current scanline up to the beam, with the PPU state before a write.
+    fn catch_up_rendering(&self, renderer: &mut Renderer) {
+        let y = self.ppu.scanline as usize;
+        if y < self.ppu.regs.visible_scanlines() as usize {
+            let x = Renderer::screen_x(self.bus.io.h_cycle as usize / 4);
+            renderer.render_until(&self.ppu, y, x);
+        }
+    }
+
+    /// Whether `addr` is one of the PPU registers ($2100-$213F), mapped in the system banks.
+    fn is_ppu_register(addr: SnesAddress) -> bool {
+        matches!(addr.bank, 0x00..=0x3F | 0x80..=0xBF) && (0x2100..0x2140).contains(&addr.addr)
+    }
+
+    /// Logs a CPU read of `byte` at `addr` in the code/data log.
+    fn log_cpu_read(&mut self, addr: SnesAddress, byte: u8) {
+        let Some(offset) = self.bus.rom_offset(addr) else {
+            return;
+        };
+        if !self.cpu.fetched_opcode() {
+            self.code_data_log.log_read(offset);
+            return;
+        }
+        let regs = self.cpu.regs();
+        let widths = RegisterWidths {
+            m8: regs.E || regs.P.M,
+            x8: regs.E || regs.P.X,
+        };
+        let len = 1 + AddrMode::of(byte).operand_len(widths);
+        self.code_data_log.log_instruction(offset, len, regs);
+    }
+
+    /// Logs a data read at `addr`, by an instruction or DMA, in the code/data log.
+    fn log_data_read(&mut self, addr: SnesAddress) {
+        if let Some(offset) = self.bus.rom_offset(addr) {
+            self.code_data_log.log_read(offset);
+        }
+    }
+
+    /// This function will be called every master cycle, it will update the CPU, PPU and APU state accordingly
+    ///
+    /// The APU only keeps count of the time here: it runs in batches, when the CPU accesses
+    /// its ports (see [`Apu::catch_up`]) and when the frame's audio is rendered.
+    pub fn update(&mut self) {
+        self.step(None);
+    }
+
+    /// [`Self::update`], drawing the current scanline with `renderer` as the CPU changes the
+    /// PPU registers.
+    fn step(&mut self, renderer: Option<&mut Renderer>) {
+        self.update_cpu_cycles(renderer);
+        self.bus.step_coprocessor(self.coprocessor_cycles);
+        self.apu.advance_master_cycles(1);
+
+        self.master_cycles += 1;
+    }
+
+    /// Runs the extra CPU cycles of [`Overclock::cpu_extra_cycles`] at the end of a
+    /// scanline. The beam stays where it is and a DMA transfer stops them, so that
+    /// transfers keep their length in master cycles.
+    fn run_cpu_extra_cycles(&mut self, mut renderer: Option<&mut Renderer>) {
+        for _ in 0..self.overclock.cpu_extra_cycles {
+            if self.dma_master_cycles_to_wait > 0 {
+                break;
+            }
+            self.update_cpu_cycles(renderer.as_deref_mut());
+        }
+    }
+
+    /// Runs the console until the PPU completes a frame, drawing the visible scanlines
+    /// with `renderer`.
+    pub fn run_frame(&mut self, renderer: &mut Renderer) {
+        #[cfg(feature = "stats")]
+        self.stats.begin_frame();
+        self.emulate_frame(Some(renderer));
+        #[cfg(feature = "stats")]
+        self.stats.end_frame();
+    }
+
+    /// [`Self::run_frame`] without closing the frame statistics, so that the caller can
+    /// add the audio rendering to the same frame. Without `renderer`, the frame is emulated
+    /// but not drawn.
+    pub(crate) fn emulate_frame(&mut self, mut renderer: Option<&mut Renderer>) {
+        let _span = tracing::debug_span!("frame", master_cycles = self.master_cycles).entered();
+
+        loop {
+            #[cfg(feature = "stats")]
+            let (start, dma_before) = (self.stats.start(), self.stats.current(Subsystem::Dma));
+            for h_cycle in self.line_cycle..VideoStandard::MASTER_CYCLES_PER_SCANLINE as u16 {
+                self.bus.io.h_cycle = h_cycle;
+                self.step(renderer.as_deref_mut());
+            }
+            self.run_cpu_extra_cycles(renderer.as_deref_mut());
+            // DMA transfers run from the CPU loop but are counted on their own
+            #[cfg(feature = "stats")]
+            if let Some(start) = start {
+                let dma = self.stats.current(Subsystem::Dma) - dma_before;
+                self.stats.add(Subsystem::Cpu, start.elapsed().saturating_sub(dma));
+            }
+
+            self.end_scanline(renderer.as_deref_mut());
+            if self.ppu.frame_ready {
+                break;
+            }
+        }
+    }
+
+    /// Ends the current scanline, once its master cycles and the extra CPU cycles are run:
+    /// draws it with `renderer` and steps the PPU to the next one.
+    fn end_scanline(&mut self, mut renderer: Option<&mut Renderer>) {
+        #[cfg(feature = "stats")]
+        let start = self.stats.start();
+        let y = self.ppu.scanline as usize;
+        if let Some(renderer) = &mut renderer
+            && y < self.ppu.regs.visible_scanlines() as usize
+        {
+            renderer.render_scanline(&self.ppu, y);
+        }
+        self.ppu.step_scanline();
+        self.bus.io.on_scanline(&mut self.ppu);
+        self.line_cycle = 0;
+        #[cfg(feature = "stats")]
+        self.stats.record(Subsystem::Ppu, start);
+
+        self.frame_hooks.scanline_stepped(&FrameEventContext {
+            ppu: &self.ppu,
+            master_cycles: self.master_cycles,
+            renderer: renderer.as_deref(),
+        });
+    }
+
+    /// Runs the console until `master_cycle` (master cycles since power on), drawing the
+    /// visible scanlines completed on the way with `renderer`.
+    ///
+    /// The run stops at exactly `master_cycle`, even in the middle of a scanline or of a CPU
+    /// instruction, or earlier if the CPU halts (STP, or a trap with [`OpcodePolicy::Trap`]).
+    /// A CPU already halted doesn't stop it. Does nothing if `master_cycle` is already past.
+    ///
+    /// It also stops right after the opcode fetch of an instruction which hits one of
+    /// [`Self::breakpoints`], before the instruction runs. A condition which cannot be
+    /// evaluated (e.g. reading an I/O register) stops it too, for the debugger to show why.
+    pub fn run_until(
+        &mut self,
+        master_cycle: u64,
+        mut renderer: Option<&mut Renderer>,
+    ) -> RunPosition {
+        let was_halted = self.cpu.halted().is_some();
+        let mut frames = 0;
+        let mut halted = None;
+        let mut breakpoint = None;
+        self.breakpoint_hit = None;
+
+        while self.master_cycles < master_cycle {
+            self.bus.io.h_cycle = self.line_cycle;
+            self.step(renderer.as_deref_mut());
+            self.line_cycle += 1;
+            if self.line_cycle as u64 == VideoStandard::MASTER_CYCLES_PER_SCANLINE {
+                self.run_cpu_extra_cycles(renderer.as_deref_mut());
+                self.end_scanline(renderer.as_deref_mut());
+                frames += self.ppu.frame_ready as u32;
+            }
+            if !was_halted && let Some(halt) = self.cpu.halted() {
+                halted = Some(halt);
+                break;
+            }
+            if self.breakpoint_hit.is_some() {
+                breakpoint = self.breakpoint_hit.take();
+                break;
+            }
+        }
+
+        RunPosition {
+            master_cycles: self.master_cycles,
+            scanline: self.ppu.scanline,
+            line_cycle: self.line_cycle,
+            mid_instruction: self.cpu.mid_instruction(),
+            frames,
+            halted,
+            breakpoint,
+        }
+    }
+
+    /// [`Self::run_until`] `cycles` master cycles from now.
+    pub fn run_for(&mut self, cycles: u64, renderer: Option<&mut Renderer>) -> RunPosition {
+        self.run_until(self.master_cycles + cycles, renderer)
+    }
+
+    /// Snapshot of the console, to restore with [`Self::load_state`].
+    ///
+    /// The CPUs are run to their next opcode fetch first, since the state of an instruction
+    /// in progress is not saved (see [`CPU::can_save_state`]): this runs the console for up
+    /// to a few dozen master cycles. Settings of the emulator (video standard, overclock,
+    /// options) are not saved.
+    pub fn save_state(&mut self) -> SaveState {
+        while !self.cpu.can_save_state() {
+            let position = self.run_for(1, None);
+            if position.halted.is_some() {
+                break;
+            }
+        }
+        if let Some(coprocessor) = &mut self.bus.coprocessor {
+            coprocessor.prepare_save_state();
+        }
+
+        let mut state = SaveState::new();
+        state.save(&CpuSection(&mut self.cpu));
+        state.save(&self.timing());
+        state.save(&self.ppu);
+        state.save(&self.apu);
+        state.save(&self.bus.io);
+        state.save(&self.bus.wram);
+        if let Some(coprocessor) = &self.bus.coprocessor {
+            state.save(coprocessor);
+        }
+        state
+    }
+
+    /// Restores a snapshot taken by [`Self::save_state`] on the same game. On error, the
+    /// console may be partly restored and should be reset or loaded with another state.
+    pub fn load_state(&mut self, state: &SaveState) -> Result<(), StateError> {
+        state.load(&mut CpuSection(&mut self.cpu))?;
+        let mut timing = Timing::default();
+        state.load(&mut timing)?;
+        state.load(&mut self.ppu)?;
+        state.load(&mut self.apu)?;
+        state.load(&mut self.bus.io)?;
+        state.load(&mut self.bus.wram)?;
+        if let Some(coprocessor) = &mut self.bus.coprocessor {
+            state.load(coprocessor)?;
+        }
+
+        self.master_cycles = timing.master_cycles;
+        self.line_cycle = timing.line_cycle;
+        self.cpu_cycles = timing.cpu_cycles;
+        self.cpu_master_cycles_to_wait = timing.cpu_master_cycles_to_wait;
+        self.dma_master_cycles_to_wait = timing.dma_master_cycles_to_wait;
+        Ok(())
+    }
+
+    fn timing(&self) -> Timing {
+        Timing {
+            master_cycles: self.master_cycles,
+            line_cycle: self.line_cycle,
+            cpu_cycles: self.cpu_cycles,
+            cpu_master_cycles_to_wait: self.cpu_master_cycles_to_wait,
+            dma_master_cycles_to_wait: self.dma_master_cycles_to_wait,
+        }
+    }
+
+    /// Reset button: restarts the CPU and the coprocessor, memory is left untouched.
+    pub fn reset(&mut self) {
+        self.cpu.start_reset();
+        if let Some(coprocessor) = &mut self.bus.coprocessor {
+            coprocessor.reset();
+        }
+        self.cpu_master_cycles_to_wait = 0;
+        self.dma_master_cycles_to_wait = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bus::rom::test_rom::*;
+    use common::snes_addr;
+
+    fn make_rsnes() -> RSnes {
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, _dir) = create_temp_rom(&rom_data);
+        RSnes::load_rom(&rom_path).unwrap()
+    }
+
+    fn set_dma_channel(
+        rsnes: &mut RSnes,
+        channel: usize,
+        dmap: u8,
+        src_bank: u8,
+        src_addr: u16,
+        size: u16,
+    ) {
+        let ch = &mut rsnes.bus.io.dma_channels[channel];
+        ch.dmap = dmap;
+        ch.bbad = 0xFF; // 0x21FF: no-op destination
+        ch.a1t.bank = src_bank;
+        ch.a1t.addr = src_addr;
+        ch.das = size;
+    }
+
+    #[test]
+    fn test_video_standard_from_header() {
+        let rsnes = make_rsnes();
+
+        // The test ROM header declares an NTSC (USA) cartridge
+        assert_eq!(rsnes.video_standard, VideoStandard::NTSC);
+        assert!(!rsnes.region.is_mismatch());
+        assert_eq!(rsnes.ppu.video_standard, VideoStandard::NTSC);
+        assert_eq!(rsnes.master_cycle_duration(), 1.0 / 21_477_272.0);
+    }
+
+    #[test]
+    fn test_forced_video_standard() {
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, _dir) = create_temp_rom(&rom_data);
+        let options = EmulatorOptions {
+            force_video_standard: Some(VideoStandard::PAL),
+            ..Default::default()
+        };
+        let rsnes = RSnes::load_rom_with_options(&rom_path, &options).unwrap();
+
+        assert_eq!(rsnes.region.detected, VideoStandard::NTSC);
+        assert!(rsnes.region.is_mismatch());
+        assert_eq!(rsnes.video_standard, VideoStandard::PAL);
+        assert_eq!(rsnes.ppu.video_standard, VideoStandard::PAL);
+        assert_eq!(rsnes.master_cycle_duration(), 1.0 / 21_281_370.0);
+    }
+
+    #[test]
+    fn test_memory_init_option() {
+        let options = EmulatorOptions {
+            memory_init: MemoryInit::Stripes,
+            ..Default::default()
+        };
+        let rom = Rom::from_bytes(create_valid_lorom(0x20000)).unwrap();
+        let rsnes = RSnes::from_rom(rom, &options);
+
+        assert_eq!(rsnes.bus.wram.data[..2], [0x55, 0xAA]);
+        assert_eq!(rsnes.ppu.vram.memory[0], 0xAA55);
+    }
+
+    #[test]
+    fn test_game_settings_applied_on_load() {
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, dir) = create_temp_rom(&rom_data);
+        let root = dir.path().join("games");
+        let settings = GameSettings {
+            video_standard: Some(VideoStandard::PAL),
+            frame_blend: Some(50),
+            ..Default::default()
+        };
+        let rom = Rom::from_bytes(rom_data).unwrap();
+        GameData::new(&root, &rom.hashes())
+            .save_settings(&settings)
+            .unwrap();
+        let options = EmulatorOptions {
+            memory_init: MemoryInit::Stripes,
+            ..Default::default()
+        };
+
+        let (rsnes, loaded) =
+            RSnes::load_rom_with_game_data(&rom_path, &options, &root).unwrap();
+        assert_eq!(loaded, settings);
+        assert_eq!(rsnes.video_standard, VideoStandard::PAL);
+        assert_eq!(rsnes.bus.wram.data[..2], [0x55, 0xAA]);
+
+        // Without saved settings the options are kept
+        let (rsnes, loaded) =
+            RSnes::load_rom_with_game_data(&rom_path, &options, &dir.path().join("none"))
+                .unwrap();
+        assert_eq!(loaded, GameSettings::default());
+        assert_eq!(rsnes.video_standard, VideoStandard::NTSC);
+    }
+
+    #[test]
+    fn test_trap_on_unhandled_opcode() {
+        let rom_data = create_valid_lorom(0x20000);
+        let (rom_path, _dir) = create_temp_rom(&rom_data);
+        let options = EmulatorOptions {
+            opcode_policy: OpcodePolicy::Trap,
+            ..Default::default()
+        };
+        let mut rsnes = RSnes::load_rom_with_options(&rom_path, &options).unwrap();
+
+        // The blank reset vector and memory make the CPU run into BRK, which isn't implemented
+        for _ in 0..1000 {
+            rsnes.update();
+        }
+        let Some(Halt::Trap(trap)) = rsnes.cpu.halted() else {
+            panic!("CPU should have trapped, got {:?}", rsnes.cpu.halted());
+        };
+        assert_eq!(trap.opcode, 0x00);
+
+        rsnes.reset();
+        assert_eq!(rsnes.cpu.halted(), None);
+    }
+
+    #[test]
+    fn test_mdmaen_cleared_after_transfer() {
+        let mut rsnes = make_rsnes();
+        rsnes.bus.io.mdmaen = 0b0000_0001;
+        set_dma_channel(&mut rsnes, 0, 0x00, 0x7E, 0x0000, 1);
+
+        rsnes.dma_transfer();
+
+        assert_eq!(
+            rsnes.bus.io.mdmaen, 0,
+            "mdmaen should be cleared after transfer"
+        );
+    }
+
+    #[test]
+    fn test_dma_to_vram() {
+        let mut rsnes = make_rsnes();
+        rsnes.ppu.write(0x2115, 0x80);
+        rsnes.bus.wram.data[0x0100..0x0104].copy_from_slice(&[0x11, 0x22, 0x33, 0x44]);
+        set_dma_channel(&mut rsnes, 0, 0x01, 0x7E, 0x0100, 4);
+        rsnes.bus.io.dma_channels[0].bbad = 0x18; // VMDATAL, then VMDATAH
+        rsnes.bus.io.mdmaen = 0b0000_0001;
+
+        rsnes.dma_transfer();
+
+        assert_eq!(rsnes.ppu.vram.memory[0x0000..0x0002], [0x2211, 0x4433]);
+    }
+
+    #[test]
+    fn test_only_enabled_channels_run() {
+        let mut rsnes = make_rsnes();
+        rsnes.bus.io.mdmaen = 0b0000_0010;
+
+        set_dma_channel(&mut rsnes, 0, 0x00, 0x7E, 0x0000, 1);
+        set_dma_channel(&mut rsnes, 1, 0x00, 0x7E, 0x0000, 1);
+
+        rsnes.dma_transfer();
+
+        // Channel 0 was not enabled, its source address should not have changed
+        let ch0 = &rsnes.bus.io.dma_channels[0];
+        let ch0_addr = ch0.a1t.addr;
+        assert_eq!(ch0_addr, 0x0000, "Channel 0 should not have run");
+        assert_eq!(rsnes.bus.io.mdmaen, 0);
+    }
+
+    #[test]
+    fn test_multiple_channels_run() {
+        let mut rsnes = make_rsnes();
+        rsnes.bus.io.mdmaen = 0b0000_0011;
+
+        set_dma_channel(&mut rsnes, 0, 0x00, 0x7E, 0x0000, 2);
+        set_dma_channel(&mut rsnes, 1, 0x00, 0x7E, 0x0100, 3);
+
+        rsnes.dma_transfer();
+
+        let ch0 = &rsnes.bus.io.dma_channels[0];
+        let ch0_addr = ch0.a1t.addr;
+        assert_eq!(ch0_addr, 0x0002, "Channel 0 should have advanced by 2");
+
+        let ch1 = &rsnes.bus.io.dma_channels[1];
+        let ch1_addr = ch1.a1t.addr;
+        assert_eq!(ch1_addr, 0x0103, "Channel 1 should have advanced by 3");
+    }
+
+    #[test]
+    fn test_a1t_increments_after_transfer() {
+        let mut rsnes = make_rsnes();
+        rsnes.bus.io.mdmaen = 0b0000_0001;
+        set_dma_channel(&mut rsnes, 0, 0x00, 0x7E, 0x0010, 4);
+
+        rsnes.dma_transfer();
+
+        let ch = &rsnes.bus.io.dma_channels[0];
+        let final_addr = ch.a1t.addr;
+        assert_eq!(
+            final_addr, 0x0014,
+            "Source address should have advanced by 4"
+        );
+    }
+
+    #[test]
+    fn test_a1t_decrements_after_transfer() {
+        let mut rsnes = make_rsnes();
+        rsnes.bus.io.mdmaen = 0b0000_0001;
+        set_dma_channel(&mut rsnes, 0, 0b0001_0000, 0x7E, 0x0010, 4);
+
+        rsnes.dma_transfer();
+
+        let ch = &rsnes.bus.io.dma_channels[0];
+        let final_addr = ch.a1t.addr;
+        assert_eq!(
+            final_addr, 0x000C,
+            "Source address should have decreased by 4"
+        );
+    }
+
+    #[test]
+    fn test_a1t_unchanged_in_fixed_mode() {
+        let mut rsnes = make_rsnes();
+        rsnes.bus.io.mdmaen = 0b0000_0001;
+        set_dma_channel(&mut rsnes, 0, 0b0000_1000, 0x7E, 0x0010, 4);
+
+        rsnes.dma_transfer();
+
+        let ch = &rsnes.bus.io.dma_channels[0];
+        let final_addr = ch.a1t.addr;
+        assert_eq!(
+            final_addr, 0x0010,
+            "Source address should not change in fixed mode"
+        );
+    }
+
+    #[test]
+    fn test_das_zeroed_after_transfer() {
+        let mut rsnes = make_rsnes();
+        rsnes.bus.io.mdmaen = 0b0000_0001;
+        set_dma_channel(&mut rsnes, 0, 0x00, 0x7E, 0x0000, 8);
+
+        rsnes.dma_transfer();
+
+        let ch = &rsnes.bus.io.dma_channels[0];
+        assert_eq!(ch.das, 0, "das should be 0 after transfer");
+    }
+
+    /// This test isn't really relevant for now because the destination
+    /// does not really registers the written value from a to b
+    #[test]
+    fn test_wram_source_bytes_are_read() {
+        let mut rsnes = make_rsnes();
+
+        rsnes.bus.wram.data[0x0100] = 0xAB;
+        rsnes.bus.wram.data[0x0101] = 0xCD;
+        rsnes.bus.wram.data[0x0102] = 0xEF;
+
+        rsnes.bus.io.mdmaen = 0b0000_0001;
+        set_dma_channel(&mut rsnes, 0, 0x00, 0x7E, 0x0100, 3);
+
+        rsnes.dma_transfer();
+
+        let ch = &rsnes.bus.io.dma_channels[0];
+        let final_addr = ch.a1t.addr;
+        assert_eq!(final_addr, 0x0103);
+    }
+
+    #[test]
+    fn test_direction_b_to_a_writes_into_wram() {
+        let mut rsnes = make_rsnes();
+
+        // Pre-fill so we can confirm it changed
+        rsnes.bus.wram.data[0x0200] = 0xFF;
+        rsnes.bus.wram.data[0x0201] = 0xFF;
+        rsnes.bus.wram.data[0x0202] = 0xFF;
+        rsnes.bus.io.mdmaen = 0b0000_0001;
+        set_dma_channel(&mut rsnes, 0, 0b1000_0000, 0x7E, 0x0200, 3);
+
+        rsnes.dma_transfer();
+
+        assert_eq!(
+            &rsnes.bus.wram.data[0x0200..=0x0202],
+            &[0x00, 0x00, 0x00],
+            "WRAM should have been overwritten with open bus value 0x00"
+        );
+    }
+
+    #[test]
+    fn test_cpu_update_function() {
+        let mut rsnes = make_rsnes();
+
+        let reset_addr = bus::rom::Rom::get_lorom_offset(snes_addr!(0:0xFFFC));
+        rsnes.bus.rom.data[reset_addr] = 0x00;
+        rsnes.bus.rom.data[reset_addr + 1] = 0x80;
+
+        rsnes.bus.rom.data[0] = 0xEA;
+        rsnes.bus.rom.data[1] = 0xA9;
+        rsnes.bus.rom.data[2] = 0x42;
+        rsnes.bus.rom.data[3] = 0x8D;
+        rsnes.bus.rom.data[4] = 0x34;
+        rsnes.bus.rom.data[5] = 0x12;
+
+        rsnes.update();
+        // reset vector read from SlowROM
+        assert_eq!(rsnes.cpu_master_cycles_to_wait, 8);
+        rsnes.cpu_master_cycles_to_wait = 0;
+        rsnes.update();
+        assert_eq!(rsnes.cpu.regs().PC, 0);
+        rsnes.cpu_master_cycles_to_wait = 0;
+
+        // NO-OP
+        rsnes.update();
+        rsnes.cpu_master_cycles_to_wait = 0;
+        assert_eq!(rsnes.cpu.regs().PC, 0x8000);
+        rsnes.update();
+        rsnes.cpu_master_cycles_to_wait = 0;
+
+        // LDA
+        assert_ne!(rsnes.cpu.regs().A, 0x42);
+        rsnes.update();
+        rsnes.cpu_master_cycles_to_wait = 0;
+        rsnes.update();
+        rsnes.cpu_master_cycles_to_wait = 0;
+
+        // STA
+        assert_ne!(rsnes.cpu.data_bus, 0x8D);
+        rsnes.update();
+        rsnes.cpu_master_cycles_to_wait = 0;
+        assert_eq!(rsnes.cpu.data_bus, 0x8D);
+        rsnes.update();
+        rsnes.cpu_master_cycles_to_wait = 0;
+        rsnes.update();
+        rsnes.cpu_master_cycles_to_wait = 0;
+        rsnes.update();
+        rsnes.cpu_master_cycles_to_wait = 0;
+
+        assert_eq!(rsnes.bus.wram.read(snes_addr!(0:0x1234)), 0x42);
+    }
+
+    /// ROM storing `value` to the first byte of WRAM, then looping
+    fn store_rom(value: u8) -> Rom {
+        let mut rom_data = create_valid_lorom(0x20000);
+        // LDA #value; STA $7E0000; BRA -2
+        rom_data[..8].copy_from_slice(&[0xA9, value, 0x8F, 0x00, 0x00, 0x7E, 0x80, 0xFE]);
+        rom_data[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0x80]);
+        Rom::from_bytes(rom_data).unwrap()
+    }
+
+    #[test]
+    fn test_code_data_log() {
+        use crate::code_data_log::usage::*;
+
+        let mut rom_data = create_valid_lorom(0x20000);
+        // LDA $8010; BRA -5
+        rom_data[..5].copy_from_slice(&[0xAD, 0x10, 0x80, 0x80, 0xFB]);
+        rom_data[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0x80]);
+        let mut rsnes = RSnes::from_rom(Rom::from_bytes(rom_data).unwrap(), &Default::default());
+        rsnes.code_data_log.set_enabled(true);
+        for _ in 0..1000 {
+            rsnes.update();
+        }
+
+        let flags = FLAG_E | FLAG_M | FLAG_X;
+        let usage = rsnes.code_data_log.usage();
+        assert_eq!(
+            usage[..6],
+            [OPCODE | EXEC | flags, EXEC, EXEC, OPCODE | EXEC | flags, EXEC, 0]
+        );
+        assert_eq!(usage[0x10], READ);
+        assert_eq!(usage[0x7FFC..0x7FFE], [READ, READ]);
+    }
+
+    #[test]
+    fn test_code_data_log_saved_next_to_rom() {
+        let dir = tempfile::tempdir().unwrap();
+        let rom_path = dir.path().join("game.sfc");
+        std::fs::write(&rom_path, create_valid_lorom(0x20000)).unwrap();
+        let options = EmulatorOptions {
+            code_data_log: true,
+            ..Default::default()
+        };
+
+        let mut rsnes = RSnes::load_rom_with_options(&rom_path, &options).unwrap();
+        rsnes.update();
+        rsnes.save().unwrap();
+        let saved = std::fs::read(dir.path().join("game.cdl")).unwrap();
+        assert_eq!(saved.len(), 0x20000);
+        assert_ne!(saved[0x7FFC], 0);
+
+        // The next session starts from the saved log
+        let rsnes = RSnes::load_rom_with_options(&rom_path, &options).unwrap();
+        assert_eq!(rsnes.code_data_log.usage(), saved);
+    }
+
+    #[test]
+    fn test_profiler_counts_instructions() {
+        let options = EmulatorOptions {
+            profile: true,
+            ..Default::default()
+        };
+        let mut rsnes = RSnes::from_rom(store_rom(0x42), &options);
+        rsnes.emulate_frame(None);
+
+        let profiler = &rsnes.profiler;
+        assert_eq!(profiler.opcode_count(0xA9), 1);
+        assert_eq!(profiler.opcode_count(0x8F), 1);
+        assert!(profiler.opcode_count(0x80) > 1000);
+        assert_eq!(profiler.bank_counts()[0], profiler.instructions());
+
+        let dir = tempfile::tempdir().unwrap();
+        rsnes.rom_path = Some(dir.path().join("game.sfc"));
+        rsnes.save().unwrap();
+        let report = std::fs::read_to_string(dir.path().join("game.profile")).unwrap();
+        assert!(report.contains("$00:8000-80FF"));
+    }
+
+    #[test]
+    fn test_instances_on_separate_threads() {
+        // Both consoles are built here and moved to their thread
+        let threads = [0x42, 0x99]
+            .map(|value| {
+                let mut rsnes = RSnes::from_rom(store_rom(value), &Default::default());
+                std::thread::spawn(move || {
+                    let mut renderer = Renderer::new();
+                    rsnes.run_frame(&mut renderer);
+                    rsnes.bus.wram.data[0]
+                })
+            });
+
+        assert_eq!(threads.map(|thread| thread.join().unwrap()), [0x42, 0x99]);
+    }
+
+    #[test]
+    fn test_frame_hooks_run_during_frame() {
+        use crate::frame_events::FrameEvent;
+        use std::sync::{Arc, Mutex};
+
+        let mut rsnes = RSnes::from_rom(store_rom(0x42), &Default::default());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        rsnes.frame_hooks.subscribe(
+            FrameEvent::VblankStart,
+            move |_, context: &FrameEventContext| {
+                sink.lock()
+                    .unwrap()
+                    .push((context.master_cycles, context.renderer.is_some()));
+            },
+        );
+
+        let mut renderer = Renderer::new();
+        rsnes.run_frame(&mut renderer);
+        rsnes.emulate_frame(None);
+
+        let cycles_to_vblank = 225 * VideoStandard::MASTER_CYCLES_PER_SCANLINE;
+        let frame_cycles = VideoStandard::NTSC.master_cycles_per_frame();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                (cycles_to_vblank, true),
+                (frame_cycles + cycles_to_vblank, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_frame_emulated_without_renderer() {
+        let mut rsnes = RSnes::from_rom(store_rom(0x42), &Default::default());
+        rsnes.emulate_frame(None);
+
+        assert!(rsnes.ppu.frame_ready);
+        assert_eq!(rsnes.bus.wram.data[0], 0x42);
+    }
+
+    #[test]
+    fn test_apu_catches_up_on_port_access() {
+        let mut rsnes = RSnes::from_rom(store_rom(0x42), &Default::default());
+        // MOV A, #$AA; MOV $F4, A, then NOPs in the cleared RAM
+        rsnes.apu.memory.ram[0x0100..0x0104].copy_from_slice(&[0xE8, 0xAA, 0xC4, 0xF4]);
+        rsnes.apu.cpu.regs.pc = 0x0100;
+        for _ in 0..100_000 {
+            rsnes.update();
+        }
+        assert_eq!(rsnes.apu.cycles, 0, "the APU runs lazily");
+
+        let port = rsnes
+            .bus
+            .read(snes_addr!(0:0x2140), &mut rsnes.ppu, &mut rsnes.apu);
+        assert_eq!(port, 0xAA);
+        let clock_hz = VideoStandard::NTSC.master_clock_hz();
+        assert_eq!(rsnes.apu.cycles, 100_000 * apu::apu::CLOCK_HZ / clock_hz);
+    }
+
+    #[test]
+    fn test_ppu_write_mid_scanline_renders_in_spans() {
+        let mut rom_data = create_valid_lorom(0x20000);
+        // LDA #$01; STA $002105; LDA #$0F; STA $002100; LDA #$1F; STA $002122;
+        // LDA #$00; STA $002122; BRA -2
+        rom_data[..26].copy_from_slice(&[
+            0xA9, 0x01, 0x8F, 0x05, 0x21, 0x00, 0xA9, 0x0F, 0x8F, 0x00, 0x21, 0x00, 0xA9, 0x1F,
+            0x8F, 0x22, 0x21, 0x00, 0xA9, 0x00, 0x8F, 0x22, 0x21, 0x00, 0x80, 0xFE,
+        ]);
+        rom_data[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0x80]);
+        let mut rsnes = RSnes::from_rom(Rom::from_bytes(rom_data).unwrap(), &Default::default());
+        let mut renderer = Renderer::new();
+        rsnes.run_frame(&mut renderer);
+
+        // The backdrop turns red partway through the first line
+        let pixel = |x: usize, y: usize| {
+            let idx = (y * ppu::constants::SCREEN_WIDTH + x) * 3;
+            renderer.framebuffer[idx..idx + 3].to_vec()
+        };
+        assert_eq!(pixel(0, 0), [0, 0, 0]);
+        assert_eq!(pixel(255, 0), [255, 0, 0]);
+        assert_eq!(pixel(0, 1), [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_run_for_stops_mid_scanline() {
+        let mut rsnes = RSnes::from_rom(store_rom(0x42), &Default::default());
+
+        let position = rsnes.run_for(1000, None);
+        assert_eq!(position.master_cycles, 1000);
+        assert_eq!((position.scanline, position.line_cycle), (0, 1000));
+        assert_eq!(position.frames, 0);
+
+        let position = rsnes.run_for(1000, None);
+        assert_eq!(position.master_cycles, 2000);
+        assert_eq!((position.scanline, position.line_cycle), (1, 636));
+        assert_eq!(rsnes.run_until(1500, None), position, "already past");
+    }
+
+    #[test]
+    fn test_bounded_runs_match_a_frame() {
+        let mut frame = RSnes::from_rom(store_rom(0x42), &Default::default());
+        frame.emulate_frame(None);
+
+        // Runs of a few master cycles stop in the middle of instructions, and resume them
+        let mut bounded = RSnes::from_rom(store_rom(0x42), &Default::default());
+        let mut mid_instruction = false;
+        let mut frames = 0;
+        while bounded.master_cycles < frame.master_cycles {
+            let end = (bounded.master_cycles + 7).min(frame.master_cycles);
+            let position = bounded.run_until(end, None);
+            mid_instruction |= position.mid_instruction;
+            frames += position.frames;
+        }
+        assert!(mid_instruction);
+        assert_eq!(frames, 1);
+        assert_eq!(bounded.master_cycles, frame.master_cycles);
+        assert_eq!(bounded.cpu_cycles, frame.cpu_cycles);
+        assert_eq!(bounded.cpu.regs().PC, frame.cpu.regs().PC);
+        assert_eq!(bounded.ppu.scanline, 0);
+        assert_eq!(bounded.bus.wram.data[0], 0x42);
+    }
+
+    #[test]
+    fn test_frame_resumes_bounded_run() {
+        let mut rsnes = RSnes::from_rom(store_rom(0x42), &Default::default());
+        rsnes.run_for(100_000, None);
+        rsnes.emulate_frame(None);
+
+        assert!(rsnes.ppu.frame_ready);
+        assert_eq!(rsnes.master_cycles, VideoStandard::NTSC.master_cycles_per_frame());
+    }
+
+    #[test]
+    fn test_run_stops_when_cpu_halts() {
+        let mut rom_data = create_valid_lorom(0x20000);
+        // LDA #$42; STP
+        rom_data[..3].copy_from_slice(&[0xA9, 0x42, 0xDB]);
+        rom_data[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0x80]);
+        let mut rsnes = RSnes::from_rom(Rom::from_bytes(rom_data).unwrap(), &Default::default());
+
+        let position = rsnes.run_for(10_000, None);
+        assert_eq!(position.halted, Some(Halt::Stopped));
+        assert!(position.master_cycles < 10_000);
+        assert!(!position.mid_instruction);
+
+        // Already halted: the next run goes to the end
+        let position = rsnes.run_until(10_000, None);
+        assert_eq!((position.master_cycles, position.halted), (10_000, None));
+    }
+
+    #[test]
+    fn test_run_stops_at_breakpoints() {
+        let mut rom_data = create_valid_lorom(0x20000);
+        // loop: INX; BRA loop
+        rom_data[..3].copy_from_slice(&[0xE8, 0x80, 0xFD]);
+        rom_data[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0x80]);
+        let mut rsnes = RSnes::from_rom(Rom::from_bytes(rom_data).unwrap(), &Default::default());
+        rsnes.breakpoints = vec![
+            Breakpoint::parse("break if X == 3").unwrap(),
+            Breakpoint::parse("break at 00:8001").unwrap(),
+        ];
+
+        let position = rsnes.run_for(10_000, None);
+        assert_eq!(position.breakpoint, Some(1));
+        assert!(position.mid_instruction);
+        assert_eq!((rsnes.cpu.regs().PC, rsnes.cpu.regs().X), (0x8001, 1));
+
+        // The next run goes on from the instruction it stopped before
+        rsnes.breakpoints.remove(1);
+        let position = rsnes.run_for(10_000, None);
+        assert_eq!(position.breakpoint, Some(0));
+        assert_eq!((rsnes.cpu.regs().PC, rsnes.cpu.regs().X), (0x8001, 3));
+
+        // A condition which cannot be evaluated stops the run too
+        rsnes.breakpoints = vec![Breakpoint::parse("break if [2140] == 0").unwrap()];
+        assert_eq!(rsnes.run_for(10_000, None).breakpoint, Some(0));
+        rsnes.breakpoints.clear();
+        let position = rsnes.run_for(10_000, None);
+        assert_eq!((position.breakpoint, position.halted), (None, None));
+    }
+
+    #[test]
+    fn test_cpu_overclock_keeps_frame_length() {
+        let mut rsnes = RSnes::from_rom(store_rom(0x42), &Default::default());
+        rsnes.emulate_frame(None);
+        let cpu_cycles = rsnes.cpu_cycles;
+
+        let options = EmulatorOptions {
+            overclock: Overclock {
+                cpu_extra_cycles: VideoStandard::MASTER_CYCLES_PER_SCANLINE as u32,
+                ..Overclock::NONE
+            },
+            ..Default::default()
+        };
+        let mut overclocked = RSnes::from_rom(store_rom(0x42), &options);
+        overclocked.emulate_frame(None);
+
+        assert_eq!(overclocked.master_cycles, rsnes.master_cycles);
+        assert!(overclocked.cpu_cycles > cpu_cycles * 3 / 2);
+    }
+
+    #[test]
+    fn test_cpu_overclock_waits_for_dma() {
+        let mut rsnes = make_rsnes();
+        rsnes.set_overclock(Overclock {
+            cpu_extra_cycles: 100,
+            ..Overclock::NONE
+        });
+        set_dma_channel(&mut rsnes, 0, 0x00, 0x7E, 0x0100, 0x200);
+        rsnes.bus.io.mdmaen = 0b0000_0001;
+        rsnes.update();
+        assert_eq!(rsnes.dma_master_cycles_to_wait, 8 * 0x200);
+        let cpu_cycles = rsnes.cpu_cycles;
+
+        rsnes.run_cpu_extra_cycles(None);
+        assert_eq!(rsnes.dma_master_cycles_to_wait, 8 * 0x200);
+        assert_eq!(rsnes.cpu_cycles, cpu_cycles);
+
+        rsnes.dma_master_cycles_to_wait = 0;
+        rsnes.run_cpu_extra_cycles(None);
+        assert!(rsnes.cpu_cycles > cpu_cycles);
+    }
+
+    #[test]
+    fn test_sa1_overclock_ignored_without_sa1() {
+        let mut rsnes = make_rsnes();
+        rsnes.set_overclock(Overclock {
+            sa1_multiplier: 4,
+            ..Overclock::NONE
+        });
+        assert_eq!(rsnes.overclock().sa1_multiplier, 4);
+        assert_eq!(rsnes.coprocessor_cycles, 1);
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_stats_cover_whole_frame() {
+        use ppu::rendering::framebuffer::PixelFormat;
+        use std::time::Duration;
+
+        // BRA to itself at the reset vector
+        let mut rom_data = create_valid_lorom(0x20000);
+        rom_data[..2].copy_from_slice(&[0x80, 0xFE]);
+        rom_data[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0x80]);
+        let mut rsnes = RSnes::from_rom(Rom::from_bytes(rom_data).unwrap(), &Default::default());
+        let mut renderer = Renderer::with_pixel_format(PixelFormat::Rgb565);
+
+        rsnes.stats.set_enabled(true);
+        rsnes.run_frame(&mut renderer);
+        rsnes.run_frame(&mut renderer);
+
+        assert_eq!(rsnes.stats.frames().count(), 2);
+        let frame = rsnes.stats.last_frame().unwrap();
+        let attributed: Duration = Subsystem::ALL.iter().map(|&s| frame.get(s)).sum();
+        assert!(frame.get(Subsystem::Cpu) > Duration::ZERO);
+        assert!(frame.get(Subsystem::Ppu) > Duration::ZERO);
+        assert!(attributed <= frame.total);
+    }
+
+    #[cfg(feature = "access-log")]
+    #[test]
+    fn test_access_log_interleaves_dma_and_cpu() {
+        use bus::access_log::{AccessKind, BusAccess};
+        use std::sync::{Arc, Mutex};
+
+        let mut rsnes = RSnes::from_rom(store_rom(0x42), &Default::default());
+        let accesses = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&accesses);
+        rsnes
+            .bus
+            .access_tap
+            .set_consumer(move |access: &BusAccess| sink.lock().unwrap().push(*access));
+
+        // 2 bytes from WRAM to the B bus, before the first CPU cycle
+        rsnes.bus.wram.data[0x0100] = 0x12;
+        rsnes.bus.wram.data[0x0101] = 0x34;
+        set_dma_channel(&mut rsnes, 0, 0x00, 0x7E, 0x0100, 2);
+        rsnes.bus.io.mdmaen = 0b0000_0001;
+
+        for _ in 0..1000 {
+            rsnes.update();
+        }
+
+        let accesses = accesses.lock().unwrap();
+        let dma: Vec<_> = accesses
+            .iter()
+            .take(4)
+            .map(|access| (access.source, access.kind, access.addr, access.value))
+            .collect();
+        assert_eq!(
+            dma,
+            [
+                (AccessSource::Dma, AccessKind::Read, snes_addr!(0x7E:0x0100), 0x12),
+                (AccessSource::Dma, AccessKind::Read, snes_addr!(0x7E:0x0101), 0x34),
+                (AccessSource::Dma, AccessKind::Write, snes_addr!(0:0x21FF), 0x12),
+                (AccessSource::Dma, AccessKind::Write, snes_addr!(0:0x21FF), 0x34),
+            ]
+        );
+
+        // Then only the CPU, running the program
+        let cpu = &accesses[4..];
+        assert!(cpu.iter().all(|access| access.source == AccessSource::Cpu));
+        assert!(cpu.windows(2).all(|pair| {
+            pair[0].cpu_cycle < pair[1].cpu_cycle && pair[0].master_cycle < pair[1].master_cycle
+        }));
+        assert!(cpu.iter().any(|access| {
+            access.kind == AccessKind::Write
+                && access.addr == snes_addr!(0x7E:0x0000)
+                && access.value == 0x42
+        }));
+    }
+}
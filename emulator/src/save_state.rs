@@ -0,0 +1,217 @@
+//! Save states of the whole console. Each component saves its own section (see
+//! [`common::save_state`] for the format): the PPU, the APU, the CPU I/O registers with the
+//! DMA channels, WRAM and the coprocessor. The sections of the main CPU and of the timing
+//! of the console are defined here.
+//!
+//! The fixtures in `emulator/fixtures/save_states` are states saved by previous versions:
+//! loading them is tested, so they must never be regenerated.
+
+pub use common::save_state::*;
+use common::video_standard::VideoStandard;
+use cpu::cpu::CPU;
+
+/// Section of the main CPU. The CPU crate only uses the core library, so its section and
+/// the migrations of older versions are kept here.
+pub(crate) struct CpuSection<'a>(pub &'a mut CPU);
+
+impl Stateful for CpuSection<'_> {
+    const TAG: Tag = *b"CPUR";
+    const VERSION: u16 = 2;
+    /// Version 1 only had the registers: add the data bus, no pending reset and no halt
+    const MIGRATIONS: &'static [Migration] = &[|data| Ok([data, &[0, 0, 0]].concat())];
+
+    fn save(&self, writer: &mut StateWriter) {
+        self.0.save_state(writer);
+    }
+
+    fn load(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.0.load_state(reader)
+    }
+}
+
+/// Master clock and the cycles the CPU waits for, see [`crate::RSnes`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Timing {
+    pub master_cycles: u64,
+    pub line_cycle: u16,
+    pub cpu_cycles: u64,
+    pub cpu_master_cycles_to_wait: u32,
+    pub dma_master_cycles_to_wait: u32,
+}
+
+impl Stateful for Timing {
+    const TAG: Tag = *b"TIME";
+    const VERSION: u16 = 1;
+
+    fn save(&self, writer: &mut StateWriter) {
+        writer.u64(self.master_cycles);
+        writer.u16(self.line_cycle);
+        writer.u64(self.cpu_cycles);
+        writer.u32(self.cpu_master_cycles_to_wait);
+        writer.u32(self.dma_master_cycles_to_wait);
+    }
+
+    fn load(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.master_cycles = reader.u64()?;
+        self.line_cycle = reader.u16()?;
+        self.cpu_cycles = reader.u64()?;
+        self.cpu_master_cycles_to_wait = reader.u32()?;
+        self.dma_master_cycles_to_wait = reader.u32()?;
+        if self.line_cycle as u64 >= VideoStandard::MASTER_CYCLES_PER_SCANLINE {
+            return Err(reader.corrupt());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RSnes;
+    use bus::rom::Rom;
+    use bus::rom::test_rom::*;
+    use bus::wram::Wram;
+    use cpu::registers::Registers;
+    use ppu::rendering::renderer::Renderer;
+
+    #[test]
+    fn test_cpu_section_round_trip() {
+        let registers = Registers {
+            A: 0x1234,
+            X: 0x0056,
+            PB: 0x80,
+            PC: 0x8123,
+            E: true,
+            ..Registers::default()
+        };
+        let mut cpu = CPU::new(registers);
+        cpu.data_bus = 0x42;
+        let mut state = SaveState::new();
+        state.save(&CpuSection(&mut cpu));
+
+        let state = SaveState::from_bytes(&state.to_bytes()).unwrap();
+        let mut loaded = CPU::poweron();
+        state.load(&mut CpuSection(&mut loaded)).unwrap();
+        assert_eq!(*loaded.regs(), registers);
+        assert_eq!(loaded.data_bus, 0x42);
+        assert!(!loaded.mid_instruction());
+    }
+
+    #[test]
+    fn test_version_1_fixture() {
+        // Saved by format version 1, with the first version of every section
+        let fixture = include_bytes!("../fixtures/save_states/v1.state");
+        let state = SaveState::from_bytes(fixture).unwrap();
+
+        let mut cpu = CPU::poweron();
+        state.load(&mut CpuSection(&mut cpu)).unwrap();
+        let registers = cpu.regs();
+        assert_eq!(registers.A, 0x1234);
+        assert_eq!(registers.S, 0x01FF);
+        assert_eq!((registers.PB, registers.PC), (0x80, 0x8000));
+        assert!(registers.E && registers.P.M && registers.P.X && !registers.P.C);
+        assert!(cpu.halted().is_none() && !cpu.reset_pending());
+
+        let mut wram = Wram::new();
+        state.load(&mut wram).unwrap();
+        assert_eq!(wram.data[..4], [0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(wram.data[0x1FFFF], 0x7F);
+    }
+
+    /// Console running a ROM which keeps the PPU, the DMA channels and WRAM busy: it shows
+    /// a backdrop color uploaded to CGRAM by DMA and changed every frame.
+    fn make_busy_rsnes() -> RSnes {
+        #[rustfmt::skip]
+        let program = [
+            0x78,                   // SEI
+            0x18, 0xFB,             // CLC ; XCE
+            0xA9, 0x0F, 0x8D, 0x00, 0x21, // LDA #$0F ; STA INIDISP
+            0xA9, 0x01, 0x8D, 0x05, 0x21, // LDA #$01 ; STA BGMODE
+            0x8D, 0x2C, 0x21,       // STA TM
+            // loop:
+            0xEE, 0x00, 0x00,       // INC $0000
+            0xAD, 0x00, 0x00,       // LDA $0000
+            0x8D, 0x01, 0x00,       // STA $0001
+            0x9C, 0x21, 0x21,       // STZ CGADD
+            0x9C, 0x00, 0x43,       // STZ DMAP0
+            0xA9, 0x22, 0x8D, 0x01, 0x43, // LDA #$22 ; STA BBAD0
+            0x9C, 0x02, 0x43,       // STZ A1T0L
+            0x9C, 0x03, 0x43,       // STZ A1T0H
+            0x9C, 0x04, 0x43,       // STZ A1B0
+            0xA9, 0x02, 0x8D, 0x05, 0x43, // LDA #$02 ; STA DAS0L
+            0x9C, 0x06, 0x43,       // STZ DAS0H
+            0xA9, 0x01, 0x8D, 0x0B, 0x42, // LDA #$01 ; STA MDMAEN
+            // wait for the end of vblank, then for the next vblank
+            0xAD, 0x12, 0x42, 0x30, 0xFB, // LDA HVBJOY ; BMI -5
+            0xAD, 0x12, 0x42, 0x10, 0xFB, // LDA HVBJOY ; BPL -5
+            0x80, 0xCA,             // BRA loop
+        ];
+        let mut rom_data = create_valid_lorom(0x20000);
+        rom_data[..program.len()].copy_from_slice(&program);
+        rom_data[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0x80]);
+        RSnes::from_rom(Rom::from_bytes(rom_data).unwrap(), &Default::default())
+    }
+
+    fn run_frames(rsnes: &mut RSnes, renderer: &mut Renderer, frames: usize) -> Vec<u8> {
+        for _ in 0..frames {
+            rsnes.run_frame(renderer);
+        }
+        renderer.framebuffer.to_vec()
+    }
+
+    #[test]
+    fn test_console_round_trip() {
+        let mut rsnes = make_busy_rsnes();
+        let mut renderer = Renderer::new();
+        let before = run_frames(&mut rsnes, &mut renderer, 3);
+        // Mid-scanline and mid-instruction: saving runs the CPU to its next opcode fetch
+        rsnes.run_for(1001, Some(&mut renderer));
+        let state = rsnes.save_state().to_bytes();
+        let expected = run_frames(&mut rsnes, &mut renderer, 4);
+        assert_ne!(expected, before);
+
+        let mut loaded = make_busy_rsnes();
+        loaded
+            .load_state(&SaveState::from_bytes(&state).unwrap())
+            .unwrap();
+        let mut renderer = Renderer::new();
+        let frame = run_frames(&mut loaded, &mut renderer, 4);
+        assert_eq!(frame, expected);
+        assert_eq!(loaded.master_cycles, rsnes.master_cycles);
+        assert_eq!(loaded.bus.wram.data, rsnes.bus.wram.data);
+        assert_eq!(loaded.save_state(), rsnes.save_state());
+    }
+
+    #[test]
+    fn test_branch_loop_round_trip() {
+        // BRA -2 is never between two instructions: it is saved past its opcode fetch
+        let make_rsnes = || {
+            let mut rom_data = create_valid_lorom(0x20000);
+            rom_data[..2].copy_from_slice(&[0x80, 0xFE]);
+            rom_data[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0x80]);
+            RSnes::from_rom(Rom::from_bytes(rom_data).unwrap(), &Default::default())
+        };
+        let mut rsnes = make_rsnes();
+        rsnes.run_for(1001, None);
+        let state = rsnes.save_state();
+        assert!(rsnes.cpu.mid_instruction());
+
+        let mut loaded = make_rsnes();
+        loaded.load_state(&state).unwrap();
+        rsnes.run_for(1001, None);
+        loaded.run_for(1001, None);
+        assert_eq!(loaded.save_state(), rsnes.save_state());
+        assert_eq!(loaded.cpu.regs().PC, 0x8000);
+    }
+
+    #[test]
+    fn test_state_missing_a_section_rejected() {
+        let mut rsnes = make_busy_rsnes();
+        let mut state = SaveState::new();
+        state.save(&rsnes.bus.wram);
+        assert_eq!(
+            rsnes.load_state(&state),
+            Err(StateError::MissingSection(*b"CPUR"))
+        );
+    }
+}
@@ -0,0 +1,407 @@
+//! Frame pacing for frontends driving a [`crate::system::System`]: when to run the next
+//! frame, whether to draw it and whether to play its audio.
+//!
+//! ```ignore
+//! loop {
+//!     let plan = scheduler.plan(Instant::now());
+//!     thread::sleep(plan.wait);
+//!     if plan.render { system.run_frame() } else { system.skip_frame() }
+//!     if plan.play_audio { queue_audio(&system.audio) }
+//! }
+//! ```
+//!
+//! Frames are paced on the host clock by default, or on the display refresh, the audio
+//! queue or not at all (see [`Pacing`]). Fast-forward and frame skipping are plain flags, so
+//! a frontend can toggle them on key presses between two frames.
+
+use common::video_standard::VideoStandard;
+use std::time::{Duration, Instant};
+
+/// What the frontend does for the next frame, see [`Scheduler::plan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramePlan {
+    /// Time to wait before running the frame
+    pub wait: Duration,
+    /// Whether to draw the frame ([`crate::system::System::run_frame`]) or only emulate it
+    /// ([`crate::system::System::skip_frame`])
+    pub render: bool,
+    /// Whether to play the audio of the frame. Fast-forwarded audio is dropped rather than
+    /// resampled; the APU still runs to stay in sync.
+    pub play_audio: bool,
+}
+
+/// What frames are paced on, see [`Scheduler::set_pacing`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Pacing {
+    /// Frames are due every frame duration, measured with the host clock
+    #[default]
+    Timer,
+    /// The frontend presents frames with vsync, which blocks until the display refresh, so
+    /// frames run without waiting. Only the right speed on a display refreshing at the
+    /// emulated frame rate.
+    Vsync,
+    /// Frames wait while the audio queue holds more than the audio latency, the frontend
+    /// reporting its queue with [`Scheduler::set_audio_queued`]. Paced on the timer until
+    /// the first report.
+    AudioClock,
+    /// Frames run as fast as the host can, without audio, e.g. for benchmarks
+    FreeRun,
+}
+
+/// Time between the starts of the frames planned so far, see [`Scheduler::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PacingStats {
+    /// Frame intervals measured
+    pub frames: u32,
+    pub mean_interval: Duration,
+    /// Mean difference between an interval and the frame duration
+    pub mean_jitter: Duration,
+    /// Largest difference between an interval and the frame duration
+    pub max_jitter: Duration,
+}
+
+pub struct Scheduler {
+    frame_duration: Duration,
+    pacing: Pacing,
+    /// Time at which the next frame is due, none until the first frame or after
+    /// fast-forward
+    deadline: Option<Instant>,
+    fast_forward: bool,
+    auto_frame_skip: bool,
+    max_frame_skip: u32,
+    /// Consecutive frames skipped so far
+    skipped: u32,
+    audio_latency: Duration,
+    /// Audio the frontend has queued but not played yet, none until it reports it
+    audio_queued: Option<Duration>,
+    /// Planned start of the last frame
+    last_start: Option<Instant>,
+    stats: PacingStats,
+    total_interval: Duration,
+    total_jitter: Duration,
+}
+
+impl Scheduler {
+    /// Frames in a row that automatic frame skipping drops at most, by default
+    pub const DEFAULT_MAX_FRAME_SKIP: u32 = 4;
+
+    /// Audio queued ahead that [`Pacing::AudioClock`] keeps, by default
+    pub const DEFAULT_AUDIO_LATENCY: Duration = Duration::from_millis(64);
+
+    pub fn new(video_standard: VideoStandard) -> Self {
+        Self {
+            frame_duration: Duration::from_secs_f64(1.0 / video_standard.frame_rate()),
+            deadline: None,
+            fast_forward: false,
+            auto_frame_skip: false,
+            max_frame_skip: Self::DEFAULT_MAX_FRAME_SKIP,
+            skipped: 0,
+            pacing: Pacing::default(),
+            audio_latency: Self::DEFAULT_AUDIO_LATENCY,
+            audio_queued: None,
+            last_start: None,
+            stats: PacingStats::default(),
+            total_interval: Duration::ZERO,
+            total_jitter: Duration::ZERO,
+        }
+    }
+
+    pub fn frame_duration(&self) -> Duration {
+        self.frame_duration
+    }
+
+    pub fn pacing(&self) -> Pacing {
+        self.pacing
+    }
+
+    /// Changes what the next frames are paced on, restarting the pacing from the next frame.
+    pub fn set_pacing(&mut self, pacing: Pacing) {
+        self.pacing = pacing;
+        self.deadline = None;
+    }
+
+    pub fn set_audio_latency(&mut self, latency: Duration) {
+        self.audio_latency = latency;
+    }
+
+    /// Reports the duration of the audio queued by the frontend and not played yet, for
+    /// [`Pacing::AudioClock`].
+    pub fn set_audio_queued(&mut self, queued: Duration) {
+        self.audio_queued = Some(queued);
+    }
+
+    /// Frame intervals since the scheduler was created or [`Self::reset_stats`].
+    pub fn stats(&self) -> PacingStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.last_start = None;
+        self.stats = PacingStats::default();
+        self.total_interval = Duration::ZERO;
+        self.total_jitter = Duration::ZERO;
+    }
+
+    pub fn fast_forward(&self) -> bool {
+        self.fast_forward
+    }
+
+    /// Runs frames as fast as the host can, without audio. Leaving fast-forward restarts
+    /// the pacing from the next frame instead of catching up.
+    pub fn set_fast_forward(&mut self, enabled: bool) {
+        self.fast_forward = enabled;
+        self.deadline = None;
+    }
+
+    pub fn toggle_fast_forward(&mut self) {
+        self.set_fast_forward(!self.fast_forward);
+    }
+
+    pub fn auto_frame_skip(&self) -> bool {
+        self.auto_frame_skip
+    }
+
+    /// Skips drawing frames while the host runs late, up to [`Self::set_max_frame_skip`]
+    /// frames in a row.
+    pub fn set_auto_frame_skip(&mut self, enabled: bool) {
+        self.auto_frame_skip = enabled;
+    }
+
+    pub fn toggle_auto_frame_skip(&mut self) {
+        self.set_auto_frame_skip(!self.auto_frame_skip);
+    }
+
+    pub fn set_max_frame_skip(&mut self, frames: u32) {
+        self.max_frame_skip = frames;
+    }
+
+    /// Plans the next frame, `now` being the current time.
+    ///
+    /// With [`Pacing::Timer`], frames are due every [`Self::frame_duration`]. When the host
+    /// runs late, frames are run without waiting (and skipped with auto frame skip) until it
+    /// catches up; when it is more frames late than it may skip, the delay is dropped rather
+    /// than caught up.
+    pub fn plan(&mut self, now: Instant) -> FramePlan {
+        let plan = self.plan_frame(now);
+        self.record_start(now + plan.wait);
+        plan
+    }
+
+    fn plan_frame(&mut self, now: Instant) -> FramePlan {
+        let unpaced = |play_audio| FramePlan {
+            wait: Duration::ZERO,
+            render: true,
+            play_audio,
+        };
+        if self.fast_forward {
+            return unpaced(false);
+        }
+        match (self.pacing, self.audio_queued) {
+            (Pacing::Timer, _) | (Pacing::AudioClock, None) => self.plan_timer(now),
+            (Pacing::Vsync, _) => unpaced(true),
+            (Pacing::AudioClock, Some(queued)) => FramePlan {
+                wait: queued.saturating_sub(self.audio_latency),
+                render: true,
+                play_audio: true,
+            },
+            (Pacing::FreeRun, _) => unpaced(false),
+        }
+    }
+
+    fn plan_timer(&mut self, now: Instant) -> FramePlan {
+        let deadline = *self.deadline.get_or_insert(now);
+        let (wait, render) = if now <= deadline {
+            (deadline - now, true)
+        } else {
+            let late = now - deadline;
+            let skip = self.auto_frame_skip
+                && late >= self.frame_duration
+                && self.skipped < self.max_frame_skip;
+            (Duration::ZERO, !skip)
+        };
+        self.skipped = if render { 0 } else { self.skipped + 1 };
+
+        let max_late = self.frame_duration * (self.max_frame_skip + 1);
+        let start = if now > deadline + max_late {
+            now
+        } else {
+            deadline
+        };
+        self.deadline = Some(start + self.frame_duration);
+
+        FramePlan {
+            wait,
+            render,
+            play_audio: true,
+        }
+    }
+
+    fn record_start(&mut self, start: Instant) {
+        let Some(last_start) = self.last_start.replace(start) else {
+            return;
+        };
+        let interval = start.saturating_duration_since(last_start);
+        let jitter = interval.abs_diff(self.frame_duration);
+        self.total_interval += interval;
+        self.total_jitter += jitter;
+        self.stats.frames += 1;
+        self.stats.mean_interval = self.total_interval / self.stats.frames;
+        self.stats.mean_jitter = self.total_jitter / self.stats.frames;
+        self.stats.max_jitter = self.stats.max_jitter.max(jitter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler() -> (Scheduler, Instant, Duration) {
+        let scheduler = Scheduler::new(VideoStandard::NTSC);
+        let frame = scheduler.frame_duration();
+        (scheduler, Instant::now(), frame)
+    }
+
+    #[test]
+    fn test_frames_paced_at_frame_rate() {
+        let (mut scheduler, start, frame) = scheduler();
+        assert_eq!(scheduler.plan(start).wait, Duration::ZERO);
+
+        // The frame took a quarter of its time: the rest is waited
+        let plan = scheduler.plan(start + frame / 4);
+        assert_eq!(plan.wait, frame - frame / 4);
+        assert!(plan.render && plan.play_audio);
+        assert_eq!(scheduler.plan(start + frame * 2).wait, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_late_frames_rendered_without_frame_skip() {
+        let (mut scheduler, start, frame) = scheduler();
+        scheduler.plan(start);
+
+        let plan = scheduler.plan(start + frame * 3);
+        assert_eq!(plan.wait, Duration::ZERO);
+        assert!(plan.render);
+    }
+
+    #[test]
+    fn test_auto_frame_skip() {
+        let (mut scheduler, start, frame) = scheduler();
+        scheduler.set_auto_frame_skip(true);
+        scheduler.plan(start);
+
+        // One and a half frames late: the next frame is skipped, then the host caught up
+        let late = start + frame * 5 / 2;
+        assert!(!scheduler.plan(late).render);
+        assert!(scheduler.plan(late).render);
+    }
+
+    #[test]
+    fn test_frame_skip_limited() {
+        let (mut scheduler, start, frame) = scheduler();
+        scheduler.set_auto_frame_skip(true);
+        scheduler.set_max_frame_skip(2);
+        scheduler.plan(start);
+
+        // The host needs two frame durations per frame: it never catches up
+        let renders: Vec<bool> = (1..=4)
+            .map(|i| scheduler.plan(start + frame * (2 * i + 1)).render)
+            .collect();
+        assert_eq!(renders, [false, false, true, false]);
+    }
+
+    #[test]
+    fn test_large_delay_dropped() {
+        let (mut scheduler, start, frame) = scheduler();
+        scheduler.plan(start);
+
+        // e.g. the window was dragged: pacing restarts instead of running frames back to back
+        let resume = start + frame * 100;
+        scheduler.plan(resume);
+        assert_eq!(scheduler.plan(resume).wait, frame);
+    }
+
+    #[test]
+    fn test_fast_forward() {
+        let (mut scheduler, start, frame) = scheduler();
+        scheduler.plan(start);
+        scheduler.toggle_fast_forward();
+
+        let plan = scheduler.plan(start);
+        assert_eq!(
+            plan,
+            FramePlan {
+                wait: Duration::ZERO,
+                render: true,
+                play_audio: false,
+            }
+        );
+
+        // Leaving fast-forward paces again from the next frame
+        scheduler.toggle_fast_forward();
+        let resume = start + frame / 2;
+        assert_eq!(scheduler.plan(resume).wait, Duration::ZERO);
+        assert_eq!(scheduler.plan(resume).wait, frame);
+    }
+
+    #[test]
+    fn test_vsync_and_free_run_do_not_wait() {
+        let (mut scheduler, start, _) = scheduler();
+        scheduler.set_pacing(Pacing::Vsync);
+        scheduler.plan(start);
+        let plan = scheduler.plan(start);
+        assert_eq!(plan.wait, Duration::ZERO);
+        assert!(plan.play_audio);
+
+        scheduler.set_pacing(Pacing::FreeRun);
+        let plan = scheduler.plan(start);
+        assert_eq!(plan.wait, Duration::ZERO);
+        assert!(!plan.play_audio);
+    }
+
+    #[test]
+    fn test_audio_clock() {
+        let (mut scheduler, start, frame) = scheduler();
+        scheduler.set_pacing(Pacing::AudioClock);
+
+        // Timer pacing until the audio queue is reported
+        scheduler.plan(start);
+        assert_eq!(scheduler.plan(start).wait, frame);
+
+        let latency = Scheduler::DEFAULT_AUDIO_LATENCY;
+        scheduler.set_audio_queued(latency + frame / 2);
+        assert_eq!(scheduler.plan(start).wait, frame / 2);
+        scheduler.set_audio_queued(latency / 2);
+        assert_eq!(scheduler.plan(start).wait, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_stats() {
+        let (mut scheduler, start, frame) = scheduler();
+        scheduler.set_pacing(Pacing::Vsync);
+        let ms = Duration::from_millis(1);
+
+        // Frames starting 1 ms early, then 3 ms late
+        scheduler.plan(start);
+        scheduler.plan(start + frame - ms);
+        scheduler.plan(start + frame * 2 + ms * 2);
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.frames, 2);
+        assert_eq!(stats.mean_interval, frame + ms);
+        assert_eq!(stats.mean_jitter, ms * 2);
+        assert_eq!(stats.max_jitter, ms * 3);
+
+        scheduler.reset_stats();
+        scheduler.plan(start + frame * 3);
+        assert_eq!(scheduler.stats(), PacingStats::default());
+    }
+
+    #[test]
+    fn test_stats_count_waits() {
+        let (mut scheduler, start, frame) = scheduler();
+        scheduler.plan(start);
+        // The frame took a quarter of its time and waits the rest: no jitter
+        scheduler.plan(start + frame / 4);
+        assert_eq!(scheduler.stats().mean_jitter, Duration::ZERO);
+    }
+}
@@ -0,0 +1,290 @@
+//! Host time spent in each part of the console, per frame (`stats` feature).
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Part of the console whose host time is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+    /// CPU and cartridge coprocessor, DMA transfers excluded
+    Cpu,
+    /// Scanline rendering and PPU/IO scanline updates
+    Ppu,
+    /// Audio rendering
+    Apu,
+    /// General purpose DMA transfers
+    Dma,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 4] = [Self::Cpu, Self::Ppu, Self::Apu, Self::Dma];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cpu => "CPU",
+            Self::Ppu => "PPU",
+            Self::Apu => "APU",
+            Self::Dma => "DMA",
+        }
+    }
+}
+
+/// Host time spent emulating one frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameTimes {
+    subsystems: [Duration; Subsystem::ALL.len()],
+    /// Whole frame, including the work not attributed to a subsystem
+    pub total: Duration,
+}
+
+impl FrameTimes {
+    pub fn get(&self, subsystem: Subsystem) -> Duration {
+        self.subsystems[subsystem as usize]
+    }
+}
+
+/// Rolling statistics over the frames kept by [`FrameStats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub average: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub max: Duration,
+}
+
+impl Summary {
+    fn of(mut samples: Vec<Duration>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+
+        // Nearest-rank percentile
+        let percentile = |p: usize| samples[(samples.len() * p).div_ceil(100).max(1) - 1];
+        Some(Self {
+            average: samples.iter().sum::<Duration>() / samples.len() as u32,
+            p50: percentile(50),
+            p95: percentile(95),
+            max: samples[samples.len() - 1],
+        })
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "avg {:.2} ms, p50 {:.2} ms, p95 {:.2} ms, max {:.2} ms",
+            ms(self.average),
+            ms(self.p50),
+            ms(self.p95),
+            ms(self.max)
+        )
+    }
+}
+
+/// Per-frame host time of each subsystem, to spot performance regressions or feed a
+/// frontend overlay.
+///
+/// Disabled by default, in which case no clock is read. Once enabled, the last
+/// [`Self::window`] frames are kept and summarized with [`Self::summary`] and
+/// [`Self::frame_summary`].
+///
+/// The driver brackets each frame with [`Self::begin_frame`] and [`Self::end_frame`], and
+/// times the work in between with [`Self::start`] and [`Self::record`].
+#[derive(Debug)]
+pub struct FrameStats {
+    enabled: bool,
+    window: usize,
+    frames: VecDeque<FrameTimes>,
+    current: FrameTimes,
+    frame_start: Option<Instant>,
+}
+
+impl Default for FrameStats {
+    /// Two seconds of NTSC frames
+    fn default() -> Self {
+        Self::with_window(120)
+    }
+}
+
+impl FrameStats {
+    /// Keeps the last `window` frames (at least one).
+    pub fn with_window(window: usize) -> Self {
+        Self {
+            enabled: false,
+            window: window.max(1),
+            frames: VecDeque::new(),
+            current: FrameTimes::default(),
+            frame_start: None,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.frame_start = None;
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Starts timing a piece of work, `None` when disabled.
+    pub fn start(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    /// Adds the time elapsed since `start` to `subsystem` in the current frame.
+    pub fn record(&mut self, subsystem: Subsystem, start: Option<Instant>) {
+        if let Some(start) = start {
+            self.add(subsystem, start.elapsed());
+        }
+    }
+
+    pub fn add(&mut self, subsystem: Subsystem, duration: Duration) {
+        self.current.subsystems[subsystem as usize] += duration;
+    }
+
+    /// Time already attributed to `subsystem` in the current frame.
+    pub fn current(&self, subsystem: Subsystem) -> Duration {
+        self.current.get(subsystem)
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.current = FrameTimes::default();
+        self.frame_start = self.start();
+    }
+
+    /// Closes the current frame and adds it to the window, dropping the oldest frame.
+    pub fn end_frame(&mut self) {
+        let Some(frame_start) = self.frame_start.take() else {
+            return;
+        };
+        self.current.total = frame_start.elapsed();
+        self.push(self.current);
+    }
+
+    fn push(&mut self, frame: FrameTimes) {
+        if self.frames.len() == self.window {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// Kept frames, oldest first.
+    pub fn frames(&self) -> impl Iterator<Item = &FrameTimes> {
+        self.frames.iter()
+    }
+
+    pub fn last_frame(&self) -> Option<&FrameTimes> {
+        self.frames.back()
+    }
+
+    /// Statistics of `subsystem` over the kept frames, `None` before the first frame.
+    pub fn summary(&self, subsystem: Subsystem) -> Option<Summary> {
+        Summary::of(
+            self.frames
+                .iter()
+                .map(|frame| frame.get(subsystem))
+                .collect(),
+        )
+    }
+
+    /// Statistics of the whole frame time over the kept frames.
+    pub fn frame_summary(&self) -> Option<Summary> {
+        Summary::of(self.frames.iter().map(|frame| frame.total).collect())
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.current = FrameTimes::default();
+        self.frame_start = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn frame(cpu: u64, total: u64) -> FrameTimes {
+        let mut frame = FrameTimes {
+            total: ms(total),
+            ..Default::default()
+        };
+        frame.subsystems[Subsystem::Cpu as usize] = ms(cpu);
+        frame
+    }
+
+    #[test]
+    fn test_disabled_stats_record_nothing() {
+        let mut stats = FrameStats::default();
+        assert_eq!(stats.start(), None);
+
+        stats.begin_frame();
+        stats.record(Subsystem::Cpu, stats.start());
+        stats.end_frame();
+
+        assert!(stats.last_frame().is_none());
+        assert_eq!(stats.frame_summary(), None);
+    }
+
+    #[test]
+    fn test_frame_collects_subsystem_times() {
+        let mut stats = FrameStats::default();
+        stats.set_enabled(true);
+
+        stats.begin_frame();
+        stats.add(Subsystem::Ppu, ms(3));
+        stats.add(Subsystem::Ppu, ms(2));
+        stats.add(Subsystem::Dma, ms(1));
+        stats.end_frame();
+
+        let frame = stats.last_frame().unwrap();
+        assert_eq!(frame.get(Subsystem::Ppu), ms(5));
+        assert_eq!(frame.get(Subsystem::Dma), ms(1));
+        assert_eq!(frame.get(Subsystem::Cpu), Duration::ZERO);
+
+        // The next frame starts from zero
+        stats.begin_frame();
+        assert_eq!(stats.current(Subsystem::Ppu), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_window_drops_oldest_frames() {
+        let mut stats = FrameStats::with_window(3);
+        for i in 1..=5 {
+            stats.push(frame(i, i));
+        }
+
+        let kept: Vec<_> = stats.frames().map(|frame| frame.total).collect();
+        assert_eq!(kept, [ms(3), ms(4), ms(5)]);
+    }
+
+    #[test]
+    fn test_summary_percentiles() {
+        let mut stats = FrameStats::with_window(100);
+        for i in 1..=100 {
+            stats.push(frame(i, 2 * i));
+        }
+
+        let cpu = stats.summary(Subsystem::Cpu).unwrap();
+        assert_eq!(cpu.p50, ms(50));
+        assert_eq!(cpu.p95, ms(95));
+        assert_eq!(cpu.max, ms(100));
+        assert_eq!(cpu.average, Duration::from_micros(50_500));
+        assert_eq!(stats.frame_summary().unwrap().p95, ms(190));
+        assert_eq!(stats.summary(Subsystem::Apu).unwrap().max, Duration::ZERO);
+    }
+}
@@ -0,0 +1,461 @@
+//! The console as driven by an embedding frontend: one call per frame, with the buttons held
+//! for that frame in and a picture and audio samples out.
+
+use crate::rsnes::RSnes;
+use crate::save_state::{SaveState, StateError};
+#[cfg(feature = "stats")]
+use crate::stats::Subsystem;
+use bus::joypad::Gamepad;
+use common::video_standard::VideoStandard;
+use ppu::rendering::composition::Composition;
+use ppu::rendering::frame_sink::FrameSink;
+use ppu::rendering::framebuffer::{FrameBuffer, PixelFormat};
+use ppu::rendering::overlay::Overlay;
+use ppu::rendering::renderer::Renderer;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Output rate of the S-DSP, in Hz
+pub const SAMPLE_RATE: f64 = 32_000.0;
+
+pub struct System {
+    pub rsnes: RSnes,
+    pub renderer: Renderer,
+    /// Interleaved stereo samples of the last frame
+    pub audio: Vec<i16>,
+    /// Fraction of a sample carried over to the next frame
+    pending_samples: f64,
+    /// Receives the picture of every frame, if set
+    frame_sink: Option<Box<dyn FrameSink>>,
+    /// Drawn on top of every frame, after the frame sink got the emulated picture
+    overlays: Vec<Box<dyn Overlay>>,
+    /// Canvas presented instead of the picture, e.g. with a border around it
+    composition: Option<Composition>,
+    /// Weight of the previous frame and that frame, when frame blending is on
+    frame_blend: Option<(u8, FrameBuffer)>,
+    /// Set once the emulated program reached an unimplemented feature; the emulation is
+    /// then stopped and the last picture kept.
+    crashed: bool,
+}
+
+// Embedders run one console per thread (netplay spectators, fuzzing workers)
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    assert_send::<System>();
+};
+
+impl System {
+    /// Wraps `rsnes`, rendering frames in `format`.
+    pub fn new(mut rsnes: RSnes, format: PixelFormat) -> Self {
+        // Frontends always have a second controller available
+        rsnes
+            .bus
+            .io
+            .joypads
+            .connect(1, Box::new(Gamepad::default()));
+
+        Self {
+            rsnes,
+            renderer: Renderer::with_pixel_format(format),
+            audio: Vec::new(),
+            pending_samples: 0.0,
+            frame_sink: None,
+            overlays: Vec::new(),
+            composition: None,
+            frame_blend: None,
+            crashed: false,
+        }
+    }
+
+    pub fn video_standard(&self) -> VideoStandard {
+        self.rsnes.video_standard
+    }
+
+    pub fn crashed(&self) -> bool {
+        self.crashed
+    }
+
+    /// Sets the buttons held on the gamepad plugged into `port`, if any.
+    pub fn set_buttons(&mut self, port: usize, buttons: u16) {
+        if let Some(pad) = self.rsnes.bus.io.joypads.device_mut::<Gamepad>(port) {
+            pad.buttons = buttons;
+        }
+    }
+
+    /// Hands the picture of every following frame to `sink`, e.g. a
+    /// [`ppu::rendering::frame_sink::Y4mWriter`] recording a video.
+    pub fn set_frame_sink(&mut self, sink: impl FrameSink + 'static) {
+        self.frame_sink = Some(Box::new(sink));
+    }
+
+    /// Stops handing frames over, returning the sink.
+    pub fn take_frame_sink(&mut self) -> Option<Box<dyn FrameSink>> {
+        self.frame_sink.take()
+    }
+
+    /// Draws `overlay` on top of every following frame, after the ones already added.
+    ///
+    /// Overlays only change what the frontend presents: the frame sink still receives the
+    /// emulated picture. After a crash the picture is no longer rendered again, so
+    /// overlays draw over their own output of the previous frame.
+    pub fn add_overlay(&mut self, overlay: impl Overlay + 'static) {
+        self.overlays.push(Box::new(overlay));
+    }
+
+    pub fn clear_overlays(&mut self) {
+        self.overlays.clear();
+    }
+
+    /// Presents every following frame on the canvas of `composition`, e.g. to surround it
+    /// with a border: see [`Self::picture`]. The picture is composed once the overlays are
+    /// drawn, and the frame sink still receives the emulated picture alone.
+    pub fn set_composition(&mut self, composition: Composition) {
+        self.composition = Some(composition);
+    }
+
+    /// Presents the emulated picture again, returning the composition.
+    pub fn take_composition(&mut self) -> Option<Composition> {
+        self.composition.take()
+    }
+
+    pub fn composition_mut(&mut self) -> Option<&mut Composition> {
+        self.composition.as_mut()
+    }
+
+    /// Picture to present and its number of lines: the composition canvas if one is set,
+    /// the rendered frame otherwise.
+    pub fn picture(&self) -> (&FrameBuffer, usize) {
+        match &self.composition {
+            Some(composition) => (composition.canvas(), composition.canvas().height()),
+            None => (&self.renderer.framebuffer, self.renderer.active_height),
+        }
+    }
+
+    /// Blends every rendered frame with the previous one, `factor` (0.0 to 1.0) being the
+    /// share of the previous frame: at 0.5, sprites flickering at 30 Hz look
+    /// half-transparent like on a TV. 0.0 turns blending off.
+    ///
+    /// The frame sink receives the blended picture; overlays are drawn on top of it.
+    pub fn set_frame_blend(&mut self, factor: f32) {
+        let weight = (factor.clamp(0.0, 1.0) * 256.0).round().min(255.0) as u8;
+        self.frame_blend = match self.frame_blend.take() {
+            _ if weight == 0 => None,
+            Some((_, previous)) => Some((weight, previous)),
+            None => Some((weight, self.renderer.framebuffer.clone())),
+        };
+    }
+
+    pub fn frame_blend(&self) -> f32 {
+        self.frame_blend
+            .as_ref()
+            .map_or(0.0, |(weight, _)| *weight as f32 / 256.0)
+    }
+
+    /// Emulates one frame and renders its audio into [`Self::audio`].
+    ///
+    /// Parts of the console are still unimplemented and panic when reached: the panic is
+    /// caught and the emulation stopped instead of unwinding into the frontend.
+    pub fn run_frame(&mut self) {
+        self.emulate(true);
+    }
+
+    /// [`Self::run_frame`] without drawing the picture, for frame skipping: the framebuffer
+    /// keeps the previous one, which the frame sink receives again, and overlays and the
+    /// composition are not drawn.
+    pub fn skip_frame(&mut self) {
+        self.emulate(false);
+    }
+
+    fn emulate(&mut self, render: bool) {
+        #[cfg(feature = "stats")]
+        self.rsnes.stats.begin_frame();
+        if !self.crashed {
+            let rsnes = &mut self.rsnes;
+            let renderer = render.then_some(&mut self.renderer);
+            let result = panic::catch_unwind(AssertUnwindSafe(|| rsnes.emulate_frame(renderer)));
+            if result.is_err() {
+                tracing::error!(
+                    master_cycles = self.rsnes.master_cycles,
+                    "Emulation stopped: unimplemented feature reached"
+                );
+                self.crashed = true;
+            }
+        }
+
+        if render && !self.crashed {
+            self.blend_frame();
+        }
+
+        // The last picture is repeated after a crash, keeping the recording in time
+        if let Some(sink) = &mut self.frame_sink
+            && let Err(err) = sink.frame(&self.renderer.framebuffer, self.renderer.active_height)
+        {
+            tracing::error!(%err, "Frame sink failed, frames are no longer recorded");
+            self.frame_sink = None;
+        }
+
+        if render {
+            for overlay in &mut self.overlays {
+                overlay.draw(
+                    &mut self.renderer.framebuffer,
+                    self.renderer.active_height,
+                    &self.rsnes.ppu,
+                );
+            }
+            if let Some(composition) = &mut self.composition {
+                composition.compose(&self.renderer.framebuffer, self.renderer.active_height);
+            }
+        }
+
+        let samples = self.next_frame_samples();
+        self.audio.clear();
+        if self.crashed {
+            self.audio.resize(samples * 2, 0);
+        } else {
+            #[cfg(feature = "stats")]
+            let start = self.rsnes.stats.start();
+            for (left, right) in self.rsnes.apu.render_audio(samples) {
+                self.audio.extend([left, right]);
+            }
+            #[cfg(feature = "stats")]
+            self.rsnes.stats.record(Subsystem::Apu, start);
+        }
+        #[cfg(feature = "stats")]
+        self.rsnes.stats.end_frame();
+    }
+
+    fn blend_frame(&mut self) {
+        if let Some((weight, previous)) = &mut self.frame_blend {
+            let height = self.renderer.active_height;
+            self.renderer
+                .framebuffer
+                .blend_previous(previous, height, *weight);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.rsnes.reset();
+        self.crashed = false;
+    }
+
+    /// Snapshot of the console, see [`RSnes::save_state`].
+    pub fn save_state(&mut self) -> SaveState {
+        self.rsnes.save_state()
+    }
+
+    /// Restores a snapshot taken by [`Self::save_state`], which also restarts an emulation
+    /// stopped by a crash.
+    pub fn load_state(&mut self, state: &SaveState) -> Result<(), StateError> {
+        self.rsnes.load_state(state)?;
+        self.crashed = false;
+        Ok(())
+    }
+
+    /// Number of samples in the next frame, keeping the average at [`SAMPLE_RATE`].
+    fn next_frame_samples(&mut self) -> usize {
+        self.pending_samples += SAMPLE_RATE / self.video_standard().frame_rate();
+        let samples = self.pending_samples.floor();
+        self.pending_samples -= samples;
+        samples as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsnes::EmulatorOptions;
+    use bus::joypad::gamepad;
+    use bus::rom::Rom;
+    use bus::rom::test_rom::*;
+    use ppu::rendering::composition::{PictureRect, Placement};
+    use std::sync::{Arc, Mutex};
+
+    fn system() -> System {
+        let rom = Rom::from_bytes(create_valid_lorom(0x20000)).unwrap();
+        let rsnes = RSnes::from_rom(rom, &EmulatorOptions::default());
+        System::new(rsnes, PixelFormat::Rgb565)
+    }
+
+    #[test]
+    fn test_gamepads_on_both_ports() {
+        let mut system = system();
+        system.set_buttons(0, gamepad::A);
+        system.set_buttons(1, gamepad::L);
+
+        let joypads = &mut system.rsnes.bus.io.joypads;
+        assert_eq!(joypads.auto_read(0xFF), [gamepad::A, gamepad::L, 0, 0]);
+    }
+
+    #[test]
+    fn test_samples_per_frame() {
+        let mut system = system();
+        let frames = 600;
+        let total: usize = (0..frames).map(|_| system.next_frame_samples()).sum();
+
+        let expected = SAMPLE_RATE * frames as f64 / VideoStandard::NTSC.frame_rate();
+        assert!((total as f64 - expected).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_crash_outputs_silence() {
+        let mut system = system();
+        system.crashed = true;
+        system.run_frame();
+
+        assert!(system.audio.len() >= 1064);
+        assert!(system.audio.iter().all(|&sample| sample == 0));
+    }
+
+    struct FailingSink;
+
+    impl FrameSink for FailingSink {
+        fn frame(&mut self, _: &FrameBuffer, _: usize) -> std::io::Result<()> {
+            Err(std::io::ErrorKind::StorageFull.into())
+        }
+    }
+
+    #[test]
+    fn test_frame_sink_receives_every_frame() {
+        let mut system = system();
+        system.crashed = true;
+        let heights = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&heights);
+        system.set_frame_sink(move |_: &FrameBuffer, height: usize| {
+            sink.lock().unwrap().push(height);
+            Ok(())
+        });
+
+        system.run_frame();
+        system.run_frame();
+        assert_eq!(*heights.lock().unwrap(), [224, 224]);
+
+        assert!(system.take_frame_sink().is_some());
+        system.run_frame();
+        assert_eq!(heights.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_failing_frame_sink_is_dropped() {
+        let mut system = system();
+        system.crashed = true;
+        system.set_frame_sink(FailingSink);
+
+        system.run_frame();
+        assert!(system.take_frame_sink().is_none());
+    }
+
+    #[test]
+    fn test_overlays_drawn_after_frame_sink() {
+        let mut system = system();
+        system.crashed = true;
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&recorded);
+        system.set_frame_sink(move |fb: &FrameBuffer, _: usize| {
+            sink.lock().unwrap().push(fb[0]);
+            Ok(())
+        });
+        system.add_overlay(|fb: &mut FrameBuffer, _: usize, _: &ppu::ppu::PPU| {
+            fb.set_pixel(0, 0, 0xFF, 0xFF, 0xFF);
+        });
+
+        system.run_frame();
+        assert_eq!(*recorded.lock().unwrap(), [0x00]);
+        assert_eq!(system.renderer.framebuffer[0], 0xFF);
+
+        system.clear_overlays();
+        system.renderer.framebuffer.set_pixel(0, 0, 0, 0, 0);
+        system.run_frame();
+        assert_eq!(system.renderer.framebuffer[0], 0x00);
+    }
+
+    #[test]
+    fn test_overlays_not_drawn_on_skipped_frames() {
+        let mut system = system();
+        system.crashed = true;
+        let overlay_frames = Arc::new(Mutex::new(0));
+        let frames = Arc::clone(&overlay_frames);
+        system.add_overlay(move |_: &mut FrameBuffer, _: usize, _: &ppu::ppu::PPU| {
+            *frames.lock().unwrap() += 1;
+        });
+
+        system.skip_frame();
+        assert_eq!(*overlay_frames.lock().unwrap(), 0);
+        system.run_frame();
+        assert_eq!(*overlay_frames.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_composition_presented() {
+        let mut system = system();
+        system.crashed = true;
+        system.add_overlay(|fb: &mut FrameBuffer, _: usize, _: &ppu::ppu::PPU| {
+            fb.set_pixel(0, 0, 0xFF, 0xFF, 0xFF);
+        });
+        let mut composition = Composition::new(320, 240, PixelFormat::Rgb565);
+        composition.add_layer(
+            Placement::Below,
+            |canvas: &mut FrameBuffer, _: PictureRect| {
+                canvas.set_pixel(0, 0, 0xFF, 0, 0);
+            },
+        );
+        system.set_composition(composition);
+
+        system.run_frame();
+        let (canvas, height) = system.picture();
+        assert_eq!((canvas.width(), height), (320, 240));
+        assert_eq!(canvas[0..2], 0xF800u16.to_le_bytes());
+        let origin = (8 * 320 + 32) * 2;
+        assert_eq!(canvas[origin..origin + 2], 0xFFFFu16.to_le_bytes());
+
+        assert!(system.take_composition().is_some());
+        let (framebuffer, height) = system.picture();
+        assert_eq!((framebuffer.width(), height), (256, 224));
+    }
+
+    #[test]
+    fn test_frame_blend_setting() {
+        let mut system = system();
+        assert_eq!(system.frame_blend(), 0.0);
+        system.set_frame_blend(0.5);
+        assert_eq!(system.frame_blend(), 0.5);
+        system.set_frame_blend(2.0);
+        assert_eq!(system.frame_blend(), 255.0 / 256.0);
+        system.set_frame_blend(0.0);
+        assert!(system.frame_blend.is_none());
+    }
+
+    #[test]
+    fn test_frame_blend_mixes_rendered_frames() {
+        let mut system = system();
+        system.blend_frame();
+        system.set_frame_blend(0.5);
+
+        system
+            .renderer
+            .framebuffer
+            .set_pixel(0, 0, 0xFF, 0xFF, 0xFF);
+        system.blend_frame();
+        assert_eq!(system.renderer.framebuffer[0..2], 0x8410u16.to_le_bytes());
+
+        system
+            .renderer
+            .framebuffer
+            .set_pixel(0, 0, 0xFF, 0xFF, 0xFF);
+        system.blend_frame();
+        assert_eq!(system.renderer.framebuffer[0..2], 0xFFFFu16.to_le_bytes());
+    }
+
+    #[test]
+    fn test_frame_blend_not_applied_after_crash() {
+        let mut system = system();
+        system.crashed = true;
+        system.set_frame_blend(0.5);
+        system
+            .renderer
+            .framebuffer
+            .set_pixel(0, 0, 0xFF, 0xFF, 0xFF);
+        system.set_frame_blend(0.75);
+
+        system.run_frame();
+        assert_eq!(system.renderer.framebuffer[0..2], 0xFFFFu16.to_le_bytes());
+    }
+}
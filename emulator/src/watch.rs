@@ -0,0 +1,736 @@
+//! Watch expressions for debugger views, evaluated against the live console, and the
+//! conditional breakpoints built on them (`break if A==0x42 && [7E0010]>3`).
+//!
+//! - Numbers: decimal `42`, hexadecimal `0x2A` or `$2A`
+//! - Registers (case insensitive): `A`, `X` and `Y` at their current width (8 bits with the
+//!   M or X flag set), `C` for the whole 16-bit accumulator, `S`, `D`, `DB`, `PB`, `PC`
+//!   and `P`
+//! - Memory: `[7E0010]` reads a byte, `[7E0010].w` a word and `[7E0010].l` a long, in
+//!   little endian. A bare hexadecimal number or `BB:AAAA` between the brackets is an
+//!   address, anything else an expression giving one: `[$7E0000 + X]`. So `[D]` reads
+//!   $00:000D, and `[D + 0]` the start of the direct page
+//! - Operators, from lowest to highest precedence: `||`, `&&`, `== != < <= > >=`, `|`,
+//!   `^`, `&`, `<< >>`, `+ -`, `* / %`, then unary `- ! ~` and parentheses
+//!
+//! Values are 64-bit signed integers. Comparisons and logical operators give 0 or 1, and a
+//! condition holds when its value is not 0. Memory is read without side effects: I/O
+//! registers cannot be watched.
+
+use crate::rsnes::RSnes;
+use common::snes_address::SnesAddress;
+use cpu::registers::Registers;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchError {
+    /// Malformed expression, `position` being a byte offset in it
+    Parse {
+        position: usize,
+        message: String,
+    },
+    /// Address that cannot be read without side effects (I/O, coprocessor registers) or
+    /// that nothing is mapped to
+    Unreadable(SnesAddress),
+    DivisionByZero,
+}
+
+impl std::error::Error for WatchError {}
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchError::Parse { position, message } => {
+                write!(
+                    f,
+                    "Invalid expression at column {}: {}",
+                    position + 1,
+                    message
+                )
+            }
+            WatchError::Unreadable(addr) => write!(
+                f,
+                "Cannot read ${:02X}:{:04X} without side effects",
+                addr.bank, addr.addr
+            ),
+            WatchError::DivisionByZero => write!(f, "Division by zero"),
+        }
+    }
+}
+
+/// Console state that expressions are evaluated against.
+pub trait WatchTarget {
+    fn registers(&self) -> Registers;
+
+    /// Byte at `addr` as the CPU would read it, `None` if reading it has side effects.
+    fn peek(&self, addr: SnesAddress) -> Option<u8>;
+}
+
+impl WatchTarget for RSnes {
+    fn registers(&self) -> Registers {
+        *self.cpu.regs()
+    }
+
+    fn peek(&self, addr: SnesAddress) -> Option<u8> {
+        self.bus.peek(addr)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    A,
+    /// 16-bit accumulator, whatever the M flag
+    C,
+    X,
+    Y,
+    S,
+    D,
+    DB,
+    PB,
+    PC,
+    P,
+}
+
+impl Register {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_uppercase().as_str() {
+            "A" => Register::A,
+            "C" => Register::C,
+            "X" => Register::X,
+            "Y" => Register::Y,
+            "S" => Register::S,
+            "D" => Register::D,
+            "DB" => Register::DB,
+            "PB" => Register::PB,
+            "PC" => Register::PC,
+            "P" => Register::P,
+            _ => return None,
+        })
+    }
+
+    fn value(self, regs: &Registers) -> i64 {
+        let accumulator_8bit = regs.E || regs.P.M;
+        let index_8bit = regs.E || regs.P.X;
+        let value = match self {
+            Register::A if accumulator_8bit => regs.A & 0xFF,
+            Register::A | Register::C => regs.A,
+            Register::X if index_8bit => regs.X & 0xFF,
+            Register::X => regs.X,
+            Register::Y if index_8bit => regs.Y & 0xFF,
+            Register::Y => regs.Y,
+            Register::S => regs.S,
+            Register::D => regs.D,
+            Register::DB => regs.DB as u16,
+            Register::PB => regs.PB as u16,
+            Register::PC => regs.PC,
+            Register::P => Into::<u8>::into(regs.P) as u16,
+        };
+        value as i64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// Binary operators by precedence level, lowest first
+const BINARY_LEVELS: &[&[(&str, BinaryOp)]] = &[
+    &[("||", BinaryOp::Or)],
+    &[("&&", BinaryOp::And)],
+    &[
+        ("==", BinaryOp::Eq),
+        ("!=", BinaryOp::Ne),
+        ("<", BinaryOp::Lt),
+        ("<=", BinaryOp::Le),
+        (">", BinaryOp::Gt),
+        (">=", BinaryOp::Ge),
+    ],
+    &[("|", BinaryOp::BitOr)],
+    &[("^", BinaryOp::BitXor)],
+    &[("&", BinaryOp::BitAnd)],
+    &[("<<", BinaryOp::Shl), (">>", BinaryOp::Shr)],
+    &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+    &[
+        ("*", BinaryOp::Mul),
+        ("/", BinaryOp::Div),
+        ("%", BinaryOp::Rem),
+    ],
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Number(i64),
+    Register(Register),
+    /// `bytes` bytes read from the address given by the inner node
+    Memory(Box<Node>, u8),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn eval(&self, target: &impl WatchTarget) -> Result<i64, WatchError> {
+        Ok(match self {
+            Node::Number(value) => *value,
+            Node::Register(register) => register.value(&target.registers()),
+            Node::Memory(addr, bytes) => {
+                let addr = addr.eval(target)? as usize;
+                let mut value = 0;
+                for i in 0..*bytes as usize {
+                    let addr = SnesAddress::from(addr.wrapping_add(i) & 0xFF_FFFF);
+                    let byte = target.peek(addr).ok_or(WatchError::Unreadable(addr))?;
+                    value |= (byte as i64) << (8 * i);
+                }
+                value
+            }
+            Node::Unary(op, operand) => {
+                let value = operand.eval(target)?;
+                match op {
+                    UnaryOp::Neg => value.wrapping_neg(),
+                    UnaryOp::Not => (value == 0) as i64,
+                    UnaryOp::BitNot => !value,
+                }
+            }
+            // Short-circuited, so that `[addr]` can be guarded
+            Node::Binary(BinaryOp::Or, lhs, rhs) => {
+                (lhs.eval(target)? != 0 || rhs.eval(target)? != 0) as i64
+            }
+            Node::Binary(BinaryOp::And, lhs, rhs) => {
+                (lhs.eval(target)? != 0 && rhs.eval(target)? != 0) as i64
+            }
+            Node::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(target)?, rhs.eval(target)?);
+                match op {
+                    BinaryOp::Eq => (lhs == rhs) as i64,
+                    BinaryOp::Ne => (lhs != rhs) as i64,
+                    BinaryOp::Lt => (lhs < rhs) as i64,
+                    BinaryOp::Le => (lhs <= rhs) as i64,
+                    BinaryOp::Gt => (lhs > rhs) as i64,
+                    BinaryOp::Ge => (lhs >= rhs) as i64,
+                    BinaryOp::BitOr => lhs | rhs,
+                    BinaryOp::BitXor => lhs ^ rhs,
+                    BinaryOp::BitAnd => lhs & rhs,
+                    BinaryOp::Shl => lhs.wrapping_shl(rhs as u32),
+                    BinaryOp::Shr => lhs.wrapping_shr(rhs as u32),
+                    BinaryOp::Add => lhs.wrapping_add(rhs),
+                    BinaryOp::Sub => lhs.wrapping_sub(rhs),
+                    BinaryOp::Mul => lhs.wrapping_mul(rhs),
+                    BinaryOp::Div | BinaryOp::Rem if rhs == 0 => {
+                        return Err(WatchError::DivisionByZero);
+                    }
+                    BinaryOp::Div => lhs.wrapping_div(rhs),
+                    BinaryOp::Rem => lhs.wrapping_rem(rhs),
+                    BinaryOp::Or | BinaryOp::And => unreachable!(),
+                }
+            }
+        })
+    }
+}
+
+/// Recursive descent parser over the expression text.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, WatchError> {
+        Err(WatchError::Parse {
+            position: self.pos,
+            message: message.into(),
+        })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes `token` if the text continues with it, after whitespace.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), WatchError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            self.error(format!("expected `{token}`"))
+        }
+    }
+
+    /// Leading run of characters matching `accept`
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !accept(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn expression(&mut self, level: usize) -> Result<Node, WatchError> {
+        let Some(operators) = BINARY_LEVELS.get(level) else {
+            return self.unary();
+        };
+        let mut lhs = self.expression(level + 1)?;
+        while let Some(op) = self.binary_op(operators) {
+            let rhs = self.expression(level + 1)?;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    /// Consumes the next operator if it is one of `operators`, the longest one matching
+    /// (so `<` is not taken from `<<`).
+    fn binary_op(&mut self, operators: &[(&str, BinaryOp)]) -> Option<BinaryOp> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let longest = BINARY_LEVELS
+            .iter()
+            .flat_map(|level| level.iter())
+            .filter(|(token, _)| rest.starts_with(token))
+            .max_by_key(|(token, _)| token.len())?;
+        operators.contains(longest).then(|| {
+            self.pos += longest.0.len();
+            longest.1
+        })
+    }
+
+    fn unary(&mut self) -> Result<Node, WatchError> {
+        for (token, op) in [
+            ("-", UnaryOp::Neg),
+            ("!", UnaryOp::Not),
+            ("~", UnaryOp::BitNot),
+        ] {
+            if self.eat(token) {
+                return Ok(Node::Unary(op, Box::new(self.unary()?)));
+            }
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Node, WatchError> {
+        if self.eat("(") {
+            let node = self.expression(0)?;
+            self.expect(")")?;
+            return Ok(node);
+        }
+        if self.eat("[") {
+            let addr = match self.bare_address()? {
+                Some(addr) => Node::Number(addr as i64),
+                None => self.expression(0)?,
+            };
+            self.expect("]")?;
+            let bytes = if self.rest().starts_with(".w") {
+                2
+            } else if self.rest().starts_with(".l") {
+                3
+            } else {
+                1
+            };
+            if bytes > 1 {
+                self.pos += 2;
+            }
+            return Ok(Node::Memory(Box::new(addr), bytes));
+        }
+
+        self.skip_whitespace();
+        let start = self.pos;
+        if self.eat("$") {
+            return self.hex_number(start);
+        }
+        if self.eat("0x") || self.eat("0X") {
+            return self.hex_number(start);
+        }
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        if !digits.is_empty() {
+            return match digits.parse() {
+                Ok(value) => Ok(Node::Number(value)),
+                Err(_) => {
+                    self.pos = start;
+                    self.error("number too large")
+                }
+            };
+        }
+        let name = self.take_while(|c| c.is_ascii_alphanumeric());
+        if name.is_empty() {
+            return self.error("expected a number, register, `[` or `(`");
+        }
+        match Register::from_name(name) {
+            Some(register) => Ok(Node::Register(register)),
+            None => {
+                self.pos = start;
+                self.error(format!("unknown register `{name}`"))
+            }
+        }
+    }
+
+    fn hex_number(&mut self, start: usize) -> Result<Node, WatchError> {
+        let digits = self.take_while(|c| c.is_ascii_hexdigit());
+        match i64::from_str_radix(digits, 16) {
+            Ok(value) => Ok(Node::Number(value)),
+            Err(_) => {
+                self.pos = start;
+                self.error("invalid hexadecimal number")
+            }
+        }
+    }
+
+    /// `BB:AAAA` or up to 6 hexadecimal digits (optionally after `$`), consumed only when
+    /// the text continues with `]`. Addresses that cannot start an expression either
+    /// (`7E0010`, `7E:0010`, but not `10` or `DB`) must be followed by `]`.
+    fn bare_address(&mut self) -> Result<Option<usize>, WatchError> {
+        let start = self.pos;
+        self.skip_whitespace();
+        let prefixed = self.eat("$");
+        let text = self.take_while(|c| c.is_ascii_hexdigit() || c == ':');
+        let addr = parse_address(text);
+        self.skip_whitespace();
+        if addr.is_some() && self.rest().starts_with(']') {
+            return Ok(addr);
+        }
+        let expression_start = prefixed
+            || text.bytes().all(|c| c.is_ascii_digit())
+            || Register::from_name(text).is_some();
+        if addr.is_some() && !expression_start {
+            return self.error("expected `]` after the address");
+        }
+        self.pos = start;
+        Ok(None)
+    }
+}
+
+/// `BB:AAAA` or up to 6 hexadecimal digits
+fn parse_address(text: &str) -> Option<usize> {
+    let hex = |digits: &str, max: usize| {
+        (!digits.is_empty() && digits.len() <= max)
+            .then(|| usize::from_str_radix(digits, 16).ok())
+            .flatten()
+    };
+    match text.split_once(':') {
+        Some((bank, addr)) => Some((hex(bank, 2)? << 16) | hex(addr, 4)?),
+        None => hex(text, 6),
+    }
+}
+
+/// A parsed watch expression, see the [module documentation](self) for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchExpr {
+    source: String,
+    root: Node,
+}
+
+impl WatchExpr {
+    pub fn parse(text: &str) -> Result<Self, WatchError> {
+        let mut parser = Parser { text, pos: 0 };
+        let root = parser.expression(0)?;
+        parser.skip_whitespace();
+        if !parser.rest().is_empty() {
+            return parser.error("unexpected text after the expression");
+        }
+        Ok(Self {
+            source: text.trim().to_string(),
+            root,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn eval(&self, target: &impl WatchTarget) -> Result<i64, WatchError> {
+        self.root.eval(target)
+    }
+
+    /// Whether the expression holds, i.e. is not 0.
+    pub fn holds(&self, target: &impl WatchTarget) -> Result<bool, WatchError> {
+        Ok(self.eval(target)? != 0)
+    }
+}
+
+impl fmt::Display for WatchExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Breakpoint on an instruction address, a condition, or both, written
+/// `break [at BB:AAAA] [if <expression>]`. Bounded runs stop at the breakpoints in
+/// [`RSnes::breakpoints`], see [`RSnes::run_until`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// Address of the instruction to stop before, any instruction if none
+    pub addr: Option<SnesAddress>,
+    pub condition: Option<WatchExpr>,
+}
+
+impl Breakpoint {
+    pub fn parse(command: &str) -> Result<Self, WatchError> {
+        let mut parser = Parser {
+            text: command,
+            pos: 0,
+        };
+        if !parser.eat("break") {
+            return parser.error("expected `break`");
+        }
+
+        let mut addr = None;
+        if parser.eat("at ") {
+            parser.skip_whitespace();
+            parser.eat("$");
+            let text = parser.take_while(|c| c.is_ascii_hexdigit() || c == ':');
+            match parse_address(text) {
+                Some(value) => addr = Some(SnesAddress::from(value)),
+                None => return parser.error("expected an address"),
+            }
+        }
+
+        let condition = if parser.eat("if ") {
+            let offset = parser.pos;
+            let condition = WatchExpr::parse(parser.rest()).map_err(|err| match err {
+                WatchError::Parse { position, message } => WatchError::Parse {
+                    position: offset + position,
+                    message,
+                },
+                err => err,
+            })?;
+            Some(condition)
+        } else {
+            parser.skip_whitespace();
+            if !parser.rest().is_empty() {
+                return parser.error("expected `at` or `if`");
+            }
+            None
+        };
+
+        if addr.is_none() && condition.is_none() {
+            return parser.error("expected `at` or `if`");
+        }
+        Ok(Self { addr, condition })
+    }
+
+    /// Whether the CPU, about to run the instruction at PB:PC, should stop.
+    pub fn hit(&self, target: &impl WatchTarget) -> Result<bool, WatchError> {
+        if let Some(addr) = self.addr {
+            let regs = target.registers();
+            if (regs.PB, regs.PC) != (addr.bank, addr.addr) {
+                return Ok(false);
+            }
+        }
+        match &self.condition {
+            Some(condition) => condition.holds(target),
+            None => Ok(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::snes_address::snes_addr;
+    use std::collections::HashMap;
+
+    struct Target {
+        regs: Registers,
+        memory: HashMap<usize, u8>,
+    }
+
+    impl WatchTarget for Target {
+        fn registers(&self) -> Registers {
+            self.regs
+        }
+
+        fn peek(&self, addr: SnesAddress) -> Option<u8> {
+            self.memory.get(&usize::from(addr)).copied()
+        }
+    }
+
+    fn target() -> Target {
+        let mut regs = Registers::default();
+        regs.E = false;
+        regs.P.M = false;
+        regs.P.X = false;
+        regs.A = 0x1242;
+        regs.X = 0x0003;
+        regs.PB = 0x00;
+        regs.PC = 0x8000;
+        let memory = HashMap::from([(0x7E0010, 0x05), (0x7E0011, 0x80), (0x7E0012, 0x01)]);
+        Target { regs, memory }
+    }
+
+    fn eval(text: &str) -> Result<i64, WatchError> {
+        WatchExpr::parse(text)?.eval(&target())
+    }
+
+    // ============================================================
+    // Parsing
+    // ============================================================
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(eval("42"), Ok(42));
+        assert_eq!(eval("0x2A"), Ok(42));
+        assert_eq!(eval("$2a"), Ok(42));
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(7));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(9));
+        assert_eq!(eval("1 << 2 + 1"), Ok(8));
+        assert_eq!(eval("1 | 2 == 3"), Ok(1));
+        assert_eq!(eval("-2 * -3"), Ok(6));
+        assert_eq!(eval("!0 && ~0 == -1"), Ok(1));
+    }
+
+    #[test]
+    fn test_longest_operator_matched() {
+        assert_eq!(eval("1<<3"), Ok(8));
+        assert_eq!(eval("3<=3"), Ok(1));
+        assert_eq!(eval("2&&0"), Ok(0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error_at = |text| match WatchExpr::parse(text) {
+            Err(WatchError::Parse { position, .. }) => position,
+            other => panic!("{text}: {other:?}"),
+        };
+        assert_eq!(error_at("1 +"), 3);
+        assert_eq!(error_at("(1"), 2);
+        assert_eq!(error_at("1 2"), 2);
+        assert_eq!(error_at("Q == 1"), 0);
+        assert_eq!(error_at("[7E0010"), 7);
+    }
+
+    // ============================================================
+    // Evaluation
+    // ============================================================
+
+    #[test]
+    fn test_registers_at_current_width() {
+        assert_eq!(eval("A"), Ok(0x1242));
+        assert_eq!(eval("x + pc"), Ok(0x8003));
+
+        let mut target = target();
+        target.regs.P.M = true;
+        let a = WatchExpr::parse("A").unwrap();
+        let c = WatchExpr::parse("C").unwrap();
+        assert_eq!(a.eval(&target), Ok(0x42));
+        assert_eq!(c.eval(&target), Ok(0x1242));
+    }
+
+    #[test]
+    fn test_memory_widths() {
+        assert_eq!(eval("[7E0010]"), Ok(0x05));
+        assert_eq!(eval("[7E:0010].w"), Ok(0x8005));
+        assert_eq!(eval("[$7E0010].l"), Ok(0x018005));
+        assert_eq!(eval("[0x7E000D + X]"), Ok(0x05));
+        assert_eq!(eval("[X + 0x7E000D]"), Ok(0x05));
+    }
+
+    #[test]
+    fn test_memory_wraps_around_the_address_space() {
+        let mut target = target();
+        target.memory.insert(0xFFFFFF, 0x34);
+        target.memory.insert(0x000000, 0x12);
+        let word = WatchExpr::parse("[FFFFFF].w").unwrap();
+        assert_eq!(word.eval(&target), Ok(0x1234));
+        // Negative addresses are masked to 24 bits instead of overflowing
+        let negative = WatchExpr::parse("[-1].w").unwrap();
+        assert_eq!(negative.eval(&target), Ok(0x1234));
+    }
+
+    #[test]
+    fn test_unreadable_memory() {
+        assert_eq!(
+            eval("[2140]"),
+            Err(WatchError::Unreadable(snes_addr!(0:0x2140)))
+        );
+        // The right-hand side is not evaluated
+        assert_eq!(eval("0 && [2140]"), Ok(0));
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        assert_eq!(eval("1 / (X - 3)"), Err(WatchError::DivisionByZero));
+        assert_eq!(eval("7 % 4"), Ok(3));
+    }
+
+    // ============================================================
+    // Breakpoints
+    // ============================================================
+
+    #[test]
+    fn test_conditional_breakpoint() {
+        let breakpoint = Breakpoint::parse("break if A==0x1242 && [7E0010]>3").unwrap();
+        assert_eq!(breakpoint.addr, None);
+        assert_eq!(breakpoint.hit(&target()), Ok(true));
+
+        let mut target = target();
+        target.memory.insert(0x7E0010, 3);
+        assert_eq!(breakpoint.hit(&target), Ok(false));
+    }
+
+    #[test]
+    fn test_breakpoint_at_address() {
+        let target = target();
+        assert_eq!(
+            Breakpoint::parse("break at 00:8000").unwrap().hit(&target),
+            Ok(true)
+        );
+        assert_eq!(
+            Breakpoint::parse("break at 008001").unwrap().hit(&target),
+            Ok(false)
+        );
+        assert_eq!(
+            Breakpoint::parse("break at 00:8000 if X == 2")
+                .unwrap()
+                .hit(&target),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn test_breakpoint_parse_errors() {
+        assert!(Breakpoint::parse("break").is_err());
+        assert!(Breakpoint::parse("break at nowhere").is_err());
+        assert_eq!(
+            Breakpoint::parse("break if A ==").unwrap_err(),
+            WatchError::Parse {
+                position: 13,
+                message: "expected a number, register, `[` or `(`".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,14 @@
+//! Mapping detection and header parsing on arbitrary ROM data, for both mappings.
+#![no_main]
+
+use bus::rom::header::RomHeader;
+use bus::rom::header::mapping_mode::MappingMode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = MappingMode::detect_rom_mapping(data);
+    for mapping in [MappingMode::LoRom, MappingMode::HiRom] {
+        let _ = MappingMode::score_header(data, mapping.get_corresponding_header_offset());
+        let _ = RomHeader::load_header(data, mapping);
+    }
+});
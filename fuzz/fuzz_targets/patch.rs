@@ -0,0 +1,25 @@
+//! IPS and BPS soft-patching of an arbitrary ROM.
+//!
+//! BPS patches are checked against the CRC-32 of the ROM and of the patch before any action
+//! runs, which random input never passes: the footer of BPS input is rewritten with the
+//! right checksums so that the actions themselves get fuzzed.
+#![no_main]
+
+use bus::rom::crc32::crc32;
+use bus::rom::patch::apply_patch;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (&[u8], &[u8])| {
+    let (rom, patch) = input;
+    let _ = apply_patch(rom, patch);
+
+    if patch.starts_with(b"BPS1") && patch.len() >= 16 {
+        let mut patch = patch.to_vec();
+        let source_crc = patch.len() - 12;
+        patch[source_crc..source_crc + 4].copy_from_slice(&crc32(rom).to_le_bytes());
+        let patch_crc = patch.len() - 4;
+        let crc = crc32(&patch[..patch_crc]);
+        patch[patch_crc..].copy_from_slice(&crc.to_le_bytes());
+        let _ = apply_patch(rom, &patch);
+    }
+});
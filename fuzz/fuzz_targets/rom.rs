@@ -0,0 +1,12 @@
+//! Whole ROM loading from an in-memory file: copier header, mapping detection, header.
+#![no_main]
+
+use bus::rom::Rom;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(rom) = Rom::from_bytes(data.to_vec()) {
+        let _ = rom.checksum();
+        let _ = rom.header.to_string();
+    }
+});
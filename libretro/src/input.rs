@@ -0,0 +1,45 @@
+//! Mapping of the libretro joypad to the SNES gamepad.
+
+use crate::api::*;
+use bus::joypad::gamepad;
+use std::ffi::c_uint;
+
+/// libretro joypad buttons and the SNES buttons they are mapped to
+pub const JOYPAD_MAP: [(c_uint, u16); 12] = [
+    (RETRO_DEVICE_ID_JOYPAD_B, gamepad::B),
+    (RETRO_DEVICE_ID_JOYPAD_Y, gamepad::Y),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, gamepad::SELECT),
+    (RETRO_DEVICE_ID_JOYPAD_START, gamepad::START),
+    (RETRO_DEVICE_ID_JOYPAD_UP, gamepad::UP),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, gamepad::DOWN),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, gamepad::LEFT),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, gamepad::RIGHT),
+    (RETRO_DEVICE_ID_JOYPAD_A, gamepad::A),
+    (RETRO_DEVICE_ID_JOYPAD_X, gamepad::X),
+    (RETRO_DEVICE_ID_JOYPAD_L, gamepad::L),
+    (RETRO_DEVICE_ID_JOYPAD_R, gamepad::R),
+];
+
+/// SNES buttons held, given whether each libretro joypad button is pressed.
+pub fn joypad_buttons(mut pressed: impl FnMut(c_uint) -> bool) -> u16 {
+    JOYPAD_MAP
+        .iter()
+        .filter(|&&(id, _)| pressed(id))
+        .fold(0, |buttons, &(_, button)| buttons | button)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_joypad_buttons() {
+        let held = [RETRO_DEVICE_ID_JOYPAD_B, RETRO_DEVICE_ID_JOYPAD_START];
+        assert_eq!(
+            joypad_buttons(|id| held.contains(&id)),
+            gamepad::B | gamepad::START
+        );
+        assert_eq!(joypad_buttons(|_| false), 0);
+        assert_eq!(joypad_buttons(|_| true), 0xFFF0);
+    }
+}
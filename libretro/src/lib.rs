@@ -0,0 +1,424 @@
+//! libretro core, so the emulator can run in RetroArch and other libretro frontends.
+//!
+//! Build the `cdylib` and load it as a core. Only gamepads are supported. Save states have
+//! the size reported by `retro_serialize_size` for the whole session, as frontends expect.
+//!
+//! # Reference
+//! [libretro API](https://docs.libretro.com/development/cores/developing-cores/)
+
+pub mod api;
+pub mod input;
+
+use api::*;
+use common::video_standard::VideoStandard;
+use emulator::save_state::SaveState;
+use emulator::system::{SAMPLE_RATE, System};
+use emulator::{EmulatorOptions, RSnes};
+use input::joypad_buttons;
+use ppu::constants::{SCREEN_HEIGHT, SCREEN_HEIGHT_OVERSCAN, SCREEN_WIDTH};
+use ppu::rendering::framebuffer::PixelFormat;
+use std::ffi::{CStr, c_char, c_uint, c_void};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::{ptr, slice};
+use tracing::{error, warn};
+
+const LIBRARY_NAME: &CStr = c"r-snes";
+const LIBRARY_VERSION: &CStr =
+    match CStr::from_bytes_with_nul(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()) {
+        Ok(version) => version,
+        Err(_) => panic!("invalid package version"),
+    };
+const VALID_EXTENSIONS: &CStr = c"sfc|smc|swc|fig";
+
+/// Callbacks registered by the frontend
+#[derive(Default)]
+struct Callbacks {
+    environment: Option<retro_environment_t>,
+    video_refresh: Option<retro_video_refresh_t>,
+    audio_sample_batch: Option<retro_audio_sample_batch_t>,
+    input_poll: Option<retro_input_poll_t>,
+    input_state: Option<retro_input_state_t>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+
+/// Loaded game, if any. Some frontends load the game and run it from different threads.
+static SYSTEM: Mutex<Option<System>> = Mutex::new(None);
+
+/// Size of the save states of the loaded game, 0 without a game
+static SERIALIZE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+/// Room left in save states for coprocessors, whose state grows by a few bytes while they
+/// run a command
+const SERIALIZE_MARGIN: usize = 1024;
+
+fn lock_callbacks() -> MutexGuard<'static, Callbacks> {
+    CALLBACKS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn lock_system() -> MutexGuard<'static, Option<System>> {
+    SYSTEM.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Runs `f` on the loaded game, if any.
+fn with_system<R>(f: impl FnOnce(&mut System) -> R) -> Option<R> {
+    lock_system().as_mut().map(f)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_info`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut retro_system_info) {
+    let info = unsafe { &mut *info };
+    info.library_name = LIBRARY_NAME.as_ptr();
+    info.library_version = LIBRARY_VERSION.as_ptr();
+    info.valid_extensions = VALID_EXTENSIONS.as_ptr();
+    // The ROM is loaded from its path, the coprocessor data being saved next to it
+    info.need_fullpath = true;
+    info.block_extract = false;
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_av_info`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut retro_system_av_info) {
+    let video_standard = with_system(|system| system.video_standard()).unwrap_or_default();
+
+    let info = unsafe { &mut *info };
+    info.geometry = retro_game_geometry {
+        base_width: SCREEN_WIDTH as c_uint,
+        base_height: SCREEN_HEIGHT as c_uint,
+        max_width: SCREEN_WIDTH as c_uint,
+        max_height: SCREEN_HEIGHT_OVERSCAN as c_uint,
+        aspect_ratio: 4.0 / 3.0,
+    };
+    info.timing = retro_system_timing {
+        fps: video_standard.frame_rate(),
+        sample_rate: SAMPLE_RATE,
+    };
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_init() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_deinit() {
+    retro_unload_game();
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_environment(callback: retro_environment_t) {
+    lock_callbacks().environment = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_video_refresh(callback: retro_video_refresh_t) {
+    lock_callbacks().video_refresh = Some(callback);
+}
+
+/// Unused: audio is sent once per frame through the batch callback.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample(_callback: retro_audio_sample_t) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample_batch(callback: retro_audio_sample_batch_t) {
+    lock_callbacks().audio_sample_batch = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_poll(callback: retro_input_poll_t) {
+    lock_callbacks().input_poll = Some(callback);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_state(callback: retro_input_state_t) {
+    lock_callbacks().input_state = Some(callback);
+}
+
+/// Gamepads are plugged into both ports and cannot be replaced yet.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_reset() {
+    with_system(System::reset);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_run() {
+    let callbacks = lock_callbacks();
+    with_system(|system| run_frame(system, &callbacks));
+}
+
+fn run_frame(system: &mut System, callbacks: &Callbacks) {
+    if let Some(input_poll) = callbacks.input_poll {
+        input_poll();
+    }
+    if let Some(input_state) = callbacks.input_state {
+        for port in 0..2 {
+            let buttons = joypad_buttons(|id| input_state(port, RETRO_DEVICE_JOYPAD, 0, id) != 0);
+            system.set_buttons(port as usize, buttons);
+        }
+    }
+
+    system.run_frame();
+
+    if let Some(video_refresh) = callbacks.video_refresh {
+        let framebuffer = &system.renderer.framebuffer;
+        video_refresh(
+            framebuffer.as_ptr().cast(),
+            framebuffer.width() as c_uint,
+            system.renderer.active_height as c_uint,
+            framebuffer.pitch(),
+        );
+    }
+    if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+        audio_sample_batch(system.audio.as_ptr(), system.audio.len() / 2);
+    }
+}
+
+/// Fixed for the loaded game, the end of smaller states being zero-filled.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_serialize_size() -> usize {
+    SERIALIZE_SIZE.load(Ordering::Relaxed)
+}
+
+/// # Safety
+/// `data` must be null or point to `size` writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+    let data = unsafe { slice::from_raw_parts_mut(data.cast::<u8>(), size) };
+    with_system(|system| {
+        let state = system.save_state().to_bytes();
+        if state.len() > size {
+            error!(size = state.len(), "Save state larger than the frontend buffer");
+            return false;
+        }
+        data[..state.len()].copy_from_slice(&state);
+        data[state.len()..].fill(0);
+        true
+    })
+    .unwrap_or(false)
+}
+
+/// # Safety
+/// `data` must be null or point to `size` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+    let data = unsafe { slice::from_raw_parts(data.cast::<u8>(), size) };
+    with_system(|system| {
+        let loaded = SaveState::from_bytes(data).and_then(|state| system.load_state(&state));
+        if let Err(err) = &loaded {
+            error!(%err, "Error loading save state");
+        }
+        loaded.is_ok()
+    })
+    .unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_reset() {
+    with_system(|system| system.rsnes.bus.cheats.clear());
+}
+
+/// Game Genie and Pro Action Replay codes, several codes being separated by `+`.
+///
+/// # Safety
+/// `code` must be a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_cheat_set(_index: c_uint, enabled: bool, code: *const c_char) {
+    if !enabled || code.is_null() {
+        return;
+    }
+    let code = unsafe { CStr::from_ptr(code) }.to_string_lossy();
+    with_system(|system| {
+        for code in code.split('+') {
+            if let Err(err) = system.rsnes.bus.cheats.add(code.trim()) {
+                warn!(code, %err, "Invalid cheat");
+            }
+        }
+    });
+}
+
+/// # Safety
+/// `game` must be null or point to a `retro_game_info` whose path is a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_load_game(game: *const retro_game_info) -> bool {
+    let Some(game) = (unsafe { game.as_ref() }) else {
+        return false;
+    };
+    if game.path.is_null() {
+        return false;
+    }
+    let path = unsafe { CStr::from_ptr(game.path) }
+        .to_string_lossy()
+        .into_owned();
+
+    let mut pixel_format = RETRO_PIXEL_FORMAT_RGB565;
+    let environment = lock_callbacks().environment;
+    if let Some(environment) = environment
+        && !environment(
+            RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+            (&raw mut pixel_format).cast(),
+        )
+    {
+        error!("The frontend does not support RGB565");
+        return false;
+    }
+
+    match RSnes::load_rom_with_options(&path, &EmulatorOptions::default()) {
+        Ok(rsnes) => {
+            start(System::new(rsnes, PixelFormat::Rgb565));
+            true
+        }
+        Err(err) => {
+            error!(%path, %err, "Error loading ROM");
+            false
+        }
+    }
+}
+
+/// Makes `system` the loaded game.
+fn start(mut system: System) {
+    let state_size = system.save_state().to_bytes().len();
+    SERIALIZE_SIZE.store(state_size + SERIALIZE_MARGIN, Ordering::Relaxed);
+    *lock_system() = Some(system);
+}
+
+/// Special game types (Super Game Boy, Sufami Turbo...) are not supported.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const retro_game_info,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_unload_game() {
+    let system = lock_system().take();
+    SERIALIZE_SIZE.store(0, Ordering::Relaxed);
+    if let Some(system) = system
+        && let Err(err) = system.rsnes.save()
+    {
+        error!(%err, "Error saving");
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_region() -> c_uint {
+    match with_system(|system| system.video_standard()) {
+        Some(VideoStandard::PAL) => RETRO_REGION_PAL,
+        _ => RETRO_REGION_NTSC,
+    }
+}
+
+/// Only the work RAM is exposed, for achievements and cheat searches.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    if id != RETRO_MEMORY_SYSTEM_RAM {
+        return ptr::null_mut();
+    }
+    // The work RAM is boxed: it stays in place as long as the game is loaded
+    with_system(|system| system.rsnes.bus.wram.data.as_mut_ptr().cast()).unwrap_or(ptr::null_mut())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    if id != RETRO_MEMORY_SYSTEM_RAM {
+        return 0;
+    }
+    with_system(|system| system.rsnes.bus.wram.data.len()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bus::rom::Rom;
+    use bus::rom::test_rom::create_valid_lorom;
+    use std::mem::MaybeUninit;
+
+    #[test]
+    fn test_system_info() {
+        let mut info = MaybeUninit::<retro_system_info>::uninit();
+        let info = unsafe {
+            retro_get_system_info(info.as_mut_ptr());
+            info.assume_init()
+        };
+        assert_eq!(unsafe { CStr::from_ptr(info.library_name) }, c"r-snes");
+        assert_eq!(unsafe { CStr::from_ptr(info.library_version) }, c"0.1.0");
+        assert!(info.need_fullpath);
+    }
+
+    #[test]
+    fn test_av_info_without_game() {
+        let mut info = MaybeUninit::<retro_system_av_info>::uninit();
+        let info = unsafe {
+            retro_get_system_av_info(info.as_mut_ptr());
+            info.assume_init()
+        };
+        assert_eq!(info.geometry.base_width, 256);
+        assert_eq!(info.geometry.max_height, 239);
+        assert_eq!(info.timing.fps, VideoStandard::NTSC.frame_rate());
+        assert_eq!(info.timing.sample_rate, 32_000.0);
+    }
+
+    /// The only test loading a game, as it is global
+    #[test]
+    fn test_save_states() {
+        let mut state = vec![0xFFu8; 64];
+        assert_eq!(retro_serialize_size(), 0);
+        assert!(!unsafe { retro_serialize(state.as_mut_ptr().cast(), state.len()) });
+
+        let rom = Rom::from_bytes(create_valid_lorom(0x20000)).unwrap();
+        let rsnes = RSnes::from_rom(rom, &EmulatorOptions::default());
+        start(System::new(rsnes, PixelFormat::Rgb565));
+        let size = retro_serialize_size();
+        assert!(size > SERIALIZE_MARGIN);
+        state.resize(size, 0xFF);
+        assert!(unsafe { retro_serialize(state.as_mut_ptr().cast(), size) });
+        assert!(state[size - SERIALIZE_MARGIN..].iter().all(|&byte| byte == 0));
+        assert!(!unsafe { retro_serialize(state.as_mut_ptr().cast(), 16) });
+
+        let wram = with_system(|system| system.rsnes.bus.wram.data.clone()).unwrap();
+        with_system(|system| system.rsnes.bus.wram.data.fill(0x55));
+        assert!(unsafe { retro_unserialize(state.as_ptr().cast(), size) });
+        assert_eq!(with_system(|system| system.rsnes.bus.wram.data.clone()), Some(wram));
+        assert!(!unsafe { retro_unserialize(state[16..].as_ptr().cast(), size - 16) });
+
+        retro_unload_game();
+        assert_eq!(retro_serialize_size(), 0);
+        assert!(!unsafe { retro_unserialize(state.as_ptr().cast(), size) });
+    }
+
+    #[test]
+    fn test_load_game_without_path() {
+        let game = retro_game_info {
+            path: ptr::null(),
+            data: ptr::null(),
+            size: 0,
+            meta: ptr::null(),
+        };
+        assert!(!unsafe { retro_load_game(&game) });
+        assert!(!unsafe { retro_load_game(ptr::null()) });
+    }
+}
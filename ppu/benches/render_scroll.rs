@@ -0,0 +1,129 @@
+//! Background rendering benchmarks
+//!
+//! Renders whole frames of BG1 scrolling diagonally over a tilemap of distinct
+//! tiles, the common case of a game scrolling its playfield: 4bpp tiles in
+//! mode 1 and 8bpp ones in mode 3.
+//!
+//! The raster benchmarks change BG1HOFS a few times per scanline, and compare the catch-up
+//! renderer, drawing a span before each change, with the naive approach of drawing every
+//! dot as the beam reaches it.
+//!
+//! ```text
+//! cargo bench -p ppu
+//! ```
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use ppu::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use ppu::ppu::PPU;
+use ppu::rendering::renderer::Renderer;
+
+/// BG1 tilemap at word 0x0400, 4bpp tiles from word 0x1000
+const TILEMAP: usize = 0x0400;
+const TILEDATA: usize = 0x1000;
+
+fn make_ppu(mode: u8) -> PPU {
+    let mut ppu = PPU::new();
+    ppu.write(0x2100, 0x0F); // full brightness
+    ppu.write(0x2105, mode);
+    ppu.write(0x2107, (TILEMAP >> 8) as u8); // BG1SC
+    ppu.write(0x210B, (TILEDATA >> 12) as u8); // BG12NBA
+    ppu.write(0x212C, 0x01); // BG1 on the main screen
+
+    // 256 distinct tiles with pseudo-random bitplanes (128 in 8bpp)
+    let mut seed: u32 = 0x1234_5678;
+    for word in &mut ppu.vram.memory[TILEDATA..TILEDATA + 256 * 16] {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        *word = (seed >> 16) as u16;
+    }
+    for (i, entry) in ppu.vram.memory[TILEMAP..TILEMAP + 32 * 32]
+        .iter_mut()
+        .enumerate()
+    {
+        let flip = ((i as u16 >> 3) & 0x03) << 14;
+        *entry = flip | ((i as u16 & 7) << 10) | (i as u16 & 0xFF);
+    }
+    for color in 0..256u16 {
+        ppu.cgram.memory[color as usize] = color.wrapping_mul(0x0421) & 0x7FFF;
+    }
+    ppu
+}
+
+fn bench_scrolling_bg1(c: &mut Criterion, name: &str, mode: u8) {
+    let mut ppu = make_ppu(mode);
+    let mut renderer = Renderer::new();
+    let mut scroll: u8 = 0;
+
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            scroll = scroll.wrapping_add(1);
+            ppu.regs.bg1hofs = scroll as u16;
+            ppu.regs.bg1vofs = scroll as u16;
+            for y in 0..SCREEN_HEIGHT {
+                renderer.render_scanline(black_box(&ppu), y);
+            }
+        })
+    });
+}
+
+fn bench_scrolling_4bpp(c: &mut Criterion) {
+    bench_scrolling_bg1(c, "scrolling_bg1_4bpp_frame", 1);
+}
+
+fn bench_scrolling_8bpp(c: &mut Criterion) {
+    bench_scrolling_bg1(c, "scrolling_bg1_8bpp_frame", 3);
+}
+
+/// Pixels at which BG1HOFS changes on every scanline
+const RASTER_SPLITS: [usize; 4] = [48, 112, 160, 216];
+
+/// BG1HOFS for the pixels from `x` on
+fn raster_hofs(x: usize, y: usize) -> u16 {
+    let split = RASTER_SPLITS.iter().filter(|&&split| x >= split).count();
+    (y * 3 + split * 17) as u16
+}
+
+fn bench_raster_catch_up(c: &mut Criterion) {
+    let mut ppu = make_ppu(1);
+    let mut renderer = Renderer::new();
+
+    c.bench_function("raster_bg1_4bpp_frame_catch_up", |b| {
+        b.iter(|| {
+            for y in 0..SCREEN_HEIGHT {
+                ppu.regs.bg1hofs = raster_hofs(0, y);
+                for x in RASTER_SPLITS {
+                    renderer.render_until(black_box(&ppu), y, x);
+                    ppu.regs.bg1hofs = raster_hofs(x, y);
+                }
+                renderer.render_scanline(black_box(&ppu), y);
+            }
+        })
+    });
+}
+
+fn bench_raster_per_dot(c: &mut Criterion) {
+    let mut ppu = make_ppu(1);
+    let mut renderer = Renderer::new();
+
+    c.bench_function("raster_bg1_4bpp_frame_per_dot", |b| {
+        b.iter(|| {
+            for y in 0..SCREEN_HEIGHT {
+                for x in 0..SCREEN_WIDTH {
+                    ppu.regs.bg1hofs = raster_hofs(x, y);
+                    renderer.render_until(black_box(&ppu), y, x + 1);
+                }
+                renderer.render_scanline(black_box(&ppu), y);
+            }
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_scrolling_4bpp,
+    bench_scrolling_8bpp,
+    bench_raster_catch_up,
+    bench_raster_per_dot
+);
+criterion_main!(benches);
@@ -1,8 +1,10 @@
 use crate::constants::CGRAM_SIZE;
 use crate::registers::PPURegisters;
 use crate::write_twice::BytePhase;
+use common::save_state::{StateError, StateReader, StateWriter};
 use common::u16_split::U16Split;
 
+#[derive(Clone)]
 pub struct CGRAM {
     pub memory: [u16; CGRAM_SIZE / 2], // CGRAM stored as u16 words
     word_addr: u8, // Internal 8-bit word address (0–255)
@@ -41,6 +43,16 @@ impl CGRAM {
         self.ppu_open_bus = value;
     }
 
+    /// Write to $2122 during active display: the word is latched and the address
+    /// advances as usual, but the data lands on the color being drawn, which isn't
+    /// modelled, so it is dropped.
+    pub fn skip_data(&mut self, PPURegisters { cgdata_latch, .. }: &mut PPURegisters, value: u8) {
+        if cgdata_latch.write(value).is_some() {
+            self.word_addr = self.word_addr.wrapping_add(1);
+        }
+        self.ppu_open_bus = value;
+    }
+
     // ============================================================
     // $213B - CGDATAREAD
     // ============================================================
@@ -67,6 +79,27 @@ impl CGRAM {
     pub fn read(&self, word_index: u8) -> u16 {
         self.memory[word_index as usize]
     }
+
+    // ============================================================
+    // Save states
+    // ============================================================
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        for word in self.memory {
+            writer.u16(word);
+        }
+        writer.u8(self.word_addr);
+        writer.u8(self.ppu_open_bus);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        for word in &mut self.memory {
+            *word = reader.u16()?;
+        }
+        self.word_addr = reader.u8()?;
+        self.ppu_open_bus = reader.u8()?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -1,6 +1,9 @@
 pub const VRAM_SIZE: usize = 64 * 1024; // 64 KB
 pub const CGRAM_SIZE: usize = 512; // 512 octets
+pub const OAM_SIZE: usize = 544; // 512 octets low table + 32 octets high table
 pub const SCANLINES_PER_FRAME: u16 = 262;
 
 pub const SCREEN_WIDTH: usize = 256;
 pub const SCREEN_HEIGHT: usize = 224;
+pub const SCREEN_HEIGHT_OVERSCAN: usize = 239; // SETINI overscan mode
+pub const FIRST_VISIBLE_DOT: usize = 22; // dot of the scanline showing screen pixel 0
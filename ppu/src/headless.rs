@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::ppu::PPU;
+use crate::rendering::framebuffer::{FrameBuffer, PixelFormat};
+use crate::rendering::renderer::Renderer;
+
+/// Renders one full frame, stepping the PPU until it reports `frame_ready`.
+///
+/// Only the visible scanlines are drawn, the V-blank lines are just stepped.
+pub fn run_frame(ppu: &mut PPU, renderer: &mut Renderer) {
+    loop {
+        let y = ppu.scanline as usize;
+        if y < ppu.regs.visible_scanlines() as usize {
+            renderer.render_scanline(ppu, y);
+        }
+        ppu.step_scanline();
+
+        if ppu.frame_ready {
+            break;
+        }
+    }
+}
+
+/// Renders `frames` frames without any window, leaving the last one in the framebuffer.
+pub fn run_frames(ppu: &mut PPU, renderer: &mut Renderer, frames: usize) {
+    for _ in 0..frames {
+        run_frame(ppu, renderer);
+    }
+}
+
+/// Converts the first `height` lines of the framebuffer to tightly packed RGB24.
+pub fn to_rgb24(framebuffer: &FrameBuffer, height: usize) -> Vec<u8> {
+    let format = framebuffer.format();
+    let bpp = format.bytes_per_pixel();
+    let pixels = framebuffer.width() * height;
+    let mut rgb = Vec::with_capacity(pixels * 3);
+
+    for pixel in framebuffer[..pixels * bpp].chunks_exact(bpp) {
+        match format {
+            PixelFormat::Rgb888 | PixelFormat::Rgba8888 => rgb.extend_from_slice(&pixel[..3]),
+            PixelFormat::Rgb565 => {
+                let value = u16::from_le_bytes([pixel[0], pixel[1]]);
+                rgb.push(((value >> 11) as u8 & 0x1F) << 3);
+                rgb.push(((value >> 5) as u8 & 0x3F) << 2);
+                rgb.push((value as u8 & 0x1F) << 3);
+            }
+            PixelFormat::Indexed => {
+                let value = u16::from_le_bytes([pixel[0], pixel[1]]);
+                rgb.push((value as u8 & 0x1F) << 3);
+                rgb.push(((value >> 5) as u8 & 0x1F) << 3);
+                rgb.push(((value >> 10) as u8 & 0x1F) << 3);
+            }
+        }
+    }
+    rgb
+}
+
+fn encode_png<W: Write>(
+    writer: W,
+    width: usize,
+    height: usize,
+    rgb: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut png_writer = encoder.write_header()?;
+    png_writer.write_image_data(rgb)?;
+    Ok(())
+}
+
+/// Encodes the visible part of the renderer's framebuffer as a PNG image.
+pub fn write_png<W: Write>(renderer: &Renderer, writer: W) -> Result<(), Box<dyn Error>> {
+    let width = renderer.framebuffer.width();
+    let height = renderer.active_height;
+    encode_png(writer, width, height, &to_rgb24(&renderer.framebuffer, height))
+}
+
+/// Encodes the visible picture at half its size as a PNG image, e.g. for save-state previews.
+/// Every other pixel of every other line is kept.
+pub fn write_thumbnail_png<W: Write>(
+    renderer: &Renderer,
+    writer: W,
+) -> Result<(), Box<dyn Error>> {
+    let width = renderer.framebuffer.width();
+    let height = renderer.active_height;
+    let rgb = to_rgb24(&renderer.framebuffer, height);
+
+    let thumbnail: Vec<u8> = rgb
+        .chunks_exact(width * 3)
+        .step_by(2)
+        .flat_map(|line| line.chunks_exact(3).step_by(2).flatten().copied())
+        .collect();
+    encode_png(writer, width.div_ceil(2), height.div_ceil(2), &thumbnail)
+}
+
+/// Saves the visible part of the renderer's framebuffer to a PNG file.
+pub fn save_png<P: AsRef<Path>>(renderer: &Renderer, path: P) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)?;
+    write_png(renderer, BufWriter::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::*;
+
+    // ============================================================
+    // Frame stepping
+    // ============================================================
+
+    /// run_frame must stop right after the frame wraps to scanline 0.
+    #[test]
+    fn test_run_frame_wraps_to_scanline_zero() {
+        let mut ppu = PPU::new();
+        let mut renderer = Renderer::new();
+        run_frame(&mut ppu, &mut renderer);
+        assert!(ppu.frame_ready);
+        assert_eq!(ppu.scanline, 0);
+    }
+
+    /// run_frames must step through every scanline of every frame.
+    #[test]
+    fn test_run_frames_counts_scanlines() {
+        let mut ppu = PPU::new();
+        let mut renderer = Renderer::new();
+        ppu.scanline = 10;
+        run_frames(&mut ppu, &mut renderer, 3);
+        assert_eq!(ppu.scanline, 0);
+        assert!(ppu.frame_ready);
+    }
+
+    // ============================================================
+    // PNG output
+    // ============================================================
+
+    /// Conversion back to RGB24 must be lossless for the native format.
+    #[test]
+    fn test_to_rgb24_from_rgb888() {
+        let mut fb = FrameBuffer::new(2, 1, PixelFormat::Rgb888);
+        fb.set_pixel(1, 0, 10, 20, 30);
+        assert_eq!(to_rgb24(&fb, 1), vec![0, 0, 0, 10, 20, 30]);
+    }
+
+    /// 16-bit formats must decode to the 5-bit precision of the SNES.
+    #[test]
+    fn test_to_rgb24_from_16bit_formats() {
+        for format in [PixelFormat::Rgb565, PixelFormat::Indexed] {
+            let mut fb = FrameBuffer::new(1, 1, format);
+            fb.set_pixel(0, 0, 0xF8, 0x80, 0x08);
+            assert_eq!(to_rgb24(&fb, 1), vec![0xF8, 0x80, 0x08]);
+        }
+    }
+
+    /// The PNG must carry the signature and the visible picture size.
+    #[test]
+    fn test_write_png_header() {
+        let renderer = Renderer::new();
+        let mut out = Vec::new();
+        write_png(&renderer, &mut out).unwrap();
+
+        assert_eq!(&out[..8], b"\x89PNG\r\n\x1a\n");
+        // IHDR width and height follow the chunk length and type
+        assert_eq!(u32::from_be_bytes(out[16..20].try_into().unwrap()), SCREEN_WIDTH as u32);
+        assert_eq!(u32::from_be_bytes(out[20..24].try_into().unwrap()), SCREEN_HEIGHT as u32);
+    }
+
+    /// Thumbnails must be half the size of the visible picture and keep the even pixels.
+    #[test]
+    fn test_write_thumbnail_png() {
+        let mut renderer = Renderer::new();
+        renderer.framebuffer.set_pixel(2, 2, 0xFF, 0xFF, 0xFF);
+        let mut out = Vec::new();
+        write_thumbnail_png(&renderer, &mut out).unwrap();
+
+        let mut reader = png::Decoder::new(&out[..]).read_info().unwrap();
+        let mut rgb = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut rgb).unwrap();
+        assert_eq!(info.width as usize, SCREEN_WIDTH / 2);
+        assert_eq!(info.height as usize, SCREEN_HEIGHT / 2);
+
+        let white = (info.width as usize + 1) * 3;
+        assert_eq!(rgb[white..white + 3], [0xFF, 0xFF, 0xFF]);
+        assert_eq!(rgb[..3], [0, 0, 0]);
+    }
+}
@@ -0,0 +1,125 @@
+/// A PPU layer that can be toggled for debugging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Bg1,
+    Bg2,
+    Bg3,
+    Bg4,
+    Obj,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 5] = [Layer::Bg1, Layer::Bg2, Layer::Bg3, Layer::Bg4, Layer::Obj];
+
+    /// Bit of this layer in TM/TS order (BG1 = bit 0 ... OBJ = bit 4)
+    pub fn bit(self) -> u8 {
+        match self {
+            Layer::Bg1 => 0x01,
+            Layer::Bg2 => 0x02,
+            Layer::Bg3 => 0x04,
+            Layer::Bg4 => 0x08,
+            Layer::Obj => 0x10,
+        }
+    }
+
+    /// Background number (1-4), `None` for OBJ
+    pub fn bg_number(self) -> Option<u8> {
+        match self {
+            Layer::Bg1 => Some(1),
+            Layer::Bg2 => Some(2),
+            Layer::Bg3 => Some(3),
+            Layer::Bg4 => Some(4),
+            Layer::Obj => None,
+        }
+    }
+}
+
+/// Frontend-controlled layer visibility.
+///
+/// Purely a debugging aid: it is applied on top of the emulated TM/TS
+/// registers at render time and is never visible to the emulated game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerToggles(u8);
+
+impl Default for LayerToggles {
+    fn default() -> Self {
+        Self::all_enabled()
+    }
+}
+
+impl LayerToggles {
+    pub fn all_enabled() -> Self {
+        Self(0x1F)
+    }
+
+    pub fn is_enabled(&self, layer: Layer) -> bool {
+        (self.0 & layer.bit()) != 0
+    }
+
+    pub fn set(&mut self, layer: Layer, enabled: bool) {
+        if enabled {
+            self.0 |= layer.bit();
+        } else {
+            self.0 &= !layer.bit();
+        }
+    }
+
+    /// Enables `layer` and disables every other one.
+    pub fn solo(&mut self, layer: Layer) {
+        self.0 = layer.bit();
+    }
+
+    /// Layers as a TM-style bitmask, to AND with TM/TS.
+    pub fn mask(&self) -> u8 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ============================================================
+    // LayerToggles
+    // ============================================================
+
+    /// Every layer must be enabled by default.
+    #[test]
+    fn test_default_all_layers_enabled() {
+        let toggles = LayerToggles::default();
+        for layer in Layer::ALL {
+            assert!(toggles.is_enabled(layer), "{:?} should be enabled", layer);
+        }
+        assert_eq!(toggles.mask(), 0x1F);
+    }
+
+    /// Disabling one layer must not affect the others.
+    #[test]
+    fn test_set_disables_single_layer() {
+        let mut toggles = LayerToggles::default();
+        toggles.set(Layer::Bg2, false);
+
+        assert!(!toggles.is_enabled(Layer::Bg2));
+        for layer in [Layer::Bg1, Layer::Bg3, Layer::Bg4, Layer::Obj] {
+            assert!(toggles.is_enabled(layer));
+        }
+
+        toggles.set(Layer::Bg2, true);
+        assert_eq!(toggles, LayerToggles::all_enabled());
+    }
+
+    /// solo must leave exactly one layer enabled.
+    #[test]
+    fn test_solo_enables_only_one_layer() {
+        let mut toggles = LayerToggles::default();
+        toggles.solo(Layer::Obj);
+        assert_eq!(toggles.mask(), 0x10);
+    }
+
+    /// Layer bits must follow the TM/TS register layout.
+    #[test]
+    fn test_layer_bits_match_tm_layout() {
+        let bits: Vec<u8> = Layer::ALL.iter().map(|l| l.bit()).collect();
+        assert_eq!(bits, vec![0x01, 0x02, 0x04, 0x08, 0x10]);
+    }
+}
@@ -1,6 +1,9 @@
 pub mod constants;
 pub mod vram;
 pub mod cgram;
+pub mod oam;
+pub mod headless;
+pub mod layers;
 pub mod ppu;
 pub mod registers;
 pub mod write_twice;
@@ -1,10 +1,14 @@
 use ppu::constants::*;
+use ppu::headless;
 use ppu::ppu::PPU;
 use ppu::rendering::renderer::Renderer;
 
 use sdl2::pixels::PixelFormatEnum;
+use tracing::info;
 
 fn main() {
+    tracing_subscriber::fmt::init();
+
     let mut ppu = PPU::new();
     let mut renderer = Renderer::new();
 
@@ -73,6 +77,16 @@ fn main() {
     ppu.write(0x2107, 0x04); // BG1SC (tilemap -> word 0x0400, 32x32)
     ppu.write(0x212C, 0x01); // TM (BG1 enabled)
 
+    // Headless mode: ppu --headless <frames> <output.png>
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() == 4 && args[1] == "--headless" {
+        let frames: usize = args[2].parse().expect("frame count must be a number");
+        headless::run_frames(&mut ppu, &mut renderer, frames);
+        headless::save_png(&renderer, &args[3]).expect("could not write PNG");
+        info!(frames, path = %args[3], "Wrote frame");
+        return;
+    }
+
     // SDL2 initialization
     let sdl_context = sdl2::init().unwrap();
     let video = sdl_context.video().unwrap();
@@ -99,16 +113,11 @@ fn main() {
             }
         }
 
-        for y in 0..SCREEN_HEIGHT {
-            renderer.render_scanline(&ppu, y);
-            ppu.step_scanline();
-        }
+        headless::run_frame(&mut ppu, &mut renderer);
 
-        if ppu.frame_ready {
-            texture.update(None, &renderer.framebuffer[..], SCREEN_WIDTH * 3).unwrap();
-            canvas.copy(&texture, None, None).unwrap();
-            canvas.present();
-        }
+        texture.update(None, &renderer.framebuffer[..], renderer.framebuffer.pitch()).unwrap();
+        canvas.copy(&texture, None, None).unwrap();
+        canvas.present();
     }
-    println!("\n>> Nice and clean.");
+    info!("Nice and clean.");
 }
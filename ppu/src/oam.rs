@@ -0,0 +1,260 @@
+use crate::constants::OAM_SIZE;
+use crate::registers::PPURegisters;
+use common::save_state::{StateError, StateReader, StateWriter};
+
+/// Number of sprites described by the OAM
+pub const SPRITE_COUNT: u8 = 128;
+
+#[derive(Clone)]
+pub struct OAM {
+    pub memory: [u8; OAM_SIZE], // 4 octets per sprite, then 2 bits per sprite in the high table
+    addr: u16, // Internal byte address (0–0x3FF)
+    latch: u8, // Even low table byte, written along with the odd one
+    first_sprite: u8, // Sprite with the highest priority
+}
+
+impl Default for OAM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OAM {
+    pub fn new() -> Self {
+        Self {
+            memory: [0; OAM_SIZE],
+            addr: 0,
+            latch: 0,
+            first_sprite: 0,
+        }
+    }
+
+    // ============================================================
+    // $2102 / $2103 - OAMADDL / OAMADDH
+    // ============================================================
+
+    pub fn write_addr_low(&mut self, regs: &mut PPURegisters, value: u8) {
+        regs.oamaddl = value;
+        self.reset_addr(regs);
+    }
+
+    pub fn write_addr_high(&mut self, regs: &mut PPURegisters, value: u8) {
+        regs.oamaddh = value;
+        self.reset_addr(regs);
+    }
+
+    /// Reloads the internal address from OAMADD, on writes to OAMADD and at the end
+    /// of V-blank. With the priority rotation bit set, the addressed sprite becomes
+    /// the one with the highest priority.
+    pub fn reset_addr(&mut self, PPURegisters { oamaddl, oamaddh, .. }: &PPURegisters) {
+        let word_addr = (*oamaddl as u16) | ((*oamaddh as u16 & 0x01) << 8);
+        self.addr = word_addr << 1;
+        self.first_sprite = if (*oamaddh & 0x80) != 0 {
+            ((self.addr >> 2) & 0x7F) as u8
+        } else {
+            0
+        };
+    }
+
+    fn increment_addr(&mut self) {
+        self.addr = (self.addr + 1) & 0x3FF;
+    }
+
+    /// Index in memory of the internal address, the high table being mirrored
+    fn memory_index(&self) -> usize {
+        if (self.addr & 0x200) == 0 {
+            self.addr as usize
+        } else {
+            0x200 | (self.addr as usize & 0x1F)
+        }
+    }
+
+    // ============================================================
+    // $2104 - OAMDATA
+    // ============================================================
+
+    /// Low table words are written at once on the odd byte, high table bytes directly.
+    pub fn write_data(&mut self, value: u8) {
+        let index = self.memory_index();
+        if (self.addr & 0x200) != 0 {
+            self.memory[index] = value;
+        } else if (self.addr & 1) == 0 {
+            self.latch = value;
+        } else {
+            self.memory[index - 1] = self.latch;
+            self.memory[index] = value;
+        }
+        self.increment_addr();
+    }
+
+    /// Write to $2104 dropped by the PPU (outside V-blank and forced blank): the
+    /// address is still incremented.
+    pub fn skip_data(&mut self) {
+        self.increment_addr();
+    }
+
+    // ============================================================
+    // $2138 - OAMDATAREAD
+    // ============================================================
+
+    pub fn read_data(&mut self) -> u8 {
+        let value = self.memory[self.memory_index()];
+        self.increment_addr();
+        value
+    }
+
+    // ============================================================
+    // Helpers
+    // ============================================================
+
+    pub fn first_sprite(&self) -> u8 {
+        self.first_sprite
+    }
+
+    /// Sprite indices from the highest priority to the lowest, the order in which the
+    /// sprite renderer must evaluate them: priority rotation starts at the first sprite
+    /// and wraps around.
+    pub fn sprites_by_priority(&self) -> impl Iterator<Item = u8> {
+        let first = self.first_sprite;
+        (0..SPRITE_COUNT).map(move |i| (first + i) % SPRITE_COUNT)
+    }
+
+    // ============================================================
+    // Save states
+    // ============================================================
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.bytes(&self.memory);
+        writer.u16(self.addr);
+        writer.u8(self.latch);
+        writer.u8(self.first_sprite);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.memory = reader.array()?;
+        self.addr = reader.u16()?;
+        self.latch = reader.u8()?;
+        self.first_sprite = reader.u8()?;
+        if self.addr > 0x3FF || self.first_sprite >= SPRITE_COUNT {
+            return Err(reader.corrupt());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_addr(oam: &mut OAM, regs: &mut PPURegisters, word_addr: u16, rotation: bool) {
+        oam.write_addr_low(regs, word_addr as u8);
+        oam.write_addr_high(regs, ((word_addr >> 8) as u8 & 0x01) | if rotation { 0x80 } else { 0 });
+    }
+
+    // ============================================================
+    // write_data ($2104)
+    // ============================================================
+
+    /// Low table bytes must only be committed once the odd byte is written.
+    #[test]
+    fn test_low_table_written_by_word() {
+        let mut oam = OAM::new();
+        let mut regs = PPURegisters::new();
+        set_addr(&mut oam, &mut regs, 0x0002, false);
+        oam.write_data(0x12);
+        assert_eq!(oam.memory[0x04], 0x00);
+        oam.write_data(0x34);
+        assert_eq!(oam.memory[0x04..0x06], [0x12, 0x34]);
+    }
+
+    /// High table bytes must be written directly, mirrored every 32 octets.
+    #[test]
+    fn test_high_table_written_by_byte() {
+        let mut oam = OAM::new();
+        let mut regs = PPURegisters::new();
+        set_addr(&mut oam, &mut regs, 0x0100, false);
+        oam.write_data(0xAA);
+        assert_eq!(oam.memory[0x200], 0xAA);
+
+        set_addr(&mut oam, &mut regs, 0x0110, false); // byte address 0x220 mirrors 0x200
+        oam.write_data(0x55);
+        assert_eq!(oam.memory[0x200], 0x55);
+    }
+
+    /// Reads must return the addressed byte and increment the address.
+    #[test]
+    fn test_read_data() {
+        let mut oam = OAM::new();
+        let mut regs = PPURegisters::new();
+        oam.memory[0x10] = 0x11;
+        oam.memory[0x11] = 0x22;
+        set_addr(&mut oam, &mut regs, 0x0008, false);
+        assert_eq!(oam.read_data(), 0x11);
+        assert_eq!(oam.read_data(), 0x22);
+    }
+
+    /// The address must wrap around after the mirrored high table.
+    #[test]
+    fn test_addr_wraps() {
+        let mut oam = OAM::new();
+        let mut regs = PPURegisters::new();
+        set_addr(&mut oam, &mut regs, 0x01FF, false);
+        oam.skip_data();
+        oam.skip_data();
+        oam.write_data(0x12);
+        oam.write_data(0x34);
+        assert_eq!(oam.memory[0x00..0x02], [0x12, 0x34]);
+    }
+
+    // ============================================================
+    // Priority rotation
+    // ============================================================
+
+    /// Without the rotation bit, sprite 0 must have the highest priority.
+    #[test]
+    fn test_no_rotation() {
+        let mut oam = OAM::new();
+        let mut regs = PPURegisters::new();
+        set_addr(&mut oam, &mut regs, 0x0010, false);
+        assert_eq!(oam.first_sprite(), 0);
+        assert!(oam.sprites_by_priority().eq(0..SPRITE_COUNT));
+    }
+
+    /// With the rotation bit, the sprite at OAMADD (two words per sprite) must have the
+    /// highest priority, and the order must wrap around.
+    #[test]
+    fn test_rotation() {
+        let mut oam = OAM::new();
+        let mut regs = PPURegisters::new();
+        set_addr(&mut oam, &mut regs, 0x0010, true);
+        assert_eq!(oam.first_sprite(), 8);
+
+        let order: Vec<u8> = oam.sprites_by_priority().collect();
+        assert_eq!(order.len(), SPRITE_COUNT as usize);
+        assert_eq!(order[..2], [8, 9]);
+        assert_eq!(order[SPRITE_COUNT as usize - 8..], [0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    /// The first sprite only depends on OAMADDL, the high table bit is ignored.
+    #[test]
+    fn test_rotation_ignores_high_table_bit() {
+        let mut oam = OAM::new();
+        let mut regs = PPURegisters::new();
+        set_addr(&mut oam, &mut regs, 0x0102, true);
+        assert_eq!(oam.first_sprite(), 1);
+    }
+
+    /// Resetting the address must discard the writes made since OAMADD was set.
+    #[test]
+    fn test_reset_addr_reloads_oamadd() {
+        let mut oam = OAM::new();
+        let mut regs = PPURegisters::new();
+        set_addr(&mut oam, &mut regs, 0x0004, false);
+        oam.write_data(0x12);
+        oam.write_data(0x34);
+        oam.reset_addr(&regs);
+        oam.write_data(0x56);
+        oam.write_data(0x78);
+        assert_eq!(oam.memory[0x08..0x0A], [0x56, 0x78]);
+    }
+}
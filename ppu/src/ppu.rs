@@ -1,27 +1,64 @@
-use crate::constants::SCANLINES_PER_FRAME;
 use crate::registers::PPURegisters;
 use crate::vram::VRAM;
 use crate::cgram::CGRAM;
+use crate::oam::OAM;
+use crate::layers::{Layer, LayerToggles};
+use common::save_state::{StateError, StateReader, StateWriter, Stateful, Tag};
 use common::u16_split::U16Split;
+use common::video_standard::VideoStandard;
+use tracing::warn;
+
+/// How strictly VRAM, OAM and CGRAM writes are restricted to the blanking periods.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VramAccess {
+    /// Writes always land, whatever the PPU is doing
+    #[default]
+    Permissive,
+    /// Like the hardware, writes during active display (outside V-blank and forced
+    /// blank) are dropped, while the address still advances. Some games rely on it.
+    Accurate,
+}
 
 pub struct PPU {
     pub regs: PPURegisters,
     pub vram: VRAM,
     pub cgram: CGRAM,
+    pub oam: OAM,
 
     // Timing
+    pub video_standard: VideoStandard, // NTSC (262 lines) or PAL (312 lines)
     pub scanline: u16,
     pub frame_ready: bool,
+    pub vblank_started: bool, // set on the scanline V-blank (and NMI) begins
+
+    // Debugging: layers hidden by the frontend, independent of TM/TS
+    pub layer_toggles: LayerToggles,
+
+    pub vram_access: VramAccess,
 }
 
 impl PPU {
     pub fn new() -> Self {
+        Self::with_video_standard(VideoStandard::NTSC)
+    }
+
+    pub fn with_video_standard(video_standard: VideoStandard) -> Self {
+        let mut regs = PPURegisters::new();
+        if video_standard == VideoStandard::PAL {
+            regs.stat78 |= 0x10; // STAT78 bit 4: PAL console
+        }
+
         Self {
-            regs: PPURegisters::new(),
+            regs,
             vram: VRAM::new(),
             cgram: CGRAM::new(),
+            oam: OAM::new(),
+            video_standard,
             scanline: 0,
             frame_ready: false,
+            vblank_started: false,
+            layer_toggles: LayerToggles::default(),
+            vram_access: VramAccess::default(),
         }
     }
 
@@ -37,9 +74,16 @@ impl PPU {
             // OAM
             // ==========================
             0x2101 => self.regs.objsel = value, // TODO
-            0x2102 => self.regs.oamaddl = value, // TODO
-            0x2103 => self.regs.oamaddh = value, // TODO
-            0x2104 => self.regs.oamdata = value, // TODO
+            0x2102 => self.oam.write_addr_low(&mut self.regs, value),
+            0x2103 => self.oam.write_addr_high(&mut self.regs, value),
+            0x2104 => {
+                self.regs.oamdata = value;
+                if self.vram_writable() {
+                    self.oam.write_data(value);
+                } else {
+                    self.oam.skip_data();
+                }
+            }
 
             // ==========================
             // BACKGROUNDS
@@ -82,25 +126,23 @@ impl PPU {
             0x2115 => self.regs.vmain = value,
             0x2116 => self.vram.write_vmadd_low(&mut self.regs, value),
             0x2117 => self.vram.write_vmadd_high(&mut self.regs, value),
-            0x2118 => self.vram.write_vmdatal(&mut self.regs, value),
-            0x2119 => self.vram.write_vmdatah(&mut self.regs, value),
+            0x2118 if self.vram_writable() => self.vram.write_vmdatal(&mut self.regs, value),
+            0x2119 if self.vram_writable() => self.vram.write_vmdatah(&mut self.regs, value),
+            0x2118 => self.vram.skip_vmdatal(&mut self.regs),
+            0x2119 => self.vram.skip_vmdatah(&mut self.regs),
 
             // ==========================
             // Mode 7
             // ==========================
             0x211A => self.regs.m7sel = value, // TODO
-            0x211B => self.regs.m7a = value as u16, // TODO
-            0x211C => self.regs.m7b = value as u16, // TODO
-            0x211D => self.regs.m7c = value as u16, // TODO
-            0x211E => self.regs.m7d = value as u16, // TODO
-            0x211F => self.regs.m7x = value as u16, // TODO
-            0x2120 => self.regs.m7y = value as u16, // TODO
+            0x211B..=0x2120 => self.write_m7(addr, value),
 
             // ==========================
             // CGRAM
             // ==========================
             0x2121 => self.cgram.write_addr(&mut self.regs, value),
-            0x2122 => self.cgram.write_data(&mut self.regs, value),
+            0x2122 if self.vram_writable() => self.cgram.write_data(&mut self.regs, value),
+            0x2122 => self.cgram.skip_data(&mut self.regs, value),
 
             // ==========================
             // Window
@@ -127,7 +169,12 @@ impl PPU {
             0x2132 => self.regs.coldata = value, // TODO
 
             _ => {
-                println!("PPU WRITE IGNORED: ${:04X} = {:02X} (register not handled by PPU)", addr, value);
+                warn!(
+                    addr = format_args!("${:04X}", addr),
+                    value,
+                    scanline = self.scanline,
+                    "PPU write ignored: register not handled by the PPU"
+                );
             }
         }
     }
@@ -137,14 +184,14 @@ impl PPU {
             // ==========================
             // Multiply
             // ==========================
-            0x2134 => Self::unimplemented_read_only(addr), // TODO
-            0x2135 => Self::unimplemented_read_only(addr), // TODO
-            0x2136 => Self::unimplemented_read_only(addr), // TODO
+            0x2134 => self.regs.mpyl,
+            0x2135 => self.regs.mpym,
+            0x2136 => self.regs.mpyh,
 
             // ==========================
             // OAM
             // ==========================
-            0x2138 => Self::unimplemented_read_only(addr), // TODO
+            0x2138 => self.oam.read_data(),
 
             // ==========================
             // VRAM
@@ -160,54 +207,161 @@ impl PPU {
             // ==========================
             // Counters
             // ==========================
-            0x2137 => Self::unimplemented_read_only(addr), // TODO
-            0x213C => Self::unimplemented_read_only(addr), // TODO
-            0x213D => Self::unimplemented_read_only(addr), // TODO
-            
+            // SLHV is latched by the bus, which knows the H position: see `latch_counters`
+            0x2137 => 0,
+            0x213C => Self::read_counter(self.regs.ophct, &mut self.regs.ophct_high),
+            0x213D => Self::read_counter(self.regs.opvct, &mut self.regs.opvct_high),
+
             // ==========================
             // Status
             // ==========================
             0x213E => Self::unimplemented_read_only(addr), // TODO
-            0x213F => Self::unimplemented_read_only(addr), // TODO
+            0x213F => {
+                let value = self.regs.stat78;
+                self.regs.stat78 &= !0x40;
+                self.regs.ophct_high = false;
+                self.regs.opvct_high = false;
+                value
+            }
 
             _ => {
-                println!("PPU READ IGNORED: ${:04X} (register not handled by PPU)", addr);
+                warn!(
+                    addr = format_args!("${:04X}", addr),
+                    scanline = self.scanline,
+                    "PPU read ignored: register not handled by the PPU"
+                );
                 0
             }
         }
     }
 
+    /// Latches the beam position (`h` in dots) into OPHCT/OPVCT and sets the STAT78 counter
+    /// latch flag: done by SLHV ($2137) reads and by light guns seeing the beam.
+    pub fn latch_counters(&mut self, h: u16, v: u16) {
+        self.regs.ophct = h & 0x1FF;
+        self.regs.opvct = v & 0x1FF;
+        self.regs.stat78 |= 0x40;
+    }
+
+    /// OPHCT/OPVCT are read twice: the low byte, then bit 8 (the other bits are open bus).
+    fn read_counter(counter: u16, high: &mut bool) -> u8 {
+        let value = if *high { (counter >> 8) as u8 & 0x01 } else { counter as u8 };
+        *high = !*high;
+        value
+    }
+
+    /// Mode 7 registers ($211B-$2120) are written twice (low then high byte)
+    /// through a latch shared by all of them.
+    fn write_m7(&mut self, addr: u16, value: u8) {
+        let word = ((value as u16) << 8) | self.regs.m7_latch as u16;
+        self.regs.m7_latch = value;
+
+        match addr {
+            0x211B => self.regs.m7a = word,
+            0x211C => self.regs.m7b = word,
+            0x211D => self.regs.m7c = word,
+            0x211E => self.regs.m7d = word,
+            0x211F => self.regs.m7x = word,
+            0x2120 => self.regs.m7y = word,
+            _ => unreachable!(),
+        }
+
+        // The multiplier result is refreshed whenever one of its factors changes
+        if matches!(addr, 0x211B | 0x211C) {
+            let product = self.regs.m7_product();
+            self.regs.mpyl = product as u8;
+            self.regs.mpym = (product >> 8) as u8;
+            self.regs.mpyh = (product >> 16) as u8;
+        }
+    }
+
     pub fn step_scanline(&mut self) {
         self.scanline += 1;
 
-        if self.scanline >= SCANLINES_PER_FRAME {
+        if self.scanline >= self.video_standard.scanlines_per_frame() {
             self.scanline = 0;
             self.frame_ready = true;
+            // End of V-blank: the OAM address is reloaded, unless in forced blank
+            if !self.force_blank() {
+                self.oam.reset_addr(&self.regs);
+            }
         } else {
             self.frame_ready = false;
         }
+
+        self.vblank_started = self.scanline == self.regs.vblank_start_scanline();
+    }
+
+    pub fn in_vblank(&self) -> bool {
+        self.scanline >= self.regs.vblank_start_scanline()
     }
 
     pub fn force_blank(&self) -> bool {
         (self.regs.inidisp & 0x80) != 0
     }
 
+    /// Whether VRAM, OAM and CGRAM writes land, see [`VramAccess`].
+    pub fn vram_writable(&self) -> bool {
+        self.vram_access == VramAccess::Permissive || self.force_blank() || self.in_vblank()
+    }
+
     pub fn brightness(&self) -> u8 {
         self.regs.inidisp & 0x0F
     }
 
+    /// Shows or hides `layer` in rendered output without touching any register.
+    pub fn set_layer_enabled(&mut self, layer: Layer, enabled: bool) {
+        self.layer_toggles.set(layer, enabled);
+    }
+
+    pub fn layer_enabled(&self, layer: Layer) -> bool {
+        self.layer_toggles.is_enabled(layer)
+    }
+
     fn unimplemented_read_only(addr: u16) -> u8 {
-        println!(
-            "PPU READ IGNORED: ${:04X} (unimplemented register)",
-            addr
+        warn!(
+            addr = format_args!("${:04X}", addr),
+            "PPU read ignored: unimplemented register"
         );
         0
     }
 }
 
+/// Registers, memories and beam position. The video standard, the layer toggles and the
+/// VRAM access policy are settings of the emulator and are not saved.
+impl Stateful for PPU {
+    const TAG: Tag = *b"PPU ";
+    const VERSION: u16 = 1;
+
+    fn save(&self, writer: &mut StateWriter) {
+        self.regs.save_state(writer);
+        self.vram.save_state(writer);
+        self.cgram.save_state(writer);
+        self.oam.save_state(writer);
+        writer.u16(self.scanline);
+        writer.bool(self.frame_ready);
+        writer.bool(self.vblank_started);
+    }
+
+    fn load(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.regs.load_state(reader)?;
+        self.vram.load_state(reader)?;
+        self.cgram.load_state(reader)?;
+        self.oam.load_state(reader)?;
+        self.scanline = reader.u16()?;
+        self.frame_ready = reader.bool()?;
+        self.vblank_started = reader.bool()?;
+        if self.scanline >= self.video_standard.scanlines_per_frame() {
+            return Err(reader.corrupt());
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::SCANLINES_PER_FRAME;
 
     // ============================================================
     // PPU::new
@@ -293,6 +447,85 @@ mod tests {
         assert_eq!(ppu.regs.oamdata, 0xBE);
     }
 
+    /// Writing $2104 twice must store a word in OAM, read back through $2138.
+    #[test]
+    fn test_oam_write_read_via_ppu() {
+        let mut ppu = PPU::new();
+        ppu.write(0x2102, 0x01);
+        ppu.write(0x2103, 0x00); // byte address 0x002
+        ppu.write(0x2104, 0x12);
+        ppu.write(0x2104, 0x34);
+        assert_eq!(ppu.oam.memory[0x02..0x04], [0x12, 0x34]);
+
+        ppu.write(0x2102, 0x01);
+        assert_eq!(ppu.read(0x2138), 0x12);
+        assert_eq!(ppu.read(0x2138), 0x34);
+    }
+
+    /// The OAM address must be reloaded from OAMADD at the end of V-blank.
+    #[test]
+    fn test_oam_addr_reset_at_end_of_vblank() {
+        let mut ppu = PPU::new();
+        ppu.write(0x2102, 0x04);
+        ppu.write(0x2103, 0x00);
+        ppu.write(0x2104, 0x11);
+        ppu.write(0x2104, 0x22); // byte address -> 0x00A
+
+        while !ppu.frame_ready {
+            ppu.step_scanline();
+        }
+        ppu.write(0x2104, 0x33);
+        ppu.write(0x2104, 0x44);
+        assert_eq!(ppu.oam.memory[0x08..0x0A], [0x33, 0x44]);
+    }
+
+    /// In forced blank the OAM address must carry on at the end of V-blank.
+    #[test]
+    fn test_oam_addr_kept_in_forced_blank() {
+        let mut ppu = PPU::new();
+        ppu.write(0x2100, 0x80);
+        ppu.write(0x2102, 0x04);
+        ppu.write(0x2103, 0x00);
+        ppu.write(0x2104, 0x11);
+        ppu.write(0x2104, 0x22);
+
+        while !ppu.frame_ready {
+            ppu.step_scanline();
+        }
+        ppu.write(0x2104, 0x33);
+        ppu.write(0x2104, 0x44);
+        assert_eq!(ppu.oam.memory[0x08..0x0C], [0x11, 0x22, 0x33, 0x44]);
+    }
+
+    /// The priority rotation bit of OAMADDH must make the addressed sprite the first one.
+    #[test]
+    fn test_oam_priority_rotation() {
+        let mut ppu = PPU::new();
+        ppu.write(0x2102, 0x20);
+        ppu.write(0x2103, 0x80);
+        assert_eq!(ppu.oam.first_sprite(), 0x10);
+
+        ppu.write(0x2103, 0x00);
+        assert_eq!(ppu.oam.first_sprite(), 0);
+    }
+
+    /// In accurate mode, OAM writes during active display are dropped but still
+    /// increment the address.
+    #[test]
+    fn test_oam_write_during_active_display_accurate() {
+        let mut ppu = PPU::new();
+        ppu.vram_access = VramAccess::Accurate;
+        ppu.write(0x2102, 0x00);
+        ppu.write(0x2103, 0x00);
+        ppu.write(0x2104, 0x11);
+        ppu.write(0x2104, 0x22); // dropped, byte address -> 0x002
+
+        ppu.write(0x2100, 0x80); // forced blank
+        ppu.write(0x2104, 0x33);
+        ppu.write(0x2104, 0x44);
+        assert_eq!(ppu.oam.memory[0x00..0x04], [0x00, 0x00, 0x33, 0x44]);
+    }
+
     // ============================================================
     // $2105 - BGMODE / bg_mode()
     // ============================================================
@@ -588,6 +821,49 @@ mod tests {
         assert_eq!(ppu.vram.memory[0x0001], 0x4433);
     }
 
+    /// In accurate mode, VRAM writes during active display are dropped but still
+    /// increment the address.
+    #[test]
+    fn test_vram_write_during_active_display_accurate() {
+        let mut ppu = PPU::new();
+        ppu.vram_access = VramAccess::Accurate;
+        ppu.write(0x2115, 0x80);
+        ppu.write(0x2116, 0x00);
+        ppu.write(0x2117, 0x00);
+        ppu.write(0x2118, 0x11);
+        ppu.write(0x2119, 0x22); // dropped, addr -> 0x0001
+        assert_eq!(ppu.vram.memory[0x0000], 0x0000);
+
+        ppu.write(0x2100, 0x80); // forced blank
+        ppu.write(0x2118, 0x33);
+        ppu.write(0x2119, 0x44);
+        assert_eq!(ppu.vram.memory[0x0001], 0x4433);
+    }
+
+    /// In accurate mode, VRAM writes during V-blank land.
+    #[test]
+    fn test_vram_write_during_vblank_accurate() {
+        let mut ppu = PPU::new();
+        ppu.vram_access = VramAccess::Accurate;
+        ppu.scanline = ppu.regs.vblank_start_scanline();
+        ppu.write(0x2115, 0x80);
+        ppu.write(0x2118, 0xCD);
+        ppu.write(0x2119, 0xAB);
+        assert_eq!(ppu.vram.memory[0x0000], 0xABCD);
+    }
+
+    /// In permissive mode, VRAM writes always land.
+    #[test]
+    fn test_vram_write_during_active_display_permissive() {
+        let mut ppu = PPU::new();
+        assert_eq!(ppu.vram_access, VramAccess::Permissive);
+        assert!(!ppu.force_blank() && !ppu.in_vblank());
+        ppu.write(0x2115, 0x80);
+        ppu.write(0x2118, 0xCD);
+        ppu.write(0x2119, 0xAB);
+        assert_eq!(ppu.vram.memory[0x0000], 0xABCD);
+    }
+
     // ============================================================
     // $211A–$2120 - Mode 7
     // ============================================================
@@ -600,52 +876,107 @@ mod tests {
         assert_eq!(ppu.regs.m7sel, 0x03);
     }
 
-    /// Writing $211B must update m7a.
+    /// Writing $211B twice must store the low then high byte of m7a.
     #[test]
     fn test_write_m7a() {
         let mut ppu = PPU::new();
         ppu.write(0x211B, 0x7F);
-        assert_eq!(ppu.regs.m7a, 0x7F);
+        ppu.write(0x211B, 0x12);
+        assert_eq!(ppu.regs.m7a, 0x127F);
     }
 
-    /// Writing $211C must update m7b.
+    /// Writing $211C twice must store the low then high byte of m7b.
     #[test]
     fn test_write_m7b() {
         let mut ppu = PPU::new();
         ppu.write(0x211C, 0x01);
-        assert_eq!(ppu.regs.m7b, 0x01);
+        ppu.write(0x211C, 0x80);
+        assert_eq!(ppu.regs.m7b, 0x8001);
     }
 
-    /// Writing $211D must update m7c.
+    /// Writing $211D twice must store the low then high byte of m7c.
     #[test]
     fn test_write_m7c() {
         let mut ppu = PPU::new();
         ppu.write(0x211D, 0x02);
-        assert_eq!(ppu.regs.m7c, 0x02);
+        ppu.write(0x211D, 0x34);
+        assert_eq!(ppu.regs.m7c, 0x3402);
     }
 
-    /// Writing $211E must update m7d.
+    /// Writing $211E twice must store the low then high byte of m7d.
     #[test]
     fn test_write_m7d() {
         let mut ppu = PPU::new();
         ppu.write(0x211E, 0x03);
-        assert_eq!(ppu.regs.m7d, 0x03);
+        ppu.write(0x211E, 0x56);
+        assert_eq!(ppu.regs.m7d, 0x5603);
     }
 
-    /// Writing $211F must update m7x.
+    /// Writing $211F twice must store the low then high byte of m7x.
     #[test]
     fn test_write_m7x() {
         let mut ppu = PPU::new();
         ppu.write(0x211F, 0x80);
-        assert_eq!(ppu.regs.m7x, 0x80);
+        ppu.write(0x211F, 0x01);
+        assert_eq!(ppu.regs.m7x, 0x0180);
     }
 
-    /// Writing $2120 must update m7y.
+    /// Writing $2120 twice must store the low then high byte of m7y.
     #[test]
     fn test_write_m7y() {
         let mut ppu = PPU::new();
         ppu.write(0x2120, 0x40);
-        assert_eq!(ppu.regs.m7y, 0x40);
+        ppu.write(0x2120, 0x02);
+        assert_eq!(ppu.regs.m7y, 0x0240);
+    }
+
+    /// Mode 7 registers share a single latch: the low byte of a write is the previous byte written to any of them.
+    #[test]
+    fn test_m7_latch_shared_between_registers() {
+        let mut ppu = PPU::new();
+        ppu.write(0x211B, 0x34);
+        ppu.write(0x211C, 0x12); // low byte comes from the M7A write
+        assert_eq!(ppu.regs.m7b, 0x1234);
+    }
+
+    // ============================================================
+    // $2134–$2136 - MPYL/MPYM/MPYH
+    // ============================================================
+
+    /// MPY registers must hold the signed product of M7A and the last byte written to M7B.
+    #[test]
+    fn test_mpy_product_of_m7a_and_m7b() {
+        let mut ppu = PPU::new();
+        ppu.write(0x211B, 0x34);
+        ppu.write(0x211B, 0x12); // M7A = 0x1234
+        ppu.write(0x211C, 0x10); // M7B high byte = 0x10
+        assert_eq!(ppu.read(0x2134), 0x40);
+        assert_eq!(ppu.read(0x2135), 0x23);
+        assert_eq!(ppu.read(0x2136), 0x01);
+    }
+
+    /// A negative result must be sign-extended over the 24-bit MPY value.
+    #[test]
+    fn test_mpy_negative_result() {
+        let mut ppu = PPU::new();
+        ppu.write(0x211B, 0x02);
+        ppu.write(0x211B, 0x00); // M7A = 2
+        ppu.write(0x211C, 0xFF); // M7B high byte = -1
+        assert_eq!(ppu.read(0x2134), 0xFE);
+        assert_eq!(ppu.read(0x2135), 0xFF);
+        assert_eq!(ppu.read(0x2136), 0xFF);
+    }
+
+    /// Writing M7A after M7B must also refresh the product.
+    #[test]
+    fn test_mpy_updates_on_m7a_write() {
+        let mut ppu = PPU::new();
+        ppu.write(0x211C, 0x03); // M7B high byte = 3
+        ppu.write(0x211B, 0x05);
+        ppu.write(0x211B, 0x00); // M7A = 5
+        assert_eq!(ppu.read(0x2134), 15);
+        assert_eq!(ppu.read(0x2135), 0);
+        assert_eq!(ppu.read(0x2136), 0);
     }
 
     // ============================================================
@@ -666,6 +997,23 @@ mod tests {
         assert_eq!(hi & 0x7F, 0x3A);
     }
 
+    /// In accurate mode, CGRAM writes during active display are dropped but still
+    /// advance the address.
+    #[test]
+    fn test_cgram_write_during_active_display_accurate() {
+        let mut ppu = PPU::new();
+        ppu.vram_access = VramAccess::Accurate;
+        ppu.write(0x2121, 0x00);
+        ppu.write(0x2122, 0xEF);
+        ppu.write(0x2122, 0x3A); // dropped, address -> 1
+
+        ppu.write(0x2100, 0x80); // forced blank
+        ppu.write(0x2122, 0x1F);
+        ppu.write(0x2122, 0x00);
+        assert_eq!(ppu.cgram.read(0), 0x0000);
+        assert_eq!(ppu.cgram.read(1), 0x001F);
+    }
+
     // ============================================================
     // $2123–$212B - Window registers
     // ============================================================
@@ -757,6 +1105,21 @@ mod tests {
         assert!(!ppu.regs.bg1_enabled());
     }
 
+    /// Debug layer toggles must not modify TM or any other register.
+    #[test]
+    fn test_set_layer_enabled_leaves_registers_untouched() {
+        let mut ppu = PPU::new();
+        ppu.write(0x212C, 0x13);
+        ppu.set_layer_enabled(Layer::Bg1, false);
+        ppu.set_layer_enabled(Layer::Obj, false);
+
+        assert!(!ppu.layer_enabled(Layer::Bg1));
+        assert!(!ppu.layer_enabled(Layer::Obj));
+        assert!(ppu.layer_enabled(Layer::Bg2));
+        assert_eq!(ppu.regs.tm, 0x13);
+        assert!(ppu.regs.bg1_enabled());
+    }
+
     /// bg1_tilemap_addr must derive the VRAM address from bits[7:2] of BG1SC.
     #[test]
     fn test_bg1_tilemap_addr_derivation() {
@@ -815,6 +1178,86 @@ mod tests {
         assert_eq!(ppu.scanline, SCANLINES_PER_FRAME - 1);
     }
 
+    /// V-blank must start on scanline 225 without overscan.
+    #[test]
+    fn test_vblank_starts_at_225() {
+        let mut ppu = PPU::new();
+        for _ in 0..224 {
+            ppu.step_scanline();
+            assert!(!ppu.vblank_started);
+            assert!(!ppu.in_vblank());
+        }
+        ppu.step_scanline();
+        assert!(ppu.vblank_started);
+        assert!(ppu.in_vblank());
+        ppu.step_scanline();
+        assert!(!ppu.vblank_started);
+        assert!(ppu.in_vblank());
+    }
+
+    /// With overscan enabled, V-blank must be delayed to scanline 240.
+    #[test]
+    fn test_vblank_starts_at_240_with_overscan() {
+        let mut ppu = PPU::new();
+        ppu.write(0x2133, 0x04);
+        for _ in 0..239 {
+            ppu.step_scanline();
+            assert!(!ppu.vblank_started);
+        }
+        assert!(!ppu.in_vblank());
+        ppu.step_scanline();
+        assert!(ppu.vblank_started);
+        assert_eq!(ppu.scanline, 240);
+    }
+
+    /// V-blank must end when the scanline counter wraps to a new frame.
+    #[test]
+    fn test_vblank_ends_on_new_frame() {
+        let mut ppu = PPU::new();
+        for _ in 0..SCANLINES_PER_FRAME {
+            ppu.step_scanline();
+        }
+        assert!(!ppu.in_vblank());
+    }
+
+    /// A PAL PPU must run 312 scanlines per frame.
+    #[test]
+    fn test_pal_frame_has_312_scanlines() {
+        let mut ppu = PPU::with_video_standard(VideoStandard::PAL);
+        for _ in 0..SCANLINES_PER_FRAME {
+            ppu.step_scanline();
+        }
+        assert!(!ppu.frame_ready);
+        assert_eq!(ppu.scanline, SCANLINES_PER_FRAME);
+        for _ in SCANLINES_PER_FRAME..312 {
+            ppu.step_scanline();
+        }
+        assert!(ppu.frame_ready);
+        assert_eq!(ppu.scanline, 0);
+    }
+
+    /// STAT78 bit 4 must report whether the console is PAL.
+    #[test]
+    fn test_stat78_pal_flag() {
+        assert_eq!(PPU::new().regs.stat78 & 0x10, 0);
+        assert_eq!(PPU::with_video_standard(VideoStandard::PAL).regs.stat78 & 0x10, 0x10);
+    }
+
+    /// OPHCT/OPVCT return their low byte then bit 8, until a STAT78 read resets the flip-flops.
+    #[test]
+    fn test_latched_counters_read_twice() {
+        let mut ppu = PPU::new();
+        ppu.latch_counters(0x123, 0x0FE);
+        assert_eq!(ppu.read(0x213F) & 0x40, 0x40);
+        assert_eq!(ppu.read(0x213F) & 0x40, 0, "the latch flag is cleared by reads");
+
+        assert_eq!([ppu.read(0x213C), ppu.read(0x213C)], [0x23, 0x01]);
+        assert_eq!([ppu.read(0x213D), ppu.read(0x213D)], [0xFE, 0x00]);
+        ppu.read(0x213C);
+        ppu.read(0x213F);
+        assert_eq!(ppu.read(0x213C), 0x23);
+    }
+
     /// frame_ready must remain true across subsequent frames.
     #[test]
     fn test_frame_ready_stays_true_on_subsequent_frames() {
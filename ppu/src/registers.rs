@@ -1,7 +1,11 @@
+use crate::constants::{SCREEN_HEIGHT, SCREEN_HEIGHT_OVERSCAN};
 use crate::write_twice::WriteTwice;
+use common::save_state::{StateError, StateReader, StateWriter};
+use common::u16_split::U16Split;
 
 /// PPU Registers placeholder definitions
 /// Each field is a placeholder; actual behavior, latches, buffering, and timing to implement later.
+#[derive(Clone)]
 pub struct PPURegisters {
     // $2100 - INIDISP
     pub inidisp: u8, // Bits: F...BBBB | Forced blanking (F), screen brightness (B).
@@ -199,6 +203,13 @@ pub struct PPURegisters {
     pub bg1hofs_latch: WriteTwice,
     pub bg1vofs_latch: WriteTwice,
     pub cgdata_latch: WriteTwice,
+
+    // Shared Mode 7 write latch ($211B-$2120): holds the previous byte written
+    pub m7_latch: u8,
+
+    // OPHCT/OPVCT read flip-flops: the next read returns the high bit (reset by STAT78 reads)
+    pub ophct_high: bool,
+    pub opvct_high: bool,
 }
 
 impl PPURegisters {
@@ -271,6 +282,9 @@ impl PPURegisters {
             bg1hofs_latch: WriteTwice::new(),
             bg1vofs_latch: WriteTwice::new(),
             cgdata_latch: WriteTwice::new(),
+            m7_latch: 0,
+            ophct_high: false,
+            opvct_high: false,
         }
     }
 
@@ -286,6 +300,11 @@ impl PPURegisters {
         self.bgmode & 0x07
     }
 
+    /// BGMODE bit 3: high priority BG3 tiles in front of every layer in mode 1.
+    pub fn bg3_priority(&self) -> bool {
+        (self.bgmode & 0x08) != 0
+    }
+
     pub fn bg1_tilemap_addr(&self) -> u16 {
         (self.bg1sc as u16 >> 2) * 0x400
     }
@@ -293,6 +312,271 @@ impl PPURegisters {
     pub fn bg1_tiledata_addr(&self) -> u16 {
         (self.bg12nba as u16) << 12
     }
+
+    pub fn bg3_tilemap_addr(&self) -> u16 {
+        (self.bg3sc as u16 >> 2) * 0x400
+    }
+
+    /// BGnSC of background `bg` (1-4).
+    fn bg_sc(&self, bg: u8) -> u8 {
+        match bg {
+            1 => self.bg1sc,
+            2 => self.bg2sc,
+            3 => self.bg3sc,
+            4 => self.bg4sc,
+            _ => panic!("invalid background BG{}", bg),
+        }
+    }
+
+    /// Tilemap VRAM word address of background `bg` (1-4).
+    pub fn bg_tilemap_addr(&self, bg: u8) -> u16 {
+        (self.bg_sc(bg) as u16 >> 2) * 0x400
+    }
+
+    /// Number of 32x32 tilemap screens of background `bg` (1-4), as (horizontal, vertical).
+    pub fn bg_tilemap_screens(&self, bg: u8) -> (usize, usize) {
+        let sc = self.bg_sc(bg);
+        (1 + (sc & 0x01) as usize, 1 + ((sc >> 1) & 0x01) as usize)
+    }
+
+    /// CHR data VRAM word address of background `bg` (1-4).
+    pub fn bg_tiledata_addr(&self, bg: u8) -> u16 {
+        let nba = match bg {
+            1 | 2 => self.bg12nba,
+            3 | 4 => self.bg34nba,
+            _ => panic!("invalid background BG{}", bg),
+        };
+        let nibble = if bg % 2 == 1 { nba & 0x0F } else { nba >> 4 };
+        (nibble as u16) << 12
+    }
+
+    /// BGMODE bits 4-7: background `bg` (1-4) uses 16x16 tiles.
+    pub fn bg_large_tiles(&self, bg: u8) -> bool {
+        (self.bgmode & (0x08 << bg)) != 0
+    }
+
+    /// Bits per pixel of background `bg` (1-4) in the current mode, `None` if the mode has no such layer.
+    /// Mode 7 is reported as 8bpp for BG1 but uses its own tilemap format.
+    pub fn bg_bpp(&self, bg: u8) -> Option<u8> {
+        let bpp: &[u8] = match self.bg_mode() {
+            0 => &[2, 2, 2, 2],
+            1 => &[4, 4, 2],
+            2 => &[4, 4],
+            3 => &[8, 4],
+            4 => &[8, 2],
+            5 => &[4, 2],
+            6 => &[4],
+            _ => &[8],
+        };
+        bpp.get((bg as usize).checked_sub(1)?).copied()
+    }
+
+    /// Modes 2, 4 and 6 use BG3 tilemap entries as per-column scroll offsets.
+    pub fn offset_per_tile(&self) -> bool {
+        matches!(self.bg_mode(), 2 | 4 | 6)
+    }
+
+    /// SETINI bit 2: 239 visible scanlines instead of 224.
+    pub fn overscan(&self) -> bool {
+        (self.setini & 0x04) != 0
+    }
+
+    /// SETINI bit 6: mode 7 BG2, drawn from the same pixels as BG1.
+    pub fn extbg(&self) -> bool {
+        (self.setini & 0x40) != 0
+    }
+
+    pub fn visible_scanlines(&self) -> u16 {
+        if self.overscan() { SCREEN_HEIGHT_OVERSCAN as u16 } else { SCREEN_HEIGHT as u16 }
+    }
+
+    /// V-blank (and the NMI) starts right after the last visible scanline.
+    pub fn vblank_start_scanline(&self) -> u16 {
+        self.visible_scanlines() + 1
+    }
+
+    /// CGWSEL bit 0: 8bpp BG pixels are BGR colors instead of CGRAM indices.
+    pub fn direct_color(&self) -> bool {
+        (self.cgwsel & 0x01) != 0
+    }
+
+    /// Signed 16x8 product of M7A and the last byte written to M7B, as exposed by MPYL/MPYM/MPYH.
+    pub fn m7_product(&self) -> i32 {
+        (self.m7a as i16 as i32) * (*self.m7b.hi() as i8 as i32)
+    }
+
+    // ============================================================
+    // Save states
+    // ============================================================
+
+    /// Writes the registers and their write-twice latches to a save state.
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        for value in [
+            self.inidisp,
+            self.objsel,
+            self.oamaddl,
+            self.oamaddh,
+            self.oamdata,
+            self.bgmode,
+            self.mosaic,
+            self.bg1sc,
+            self.bg2sc,
+            self.bg3sc,
+            self.bg4sc,
+            self.bg12nba,
+            self.bg34nba,
+            self.vmain,
+            self.vmaddl,
+            self.vmaddh,
+            self.vmdatal,
+            self.vmdatah,
+            self.m7sel,
+            self.cgadd,
+            self.w12sel,
+            self.w34sel,
+            self.wobjsel,
+            self.wh0,
+            self.wh1,
+            self.wh2,
+            self.wh3,
+            self.wbglog,
+            self.wobjlog,
+            self.tm,
+            self.ts,
+            self.tmw,
+            self.tsw,
+            self.cgwsel,
+            self.cgadsub,
+            self.coldata,
+            self.setini,
+            self.mpyl,
+            self.mpym,
+            self.mpyh,
+            self.slhv,
+            self.oamdataread,
+            self.vmdatalread,
+            self.vmdatahread,
+            self.stat77,
+            self.stat78,
+            self.m7_latch,
+        ] {
+            writer.u8(value);
+        }
+        for value in [
+            self.bg1hofs,
+            self.m7hofs,
+            self.bg1vofs,
+            self.m7vofs,
+            self.bg2hofs,
+            self.bg2vofs,
+            self.bg3hofs,
+            self.bg3vofs,
+            self.m7a,
+            self.m7b,
+            self.m7c,
+            self.m7d,
+            self.m7x,
+            self.m7y,
+            self.cgdata,
+            self.cgdataread,
+            self.ophct,
+            self.opvct,
+        ] {
+            writer.u16(value);
+        }
+        for latch in [&self.bg1hofs_latch, &self.bg1vofs_latch, &self.cgdata_latch] {
+            latch.save_state(writer);
+        }
+        writer.bool(self.ophct_high);
+        writer.bool(self.opvct_high);
+    }
+
+    /// Restores the registers written by [`Self::save_state`].
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        for value in [
+            &mut self.inidisp,
+            &mut self.objsel,
+            &mut self.oamaddl,
+            &mut self.oamaddh,
+            &mut self.oamdata,
+            &mut self.bgmode,
+            &mut self.mosaic,
+            &mut self.bg1sc,
+            &mut self.bg2sc,
+            &mut self.bg3sc,
+            &mut self.bg4sc,
+            &mut self.bg12nba,
+            &mut self.bg34nba,
+            &mut self.vmain,
+            &mut self.vmaddl,
+            &mut self.vmaddh,
+            &mut self.vmdatal,
+            &mut self.vmdatah,
+            &mut self.m7sel,
+            &mut self.cgadd,
+            &mut self.w12sel,
+            &mut self.w34sel,
+            &mut self.wobjsel,
+            &mut self.wh0,
+            &mut self.wh1,
+            &mut self.wh2,
+            &mut self.wh3,
+            &mut self.wbglog,
+            &mut self.wobjlog,
+            &mut self.tm,
+            &mut self.ts,
+            &mut self.tmw,
+            &mut self.tsw,
+            &mut self.cgwsel,
+            &mut self.cgadsub,
+            &mut self.coldata,
+            &mut self.setini,
+            &mut self.mpyl,
+            &mut self.mpym,
+            &mut self.mpyh,
+            &mut self.slhv,
+            &mut self.oamdataread,
+            &mut self.vmdatalread,
+            &mut self.vmdatahread,
+            &mut self.stat77,
+            &mut self.stat78,
+            &mut self.m7_latch,
+        ] {
+            *value = reader.u8()?;
+        }
+        for value in [
+            &mut self.bg1hofs,
+            &mut self.m7hofs,
+            &mut self.bg1vofs,
+            &mut self.m7vofs,
+            &mut self.bg2hofs,
+            &mut self.bg2vofs,
+            &mut self.bg3hofs,
+            &mut self.bg3vofs,
+            &mut self.m7a,
+            &mut self.m7b,
+            &mut self.m7c,
+            &mut self.m7d,
+            &mut self.m7x,
+            &mut self.m7y,
+            &mut self.cgdata,
+            &mut self.cgdataread,
+            &mut self.ophct,
+            &mut self.opvct,
+        ] {
+            *value = reader.u16()?;
+        }
+        for latch in [
+            &mut self.bg1hofs_latch,
+            &mut self.bg1vofs_latch,
+            &mut self.cgdata_latch,
+        ] {
+            latch.load_state(reader)?;
+        }
+        self.ophct_high = reader.bool()?;
+        self.opvct_high = reader.bool()?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -349,6 +633,16 @@ mod tests {
         assert_eq!(regs.bg_mode(), 0);
     }
 
+    /// BGMODE bit 3 is the mode 1 BG3 priority flag and SETINI bit 6 EXTBG.
+    #[test]
+    fn test_bg3_priority_and_extbg_bits() {
+        let mut regs = PPURegisters::new();
+        assert!(!regs.bg3_priority() && !regs.extbg());
+        regs.bgmode = 0x09;
+        regs.setini = 0x40;
+        assert!(regs.bg3_priority() && regs.extbg());
+    }
+
     // ============================================================
     // bg1_tilemap_addr
     // ============================================================
@@ -404,4 +698,144 @@ mod tests {
         regs.bg12nba = 0x0F;
         assert_eq!(regs.bg1_tiledata_addr(), 0xF000);
     }
+
+    // ============================================================
+    // bg3_tilemap_addr / offset_per_tile
+    // ============================================================
+
+    /// BG3SC bits[7:2] select the tilemap word address in 0x400-word steps.
+    #[test]
+    fn test_bg3_tilemap_addr_derivation() {
+        let mut regs = PPURegisters::new();
+        regs.bg3sc = 0b00001011;
+        assert_eq!(regs.bg3_tilemap_addr(), 0x0800);
+    }
+
+    /// Offset-per-tile is only available in modes 2, 4 and 6.
+    #[test]
+    fn test_offset_per_tile_modes() {
+        let mut regs = PPURegisters::new();
+        for mode in 0..8 {
+            regs.bgmode = mode;
+            assert_eq!(regs.offset_per_tile(), matches!(mode, 2 | 4 | 6), "mode {}", mode);
+        }
+    }
+
+    // ============================================================
+    // overscan / visible_scanlines / vblank_start_scanline
+    // ============================================================
+
+    /// Without overscan, 224 lines are visible and V-blank starts at line 225.
+    #[test]
+    fn test_visible_scanlines_normal() {
+        let mut regs = PPURegisters::new();
+        regs.setini = 0xFB;
+        assert!(!regs.overscan());
+        assert_eq!(regs.visible_scanlines(), 224);
+        assert_eq!(regs.vblank_start_scanline(), 225);
+    }
+
+    /// With SETINI bit 2 set, 239 lines are visible and V-blank starts at line 240.
+    #[test]
+    fn test_visible_scanlines_overscan() {
+        let mut regs = PPURegisters::new();
+        regs.setini = 0x04;
+        assert!(regs.overscan());
+        assert_eq!(regs.visible_scanlines(), 239);
+        assert_eq!(regs.vblank_start_scanline(), 240);
+    }
+
+    // ============================================================
+    // direct_color
+    // ============================================================
+
+    /// Only bit 0 of CGWSEL enables direct color.
+    #[test]
+    fn test_direct_color_bit0() {
+        let mut regs = PPURegisters::new();
+        regs.cgwsel = 0xFE;
+        assert!(!regs.direct_color());
+        regs.cgwsel = 0x01;
+        assert!(regs.direct_color());
+    }
+
+    // ============================================================
+    // Per-background helpers
+    // ============================================================
+
+    /// Tilemap address and screen count must come from the matching BGnSC.
+    #[test]
+    fn test_bg_tilemap_addr_and_screens() {
+        let mut regs = PPURegisters::new();
+        regs.bg2sc = 0x49; // address bits 0x12, horizontal mirror
+        regs.bg4sc = 0x03;
+        assert_eq!(regs.bg_tilemap_addr(2), 0x4800);
+        assert_eq!(regs.bg_tilemap_screens(2), (2, 1));
+        assert_eq!(regs.bg_tilemap_screens(4), (2, 2));
+        assert_eq!(regs.bg_tilemap_screens(1), (1, 1));
+    }
+
+    /// Each background must use its own nibble of BG12NBA/BG34NBA.
+    #[test]
+    fn test_bg_tiledata_addr_nibbles() {
+        let mut regs = PPURegisters::new();
+        regs.bg12nba = 0x21;
+        regs.bg34nba = 0x43;
+        assert_eq!(regs.bg_tiledata_addr(1), 0x1000);
+        assert_eq!(regs.bg_tiledata_addr(2), 0x2000);
+        assert_eq!(regs.bg_tiledata_addr(3), 0x3000);
+        assert_eq!(regs.bg_tiledata_addr(4), 0x4000);
+    }
+
+    /// BGMODE bits 4-7 select 16x16 tiles for BG1-BG4.
+    #[test]
+    fn test_bg_large_tiles() {
+        let mut regs = PPURegisters::new();
+        regs.bgmode = 0x50;
+        assert!(regs.bg_large_tiles(1));
+        assert!(!regs.bg_large_tiles(2));
+        assert!(regs.bg_large_tiles(3));
+        assert!(!regs.bg_large_tiles(4));
+    }
+
+    /// Layer depth must follow the BG mode table, and missing layers give None.
+    #[test]
+    fn test_bg_bpp_per_mode() {
+        let mut regs = PPURegisters::new();
+        regs.bgmode = 0x01;
+        assert_eq!(regs.bg_bpp(1), Some(4));
+        assert_eq!(regs.bg_bpp(3), Some(2));
+        assert_eq!(regs.bg_bpp(4), None);
+        regs.bgmode = 0x00;
+        assert_eq!(regs.bg_bpp(4), Some(2));
+        regs.bgmode = 0x03;
+        assert_eq!(regs.bg_bpp(1), Some(8));
+        regs.bgmode = 0x07;
+        assert_eq!(regs.bg_bpp(2), None);
+    }
+
+    // ============================================================
+    // m7_product
+    // ============================================================
+
+    /// M7A is a signed 16-bit factor and only the high byte of M7B is used (signed 8-bit).
+    #[test]
+    fn test_m7_product_uses_m7b_high_byte() {
+        let mut regs = PPURegisters::new();
+        regs.m7a = 0x0100;
+        regs.m7b = 0x02FF; // low byte ignored
+        assert_eq!(regs.m7_product(), 0x0200);
+    }
+
+    /// Negative operands must produce a signed result.
+    #[test]
+    fn test_m7_product_signed() {
+        let mut regs = PPURegisters::new();
+        regs.m7a = 0xFFFF; // -1
+        regs.m7b = 0x0500; // 5
+        assert_eq!(regs.m7_product(), -5);
+        regs.m7a = 0x8000; // -32768
+        regs.m7b = 0x8000; // -128
+        assert_eq!(regs.m7_product(), 0x400000);
+    }
 }
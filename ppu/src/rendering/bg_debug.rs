@@ -0,0 +1,301 @@
+use crate::layers::Layer;
+use crate::ppu::PPU;
+use crate::rendering::framebuffer::{FrameBuffer, PixelFormat};
+use crate::rendering::renderer::Renderer;
+use crate::vram::RawVRAM;
+
+/// Mode 7 tilemap: 128x128 tiles of 8x8 pixels
+const MODE7_MAP_SIZE: usize = 128;
+
+impl PPU {
+    /// Draws the whole tilemap of `layer` into an offscreen RGB888 buffer, for a tilemap viewer.
+    ///
+    /// Scroll, windows, mosaic, color math and brightness are ignored, and transparent pixels show
+    /// the backdrop color. The buffer covers every tilemap screen: from 256x256 up to 1024x1024
+    /// (64x64 screens of 16x16 tiles, or the mode 7 map).
+    /// Layers the current BG mode does not have, and OBJ, give an empty 0x0 buffer.
+    pub fn render_bg_debug(&self, layer: Layer) -> FrameBuffer {
+        let Some(bg) = layer.bg_number() else {
+            return FrameBuffer::new(0, 0, PixelFormat::Rgb888);
+        };
+        let Some(bpp) = self.regs.bg_bpp(bg) else {
+            return FrameBuffer::new(0, 0, PixelFormat::Rgb888);
+        };
+
+        let mode = self.regs.bg_mode();
+        if mode == 7 {
+            return self.render_mode7_debug();
+        }
+
+        // Hi-res modes 5 and 6 always use 16 pixel wide tiles
+        let large = self.regs.bg_large_tiles(bg);
+        let tile_w = if large || matches!(mode, 5 | 6) { 16 } else { 8 };
+        let tile_h = if large { 16 } else { 8 };
+
+        let (screens_x, screens_y) = self.regs.bg_tilemap_screens(bg);
+        let width = screens_x * 32 * tile_w;
+        let height = screens_y * 32 * tile_h;
+        let mut framebuffer = FrameBuffer::new(width, height, PixelFormat::Rgb888);
+
+        let tilemap_base = self.regs.bg_tilemap_addr(bg) as usize;
+        let tiledata_base = self.regs.bg_tiledata_addr(bg) as usize;
+        let tile_words = bpp as usize * 4;
+
+        // Mode 0 gives each BG its own 32 color block of CGRAM
+        let palette_base = if mode == 0 { (bg as usize - 1) * 32 } else { 0 };
+        let direct_color = bpp == 8 && self.regs.direct_color();
+        let backdrop = self.cgram.read(0);
+
+        for y in 0..height {
+            for x in 0..width {
+                let (tile_col, tile_row) = (x / tile_w, y / tile_h);
+
+                // 32x32 screens are laid out left to right, then top to bottom
+                let screen = (tile_row / 32) * screens_x + tile_col / 32;
+                let map_word_addr = tilemap_base + screen * 0x400 + (tile_row % 32) * 32 + tile_col % 32;
+                let entry = self.vram.memory[map_word_addr & 0x7FFF];
+
+                let tile_index = (entry & 0x03FF) as usize; // bits 9:0
+                let palette_num = ((entry >> 10) & 0x07) as u8; // bits 12:10
+                let flip_x = (entry & 0x4000) != 0; // bit 14
+                let flip_y = (entry & 0x8000) != 0; // bit 15
+
+                let fx = if flip_x { tile_w - 1 - x % tile_w } else { x % tile_w };
+                let fy = if flip_y { tile_h - 1 - y % tile_h } else { y % tile_h };
+
+                // 16x16 tiles are made of tile N, N+1 (right), N+16 (below) and N+17
+                let tile = tile_index + fx / 8 + (fy / 8) * 16;
+                let tile_word_base = tiledata_base + tile * tile_words;
+                let color_index = decode_tile_pixel(&self.vram.memory, tile_word_base, bpp, fx % 8, fy % 8);
+
+                let color = if color_index == 0 {
+                    backdrop
+                } else if direct_color {
+                    Renderer::direct_color(color_index, palette_num)
+                } else {
+                    let palette_offset = if bpp == 8 { 0 } else { palette_num as usize * (1 << bpp) };
+                    self.cgram.read((palette_base + palette_offset + color_index as usize) as u8)
+                };
+
+                let (r, g, b) = Renderer::apply_brightness(color, 15);
+                framebuffer.set_pixel(x, y, r, g, b);
+            }
+        }
+
+        framebuffer
+    }
+
+    /// Mode 7 keeps a 128x128 byte tilemap in the low bytes of VRAM and 8bpp
+    /// linear tile data (64 bytes per tile) in the high bytes.
+    fn render_mode7_debug(&self) -> FrameBuffer {
+        let size = MODE7_MAP_SIZE * 8;
+        let mut framebuffer = FrameBuffer::new(size, size, PixelFormat::Rgb888);
+
+        let direct_color = self.regs.direct_color();
+        let backdrop = self.cgram.read(0);
+
+        for y in 0..size {
+            for x in 0..size {
+                let [tile, _] = self.vram.memory[(y / 8) * MODE7_MAP_SIZE + x / 8].to_le_bytes();
+                let [_, color_index] = self.vram.memory[tile as usize * 64 + (y % 8) * 8 + x % 8].to_le_bytes();
+
+                let color = if color_index == 0 {
+                    backdrop
+                } else if direct_color {
+                    Renderer::direct_color(color_index, 0)
+                } else {
+                    self.cgram.read(color_index)
+                };
+
+                let (r, g, b) = Renderer::apply_brightness(color, 15);
+                framebuffer.set_pixel(x, y, r, g, b);
+            }
+        }
+
+        framebuffer
+    }
+}
+
+/// Decodes one pixel of a 2, 4 or 8bpp planar tile.
+/// Bitplanes are stored in pairs: planes 0+1 in words 0-7, 2+3 in words 8-15, etc.
+fn decode_tile_pixel(vram: &RawVRAM, tile_word_base: usize, bpp: u8, x: usize, y: usize) -> u8 {
+    let bit = 7 - x;
+    let mut color_index = 0;
+
+    for pair in 0..(bpp as usize / 2) {
+        let [lo, hi] = vram[(tile_word_base + pair * 8 + y) & 0x7FFF].to_le_bytes();
+        color_index |= ((lo >> bit) & 1) << (pair * 2);
+        color_index |= ((hi >> bit) & 1) << (pair * 2 + 1);
+    }
+    color_index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ============================================================
+    // Helpers
+    // ============================================================
+
+    /// Writes BGR555 `color` to CGRAM entry `index`.
+    fn set_color(ppu: &mut PPU, index: u8, color: u16) {
+        ppu.write(0x2121, index);
+        ppu.write(0x2122, color as u8);
+        ppu.write(0x2122, (color >> 8) as u8);
+    }
+
+    /// Writes `word` to VRAM word address `addr`.
+    fn write_vram(ppu: &mut PPU, addr: u16, word: u16) {
+        ppu.write(0x2115, 0x80);
+        ppu.write(0x2116, addr as u8);
+        ppu.write(0x2117, (addr >> 8) as u8);
+        ppu.write(0x2118, word as u8);
+        ppu.write(0x2119, (word >> 8) as u8);
+    }
+
+    fn pixel(fb: &FrameBuffer, x: usize, y: usize) -> (u8, u8, u8) {
+        let i = y * fb.pitch() + x * 3;
+        (fb[i], fb[i + 1], fb[i + 2])
+    }
+
+    /// Mode 1 PPU whose tile 1 (4bpp, CHR at 0) has a solid color 1 row 0.
+    fn make_ppu_mode1() -> PPU {
+        let mut ppu = PPU::new();
+        ppu.write(0x2105, 0x01);
+        ppu.write(0x2107, 0x10); // BG1 tilemap at word 0x1000
+        set_color(&mut ppu, 0, 0x0000);
+        set_color(&mut ppu, 1, 0x001F); // red
+        set_color(&mut ppu, 0x21, 0x7C00); // palette 2, color 1: blue
+        write_vram(&mut ppu, 16, 0x00FF); // tile 1 row 0, plane 0
+        ppu
+    }
+
+    // ============================================================
+    // Buffer size
+    // ============================================================
+
+    /// A 32x32 map of 8x8 tiles must produce a 256x256 buffer.
+    #[test]
+    fn test_size_single_screen() {
+        let ppu = make_ppu_mode1();
+        let fb = ppu.render_bg_debug(Layer::Bg1);
+        assert_eq!((fb.width(), fb.height()), (256, 256));
+    }
+
+    /// Tilemap screen count and 16x16 tiles must scale the buffer up to 1024x1024.
+    #[test]
+    fn test_size_64x64_large_tiles() {
+        let mut ppu = make_ppu_mode1();
+        ppu.write(0x2107, 0x13); // 64x64
+        ppu.write(0x2105, 0x11); // BG1 16x16 tiles
+        let fb = ppu.render_bg_debug(Layer::Bg1);
+        assert_eq!((fb.width(), fb.height()), (1024, 1024));
+
+        ppu.write(0x2107, 0x11); // 64x32
+        let fb = ppu.render_bg_debug(Layer::Bg1);
+        assert_eq!((fb.width(), fb.height()), (1024, 512));
+    }
+
+    /// The mode 7 map is always 1024x1024.
+    #[test]
+    fn test_size_mode7() {
+        let mut ppu = PPU::new();
+        ppu.write(0x2105, 0x07);
+        let fb = ppu.render_bg_debug(Layer::Bg1);
+        assert_eq!((fb.width(), fb.height()), (1024, 1024));
+    }
+
+    /// OBJ and layers missing from the current mode must give an empty buffer.
+    #[test]
+    fn test_missing_layer_is_empty() {
+        let ppu = make_ppu_mode1();
+        assert_eq!(ppu.render_bg_debug(Layer::Obj).width(), 0);
+        assert_eq!(ppu.render_bg_debug(Layer::Bg4).height(), 0);
+    }
+
+    // ============================================================
+    // Pixels
+    // ============================================================
+
+    /// Tile pixels must use the entry's palette, transparent ones the backdrop.
+    #[test]
+    fn test_tile_pixels_and_backdrop() {
+        let mut ppu = make_ppu_mode1();
+        write_vram(&mut ppu, 0x1000, 0x0001); // (0,0): tile 1, palette 0
+        write_vram(&mut ppu, 0x1001, 0x0801); // (1,0): tile 1, palette 2
+
+        let fb = ppu.render_bg_debug(Layer::Bg1);
+        assert_eq!(pixel(&fb, 0, 0), (0xFF, 0, 0));
+        assert_eq!(pixel(&fb, 8, 0), (0, 0, 0xFF));
+        assert_eq!(pixel(&fb, 0, 1), (0, 0, 0), "row 1 of tile 1 is transparent");
+    }
+
+    /// Scroll registers must not move the tilemap.
+    #[test]
+    fn test_ignores_scroll() {
+        let mut ppu = make_ppu_mode1();
+        write_vram(&mut ppu, 0x1000, 0x0001);
+        let reference = ppu.render_bg_debug(Layer::Bg1);
+
+        ppu.write(0x210D, 0x23);
+        ppu.write(0x210D, 0x00);
+        ppu.write(0x210E, 0x11);
+        ppu.write(0x210E, 0x00);
+        let scrolled = ppu.render_bg_debug(Layer::Bg1);
+
+        assert_eq!(&scrolled[..], &reference[..]);
+    }
+
+    /// Entries of the second horizontal screen must be drawn to the right of the first.
+    #[test]
+    fn test_second_screen_placement() {
+        let mut ppu = make_ppu_mode1();
+        ppu.write(0x2107, 0x11); // 64x32
+        write_vram(&mut ppu, 0x1400, 0x0001); // screen 1, tile (0,0)
+
+        let fb = ppu.render_bg_debug(Layer::Bg1);
+        assert_eq!(pixel(&fb, 256, 0), (0xFF, 0, 0));
+        assert_eq!(pixel(&fb, 0, 0), (0, 0, 0));
+    }
+
+    /// Horizontal flip must mirror the tile.
+    #[test]
+    fn test_flip_x() {
+        let mut ppu = make_ppu_mode1();
+        write_vram(&mut ppu, 16, 0x0080); // tile 1 row 0: only leftmost pixel set
+        write_vram(&mut ppu, 0x1000, 0x4001);
+
+        let fb = ppu.render_bg_debug(Layer::Bg1);
+        assert_eq!(pixel(&fb, 0, 0), (0, 0, 0));
+        assert_eq!(pixel(&fb, 7, 0), (0xFF, 0, 0));
+    }
+
+    /// Mode 0 backgrounds must each use their own 32 color CGRAM block.
+    #[test]
+    fn test_mode0_palette_block_per_bg() {
+        let mut ppu = PPU::new();
+        ppu.write(0x2105, 0x00);
+        ppu.write(0x2109, 0x10); // BG3 tilemap at word 0x1000
+        set_color(&mut ppu, 0x41, 0x03E0); // BG3 block, palette 0, color 1: green
+        write_vram(&mut ppu, 8, 0x00FF); // 2bpp tile 1 row 0, plane 0
+        write_vram(&mut ppu, 0x1000, 0x0001);
+
+        let fb = ppu.render_bg_debug(Layer::Bg3);
+        assert_eq!(pixel(&fb, 0, 0), (0, 0xFF, 0));
+    }
+
+    /// Mode 7 pixels must come from the high bytes of the tile's 64 words.
+    #[test]
+    fn test_mode7_pixels() {
+        let mut ppu = PPU::new();
+        ppu.write(0x2105, 0x07);
+        set_color(&mut ppu, 5, 0x001F);
+        write_vram(&mut ppu, 1, 0x0002); // map (1,0) = tile 2
+        write_vram(&mut ppu, 2 * 64 + 3, 0x0500); // tile 2 pixel (3,0) = color 5
+
+        let fb = ppu.render_bg_debug(Layer::Bg1);
+        assert_eq!(pixel(&fb, 8 + 3, 0), (0xFF, 0, 0));
+        assert_eq!(pixel(&fb, 8 + 2, 0), (0, 0, 0));
+    }
+}
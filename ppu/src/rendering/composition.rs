@@ -0,0 +1,283 @@
+use crate::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::rendering::framebuffer::{FrameBuffer, PixelFormat};
+
+/// Where the emulated picture lies on the canvas of a [`Composition`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PictureRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Whether a layer is drawn before the emulated picture, which then covers it, or after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Below,
+    Above,
+}
+
+/// Draws on the canvas of a [`Composition`], around or over the emulated picture, e.g. a
+/// border image. Unlike an [`Overlay`](super::overlay::Overlay), a layer can draw outside
+/// the picture, and it never sees the PPU: presentation stays out of the emulation.
+pub trait CompositionLayer: Send {
+    /// Called once per presented frame, `picture` being where the emulated picture is
+    /// (or is about to be, for [`Placement::Below`] layers) on `canvas`.
+    fn draw(&mut self, canvas: &mut FrameBuffer, picture: PictureRect);
+}
+
+impl<F: FnMut(&mut FrameBuffer, PictureRect) + Send> CompositionLayer for F {
+    fn draw(&mut self, canvas: &mut FrameBuffer, picture: PictureRect) {
+        self(canvas, picture)
+    }
+}
+
+/// Image drawn from the top-left corner of the canvas, like the borders of the Super Game
+/// Boy. Pixels with an alpha below 0x80 are skipped, so that a border placed
+/// [`Placement::Above`] shows the picture through a transparent window.
+pub struct Border {
+    width: usize,
+    height: usize,
+    rgba: Vec<u8>,
+}
+
+impl Border {
+    /// `rgba` holds `width` × `height` pixels of 4 bytes: R, G, B, A.
+    ///
+    /// # Panics
+    /// Panics if `rgba` is not `width` × `height` × 4 bytes long.
+    pub fn from_rgba(width: usize, height: usize, rgba: Vec<u8>) -> Self {
+        assert_eq!(
+            rgba.len(),
+            width * height * 4,
+            "border of {width}x{height} pixels"
+        );
+        Self {
+            width,
+            height,
+            rgba,
+        }
+    }
+}
+
+impl CompositionLayer for Border {
+    fn draw(&mut self, canvas: &mut FrameBuffer, _: PictureRect) {
+        for y in 0..self.height.min(canvas.height()) {
+            for x in 0..self.width.min(canvas.width()) {
+                let index = (y * self.width + x) * 4;
+                if let [r, g, b, alpha] = self.rgba[index..index + 4]
+                    && alpha >= 0x80
+                {
+                    canvas.set_pixel(x, y, r, g, b);
+                }
+            }
+        }
+    }
+}
+
+/// Canvas presented instead of the emulated picture, possibly larger than 256 × 224 to
+/// make room for a border. Each frame, the [`Placement::Below`] layers are drawn, then the
+/// picture is copied at its origin, then the [`Placement::Above`] layers are drawn, each
+/// group in the order the layers were added.
+///
+/// Canvas pixels no layer draws keep the last color drawn there.
+pub struct Composition {
+    canvas: FrameBuffer,
+    origin: (usize, usize),
+    layers: Vec<(Placement, Box<dyn CompositionLayer>)>,
+}
+
+impl Composition {
+    /// Canvas of `width` × `height` pixels in `format`, which must be the format of the
+    /// composed frames, with a 256 × 224 picture centered.
+    pub fn new(width: usize, height: usize, format: PixelFormat) -> Self {
+        Self {
+            canvas: FrameBuffer::new(width, height, format),
+            origin: (
+                width.saturating_sub(SCREEN_WIDTH) / 2,
+                height.saturating_sub(SCREEN_HEIGHT) / 2,
+            ),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Moves the top-left corner of the picture on the canvas, from the next frame.
+    pub fn set_picture_origin(&mut self, x: usize, y: usize) {
+        self.origin = (x, y);
+    }
+
+    pub fn picture_origin(&self) -> (usize, usize) {
+        self.origin
+    }
+
+    /// Draws `layer` on every following frame, after the layers with the same
+    /// `placement` already added.
+    pub fn add_layer(&mut self, placement: Placement, layer: impl CompositionLayer + 'static) {
+        self.layers.push((placement, Box::new(layer)));
+    }
+
+    pub fn clear_layers(&mut self) {
+        self.layers.clear();
+    }
+
+    /// The last composed frame.
+    pub fn canvas(&self) -> &FrameBuffer {
+        &self.canvas
+    }
+
+    /// Mutable access to the canvas, e.g. to take its dirty region.
+    pub fn canvas_mut(&mut self) -> &mut FrameBuffer {
+        &mut self.canvas
+    }
+
+    /// Composes the first `height` lines of `picture` with the layers.
+    pub fn compose(&mut self, picture: &FrameBuffer, height: usize) -> &FrameBuffer {
+        let rect = PictureRect {
+            x: self.origin.0,
+            y: self.origin.1,
+            width: picture.width(),
+            height,
+        };
+        for placement in [Placement::Below, Placement::Above] {
+            if placement == Placement::Above {
+                self.canvas.blit(picture, height, self.origin);
+            }
+            for (_, layer) in self.layers.iter_mut().filter(|(p, _)| *p == placement) {
+                layer.draw(&mut self.canvas, rect);
+            }
+        }
+        &self.canvas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [0xFF, 0, 0, 0xFF];
+    const BLUE: [u8; 4] = [0, 0, 0xFF, 0xFF];
+    const BLACK: [u8; 4] = [0, 0, 0, 0xFF];
+
+    fn pixel(framebuffer: &FrameBuffer, x: usize, y: usize) -> [u8; 4] {
+        let index = (y * framebuffer.width() + x) * 4;
+        framebuffer[index..index + 4].try_into().unwrap()
+    }
+
+    fn picture(width: usize, height: usize) -> FrameBuffer {
+        let mut picture = FrameBuffer::new(width, height, PixelFormat::Rgba8888);
+        for y in 0..height {
+            for x in 0..width {
+                picture.set_pixel(x, y, 0xFF, 0, 0);
+            }
+        }
+        picture
+    }
+
+    fn fill(rgba: [u8; 4]) -> impl FnMut(&mut FrameBuffer, PictureRect) + Send {
+        move |canvas: &mut FrameBuffer, _: PictureRect| {
+            for y in 0..canvas.height() {
+                for x in 0..canvas.width() {
+                    canvas.set_pixel(x, y, rgba[0], rgba[1], rgba[2]);
+                }
+            }
+        }
+    }
+
+    // ============================================================
+    // Composition
+    // ============================================================
+
+    /// A new composition must center a 256 × 224 picture on its canvas.
+    #[test]
+    fn test_picture_centered() {
+        let composition = Composition::new(320, 240, PixelFormat::Rgba8888);
+        assert_eq!(composition.picture_origin(), (32, 8));
+        assert_eq!(composition.canvas().width(), 320);
+
+        let small = Composition::new(128, 128, PixelFormat::Rgba8888);
+        assert_eq!(small.picture_origin(), (0, 0));
+    }
+
+    /// Layers below must be covered by the picture, layers above must cover it.
+    #[test]
+    fn test_layers_around_picture() {
+        let mut composition = Composition::new(4, 4, PixelFormat::Rgba8888);
+        composition.set_picture_origin(1, 1);
+        composition.add_layer(Placement::Below, fill(BLUE));
+        composition.add_layer(
+            Placement::Above,
+            |canvas: &mut FrameBuffer, rect: PictureRect| {
+                assert_eq!(
+                    rect,
+                    PictureRect {
+                        x: 1,
+                        y: 1,
+                        width: 2,
+                        height: 1
+                    }
+                );
+                canvas.set_pixel(2, 1, 0, 0, 0);
+            },
+        );
+        let canvas = composition.compose(&picture(2, 2), 1);
+
+        assert_eq!(pixel(canvas, 0, 0), BLUE);
+        assert_eq!(pixel(canvas, 1, 1), RED);
+        assert_eq!(pixel(canvas, 2, 1), BLACK);
+        assert_eq!(
+            pixel(canvas, 1, 2),
+            BLUE,
+            "only the first lines are the picture"
+        );
+    }
+
+    /// Layers with the same placement must be drawn in the order they were added.
+    #[test]
+    fn test_layer_order() {
+        let mut composition = Composition::new(2, 2, PixelFormat::Rgba8888);
+        composition.set_picture_origin(2, 2);
+        composition.add_layer(Placement::Above, fill(RED));
+        composition.add_layer(Placement::Above, fill(BLUE));
+        assert_eq!(pixel(composition.compose(&picture(1, 1), 1), 0, 0), BLUE);
+
+        composition.clear_layers();
+        composition.canvas_mut().set_pixel(0, 0, 0, 0, 0);
+        assert_eq!(pixel(composition.compose(&picture(1, 1), 1), 0, 0), BLACK);
+    }
+
+    // ============================================================
+    // Border
+    // ============================================================
+
+    /// A border above the picture must show it through its transparent pixels.
+    #[test]
+    fn test_border_window() {
+        let mut rgba = [BLUE; 9];
+        rgba[4] = [0, 0, 0, 0];
+        let mut composition = Composition::new(3, 3, PixelFormat::Rgba8888);
+        composition.set_picture_origin(1, 1);
+        composition.add_layer(Placement::Above, Border::from_rgba(3, 3, rgba.concat()));
+        let canvas = composition.compose(&picture(1, 1), 1);
+
+        assert_eq!(pixel(canvas, 1, 1), RED);
+        assert_eq!(pixel(canvas, 0, 1), BLUE);
+        assert_eq!(pixel(canvas, 2, 2), BLUE);
+    }
+
+    /// A border larger than the canvas must be clipped.
+    #[test]
+    fn test_border_clipped() {
+        let mut canvas = FrameBuffer::new(2, 1, PixelFormat::Rgba8888);
+        let mut border = Border::from_rgba(3, 2, [BLUE; 6].concat());
+        border.draw(
+            &mut canvas,
+            PictureRect {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+            },
+        );
+        assert_eq!(pixel(&canvas, 1, 0), BLUE);
+    }
+}
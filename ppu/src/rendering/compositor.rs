@@ -0,0 +1,256 @@
+use std::ops::Range;
+
+use crate::constants::SCREEN_WIDTH;
+use crate::layers::Layer;
+use Layer::{Bg1, Bg2, Bg3, Bg4, Obj};
+
+/// Pixel drawn by a layer, before the layers are put together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerPixel {
+    /// BGR555 color
+    pub color: u16,
+    /// Tile priority bit (0-1) for backgrounds, OAM priority (0-3) for sprites
+    pub priority: u8,
+}
+
+/// One line of a layer, `None` where the layer is transparent.
+pub type LayerLine = [Option<LayerPixel>; SCREEN_WIDTH];
+
+/// Layer and priority pairs from front to back, see [`priority_order`]
+pub type PriorityOrder = &'static [(Layer, u8)];
+
+#[rustfmt::skip]
+const MODE_0: PriorityOrder = &[
+    (Obj, 3), (Bg1, 1), (Bg2, 1), (Obj, 2), (Bg1, 0), (Bg2, 0),
+    (Obj, 1), (Bg3, 1), (Bg4, 1), (Obj, 0), (Bg3, 0), (Bg4, 0),
+];
+#[rustfmt::skip]
+const MODE_1: PriorityOrder = &[
+    (Obj, 3), (Bg1, 1), (Bg2, 1), (Obj, 2), (Bg1, 0), (Bg2, 0),
+    (Obj, 1), (Bg3, 1), (Obj, 0), (Bg3, 0),
+];
+/// Mode 1 with BGMODE bit 3 set: high priority BG3 tiles in front of everything
+#[rustfmt::skip]
+const MODE_1_BG3_PRIORITY: PriorityOrder = &[
+    (Bg3, 1), (Obj, 3), (Bg1, 1), (Bg2, 1), (Obj, 2), (Bg1, 0),
+    (Bg2, 0), (Obj, 1), (Obj, 0), (Bg3, 0),
+];
+#[rustfmt::skip]
+const MODES_2_TO_5: PriorityOrder = &[
+    (Obj, 3), (Bg1, 1), (Obj, 2), (Bg2, 1), (Obj, 1), (Bg1, 0), (Obj, 0), (Bg2, 0),
+];
+const MODE_6: PriorityOrder = &[(Obj, 3), (Bg1, 1), (Obj, 2), (Obj, 1), (Bg1, 0), (Obj, 0)];
+/// Mode 7 BG1 has no priority bit, it is drawn at priority 0
+const MODE_7: PriorityOrder = &[(Obj, 3), (Obj, 2), (Obj, 1), (Bg1, 0), (Obj, 0)];
+/// Mode 7 with SETINI EXTBG: BG2 uses bit 7 of the BG1 pixel as its priority
+#[rustfmt::skip]
+const MODE_7_EXTBG: PriorityOrder = &[
+    (Obj, 3), (Obj, 2), (Bg2, 1), (Obj, 1), (Bg1, 0), (Obj, 0), (Bg2, 0),
+];
+
+/// Order in which the layers cover each other in BG `mode`, front to back.
+///
+/// `bg3_priority` is BGMODE bit 3 (only used in mode 1) and `extbg` SETINI bit 6 (only used
+/// in mode 7).
+pub fn priority_order(mode: u8, bg3_priority: bool, extbg: bool) -> PriorityOrder {
+    match mode {
+        0 => MODE_0,
+        1 if bg3_priority => MODE_1_BG3_PRIORITY,
+        1 => MODE_1,
+        2..=5 => MODES_2_TO_5,
+        6 => MODE_6,
+        _ if extbg => MODE_7_EXTBG,
+        _ => MODE_7,
+    }
+}
+
+/// Puts the layers of a scanline together: each layer is drawn in its own line buffer,
+/// then every screen pixel takes the frontmost opaque layer pixel, following the
+/// [`priority_order`] of the BG mode.
+///
+/// Layers draw with [`Self::draw`] rather than into the framebuffer, so later stages (color
+/// math, windows) work on whole layer lines.
+pub struct Compositor {
+    lines: [LayerLine; Layer::ALL.len()],
+}
+
+impl Default for Compositor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compositor {
+    pub fn new() -> Self {
+        Self {
+            lines: [[None; SCREEN_WIDTH]; Layer::ALL.len()],
+        }
+    }
+
+    /// Makes every layer transparent, before drawing a new scanline.
+    pub fn clear(&mut self) {
+        for line in &mut self.lines {
+            line.fill(None);
+        }
+    }
+
+    /// Makes pixels `xs` of every layer transparent, before drawing part of a scanline.
+    pub fn clear_span(&mut self, xs: Range<usize>) {
+        for line in &mut self.lines {
+            line[xs.clone()].fill(None);
+        }
+    }
+
+    pub fn line(&self, layer: Layer) -> &LayerLine {
+        &self.lines[layer as usize]
+    }
+
+    pub fn draw(&mut self, layer: Layer, x: usize, color: u16, priority: u8) {
+        self.lines[layer as usize][x] = Some(LayerPixel { color, priority });
+    }
+
+    /// Color of screen pixel `x`: the first opaque pixel of `order` among the `layers`
+    /// (TM-style mask), `None` when they are all transparent and the backdrop shows.
+    pub fn pixel(&self, x: usize, order: PriorityOrder, layers: u8) -> Option<u16> {
+        order
+            .iter()
+            .filter(|(layer, _)| (layers & layer.bit()) != 0)
+            .find_map(|&(layer, priority)| {
+                self.lines[layer as usize][x]
+                    .filter(|pixel| pixel.priority == priority)
+                    .map(|pixel| pixel.color)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: u16 = 0x001F;
+    const GREEN: u16 = 0x03E0;
+    const BLUE: u16 = 0x7C00;
+
+    // ============================================================
+    // priority_order
+    // ============================================================
+
+    /// Every mode must list each of its (layer, priority) pairs exactly once.
+    #[test]
+    fn test_priority_orders_have_no_duplicates() {
+        for order in [
+            MODE_0,
+            MODE_1,
+            MODE_1_BG3_PRIORITY,
+            MODES_2_TO_5,
+            MODE_6,
+            MODE_7,
+            MODE_7_EXTBG,
+        ] {
+            for (i, entry) in order.iter().enumerate() {
+                assert!(!order[i + 1..].contains(entry), "{:?} listed twice", entry);
+            }
+        }
+    }
+
+    /// Modes must select their table, BG3 priority and EXTBG only where they apply.
+    #[test]
+    fn test_priority_order_selection() {
+        assert_eq!(priority_order(1, true, false)[0], (Bg3, 1));
+        assert_eq!(priority_order(1, false, false), MODE_1);
+        assert_eq!(priority_order(3, true, true), MODES_2_TO_5);
+        assert_eq!(priority_order(7, false, true), MODE_7_EXTBG);
+        assert_eq!(priority_order(7, true, false), MODE_7);
+    }
+
+    // ============================================================
+    // Compositor
+    // ============================================================
+
+    /// With nothing drawn, the backdrop must show.
+    #[test]
+    fn test_transparent_layers_show_backdrop() {
+        let compositor = Compositor::new();
+        assert_eq!(compositor.pixel(0, MODE_1, 0x1F), None);
+    }
+
+    /// A high priority tile of BG2 must cover a low priority tile of BG1.
+    #[test]
+    fn test_tile_priority_beats_layer_order() {
+        let mut compositor = Compositor::new();
+        compositor.draw(Bg1, 0, RED, 0);
+        compositor.draw(Bg2, 0, GREEN, 1);
+        assert_eq!(compositor.pixel(0, MODE_1, 0x1F), Some(GREEN));
+
+        compositor.draw(Bg1, 0, RED, 1);
+        assert_eq!(compositor.pixel(0, MODE_1, 0x1F), Some(RED));
+    }
+
+    /// Sprites must sit between background priorities according to their own priority.
+    #[test]
+    fn test_obj_priorities_interleave_with_backgrounds() {
+        let mut compositor = Compositor::new();
+        compositor.draw(Bg1, 0, RED, 1);
+        compositor.draw(Obj, 0, BLUE, 2);
+        assert_eq!(compositor.pixel(0, MODES_2_TO_5, 0x1F), Some(RED));
+
+        compositor.draw(Obj, 0, BLUE, 3);
+        assert_eq!(compositor.pixel(0, MODES_2_TO_5, 0x1F), Some(BLUE));
+    }
+
+    /// The mode 1 BG3 priority bit must bring high priority BG3 tiles to the front.
+    #[test]
+    fn test_mode1_bg3_priority() {
+        let mut compositor = Compositor::new();
+        compositor.draw(Bg1, 0, RED, 1);
+        compositor.draw(Bg3, 0, GREEN, 1);
+        assert_eq!(
+            compositor.pixel(0, priority_order(1, false, false), 0x1F),
+            Some(RED)
+        );
+        assert_eq!(
+            compositor.pixel(0, priority_order(1, true, false), 0x1F),
+            Some(GREEN)
+        );
+    }
+
+    /// Layers left out of the mask must be skipped, uncovering the ones behind.
+    #[test]
+    fn test_layer_mask() {
+        let mut compositor = Compositor::new();
+        compositor.draw(Bg1, 0, RED, 1);
+        compositor.draw(Bg2, 0, GREEN, 0);
+        assert_eq!(compositor.pixel(0, MODE_1, 0x1E), Some(GREEN));
+        assert_eq!(compositor.pixel(0, MODE_1, 0x1C), None);
+    }
+
+    /// clear must make every layer transparent again.
+    #[test]
+    fn test_clear() {
+        let mut compositor = Compositor::new();
+        compositor.draw(Obj, 5, BLUE, 0);
+        assert_eq!(
+            compositor.line(Obj)[5],
+            Some(LayerPixel {
+                color: BLUE,
+                priority: 0
+            })
+        );
+        compositor.clear();
+        assert_eq!(compositor.pixel(5, MODE_0, 0x1F), None);
+    }
+
+    /// clear_span must only make the pixels of the span transparent.
+    #[test]
+    fn test_clear_span() {
+        let mut compositor = Compositor::new();
+        for x in [9, 10, 19, 20] {
+            compositor.draw(Bg1, x, RED, 0);
+        }
+        compositor.clear_span(10..20);
+        assert_eq!(compositor.pixel(9, MODE_0, 0x1F), Some(RED));
+        assert_eq!(compositor.pixel(10, MODE_0, 0x1F), None);
+        assert_eq!(compositor.pixel(19, MODE_0, 0x1F), None);
+        assert_eq!(compositor.pixel(20, MODE_0, 0x1F), Some(RED));
+    }
+}
@@ -0,0 +1,149 @@
+use std::io::{self, Write};
+
+use common::video_standard::VideoStandard;
+
+use crate::headless::to_rgb24;
+use crate::rendering::framebuffer::FrameBuffer;
+
+/// Receives every rendered frame, e.g. to record a video of a test ROM.
+pub trait FrameSink: Send {
+    /// Called once per emulated frame with the first `height` lines of `framebuffer`
+    /// being the picture.
+    fn frame(&mut self, framebuffer: &FrameBuffer, height: usize) -> io::Result<()>;
+}
+
+impl<F: FnMut(&FrameBuffer, usize) -> io::Result<()> + Send> FrameSink for F {
+    fn frame(&mut self, framebuffer: &FrameBuffer, height: usize) -> io::Result<()> {
+        self(framebuffer, height)
+    }
+}
+
+/// Writes frames as an uncompressed YUV4MPEG2 stream (`.y4m`), which video tools
+/// read directly and which can be compared frame by frame across commits.
+///
+/// The frame rate is the exact one of the console (master clock over master cycles
+/// per frame) and pixels are 8:7, like on a TV. The picture height is the one of the
+/// first frame: later frames are cropped or padded with black to fit.
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    video_standard: VideoStandard,
+    height: Option<usize>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(writer: W, video_standard: VideoStandard) -> Self {
+        Self {
+            writer,
+            video_standard,
+            height: None,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_header(&mut self, width: usize, height: usize) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "YUV4MPEG2 W{width} H{height} F{}:{} Ip A8:7 C444",
+            self.video_standard.master_clock_hz(),
+            self.video_standard.master_cycles_per_frame(),
+        )
+    }
+
+    /// BT.601 studio range conversion of an RGB24 pixel
+    fn yuv(pixel: &[u8]) -> [u8; 3] {
+        let [r, g, b] = [pixel[0] as i32, pixel[1] as i32, pixel[2] as i32];
+        let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+        let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+        let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+        [y as u8, u as u8, v as u8]
+    }
+}
+
+impl<W: Write + Send> FrameSink for Y4mWriter<W> {
+    fn frame(&mut self, framebuffer: &FrameBuffer, height: usize) -> io::Result<()> {
+        let width = framebuffer.width();
+        let stream_height = match self.height {
+            Some(stream_height) => stream_height,
+            None => {
+                self.write_header(width, height)?;
+                *self.height.insert(height)
+            }
+        };
+
+        let rgb = to_rgb24(framebuffer, height.min(stream_height));
+        let pixels: Vec<[u8; 3]> = rgb.chunks_exact(3).map(Self::yuv).collect();
+        let padding = width * stream_height - pixels.len();
+        let black = Self::yuv(&[0, 0, 0]);
+
+        self.writer.write_all(b"FRAME\n")?;
+        for plane in 0..3 {
+            let samples: Vec<u8> = pixels
+                .iter()
+                .map(|pixel| pixel[plane])
+                .chain(std::iter::repeat_n(black[plane], padding))
+                .collect();
+            self.writer.write_all(&samples)?;
+        }
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::framebuffer::PixelFormat;
+
+    fn frame_size(width: usize, height: usize) -> usize {
+        b"FRAME\n".len() + width * height * 3
+    }
+
+    /// The header must be written once, with the first frame size and the console timing.
+    #[test]
+    fn test_header() {
+        let fb = FrameBuffer::new(4, 3, PixelFormat::Rgb888);
+        let mut writer = Y4mWriter::new(Vec::new(), VideoStandard::NTSC);
+        writer.frame(&fb, 2).unwrap();
+        writer.frame(&fb, 2).unwrap();
+
+        let out = writer.into_inner();
+        let header = b"YUV4MPEG2 W4 H2 F21477272:357368 Ip A8:7 C444\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(out.len(), header.len() + 2 * frame_size(4, 2));
+    }
+
+    /// Pixels must be converted to planar YUV, black and white at the studio range ends.
+    #[test]
+    fn test_planes() {
+        let mut fb = FrameBuffer::new(2, 1, PixelFormat::Rgb888);
+        fb.set_pixel(1, 0, 0xFF, 0xFF, 0xFF);
+        let mut writer = Y4mWriter::new(Vec::new(), VideoStandard::PAL);
+        writer.frame(&fb, 1).unwrap();
+
+        let out = writer.into_inner();
+        let frame = &out[out.len() - frame_size(2, 1)..];
+        assert_eq!(frame, b"FRAME\n\x10\xEB\x80\x80\x80\x80");
+    }
+
+    /// Frames taller than the stream must be cropped, shorter ones padded with black.
+    #[test]
+    fn test_height_follows_first_frame() {
+        let mut fb = FrameBuffer::new(1, 3, PixelFormat::Rgb888);
+        for y in 0..3 {
+            fb.set_pixel(0, y, 0xFF, 0xFF, 0xFF);
+        }
+        let mut writer = Y4mWriter::new(Vec::new(), VideoStandard::NTSC);
+        writer.frame(&fb, 2).unwrap();
+        writer.frame(&fb, 3).unwrap();
+        writer.frame(&fb, 1).unwrap();
+
+        let out = writer.into_inner();
+        let frames: Vec<&[u8]> = out[out.len() - 3 * frame_size(1, 2)..]
+            .chunks(frame_size(1, 2))
+            .map(|frame| &frame[b"FRAME\n".len()..b"FRAME\n".len() + 2])
+            .collect();
+        assert_eq!(frames, [b"\xEB\xEB", b"\xEB\xEB", b"\xEB\x10"]);
+    }
+}
@@ -0,0 +1,406 @@
+use std::ops::{Deref, DerefMut};
+
+/// Pixel layouts a [`FrameBuffer`] can store its pixels in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 3 bytes per pixel: R, G, B
+    Rgb888,
+    /// 4 bytes per pixel: R, G, B, A (A is always 0xFF)
+    Rgba8888,
+    /// 2 bytes per pixel (little endian): RRRRRGGG GGGBBBBB
+    Rgb565,
+    /// 2 bytes per pixel (little endian): index into the 32768-color SNES
+    /// palette, i.e. the BGR555 value of the pixel (0BBBBBGG GGGRRRRR)
+    Indexed,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Rgb888 => 3,
+            PixelFormat::Rgba8888 => 4,
+            PixelFormat::Rgb565 | PixelFormat::Indexed => 2,
+        }
+    }
+
+    /// Encodes an 8-bit per channel color into this format.
+    /// Only the first `bytes_per_pixel` bytes of the result are meaningful.
+    pub fn encode(&self, r: u8, g: u8, b: u8) -> [u8; 4] {
+        match self {
+            PixelFormat::Rgb888 => [r, g, b, 0],
+            PixelFormat::Rgba8888 => [r, g, b, 0xFF],
+            PixelFormat::Rgb565 => {
+                let value = ((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3);
+                let [lo, hi] = value.to_le_bytes();
+                [lo, hi, 0, 0]
+            }
+            PixelFormat::Indexed => {
+                let value = (r as u16 >> 3) | ((g as u16 >> 3) << 5) | ((b as u16 >> 3) << 10);
+                let [lo, hi] = value.to_le_bytes();
+                [lo, hi, 0, 0]
+            }
+        }
+    }
+
+    /// Decodes a pixel of this format back to 8 bits per channel, the low bits of 5 and 6
+    /// bit channels repeating the high ones (so 0x1F decodes to 0xFF).
+    pub fn decode(&self, pixel: &[u8]) -> (u8, u8, u8) {
+        let expand5 = |value: u16| ((value << 3) | (value >> 2)) as u8;
+        let expand6 = |value: u16| ((value << 2) | (value >> 4)) as u8;
+        match self {
+            PixelFormat::Rgb888 | PixelFormat::Rgba8888 => (pixel[0], pixel[1], pixel[2]),
+            PixelFormat::Rgb565 => {
+                let value = u16::from_le_bytes([pixel[0], pixel[1]]);
+                (
+                    expand5(value >> 11),
+                    expand6((value >> 5) & 0x3F),
+                    expand5(value & 0x1F),
+                )
+            }
+            PixelFormat::Indexed => {
+                let value = u16::from_le_bytes([pixel[0], pixel[1]]);
+                (
+                    expand5(value & 0x1F),
+                    expand5((value >> 5) & 0x1F),
+                    expand5((value >> 10) & 0x1F),
+                )
+            }
+        }
+    }
+}
+
+/// Rectangle of pixels, bounds are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyRegion {
+    pub x_min: usize,
+    pub y_min: usize,
+    pub x_max: usize,
+    pub y_max: usize,
+}
+
+/// Output picture of the PPU, stored in a frontend-selected [`PixelFormat`].
+///
+/// Pixels changed through [`Self::set_pixel`] are tracked in a dirty region,
+/// so frontends can only upload the part of the picture that changed since
+/// the last call to [`Self::take_dirty_region`].
+///
+/// The raw bytes are accessible by dereferencing to a slice. Writes done
+/// that way bypass dirty tracking.
+#[derive(Clone)]
+pub struct FrameBuffer {
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    data: Vec<u8>,
+    dirty: Option<DirtyRegion>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: usize, height: usize, format: PixelFormat) -> Self {
+        let mut data = vec![0; width * height * format.bytes_per_pixel()];
+
+        // Fill with opaque black so the alpha channel is valid from the start
+        if format == PixelFormat::Rgba8888 {
+            data.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 0xFF);
+        }
+
+        Self {
+            width,
+            height,
+            format,
+            data,
+            dirty: None,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Number of bytes in one line of pixels
+    pub fn pitch(&self) -> usize {
+        self.width * self.format.bytes_per_pixel()
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, r: u8, g: u8, b: u8) {
+        let bpp = self.format.bytes_per_pixel();
+        let index = (y * self.width + x) * bpp;
+        let encoded = self.format.encode(r, g, b);
+        let pixel = &mut self.data[index..index + bpp];
+
+        if pixel == &encoded[..bpp] {
+            return;
+        }
+        pixel.copy_from_slice(&encoded[..bpp]);
+        self.mark_dirty(x, y);
+    }
+
+    fn mark_dirty(&mut self, x: usize, y: usize) {
+        self.dirty = Some(match self.dirty {
+            None => DirtyRegion { x_min: x, y_min: y, x_max: x, y_max: y },
+            Some(region) => DirtyRegion {
+                x_min: region.x_min.min(x),
+                y_min: region.y_min.min(y),
+                x_max: region.x_max.max(x),
+                y_max: region.y_max.max(y),
+            },
+        });
+    }
+
+    /// Blends the first `height` lines with the previous frame, kept in `previous`, for
+    /// games relying on the blur of a TV: sprites flickering at 30 Hz to show more of them
+    /// or to fake transparency, and interlaced pictures. Each pixel keeps `weight` / 256 of
+    /// the previous frame, 128 averaging the two frames.
+    ///
+    /// `previous` must have the size and format of this buffer; it receives the unblended
+    /// lines of this frame, ready for the next one. Changed pixels are marked dirty.
+    pub fn blend_previous(&mut self, previous: &mut FrameBuffer, height: usize, weight: u8) {
+        debug_assert_eq!((previous.width, previous.format), (self.width, self.format));
+        let bpp = self.format.bytes_per_pixel();
+        let mix = |current: u8, previous: u8| {
+            let delta = (previous as i32 - current as i32) * weight as i32;
+            (current as i32 + (delta + 128).div_euclid(256)) as u8
+        };
+
+        for y in 0..height.min(self.height) {
+            for x in 0..self.width {
+                let index = (y * self.width + x) * bpp;
+                let current = &self.data[index..index + bpp];
+                let (r, g, b) = self.format.decode(current);
+                let (pr, pg, pb) = self.format.decode(&previous.data[index..index + bpp]);
+                previous.data[index..index + bpp].copy_from_slice(current);
+                self.set_pixel(x, y, mix(r, pr), mix(g, pg), mix(b, pb));
+            }
+        }
+    }
+
+    /// Copies the first `height` lines of `source` with their top-left corner at (`x`, `y`),
+    /// clipped to this buffer. Changed lines are marked dirty.
+    ///
+    /// `source` must have the format of this buffer.
+    pub fn blit(&mut self, source: &FrameBuffer, height: usize, (x, y): (usize, usize)) {
+        debug_assert_eq!(source.format, self.format);
+        let bpp = self.format.bytes_per_pixel();
+        let width = source.width.min(self.width.saturating_sub(x));
+        if width == 0 {
+            return;
+        }
+
+        for row in 0..height.min(source.height).min(self.height.saturating_sub(y)) {
+            let from = row * source.pitch();
+            let to = ((y + row) * self.width + x) * bpp;
+            let line = &source.data[from..from + width * bpp];
+            if self.data[to..to + width * bpp] != *line {
+                self.data[to..to + width * bpp].copy_from_slice(line);
+                self.mark_dirty(x, y + row);
+                self.mark_dirty(x + width - 1, y + row);
+            }
+        }
+    }
+
+    /// Region changed since the last call, `None` if nothing changed.
+    pub fn dirty_region(&self) -> Option<DirtyRegion> {
+        self.dirty
+    }
+
+    /// Returns the region changed since the last call and resets tracking.
+    pub fn take_dirty_region(&mut self) -> Option<DirtyRegion> {
+        self.dirty.take()
+    }
+}
+
+impl Deref for FrameBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl DerefMut for FrameBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ============================================================
+    // PixelFormat
+    // ============================================================
+
+    /// Each format must report its pixel size in bytes.
+    #[test]
+    fn test_bytes_per_pixel() {
+        assert_eq!(PixelFormat::Rgb888.bytes_per_pixel(), 3);
+        assert_eq!(PixelFormat::Rgba8888.bytes_per_pixel(), 4);
+        assert_eq!(PixelFormat::Rgb565.bytes_per_pixel(), 2);
+        assert_eq!(PixelFormat::Indexed.bytes_per_pixel(), 2);
+    }
+
+    /// RGB565 must keep the top 5/6/5 bits of each channel.
+    #[test]
+    fn test_encode_rgb565() {
+        assert_eq!(PixelFormat::Rgb565.encode(0xFF, 0, 0)[..2], 0xF800u16.to_le_bytes());
+        assert_eq!(PixelFormat::Rgb565.encode(0, 0xFF, 0)[..2], 0x07E0u16.to_le_bytes());
+        assert_eq!(PixelFormat::Rgb565.encode(0, 0, 0xFF)[..2], 0x001Fu16.to_le_bytes());
+    }
+
+    /// Indexed output must be the BGR555 value of the color.
+    #[test]
+    fn test_encode_indexed_is_bgr555() {
+        assert_eq!(PixelFormat::Indexed.encode(0xFF, 0, 0)[..2], 0x001Fu16.to_le_bytes());
+        assert_eq!(PixelFormat::Indexed.encode(0, 0xFF, 0)[..2], 0x03E0u16.to_le_bytes());
+        assert_eq!(PixelFormat::Indexed.encode(0, 0, 0xFF)[..2], 0x7C00u16.to_le_bytes());
+    }
+
+    /// decode must give back the color encode got, up to the precision of the format.
+    #[test]
+    fn test_decode_round_trip() {
+        for format in [PixelFormat::Rgb888, PixelFormat::Rgba8888] {
+            assert_eq!(format.decode(&format.encode(1, 2, 3)), (1, 2, 3));
+        }
+        for format in [PixelFormat::Rgb565, PixelFormat::Indexed] {
+            assert_eq!(format.decode(&format.encode(0xFF, 0, 0xFF)), (0xFF, 0, 0xFF));
+            assert_eq!(format.decode(&format.encode(0x80, 0x40, 0x10)).0, 0x84);
+        }
+    }
+
+    // ============================================================
+    // FrameBuffer
+    // ============================================================
+
+    /// A new buffer must have the right size and opaque black pixels.
+    #[test]
+    fn test_new_size_and_contents() {
+        let fb = FrameBuffer::new(4, 2, PixelFormat::Rgba8888);
+        assert_eq!(fb.len(), 4 * 2 * 4);
+        assert_eq!(fb.pitch(), 16);
+        assert!(fb.chunks_exact(4).all(|pixel| pixel == [0, 0, 0, 0xFF]));
+        assert_eq!(fb.dirty_region(), None);
+    }
+
+    /// set_pixel must encode the color at the right offset.
+    #[test]
+    fn test_set_pixel_encodes_color() {
+        let mut fb = FrameBuffer::new(4, 2, PixelFormat::Rgb888);
+        fb.set_pixel(1, 1, 1, 2, 3);
+        assert_eq!(fb[(4 + 1) * 3..(4 + 1) * 3 + 3], [1, 2, 3]);
+    }
+
+    /// The dirty region must grow to cover every changed pixel.
+    #[test]
+    fn test_dirty_region_grows() {
+        let mut fb = FrameBuffer::new(16, 16, PixelFormat::Rgb565);
+        fb.set_pixel(5, 3, 0xFF, 0, 0);
+        fb.set_pixel(2, 9, 0xFF, 0, 0);
+        assert_eq!(
+            fb.dirty_region(),
+            Some(DirtyRegion { x_min: 2, y_min: 3, x_max: 5, y_max: 9 })
+        );
+    }
+
+    /// Writing the color a pixel already has must not mark it dirty.
+    #[test]
+    fn test_unchanged_pixel_not_dirty() {
+        let mut fb = FrameBuffer::new(4, 4, PixelFormat::Rgb888);
+        fb.set_pixel(0, 0, 0, 0, 0);
+        assert_eq!(fb.dirty_region(), None);
+    }
+
+    /// take_dirty_region must return the region once and reset tracking.
+    #[test]
+    fn test_take_dirty_region_resets() {
+        let mut fb = FrameBuffer::new(4, 4, PixelFormat::Indexed);
+        fb.set_pixel(3, 3, 0xFF, 0xFF, 0xFF);
+        assert!(fb.take_dirty_region().is_some());
+        assert_eq!(fb.take_dirty_region(), None);
+    }
+
+    // ============================================================
+    // Frame blending
+    // ============================================================
+
+    /// blit must copy the first lines of the source at the given position, clipped, and only
+    /// mark changed lines dirty.
+    #[test]
+    fn test_blit() {
+        let mut source = FrameBuffer::new(2, 3, PixelFormat::Rgb888);
+        for y in 0..3 {
+            source.set_pixel(0, y, 0xFF, 0, 0);
+        }
+        let mut fb = FrameBuffer::new(4, 4, PixelFormat::Rgb888);
+        fb.blit(&source, 2, (3, 1));
+
+        let pixel = |fb: &FrameBuffer, y: usize| fb[(y * 4 + 3) * 3..][..3].to_vec();
+        assert_eq!(pixel(&fb, 1), [0xFF, 0, 0]);
+        assert_eq!(pixel(&fb, 2), [0xFF, 0, 0]);
+        assert_eq!(pixel(&fb, 3), [0, 0, 0]);
+        assert_eq!(
+            fb.take_dirty_region(),
+            Some(DirtyRegion { x_min: 3, y_min: 1, x_max: 3, y_max: 2 })
+        );
+
+        fb.blit(&source, 2, (3, 1));
+        assert_eq!(fb.take_dirty_region(), None);
+        fb.blit(&source, 3, (4, 0));
+        assert_eq!(fb.take_dirty_region(), None);
+    }
+
+    /// Blending must mix both frames and hand the unblended frame over for the next one.
+    #[test]
+    fn test_blend_previous_mixes_frames() {
+        let mut previous = FrameBuffer::new(2, 2, PixelFormat::Rgb888);
+        let mut fb = FrameBuffer::new(2, 2, PixelFormat::Rgb888);
+        fb.set_pixel(0, 0, 0xFF, 0x80, 0x00);
+        fb.take_dirty_region();
+
+        fb.blend_previous(&mut previous, 2, 128);
+        assert_eq!(fb[0..3], [0x80, 0x40, 0x00]);
+        assert_eq!(previous[0..3], [0xFF, 0x80, 0x00]);
+        assert_eq!(
+            fb.take_dirty_region(),
+            Some(DirtyRegion { x_min: 0, y_min: 0, x_max: 0, y_max: 0 })
+        );
+    }
+
+    /// A sprite flickering at 30 Hz must show as a steady half-transparent one.
+    #[test]
+    fn test_blend_previous_steadies_flicker() {
+        let mut previous = FrameBuffer::new(1, 1, PixelFormat::Rgba8888);
+        let mut fb = FrameBuffer::new(1, 1, PixelFormat::Rgba8888);
+        for frame in 0..4 {
+            let shade = if frame % 2 == 0 { 0xFE } else { 0 };
+            fb.set_pixel(0, 0, shade, shade, shade);
+            fb.blend_previous(&mut previous, 1, 128);
+            if frame > 0 {
+                assert_eq!(fb[..], [0x7F, 0x7F, 0x7F, 0xFF]);
+            }
+        }
+    }
+
+    /// Only the first `height` lines must be blended, and a weight of 0 must keep the frame.
+    #[test]
+    fn test_blend_previous_height_and_zero_weight() {
+        let mut previous = FrameBuffer::new(1, 2, PixelFormat::Rgb565);
+        previous.set_pixel(0, 1, 0xFF, 0xFF, 0xFF);
+        let mut fb = FrameBuffer::new(1, 2, PixelFormat::Rgb565);
+        fb.blend_previous(&mut previous, 1, 0xFF);
+        assert!(fb.iter().all(|&b| b == 0));
+
+        fb.set_pixel(0, 0, 0xFF, 0, 0);
+        fb.blend_previous(&mut previous, 2, 0);
+        assert_eq!(fb[0..2], 0xF800u16.to_le_bytes());
+        assert_eq!(fb[2..4], [0, 0]);
+    }
+}
@@ -1,2 +1,12 @@
+pub mod composition;
+pub mod compositor;
+pub mod frame_sink;
+pub mod framebuffer;
+pub mod overlay;
 pub mod renderer;
 pub mod mode_1;
+pub mod mode_3;
+pub mod mode_6;
+pub mod mode_7;
+pub mod offset_per_tile;
+pub mod bg_debug;
@@ -1,22 +1,32 @@
-use crate::constants::*;
+use std::ops::Range;
+
+use crate::layers::Layer;
 use crate::ppu::PPU;
 use crate::vram::RawVRAM;
 use crate::rendering::renderer::Renderer;
 
 impl Renderer {
-    pub fn render_scanline_mode1(&mut self, ppu: &PPU, y: usize) {
+    pub fn render_scanline_mode1(&mut self, ppu: &PPU, y: usize, xs: Range<usize>) {
         // VRAM word addresses
         let tilemap_base = ppu.regs.bg1_tilemap_addr(); // tilemap
         let tiledata_base = ppu.regs.bg1_tiledata_addr(); // CHR data
 
         // BG1 scroll registers
-        let scroll_x = ppu.regs.bg1hofs as usize;
-        let scroll_y = ppu.regs.bg1vofs as usize;
+        let hofs = ppu.regs.bg1hofs;
+        let vofs = ppu.regs.bg1vofs;
+        let offset_per_tile = ppu.regs.offset_per_tile();
 
-        for x in 0..SCREEN_WIDTH {
+        for x in xs {
             // ============================================================
             // Screen pixel -> tile coordinates
             // ============================================================
+            let (scroll_x, scroll_y) = if offset_per_tile {
+                Self::offset_per_tile_scroll(ppu, 1, x, hofs, vofs)
+            } else {
+                (hofs, vofs)
+            };
+            let (scroll_x, scroll_y) = (scroll_x as usize, scroll_y as usize);
+
             let px = (x + scroll_x) & 0xFF;
             let py = (y + scroll_y) & 0xFF;
 
@@ -33,7 +43,7 @@ impl Renderer {
 
             let tile_index = entry & 0x03FF; // bits 9:0
             let palette_num = (entry >> 10) & 0x07; // bits 12:10
-            let _priority = (entry & 0x2000) != 0; // bit 13
+            let priority = (entry & 0x2000) != 0; // bit 13
             let flip_x = (entry & 0x4000) != 0; // bit 14
             let flip_y = (entry & 0x8000) != 0; // bit 15
 
@@ -55,12 +65,11 @@ impl Renderer {
             let palette_entry = ((palette_num as u8) << 4) | color_index;
             let color = ppu.cgram.read(palette_entry);
 
-            let (r, g, b) = Self::apply_brightness(color, self.current_brightness as u16);
-            self.set_pixel(x, y, r, g, b);
+            self.compositor.draw(Layer::Bg1, x, color, priority as u8);
         }
     }
 
-    fn decode_4bpp_tile_pixel_from(vram: &RawVRAM, tile_word_base: usize, x: usize, y: usize) -> u8 {
+    pub(crate) fn decode_4bpp_tile_pixel_from(vram: &RawVRAM, tile_word_base: usize, x: usize, y: usize) -> u8 {
         // Planes 0+1: p0 = low byte, p1 = high byte
         let [p0, p1] = vram[tile_word_base + y].to_le_bytes();
 
@@ -78,7 +87,9 @@ impl Renderer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::SCREEN_WIDTH;
     use crate::ppu::PPU;
+    use crate::rendering::compositor::LayerPixel;
     use crate::rendering::renderer::Renderer;
 
     // ============================================================
@@ -222,7 +233,8 @@ mod tests {
     // render_scanline_mode1 - transparent pixels
     // ============================================================
 
-    /// A fully transparent tile (all zero CHR data) must leave the framebuffer unchanged.
+    /// A fully transparent tile (all zero CHR data) must leave BG1 transparent and the
+    /// framebuffer unchanged, the backdrop being drawn by the compositor.
     #[test]
     fn test_render_mode1_transparent_tile_leaves_framebuffer() {
         let mut renderer = Renderer::new();
@@ -235,20 +247,22 @@ mod tests {
         ppu.vram.memory[0] = 0x0000; // tilemap entry: tile index 0
         // CHR data for tile 0 is already all zero
 
-        renderer.render_scanline_mode1(&ppu, 0);
+        renderer.render_scanline_mode1(&ppu, 0, 0..SCREEN_WIDTH);
 
         // All pixels on scanline 0 must still be 0xAA (untouched)
         for x in 0..SCREEN_WIDTH {
             let idx = x * 3;
             assert_eq!(renderer.framebuffer[idx], 0xAA, "R changed at x={}", x);
         }
+        assert!(renderer.compositor.line(Layer::Bg1).iter().all(Option::is_none));
     }
 
     // ============================================================
     // render_scanline_mode1 - opaque pixels
     // ============================================================
 
-    /// An opaque tile pixel must write the CGRAM colour (with brightness) to the framebuffer.
+    /// An opaque tile pixel must draw the CGRAM colour into the BG1 line, and through the
+    /// compositor (with brightness) into the framebuffer.
     #[test]
     fn test_render_mode1_opaque_pixel_written() {
         let mut renderer = Renderer::new();
@@ -266,12 +280,30 @@ mod tests {
         // CGRAM palette 0 entry 1 = pure red (BGR555)
         ppu.cgram.memory[0x01] = 0x001F;
 
-        renderer.render_scanline_mode1(&ppu, 0);
+        renderer.render_scanline_mode1(&ppu, 0, 0..SCREEN_WIDTH);
+        assert_eq!(
+            renderer.compositor.line(Layer::Bg1)[0],
+            Some(LayerPixel { color: 0x001F, priority: 0 })
+        );
 
+        renderer.render_scanline(&ppu, 0);
         let (r, _g, _b) = Renderer::apply_brightness(0x001F, 15);
         assert_eq!(renderer.framebuffer[0], r);
     }
 
+    /// The tile priority bit must be passed on to the compositor.
+    #[test]
+    fn test_render_mode1_tile_priority() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode1();
+        ppu.write(0x2107, 0x04);
+        ppu.vram.memory[0x0400] = 0x2000; // tile 0, priority 1
+        ppu.vram.memory[0] = 0x00FF;
+
+        renderer.render_scanline_mode1(&ppu, 0, 0..SCREEN_WIDTH);
+        assert_eq!(renderer.compositor.line(Layer::Bg1)[0].unwrap().priority, 1);
+    }
+
     // ============================================================
     // render_scanline_mode1 - flip_x / flip_y
     // ============================================================
@@ -0,0 +1,240 @@
+use std::ops::Range;
+
+use crate::layers::Layer;
+use crate::ppu::PPU;
+use crate::vram::RawVRAM;
+use crate::rendering::renderer::Renderer;
+
+impl Renderer {
+    /// BG1 is an 8bpp layer in both modes 3 and 4, so this is used for both.
+    pub fn render_scanline_mode3(&mut self, ppu: &PPU, y: usize, xs: Range<usize>) {
+        // VRAM word addresses
+        let tilemap_base = ppu.regs.bg1_tilemap_addr(); // tilemap
+        let tiledata_base = ppu.regs.bg1_tiledata_addr(); // CHR data
+
+        // BG1 scroll registers
+        let hofs = ppu.regs.bg1hofs;
+        let vofs = ppu.regs.bg1vofs;
+        let offset_per_tile = ppu.regs.offset_per_tile();
+
+        let direct_color = ppu.regs.direct_color();
+
+        for x in xs {
+            // ============================================================
+            // Screen pixel -> tile coordinates
+            // ============================================================
+            let (scroll_x, scroll_y) = if offset_per_tile {
+                Self::offset_per_tile_scroll(ppu, 1, x, hofs, vofs)
+            } else {
+                (hofs, vofs)
+            };
+            let (scroll_x, scroll_y) = (scroll_x as usize, scroll_y as usize);
+
+            let px = (x + scroll_x) & 0xFF;
+            let py = (y + scroll_y) & 0xFF;
+
+            let tile_col = px >> 3;
+            let tile_row = py >> 3;
+            let fine_x = px & 7;
+            let fine_y = py & 7;
+
+            // ==========================================================================
+            // Read tilemap entry
+            // ==========================================================================
+            let map_word_addr = tilemap_base as usize + tile_row * 32 + tile_col;
+            let entry = ppu.vram.memory[map_word_addr];
+
+            let tile_index = entry & 0x03FF; // bits 9:0
+            let palette_num = ((entry >> 10) & 0x07) as u8; // bits 12:10, only used by direct color
+            let priority = (entry & 0x2000) != 0; // bit 13
+            let flip_x = (entry & 0x4000) != 0; // bit 14
+            let flip_y = (entry & 0x8000) != 0; // bit 15
+
+            // Apply flip
+            let fx = if flip_x { 7 - fine_x } else { fine_x };
+            let fy = if flip_y { 7 - fine_y } else { fine_y };
+
+            // ============================================================
+            // Decode 8bpp pixel from CHR data
+            // ============================================================
+            let tile_word_base = (tiledata_base as usize + tile_index as usize * 32) & 0x7FFF;
+            let color_index = Self::decode_8bpp_tile_pixel_from(&ppu.vram.memory, tile_word_base, fx, fy);
+
+            // Transparent pixel -> do nothing
+            if color_index == 0 {
+                continue;
+            }
+
+            let color = if direct_color {
+                Self::direct_color(color_index, palette_num)
+            } else {
+                ppu.cgram.read(color_index)
+            };
+
+            self.compositor.draw(Layer::Bg1, x, color, priority as u8);
+        }
+    }
+
+    fn decode_8bpp_tile_pixel_from(vram: &RawVRAM, tile_word_base: usize, x: usize, y: usize) -> u8 {
+        let bit = 7 - x;
+        let mut color_index = 0;
+
+        // Bitplanes are stored in pairs: planes 0+1 in words 0-7, 2+3 in words 8-15, etc.
+        for pair in 0..4 {
+            let [lo, hi] = vram[(tile_word_base + pair * 8 + y) & 0x7FFF].to_le_bytes();
+            color_index |= ((lo >> bit) & 1) << (pair * 2);
+            color_index |= ((hi >> bit) & 1) << (pair * 2 + 1);
+        }
+        color_index
+    }
+
+    /// Converts an 8bpp pixel to a BGR555 color when CGWSEL direct color is enabled.
+    ///
+    /// The color index is laid out as `BBGGGRRR`, and the tilemap palette bits
+    /// (`bgr`) provide the low bit of each component.
+    pub fn direct_color(color_index: u8, palette_num: u8) -> u16 {
+        let r = ((color_index & 0x07) << 2) | ((palette_num & 0x01) << 1);
+        let g = (((color_index >> 3) & 0x07) << 2) | (palette_num & 0x02);
+        let b = (((color_index >> 6) & 0x03) << 3) | (palette_num & 0x04);
+
+        (r as u16) | ((g as u16) << 5) | ((b as u16) << 10)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::SCREEN_WIDTH;
+    use crate::layers::Layer;
+    use crate::ppu::PPU;
+    use crate::rendering::renderer::Renderer;
+
+    // ============================================================
+    // Helpers
+    // ============================================================
+
+    /// Build a minimal PPU configured for mode 3 with BG1 enabled.
+    fn make_ppu_mode3() -> PPU {
+        let mut ppu = PPU::new();
+        ppu.write(0x2100, 0x0F); // no force blank, full brightness
+        ppu.write(0x2105, 0x03); // BG mode 3
+        ppu.write(0x212C, 0x01); // BG1 enabled on main screen
+        ppu.write(0x2107, 0x04); // tilemap at 0x0400, CHR data at 0x0000
+        ppu
+    }
+
+    // ============================================================
+    // decode_8bpp_tile_pixel_from
+    // ============================================================
+
+    /// All-zero tile data must decode to color index 0 (transparent).
+    #[test]
+    fn test_decode_8bpp_all_zero_is_transparent() {
+        let vram = Box::new([0; _]);
+        for y in 0..8 {
+            for x in 0..8 {
+                assert_eq!(Renderer::decode_8bpp_tile_pixel_from(&vram, 0, x, y), 0);
+            }
+        }
+    }
+
+    /// Each bitplane pair must contribute its two bits of the color index.
+    #[test]
+    fn test_decode_8bpp_each_plane() {
+        for plane in 0..8 {
+            let mut vram = Box::new([0; _]);
+            let word = (plane / 2) * 8;
+            vram[word] = if plane % 2 == 0 { 0x0080 } else { 0x8000 };
+            let idx = Renderer::decode_8bpp_tile_pixel_from(&vram, 0, 0, 0);
+            assert_eq!(idx, 1 << plane, "plane {} must set bit {}", plane, plane);
+        }
+    }
+
+    /// A tile with every bitplane set must decode to color index 255.
+    #[test]
+    fn test_decode_8bpp_all_ones_is_color_255() {
+        let mut vram = Box::new([0; _]);
+        for word in 0..32 {
+            vram[word] = 0xFFFF;
+        }
+        assert_eq!(Renderer::decode_8bpp_tile_pixel_from(&vram, 0, 3, 5), 255);
+    }
+
+    // ============================================================
+    // direct_color
+    // ============================================================
+
+    /// Color index bits must map to the upper bits of each BGR555 component.
+    #[test]
+    fn test_direct_color_index_bits() {
+        assert_eq!(Renderer::direct_color(0b00_000_111, 0), 0b11100);
+        assert_eq!(Renderer::direct_color(0b00_111_000, 0), 0b11100 << 5);
+        assert_eq!(Renderer::direct_color(0b11_000_000, 0), 0b11000 << 10);
+    }
+
+    /// Palette bits must provide the extra low bit of each component.
+    #[test]
+    fn test_direct_color_palette_bits() {
+        assert_eq!(Renderer::direct_color(0, 0b001), 0b00010);
+        assert_eq!(Renderer::direct_color(0, 0b010), 0b00010 << 5);
+        assert_eq!(Renderer::direct_color(0, 0b100), 0b00100 << 10);
+    }
+
+    /// The brightest direct color must not exceed the 15-bit color range.
+    #[test]
+    fn test_direct_color_maximum() {
+        assert_eq!(Renderer::direct_color(0xFF, 0x07), 0b11100_11110_11110);
+    }
+
+    // ============================================================
+    // render_scanline_mode3
+    // ============================================================
+
+    /// Without direct color, the 8bpp color index must address CGRAM directly.
+    #[test]
+    fn test_render_mode3_uses_cgram_index() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode3();
+
+        ppu.vram.memory[0x0400] = 0x0000; // tile 0, palette 0
+        ppu.vram.memory[24] = 0x8000; // plane 7 -> color index 0x80 at x=0
+        ppu.cgram.memory[0x80] = 0x001F;
+
+        renderer.render_scanline(&ppu, 0);
+
+        let (r, g, b) = Renderer::apply_brightness(0x001F, 15);
+        assert_eq!(&renderer.framebuffer[0..3], &[r, g, b]);
+    }
+
+    /// With direct color enabled, CGRAM must be ignored and the index used as a color.
+    #[test]
+    fn test_render_mode3_direct_color() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode3();
+        ppu.write(0x2130, 0x01); // CGWSEL direct color
+
+        ppu.vram.memory[0x0400] = 0x0400; // tile 0, palette 1
+        ppu.vram.memory[0] = 0x0080; // plane 0 -> color index 1 at x=0
+        ppu.cgram.memory[0x01] = 0x7FFF;
+
+        renderer.render_scanline_mode3(&ppu, 0, 0..SCREEN_WIDTH);
+
+        let color = renderer.compositor.line(Layer::Bg1)[0].map(|pixel| pixel.color);
+        assert_eq!(color, Some(Renderer::direct_color(1, 1)));
+    }
+
+    /// Mode 4 BG1 must also be rendered as an 8bpp layer.
+    #[test]
+    fn test_render_scanline_dispatches_mode4() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode3();
+        ppu.write(0x2105, 0x04);
+
+        ppu.vram.memory[24] = 0x8000;
+        ppu.cgram.memory[0x80] = 0x03E0;
+
+        renderer.render_scanline(&ppu, 0);
+
+        let (r, g, b) = Renderer::apply_brightness(0x03E0, 15);
+        assert_eq!(&renderer.framebuffer[0..3], &[r, g, b]);
+    }
+}
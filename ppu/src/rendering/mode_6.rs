@@ -0,0 +1,186 @@
+use std::ops::Range;
+
+use crate::layers::Layer;
+use crate::ppu::PPU;
+use crate::rendering::renderer::Renderer;
+
+impl Renderer {
+    /// Mode 6 is a 512 pixels wide (hires) mode with a single 4bpp layer, BG1, which also
+    /// supports offset-per-tile. Its tiles are 16 pixels wide: tile N on the left half and
+    /// N + 1 on the right half.
+    ///
+    /// The framebuffer is 256 pixels wide, so only the odd hires pixels, which are the ones
+    /// of the main screen, are drawn.
+    pub fn render_scanline_mode6(&mut self, ppu: &PPU, y: usize, xs: Range<usize>) {
+        // VRAM word addresses
+        let tilemap_base = ppu.regs.bg1_tilemap_addr(); // tilemap
+        let tiledata_base = ppu.regs.bg1_tiledata_addr(); // CHR data
+
+        // BG1 scroll registers
+        let hofs = ppu.regs.bg1hofs;
+        let vofs = ppu.regs.bg1vofs;
+        let offset_per_tile = ppu.regs.offset_per_tile();
+
+        for x in xs {
+            // ============================================================
+            // Screen pixel -> tile coordinates
+            // ============================================================
+            let (scroll_x, scroll_y) = if offset_per_tile {
+                Self::offset_per_tile_scroll(ppu, 1, x, hofs, vofs)
+            } else {
+                (hofs, vofs)
+            };
+            let (scroll_x, scroll_y) = (scroll_x as usize, scroll_y as usize);
+
+            // The horizontal scroll counts hires pixels two by two
+            let px = (x * 2 + 1 + scroll_x * 2) & 0x1FF;
+            let py = (y + scroll_y) & 0xFF;
+
+            let tile_col = px >> 4;
+            let tile_row = py >> 3;
+            let fine_x = px & 15;
+            let fine_y = py & 7;
+
+            // ==========================================================================
+            // Read tilemap entry
+            // ==========================================================================
+            let map_word_addr = tilemap_base as usize + tile_row * 32 + tile_col;
+            let entry = ppu.vram.memory[map_word_addr & 0x7FFF];
+
+            let tile_index = entry & 0x03FF; // bits 9:0
+            let palette_num = (entry >> 10) & 0x07; // bits 12:10
+            let priority = (entry & 0x2000) != 0; // bit 13
+            let flip_x = (entry & 0x4000) != 0; // bit 14
+            let flip_y = (entry & 0x8000) != 0; // bit 15
+
+            // Apply flip, which also swaps the two halves of the tile
+            let fx = if flip_x { 15 - fine_x } else { fine_x };
+            let fy = if flip_y { 7 - fine_y } else { fine_y };
+            let tile_index = (tile_index as usize + (fx >> 3)) & 0x03FF;
+
+            // ============================================================
+            // Decode 4bpp pixel from CHR data
+            // ============================================================
+            let tile_word_base = (tiledata_base as usize + tile_index * 16) & 0x7FFF;
+            let color_index = Self::decode_4bpp_tile_pixel_from(&ppu.vram.memory, tile_word_base, fx & 7, fy);
+
+            // Transparent pixel -> do nothing
+            if color_index == 0 {
+                continue;
+            }
+
+            let palette_entry = ((palette_num as u8) << 4) | color_index;
+            let color = ppu.cgram.read(palette_entry);
+
+            self.compositor.draw(Layer::Bg1, x, color, priority as u8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::SCREEN_WIDTH;
+    use crate::layers::Layer;
+    use crate::ppu::PPU;
+    use crate::rendering::renderer::Renderer;
+
+    // ============================================================
+    // Helpers
+    // ============================================================
+
+    /// Build a minimal PPU configured for mode 6 with BG1 enabled.
+    fn make_ppu_mode6() -> PPU {
+        let mut ppu = PPU::new();
+        ppu.write(0x2100, 0x0F); // no force blank, full brightness
+        ppu.write(0x2105, 0x06); // BG mode 6
+        ppu.write(0x212C, 0x01); // BG1 enabled on main screen
+        ppu.write(0x2107, 0x04); // tilemap at 0x0400, CHR data at 0x0000
+        ppu.write(0x2109, 0x08); // BG3 tilemap (offset-per-tile entries) at 0x0800
+        ppu
+    }
+
+    /// Colors of the BG1 line for the screen pixels `xs`.
+    fn bg1_colors(renderer: &Renderer, xs: std::ops::Range<usize>) -> Vec<Option<u16>> {
+        renderer.compositor.line(Layer::Bg1)[xs]
+            .iter()
+            .map(|pixel| pixel.map(|pixel| pixel.color))
+            .collect()
+    }
+
+    /// Tile 1 has color 1 on its odd pixels of row 0, tile 2 has color 2 on all of them.
+    fn make_tiles(ppu: &mut PPU) {
+        ppu.vram.memory[16] = 0x0055; // plane 0: pixels 1, 3, 5 and 7
+        ppu.vram.memory[32] = 0xFF00; // plane 1: every pixel
+        ppu.cgram.memory[1] = 0x001F;
+        ppu.cgram.memory[2] = 0x03E0;
+    }
+
+    // ============================================================
+    // render_scanline_mode6
+    // ============================================================
+
+    /// Mode 6 must be dispatched by the renderer instead of falling back to black.
+    #[test]
+    fn test_mode6_dispatched() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode6();
+        make_tiles(&mut ppu);
+        ppu.vram.memory[0x0400] = 0x0001;
+
+        renderer.render_scanline(&ppu, 0);
+
+        let (r, _, _) = Renderer::apply_brightness(0x001F, 15);
+        assert_eq!(renderer.framebuffer[0], r);
+    }
+
+    /// A 16 pixels wide tile must cover 8 screen pixels with tile N, then 8 with tile N + 1,
+    /// showing the odd hires pixels.
+    #[test]
+    fn test_render_mode6_wide_tiles() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode6();
+        make_tiles(&mut ppu);
+        ppu.vram.memory[0x0400] = 0x0001; // tiles 1 and 2
+
+        renderer.render_scanline_mode6(&ppu, 0, 0..SCREEN_WIDTH);
+
+        let (red, green) = (Some(0x001F), Some(0x03E0));
+        assert_eq!(bg1_colors(&renderer, 0..4), [red; 4]);
+        assert_eq!(bg1_colors(&renderer, 4..8), [green; 4]);
+        assert_eq!(bg1_colors(&renderer, 8..9), [None]);
+    }
+
+    /// flip_x must mirror the whole 16 pixels wide tile, swapping its halves.
+    #[test]
+    fn test_render_mode6_flip_x() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode6();
+        make_tiles(&mut ppu);
+        ppu.vram.memory[0x0400] = 0x4001; // tiles 1 and 2, flipped
+
+        renderer.render_scanline_mode6(&ppu, 0, 0..SCREEN_WIDTH);
+
+        // The left half shows tile 2, the right half the even (transparent) pixels of tile 1
+        assert_eq!(bg1_colors(&renderer, 0..4), [Some(0x03E0); 4]);
+        assert_eq!(bg1_colors(&renderer, 4..8), [None; 4]);
+    }
+
+    /// Offset-per-tile must apply to BG1 in mode 6.
+    #[test]
+    fn test_render_mode6_offset_per_tile() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode6();
+        make_tiles(&mut ppu);
+        ppu.vram.memory[0x0400] = 0x0004; // tilemap column 0 -> empty tiles 4 and 5
+        ppu.vram.memory[0x0400 + 2] = 0x0001; // tilemap column 2 -> tiles 1 and 2
+
+        // Screen column 1 scrolled by 8 pixels (16 hires pixels) shows tilemap column 2
+        ppu.vram.memory[0x0800] = 0x2000 | 0x0008;
+
+        renderer.render_scanline_mode6(&ppu, 0, 0..SCREEN_WIDTH);
+
+        assert_eq!(bg1_colors(&renderer, 0..8), [None; 8]); // column 0 is never offset
+        assert_eq!(bg1_colors(&renderer, 8..12), [Some(0x001F); 4]);
+        assert_eq!(bg1_colors(&renderer, 12..16), [Some(0x03E0); 4]);
+    }
+}
@@ -0,0 +1,307 @@
+use std::ops::Range;
+
+use crate::layers::Layer;
+use crate::ppu::PPU;
+use crate::rendering::renderer::Renderer;
+
+/// The mode 7 map is 128x128 tiles of 8x8 pixels
+const MAP_SIZE: i32 = 128;
+
+/// M7SEL bit 0: flip the screen horizontally
+const M7SEL_FLIP_X: u8 = 0x01;
+/// M7SEL bit 1: flip the screen vertically
+const M7SEL_FLIP_Y: u8 = 0x02;
+/// M7SEL bit 7: outside of the map is not a repeat of it
+const M7SEL_NO_REPEAT: u8 = 0x80;
+/// M7SEL bit 6: outside of the map is filled with tile 0 instead of being transparent
+const M7SEL_FILL_TILE_0: u8 = 0x40;
+
+impl Renderer {
+    /// Mode 7: BG1 is a single 1024x1024 map of 8bpp tiles, transformed by the M7A-M7D matrix
+    /// around the M7X/M7Y center.
+    ///
+    /// With SETINI EXTBG, BG2 shows the same pixels as a 7bpp layer, bit 7 of each pixel
+    /// giving its priority, which lets games put parts of the mode 7 map in front of sprites.
+    pub fn render_scanline_mode7(&mut self, ppu: &PPU, y: usize, xs: Range<usize>) {
+        let regs = &ppu.regs;
+        let [a, b, c, d] = [regs.m7a, regs.m7b, regs.m7c, regs.m7d].map(|m| m as i16 as i32);
+        let (center_x, center_y) = (Self::m7_signed(regs.m7x), Self::m7_signed(regs.m7y));
+        let (hofs, vofs) = (Self::m7_signed(regs.m7hofs), Self::m7_signed(regs.m7vofs));
+
+        let flip_x = (regs.m7sel & M7SEL_FLIP_X) != 0;
+        let screen_y = if (regs.m7sel & M7SEL_FLIP_Y) != 0 {
+            255 - y
+        } else {
+            y
+        } as i32;
+
+        // Map position of the first pixel of the line, with 8 fraction bits. The hardware
+        // drops the 6 lowest bits of each product.
+        let dx = Self::m7_clip(hofs - center_x);
+        let dy = Self::m7_clip(vofs - center_y);
+        let origin_x =
+            ((a * dx) & !63) + ((b * dy) & !63) + ((b * screen_y) & !63) + (center_x << 8);
+        let origin_y =
+            ((c * dx) & !63) + ((d * dy) & !63) + ((d * screen_y) & !63) + (center_y << 8);
+
+        let direct_color = regs.direct_color();
+        let extbg = regs.extbg();
+
+        for x in xs {
+            let screen_x = if flip_x { 255 - x } else { x } as i32;
+            let map_x = (origin_x + a * screen_x) >> 8;
+            let map_y = (origin_y + c * screen_x) >> 8;
+
+            let Some(color_index) = Self::mode7_pixel(ppu, map_x, map_y) else {
+                continue;
+            };
+
+            if color_index != 0 {
+                let color = if direct_color {
+                    Self::direct_color(color_index, 0)
+                } else {
+                    ppu.cgram.read(color_index)
+                };
+                self.compositor.draw(Layer::Bg1, x, color, 0);
+            }
+
+            // EXTBG: bits 0-6 are the color, bit 7 the priority
+            let extbg_index = color_index & 0x7F;
+            if extbg && extbg_index != 0 {
+                let color = ppu.cgram.read(extbg_index);
+                self.compositor.draw(Layer::Bg2, x, color, color_index >> 7);
+            }
+        }
+    }
+
+    /// Color index of the mode 7 map at (`map_x`, `map_y`) in pixels, `None` when outside of
+    /// the map and M7SEL makes it transparent.
+    ///
+    /// The tilemap is the low byte of the first 16K words of VRAM and the tiles are stored as
+    /// one byte per pixel in the high bytes.
+    fn mode7_pixel(ppu: &PPU, map_x: i32, map_y: i32) -> Option<u8> {
+        let outside = ((map_x | map_y) & !(MAP_SIZE * 8 - 1)) != 0;
+        let m7sel = ppu.regs.m7sel;
+
+        let tile = if !outside || (m7sel & M7SEL_NO_REPEAT) == 0 {
+            let tile_x = (map_x >> 3) & (MAP_SIZE - 1);
+            let tile_y = (map_y >> 3) & (MAP_SIZE - 1);
+            let [tile, _] = ppu.vram.memory[(tile_y * MAP_SIZE + tile_x) as usize].to_le_bytes();
+            tile
+        } else if (m7sel & M7SEL_FILL_TILE_0) != 0 {
+            0
+        } else {
+            return None;
+        };
+
+        let pixel = ((map_y & 7) * 8 + (map_x & 7)) as usize;
+        let [_, color_index] = ppu.vram.memory[tile as usize * 64 + pixel].to_le_bytes();
+        Some(color_index)
+    }
+
+    /// Mode 7 scroll and center registers are 13-bit signed values.
+    fn m7_signed(value: u16) -> i32 {
+        ((value as i32) << 19) >> 19
+    }
+
+    /// Offsets from the center wrap to 10-bit signed values before being transformed.
+    fn m7_clip(value: i32) -> i32 {
+        if (value & 0x2000) != 0 {
+            value | !0x3FF
+        } else {
+            value & 0x3FF
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::SCREEN_WIDTH;
+    use crate::layers::Layer;
+    use crate::ppu::PPU;
+    use crate::rendering::compositor::priority_order;
+    use crate::rendering::renderer::Renderer;
+
+    // ============================================================
+    // Helpers
+    // ============================================================
+
+    /// Build a PPU in mode 7 with the identity matrix and BG1/BG2/OBJ on the main screen.
+    fn make_ppu_mode7() -> PPU {
+        let mut ppu = PPU::new();
+        ppu.write(0x2100, 0x0F); // no force blank, full brightness
+        ppu.write(0x2105, 0x07); // BG mode 7
+        ppu.write(0x212C, 0x13); // BG1, BG2 and OBJ on the main screen
+        ppu.regs.m7a = 0x0100;
+        ppu.regs.m7d = 0x0100;
+        ppu
+    }
+
+    /// Sets pixel (`x`, `y`) of mode 7 tile `tile` to `color_index`.
+    fn set_tile_pixel(ppu: &mut PPU, tile: u8, x: usize, y: usize, color_index: u8) {
+        let word = &mut ppu.vram.memory[tile as usize * 64 + y * 8 + x];
+        *word = (*word & 0x00FF) | ((color_index as u16) << 8);
+    }
+
+    /// Sets map entry (`x`, `y`) to `tile`.
+    fn set_map_tile(ppu: &mut PPU, x: usize, y: usize, tile: u8) {
+        let word = &mut ppu.vram.memory[y * 128 + x];
+        *word = (*word & 0xFF00) | tile as u16;
+    }
+
+    fn color_at(renderer: &Renderer, layer: Layer, x: usize) -> Option<(u16, u8)> {
+        renderer.compositor.line(layer)[x].map(|pixel| (pixel.color, pixel.priority))
+    }
+
+    // ============================================================
+    // render_scanline_mode7 - BG1
+    // ============================================================
+
+    /// With the identity matrix, screen pixels must map to the same map pixels.
+    #[test]
+    fn test_identity_matrix() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode7();
+        set_map_tile(&mut ppu, 1, 0, 2);
+        set_tile_pixel(&mut ppu, 2, 3, 0, 0x85);
+        ppu.cgram.memory[0x85] = 0x001F;
+
+        renderer.render_scanline_mode7(&ppu, 0, 0..SCREEN_WIDTH);
+
+        assert_eq!(color_at(&renderer, Layer::Bg1, 11), Some((0x001F, 0)));
+        assert_eq!(color_at(&renderer, Layer::Bg1, 10), None);
+        assert_eq!(color_at(&renderer, Layer::Bg2, 11), None, "BG2 needs EXTBG");
+    }
+
+    /// Scrolling must move the map, and the matrix scale it around the center.
+    #[test]
+    fn test_scroll_and_scale() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode7();
+        set_map_tile(&mut ppu, 2, 0, 1);
+        set_tile_pixel(&mut ppu, 1, 0, 0, 0x01);
+        ppu.cgram.memory[0x01] = 0x03E0;
+
+        ppu.regs.m7hofs = 8;
+        renderer.render_scanline_mode7(&ppu, 0, 0..SCREEN_WIDTH);
+        assert_eq!(color_at(&renderer, Layer::Bg1, 8), Some((0x03E0, 0)));
+
+        // Twice the map pixels per screen pixel
+        renderer.compositor.clear();
+        ppu.regs.m7hofs = 0;
+        ppu.regs.m7a = 0x0200;
+        renderer.render_scanline_mode7(&ppu, 0, 0..SCREEN_WIDTH);
+        assert_eq!(color_at(&renderer, Layer::Bg1, 8), Some((0x03E0, 0)));
+        assert_eq!(color_at(&renderer, Layer::Bg1, 16), None);
+    }
+
+    /// M7SEL bit 0 must flip the line horizontally.
+    #[test]
+    fn test_flip_x() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode7();
+        set_tile_pixel(&mut ppu, 0, 0, 0, 0x01);
+        ppu.regs.m7sel = 0x01;
+
+        renderer.render_scanline_mode7(&ppu, 0, 0..SCREEN_WIDTH);
+        assert!(color_at(&renderer, Layer::Bg1, 255).is_some());
+        assert_eq!(color_at(&renderer, Layer::Bg1, 0), None);
+    }
+
+    /// Outside of the map, M7SEL must select between repeating the map, transparency and tile 0.
+    #[test]
+    fn test_screen_over() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode7();
+        set_map_tile(&mut ppu, 0, 0, 1);
+        set_tile_pixel(&mut ppu, 1, 0, 0, 0x01);
+        set_tile_pixel(&mut ppu, 0, 0, 0, 0x02);
+        ppu.cgram.memory[0x01] = 0x001F;
+        ppu.cgram.memory[0x02] = 0x7C00;
+        ppu.regs.m7hofs = 1024 - 8; // x = 8 is the first pixel past the map
+
+        for (m7sel, expected) in [(0x00, Some(0x001F)), (0x80, None), (0xC0, Some(0x7C00))] {
+            renderer.compositor.clear();
+            ppu.regs.m7sel = m7sel;
+            renderer.render_scanline_mode7(&ppu, 0, 0..SCREEN_WIDTH);
+            let color = color_at(&renderer, Layer::Bg1, 8).map(|(color, _)| color);
+            assert_eq!(color, expected, "M7SEL {:#04X}", m7sel);
+        }
+    }
+
+    /// Direct color must apply to mode 7 BG1.
+    #[test]
+    fn test_direct_color() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode7();
+        ppu.write(0x2130, 0x01);
+        set_tile_pixel(&mut ppu, 0, 0, 0, 0x07);
+
+        renderer.render_scanline_mode7(&ppu, 0, 0..SCREEN_WIDTH);
+        assert_eq!(
+            color_at(&renderer, Layer::Bg1, 0),
+            Some((Renderer::direct_color(0x07, 0), 0))
+        );
+    }
+
+    /// Mode 7 must be dispatched by render_scanline.
+    #[test]
+    fn test_render_scanline_dispatches_mode7() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode7();
+        set_tile_pixel(&mut ppu, 0, 0, 0, 0x01);
+        ppu.cgram.memory[0x01] = 0x7FFF;
+
+        renderer.render_scanline(&ppu, 0);
+        assert_eq!(&renderer.framebuffer[0..3], &[0xFF, 0xFF, 0xFF]);
+    }
+
+    // ============================================================
+    // render_scanline_mode7 - EXTBG
+    // ============================================================
+
+    /// With EXTBG, BG2 must use bits 0-6 of the pixel as its color and bit 7 as its priority.
+    #[test]
+    fn test_extbg_bg2_color_and_priority() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode7();
+        ppu.write(0x2133, 0x40);
+        set_tile_pixel(&mut ppu, 0, 0, 0, 0x81);
+        set_tile_pixel(&mut ppu, 0, 1, 0, 0x02);
+        set_tile_pixel(&mut ppu, 0, 2, 0, 0x80);
+        ppu.cgram.memory[0x01] = 0x001F;
+        ppu.cgram.memory[0x02] = 0x03E0;
+        ppu.cgram.memory[0x80] = 0x7C00;
+        ppu.cgram.memory[0x81] = 0x7FFF;
+
+        renderer.render_scanline_mode7(&ppu, 0, 0..SCREEN_WIDTH);
+
+        assert_eq!(color_at(&renderer, Layer::Bg1, 0), Some((0x7FFF, 0)));
+        assert_eq!(color_at(&renderer, Layer::Bg2, 0), Some((0x001F, 1)));
+        assert_eq!(color_at(&renderer, Layer::Bg2, 1), Some((0x03E0, 0)));
+        // Color 0 of the 7 bits is transparent for BG2 only
+        assert_eq!(color_at(&renderer, Layer::Bg1, 2), Some((0x7C00, 0)));
+        assert_eq!(color_at(&renderer, Layer::Bg2, 2), None);
+    }
+
+    /// High priority BG2 pixels must cover sprites of priority 1, low priority ones must not.
+    #[test]
+    fn test_extbg_priority_against_sprites() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_mode7();
+        ppu.write(0x2133, 0x40);
+        ppu.write(0x212C, 0x12); // BG2 and OBJ only
+        set_tile_pixel(&mut ppu, 0, 0, 0, 0x81);
+        set_tile_pixel(&mut ppu, 0, 1, 0, 0x01);
+        ppu.cgram.memory[0x01] = 0x001F;
+
+        renderer.render_scanline_mode7(&ppu, 0, 0..SCREEN_WIDTH);
+        renderer.compositor.draw(Layer::Obj, 0, 0x7C00, 1);
+        renderer.compositor.draw(Layer::Obj, 1, 0x7C00, 1);
+
+        let order = priority_order(7, false, ppu.regs.extbg());
+        let layers = ppu.regs.tm;
+        assert_eq!(renderer.compositor.pixel(0, order, layers), Some(0x001F));
+        assert_eq!(renderer.compositor.pixel(1, order, layers), Some(0x7C00));
+    }
+}
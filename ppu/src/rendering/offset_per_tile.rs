@@ -0,0 +1,151 @@
+use crate::ppu::PPU;
+use crate::rendering::renderer::Renderer;
+
+impl Renderer {
+    /// Scroll values (hofs, vofs) to use for `bg` at screen column `x` when
+    /// offset-per-tile is active (modes 2, 4 and 6).
+    ///
+    /// Each BG3 tilemap entry provides an offset for one column of 8 pixels:
+    /// bit 13 applies it to BG1 and bit 14 to BG2. Modes 2 and 6 read a
+    /// horizontal row and the vertical row right below it, while mode 4 reads
+    /// a single row where bit 15 selects whether the entry is vertical.
+    /// The leftmost (partially scrolled) column is never affected.
+    pub fn offset_per_tile_scroll(ppu: &PPU, bg: u8, x: usize, hofs: u16, vofs: u16) -> (u16, u16) {
+        let column = (x + (hofs as usize & 7)) >> 3;
+        if column == 0 {
+            return (hofs, vofs);
+        }
+
+        let enable_bit = match bg {
+            1 => 0x2000,
+            2 => 0x4000,
+            _ => return (hofs, vofs),
+        };
+
+        let map_base = ppu.regs.bg3_tilemap_addr() as usize;
+        let map_col = (((column - 1) * 8 + (ppu.regs.bg3hofs as usize & !7)) >> 3) & 0x1F;
+        let map_row = (ppu.regs.bg3vofs as usize >> 3) & 0x1F;
+        let read_entry = |row: usize| ppu.vram.memory[(map_base + row * 32 + map_col) & 0x7FFF];
+
+        let mut scroll = (hofs, vofs);
+        if ppu.regs.bg_mode() == 4 {
+            let entry = read_entry(map_row);
+            if entry & enable_bit != 0 {
+                if entry & 0x8000 != 0 {
+                    scroll.1 = entry & 0x03FF;
+                } else {
+                    scroll.0 = (entry & 0x03F8) | (hofs & 7);
+                }
+            }
+        } else {
+            let h_entry = read_entry(map_row);
+            let v_entry = read_entry((map_row + 1) & 0x1F);
+            if h_entry & enable_bit != 0 {
+                scroll.0 = (h_entry & 0x03F8) | (hofs & 7);
+            }
+            if v_entry & enable_bit != 0 {
+                scroll.1 = v_entry & 0x03FF;
+            }
+        }
+        scroll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ppu::PPU;
+    use crate::rendering::renderer::Renderer;
+
+    // ============================================================
+    // Helpers
+    // ============================================================
+
+    /// Build a PPU in the given mode with the BG3 tilemap at word 0x0800.
+    fn make_ppu_opt(mode: u8) -> PPU {
+        let mut ppu = PPU::new();
+        ppu.write(0x2105, mode);
+        ppu.write(0x2109, 0x08); // BG3SC -> 0x0800
+        ppu
+    }
+
+    // ============================================================
+    // offset_per_tile_scroll
+    // ============================================================
+
+    /// The first tile column must always use the regular scroll values.
+    #[test]
+    fn test_first_column_not_affected() {
+        let mut ppu = make_ppu_opt(2);
+        ppu.vram.memory[0x0800] = 0x2000 | 0x0040;
+        assert_eq!(Renderer::offset_per_tile_scroll(&ppu, 1, 0, 0, 0), (0, 0));
+        assert_eq!(Renderer::offset_per_tile_scroll(&ppu, 1, 7, 0, 0), (0, 0));
+    }
+
+    /// Mode 2 must take the horizontal offset from the first row, keeping the fine scroll bits.
+    #[test]
+    fn test_mode2_horizontal_offset() {
+        let mut ppu = make_ppu_opt(2);
+        ppu.vram.memory[0x0800] = 0x2000 | 0x0043; // column 1, BG1, hofs 0x40 (fine bits dropped)
+        assert_eq!(Renderer::offset_per_tile_scroll(&ppu, 1, 8, 0x0005, 0), (0x0040 | 5, 0));
+    }
+
+    /// Mode 2 must take the vertical offset from the row below the horizontal one.
+    #[test]
+    fn test_mode2_vertical_offset() {
+        let mut ppu = make_ppu_opt(2);
+        ppu.vram.memory[0x0800 + 32] = 0x2000 | 0x0123;
+        assert_eq!(Renderer::offset_per_tile_scroll(&ppu, 1, 8, 0, 0), (0, 0x0123));
+    }
+
+    /// Entries must only apply to the BG selected by bits 13 (BG1) and 14 (BG2).
+    #[test]
+    fn test_enable_bits_select_bg() {
+        let mut ppu = make_ppu_opt(2);
+        ppu.vram.memory[0x0800] = 0x4000 | 0x0080;
+        assert_eq!(Renderer::offset_per_tile_scroll(&ppu, 1, 8, 0, 0), (0, 0));
+        assert_eq!(Renderer::offset_per_tile_scroll(&ppu, 2, 8, 0, 0), (0x0080, 0));
+    }
+
+    /// Mode 4 must read a single row where bit 15 selects a vertical offset.
+    #[test]
+    fn test_mode4_single_row() {
+        let mut ppu = make_ppu_opt(4);
+        ppu.vram.memory[0x0800] = 0x2000 | 0x0080; // column 1: horizontal
+        ppu.vram.memory[0x0801] = 0xA000 | 0x0055; // column 2: vertical
+        assert_eq!(Renderer::offset_per_tile_scroll(&ppu, 1, 8, 0, 0), (0x0080, 0));
+        assert_eq!(Renderer::offset_per_tile_scroll(&ppu, 1, 16, 0, 0), (0, 0x0055));
+    }
+
+    /// The BG3 scroll position must select which tilemap column is used.
+    #[test]
+    fn test_bg3_scroll_selects_entries() {
+        let mut ppu = make_ppu_opt(2);
+        ppu.write(0x2113, 0x10); // BG3 hofs = 16 -> skip two entries
+        ppu.vram.memory[0x0802] = 0x2000 | 0x0100;
+        assert_eq!(Renderer::offset_per_tile_scroll(&ppu, 1, 8, 0, 0), (0x0100, 0));
+    }
+
+    /// Rendering BG1 in mode 2 must apply the per-column offset.
+    #[test]
+    fn test_render_mode2_applies_offsets() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_opt(2);
+        ppu.write(0x2100, 0x0F);
+        ppu.write(0x2107, 0x04); // BG1 tilemap at 0x0400
+        ppu.write(0x212C, 0x01); // BG1 on the main screen
+
+        // Tile 1 is opaque (color 1), tile 0 is transparent
+        ppu.vram.memory[16] = 0x00FF;
+        ppu.vram.memory[0x0400 + 2] = 0x0001; // tilemap column 2 -> tile 1
+        ppu.cgram.memory[1] = 0x7FFF;
+
+        // Screen column 1 scrolled by 8 pixels shows tilemap column 2
+        ppu.vram.memory[0x0800] = 0x2000 | 0x0008;
+
+        renderer.render_scanline(&ppu, 0);
+
+        let (r, _, _) = Renderer::apply_brightness(0x7FFF, 15);
+        assert_eq!(renderer.framebuffer[0], 0); // column 0 is never offset
+        assert_eq!(renderer.framebuffer[8 * 3], r); // column 1 shows tilemap column 2
+    }
+}
@@ -0,0 +1,135 @@
+use crate::layers::Layer;
+use crate::ppu::PPU;
+use crate::rendering::framebuffer::FrameBuffer;
+
+/// Draws on top of a rendered frame before it is presented, e.g. the buttons held, the
+/// frame rate or which layers are shown. Frontends keep the state they display (inputs,
+/// timings) themselves; the PPU is handed over for what only it knows.
+pub trait Overlay: Send {
+    /// Called once per frame, after rendering, with the first `height` lines of
+    /// `framebuffer` being the picture.
+    fn draw(&mut self, framebuffer: &mut FrameBuffer, height: usize, ppu: &PPU);
+}
+
+impl<F: FnMut(&mut FrameBuffer, usize, &PPU) + Send> Overlay for F {
+    fn draw(&mut self, framebuffer: &mut FrameBuffer, height: usize, ppu: &PPU) {
+        self(framebuffer, height, ppu)
+    }
+}
+
+/// Fills the `width` × `height` rectangle at (`x`, `y`), clipped to the first
+/// `max_height` lines of the framebuffer.
+pub fn fill_rect(
+    framebuffer: &mut FrameBuffer,
+    max_height: usize,
+    (x, y): (usize, usize),
+    (width, height): (usize, usize),
+    (r, g, b): (u8, u8, u8),
+) {
+    let x_end = (x + width).min(framebuffer.width());
+    let y_end = (y + height).min(max_height.min(framebuffer.height()));
+    for py in y..y_end {
+        for px in x..x_end {
+            framebuffer.set_pixel(px, py, r, g, b);
+        }
+    }
+}
+
+/// Debug overlay showing one square per layer (BG1–BG4, OBJ) in the top-left corner:
+/// white when the layer is drawn, grey when the game enables it on the main screen but
+/// it is hidden with [`PPU::set_layer_enabled`], and dark when the game disables it.
+#[derive(Default)]
+pub struct LayerIndicator;
+
+impl LayerIndicator {
+    const SIZE: usize = 4;
+    const MARGIN: usize = 2;
+
+    const SHOWN: (u8, u8, u8) = (0xFF, 0xFF, 0xFF);
+    const HIDDEN: (u8, u8, u8) = (0x80, 0x80, 0x80);
+    const DISABLED: (u8, u8, u8) = (0x20, 0x20, 0x20);
+
+    fn color(ppu: &PPU, layer: Layer) -> (u8, u8, u8) {
+        if (ppu.regs.tm & layer.bit()) == 0 {
+            Self::DISABLED
+        } else if ppu.layer_enabled(layer) {
+            Self::SHOWN
+        } else {
+            Self::HIDDEN
+        }
+    }
+}
+
+impl Overlay for LayerIndicator {
+    fn draw(&mut self, framebuffer: &mut FrameBuffer, height: usize, ppu: &PPU) {
+        for (i, layer) in Layer::ALL.into_iter().enumerate() {
+            let x = Self::MARGIN + i * (Self::SIZE + Self::MARGIN);
+            fill_rect(
+                framebuffer,
+                height,
+                (x, Self::MARGIN),
+                (Self::SIZE, Self::SIZE),
+                Self::color(ppu, layer),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::framebuffer::PixelFormat;
+
+    const BLACK: [u8; 4] = [0, 0, 0, 0xFF];
+
+    fn pixel(framebuffer: &FrameBuffer, x: usize, y: usize) -> [u8; 4] {
+        let index = (y * framebuffer.width() + x) * 4;
+        framebuffer[index..index + 4].try_into().unwrap()
+    }
+
+    // ============================================================
+    // fill_rect
+    // ============================================================
+
+    /// Rectangles must be clipped to the framebuffer width and the picture height.
+    #[test]
+    fn test_fill_rect_clipped() {
+        let mut fb = FrameBuffer::new(4, 4, PixelFormat::Rgba8888);
+        fill_rect(&mut fb, 2, (2, 1), (8, 8), (0xFF, 0, 0));
+
+        assert_eq!(pixel(&fb, 3, 1), [0xFF, 0, 0, 0xFF]);
+        assert_eq!(pixel(&fb, 1, 1), BLACK);
+        assert_eq!(pixel(&fb, 3, 2), BLACK);
+    }
+
+    // ============================================================
+    // LayerIndicator
+    // ============================================================
+
+    /// Each square must tell apart drawn, hidden and disabled layers.
+    #[test]
+    fn test_layer_indicator_colors() {
+        let mut ppu = PPU::new();
+        ppu.regs.tm = 0x03; // BG1 and BG2 on the main screen
+        ppu.set_layer_enabled(Layer::Bg2, false);
+        let mut fb = FrameBuffer::new(32, 8, PixelFormat::Rgba8888);
+        LayerIndicator.draw(&mut fb, 8, &ppu);
+
+        let square = |i: usize| pixel(&fb, 2 + i * 6, 2);
+        assert_eq!(square(0), [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(square(1), [0x80, 0x80, 0x80, 0xFF]);
+        assert_eq!(square(4), [0x20, 0x20, 0x20, 0xFF]);
+        assert_eq!(pixel(&fb, 0, 0), BLACK);
+    }
+
+    /// Closures must be usable as overlays.
+    #[test]
+    fn test_closure_overlay() {
+        let mut overlay = |fb: &mut FrameBuffer, height: usize, _: &PPU| {
+            fill_rect(fb, height, (0, 0), (1, 1), (0, 0xFF, 0));
+        };
+        let mut fb = FrameBuffer::new(2, 2, PixelFormat::Rgba8888);
+        overlay.draw(&mut fb, 2, &PPU::new());
+        assert_eq!(pixel(&fb, 0, 0), [0, 0xFF, 0, 0xFF]);
+    }
+}
@@ -1,39 +1,116 @@
+use std::ops::Range;
+
 use crate::constants::*;
 use crate::ppu::PPU;
+use crate::rendering::compositor::{Compositor, priority_order};
+use crate::rendering::framebuffer::{FrameBuffer, PixelFormat};
+use tracing::warn;
 
 pub struct Renderer {
-    pub framebuffer: Box<[u8; SCREEN_WIDTH * SCREEN_HEIGHT * 3]>,
+    /// Sized for the overscan height, only the first `active_height` lines are part of the picture
+    pub framebuffer: FrameBuffer,
     pub current_brightness: u8,
+    pub active_height: usize,
+    /// Line buffers the layers of the current scanline are drawn into
+    pub compositor: Compositor,
 
     brightness_delay: u8,
+    /// Scanline being drawn in spans by [`Self::render_until`], and the first pixel left to draw
+    line_progress: Option<(usize, usize)>,
 }
 
 impl Renderer {
     pub fn new() -> Self {
+        Self::with_pixel_format(PixelFormat::Rgb888)
+    }
+
+    pub fn with_pixel_format(format: PixelFormat) -> Self {
         Self {
-            framebuffer: Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT * 3]),
+            framebuffer: FrameBuffer::new(SCREEN_WIDTH, SCREEN_HEIGHT_OVERSCAN, format),
             current_brightness: 15, // full brightness 
+            active_height: SCREEN_HEIGHT,
+            compositor: Compositor::new(),
             brightness_delay: 0,
+            line_progress: None,
         }
     }
 
+    /// Screen pixel under the beam at `dot` of a scanline, `SCREEN_WIDTH` once the visible
+    /// part of the line is over.
+    pub fn screen_x(dot: usize) -> usize {
+        dot.saturating_sub(FIRST_VISIBLE_DOT).min(SCREEN_WIDTH)
+    }
+
+    /// Draws the rest of scanline `y`, after the pixels already drawn by [`Self::render_until`].
     pub fn render_scanline(&mut self, ppu: &PPU, y: usize) {
+        self.render_until(ppu, y, SCREEN_WIDTH);
+        self.line_progress = None;
+    }
+
+    /// Catch-up rendering: draws scanline `y` up to pixel `x_end` (excluded) with the current
+    /// PPU state, from where the previous call on the same line stopped.
+    ///
+    /// Called before a PPU register changes mid-scanline, so that the pixels before the beam
+    /// keep the old value and the ones after it get the new one, without the cost of
+    /// rendering dot by dot.
+    pub fn render_until(&mut self, ppu: &PPU, y: usize, x_end: usize) {
+        let x_start = match self.line_progress {
+            Some((line, x)) if line == y => x,
+            _ => 0,
+        };
+        let x_end = x_end.min(SCREEN_WIDTH);
+        if x_end > x_start {
+            self.render_span(ppu, y, x_start..x_end);
+            self.line_progress = Some((y, x_end));
+        }
+    }
+
+    /// Draws the pixels `xs` of scanline `y`. Per-line state (active height, brightness) is
+    /// only updated by the span starting the line.
+    fn render_span(&mut self, ppu: &PPU, y: usize, xs: Range<usize>) {
+        if xs.start == 0 {
+            self.active_height = ppu.regs.visible_scanlines() as usize;
+        }
+
         // Hardware force blank: output black
         if ppu.force_blank() {
-            self.render_full_black(y);
+            self.render_black(y, xs);
             return;
         }
 
         // Update brightness
-        self.update_brightness(ppu.brightness());
+        if xs.start == 0 {
+            self.update_brightness(ppu.brightness());
+        }
 
+        // Only BG1, and BG2 in mode 7 with EXTBG, are rendered so far
+        self.compositor.clear_span(xs.clone());
         match ppu.regs.bg_mode() {
-            1 => self.render_scanline_mode1(ppu, y),
+            1 | 2 => self.render_scanline_mode1(ppu, y, xs.clone()),
+            3 | 4 => self.render_scanline_mode3(ppu, y, xs.clone()),
+            6 => self.render_scanline_mode6(ppu, y, xs.clone()),
+            7 => self.render_scanline_mode7(ppu, y, xs.clone()),
             mode => {
-                self.render_full_black(y);
-                println!("PPU mode {} not implemented", mode);
+                self.render_black(y, xs);
+                warn!(mode, scanline = y, "PPU mode not implemented");
+                return;
             }
         }
+        self.composite_span(ppu, y, xs);
+    }
+
+    /// Draws pixels `xs` of scanline `y` from the layer lines: the main screen layers (TM,
+    /// minus the ones hidden by the frontend) over the backdrop color.
+    fn composite_span(&mut self, ppu: &PPU, y: usize, xs: Range<usize>) {
+        let order = priority_order(ppu.regs.bg_mode(), ppu.regs.bg3_priority(), ppu.regs.extbg());
+        let layers = ppu.regs.tm & ppu.layer_toggles.mask();
+        let backdrop = ppu.cgram.read(0);
+
+        for x in xs {
+            let color = self.compositor.pixel(x, order, layers).unwrap_or(backdrop);
+            let (r, g, b) = Self::apply_brightness(color, self.current_brightness as u16);
+            self.set_pixel(x, y, r, g, b);
+        }
     }
 
     fn update_brightness(&mut self, target: u8) {
@@ -72,14 +149,11 @@ impl Renderer {
     }
 
     pub fn set_pixel(&mut self, x: usize, y: usize, r: u8, g: u8, b: u8) {
-        let index = (y * SCREEN_WIDTH + x) * 3;
-        self.framebuffer[index] = r;
-        self.framebuffer[index + 1] = g;
-        self.framebuffer[index + 2] = b;
+        self.framebuffer.set_pixel(x, y, r, g, b);
     }
 
-    fn render_full_black(&mut self, y: usize) {
-        for x in 0..SCREEN_WIDTH {
+    fn render_black(&mut self, y: usize, xs: Range<usize>) {
+        for x in xs {
             self.set_pixel(x, y, 0, 0, 0);
         }
     }
@@ -88,6 +162,7 @@ impl Renderer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::layers::Layer;
 
     // ============================================================
     // Helpers
@@ -114,6 +189,51 @@ mod tests {
         assert_eq!(renderer.current_brightness, 15);
     }
 
+    // ============================================================
+    // active_height
+    // ============================================================
+
+    /// The active height must follow the SETINI overscan bit.
+    #[test]
+    fn test_active_height_follows_overscan() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_with_mode(1, false, 15);
+        renderer.render_scanline(&ppu, 0);
+        assert_eq!(renderer.active_height, SCREEN_HEIGHT);
+
+        ppu.write(0x2133, 0x04);
+        renderer.render_scanline(&ppu, 0);
+        assert_eq!(renderer.active_height, SCREEN_HEIGHT_OVERSCAN);
+    }
+
+    /// with_pixel_format must allocate the framebuffer in the requested format.
+    #[test]
+    fn test_with_pixel_format() {
+        let renderer = Renderer::with_pixel_format(PixelFormat::Rgba8888);
+        assert_eq!(renderer.framebuffer.format(), PixelFormat::Rgba8888);
+        assert_eq!(renderer.framebuffer.len(), SCREEN_WIDTH * SCREEN_HEIGHT_OVERSCAN * 4);
+    }
+
+    /// Rendering must mark the drawn scanline as dirty.
+    #[test]
+    fn test_render_scanline_marks_dirty() {
+        let mut renderer = Renderer::new();
+        renderer.framebuffer.iter_mut().for_each(|b| *b = 0xFF);
+        let ppu = make_ppu_with_mode(1, true, 15);
+        renderer.render_scanline(&ppu, 3);
+        let region = renderer.framebuffer.take_dirty_region().unwrap();
+        assert_eq!((region.y_min, region.y_max), (3, 3));
+        assert_eq!((region.x_min, region.x_max), (0, SCREEN_WIDTH - 1));
+    }
+
+    /// The framebuffer must be able to hold the extra overscan lines.
+    #[test]
+    fn test_set_pixel_on_last_overscan_line() {
+        let mut renderer = Renderer::new();
+        renderer.set_pixel(SCREEN_WIDTH - 1, SCREEN_HEIGHT_OVERSCAN - 1, 1, 2, 3);
+        assert_eq!(renderer.framebuffer[renderer.framebuffer.len() - 3..], [1, 2, 3]);
+    }
+
     // ============================================================
     // set_pixel
     // ============================================================
@@ -285,6 +405,50 @@ mod tests {
         }
     }
 
+    // ============================================================
+    // render_scanline - debug layer toggles
+    // ============================================================
+
+    /// Hiding BG1 from the frontend must output black without touching TM.
+    #[test]
+    fn test_render_scanline_hidden_bg1_outputs_black() {
+        let mut renderer = Renderer::new();
+        for b in renderer.framebuffer.iter_mut() { *b = 0xFF; }
+        let mut ppu = make_ppu_with_mode(1, false, 15);
+        ppu.write(0x212C, 0x01);
+        ppu.set_layer_enabled(Layer::Bg1, false);
+
+        renderer.render_scanline(&ppu, 0);
+
+        assert!(renderer.framebuffer[..SCREEN_WIDTH * 3].iter().all(|&b| b == 0));
+        assert_eq!(ppu.regs.tm, 0x01, "TM must be left untouched");
+    }
+
+    /// Hiding a layer that is not rendered yet must not affect BG1.
+    #[test]
+    fn test_render_scanline_hidden_other_layer_keeps_bg1() {
+        let mut hidden = Renderer::new();
+        let mut reference = Renderer::new();
+        let mut ppu = make_ppu_with_mode(1, false, 15);
+        ppu.write(0x212C, 0x01);
+        ppu.write(0x2121, 0x01);
+        ppu.write(0x2122, 0x1F);
+        ppu.write(0x2122, 0x00);
+        ppu.write(0x2115, 0x80);
+        ppu.write(0x2116, 0x00);
+        ppu.write(0x2117, 0x00);
+        ppu.write(0x2118, 0xFF); // tile 0 row 0, plane 0
+        ppu.write(0x2119, 0x00);
+
+        reference.render_scanline(&ppu, 0);
+        ppu.set_layer_enabled(Layer::Bg2, false);
+        ppu.set_layer_enabled(Layer::Obj, false);
+        hidden.render_scanline(&ppu, 0);
+
+        assert!(reference.framebuffer.iter().any(|&b| b != 0));
+        assert_eq!(&hidden.framebuffer[..], &reference.framebuffer[..]);
+    }
+
     // ============================================================
     // update_brightness (tested via render_scanline)
     // ============================================================
@@ -325,4 +489,149 @@ mod tests {
         renderer.render_scanline(&ppu, 0);
         assert_eq!(renderer.current_brightness, 14);
     }
+
+    // ============================================================
+    // render_until - catch-up rendering
+    // ============================================================
+
+    const RED: u16 = 0x001F;
+    const BLUE: u16 = 0x7C00;
+
+    fn set_backdrop(ppu: &mut PPU, color: u16) {
+        ppu.write(0x2121, 0x00);
+        ppu.write(0x2122, color as u8);
+        ppu.write(0x2122, (color >> 8) as u8);
+    }
+
+    fn pixel(renderer: &Renderer, x: usize, y: usize) -> [u8; 3] {
+        let idx = (y * SCREEN_WIDTH + x) * 3;
+        renderer.framebuffer[idx..idx + 3].try_into().unwrap()
+    }
+
+    /// Screen pixel 0 is under the beam at FIRST_VISIBLE_DOT, the line ends SCREEN_WIDTH
+    /// dots later.
+    #[test]
+    fn test_screen_x() {
+        assert_eq!(Renderer::screen_x(0), 0);
+        assert_eq!(Renderer::screen_x(FIRST_VISIBLE_DOT), 0);
+        assert_eq!(Renderer::screen_x(FIRST_VISIBLE_DOT + 100), 100);
+        assert_eq!(Renderer::screen_x(340), SCREEN_WIDTH);
+    }
+
+    /// A register changed between two spans must only apply to the pixels after it.
+    #[test]
+    fn test_render_until_mid_line_change() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_with_mode(1, false, 15);
+        set_backdrop(&mut ppu, RED);
+
+        renderer.render_until(&ppu, 0, 128);
+        set_backdrop(&mut ppu, BLUE);
+        renderer.render_scanline(&ppu, 0);
+
+        assert_eq!(pixel(&renderer, 0, 0), [255, 0, 0]);
+        assert_eq!(pixel(&renderer, 127, 0), [255, 0, 0]);
+        assert_eq!(pixel(&renderer, 128, 0), [0, 0, 255]);
+        assert_eq!(pixel(&renderer, SCREEN_WIDTH - 1, 0), [0, 0, 255]);
+    }
+
+    /// Without register changes, a line drawn in spans must match the line drawn at once.
+    #[test]
+    fn test_render_until_spans_match_whole_line() {
+        let mut spans = Renderer::new();
+        let mut reference = Renderer::new();
+        let mut ppu = make_ppu_with_mode(1, false, 15);
+        ppu.write(0x212C, 0x01);
+        ppu.write(0x210D, 0x03); // BG1HOFS = 3, tiles straddle the span edges
+        ppu.write(0x210D, 0x00);
+        ppu.vram.memory[0x0000] = 0x00A5; // tile 0 row 0, plane 0
+        ppu.cgram.memory[1] = RED;
+
+        reference.render_scanline(&ppu, 0);
+        for x_end in [1, 37, 37, 200] {
+            spans.render_until(&ppu, 0, x_end);
+        }
+        spans.render_scanline(&ppu, 0);
+
+        assert!(reference.framebuffer.iter().any(|&b| b != 0));
+        assert_eq!(&spans.framebuffer[..], &reference.framebuffer[..]);
+    }
+
+    /// A span ending before the pixels already drawn must not redraw them.
+    #[test]
+    fn test_render_until_does_not_go_back() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_with_mode(1, false, 15);
+        set_backdrop(&mut ppu, RED);
+
+        renderer.render_until(&ppu, 0, 128);
+        set_backdrop(&mut ppu, BLUE);
+        renderer.render_until(&ppu, 0, 64);
+
+        assert_eq!(pixel(&renderer, 100, 0), [255, 0, 0]);
+        assert_eq!(pixel(&renderer, 128, 0), [0, 0, 0], "not drawn yet");
+    }
+
+    /// Finishing a line must start the next one, or the same one in the next frame, from
+    /// pixel 0.
+    #[test]
+    fn test_render_scanline_ends_catch_up() {
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_with_mode(1, false, 15);
+        set_backdrop(&mut ppu, RED);
+
+        renderer.render_until(&ppu, 0, 128);
+        renderer.render_scanline(&ppu, 0);
+        set_backdrop(&mut ppu, BLUE);
+        renderer.render_until(&ppu, 0, 64);
+
+        assert_eq!(pixel(&renderer, 0, 0), [0, 0, 255]);
+        assert_eq!(pixel(&renderer, 64, 0), [255, 0, 0]);
+    }
+
+    /// Brightness must only step once per scanline, however many spans it is drawn in.
+    #[test]
+    fn test_render_until_steps_brightness_once_per_line() {
+        let mut renderer = Renderer::new();
+        let ppu = make_ppu_with_mode(1, false, 0);
+
+        // Line 0 sets the delay, line 1 steps 15 -> 14
+        for y in 0..2 {
+            renderer.render_until(&ppu, y, 100);
+            renderer.render_until(&ppu, y, 200);
+            renderer.render_scanline(&ppu, y);
+        }
+        assert_eq!(renderer.current_brightness, 14);
+    }
+
+    // ============================================================
+    // Mid-frame palette changes
+    // ============================================================
+
+    /// CGRAM is read as each scanline is drawn, so a palette entry changed between two
+    /// scanlines (e.g. by HDMA) splits the picture on that line.
+    #[test]
+    fn test_mid_frame_palette_change_splits_picture() {
+        const SPLIT_Y: u16 = 100;
+        let mut renderer = Renderer::new();
+        let mut ppu = make_ppu_with_mode(1, false, 15);
+        set_backdrop(&mut ppu, RED);
+
+        loop {
+            if ppu.scanline == SPLIT_Y {
+                set_backdrop(&mut ppu, BLUE);
+            }
+            if (ppu.scanline as usize) < ppu.regs.visible_scanlines() as usize {
+                renderer.render_scanline(&ppu, ppu.scanline as usize);
+            }
+            ppu.step_scanline();
+            if ppu.frame_ready {
+                break;
+            }
+        }
+
+        let split = SPLIT_Y as usize;
+        assert!((0..split).all(|y| pixel(&renderer, 0, y) == [255, 0, 0]));
+        assert!((split..SCREEN_HEIGHT).all(|y| pixel(&renderer, 0, y) == [0, 0, 255]));
+    }
 }
@@ -1,9 +1,12 @@
 use crate::constants::VRAM_SIZE;
 use crate::registers::PPURegisters;
+use common::power_on::MemoryInit;
+use common::save_state::{StateError, StateReader, StateWriter};
 use common::u16_split::U16Split;
 
 pub type RawVRAM = [u16; VRAM_SIZE / 2];
 
+#[derive(Clone)]
 pub struct VRAM {
     pub memory: Box<RawVRAM>, // VRAM stored as u16 words
     pub vram_latch: u16, // word latch for reads
@@ -17,6 +20,11 @@ impl VRAM {
         }
     }
 
+    /// Sets the power-on contents.
+    pub fn power_on(&mut self, init: MemoryInit) {
+        init.fill_words(&mut self.memory[..]);
+    }
+
     // ============================================================
     // Address increment logic
     // ============================================================
@@ -112,6 +120,21 @@ impl VRAM {
         }
     }
 
+    /// Write to $2118 dropped by the PPU (outside V-blank and forced blank): the
+    /// address is still incremented.
+    pub fn skip_vmdatal(&mut self, PPURegisters { vmain, vmaddl, vmaddh, .. }: &mut PPURegisters) {
+        if Self::increment_after_low(*vmain) {
+            Self::increment_vmadd(*vmain, vmaddl, vmaddh);
+        }
+    }
+
+    /// Write to $2119 dropped by the PPU, see [`Self::skip_vmdatal`].
+    pub fn skip_vmdatah(&mut self, PPURegisters { vmain, vmaddl, vmaddh, .. }: &mut PPURegisters) {
+        if Self::increment_after_high(*vmain) {
+            Self::increment_vmadd(*vmain, vmaddl, vmaddh);
+        }
+    }
+
     // ============================================================
     // VRAM DATA READ ($2139 / $213A)
     // ============================================================
@@ -163,6 +186,26 @@ impl VRAM {
     pub fn load_latch(&mut self, vmaddl: u8, vmaddh: u8) {
         self.vram_latch = self.memory[Self::vmadd(vmaddl, vmaddh) as usize];
     }
+
+    // ============================================================
+    // Save states
+    // ============================================================
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        for &word in self.memory.iter() {
+            writer.u16(word);
+        }
+        writer.u16(self.vram_latch);
+    }
+
+    /// Restores the VRAM written by [`Self::save_state`].
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        for word in self.memory.iter_mut() {
+            *word = reader.u16()?;
+        }
+        self.vram_latch = reader.u16()?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -916,4 +959,17 @@ mod tests {
         assert_eq!(vram.memory[0x0000], 0xAABB);
         assert_eq!(vram.memory[0x0001], 0xCCDD);
     }
+
+    // ============================================================
+    // Power on
+    // ============================================================
+
+    /// The power-on pattern must fill every word.
+    #[test]
+    fn test_power_on_pattern() {
+        let mut vram = VRAM::new();
+        vram.power_on(MemoryInit::Stripes);
+
+        assert!(vram.memory.iter().all(|&word| word == 0xAA55));
+    }
 }
@@ -1,12 +1,15 @@
+use common::save_state::{StateError, StateReader, StateWriter};
+
 /// Two-write latch used by registers like BG1HOFS, BG1VOFS, CGDATA.
 /// Models a hardware flipflop: first access = low byte, second = high byte.
+#[derive(Clone)]
 pub struct WriteTwice {
     latch: u8,
     pub phase: BytePhase,
 }
 
 /// Helper enum to keep track of the byte phase
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BytePhase {
     /// Next read/write affects the low byte of the addressed word
     Low,
@@ -55,6 +58,17 @@ impl WriteTwice {
     pub fn reset(&mut self) {
         self.phase = BytePhase::Low;
     }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.u8(self.latch);
+        writer.bool(self.phase.is_high());
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.latch = reader.u8()?;
+        self.phase = if reader.bool()? { BytePhase::High } else { BytePhase::Low };
+        Ok(())
+    }
 }
 
 #[cfg(test)]
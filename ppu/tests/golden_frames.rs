@@ -0,0 +1,178 @@
+/// Golden-frame regression tests
+///
+/// Loads a fixed VRAM/CGRAM fixture through the PPU ports, renders one frame
+/// headlessly in each BG mode and compares a hash of the framebuffer against
+/// checked-in values, so rendering changes can't go unnoticed.
+///
+/// When a change to the output is intended, run the tests, check the new
+/// picture with `ppu --headless`, and paste the reported hash below.
+///
+/// OAM is not part of the fixture yet: sprites are not stored nor rendered.
+
+use ppu::headless;
+use ppu::ppu::PPU;
+use ppu::rendering::renderer::Renderer;
+
+// ============================================================
+// Golden values
+// ============================================================
+
+/// Expected framebuffer hash for BG modes 0 to 7.
+/// Modes without a renderer yet (0, 5) output a black frame. The fixture
+/// leaves the mode 7 matrix at 0, so mode 7 repeats the pixel at the center.
+const GOLDEN_HASHES: [u64; 8] = [
+    0x815B_B645_BC46_A325, // mode 0 (black)
+    0xB934_7AEE_5BF2_3A4E, // mode 1
+    0x544B_7190_CB10_6751, // mode 2
+    0xC8A6_D5BB_D6A5_749F, // mode 3
+    0x210C_7497_8936_CB78, // mode 4
+    0x815B_B645_BC46_A325, // mode 5 (black)
+    0x0E12_69B0_C22E_D47A, // mode 6
+    0xD18E_0903_06C6_A325, // mode 7
+];
+
+// ============================================================
+// Helpers
+// ============================================================
+
+/// 64-bit FNV-1a, stable across platforms and Rust versions.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+/// Deterministic pseudo-random word sequence (xorshift16).
+fn fixture_words(count: usize) -> Vec<u16> {
+    let mut state: u16 = 0xACE1;
+    (0..count)
+        .map(|_| {
+            state ^= state << 7;
+            state ^= state >> 9;
+            state ^= state << 8;
+            state
+        })
+        .collect()
+}
+
+/// Fills CGRAM and VRAM, and sets up BG1-BG3 maps and character data.
+fn load_fixture(ppu: &mut PPU) {
+    // CGRAM: every entry gets a distinct BGR555 color
+    ppu.write(0x2121, 0x00);
+    for i in 0u16..256 {
+        let color = i.wrapping_mul(0x0123) & 0x7FFF;
+        ppu.write(0x2122, color as u8);
+        ppu.write(0x2122, (color >> 8) as u8);
+    }
+
+    // VRAM: character data in the low half, tile maps with small tile
+    // numbers (and random palette/priority/flip bits) in the high half
+    ppu.write(0x2115, 0x80);
+    ppu.write(0x2116, 0x00);
+    ppu.write(0x2117, 0x00);
+    for (i, word) in fixture_words(0x8000).into_iter().enumerate() {
+        let word = if i >= 0x4000 { word & 0xFC3F } else { word };
+        ppu.write(0x2118, word as u8);
+        ppu.write(0x2119, (word >> 8) as u8);
+    }
+
+    ppu.write(0x2107, 0x40); // BG1SC: map at word $4000
+    ppu.write(0x2108, 0x48); // BG2SC: map at word $4800
+    ppu.write(0x2109, 0x50); // BG3SC: map at word $5000
+    ppu.write(0x210B, 0x00); // BG12NBA: character data at word $0000
+    ppu.write(0x210C, 0x00); // BG34NBA: character data at word $0000
+
+    // Scroll BG1 so wrapping is part of the picture
+    ppu.write(0x210D, 0x13);
+    ppu.write(0x210D, 0x00);
+    ppu.write(0x210E, 0x07);
+    ppu.write(0x210E, 0x00);
+
+    ppu.write(0x212C, 0x1F); // TM: every layer on the main screen
+    ppu.write(0x2100, 0x0F); // INIDISP: display on, max brightness
+}
+
+fn render_mode(mode: u8) -> u64 {
+    let mut ppu = PPU::new();
+    let mut renderer = Renderer::new();
+    load_fixture(&mut ppu);
+    ppu.write(0x2105, mode);
+
+    headless::run_frame(&mut ppu, &mut renderer);
+    fnv1a(&renderer.framebuffer[..renderer.framebuffer.pitch() * renderer.active_height])
+}
+
+fn assert_golden(mode: u8) {
+    let hash = render_mode(mode);
+    assert_eq!(
+        hash, GOLDEN_HASHES[mode as usize],
+        "mode {} output changed, new hash: {:#018X}",
+        mode, hash
+    );
+}
+
+// ============================================================
+// Harness
+// ============================================================
+
+/// The same fixture must always render to the same picture.
+#[test]
+fn test_rendering_is_deterministic() {
+    assert_eq!(render_mode(1), render_mode(1));
+}
+
+/// The fixture must produce a picture that is not all black.
+#[test]
+fn test_fixture_is_visible() {
+    let mut ppu = PPU::new();
+    let mut renderer = Renderer::new();
+    load_fixture(&mut ppu);
+    ppu.write(0x2105, 0x01);
+
+    headless::run_frame(&mut ppu, &mut renderer);
+    assert!(renderer.framebuffer.iter().any(|&b| b != 0));
+}
+
+// ============================================================
+// BG modes
+// ============================================================
+
+#[test]
+fn test_golden_mode_0() {
+    assert_golden(0);
+}
+
+#[test]
+fn test_golden_mode_1() {
+    assert_golden(1);
+}
+
+#[test]
+fn test_golden_mode_2() {
+    assert_golden(2);
+}
+
+#[test]
+fn test_golden_mode_3() {
+    assert_golden(3);
+}
+
+#[test]
+fn test_golden_mode_4() {
+    assert_golden(4);
+}
+
+#[test]
+fn test_golden_mode_5() {
+    assert_golden(5);
+}
+
+#[test]
+fn test_golden_mode_6() {
+    assert_golden(6);
+}
+
+#[test]
+fn test_golden_mode_7() {
+    assert_golden(7);
+}
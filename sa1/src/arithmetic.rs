@@ -0,0 +1,153 @@
+/// Result of a cumulative sum is 40 bits wide
+const SUM_MASK: u64 = (1 << 40) - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArithmeticMode {
+    #[default]
+    Multiply,
+    Divide,
+    CumulativeSum,
+}
+
+/// Multiplication, division and multiply-accumulate unit (`$2250–$2254`, `$2306–$230B`).
+///
+/// The operation runs when the high byte of MB is written.
+#[derive(Debug, Clone, Default)]
+pub struct Arithmetic {
+    pub mode: ArithmeticMode,
+    pub ma: u16,
+    pub mb: u16,
+    /// MR, 40 bits
+    pub result: u64,
+    pub overflow: bool,
+}
+
+impl Arithmetic {
+    /// MCNT: bit 1 selects the cumulative sum (clearing the result), bit 0 division.
+    pub fn set_control(&mut self, value: u8) {
+        self.mode = if value & 0x02 != 0 {
+            self.result = 0;
+            self.overflow = false;
+            ArithmeticMode::CumulativeSum
+        } else if value & 0x01 != 0 {
+            ArithmeticMode::Divide
+        } else {
+            ArithmeticMode::Multiply
+        };
+    }
+
+    pub fn set_ma(&mut self, high: bool, value: u8) {
+        set_byte(&mut self.ma, high, value);
+    }
+
+    pub fn set_mb(&mut self, high: bool, value: u8) {
+        set_byte(&mut self.mb, high, value);
+        if high {
+            self.execute();
+        }
+    }
+
+    fn execute(&mut self) {
+        match self.mode {
+            ArithmeticMode::Multiply => {
+                self.result = (self.ma as i16 as i32 * self.mb as i16 as i32) as u32 as u64;
+            }
+            ArithmeticMode::Divide => {
+                // Signed dividend, unsigned divisor, positive remainder
+                self.result = if self.mb == 0 {
+                    0
+                } else {
+                    let dividend = self.ma as i16 as i32;
+                    let divisor = self.mb as i32;
+                    let remainder = dividend.rem_euclid(divisor);
+                    let quotient = (dividend - remainder) / divisor;
+                    ((remainder as u16 as u64) << 16) | quotient as u16 as u64
+                };
+                self.ma = 0;
+            }
+            ArithmeticMode::CumulativeSum => {
+                let product = self.ma as i16 as i64 * self.mb as i16 as i64;
+                let sum = self.result.wrapping_add(product as u64);
+                self.overflow = sum > SUM_MASK;
+                self.result = sum & SUM_MASK;
+            }
+        }
+        self.mb = 0;
+    }
+
+    /// Byte `index` (0–4) of MR
+    pub fn result_byte(&self, index: u16) -> u8 {
+        (self.result >> (index * 8)) as u8
+    }
+}
+
+fn set_byte(word: &mut u16, high: bool, value: u8) {
+    *word = if high {
+        (*word & 0x00FF) | (value as u16) << 8
+    } else {
+        (*word & 0xFF00) | value as u16
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(arithmetic: &mut Arithmetic, ma: i16, mb: u16) {
+        let [ma_lo, ma_hi] = ma.to_le_bytes();
+        let [mb_lo, mb_hi] = mb.to_le_bytes();
+        arithmetic.set_ma(false, ma_lo);
+        arithmetic.set_ma(true, ma_hi);
+        arithmetic.set_mb(false, mb_lo);
+        arithmetic.set_mb(true, mb_hi);
+    }
+
+    #[test]
+    fn test_signed_multiply() {
+        let mut arithmetic = Arithmetic::default();
+        run(&mut arithmetic, -3, 1000);
+        assert_eq!(arithmetic.result, (-3000i32) as u32 as u64);
+        assert_eq!(arithmetic.mb, 0);
+        assert_eq!(arithmetic.result_byte(0), 0x48);
+        assert_eq!(arithmetic.result_byte(3), 0xFF);
+    }
+
+    #[test]
+    fn test_divide() {
+        let mut arithmetic = Arithmetic::default();
+        arithmetic.set_control(0x01);
+
+        run(&mut arithmetic, 100, 7);
+        assert_eq!(arithmetic.result, (2 << 16) | 14);
+
+        // -7 = -3 * 3 + 2
+        run(&mut arithmetic, -7, 3);
+        assert_eq!(arithmetic.result, (2 << 16) | 0xFFFD);
+
+        run(&mut arithmetic, 5, 0);
+        assert_eq!(arithmetic.result, 0);
+    }
+
+    #[test]
+    fn test_cumulative_sum() {
+        let mut arithmetic = Arithmetic::default();
+        arithmetic.result = 1234;
+        arithmetic.set_control(0x02);
+        assert_eq!(arithmetic.result, 0);
+
+        run(&mut arithmetic, 100, 200);
+        run(&mut arithmetic, -50, 10);
+        assert_eq!(arithmetic.result, 19500);
+        assert!(!arithmetic.overflow);
+    }
+
+    #[test]
+    fn test_cumulative_sum_overflow() {
+        let mut arithmetic = Arithmetic::default();
+        arithmetic.set_control(0x02);
+        arithmetic.result = SUM_MASK;
+        run(&mut arithmetic, 1, 1);
+        assert_eq!(arithmetic.result, 0);
+        assert!(arithmetic.overflow);
+    }
+}
@@ -0,0 +1,87 @@
+/// Variable-length bit data reader (`$2258–$225B`, `$230C–$230D`), used to decode
+/// bit-packed data in ROM.
+#[derive(Debug, Clone, Default)]
+pub struct BitStream {
+    /// VDA: ROM address of the current byte
+    pub address: u32,
+    /// Position of the next bit in the current byte
+    pub bit: u32,
+    /// VBD bit 7: advance after each read of `$230D` instead of each VBD write
+    pub auto_increment: bool,
+    /// Number of bits consumed per step, 1–16
+    pub length: u32,
+}
+
+impl BitStream {
+    /// VBD: sets the mode and length; in fixed mode, also advances the stream.
+    pub fn set_control(&mut self, value: u8) {
+        self.auto_increment = value & 0x80 != 0;
+        self.length = match value & 0x0F {
+            0 => 16,
+            length => length as u32,
+        };
+        if !self.auto_increment {
+            self.advance();
+        }
+    }
+
+    /// Writes byte `index` (0–2) of VDA; writing the bank restarts at bit 0.
+    pub fn set_address(&mut self, index: u16, value: u8) {
+        let shift = index * 8;
+        self.address = (self.address & !(0xFF << shift)) | (value as u32) << shift;
+        if index == 2 {
+            self.bit = 0;
+        }
+    }
+
+    pub fn advance(&mut self) {
+        self.bit += self.length;
+        self.address = (self.address + (self.bit >> 3)) & 0xFF_FFFF;
+        self.bit &= 7;
+    }
+
+    /// 16 bits starting at the current position, from the 3 bytes at [`Self::address`].
+    pub fn value(&self, bytes: [u8; 3]) -> u16 {
+        let data = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+        (data >> self.bit) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_mode_advances_on_control_write() {
+        let mut bits = BitStream::default();
+        bits.set_address(0, 0x00);
+        bits.set_address(1, 0x80);
+        bits.set_address(2, 0x01);
+        assert_eq!(bits.address, 0x01_8000);
+
+        let bytes = [0b1010_1100, 0xFF, 0x00];
+        assert_eq!(bits.value(bytes), 0xFFAC);
+
+        bits.set_control(0x03);
+        assert_eq!((bits.address, bits.bit), (0x01_8000, 3));
+        assert_eq!(bits.value(bytes), 0x1FF5);
+
+        bits.set_control(0x06);
+        assert_eq!((bits.address, bits.bit), (0x01_8001, 1));
+    }
+
+    #[test]
+    fn test_auto_increment_and_restart() {
+        let mut bits = BitStream::default();
+        bits.set_control(0x80);
+        assert_eq!(bits.length, 16);
+        assert_eq!(bits.bit, 0);
+
+        bits.advance();
+        assert_eq!(bits.address, 2);
+
+        bits.bit = 5;
+        bits.set_address(2, 0x00);
+        assert_eq!(bits.bit, 0);
+    }
+}
@@ -0,0 +1,226 @@
+/// DMA and character conversion registers (`$2230–$2239`, `$223F–$224F`).
+///
+/// Normal DMA copies between ROM, BW-RAM and I-RAM instantly. Character conversion turns
+/// packed bitmaps into SNES planar tiles: type 1 converts a BW-RAM bitmap while the SNES
+/// DMA reads it, type 2 converts the rows of pixels written by the SA-1 to the bitmap
+/// register file into a 2-tile I-RAM buffer.
+#[derive(Debug, Clone, Default)]
+pub struct Dma {
+    /// DCNT
+    pub control: u8,
+    /// CDMA
+    pub conversion: u8,
+    /// SDA, 24 bits
+    pub source: u32,
+    /// DDA, 24 bits
+    pub destination: u32,
+    /// DTC
+    pub length: u16,
+    /// BRF: two rows of 8 pixels, one byte each
+    pub bitmap_registers: [u8; 16],
+    /// Row of the type 2 buffer written next, 0–15
+    pub line: u8,
+    /// Type 1 conversion running, until the SNES ends it through CDMA
+    pub type1_active: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmaSource {
+    Rom,
+    Bwram,
+    Iram,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmaDestination {
+    Iram,
+    Bwram,
+}
+
+impl Dma {
+    pub fn set_control(&mut self, value: u8) {
+        self.control = value;
+        if !self.enabled() {
+            self.line = 0;
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.control & 0x80 != 0
+    }
+
+    pub fn character_conversion(&self) -> bool {
+        self.control & 0x20 != 0
+    }
+
+    /// Type 1 (BW-RAM bitmap read by the SNES) rather than type 2 (bitmap registers)
+    pub fn is_type1(&self) -> bool {
+        self.control & 0x10 != 0
+    }
+
+    pub fn source_device(&self) -> DmaSource {
+        match self.control & 0x03 {
+            0 => DmaSource::Rom,
+            1 => DmaSource::Bwram,
+            _ => DmaSource::Iram,
+        }
+    }
+
+    pub fn destination_device(&self) -> DmaDestination {
+        if self.control & 0x04 != 0 {
+            DmaDestination::Bwram
+        } else {
+            DmaDestination::Iram
+        }
+    }
+
+    /// Bits per pixel of the converted tiles: 8, 4 or 2
+    pub fn bpp(&self) -> usize {
+        match self.conversion & 0x03 {
+            0 => 8,
+            1 => 4,
+            _ => 2,
+        }
+    }
+
+    /// Width of the type 1 bitmap in tiles, as a power of 2 (1 to 32 tiles)
+    pub fn width_shift(&self) -> usize {
+        (((self.conversion >> 2) & 0x07) as usize).min(5)
+    }
+
+    pub fn set_source(&mut self, index: u16, value: u8) {
+        set_byte(&mut self.source, index, value);
+    }
+
+    pub fn set_destination(&mut self, index: u16, value: u8) {
+        set_byte(&mut self.destination, index, value);
+    }
+
+    /// Byte `index` of the tiles converted from the bitmap at `source` in `bwram` (type 1).
+    pub fn type1_byte(&self, bwram: &[u8], source: usize, index: usize) -> u8 {
+        let bpp = self.bpp();
+        let tile = index / (8 * bpp);
+        let byte = index % (8 * bpp);
+        let width = 1 << self.width_shift();
+        let (tile_x, tile_y) = (tile % width, tile / width);
+        let bytes_per_line = width * bpp;
+
+        let (row, plane) = planar_position(byte);
+        let line = source + (tile_y * 8 + row) * bytes_per_line + tile_x * bpp;
+        (0..8).fold(0, |out, x| {
+            let bit = x * bpp;
+            let packed = bwram[(line + bit / 8) % bwram.len()];
+            let pixel = packed >> (bit % 8);
+            out | ((pixel >> plane) & 1) << (7 - x)
+        })
+    }
+
+    /// Converts the row of bitmap registers just completed into the I-RAM buffer (type 2).
+    pub fn convert_type2_row(&mut self, iram: &mut [u8]) {
+        let bpp = self.bpp();
+        let tile_size = 8 * bpp;
+        let row = &self.bitmap_registers[(self.line as usize & 1) * 8..][..8];
+
+        // Buffer of 2 tiles at DDA, rows 8–15 going to the second tile
+        let mut addr = self.destination as usize & (iram.len() - 1) & !(2 * tile_size - 1);
+        addr += (self.line as usize & 8) / 8 * tile_size + (self.line as usize & 7) * 2;
+
+        for plane in 0..bpp {
+            let out = (0..8).fold(0, |out, x| out | ((row[x] >> plane) & 1) << (7 - x));
+            iram[(addr + (plane & 6) * 8 + (plane & 1)) % iram.len()] = out;
+        }
+        self.line = (self.line + 1) & 15;
+    }
+}
+
+/// Row and bit plane stored at byte `byte` of a planar SNES tile
+fn planar_position(byte: usize) -> (usize, usize) {
+    ((byte % 16) / 2, (byte / 16) * 2 + (byte & 1))
+}
+
+fn set_byte(word: &mut u32, index: u16, value: u8) {
+    let shift = index * 8;
+    *word = (*word & !(0xFF << shift)) | (value as u32) << shift;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_decoding() {
+        let mut dma = Dma::default();
+        dma.set_control(0b1000_0110);
+        assert!(dma.enabled());
+        assert!(!dma.character_conversion());
+        assert_eq!(dma.source_device(), DmaSource::Iram);
+        assert_eq!(dma.destination_device(), DmaDestination::Bwram);
+
+        dma.set_control(0b1011_0001);
+        assert!(dma.character_conversion());
+        assert!(dma.is_type1());
+        assert_eq!(dma.source_device(), DmaSource::Bwram);
+    }
+
+    #[test]
+    fn test_type1_2bpp_tile() {
+        let mut dma = Dma::default();
+        dma.conversion = 0x02; // 2bpp, 1 tile wide
+
+        // Each line of the 1-tile bitmap is 2 bytes: pixels 0-3 then 4-7, low bits first
+        let mut bwram = vec![0; 0x100];
+        bwram[0] = 0b11_10_01_00; // pixels 0..3 = 0, 1, 2, 3
+        bwram[1] = 0b00_00_00_11; // pixel 4 = 3
+
+        // Row 0, plane 0: pixels 1, 3 and 4
+        assert_eq!(dma.type1_byte(&bwram, 0, 0), 0b0101_1000);
+        // Row 0, plane 1: pixels 2, 3 and 4
+        assert_eq!(dma.type1_byte(&bwram, 0, 1), 0b0011_1000);
+        // Row 1 is empty
+        assert_eq!(dma.type1_byte(&bwram, 0, 2), 0);
+    }
+
+    #[test]
+    fn test_type1_second_tile_of_wide_bitmap() {
+        let mut dma = Dma::default();
+        dma.conversion = 0x02 | (1 << 2); // 2bpp, 2 tiles wide
+
+        let mut bwram = vec![0; 0x100];
+        bwram[2] = 0x01; // First pixel of the second tile, line 0
+
+        // Second tile starts at byte 16
+        assert_eq!(dma.type1_byte(&bwram, 0, 16), 0x80);
+        assert_eq!(dma.type1_byte(&bwram, 0, 0), 0x00);
+    }
+
+    #[test]
+    fn test_type2_rows() {
+        let mut dma = Dma::default();
+        dma.conversion = 0x01; // 4bpp
+        dma.destination = 0x0100;
+        let mut iram = vec![0; 0x800];
+
+        dma.bitmap_registers[..8].copy_from_slice(&[0xF, 0, 0, 0, 0, 0, 0, 0x5]);
+        dma.convert_type2_row(&mut iram);
+        // Planes 0 and 1 of row 0, then planes 2 and 3 at +16
+        assert_eq!(&iram[0x100..0x102], &[0x81, 0x80]);
+        assert_eq!(&iram[0x110..0x112], &[0x81, 0x80]);
+
+        dma.bitmap_registers[8..].copy_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0x1]);
+        dma.convert_type2_row(&mut iram);
+        assert_eq!(iram[0x102], 0x01);
+        assert_eq!(dma.line, 2);
+    }
+
+    #[test]
+    fn test_type2_second_tile() {
+        let mut dma = Dma::default();
+        dma.conversion = 0x02; // 2bpp
+        dma.line = 8;
+        let mut iram = vec![0; 0x800];
+
+        dma.bitmap_registers[..8].copy_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        dma.convert_type2_row(&mut iram);
+        assert_eq!(iram[16], 0x80);
+    }
+}
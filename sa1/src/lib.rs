@@ -0,0 +1,7 @@
+pub mod arithmetic;
+pub mod bit_stream;
+pub mod dma;
+pub mod mapping;
+pub mod sa1;
+
+pub use sa1::Sa1;
@@ -0,0 +1,141 @@
+use common::snes_address::SnesAddress;
+
+/// Size of a ROM block selectable by the super MMC
+pub const ROM_BLOCK_SIZE: usize = 0x10_0000;
+/// Size of a BW-RAM block mapped at `$6000–$7FFF`
+pub const BWRAM_BLOCK_SIZE: usize = 0x2000;
+
+/// Memory mapping controller: selects the 1 MiB ROM blocks seen in each quarter of the
+/// address space, and the BW-RAM blocks mapped at `$6000–$7FFF` for each CPU.
+#[derive(Debug, Clone, Default)]
+pub struct Mmc {
+    /// CXB, DXB, EXB and FXB (`$2220–$2223`): block in bits 0–2, bit 7 applies
+    /// the block to the LoROM banks too
+    pub rom_banks: [u8; 4],
+    /// BMAPS (`$2224`): BW-RAM block seen by the SNES
+    pub snes_bwram_block: u8,
+    /// BMAP (`$2225`): BW-RAM block seen by the SA-1, bit 7 selecting the bitmap view
+    pub sa1_bwram_block: u8,
+}
+
+impl Mmc {
+    pub fn new() -> Self {
+        Self {
+            rom_banks: [0, 1, 2, 3],
+            ..Self::default()
+        }
+    }
+
+    /// ROM offset of `addr`, in the LoROM banks `$00–$3F`/`$80–$BF:$8000–$FFFF`
+    /// or the HiROM banks `$C0–$FF`.
+    pub fn rom_offset(&self, addr: SnesAddress) -> Option<usize> {
+        match (addr.bank, addr.addr) {
+            (0x00..=0x3F | 0x80..=0xBF, 0x8000..=0xFFFF) => {
+                let quarter = ((addr.bank >> 5) & 1 | (addr.bank >> 6) & 2) as usize;
+                let register = self.rom_banks[quarter];
+                let block = if register & 0x80 != 0 {
+                    (register & 0x07) as usize
+                } else {
+                    quarter
+                };
+                let bank = (addr.bank & 0x1F) as usize;
+                Some(block * ROM_BLOCK_SIZE + bank * 0x8000 + (addr.addr & 0x7FFF) as usize)
+            }
+            (0xC0..=0xFF, _) => {
+                let block = (self.rom_banks[((addr.bank >> 4) & 3) as usize] & 0x07) as usize;
+                let bank = (addr.bank & 0x0F) as usize;
+                Some(block * ROM_BLOCK_SIZE + (bank << 16 | addr.addr as usize))
+            }
+            _ => None,
+        }
+    }
+
+    /// BW-RAM offset of an SNES access to `$6000–$7FFF`.
+    pub fn snes_bwram_offset(&self, addr: u16) -> usize {
+        (self.snes_bwram_block & 0x1F) as usize * BWRAM_BLOCK_SIZE + (addr & 0x1FFF) as usize
+    }
+
+    /// Whether the SA-1 sees the bitmap view of BW-RAM at `$6000–$7FFF`.
+    pub fn sa1_bwram_bitmap(&self) -> bool {
+        self.sa1_bwram_block & 0x80 != 0
+    }
+
+    /// BW-RAM offset (or bitmap pixel index) of an SA-1 access to `$6000–$7FFF`.
+    pub fn sa1_bwram_offset(&self, addr: u16) -> usize {
+        let mask = if self.sa1_bwram_bitmap() { 0x7F } else { 0x1F };
+        (self.sa1_bwram_block & mask) as usize * BWRAM_BLOCK_SIZE + (addr & 0x1FFF) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::snes_address::snes_addr;
+
+    #[test]
+    fn test_default_lorom_blocks() {
+        let mmc = Mmc::new();
+        assert_eq!(mmc.rom_offset(snes_addr!(0x00:0x8000)), Some(0));
+        assert_eq!(mmc.rom_offset(snes_addr!(0x01:0x8000)), Some(0x8000));
+        assert_eq!(
+            mmc.rom_offset(snes_addr!(0x20:0x8000)),
+            Some(ROM_BLOCK_SIZE)
+        );
+        assert_eq!(
+            mmc.rom_offset(snes_addr!(0x80:0x8000)),
+            Some(2 * ROM_BLOCK_SIZE)
+        );
+        assert_eq!(
+            mmc.rom_offset(snes_addr!(0xBF:0xFFFF)),
+            Some(4 * ROM_BLOCK_SIZE - 1)
+        );
+        assert_eq!(mmc.rom_offset(snes_addr!(0x00:0x7FFF)), None);
+    }
+
+    #[test]
+    fn test_lorom_block_applied_with_bit7() {
+        let mut mmc = Mmc::new();
+        mmc.rom_banks[0] = 0x05;
+        assert_eq!(mmc.rom_offset(snes_addr!(0x00:0x8000)), Some(0));
+
+        mmc.rom_banks[0] = 0x85;
+        assert_eq!(
+            mmc.rom_offset(snes_addr!(0x00:0x8000)),
+            Some(5 * ROM_BLOCK_SIZE)
+        );
+    }
+
+    #[test]
+    fn test_hirom_blocks() {
+        let mut mmc = Mmc::new();
+        assert_eq!(mmc.rom_offset(snes_addr!(0xC0:0x0000)), Some(0));
+        assert_eq!(
+            mmc.rom_offset(snes_addr!(0xCF:0xFFFF)),
+            Some(ROM_BLOCK_SIZE - 1)
+        );
+        assert_eq!(
+            mmc.rom_offset(snes_addr!(0xF0:0x1234)),
+            Some(3 * ROM_BLOCK_SIZE + 0x1234)
+        );
+
+        mmc.rom_banks[3] = 0x06;
+        assert_eq!(
+            mmc.rom_offset(snes_addr!(0xF1:0x0000)),
+            Some(6 * ROM_BLOCK_SIZE + 0x10000)
+        );
+    }
+
+    #[test]
+    fn test_bwram_blocks() {
+        let mut mmc = Mmc::new();
+        mmc.snes_bwram_block = 0x03;
+        mmc.sa1_bwram_block = 0x02;
+        assert_eq!(mmc.snes_bwram_offset(0x6010), 3 * BWRAM_BLOCK_SIZE + 0x10);
+        assert_eq!(mmc.sa1_bwram_offset(0x7FFF), 3 * BWRAM_BLOCK_SIZE - 1);
+        assert!(!mmc.sa1_bwram_bitmap());
+
+        mmc.sa1_bwram_block = 0xA0;
+        assert!(mmc.sa1_bwram_bitmap());
+        assert_eq!(mmc.sa1_bwram_offset(0x6000), 0x20 * BWRAM_BLOCK_SIZE);
+    }
+}
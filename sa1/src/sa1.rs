@@ -0,0 +1,1118 @@
+use crate::arithmetic::{Arithmetic, ArithmeticMode};
+use crate::bit_stream::BitStream;
+use crate::dma::{Dma, DmaDestination, DmaSource};
+use crate::mapping::Mmc;
+use bus::coprocessor::Coprocessor;
+use bus::rom::Rom;
+use common::save_state::{StateError, StateReader, StateWriter};
+use common::snes_address::SnesAddress;
+use cpu::cpu::{CPU, CycleResult};
+
+pub const IRAM_SIZE: usize = 0x800;
+/// The SA-1 runs at 10.74 MHz, half the master clock
+const MASTER_CYCLES_PER_CYCLE: u32 = 2;
+/// CCNT at power-on: the SA-1 is held in reset until the SNES releases it
+const CCNT_POWERON: u8 = 0x20;
+
+/// Interrupt flags, as laid out in SFR/CFR and the enable/clear registers
+const IRQ: u8 = 0x80;
+const TIMER_IRQ: u8 = 0x40;
+const DMA_IRQ: u8 = 0x20;
+const NMI: u8 = 0x10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Snes,
+    Sa1,
+}
+
+/// Interrupt and message registers shared by both CPUs.
+#[derive(Debug, Clone, Default)]
+struct Control {
+    /// CCNT (`$2200`): SA-1 IRQ/ready/reset/NMI and message to the SA-1
+    ccnt: u8,
+    /// SIE (`$2201`): interrupts the SA-1 may raise on the SNES
+    snes_irq_enable: u8,
+    /// SCNT (`$2209`): SNES IRQ, vector overrides and message to the SNES
+    scnt: u8,
+    /// CIE (`$220A`): interrupts the SNES may raise on the SA-1
+    sa1_irq_enable: u8,
+    /// Pending interrupts read in SFR (`$2300`)
+    snes_flags: u8,
+    /// Pending interrupts read in CFR (`$2301`)
+    sa1_flags: u8,
+    /// CRV, CNV, CIV: SA-1 reset, NMI and IRQ vectors
+    sa1_vectors: [u16; 3],
+    /// SNV, SIV: SNES NMI and IRQ vectors, used when enabled in SCNT
+    snes_vectors: [u16; 2],
+}
+
+/// Write protection registers.
+#[derive(Debug, Clone, Default)]
+struct WriteProtect {
+    /// SBWE/CBWE bit 7: BW-RAM writes allowed in the protected area
+    bwram_enable: [bool; 2],
+    /// BWPA: protected area at the start of BW-RAM, 256 << n bytes
+    bwram_area: u8,
+    /// SIWP/CIWP: I-RAM pages (256 bytes) writable by each CPU
+    iram_pages: [u8; 2],
+}
+
+/// SA-1 coprocessor: a 10.74 MHz 65C816 sharing the cartridge ROM and BW-RAM with the
+/// SNES, with its own 2 KiB I-RAM, a memory mapping controller, DMA and character
+/// conversion, arithmetic and bit stream units.
+///
+/// The SNES side goes through the [`Coprocessor`] interface, the SA-1 CPU runs in
+/// [`Coprocessor::step`]. The CPU core has no interrupt inputs yet, so interrupts are
+/// only latched in SFR/CFR: [`Sa1::irq_to_snes`] exposes the line to the SNES. The H/V
+/// timer is not emulated.
+pub struct Sa1 {
+    cpu: CPU,
+    /// Master cycles not yet spent running the SA-1
+    pending_cycles: u32,
+    rom: Vec<u8>,
+    bwram: Vec<u8>,
+    iram: [u8; IRAM_SIZE],
+    mmc: Mmc,
+    control: Control,
+    write_protect: WriteProtect,
+    dma: Dma,
+    arithmetic: Arithmetic,
+    bit_stream: BitStream,
+    /// BBF bit 7: the bitmap view of BW-RAM packs 2 bits per pixel instead of 4
+    bitmap_2bpp: bool,
+}
+
+impl Sa1 {
+    /// SA-1 for the cartridge `rom`, with BW-RAM sized from its header.
+    pub fn new(rom: &Rom) -> Self {
+        let bwram_size = 0x400 << rom.header.ram_size.clamp(3, 8);
+        Self::with_data(rom.data.clone(), bwram_size)
+    }
+
+    pub fn with_data(rom: Vec<u8>, bwram_size: usize) -> Self {
+        Self {
+            cpu: CPU::poweron(),
+            pending_cycles: 0,
+            rom,
+            bwram: vec![0; bwram_size],
+            iram: [0; IRAM_SIZE],
+            mmc: Mmc::new(),
+            control: Control {
+                ccnt: CCNT_POWERON,
+                ..Control::default()
+            },
+            write_protect: WriteProtect::default(),
+            dma: Dma::default(),
+            arithmetic: Arithmetic::default(),
+            bit_stream: BitStream::default(),
+            bitmap_2bpp: false,
+        }
+    }
+
+    pub fn cpu(&self) -> &CPU {
+        &self.cpu
+    }
+
+    pub fn iram(&self) -> &[u8; IRAM_SIZE] {
+        &self.iram
+    }
+
+    /// Battery-backed RAM, which [`Coprocessor::save_data`] saves
+    pub fn bwram(&self) -> &[u8] {
+        &self.bwram
+    }
+
+    pub fn bwram_mut(&mut self) -> &mut [u8] {
+        &mut self.bwram
+    }
+
+    /// The SA-1 CPU is released from reset and not waiting.
+    pub fn is_running(&self) -> bool {
+        self.control.ccnt & 0x60 == 0
+    }
+
+    /// IRQ line from the SA-1 to the SNES CPU.
+    pub fn irq_to_snes(&self) -> bool {
+        self.control.snes_flags & self.control.snes_irq_enable & (IRQ | DMA_IRQ) != 0
+    }
+
+    /// IRQ line of the SA-1 CPU.
+    pub fn irq_to_sa1(&self) -> bool {
+        self.control.sa1_flags & self.control.sa1_irq_enable & (IRQ | TIMER_IRQ | DMA_IRQ) != 0
+    }
+
+    /// NMI line of the SA-1 CPU.
+    pub fn nmi_to_sa1(&self) -> bool {
+        self.control.sa1_flags & self.control.sa1_irq_enable & NMI != 0
+    }
+
+    fn cycle(&mut self) {
+        match self.cpu.cycle() {
+            CycleResult::Read => {
+                let addr = *self.cpu.addr_bus();
+                self.cpu.data_bus = self.read_from(Side::Sa1, addr);
+            }
+            CycleResult::Write => {
+                let addr = *self.cpu.addr_bus();
+                let value = self.cpu.data_bus;
+                self.write_from(Side::Sa1, addr, value);
+            }
+            CycleResult::Internal => {}
+        }
+    }
+
+    // ============================================================
+    // Memory map
+    // ============================================================
+
+    fn read_from(&mut self, side: Side, addr: SnesAddress) -> u8 {
+        match (addr.bank, addr.addr) {
+            (0x00..=0x3F | 0x80..=0xBF, 0x0000..=0x07FF) if side == Side::Sa1 => {
+                self.iram[addr.addr as usize]
+            }
+            (0x00..=0x3F | 0x80..=0xBF, 0x2200..=0x23FF) => self.read_register(side, addr.addr),
+            (0x00..=0x3F | 0x80..=0xBF, 0x3000..=0x37FF) => {
+                self.iram[addr.addr as usize & (IRAM_SIZE - 1)]
+            }
+            (0x00..=0x3F | 0x80..=0xBF, 0x6000..=0x7FFF) => match side {
+                Side::Snes => self.bwram_read(self.mmc.snes_bwram_offset(addr.addr)),
+                Side::Sa1 if self.mmc.sa1_bwram_bitmap() => {
+                    self.bitmap_read(self.mmc.sa1_bwram_offset(addr.addr))
+                }
+                Side::Sa1 => self.bwram_read(self.mmc.sa1_bwram_offset(addr.addr)),
+            },
+            (0x40..=0x4F, _) => {
+                let offset = bwram_linear_offset(addr);
+                if side == Side::Snes && self.dma.type1_active {
+                    self.type1_read(offset)
+                } else {
+                    self.bwram_read(offset)
+                }
+            }
+            (0x60..=0x6F, _) if side == Side::Sa1 => self.bitmap_read(bwram_linear_offset(addr)),
+            _ => self.rom_read(side, addr),
+        }
+    }
+
+    fn write_from(&mut self, side: Side, addr: SnesAddress, value: u8) {
+        match (addr.bank, addr.addr) {
+            (0x00..=0x3F | 0x80..=0xBF, 0x0000..=0x07FF) if side == Side::Sa1 => {
+                self.iram_write(side, addr.addr as usize, value)
+            }
+            (0x00..=0x3F | 0x80..=0xBF, 0x2200..=0x23FF) => {
+                self.write_register(side, addr.addr, value)
+            }
+            (0x00..=0x3F | 0x80..=0xBF, 0x3000..=0x37FF) => {
+                self.iram_write(side, addr.addr as usize & (IRAM_SIZE - 1), value)
+            }
+            (0x00..=0x3F | 0x80..=0xBF, 0x6000..=0x7FFF) => match side {
+                Side::Snes => self.bwram_write(side, self.mmc.snes_bwram_offset(addr.addr), value),
+                Side::Sa1 if self.mmc.sa1_bwram_bitmap() => {
+                    self.bitmap_write(self.mmc.sa1_bwram_offset(addr.addr), value)
+                }
+                Side::Sa1 => self.bwram_write(side, self.mmc.sa1_bwram_offset(addr.addr), value),
+            },
+            (0x40..=0x4F, _) => self.bwram_write(side, bwram_linear_offset(addr), value),
+            (0x60..=0x6F, _) if side == Side::Sa1 => {
+                self.bitmap_write(bwram_linear_offset(addr), value)
+            }
+            // ROM is read-only
+            _ => {}
+        }
+    }
+
+    /// ROM through the MMC, with the interrupt vectors overridden by the SA-1 registers.
+    fn rom_read(&self, side: Side, addr: SnesAddress) -> u8 {
+        if let Some(vector) = self.vector_override(side, addr) {
+            return vector.to_le_bytes()[addr.addr as usize & 1];
+        }
+        match self.mmc.rom_offset(addr) {
+            Some(offset) if !self.rom.is_empty() => self.rom[offset % self.rom.len()],
+            _ => 0,
+        }
+    }
+
+    fn vector_override(&self, side: Side, addr: SnesAddress) -> Option<u16> {
+        if addr.bank != 0x00 {
+            return None;
+        }
+        let control = &self.control;
+        match (side, addr.addr) {
+            (Side::Sa1, 0xFFFC..=0xFFFD) => Some(control.sa1_vectors[0]),
+            (Side::Sa1, 0xFFEA..=0xFFEB) => Some(control.sa1_vectors[1]),
+            (Side::Sa1, 0xFFEE..=0xFFEF) => Some(control.sa1_vectors[2]),
+            (Side::Snes, 0xFFEA..=0xFFEB) if control.scnt & 0x10 != 0 => {
+                Some(control.snes_vectors[0])
+            }
+            (Side::Snes, 0xFFEE..=0xFFEF) if control.scnt & 0x40 != 0 => {
+                Some(control.snes_vectors[1])
+            }
+            _ => None,
+        }
+    }
+
+    fn iram_write(&mut self, side: Side, offset: usize, value: u8) {
+        if self.write_protect.iram_pages[side as usize] & (1 << (offset >> 8)) != 0 {
+            self.iram[offset] = value;
+        }
+    }
+
+    /// BW-RAM is mirrored over its size. Without BW-RAM, reads are open bus and writes are
+    /// ignored.
+    fn bwram_index(&self, offset: usize) -> Option<usize> {
+        (!self.bwram.is_empty()).then(|| offset % self.bwram.len())
+    }
+
+    fn bwram_read(&self, offset: usize) -> u8 {
+        self.bwram_index(offset).map_or(0, |offset| self.bwram[offset])
+    }
+
+    fn bwram_write(&mut self, side: Side, offset: usize, value: u8) {
+        let Some(offset) = self.bwram_index(offset) else {
+            return;
+        };
+        let protected = offset < 0x100 << (self.write_protect.bwram_area & 0x0F);
+        if !protected || self.write_protect.bwram_enable[side as usize] {
+            self.bwram[offset] = value;
+        }
+    }
+
+    /// Location of a pixel of the bitmap view: byte offset, shift and mask
+    fn bitmap_pixel(&self, pixel: usize) -> Option<(usize, u32, u8)> {
+        let (per_byte, mask) = if self.bitmap_2bpp {
+            (4, 0x03)
+        } else {
+            (2, 0x0F)
+        };
+        let shift = (pixel % per_byte) as u32 * (8 / per_byte as u32);
+        Some((self.bwram_index(pixel / per_byte)?, shift, mask))
+    }
+
+    fn bitmap_read(&self, pixel: usize) -> u8 {
+        self.bitmap_pixel(pixel)
+            .map_or(0, |(offset, shift, mask)| (self.bwram[offset] >> shift) & mask)
+    }
+
+    fn bitmap_write(&mut self, pixel: usize, value: u8) {
+        let Some((offset, shift, mask)) = self.bitmap_pixel(pixel) else {
+            return;
+        };
+        let byte = &mut self.bwram[offset];
+        *byte = (*byte & !(mask << shift)) | (value & mask) << shift;
+    }
+
+    fn type1_read(&self, offset: usize) -> u8 {
+        let Some(source) = self.bwram_index(self.dma.source as usize) else {
+            return 0;
+        };
+        let index = offset.wrapping_sub(source) % self.bwram.len();
+        self.dma.type1_byte(&self.bwram, source, index)
+    }
+
+    // ============================================================
+    // Registers
+    // ============================================================
+
+    fn read_register(&mut self, side: Side, reg: u16) -> u8 {
+        let control = &self.control;
+        match (side, reg) {
+            // SFR
+            (Side::Snes, 0x2300) => control.snes_flags & (IRQ | DMA_IRQ) | control.scnt & 0x5F,
+            // CFR
+            (Side::Sa1, 0x2301) => control.sa1_flags & 0xF0 | control.ccnt & 0x0F,
+            // MR
+            (Side::Sa1, 0x2306..=0x230A) => self.arithmetic.result_byte(reg - 0x2306),
+            // OF
+            (Side::Sa1, 0x230B) => (self.arithmetic.overflow as u8) << 7,
+            // VDP
+            (Side::Sa1, 0x230C) => self.bit_stream_value() as u8,
+            (Side::Sa1, 0x230D) => {
+                let value = (self.bit_stream_value() >> 8) as u8;
+                if self.bit_stream.auto_increment {
+                    self.bit_stream.advance();
+                }
+                value
+            }
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, side: Side, reg: u16, value: u8) {
+        match (side, reg) {
+            (Side::Snes, 0x2200) => self.write_ccnt(value),
+            (Side::Snes, 0x2201) => self.control.snes_irq_enable = value,
+            (Side::Snes, 0x2202) => self.control.snes_flags &= !value,
+            (Side::Snes, 0x2203..=0x2208) => {
+                let index = (reg - 0x2203) / 2;
+                set_word_byte(
+                    &mut self.control.sa1_vectors[index as usize],
+                    reg & 1 == 1,
+                    value,
+                );
+            }
+            (Side::Sa1, 0x2209) => {
+                self.control.scnt = value;
+                if value & IRQ != 0 {
+                    self.control.snes_flags |= IRQ;
+                }
+            }
+            (Side::Sa1, 0x220A) => self.control.sa1_irq_enable = value,
+            (Side::Sa1, 0x220B) => self.control.sa1_flags &= !value,
+            (Side::Sa1, 0x220C..=0x220F) => {
+                let index = (reg - 0x220C) / 2;
+                set_word_byte(
+                    &mut self.control.snes_vectors[index as usize],
+                    reg & 1 == 0,
+                    value,
+                );
+            }
+            (Side::Snes, 0x2220..=0x2223) => self.mmc.rom_banks[(reg - 0x2220) as usize] = value,
+            (Side::Snes, 0x2224) => self.mmc.snes_bwram_block = value,
+            (Side::Sa1, 0x2225) => self.mmc.sa1_bwram_block = value,
+            (Side::Snes, 0x2226) => {
+                self.write_protect.bwram_enable[Side::Snes as usize] = value & 0x80 != 0
+            }
+            (Side::Sa1, 0x2227) => {
+                self.write_protect.bwram_enable[Side::Sa1 as usize] = value & 0x80 != 0
+            }
+            (Side::Snes, 0x2228) => self.write_protect.bwram_area = value,
+            (Side::Snes, 0x2229) => self.write_protect.iram_pages[Side::Snes as usize] = value,
+            (Side::Sa1, 0x222A) => self.write_protect.iram_pages[Side::Sa1 as usize] = value,
+            (Side::Sa1, 0x2230) => self.dma.set_control(value),
+            (_, 0x2231) => {
+                self.dma.conversion = value;
+                // End of a type 1 conversion
+                if value & 0x80 != 0 {
+                    self.dma.type1_active = false;
+                }
+            }
+            (Side::Sa1, 0x2232..=0x2234) => self.dma.set_source(reg - 0x2232, value),
+            (Side::Sa1, 0x2235..=0x2237) => {
+                self.dma.set_destination(reg - 0x2235, value);
+                self.start_dma(reg);
+            }
+            (Side::Sa1, 0x2238) => self.dma.length = (self.dma.length & 0xFF00) | value as u16,
+            (Side::Sa1, 0x2239) => {
+                self.dma.length = (self.dma.length & 0x00FF) | (value as u16) << 8
+            }
+            (Side::Sa1, 0x223F) => self.bitmap_2bpp = value & 0x80 != 0,
+            (Side::Sa1, 0x2240..=0x224F) => {
+                let index = (reg - 0x2240) as usize;
+                self.dma.bitmap_registers[index] = value;
+                if index & 7 == 7
+                    && self.dma.enabled()
+                    && self.dma.character_conversion()
+                    && !self.dma.is_type1()
+                {
+                    self.dma.convert_type2_row(&mut self.iram);
+                }
+            }
+            (Side::Sa1, 0x2250) => self.arithmetic.set_control(value),
+            (Side::Sa1, 0x2251..=0x2252) => self.arithmetic.set_ma(reg == 0x2252, value),
+            (Side::Sa1, 0x2253..=0x2254) => self.arithmetic.set_mb(reg == 0x2254, value),
+            (Side::Sa1, 0x2258) => self.bit_stream.set_control(value),
+            (Side::Sa1, 0x2259..=0x225B) => self.bit_stream.set_address(reg - 0x2259, value),
+            // Timer registers, and registers of the other CPU
+            _ => {}
+        }
+    }
+
+    fn write_ccnt(&mut self, value: u8) {
+        let was_reset = self.control.ccnt & 0x20 != 0;
+        self.control.ccnt = value;
+
+        if was_reset && value & 0x20 == 0 {
+            self.cpu.start_reset();
+            self.pending_cycles = 0;
+        }
+        if value & IRQ != 0 {
+            self.control.sa1_flags |= IRQ;
+        }
+        if value & NMI != 0 {
+            self.control.sa1_flags |= NMI;
+        }
+    }
+
+    fn bit_stream_value(&self) -> u16 {
+        let address = self.bit_stream.address;
+        let bytes = [0, 1, 2].map(|i| {
+            self.rom_read(
+                Side::Sa1,
+                SnesAddress::from((address + i) as usize & 0xFF_FFFF),
+            )
+        });
+        self.bit_stream.value(bytes)
+    }
+
+    // ============================================================
+    // DMA
+    // ============================================================
+
+    /// DMA starts when the destination address is complete: the middle byte for I-RAM,
+    /// the bank for BW-RAM.
+    fn start_dma(&mut self, reg: u16) {
+        if !self.dma.enabled() {
+            return;
+        }
+        if self.dma.character_conversion() {
+            if self.dma.is_type1() && reg == 0x2236 {
+                self.dma.type1_active = true;
+                self.control.snes_flags |= DMA_IRQ;
+            }
+            return;
+        }
+        let start = match self.dma.destination_device() {
+            DmaDestination::Iram => reg == 0x2236,
+            DmaDestination::Bwram => reg == 0x2237,
+        };
+        if start {
+            self.normal_dma();
+        }
+    }
+
+    fn normal_dma(&mut self) {
+        let source = self.dma.source as usize;
+        let destination = self.dma.destination as usize;
+
+        for i in 0..self.dma.length as usize {
+            let value = match self.dma.source_device() {
+                DmaSource::Rom => {
+                    self.rom_read(Side::Sa1, SnesAddress::from((source + i) & 0xFF_FFFF))
+                }
+                DmaSource::Bwram => self.bwram_read(source + i),
+                DmaSource::Iram => self.iram[(source + i) & (IRAM_SIZE - 1)],
+            };
+            match self.dma.destination_device() {
+                DmaDestination::Iram => self.iram[(destination + i) & (IRAM_SIZE - 1)] = value,
+                DmaDestination::Bwram => {
+                    if let Some(offset) = self.bwram_index(destination + i) {
+                        self.bwram[offset] = value;
+                    }
+                }
+            }
+        }
+
+        self.control.sa1_flags |= DMA_IRQ;
+    }
+}
+
+impl Coprocessor for Sa1 {
+    fn name(&self) -> &'static str {
+        "SA-1"
+    }
+
+    fn maps(&self, addr: SnesAddress) -> bool {
+        match addr.bank {
+            0x00..=0x3F | 0x80..=0xBF => {
+                matches!(addr.addr, 0x2200..=0x23FF | 0x3000..=0x37FF | 0x6000..=0xFFFF)
+            }
+            0x40..=0x4F | 0xC0..=0xFF => true,
+            _ => false,
+        }
+    }
+
+    fn read(&mut self, addr: SnesAddress) -> u8 {
+        self.read_from(Side::Snes, addr)
+    }
+
+    fn write(&mut self, addr: SnesAddress, value: u8) {
+        self.write_from(Side::Snes, addr, value);
+    }
+
+    fn step(&mut self, master_cycles: u32) {
+        if !self.is_running() {
+            return;
+        }
+        self.pending_cycles += master_cycles;
+        while self.pending_cycles >= MASTER_CYCLES_PER_CYCLE {
+            self.pending_cycles -= MASTER_CYCLES_PER_CYCLE;
+            self.cycle();
+        }
+    }
+
+    /// Resets the SA-1 CPU and registers; the battery-backed BW-RAM and the I-RAM keep
+    /// their contents.
+    fn reset(&mut self) {
+        *self = Self {
+            rom: std::mem::take(&mut self.rom),
+            bwram: std::mem::take(&mut self.bwram),
+            iram: self.iram,
+            ..Self::with_data(Vec::new(), 0)
+        };
+    }
+
+    /// The BW-RAM, if the cartridge has any
+    fn save_data(&self) -> Option<Vec<u8>> {
+        (!self.bwram.is_empty()).then(|| self.bwram.clone())
+    }
+
+    /// A save file of another size fills what fits of BW-RAM.
+    fn load_save_data(&mut self, data: &[u8]) {
+        let len = data.len().min(self.bwram.len());
+        self.bwram[..len].copy_from_slice(&data[..len]);
+    }
+
+    fn save_data_extension(&self) -> &'static str {
+        "srm"
+    }
+
+    /// The CPU state can only be saved around opcode fetches, see [`CPU::can_save_state`]
+    fn prepare_save_state(&mut self) {
+        while !self.cpu.can_save_state() {
+            self.cycle();
+        }
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.cpu.save_state(writer);
+        writer.u32(self.pending_cycles);
+        writer.bytes(&self.bwram);
+        writer.bytes(&self.iram);
+
+        writer.bytes(&self.mmc.rom_banks);
+        writer.u8(self.mmc.snes_bwram_block);
+        writer.u8(self.mmc.sa1_bwram_block);
+
+        let control = &self.control;
+        for value in [
+            control.ccnt,
+            control.snes_irq_enable,
+            control.scnt,
+            control.sa1_irq_enable,
+            control.snes_flags,
+            control.sa1_flags,
+        ] {
+            writer.u8(value);
+        }
+        for &vector in control.sa1_vectors.iter().chain(&control.snes_vectors) {
+            writer.u16(vector);
+        }
+
+        let write_protect = &self.write_protect;
+        writer.bool(write_protect.bwram_enable[0]);
+        writer.bool(write_protect.bwram_enable[1]);
+        writer.u8(write_protect.bwram_area);
+        writer.bytes(&write_protect.iram_pages);
+
+        let dma = &self.dma;
+        writer.u8(dma.control);
+        writer.u8(dma.conversion);
+        writer.u32(dma.source);
+        writer.u32(dma.destination);
+        writer.u16(dma.length);
+        writer.bytes(&dma.bitmap_registers);
+        writer.u8(dma.line);
+        writer.bool(dma.type1_active);
+
+        let arithmetic = &self.arithmetic;
+        writer.u8(match arithmetic.mode {
+            ArithmeticMode::Multiply => 0,
+            ArithmeticMode::Divide => 1,
+            ArithmeticMode::CumulativeSum => 2,
+        });
+        writer.u16(arithmetic.ma);
+        writer.u16(arithmetic.mb);
+        writer.u64(arithmetic.result);
+        writer.bool(arithmetic.overflow);
+
+        let bit_stream = &self.bit_stream;
+        writer.u32(bit_stream.address);
+        writer.u32(bit_stream.bit);
+        writer.bool(bit_stream.auto_increment);
+        writer.u32(bit_stream.length);
+
+        writer.bool(self.bitmap_2bpp);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.cpu.load_state(reader)?;
+        self.pending_cycles = reader.u32()?;
+        let bwram = reader.bytes(self.bwram.len())?;
+        self.bwram.copy_from_slice(bwram);
+        self.iram = reader.array()?;
+
+        self.mmc.rom_banks = reader.array()?;
+        self.mmc.snes_bwram_block = reader.u8()?;
+        self.mmc.sa1_bwram_block = reader.u8()?;
+
+        let control = &mut self.control;
+        for value in [
+            &mut control.ccnt,
+            &mut control.snes_irq_enable,
+            &mut control.scnt,
+            &mut control.sa1_irq_enable,
+            &mut control.snes_flags,
+            &mut control.sa1_flags,
+        ] {
+            *value = reader.u8()?;
+        }
+        for vector in control.sa1_vectors.iter_mut().chain(&mut control.snes_vectors) {
+            *vector = reader.u16()?;
+        }
+
+        let write_protect = &mut self.write_protect;
+        write_protect.bwram_enable = [reader.bool()?, reader.bool()?];
+        write_protect.bwram_area = reader.u8()?;
+        write_protect.iram_pages = reader.array()?;
+
+        let dma = &mut self.dma;
+        dma.control = reader.u8()?;
+        dma.conversion = reader.u8()?;
+        dma.source = reader.u32()?;
+        dma.destination = reader.u32()?;
+        dma.length = reader.u16()?;
+        dma.bitmap_registers = reader.array()?;
+        dma.line = reader.u8()? & 15;
+        dma.type1_active = reader.bool()?;
+
+        let arithmetic = &mut self.arithmetic;
+        arithmetic.mode = match reader.u8()? {
+            0 => ArithmeticMode::Multiply,
+            1 => ArithmeticMode::Divide,
+            2 => ArithmeticMode::CumulativeSum,
+            _ => return Err(reader.corrupt()),
+        };
+        arithmetic.ma = reader.u16()?;
+        arithmetic.mb = reader.u16()?;
+        arithmetic.result = reader.u64()?;
+        arithmetic.overflow = reader.bool()?;
+
+        let bit_stream = &mut self.bit_stream;
+        bit_stream.address = reader.u32()? & 0xFF_FFFF;
+        bit_stream.bit = reader.u32()? & 7;
+        bit_stream.auto_increment = reader.bool()?;
+        bit_stream.length = reader.u32()?;
+        if bit_stream.length > 16 {
+            return Err(reader.corrupt());
+        }
+
+        self.bitmap_2bpp = reader.bool()?;
+        Ok(())
+    }
+}
+
+/// BW-RAM offset of an access to banks `$40–$4F`
+fn bwram_linear_offset(addr: SnesAddress) -> usize {
+    ((addr.bank & 0x0F) as usize) << 16 | addr.addr as usize
+}
+
+fn set_word_byte(word: &mut u16, low: bool, value: u8) {
+    *word = if low {
+        (*word & 0xFF00) | value as u16
+    } else {
+        (*word & 0x00FF) | (value as u16) << 8
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::save_state::SaveState;
+    use common::snes_address::snes_addr;
+
+    const BWRAM_SIZE: usize = 0x2000;
+
+    /// ROM with an SA-1 program at $00:8000 and the SA-1 reset vector pointing to it
+    fn sa1_with_program(program: &[u8]) -> Sa1 {
+        let mut rom = vec![0xEA; 0x8000]; // NOP
+        rom[..program.len()].copy_from_slice(program);
+        Sa1::with_data(rom, BWRAM_SIZE)
+    }
+
+    fn snes_write(sa1: &mut Sa1, addr: SnesAddress, value: u8) {
+        Coprocessor::write(sa1, addr, value);
+    }
+
+    fn snes_read(sa1: &mut Sa1, addr: SnesAddress) -> u8 {
+        Coprocessor::read(sa1, addr)
+    }
+
+    /// Releases the SA-1 from reset, starting at $00:8000
+    fn start(sa1: &mut Sa1) {
+        snes_write(sa1, snes_addr!(0:0x2203), 0x00);
+        snes_write(sa1, snes_addr!(0:0x2204), 0x80);
+        snes_write(sa1, snes_addr!(0:0x2200), 0x00);
+    }
+
+    // ============================================================
+    // SA-1 CPU
+    // ============================================================
+
+    #[test]
+    fn test_held_in_reset_at_poweron() {
+        let mut sa1 = sa1_with_program(&[]);
+        assert!(!sa1.is_running());
+        sa1.step(1000);
+        assert_eq!(sa1.cpu().regs().PC, 0);
+    }
+
+    #[test]
+    fn test_sa1_runs_program_writing_iram() {
+        // LDA #$42 ; STA $3000 ; STA $0010 ; STP-like loop (BRA -2)
+        let program = [0xA9, 0x42, 0x8D, 0x00, 0x30, 0x8D, 0x10, 0x00, 0x80, 0xFE];
+        let mut sa1 = sa1_with_program(&program);
+        sa1.write_register(Side::Sa1, 0x222A, 0xFF);
+        start(&mut sa1);
+        assert!(sa1.is_running());
+
+        sa1.step(200);
+        assert_eq!(sa1.iram()[0x000], 0x42);
+        assert_eq!(sa1.iram()[0x010], 0x42);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0x80:0x3010)), 0x42);
+    }
+
+    #[test]
+    fn test_reset_keeps_bwram_and_iram() {
+        let program = [0xA9, 0x42, 0x8D, 0x00, 0x30, 0x80, 0xFE];
+        let mut sa1 = sa1_with_program(&program);
+        sa1.write_register(Side::Sa1, 0x222A, 0xFF);
+        start(&mut sa1);
+        sa1.step(200);
+        sa1.bwram_mut()[0x10] = 0x33;
+
+        sa1.reset();
+        assert!(!sa1.is_running());
+        assert_eq!(sa1.cpu().regs().PC, 0);
+        assert_eq!(sa1.iram()[0], 0x42);
+        assert_eq!(sa1.bwram()[0x10], 0x33);
+        assert_eq!(sa1.rom.len(), 0x8000);
+    }
+
+    #[test]
+    fn test_wait_stops_sa1() {
+        let program = [0xA9, 0x42, 0x8D, 0x00, 0x30, 0x80, 0xFE];
+        let mut sa1 = sa1_with_program(&program);
+        sa1.write_register(Side::Sa1, 0x222A, 0xFF);
+        start(&mut sa1);
+        snes_write(&mut sa1, snes_addr!(0:0x2200), 0x40);
+
+        sa1.step(200);
+        assert_eq!(sa1.iram()[0], 0);
+    }
+
+    #[test]
+    fn test_vector_overrides() {
+        let mut sa1 = sa1_with_program(&[]);
+        sa1.rom[0x7FEA] = 0x11;
+        sa1.rom[0x7FEE] = 0x22;
+        snes_write(&mut sa1, snes_addr!(0:0x2203), 0x34);
+        snes_write(&mut sa1, snes_addr!(0:0x2204), 0x12);
+        sa1.write_register(Side::Sa1, 0x220C, 0x78);
+        sa1.write_register(Side::Sa1, 0x220D, 0x56);
+
+        assert_eq!(sa1.read_from(Side::Sa1, snes_addr!(0:0xFFFC)), 0x34);
+        assert_eq!(sa1.read_from(Side::Sa1, snes_addr!(0:0xFFFD)), 0x12);
+
+        // SNES NMI vector only overridden when selected in SCNT
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0:0xFFEA)), 0x11);
+        sa1.write_register(Side::Sa1, 0x2209, 0x10);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0:0xFFEA)), 0x78);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0:0xFFEB)), 0x56);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0:0xFFEE)), 0x22);
+    }
+
+    // ============================================================
+    // Interrupts and messages
+    // ============================================================
+
+    #[test]
+    fn test_messages_and_flags() {
+        let mut sa1 = sa1_with_program(&[]);
+
+        // SNES -> SA-1: IRQ with message 5
+        snes_write(&mut sa1, snes_addr!(0:0x2200), 0xA5);
+        assert_eq!(sa1.read_register(Side::Sa1, 0x2301), 0x85);
+        assert!(!sa1.irq_to_sa1());
+        sa1.write_register(Side::Sa1, 0x220A, IRQ);
+        assert!(sa1.irq_to_sa1());
+        sa1.write_register(Side::Sa1, 0x220B, IRQ);
+        assert!(!sa1.irq_to_sa1());
+
+        // SA-1 -> SNES: IRQ with message 9
+        sa1.write_register(Side::Sa1, 0x2209, 0x89);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0:0x2300)), 0x89);
+        snes_write(&mut sa1, snes_addr!(0:0x2201), IRQ);
+        assert!(sa1.irq_to_snes());
+        snes_write(&mut sa1, snes_addr!(0:0x2202), IRQ);
+        assert!(!sa1.irq_to_snes());
+    }
+
+    #[test]
+    fn test_registers_ignore_other_side() {
+        let mut sa1 = sa1_with_program(&[]);
+        // CXB is written by the SNES only
+        sa1.write_register(Side::Sa1, 0x2220, 0x81);
+        assert_eq!(sa1.mmc.rom_banks[0], 0);
+        snes_write(&mut sa1, snes_addr!(0:0x2220), 0x81);
+        assert_eq!(sa1.mmc.rom_banks[0], 0x81);
+    }
+
+    // ============================================================
+    // Memory
+    // ============================================================
+
+    #[test]
+    fn test_bwram_mapping_and_protection() {
+        let mut sa1 = sa1_with_program(&[]);
+        snes_write(&mut sa1, snes_addr!(0:0x2228), 0x00); // First 256 bytes protected
+
+        snes_write(&mut sa1, snes_addr!(0x40:0x0010), 0x11);
+        assert_eq!(sa1.bwram()[0x10], 0);
+        snes_write(&mut sa1, snes_addr!(0x40:0x0110), 0x22);
+        assert_eq!(sa1.bwram()[0x110], 0x22);
+
+        snes_write(&mut sa1, snes_addr!(0:0x2226), 0x80);
+        snes_write(&mut sa1, snes_addr!(0:0x6010), 0x33);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0x40:0x0010)), 0x33);
+    }
+
+    #[test]
+    fn test_without_bwram() {
+        let mut sa1 = Sa1::with_data(vec![0xEA; 0x8000], 0);
+        snes_write(&mut sa1, snes_addr!(0:0x2226), 0x80);
+        snes_write(&mut sa1, snes_addr!(0x40:0x0010), 0x11);
+        snes_write(&mut sa1, snes_addr!(0:0x6010), 0x22);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0x40:0x0010)), 0);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0:0x6010)), 0);
+
+        sa1.write_register(Side::Sa1, 0x2227, 0x80);
+        sa1.write_from(Side::Sa1, snes_addr!(0x60:0x0000), 0x0A);
+        assert_eq!(sa1.read_from(Side::Sa1, snes_addr!(0:0x6000)), 0);
+
+        // I-RAM -> BW-RAM DMA, then a type 1 conversion reading BW-RAM
+        sa1.write_register(Side::Sa1, 0x2230, 0x86);
+        for (reg, value) in [(0x2238, 2), (0x2235, 0x00), (0x2236, 0x04), (0x2237, 0x40)] {
+            sa1.write_register(Side::Sa1, reg, value);
+        }
+        sa1.write_register(Side::Sa1, 0x2230, 0xB1);
+        sa1.write_register(Side::Sa1, 0x2236, 0x00);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0x40:0x0100)), 0);
+        assert!(sa1.bwram().is_empty());
+    }
+
+    #[test]
+    fn test_iram_write_protection() {
+        let mut sa1 = sa1_with_program(&[]);
+        snes_write(&mut sa1, snes_addr!(0:0x3000), 0x11);
+        assert_eq!(sa1.iram()[0], 0);
+
+        snes_write(&mut sa1, snes_addr!(0:0x2229), 0x01);
+        snes_write(&mut sa1, snes_addr!(0:0x3000), 0x11);
+        snes_write(&mut sa1, snes_addr!(0:0x3100), 0x22);
+        assert_eq!(sa1.iram()[0], 0x11);
+        assert_eq!(sa1.iram()[0x100], 0);
+    }
+
+    #[test]
+    fn test_bitmap_view() {
+        let mut sa1 = sa1_with_program(&[]);
+        sa1.write_register(Side::Sa1, 0x2227, 0x80);
+
+        // 4bpp: 2 pixels per byte, first pixel in the low nibble
+        sa1.write_from(Side::Sa1, snes_addr!(0x60:0x0000), 0x0A);
+        sa1.write_from(Side::Sa1, snes_addr!(0x60:0x0001), 0x05);
+        assert_eq!(sa1.bwram()[0], 0x5A);
+
+        // 2bpp through BMAP at $6000
+        sa1.write_register(Side::Sa1, 0x223F, 0x80);
+        sa1.write_register(Side::Sa1, 0x2225, 0x80);
+        sa1.write_from(Side::Sa1, snes_addr!(0:0x6003), 0x03);
+        assert_eq!(sa1.bwram()[0], 0xDA);
+        assert_eq!(sa1.read_from(Side::Sa1, snes_addr!(0:0x6001)), 0x02);
+    }
+
+    #[test]
+    fn test_rom_through_mmc() {
+        let mut rom = vec![0; 0x20_0000];
+        rom[0x10_0000] = 0x77;
+        let mut sa1 = Sa1::with_data(rom, BWRAM_SIZE);
+
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0x20:0x8000)), 0x77);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0xD0:0x0000)), 0x77);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0x00:0x8000)), 0x00);
+
+        snes_write(&mut sa1, snes_addr!(0:0x2220), 0x81);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0x00:0x8000)), 0x77);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0xC0:0x0000)), 0x77);
+    }
+
+    #[test]
+    fn test_maps() {
+        let sa1 = sa1_with_program(&[]);
+        assert!(sa1.maps(snes_addr!(0x00:0x2200)));
+        assert!(sa1.maps(snes_addr!(0x80:0x3000)));
+        assert!(sa1.maps(snes_addr!(0x00:0x6000)));
+        assert!(sa1.maps(snes_addr!(0x41:0x0000)));
+        assert!(sa1.maps(snes_addr!(0xFF:0xFFFF)));
+        assert!(!sa1.maps(snes_addr!(0x00:0x2100)));
+        assert!(!sa1.maps(snes_addr!(0x00:0x1000)));
+        assert!(!sa1.maps(snes_addr!(0x7E:0x0000)));
+        assert!(!sa1.maps(snes_addr!(0x60:0x0000)));
+    }
+
+    // ============================================================
+    // DMA, arithmetic and bit stream
+    // ============================================================
+
+    #[test]
+    fn test_normal_dma_rom_to_iram() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x100..0x104].copy_from_slice(&[1, 2, 3, 4]);
+        let mut sa1 = Sa1::with_data(rom, BWRAM_SIZE);
+
+        sa1.write_register(Side::Sa1, 0x2230, 0x80); // ROM -> I-RAM
+        for (reg, value) in [
+            (0x2232, 0x00),
+            (0x2233, 0x81),
+            (0x2234, 0x00),
+            (0x2238, 4),
+            (0x2239, 0),
+        ] {
+            sa1.write_register(Side::Sa1, reg, value);
+        }
+        sa1.write_register(Side::Sa1, 0x2235, 0x20);
+        assert_eq!(sa1.iram()[0x20], 0);
+        sa1.write_register(Side::Sa1, 0x2236, 0x00);
+
+        assert_eq!(&sa1.iram()[0x20..0x24], &[1, 2, 3, 4]);
+        assert_eq!(sa1.read_register(Side::Sa1, 0x2301) & DMA_IRQ, DMA_IRQ);
+    }
+
+    #[test]
+    fn test_normal_dma_iram_to_bwram() {
+        let mut sa1 = sa1_with_program(&[]);
+        sa1.iram[0x10..0x12].copy_from_slice(&[9, 8]);
+
+        sa1.write_register(Side::Sa1, 0x2230, 0x86); // I-RAM -> BW-RAM
+        for (reg, value) in [(0x2232, 0x10), (0x2238, 2), (0x2235, 0x00), (0x2236, 0x04)] {
+            sa1.write_register(Side::Sa1, reg, value);
+        }
+        assert_eq!(sa1.bwram()[0x400], 0);
+        sa1.write_register(Side::Sa1, 0x2237, 0x40);
+        assert_eq!(&sa1.bwram()[0x400..0x402], &[9, 8]);
+    }
+
+    #[test]
+    fn test_type1_conversion_through_snes_reads() {
+        let mut sa1 = sa1_with_program(&[]);
+        sa1.bwram[0x100] = 0x01; // First pixel of a 2bpp bitmap at $40:0100
+
+        sa1.write_register(Side::Sa1, 0x2231, 0x02);
+        sa1.write_register(Side::Sa1, 0x2230, 0xB1);
+        sa1.write_register(Side::Sa1, 0x2232, 0x00);
+        sa1.write_register(Side::Sa1, 0x2233, 0x01);
+        sa1.write_register(Side::Sa1, 0x2234, 0x40);
+        sa1.write_register(Side::Sa1, 0x2236, 0x00);
+
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0:0x2300)) & DMA_IRQ, DMA_IRQ);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0x40:0x0100)), 0x80);
+
+        // The SNES ends the conversion, BW-RAM reads are plain again
+        snes_write(&mut sa1, snes_addr!(0:0x2231), 0x80);
+        assert_eq!(snes_read(&mut sa1, snes_addr!(0x40:0x0100)), 0x01);
+    }
+
+    #[test]
+    fn test_type2_conversion_through_registers() {
+        let mut sa1 = sa1_with_program(&[]);
+        sa1.write_register(Side::Sa1, 0x2231, 0x02);
+        sa1.write_register(Side::Sa1, 0x2230, 0xA0);
+        sa1.write_register(Side::Sa1, 0x2236, 0x01);
+
+        for (i, pixel) in [3, 0, 0, 0, 0, 0, 0, 1].into_iter().enumerate() {
+            sa1.write_register(Side::Sa1, 0x2240 + i as u16, pixel);
+        }
+        assert_eq!(&sa1.iram()[0x100..0x102], &[0x81, 0x80]);
+    }
+
+    #[test]
+    fn test_arithmetic_registers() {
+        let mut sa1 = sa1_with_program(&[]);
+        for (reg, value) in [
+            (0x2250, 0),
+            (0x2251, 0x10),
+            (0x2252, 0x00),
+            (0x2253, 0x20),
+            (0x2254, 0x00),
+        ] {
+            sa1.write_register(Side::Sa1, reg, value);
+        }
+        assert_eq!(sa1.read_register(Side::Sa1, 0x2306), 0x00);
+        assert_eq!(sa1.read_register(Side::Sa1, 0x2307), 0x02);
+        assert_eq!(sa1.read_register(Side::Sa1, 0x230B), 0x00);
+    }
+
+    #[test]
+    fn test_bit_stream_registers() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x10..0x13].copy_from_slice(&[0x34, 0x12, 0xFF]);
+        let mut sa1 = Sa1::with_data(rom, BWRAM_SIZE);
+
+        for (reg, value) in [
+            (0x2259, 0x10),
+            (0x225A, 0x80),
+            (0x225B, 0x00),
+            (0x2258, 0x84),
+        ] {
+            sa1.write_register(Side::Sa1, reg, value);
+        }
+        assert_eq!(sa1.read_register(Side::Sa1, 0x230C), 0x34);
+        assert_eq!(sa1.read_register(Side::Sa1, 0x230D), 0x12);
+        // Auto-increment by 4 bits after reading the high byte
+        assert_eq!(sa1.read_register(Side::Sa1, 0x230C), 0x23);
+        assert_eq!(sa1.read_register(Side::Sa1, 0x230D), 0xF1);
+    }
+
+    // ============================================================
+    // Save data and save states
+    // ============================================================
+
+    #[test]
+    fn test_save_data_is_bwram() {
+        let mut sa1 = sa1_with_program(&[]);
+        sa1.bwram_mut()[0x100] = 0x5A;
+        let data = sa1.save_data().unwrap();
+        assert_eq!(data.len(), BWRAM_SIZE);
+
+        let mut reloaded = sa1_with_program(&[]);
+        reloaded.load_save_data(&data);
+        assert_eq!(reloaded.bwram()[0x100], 0x5A);
+
+        // a shorter file fills the start of BW-RAM
+        reloaded.load_save_data(&[0x11, 0x22]);
+        assert_eq!(&reloaded.bwram()[..2], &[0x11, 0x22]);
+        assert_eq!(reloaded.bwram()[0x100], 0x5A);
+
+        assert!(Sa1::with_data(vec![0; 0x8000], 0).save_data().is_none());
+    }
+
+    #[test]
+    fn test_save_state_resumes_program() {
+        // INC $3000 ; BRA -5
+        let program = [0xEE, 0x00, 0x30, 0x80, 0xFB];
+        let mut sa1 = sa1_with_program(&program);
+        sa1.write_register(Side::Sa1, 0x222A, 0xFF);
+        start(&mut sa1);
+        sa1.step(101);
+
+        let mut sa1: Box<dyn Coprocessor> = Box::new(sa1);
+        sa1.prepare_save_state();
+        let mut state = SaveState::new();
+        state.save(&sa1);
+
+        let mut loaded: Box<dyn Coprocessor> = Box::new(sa1_with_program(&program));
+        state.load(&mut loaded).unwrap();
+        sa1.step(500);
+        loaded.step(500);
+        assert_ne!(sa1.read(snes_addr!(0:0x3000)), 0);
+
+        sa1.prepare_save_state();
+        loaded.prepare_save_state();
+        let mut expected = SaveState::new();
+        expected.save(&sa1);
+        let mut state = SaveState::new();
+        state.save(&loaded);
+        assert_eq!(state, expected);
+    }
+}
@@ -0,0 +1,534 @@
+//! Command line interface: `r-snes [run] [ROM]` opens the emulator window, `record` runs
+//! the console without it, the other subcommands inspect a ROM without starting the console.
+
+use apu::dsp::OutputMode;
+use bus::rom::Rom;
+use bus::rom::database::RomDatabase;
+use bus::rom::header::mapping_mode::MappingMode;
+use bus::rom::rom::LoadOptions;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use common::power_on::MemoryInit;
+use common::snes_address::SnesAddress;
+use common::symbols::Symbols;
+use common::video_standard::VideoStandard;
+use cpu::cpu::OpcodePolicy;
+use cpu::disasm::{Instruction, RegisterWidths};
+use emulator::code_data_log::{register_widths, usage};
+use emulator::game_data::GameSettings;
+use emulator::scheduler::Pacing;
+use emulator::system::System;
+use emulator::{EmulatorOptions, Overclock, RSnes};
+use ppu::ppu::VramAccess;
+use ppu::rendering::frame_sink::Y4mWriter;
+use ppu::rendering::framebuffer::PixelFormat;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Default directory of the per-game data, next to the ROM
+const GAME_DATA_DIR: &str = "r-snes-data";
+
+#[derive(Parser)]
+#[command(name = "r-snes", version, about = "A Super Nintendo emulator")]
+pub struct Cli {
+    /// Opens the emulator window without a ROM when omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Open the emulator window, optionally loading a ROM
+    Run(RunArgs),
+    /// Print the cartridge header and the ROM hashes
+    Info(RomArgs),
+    /// Disassemble 65C816 code from a ROM bank
+    Disasm(DisasmArgs),
+    /// Check the header checksum and, with a database, the dump status
+    Verify(RomArgs),
+    /// Run a ROM without window, recording its frames to a Y4M video
+    Record(RecordArgs),
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    pub rom: Option<PathBuf>,
+
+    /// What frames are paced on
+    #[arg(long, value_enum, default_value_t)]
+    pub pacing: PacingArg,
+
+    #[command(flatten)]
+    pub emulation: EmulationArgs,
+}
+
+#[derive(Args, Default)]
+pub struct EmulationArgs {
+    /// Video standard to emulate instead of the one from the ROM header
+    #[arg(long, value_enum)]
+    pub video: Option<VideoArg>,
+    /// Halt the CPU on WDM and unimplemented opcodes instead of skipping them as NOPs
+    #[arg(long)]
+    pub trap_opcodes: bool,
+    /// Drop VRAM and CGRAM writes made during active display, like the hardware
+    #[arg(long)]
+    pub accurate_vram: bool,
+    /// Saturate the audio mix after each voice and filter it like the DAC of the console,
+    /// instead of outputting the raw DSP mix
+    #[arg(long)]
+    pub accurate_audio: bool,
+    /// Contents of WRAM and VRAM at power on, for games reading memory they never wrote
+    #[arg(long, value_enum, default_value_t)]
+    pub memory_init: MemoryInitArg,
+    /// Seed of `--memory-init random`, taken from the clock (and logged) when not given
+    #[arg(long)]
+    pub memory_seed: Option<u64>,
+    /// Log the ROM bytes run as code or read as data to ROM.cdl (bsnes-plus usage map),
+    /// adding to the log of the previous sessions
+    #[arg(long)]
+    pub cdl: bool,
+    /// Count the instructions run by opcode and address, writing the most run ones to
+    /// ROM.profile on exit
+    #[arg(long)]
+    pub profile: bool,
+    /// Extra master cycles run by the CPU at the end of each scanline, to reduce slowdown
+    /// (1364 doubles its clock)
+    #[arg(long, default_value_t = 0)]
+    pub cpu_overclock: u32,
+    /// Clock multiplier of the SA-1, to reduce slowdown in SA-1 games
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=8))]
+    pub sa1_overclock: Option<u32>,
+    /// Directory of the per-game settings, in one subdirectory per ROM named after its
+    /// SHA-1; `r-snes-data` next to the ROM when not given
+    #[arg(long)]
+    pub data_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub load: LoadArgs,
+}
+
+#[derive(Args, Default)]
+pub struct LoadArgs {
+    /// ROM mapping to use when the detection fails or gets it wrong
+    #[arg(long, value_enum)]
+    pub mapping: Option<MappingArg>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum MappingArg {
+    Lorom,
+    Hirom,
+}
+
+impl LoadArgs {
+    pub fn force_mapping(&self) -> Option<MappingMode> {
+        self.mapping.map(|mapping| match mapping {
+            MappingArg::Lorom => MappingMode::LoRom,
+            MappingArg::Hirom => MappingMode::HiRom,
+        })
+    }
+
+    pub fn load_rom(&self, path: &Path) -> Result<Rom, Box<dyn Error>> {
+        let options = LoadOptions {
+            force_mapping: self.force_mapping(),
+        };
+        Ok(Rom::load_from_file_with_options(path, &options)?)
+    }
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum MemoryInitArg {
+    #[default]
+    Zero,
+    /// Bytes alternating between 0x55 and 0xAA
+    Stripes,
+    Random,
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum PacingArg {
+    /// The host clock, at the emulated frame rate
+    #[default]
+    Timer,
+    /// The display refresh, for displays refreshing at the emulated frame rate
+    Vsync,
+    /// None, as fast as the host can
+    FreeRun,
+}
+
+impl RunArgs {
+    pub fn pacing(&self) -> Pacing {
+        match self.pacing {
+            PacingArg::Timer => Pacing::Timer,
+            PacingArg::Vsync => Pacing::Vsync,
+            PacingArg::FreeRun => Pacing::FreeRun,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum VideoArg {
+    Ntsc,
+    Pal,
+}
+
+impl EmulationArgs {
+    pub fn options(&self) -> EmulatorOptions {
+        EmulatorOptions {
+            force_video_standard: self.video.map(|video| match video {
+                VideoArg::Ntsc => VideoStandard::NTSC,
+                VideoArg::Pal => VideoStandard::PAL,
+            }),
+            opcode_policy: if self.trap_opcodes {
+                OpcodePolicy::Trap
+            } else {
+                OpcodePolicy::Nop
+            },
+            vram_access: if self.accurate_vram {
+                VramAccess::Accurate
+            } else {
+                VramAccess::Permissive
+            },
+            audio_output: if self.accurate_audio {
+                OutputMode::Hardware
+            } else {
+                OutputMode::Raw
+            },
+            force_mapping: self.load.force_mapping(),
+            memory_init: self.memory_init(),
+            code_data_log: self.cdl,
+            profile: self.profile,
+            overclock: Overclock {
+                cpu_extra_cycles: self.cpu_overclock,
+                sa1_multiplier: self.sa1_overclock.unwrap_or(1),
+            },
+        }
+    }
+
+    /// Loads the ROM at `path` with the options overridden by the settings of its game,
+    /// which are returned for the frontend ones.
+    pub fn load_rom(&self, path: &Path) -> Result<(RSnes, GameSettings), Box<dyn Error>> {
+        let root = match &self.data_dir {
+            Some(dir) => dir.clone(),
+            None => path.with_file_name(GAME_DATA_DIR),
+        };
+        RSnes::load_rom_with_game_data(&path, &self.options(), &root)
+    }
+
+    fn memory_init(&self) -> MemoryInit {
+        match self.memory_init {
+            MemoryInitArg::Zero => MemoryInit::Zero,
+            MemoryInitArg::Stripes => MemoryInit::Stripes,
+            MemoryInitArg::Random => {
+                let seed = self.memory_seed.unwrap_or_else(|| {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |time| time.as_nanos() as u64)
+                });
+                info!(seed, "Random power-on memory");
+                MemoryInit::Random(seed)
+            }
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct RecordArgs {
+    pub rom: PathBuf,
+
+    /// Y4M video file to write
+    #[arg(long, short)]
+    pub output: PathBuf,
+
+    /// Number of frames to run and record
+    #[arg(long, default_value_t = 600)]
+    pub frames: usize,
+
+    /// Share of the previous frame blended into each one (0.0 to 1.0), 0.5 smoothing the
+    /// sprites games flicker at 30 Hz
+    #[arg(long, default_value_t = 0.0)]
+    pub frame_blend: f32,
+
+    #[command(flatten)]
+    pub emulation: EmulationArgs,
+}
+
+#[derive(Args)]
+pub struct RomArgs {
+    pub rom: PathBuf,
+
+    /// No-Intro (Logiqx XML) dat file used to identify the ROM
+    #[arg(long)]
+    pub dat: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub load: LoadArgs,
+}
+
+#[derive(Args)]
+pub struct DisasmArgs {
+    pub rom: PathBuf,
+
+    /// Bank to disassemble, in hexadecimal
+    #[arg(long, default_value = "00", value_parser = parse_bank)]
+    pub bank: u8,
+
+    /// First address in the bank, in hexadecimal
+    #[arg(long, default_value = "8000", value_parser = parse_addr)]
+    pub start: u16,
+
+    /// Number of instructions to print
+    #[arg(long, default_value_t = 64)]
+    pub count: usize,
+
+    /// Start with a 16-bit accumulator (M flag cleared)
+    #[arg(long)]
+    pub m16: bool,
+
+    /// Start with 16-bit index registers (X flag cleared)
+    #[arg(long)]
+    pub x16: bool,
+
+    /// Symbol file (WLA-DX or bass) used to label addresses
+    #[arg(long)]
+    pub symbols: Option<PathBuf>,
+
+    /// Code/data log (bsnes-plus usage map, as written by `run --cdl`): bytes read as data
+    /// are listed as such, and instructions use the register widths they ran with
+    #[arg(long)]
+    pub cdl: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub load: LoadArgs,
+}
+
+fn parse_hex(text: &str) -> Result<u32, String> {
+    let digits = text.trim_start_matches('$').trim_start_matches("0x");
+    u32::from_str_radix(digits, 16).map_err(|err| format!("invalid hexadecimal value: {err}"))
+}
+
+fn parse_bank(text: &str) -> Result<u8, String> {
+    u8::try_from(parse_hex(text)?).map_err(|_| "bank must be between 00 and FF".to_string())
+}
+
+fn parse_addr(text: &str) -> Result<u16, String> {
+    u16::try_from(parse_hex(text)?).map_err(|_| "address must be between 0000 and FFFF".to_string())
+}
+
+fn load_database(dat: Option<&Path>) -> Result<Option<RomDatabase>, Box<dyn Error>> {
+    Ok(dat.map(RomDatabase::load_from_file).transpose()?)
+}
+
+pub fn info(args: &RomArgs) -> Result<(), Box<dyn Error>> {
+    let rom = args.load.load_rom(&args.rom)?;
+    let hashes = rom.hashes();
+    rom.header.log_header_bytes();
+
+    print!("{}", rom.header);
+    println!("Size: {} KiB", rom.data.len() / 1024);
+    println!("Mapping used: {}", rom.map);
+    println!(
+        "Copier header: {}",
+        if rom.copier_format.header_stripped {
+            "stripped"
+        } else {
+            "none"
+        }
+    );
+    if rom.copier_format.deinterleaved {
+        println!("Interleaved dump: de-interleaved");
+    }
+    println!(
+        "Computed checksum: {:04X} ({})",
+        rom.checksum(),
+        if rom.verify_checksum() {
+            "match"
+        } else {
+            "mismatch"
+        }
+    );
+    println!("CRC32: {:08x}", hashes.crc32);
+    println!("SHA-1: {}", hashes.sha1_hex());
+
+    if let Some(database) = load_database(args.dat.as_deref())? {
+        let identity = rom.identify(&database);
+        println!("Name: {}", identity.name().unwrap_or("unknown"));
+    }
+    Ok(())
+}
+
+pub fn verify(args: &RomArgs) -> Result<(), Box<dyn Error>> {
+    let rom = args.load.load_rom(&args.rom)?;
+    let mut valid = rom.verify_checksum();
+
+    println!(
+        "Checksum: header {:04X}, complement {:04X}, computed {:04X}: {}",
+        rom.header.checksum,
+        rom.header.checksum_complement,
+        rom.checksum(),
+        if valid { "OK" } else { "BAD" }
+    );
+
+    if let Some(database) = load_database(args.dat.as_deref())? {
+        let identity = rom.identify(&database);
+        match identity.entry {
+            Some(entry) => println!("Database: {} ({:?})", entry.name, entry.status),
+            None => println!("Database: unknown ROM"),
+        }
+        valid &= identity.is_known_good();
+    }
+
+    if valid {
+        Ok(())
+    } else {
+        Err("ROM verification failed".into())
+    }
+}
+
+pub fn record(args: &RecordArgs) -> Result<(), Box<dyn Error>> {
+    let (rsnes, settings) = args.emulation.load_rom(&args.rom)?;
+    let mut system = System::new(rsnes, PixelFormat::Rgb888);
+    let video = BufWriter::new(File::create(&args.output)?);
+    system.set_frame_blend(args.frame_blend);
+    settings.apply_to_system(&mut system);
+    system.set_frame_sink(Y4mWriter::new(video, system.video_standard()));
+
+    for _ in 0..args.frames {
+        system.run_frame();
+    }
+    system.rsnes.save()?;
+
+    // The sink is dropped on the first write error, which is logged
+    if system.crashed() {
+        warn!("Emulation stopped before the end of the recording");
+    }
+    match system.take_frame_sink() {
+        Some(_) => Ok(()),
+        None => Err("could not write the video".into()),
+    }
+}
+
+pub fn disasm(args: &DisasmArgs) -> Result<(), Box<dyn Error>> {
+    let rom = args.load.load_rom(&args.rom)?;
+    let symbols = match &args.symbols {
+        Some(path) => Symbols::load_from_file(path)?,
+        None => Symbols::new(),
+    };
+    let usage = match &args.cdl {
+        Some(path) => fs::read(path)?,
+        None => Vec::new(),
+    };
+    if !usage.is_empty() && usage.len() != rom.data.len() {
+        return Err(format!(
+            "the code/data log has {} bytes for a ROM of {} bytes",
+            usage.len(),
+            rom.data.len()
+        )
+        .into());
+    }
+    // Usage flags of the byte at `offset` from the start address, 0 when unknown
+    let usage_at = |offset: usize| {
+        let addr = SnesAddress {
+            bank: args.bank,
+            addr: args.start + offset as u16,
+        };
+        rom.offset(addr)
+            .and_then(|rom_offset| usage.get(rom_offset).copied())
+            .unwrap_or(0)
+    };
+    let is_data = |offset| {
+        let usage = usage_at(offset);
+        usage & usage::READ != 0 && usage & usage::EXEC == 0
+    };
+
+    // Everything from the start address to the end of the bank, up to the first unmapped byte
+    let bytes: Vec<u8> = (args.start..=0xFFFF)
+        .map_while(|addr| {
+            rom.try_read(SnesAddress {
+                bank: args.bank,
+                addr,
+            })
+        })
+        .collect();
+    if bytes.is_empty() {
+        return Err(format!(
+            "${:02X}:{:04X} is not mapped to the ROM",
+            args.bank, args.start
+        )
+        .into());
+    }
+
+    let mut widths = RegisterWidths {
+        m8: !args.m16,
+        x8: !args.x16,
+    };
+    let mut offset = 0;
+    for _ in 0..args.count {
+        let pc = SnesAddress {
+            bank: args.bank,
+            addr: args.start + offset as u16,
+        };
+        if is_data(offset) {
+            let len = (offset..bytes.len().min(offset + 8))
+                .take_while(|&offset| is_data(offset))
+                .count();
+            let values: Vec<String> = bytes[offset..offset + len]
+                .iter()
+                .map(|b| format!("${b:02X}"))
+                .collect();
+            println!(
+                "${:02X}:{:04X}  {:<12} .db {}",
+                pc.bank,
+                pc.addr,
+                "",
+                values.join(",")
+            );
+            offset += len;
+            if offset >= bytes.len() {
+                break;
+            }
+            continue;
+        }
+        let usage = usage_at(offset);
+        if usage & usage::OPCODE != 0 {
+            widths = register_widths(usage);
+        }
+
+        let Some(instr) = Instruction::decode(usize::from(pc) as u32, &bytes[offset..], widths)
+        else {
+            break;
+        };
+
+        for label in symbols.labels_at(pc) {
+            println!("{label}:");
+        }
+
+        let raw: Vec<String> = bytes[offset..offset + instr.len]
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect();
+        let comment = instr
+            .target()
+            .and_then(|target| symbols.label(SnesAddress::from(target as usize)))
+            .map(|label| format!(" ; {label}"))
+            .unwrap_or_default();
+        println!(
+            "${:02X}:{:04X}  {:<12} {}{}",
+            pc.bank,
+            pc.addr,
+            raw.join(" "),
+            instr,
+            comment
+        );
+
+        widths.update(&instr);
+        offset += instr.len;
+        if offset >= bytes.len() {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -20,10 +20,8 @@ impl Gui {
     pub const SNES_WIDTH: usize = 256; // TODO : Remove when GUI linked with PPU
     pub const SNES_HEIGHT: usize = 224; // TODO : Remove when GUI linked with PPU
 
-    pub const FRAME_RATE: u16 = 60;
-    pub const FRAME_DURATION: f64 = 1.0 / Self::FRAME_RATE as f64;
-
-    pub fn new() -> Result<Self, String> {
+    /// Opens the window; with `vsync`, presenting a frame waits for the display refresh.
+    pub fn new(vsync: bool) -> Result<Self, String> {
         let sdl_ctx = sdl2::init()?;
         let video_subsystem = sdl_ctx.video()?;
 
@@ -33,11 +31,11 @@ impl Gui {
             .build()
             .map_err(|e| e.to_string())?;
 
-        let canvas = window
-            .into_canvas()
-            .accelerated()
-            .build()
-            .map_err(|e| e.to_string())?;
+        let mut canvas = window.into_canvas().accelerated();
+        if vsync {
+            canvas = canvas.present_vsync();
+        }
+        let canvas = canvas.build().map_err(|e| e.to_string())?;
 
         let event_pump = sdl_ctx.event_pump()?;
 
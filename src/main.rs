@@ -1,70 +1,125 @@
+mod cli;
 mod gui;
-mod rsnes;
 
-use crate::{
-    gui::{Gui, RSnesEvent},
-    rsnes::RSnes,
-};
+use crate::cli::{Cli, Command, EmulationArgs, RunArgs};
+use crate::gui::RSnesEvent;
+use clap::Parser;
+use common::video_standard::VideoStandard;
+use emulator::scheduler::{Pacing, Scheduler};
+use emulator::RSnes;
+use std::error::Error;
+use std::path::Path;
+use std::thread;
 use std::time::Instant;
+use tracing::{error, info, warn};
 
-fn main() -> Result<(), String> {
-    let mut gui = gui::Gui::new()?;
-    let mut rsnes_app: Option<rsnes::RSnes> = None;
+fn main() -> Result<(), Box<dyn Error>> {
+    tracing_subscriber::fmt::init();
+
+    match Cli::parse().command {
+        None => run_gui(&RunArgs {
+            rom: None,
+            pacing: Default::default(),
+            emulation: EmulationArgs::default(),
+        }),
+        Some(Command::Run(args)) => run_gui(&args),
+        Some(Command::Info(args)) => cli::info(&args),
+        Some(Command::Disasm(args)) => cli::disasm(&args),
+        Some(Command::Verify(args)) => cli::verify(&args),
+        Some(Command::Record(args)) => cli::record(&args),
+    }
+}
+
+fn run_gui(args: &RunArgs) -> Result<(), Box<dyn Error>> {
+    let pacing = args.pacing();
+    let mut gui = gui::Gui::new(pacing == Pacing::Vsync)?;
+    let mut rsnes_app: Option<RSnes> = match &args.rom {
+        Some(path) => Some(load_rom(path, &args.emulation)?),
+        None => None,
+    };
 
     // Reference variables
     let mut frame_nb = 0;
     let exec_start = Instant::now();
 
-    // Clock utility variables
-    let mut last_instant = Instant::now();
-    let mut frame_accum: f64 = 0.0;
+    let mut scheduler = frame_scheduler(&rsnes_app, pacing);
     let mut master_cycle_accum: f64 = 0.0;
 
     'emulation_loop: loop {
-        // Get new delta based on current Instant::now()
-        let current_instant = Instant::now();
-        let delta = current_instant.duration_since(last_instant).as_secs_f64();
-        last_instant = current_instant;
-
-        frame_accum += delta;
-
-        // Emulation update if emulator exists and if master_cycle duration treshold is crossed
-        match rsnes_app {
-            Some(ref mut app) => {
-                master_cycle_accum += delta;
-
-                while master_cycle_accum >= RSnes::MASTER_CYCLE_DURATION {
-                    master_cycle_accum -= RSnes::MASTER_CYCLE_DURATION;
-                    app.update();
-                }
+        // The window doesn't show the picture nor play the audio of the emulator yet, so
+        // only the wait of the plan applies
+        let plan = scheduler.plan(Instant::now());
+        thread::sleep(plan.wait);
+
+        // Emulation of the master cycles of one frame
+        if let Some(app) = &mut rsnes_app {
+            master_cycle_accum += scheduler.frame_duration().as_secs_f64();
+
+            let master_cycle_duration = app.master_cycle_duration();
+            while master_cycle_accum >= master_cycle_duration {
+                master_cycle_accum -= master_cycle_duration;
+                app.update();
             }
-            None => {}
         }
 
-        // Window update if frame treshold is crossed
-        if frame_accum >= Gui::FRAME_DURATION {
-            frame_accum -= Gui::FRAME_DURATION;
-
-            for state_event in gui.update() {
-                match state_event {
-                    RSnesEvent::LoadRom { path } => match rsnes::RSnes::load_rom(&path) {
-                        Ok(emu) => rsnes_app = Some(emu),
-                        Err(err) => println!("Error loading ROM: {}", err),
-                    },
-                    RSnesEvent::Quit => break 'emulation_loop,
-                }
+        for state_event in gui.update() {
+            match state_event {
+                RSnesEvent::LoadRom { path } => match load_rom(&path, &args.emulation) {
+                    Ok(emu) => {
+                        save_app(&rsnes_app);
+                        rsnes_app = Some(emu);
+                        scheduler = frame_scheduler(&rsnes_app, pacing);
+                    }
+                    Err(err) => error!(%err, "Error loading ROM"),
+                },
+                RSnesEvent::Quit => break 'emulation_loop,
             }
-            frame_nb += 1;
         }
+        frame_nb += 1;
     }
 
+    save_app(&rsnes_app);
     // TODO : Potential Cleanup or user settings save ?
 
     // Print of the window frame rate and program duration
     let time = Instant::now();
     let program_duration = time.duration_since(exec_start).as_secs_f64();
-    println!("Program duration : {}", program_duration);
-    println!("Frame rate : {}", frame_nb as f64 / program_duration);
+    let stats = scheduler.stats();
+    info!(
+        program_duration,
+        frame_rate = frame_nb as f64 / program_duration,
+        mean_jitter = ?stats.mean_jitter,
+        max_jitter = ?stats.max_jitter,
+        "Exiting"
+    );
 
     Ok(())
 }
+
+/// Frame pacing at the frame rate of the loaded ROM, NTSC without ROM
+fn frame_scheduler(rsnes_app: &Option<RSnes>, pacing: Pacing) -> Scheduler {
+    let video_standard = rsnes_app
+        .as_ref()
+        .map_or(VideoStandard::NTSC, |app| app.video_standard);
+    let mut scheduler = Scheduler::new(video_standard);
+    scheduler.set_pacing(pacing);
+    scheduler
+}
+
+/// Loads a ROM with the settings of its game; the picture ones wait for the window to
+/// show the emulated picture
+fn load_rom(path: &Path, args: &EmulationArgs) -> Result<RSnes, Box<dyn Error>> {
+    let (emu, _settings) = args.load_rom(path)?;
+    if emu.region.is_mismatch() {
+        warn!(region = %emu.region, "Region mismatch");
+    }
+    Ok(emu)
+}
+
+fn save_app(rsnes_app: &Option<RSnes>) {
+    if let Some(app) = rsnes_app
+        && let Err(err) = app.save()
+    {
+        error!(%err, "Error saving");
+    }
+}
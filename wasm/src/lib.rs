@@ -0,0 +1,140 @@
+//! WebAssembly bindings for a browser frontend.
+//!
+//! Build with `wasm-pack build wasm --target web`, then from JavaScript:
+//!
+//! ```js
+//! const emulator = new Emulator(new Uint8Array(romFile));
+//! function frame() {
+//!     emulator.set_buttons(0, held);
+//!     emulator.run_frame();
+//!     context.putImageData(new ImageData(emulator.framebuffer(), emulator.width(), emulator.height()), 0, 0);
+//!     queueAudio(emulator.audio(), emulator.sample_rate());
+//! }
+//! ```
+//!
+//! The ROM comes from a buffer and nothing is saved: the page stores what it needs itself.
+
+use bus::rom::Rom;
+use emulator::system::{SAMPLE_RATE, System};
+use emulator::{EmulatorOptions, RSnes};
+use ppu::rendering::framebuffer::PixelFormat;
+use wasm_bindgen::Clamped;
+use wasm_bindgen::prelude::*;
+
+/// Gamepad buttons, to combine for [`Emulator::set_buttons`]. wasm-bindgen only accepts
+/// literals: the values are those of `bus::joypad::gamepad`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub enum Button {
+    B = 0x8000,
+    Y = 0x4000,
+    Select = 0x2000,
+    Start = 0x1000,
+    Up = 0x0800,
+    Down = 0x0400,
+    Left = 0x0200,
+    Right = 0x0100,
+    A = 0x0080,
+    X = 0x0040,
+    L = 0x0020,
+    R = 0x0010,
+}
+
+#[wasm_bindgen]
+pub struct Emulator {
+    system: System,
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    /// Loads the contents of a ROM file, copier header included.
+    #[wasm_bindgen(constructor)]
+    pub fn new(rom: Vec<u8>) -> Result<Emulator, JsError> {
+        let rom = Rom::from_bytes(rom)?;
+        let rsnes = RSnes::from_rom(rom, &EmulatorOptions::default());
+        Ok(Self {
+            system: System::new(rsnes, PixelFormat::Rgba8888),
+        })
+    }
+
+    /// Emulates one frame. Returns false once the emulation stopped on an unimplemented
+    /// feature, after which frames keep the last picture and are silent.
+    pub fn run_frame(&mut self) -> bool {
+        self.system.run_frame();
+        !self.system.crashed()
+    }
+
+    /// [`Self::run_frame`] without drawing the picture, for frame skipping: the
+    /// framebuffer keeps the previous one.
+    pub fn skip_frame(&mut self) -> bool {
+        self.system.skip_frame();
+        !self.system.crashed()
+    }
+
+    pub fn reset(&mut self) {
+        self.system.reset();
+    }
+
+    /// Sets the buttons held on the gamepad of `port` (0 or 1), as [`Button`] flags.
+    pub fn set_buttons(&mut self, port: usize, buttons: u16) {
+        if port < 2 {
+            self.system.set_buttons(port, buttons);
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.system.renderer.framebuffer.width()
+    }
+
+    /// Number of visible lines
+    pub fn height(&self) -> usize {
+        self.system.renderer.active_height
+    }
+
+    /// Visible part of the last frame as RGBA bytes, ready for an `ImageData`.
+    pub fn framebuffer(&self) -> Clamped<Vec<u8>> {
+        let framebuffer = &self.system.renderer.framebuffer;
+        Clamped(framebuffer[..framebuffer.pitch() * self.height()].to_vec())
+    }
+
+    /// Audio of the last frame, as interleaved left/right samples.
+    pub fn audio(&self) -> Vec<i16> {
+        self.system.audio.clone()
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        SAMPLE_RATE
+    }
+
+    /// About 60.1 for NTSC and 50.0 for PAL
+    pub fn frame_rate(&self) -> f64 {
+        self.system.video_standard().frame_rate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bus::joypad::gamepad;
+
+    #[test]
+    fn test_buttons_match_gamepad() {
+        let buttons = [
+            (Button::B, gamepad::B),
+            (Button::Y, gamepad::Y),
+            (Button::Select, gamepad::SELECT),
+            (Button::Start, gamepad::START),
+            (Button::Up, gamepad::UP),
+            (Button::Down, gamepad::DOWN),
+            (Button::Left, gamepad::LEFT),
+            (Button::Right, gamepad::RIGHT),
+            (Button::A, gamepad::A),
+            (Button::X, gamepad::X),
+            (Button::L, gamepad::L),
+            (Button::R, gamepad::R),
+        ];
+        for (button, bit) in buttons {
+            assert_eq!(button as u16, bit, "{:?}", button);
+        }
+    }
+}